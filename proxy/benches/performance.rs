@@ -1,6 +1,7 @@
-use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
 use slapenir_proxy::sanitizer::SecretMap;
 use std::collections::HashMap;
+use std::hint::black_box;
 
 fn create_secret_map(num_secrets: usize) -> SecretMap {
     let mut secrets = HashMap::new();
@@ -0,0 +1,25 @@
+-- SLAPENIR Auto-Detection Database Schema (SQLite)
+-- Mirrors the PostgreSQL schema for local development and CI. Arrays are
+-- stored as JSON text, e.g. env_vars = '["OPENAI_API_KEY", "OPENAI_TOKEN"]'.
+
+CREATE TABLE IF NOT EXISTS api_definitions (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    name TEXT NOT NULL UNIQUE,
+    display_name TEXT NOT NULL,
+    description TEXT,
+    category TEXT NOT NULL DEFAULT 'other',
+    tags TEXT NOT NULL DEFAULT '[]',
+    env_vars TEXT NOT NULL,
+    strategy_type TEXT NOT NULL DEFAULT 'bearer',
+    dummy_prefix TEXT NOT NULL,
+    allowed_hosts TEXT NOT NULL,
+    header_name TEXT,
+    documentation_url TEXT,
+    icon_url TEXT,
+    is_active INTEGER NOT NULL DEFAULT 1,
+    is_builtin INTEGER NOT NULL DEFAULT 1,
+    created_at TEXT DEFAULT CURRENT_TIMESTAMP,
+    updated_at TEXT DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_api_definitions_active ON api_definitions(is_active);
@@ -0,0 +1,156 @@
+// SLAPENIR Admin API - Runtime management of security lists
+// Lets operators block leaky headers or deny egress hosts during an incident
+// without restarting the proxy, and pull sanitization effectiveness, egress
+// destination and strategy usage reports.
+
+use crate::metrics::{self, SanitizationReport};
+use crate::middleware::AppState;
+use crate::strategy_usage::StrategyLastUsed;
+use crate::upstream_hosts::UpstreamHost;
+use axum::{
+    extract::{Request, State},
+    http::{header, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::get,
+    Json, Router,
+};
+use serde::{Deserialize, Serialize};
+
+/// Request and response body for the security list endpoints
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SecurityList {
+    pub entries: Vec<String>,
+}
+
+/// Admin routes, protected by the `admin_token` bearer token
+pub fn routes(state: AppState) -> Router<AppState> {
+    Router::new()
+        .route(
+            "/admin/blocked-headers",
+            get(get_blocked_headers).put(put_blocked_headers),
+        )
+        .route(
+            "/admin/denied-hosts",
+            get(get_denied_hosts).put(put_denied_hosts),
+        )
+        .route("/admin/sanitization-report", get(get_sanitization_report))
+        .route("/admin/upstream-hosts", get(get_upstream_hosts))
+        .route("/admin/strategies", get(get_strategies))
+        .route_layer(middleware::from_fn_with_state(state, require_admin))
+}
+
+/// Reject requests without the configured admin bearer token
+async fn require_admin(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let Some(expected) = state.config.as_ref().and_then(|c| c.admin_token.as_deref()) else {
+        return (StatusCode::FORBIDDEN, "Admin API disabled").into_response();
+    };
+
+    let provided = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "));
+
+    match provided {
+        Some(token) if constant_time_eq(token.as_bytes(), expected.as_bytes()) => {
+            next.run(request).await
+        }
+        _ => {
+            tracing::warn!("Rejected unauthenticated admin request");
+            (StatusCode::UNAUTHORIZED, "Invalid admin token").into_response()
+        }
+    }
+}
+
+/// Compare secrets without leaking the mismatch position through timing
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+async fn get_blocked_headers(State(state): State<AppState>) -> Json<SecurityList> {
+    Json(SecurityList {
+        entries: state.blocked_headers(),
+    })
+}
+
+async fn put_blocked_headers(
+    State(state): State<AppState>,
+    Json(body): Json<SecurityList>,
+) -> Result<Json<SecurityList>, (StatusCode, String)> {
+    let entries = state
+        .set_blocked_headers(body.entries)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    tracing::warn!("🛡️  Blocked headers replaced: {:?}", entries);
+    Ok(Json(SecurityList { entries }))
+}
+
+async fn get_denied_hosts(State(state): State<AppState>) -> Json<SecurityList> {
+    Json(SecurityList {
+        entries: state.denied_hosts(),
+    })
+}
+
+async fn put_denied_hosts(
+    State(state): State<AppState>,
+    Json(body): Json<SecurityList>,
+) -> Result<Json<SecurityList>, (StatusCode, String)> {
+    let entries = state
+        .set_denied_hosts(body.entries)
+        .map_err(|e| (StatusCode::BAD_REQUEST, e))?;
+    tracing::warn!("🛡️  Denied hosts replaced: {:?}", entries);
+    Ok(Json(SecurityList { entries }))
+}
+
+/// Redaction counts for the current report window; never secret values
+async fn get_sanitization_report() -> Json<SanitizationReport> {
+    Json(metrics::sanitization_report())
+}
+
+/// Response body for the upstream hosts endpoint
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UpstreamHostsReport {
+    /// Distinct hosts currently tracked
+    pub distinct: usize,
+    /// Hosts remembered before the least recently used is evicted
+    pub capacity: usize,
+    /// Tracked hosts, most requested first
+    pub hosts: Vec<UpstreamHost>,
+}
+
+/// Distinct upstream hosts contacted, with request counts
+async fn get_upstream_hosts(State(state): State<AppState>) -> Json<UpstreamHostsReport> {
+    let hosts = state.upstream_hosts.snapshot();
+    Json(UpstreamHostsReport {
+        distinct: hosts.len(),
+        capacity: state.upstream_hosts.capacity(),
+        hosts,
+    })
+}
+
+/// Response body for the strategies endpoint
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StrategiesReport {
+    /// Every configured strategy by name, with when it was last injected
+    pub strategies: Vec<StrategyLastUsed>,
+}
+
+/// When each strategy was last injected; names and timestamps only
+async fn get_strategies(State(state): State<AppState>) -> Json<StrategiesReport> {
+    Json(StrategiesReport {
+        strategies: state.strategy_usage.snapshot(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"token", b"token"));
+        assert!(!constant_time_eq(b"token", b"tokem"));
+        assert!(!constant_time_eq(b"token", b"token2"));
+        assert!(!constant_time_eq(b"", b"token"));
+    }
+}
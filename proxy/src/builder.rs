@@ -1,8 +1,8 @@
 // SLAPENIR Strategy Builder - Builds strategy instances from configuration
 
 use crate::config::{Config, StrategyConfig};
-use crate::strategies::AWSSigV4Strategy;
-use crate::strategy::{AuthStrategy, BearerStrategy, StrategyError};
+use crate::strategies::{AWSSigV4Strategy, BasicAuthStrategy, HmacStrategy, QueryParamStrategy};
+use crate::strategy::{AuthStrategy, BearerStrategy, InjectTargets, StrategyError};
 
 /// Build strategy instances from configuration
 pub fn build_strategies_from_config(config: &Config) -> Result<Vec<Box<dyn AuthStrategy>>, String> {
@@ -40,6 +40,43 @@ pub fn build_strategies_from_config(config: &Config) -> Result<Vec<Box<dyn AuthS
     Ok(strategies)
 }
 
+/// Check that every strategy's credential environment variables are set
+///
+/// Strategies whose credential is missing are otherwise skipped with a
+/// warning; strict mode refuses to start instead. The error lists each such
+/// strategy with the variables it lacks.
+pub fn check_strategy_credentials(config: &Config) -> Result<(), String> {
+    let missing: Vec<String> = config
+        .strategies
+        .iter()
+        .filter_map(|strategy| {
+            let params = &strategy.config;
+            let unset: Vec<&str> = [
+                &params.env_var,
+                &params.access_key_env,
+                &params.secret_key_env,
+                &params.username_env,
+                &params.password_env,
+            ]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .filter(|var| std::env::var(var).map_or(true, |value| value.is_empty()))
+            .collect();
+            (!unset.is_empty()).then(|| format!("{} ({})", strategy.name, unset.join(", ")))
+        })
+        .collect();
+
+    if missing.is_empty() {
+        return Ok(());
+    }
+    Err(format!(
+        "Credentials missing for {} strategies, environment variables unset or empty: {}",
+        missing.len(),
+        missing.join("; ")
+    ))
+}
+
 /// Build a single strategy from configuration
 fn build_strategy(config: &StrategyConfig) -> Result<Box<dyn AuthStrategy>, StrategyError> {
     match config.strategy_type.as_str() {
@@ -48,18 +85,26 @@ fn build_strategy(config: &StrategyConfig) -> Result<Box<dyn AuthStrategy>, Stra
                 StrategyError::InvalidCredential("Bearer strategy missing env_var".to_string())
             })?;
 
-            let dummy_pattern = config.config.dummy_pattern.as_ref().ok_or_else(|| {
-                StrategyError::InvalidCredential(
-                    "Bearer strategy missing dummy_pattern".to_string(),
-                )
-            })?;
+            // Sanitize-only credentials are never injected, so need no dummy
+            let dummy_pattern = match &config.config.dummy_pattern {
+                Some(pattern) => pattern.clone(),
+                None if config.config.sanitize_only => String::new(),
+                None => {
+                    return Err(StrategyError::InvalidCredential(
+                        "Bearer strategy missing dummy_pattern".to_string(),
+                    ))
+                }
+            };
 
             let strategy = BearerStrategy::new(
                 config.name.clone(),
                 env_var.clone(),
-                dummy_pattern.clone(),
+                dummy_pattern,
                 config.config.allowed_hosts.clone(),
-            )?;
+            )?
+            .with_sanitize_only(config.config.sanitize_only)
+            .with_priority(config.config.priority)
+            .with_inject_targets(InjectTargets::parse(&config.config.inject_targets)?);
 
             Ok(Box::new(strategy))
         }
@@ -81,23 +126,105 @@ fn build_strategy(config: &StrategyConfig) -> Result<Box<dyn AuthStrategy>, Stra
                 StrategyError::InvalidCredential("AWS SigV4 strategy missing region".to_string())
             })?;
 
-            let strategy = AWSSigV4Strategy::new(
+            let mut strategy = AWSSigV4Strategy::new(
                 config.name.clone(),
                 access_key_env.clone(),
                 secret_key_env.clone(),
                 region.clone(),
                 None, // service is auto-detected from host
                 config.config.allowed_hosts.clone(),
-            )?;
+            )?
+            .with_priority(config.config.priority);
+
+            if let Some(dummy_pattern) = &config.config.dummy_pattern {
+                strategy = strategy.with_dummy_pattern(dummy_pattern.clone());
+            }
 
             Ok(Box::new(strategy))
         }
 
         "hmac" => {
-            // TODO(#6): Implement HMAC signing strategy for credential injection
-            Err(StrategyError::InvalidCredential(
-                "HMAC strategy not yet implemented".to_string(),
-            ))
+            let env_var = config.config.env_var.as_ref().ok_or_else(|| {
+                StrategyError::InvalidCredential("HMAC strategy missing env_var".to_string())
+            })?;
+
+            let dummy_pattern = config.config.dummy_pattern.as_ref().ok_or_else(|| {
+                StrategyError::InvalidCredential("HMAC strategy missing dummy_pattern".to_string())
+            })?;
+
+            let mut strategy = HmacStrategy::new(
+                config.name.clone(),
+                env_var.clone(),
+                dummy_pattern.clone(),
+                config.config.allowed_hosts.clone(),
+            )?
+            .with_priority(config.config.priority);
+
+            if let Some(header) = &config.config.signature_header {
+                strategy = strategy.with_signature_header(header)?;
+            }
+
+            Ok(Box::new(strategy))
+        }
+
+        "query_param" => {
+            let env_var = config.config.env_var.as_ref().ok_or_else(|| {
+                StrategyError::InvalidCredential("Query param strategy missing env_var".to_string())
+            })?;
+
+            let dummy_pattern = config.config.dummy_pattern.as_ref().ok_or_else(|| {
+                StrategyError::InvalidCredential(
+                    "Query param strategy missing dummy_pattern".to_string(),
+                )
+            })?;
+
+            let param = config.config.query_param.as_ref().ok_or_else(|| {
+                StrategyError::InvalidCredential(
+                    "Query param strategy missing query_param".to_string(),
+                )
+            })?;
+
+            let strategy = QueryParamStrategy::new(
+                config.name.clone(),
+                env_var.clone(),
+                param.clone(),
+                dummy_pattern.clone(),
+                config.config.allowed_hosts.clone(),
+            )?
+            .with_priority(config.config.priority);
+
+            Ok(Box::new(strategy))
+        }
+
+        "basic" => {
+            let username_env = config.config.username_env.as_ref().ok_or_else(|| {
+                StrategyError::InvalidCredential(
+                    "Basic auth strategy missing username_env".to_string(),
+                )
+            })?;
+
+            let password_env = config.config.password_env.as_ref().ok_or_else(|| {
+                StrategyError::InvalidCredential(
+                    "Basic auth strategy missing password_env".to_string(),
+                )
+            })?;
+
+            let dummy_pattern = config.config.dummy_pattern.as_ref().ok_or_else(|| {
+                StrategyError::InvalidCredential(
+                    "Basic auth strategy missing dummy_pattern".to_string(),
+                )
+            })?;
+
+            let strategy = BasicAuthStrategy::new(
+                config.name.clone(),
+                username_env.clone(),
+                password_env.clone(),
+                dummy_pattern.clone(),
+                config.config.allowed_hosts.clone(),
+            )?
+            .with_priority(config.config.priority);
+
+            Ok(Box::new(strategy))
         }
 
         _ => Err(StrategyError::InvalidCredential(format!(
@@ -173,12 +300,183 @@ mod tests {
                 access_key_env: None,
                 secret_key_env: None,
                 region: None,
+                sanitize_only: false,
+                priority: 0,
+                inject_targets: Vec::new(),
+                signature_header: None,
+                query_param: None,
+                username_env: None,
+                password_env: None,
             },
         };
 
         let strategy = build_strategy(&config).unwrap();
         assert_eq!(strategy.name(), "test");
         assert_eq!(strategy.strategy_type(), "bearer");
+        assert!(strategy.inject_targets().is_unrestricted());
+
+        let mut header_only = config.clone();
+        header_only.config.inject_targets = vec!["x-api-key".to_string()];
+        let targets = build_strategy(&header_only).unwrap().inject_targets();
+        assert!(!targets.body());
+        assert!(targets.header("X-API-Key"));
+
+        header_only.config.inject_targets = vec!["not a header".to_string()];
+        assert!(build_strategy(&header_only).is_err());
+    }
+
+    #[test]
+    fn test_build_aws_strategy_dummy_pattern() {
+        use crate::config::StrategyParams;
+
+        let aws_config = |dummy_pattern: Option<&str>| StrategyConfig {
+            name: "aws".to_string(),
+            strategy_type: "aws_sigv4".to_string(),
+            config: StrategyParams {
+                env_var: None,
+                dummy_pattern: dummy_pattern.map(str::to_string),
+                allowed_hosts: vec!["*.amazonaws.com".to_string()],
+                access_key_env: Some("TEST_BUILD_AWS_ACCESS".to_string()),
+                secret_key_env: Some("TEST_BUILD_AWS_SECRET".to_string()),
+                region: Some("us-east-1".to_string()),
+                sanitize_only: false,
+                priority: 0,
+                inject_targets: Vec::new(),
+                signature_header: None,
+                query_param: None,
+                username_env: None,
+                password_env: None,
+            },
+        };
+
+        let custom = build_strategy(&aws_config(Some("AKIACUSTOMDUMMY"))).unwrap();
+        assert_eq!(custom.dummy_patterns(), vec!["AKIACUSTOMDUMMY".to_string()]);
+
+        let default = build_strategy(&aws_config(None)).unwrap();
+        assert_eq!(
+            default.dummy_patterns(),
+            vec!["AKIADUMMY".to_string(), "AKIA00000000DUMMY".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_build_hmac_strategy() {
+        use crate::config::StrategyParams;
+
+        std::env::set_var("TEST_BUILD_HMAC_SECRET", "hmac_secret_value");
+
+        let mut config = StrategyConfig {
+            name: "webhooks".to_string(),
+            strategy_type: "hmac".to_string(),
+            config: StrategyParams {
+                env_var: Some("TEST_BUILD_HMAC_SECRET".to_string()),
+                dummy_pattern: Some("DUMMY_HMAC".to_string()),
+                allowed_hosts: vec!["api.example.com".to_string()],
+                access_key_env: None,
+                secret_key_env: None,
+                region: None,
+                sanitize_only: false,
+                priority: 0,
+                inject_targets: Vec::new(),
+                signature_header: Some("X-Hub-Signature".to_string()),
+                query_param: None,
+                username_env: None,
+                password_env: None,
+            },
+        };
+
+        let strategy = build_strategy(&config).unwrap();
+        assert_eq!(strategy.strategy_type(), "hmac");
+        assert!(strategy.signs_request());
+        assert_eq!(
+            strategy.real_credential(),
+            Some("hmac_secret_value".to_string())
+        );
+
+        let mut headers = axum::http::HeaderMap::new();
+        headers.insert("x-hub-signature", "DUMMY_HMAC".parse().unwrap());
+        strategy
+            .inject_request("POST", "/hooks", "{}", &mut headers)
+            .unwrap();
+        assert_eq!(headers["x-hub-signature"].len(), 64);
+
+        config.config.dummy_pattern = None;
+        assert!(build_strategy(&config).is_err());
+    }
+
+    #[test]
+    fn test_build_query_param_strategy() {
+        use crate::config::StrategyParams;
+
+        std::env::set_var("TEST_BUILD_MAPS_KEY", "AIzaBuildReal");
+
+        let mut config = StrategyConfig {
+            name: "maps".to_string(),
+            strategy_type: "query_param".to_string(),
+            config: StrategyParams {
+                env_var: Some("TEST_BUILD_MAPS_KEY".to_string()),
+                dummy_pattern: Some("DUMMY_MAPS".to_string()),
+                allowed_hosts: vec!["maps.googleapis.com".to_string()],
+                access_key_env: None,
+                secret_key_env: None,
+                region: None,
+                sanitize_only: false,
+                priority: 0,
+                inject_targets: Vec::new(),
+                signature_header: None,
+                query_param: Some("key".to_string()),
+                username_env: None,
+                password_env: None,
+            },
+        };
+
+        let strategy = build_strategy(&config).unwrap();
+        assert_eq!(strategy.strategy_type(), "query_param");
+        assert_eq!(
+            strategy
+                .inject_uri("/geocode?address=x&key=DUMMY_MAPS")
+                .unwrap(),
+            "/geocode?address=x&key=AIzaBuildReal"
+        );
+
+        config.config.query_param = None;
+        assert!(build_strategy(&config).is_err());
+    }
+
+    #[test]
+    fn test_build_basic_auth_strategy() {
+        use crate::config::StrategyParams;
+
+        std::env::set_var("TEST_BUILD_BASIC_USER", "user");
+        std::env::set_var("TEST_BUILD_BASIC_PASS", "pass");
+
+        let mut config = StrategyConfig {
+            name: "jira".to_string(),
+            strategy_type: "basic".to_string(),
+            config: StrategyParams {
+                env_var: None,
+                dummy_pattern: Some("DUMMY_BASIC_JIRA".to_string()),
+                allowed_hosts: vec!["*.atlassian.net".to_string()],
+                access_key_env: None,
+                secret_key_env: None,
+                region: None,
+                sanitize_only: false,
+                priority: 0,
+                inject_targets: Vec::new(),
+                signature_header: None,
+                query_param: None,
+                username_env: Some("TEST_BUILD_BASIC_USER".to_string()),
+                password_env: Some("TEST_BUILD_BASIC_PASS".to_string()),
+            },
+        };
+
+        let strategy = build_strategy(&config).unwrap();
+        assert_eq!(strategy.strategy_type(), "basic");
+        assert_eq!(strategy.real_credential(), Some("dXNlcjpwYXNz".to_string()));
+        assert_eq!(strategy.sanitize_credentials(), vec!["pass".to_string()]);
+
+        config.config.password_env = None;
+        assert!(build_strategy(&config).is_err());
     }
 
     #[test]
@@ -195,6 +493,13 @@ mod tests {
                 access_key_env: None,
                 secret_key_env: None,
                 region: None,
+                sanitize_only: false,
+                priority: 0,
+                inject_targets: Vec::new(),
+                signature_header: None,
+                query_param: None,
+                username_env: None,
+                password_env: None,
             },
         };
 
@@ -202,6 +507,80 @@ mod tests {
         assert!(result.is_ok()); // Strategy builds but warns about missing env var
     }
 
+    #[test]
+    fn test_strict_check_lists_missing_env_vars() {
+        std::env::set_var("STRICT_TEST_OPENAI_KEY", "sk-real");
+        std::env::set_var("STRICT_TEST_AWS_ACCESS", "");
+        let config = Config::from_yaml(
+            r#"
+strategies:
+  - name: openai
+    type: bearer
+    config:
+      env_var: STRICT_TEST_OPENAI_KEY
+      dummy_pattern: "DUMMY_OPENAI"
+  - name: github
+    type: bearer
+    config:
+      env_var: STRICT_TEST_GITHUB_TOKEN
+      dummy_pattern: "DUMMY_GITHUB"
+  - name: aws
+    type: aws_sigv4
+    config:
+      access_key_env: STRICT_TEST_AWS_ACCESS
+      secret_key_env: STRICT_TEST_AWS_SECRET
+"#,
+        )
+        .unwrap();
+
+        let err = check_strategy_credentials(&config).unwrap_err();
+        assert!(err.contains("2 strategies"));
+        assert!(err.contains("github (STRICT_TEST_GITHUB_TOKEN)"));
+        assert!(err.contains("aws (STRICT_TEST_AWS_ACCESS, STRICT_TEST_AWS_SECRET)"));
+        assert!(!err.contains("openai"));
+
+        std::env::set_var("STRICT_TEST_GITHUB_TOKEN", "ghp-real");
+        std::env::set_var("STRICT_TEST_AWS_ACCESS", "AKIA-real");
+        std::env::set_var("STRICT_TEST_AWS_SECRET", "aws-real");
+        assert!(check_strategy_credentials(&config).is_ok());
+    }
+
+    #[test]
+    fn test_build_sanitize_only_bearer_without_dummy() {
+        use crate::config::StrategyParams;
+
+        std::env::set_var("TEST_BUILD_SANITIZE_ONLY", "webhook_secret_value");
+
+        let config = StrategyConfig {
+            name: "webhook".to_string(),
+            strategy_type: "bearer".to_string(),
+            config: StrategyParams {
+                env_var: Some("TEST_BUILD_SANITIZE_ONLY".to_string()),
+                dummy_pattern: None,
+                allowed_hosts: vec![],
+                access_key_env: None,
+                secret_key_env: None,
+                region: None,
+                sanitize_only: true,
+                priority: 0,
+                inject_targets: Vec::new(),
+                signature_header: None,
+                query_param: None,
+                username_env: None,
+                password_env: None,
+            },
+        };
+
+        let strategy = build_strategy(&config).unwrap();
+        assert!(strategy.sanitize_only());
+        assert!(strategy.dummy_patterns().is_empty());
+        assert!(!strategy.detect(&axum::http::HeaderMap::new(), "anything"));
+        assert_eq!(
+            strategy.real_credential(),
+            Some("webhook_secret_value".to_string())
+        );
+    }
+
     #[test]
     fn test_build_strategy_unknown_type() {
         use crate::config::StrategyParams;
@@ -216,6 +595,13 @@ mod tests {
                 access_key_env: None,
                 secret_key_env: None,
                 region: None,
+                sanitize_only: false,
+                priority: 0,
+                inject_targets: Vec::new(),
+                signature_header: None,
+                query_param: None,
+                username_env: None,
+                password_env: None,
             },
         };
 
@@ -0,0 +1,205 @@
+// SLAPENIR Client Scopes
+// Limits the credentials each mTLS client identity may inject
+
+use crate::config::ClientScopeConfig;
+use crate::mtls::ClientCertInfo;
+use crate::sanitizer::SecretMap;
+use std::sync::Arc;
+
+/// The strategies one client may inject, with the SecretMap restricted to them
+pub struct ClientScope {
+    /// Strategy names whose credentials may be injected
+    pub strategies: Vec<String>,
+    /// Injects only those strategies; redacts every secret
+    pub secret_map: Arc<SecretMap>,
+    /// Whether secrets registered at runtime may be injected too
+    pub runtime_secrets: bool,
+}
+
+impl ClientScope {
+    fn new(
+        secret_map: &SecretMap,
+        strategies: Vec<String>,
+        runtime_secrets: bool,
+    ) -> Result<Self, String> {
+        Ok(Self {
+            secret_map: Arc::new(secret_map.scoped(&strategies)?),
+            strategies,
+            runtime_secrets,
+        })
+    }
+
+    /// Whether the client may use strategy `name`
+    pub fn allows(&self, name: &str) -> bool {
+        self.strategies.iter().any(|allowed| allowed == name)
+    }
+}
+
+/// Per-client scopes built from the `security.client_scopes` config
+///
+/// A client matches the scope for its certificate CN, else the one for its
+/// organization. Clients matching neither, or presenting no certificate,
+/// inject nothing, runtime secrets included: one agent's certificate never
+/// unlocks another's keys.
+pub struct ClientScopes {
+    by_common_name: Vec<(String, Arc<ClientScope>)>,
+    by_organization: Vec<(String, Arc<ClientScope>)>,
+    unmatched: Arc<ClientScope>,
+}
+
+impl ClientScopes {
+    /// Build a restricted SecretMap for every configured scope
+    ///
+    /// Each entry names exactly one of `common_name` and `organization`.
+    pub fn new(configs: &[ClientScopeConfig], secret_map: &SecretMap) -> Result<Self, String> {
+        let mut by_common_name = Vec::new();
+        let mut by_organization = Vec::new();
+
+        for config in configs {
+            let scope = Arc::new(ClientScope::new(
+                secret_map,
+                config.strategies.clone(),
+                config.runtime_secrets,
+            )?);
+            let (identity, scopes) = match (&config.common_name, &config.organization) {
+                (Some(cn), None) => (cn, &mut by_common_name),
+                (None, Some(org)) => (org, &mut by_organization),
+                _ => {
+                    return Err(
+                        "Client scope needs exactly one of common_name and organization"
+                            .to_string(),
+                    )
+                }
+            };
+            if identity.is_empty() {
+                return Err("Client scope identity cannot be empty".to_string());
+            }
+            if scopes.iter().any(|(existing, _)| existing == identity) {
+                return Err(format!("Duplicate client scope for '{}'", identity));
+            }
+            scopes.push((identity.clone(), scope));
+        }
+
+        Ok(Self {
+            by_common_name,
+            by_organization,
+            unmatched: Arc::new(ClientScope::new(secret_map, Vec::new(), false)?),
+        })
+    }
+
+    /// Scope for the client that presented `cert`
+    pub fn select(&self, cert: Option<&ClientCertInfo>) -> Arc<ClientScope> {
+        let Some(cert) = cert.filter(|cert| cert.valid) else {
+            return self.unmatched.clone();
+        };
+
+        let by_cn = self
+            .by_common_name
+            .iter()
+            .find(|(cn, _)| *cn == cert.common_name);
+        let by_org = || {
+            self.by_organization
+                .iter()
+                .find(|(org, _)| Some(org) == cert.organization.as_ref())
+        };
+        match by_cn.or_else(by_org) {
+            Some((_, scope)) => scope.clone(),
+            None => {
+                tracing::warn!(
+                    "No client scope for '{}'; no credentials will be injected",
+                    cert.common_name
+                );
+                self.unmatched.clone()
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn secret_map() -> SecretMap {
+        SecretMap::new(HashMap::from([
+            ("DUMMY_OPENAI".to_string(), "sk-openai-real".to_string()),
+            ("DUMMY_GITHUB".to_string(), "ghp_github_real".to_string()),
+        ]))
+        .unwrap()
+    }
+
+    fn cert(common_name: &str, organization: Option<&str>) -> ClientCertInfo {
+        ClientCertInfo {
+            common_name: common_name.to_string(),
+            organization: organization.map(str::to_string),
+            serial: "01".to_string(),
+            valid: true,
+        }
+    }
+
+    fn scope(cn: Option<&str>, org: Option<&str>, strategies: &[&str]) -> ClientScopeConfig {
+        ClientScopeConfig {
+            common_name: cn.map(str::to_string),
+            organization: org.map(str::to_string),
+            strategies: strategies.iter().map(|s| s.to_string()).collect(),
+            runtime_secrets: false,
+        }
+    }
+
+    #[test]
+    fn test_select_by_common_name_then_organization() {
+        let scopes = ClientScopes::new(
+            &[
+                scope(Some("agent-a"), None, &["DUMMY_OPENAI"]),
+                scope(None, Some("team-b"), &["DUMMY_GITHUB"]),
+            ],
+            &secret_map(),
+        )
+        .unwrap();
+
+        let a = scopes.select(Some(&cert("agent-a", Some("team-b"))));
+        assert!(a.allows("DUMMY_OPENAI"));
+        assert_eq!(
+            a.secret_map.inject("DUMMY_OPENAI DUMMY_GITHUB"),
+            "sk-openai-real DUMMY_GITHUB"
+        );
+
+        let b = scopes.select(Some(&cert("agent-b", Some("team-b"))));
+        assert!(b.allows("DUMMY_GITHUB"));
+        assert!(!b.allows("DUMMY_OPENAI"));
+    }
+
+    #[test]
+    fn test_unmatched_client_injects_nothing() {
+        let scopes = ClientScopes::new(
+            &[scope(Some("agent-a"), None, &["DUMMY_OPENAI"])],
+            &secret_map(),
+        )
+        .unwrap();
+
+        for unmatched in [
+            scopes.select(Some(&cert("agent-z", None))),
+            scopes.select(None),
+        ] {
+            assert!(!unmatched.allows("DUMMY_OPENAI"));
+            assert_eq!(unmatched.secret_map.inject("DUMMY_OPENAI"), "DUMMY_OPENAI");
+            assert_eq!(
+                unmatched.secret_map.sanitize("sk-openai-real"),
+                "[REDACTED]"
+            );
+        }
+    }
+
+    #[test]
+    fn test_invalid_scopes_rejected() {
+        let map = secret_map();
+        assert!(ClientScopes::new(&[scope(None, None, &[])], &map).is_err());
+        assert!(ClientScopes::new(&[scope(Some("a"), Some("b"), &[])], &map).is_err());
+        assert!(ClientScopes::new(&[scope(Some(""), None, &[])], &map).is_err());
+        assert!(ClientScopes::new(
+            &[scope(Some("a"), None, &[]), scope(Some("a"), None, &[])],
+            &map
+        )
+        .is_err());
+    }
+}
@@ -2,6 +2,7 @@
 // Inspired by safe-claude's flexible configuration system
 
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::Path;
 
@@ -22,6 +23,22 @@ pub struct Config {
     /// Logging configuration
     #[serde(default)]
     pub logging: LoggingConfig,
+
+    /// TCP socket tuning
+    #[serde(default)]
+    pub network: NetworkConfig,
+
+    /// Request limits, timeouts and forwarding behaviour
+    #[serde(default)]
+    pub proxy: ProxySection,
+
+    /// Per-route and per-destination handling
+    #[serde(default)]
+    pub routing: RoutingSection,
+
+    /// Anomaly guards and MITM concurrency limits
+    #[serde(default)]
+    pub limits: LimitsSection,
 }
 
 /// Auto-detection configuration section
@@ -50,7 +67,7 @@ fn default_max_strategies() -> usize {
 }
 
 /// Strategy configuration
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct StrategyConfig {
     /// Strategy name for identification
     pub name: String,
@@ -64,7 +81,7 @@ pub struct StrategyConfig {
 }
 
 /// Strategy parameters (flexible key-value pairs)
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct StrategyParams {
     /// Environment variable name for the credential
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -89,6 +106,34 @@ pub struct StrategyParams {
     /// AWS-specific: region
     #[serde(skip_serializing_if = "Option::is_none")]
     pub region: Option<String>,
+
+    /// Only redact this credential from responses; never inject it
+    #[serde(default)]
+    pub sanitize_only: bool,
+
+    /// Precedence over strategies with overlapping dummy patterns (higher wins)
+    #[serde(default)]
+    pub priority: i32,
+
+    /// Where the credential may be injected: `body`, `headers` or header names (default both)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inject_targets: Vec<String>,
+
+    /// HMAC-specific: header carrying the signature (default `X-Signature`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature_header: Option<String>,
+
+    /// Query-param-specific: name of the parameter carrying the key
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub query_param: Option<String>,
+
+    /// Basic-auth-specific: username environment variable
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username_env: Option<String>,
+
+    /// Basic-auth-specific: password environment variable
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password_env: Option<String>,
 }
 
 /// Security configuration
@@ -98,6 +143,10 @@ pub struct SecurityConfig {
     #[serde(default = "default_fail_mode")]
     pub fail_mode: String,
 
+    /// Refuse to start when a strategy's credential environment variable is unset
+    #[serde(default)]
+    pub strict_mode: bool,
+
     /// Enable telemetry blocking
     #[serde(default = "default_true")]
     pub block_telemetry: bool,
@@ -105,12 +154,86 @@ pub struct SecurityConfig {
     /// List of telemetry domains to block
     #[serde(default)]
     pub telemetry_domains: Vec<String>,
+
+    /// Egress denylist (`*.` for subdomains); the admin API can replace it at runtime
+    #[serde(default)]
+    pub denied_hosts: Vec<String>,
+
+    /// Egress allowlist for the plain HTTP path (`*.` for subdomains); when set,
+    /// every other target is refused before injection
+    #[serde(default)]
+    pub egress_allowed_hosts: Vec<String>,
+
+    /// Hosts any credential may be injected for (`*.` for subdomains), checked
+    /// on top of each strategy's `allowed_hosts`; empty defers to the strategies
+    #[serde(default)]
+    pub injection_allowed_hosts: Vec<String>,
+
+    /// Requests carrying a dummy and an unmanaged real credential: `warn`, `strip` or `block`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mixed_credential_policy: Option<String>,
+
+    /// Request bodies that are not UTF-8: `reject`, `inject_bytes` or `passthrough`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub non_utf8_body_policy: Option<String>,
+
+    /// Response headers stripped from proxied responses (built-in list when unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub blocked_headers: Option<Vec<String>>,
+
+    /// Media types (`type/subtype` or `type/*`) responses may carry; empty allows all
+    #[serde(default)]
+    pub allowed_response_content_types: Vec<String>,
+
+    /// Regex rules over outbound request bodies, applied in order
+    #[serde(default)]
+    pub dlp_rules: Vec<DlpRuleConfig>,
+
+    /// Strategies each mTLS client may inject; when set, unmatched clients inject none
+    #[serde(default)]
+    pub client_scopes: Vec<ClientScopeConfig>,
+}
+
+/// Strategies one mTLS client identity may inject, matched by certificate
+/// common name or, failing that, organization
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClientScopeConfig {
+    /// Client certificate CN this scope applies to
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub common_name: Option<String>,
+
+    /// Client certificate O this scope applies to, when no CN scope matches
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub organization: Option<String>,
+
+    /// Names of the strategies whose credentials the client may inject
+    #[serde(default)]
+    pub strategies: Vec<String>,
+
+    /// Whether the client may inject secrets registered at runtime
+    #[serde(default)]
+    pub runtime_secrets: bool,
+}
+
+/// Outbound DLP rule: requests whose body matches `pattern` are handled by `action`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DlpRuleConfig {
+    /// Reported in logs, errors and the `rule` metric label instead of the match
+    pub name: String,
+
+    /// Regular expression matched against the request body
+    pub pattern: String,
+
+    /// `block` (403) or `redact` (replace the match and forward)
+    #[serde(default = "default_dlp_action")]
+    pub action: String,
 }
 
 impl Default for SecurityConfig {
     fn default() -> Self {
         Self {
             fail_mode: "closed".to_string(),
+            strict_mode: false,
             block_telemetry: true,
             telemetry_domains: vec![
                 "telemetry.anthropic.com".to_string(),
@@ -121,6 +244,15 @@ impl Default for SecurityConfig {
                 "mixpanel.com".to_string(),
                 "*.mixpanel.com".to_string(),
             ],
+            denied_hosts: Vec::new(),
+            egress_allowed_hosts: Vec::new(),
+            injection_allowed_hosts: Vec::new(),
+            mixed_credential_policy: None,
+            non_utf8_body_policy: None,
+            blocked_headers: None,
+            allowed_response_content_types: Vec::new(),
+            dlp_rules: Vec::new(),
+            client_scopes: Vec::new(),
         }
     }
 }
@@ -146,11 +278,291 @@ impl Default for LoggingConfig {
     }
 }
 
+/// TCP socket options for accepted client and upstream connections
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NetworkConfig {
+    /// Disable Nagle's algorithm (on by default for interactive LLM traffic)
+    #[serde(default = "default_true")]
+    pub tcp_nodelay: bool,
+
+    /// SO_SNDBUF size in bytes (OS default when unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub so_sndbuf: Option<usize>,
+
+    /// SO_RCVBUF size in bytes (OS default when unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub so_rcvbuf: Option<usize>,
+
+    /// TCP keepalive idle time in seconds (disabled when unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tcp_keepalive: Option<u64>,
+}
+
+impl Default for NetworkConfig {
+    fn default() -> Self {
+        Self {
+            tcp_nodelay: true,
+            so_sndbuf: None,
+            so_rcvbuf: None,
+            tcp_keepalive: None,
+        }
+    }
+}
+
+/// Request limits, timeouts and forwarding behaviour; unset fields keep the built-in defaults
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProxySection {
+    /// `inject` or `sanitize_only`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mode: Option<String>,
+
+    /// Maximum request body size in bytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_request_size: Option<usize>,
+
+    /// Maximum buffered response body size in bytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_response_size: Option<usize>,
+
+    /// Responses declaring a larger Content-Length are sanitized as a stream
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream_response_threshold: Option<usize>,
+
+    /// Sanitize unencoded responses without a Content-Length as a stream
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream_unsized_responses: Option<bool>,
+
+    /// Seconds to wait for the client to finish sending the request body
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_body_timeout_secs: Option<u64>,
+
+    /// Seconds allowed to connect to an upstream
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upstream_connect_timeout_secs: Option<u64>,
+
+    /// Seconds allowed for an upstream's response (whole body when buffered, headers when streamed)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upstream_response_timeout_secs: Option<u64>,
+
+    /// Seconds a streamed response may go without a chunk before it is aborted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub upstream_read_timeout_secs: Option<u64>,
+
+    /// Attempts per upstream request, the first included (1 disables retries)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_max_attempts: Option<u32>,
+
+    /// Milliseconds before the first retry, doubled for each one after it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_base_delay_ms: Option<u64>,
+
+    /// Randomize retry delays between half and all of their value
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_jitter: Option<bool>,
+
+    /// Follow upstream 3xx redirects instead of returning them to the agent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub follow_redirects: Option<bool>,
+
+    /// Redirect hops followed for one request before it fails
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_redirects: Option<usize>,
+
+    /// Send `Connection: close` to the agent when the upstream closes its connection
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub propagate_upstream_close: Option<bool>,
+
+    /// Sanitize `application/x-ndjson` responses line by line as they stream
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream_ndjson: Option<bool>,
+
+    /// Sanitize `text/event-stream` responses event by event as they stream
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stream_sse: Option<bool>,
+
+    /// Let identical in-flight GET/HEAD or `Idempotency-Key` requests share one upstream response
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deduplicate_requests: Option<bool>,
+
+    /// Longest unterminated line in a streamed NDJSON/SSE response before it is aborted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_stream_line_length: Option<usize>,
+
+    /// Redact only string values of JSON responses, leaving keys and structure intact
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sanitize_json_values: Option<bool>,
+
+    /// JSON responses left unparseable by redaction: `warn` or `text_plain`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub invalid_sanitized_json: Option<String>,
+
+    /// Trace context headers added to forwarded requests: `off`, `w3c` or `w3c_b3`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trace_propagation: Option<String>,
+
+    /// Redact real secrets from outbound headers other than credential headers
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sanitize_request_headers: Option<bool>,
+
+    /// Drop upstream `Access-Control-*` headers so `add_response_headers` sets the CORS policy
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub strip_upstream_cors: Option<bool>,
+
+    /// Headers added to, or overriding, every proxied response
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub add_response_headers: BTreeMap<String, String>,
+
+    /// Compress sanitized buffered responses (gzip or br) for clients that accept it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub compress_responses: Option<bool>,
+
+    /// HTML responses larger than this many bytes are not entity-decoded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_html_entity_decode_size: Option<usize>,
+
+    /// Milliseconds of quiet after runtime secret changes before their automaton is rebuilt
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub runtime_secret_rebuild_debounce_ms: Option<u64>,
+
+    /// Forward `Link` headers from upstream `103 Early Hints` onto the final response
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub forward_early_hints: Option<bool>,
+
+    /// Transcode buffered responses in a declared non-UTF-8 charset to UTF-8 before sanitizing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub normalize_response_charset: Option<bool>,
+
+    /// Also redact secrets hidden behind HTML entities in `text/html` responses
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decode_html_entities: Option<bool>,
+
+    /// Identify the proxy to upstreams: `off`, `user-agent` or `header`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy_identification: Option<String>,
+
+    /// Longest upstream response header value passed through, in bytes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_header_value_len: Option<usize>,
+
+    /// Longer header values: `truncate`, or `reject` the response
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub oversized_header_action: Option<String>,
+}
+
+/// Per-route and per-destination handling
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RoutingSection {
+    /// Per-route overrides, matched on the forwarded path; first match wins
+    #[serde(default)]
+    pub routes: Vec<RouteConfig>,
+
+    /// Probe paths answered locally (trailing `*` = prefix)
+    #[serde(default)]
+    pub probe_paths: Vec<String>,
+
+    /// Route templates for the metrics `endpoint` label (built-in list when unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint_templates: Option<Vec<String>>,
+
+    /// ClientHello SNIs tunnelled without MITM (`*.` = subdomains)
+    #[serde(default)]
+    pub mitm_bypass_sni: Vec<String>,
+
+    /// CONNECT ports carrying plaintext that is injected and sanitized
+    #[serde(default)]
+    pub inspect_plaintext_ports: Vec<u16>,
+
+    /// CONNECT ports whose TLS is intercepted (443 and 8443 when unset; empty disables)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub intercept_ports: Option<Vec<u16>>,
+
+    /// Intercept TLS on every CONNECT port not inspected as plaintext
+    #[serde(default)]
+    pub intercept_all_tls: bool,
+
+    /// Path (and optionally method) rewrites applied before forwarding; first match wins
+    #[serde(default)]
+    pub rewrite_rules: Vec<RewriteRuleConfig>,
+}
+
+/// Anomaly guards and MITM concurrency limits; unset fields keep the built-in defaults
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LimitsSection {
+    /// Responses containing more secrets than this are rejected
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_redactions_per_response: Option<usize>,
+
+    /// Fail MITM tunnels whose request still holds a dummy after injection
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub fail_on_residual_dummy: Option<bool>,
+
+    /// MITM handshakes allowed in progress at once
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_concurrent_mitm_handshakes: Option<usize>,
+
+    /// Seconds a tunnel waits for a handshake slot before it is shed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mitm_handshake_queue_timeout_secs: Option<u64>,
+
+    /// Distinct upstream hosts remembered for egress auditing (0 disables)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_tracked_upstream_hosts: Option<usize>,
+
+    /// Total bytes of real secret material held in memory, static and runtime
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_secret_bytes: Option<usize>,
+
+    /// Sustained requests per second each client identity may send (unlimited when unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit_requests_per_second: Option<u32>,
+
+    /// Requests an idle client identity may send at once (defaults to the per-second rate)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit_burst: Option<u32>,
+
+    /// Request and response bytes each client identity may proxy per window (unlimited when unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub byte_quota_max_bytes: Option<u64>,
+
+    /// Length of the byte quota window in seconds (3600 when unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub byte_quota_window_secs: Option<u64>,
+}
+
+/// Rewrite of matching request paths, e.g. to map one provider's API onto another's
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RewriteRuleConfig {
+    /// Regular expression matched against the request path (without the query)
+    pub pattern: String,
+
+    /// Replacement path; captures are referenced as `$1` or `${name}`
+    pub replacement: String,
+
+    /// Method to forward matching requests with (kept as sent when unset)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub method: Option<String>,
+}
+
+/// Per-route behaviour, e.g. a public endpoint that must not see credentials
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RouteConfig {
+    /// Path pattern; matches exactly, or by prefix when it ends in `*`
+    pub path: String,
+
+    /// Replace dummies with real credentials (responses are sanitized either way)
+    #[serde(default = "default_true")]
+    pub inject: bool,
+}
+
 // Default value functions for serde
 fn default_fail_mode() -> String {
     "closed".to_string()
 }
 
+fn default_dlp_action() -> String {
+    "block".to_string()
+}
+
 fn default_true() -> bool {
     true
 }
@@ -164,12 +576,37 @@ fn default_log_format() -> String {
 }
 
 impl Config {
-    /// Load configuration from YAML file
+    /// Load configuration from a YAML file, or TOML when the extension is `.toml`
+    ///
+    /// String values may reference environment variables as `${VAR}`, or
+    /// `${VAR:-default}` to fall back when VAR is unset; `$${` is a literal
+    /// `${`. A referenced variable that is unset, with no default, is an error.
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, String> {
         let content = fs::read_to_string(path.as_ref())
             .map_err(|e| format!("Failed to read config file: {}", e))?;
+        let toml = path.as_ref().extension().and_then(|ext| ext.to_str()) == Some("toml");
 
-        Self::from_yaml(&content)
+        // Without references the file is parsed directly, keeping error locations
+        if !content.contains("${") {
+            return match toml {
+                true => Self::from_toml(&content),
+                false => Self::from_yaml(&content),
+            };
+        }
+        let lookup = |name: &str| std::env::var(name).ok();
+        if toml {
+            let mut value: toml::Value = toml::from_str(&content)
+                .map_err(|e| format!("Failed to parse config TOML: {}", e))?;
+            interpolate_toml(&mut value, &lookup)?;
+            value
+                .try_into()
+                .map_err(|e| format!("Failed to parse config TOML: {}", e))
+        } else {
+            let mut value: serde_yaml::Value = serde_yaml::from_str(&content)
+                .map_err(|e| format!("Failed to parse config YAML: {}", e))?;
+            interpolate_yaml(&mut value, &lookup)?;
+            serde_yaml::from_value(value).map_err(|e| format!("Failed to parse config YAML: {}", e))
+        }
     }
 
     /// Parse configuration from YAML string
@@ -177,6 +614,11 @@ impl Config {
         serde_yaml::from_str(yaml).map_err(|e| format!("Failed to parse config YAML: {}", e))
     }
 
+    /// Parse configuration from TOML string
+    pub fn from_toml(toml: &str) -> Result<Self, String> {
+        toml::from_str(toml).map_err(|e| format!("Failed to parse config TOML: {}", e))
+    }
+
     /// Validate configuration
     pub fn validate(&self) -> Result<(), String> {
         if self.strategies.is_empty() {
@@ -191,7 +633,7 @@ impl Config {
 
             // Validate strategy type
             match strategy.strategy_type.as_str() {
-                "bearer" | "aws_sigv4" | "hmac" => {}
+                "bearer" | "aws_sigv4" | "hmac" | "query_param" | "basic" => {}
                 _ => {
                     return Err(format!(
                         "Unknown strategy type '{}' for strategy '{}'",
@@ -216,6 +658,30 @@ impl Config {
                 }
             }
 
+            // Validate query param strategy has required fields
+            if strategy.strategy_type == "query_param"
+                && (strategy.config.env_var.is_none()
+                    || strategy.config.dummy_pattern.is_none()
+                    || strategy.config.query_param.is_none())
+            {
+                return Err(format!(
+                    "Query param strategy '{}' missing env_var, dummy_pattern or query_param",
+                    strategy.name
+                ));
+            }
+
+            // Validate Basic auth strategy has required fields
+            if strategy.strategy_type == "basic"
+                && (strategy.config.username_env.is_none()
+                    || strategy.config.password_env.is_none()
+                    || strategy.config.dummy_pattern.is_none())
+            {
+                return Err(format!(
+                    "Basic auth strategy '{}' missing username_env, password_env or dummy_pattern",
+                    strategy.name
+                ));
+            }
+
             // Validate AWS SigV4 strategy has required fields
             if strategy.strategy_type == "aws_sigv4"
                 && (strategy.config.access_key_env.is_none()
@@ -284,6 +750,13 @@ impl Config {
                         access_key_env: None,
                         secret_key_env: None,
                         region: None,
+                        sanitize_only: false,
+                        priority: 0,
+                        inject_targets: Vec::new(),
+                        signature_header: None,
+                        query_param: None,
+                        username_env: None,
+                        password_env: None,
                     },
                 },
                 StrategyConfig {
@@ -296,16 +769,113 @@ impl Config {
                         access_key_env: None,
                         secret_key_env: None,
                         region: None,
+                        sanitize_only: false,
+                        priority: 0,
+                        inject_targets: Vec::new(),
+                        signature_header: None,
+                        query_param: None,
+                        username_env: None,
+                        password_env: None,
                     },
                 },
             ],
             auto_detect: AutoDetectSection::default(),
             security: SecurityConfig::default(),
             logging: LoggingConfig::default(),
+            network: NetworkConfig::default(),
+            proxy: ProxySection::default(),
+            routing: RoutingSection::default(),
+            limits: LimitsSection::default(),
         }
     }
 }
 
+/// Expand `${VAR}` and `${VAR:-default}` references in `text` using `lookup`
+fn interpolate_env(text: &str, lookup: &dyn Fn(&str) -> Option<String>) -> Result<String, String> {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        if rest[..start].ends_with('$') {
+            result.push_str(&rest[..start - 1]);
+            result.push_str("${");
+            rest = &rest[start + 2..];
+            continue;
+        }
+        result.push_str(&rest[..start]);
+        let reference = &rest[start + 2..];
+        let end = reference
+            .find('}')
+            .ok_or_else(|| format!("Unterminated '${{' in config value '{}'", text))?;
+        let (name, default) = match reference[..end].split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (&reference[..end], None),
+        };
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(format!(
+                "Invalid environment variable name '{}' in config",
+                name
+            ));
+        }
+        match lookup(name).or_else(|| default.map(String::from)) {
+            Some(value) => result.push_str(&value),
+            None => {
+                return Err(format!(
+                    "Environment variable '{}' referenced in config is not set",
+                    name
+                ))
+            }
+        }
+        rest = &reference[end + 1..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// Expand environment references in every string value of a YAML document
+fn interpolate_yaml(
+    value: &mut serde_yaml::Value,
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> Result<(), String> {
+    match value {
+        serde_yaml::Value::String(text) => *text = interpolate_env(text, lookup)?,
+        serde_yaml::Value::Sequence(items) => {
+            for item in items {
+                interpolate_yaml(item, lookup)?;
+            }
+        }
+        serde_yaml::Value::Mapping(entries) => {
+            for (_, item) in entries.iter_mut() {
+                interpolate_yaml(item, lookup)?;
+            }
+        }
+        serde_yaml::Value::Tagged(tagged) => interpolate_yaml(&mut tagged.value, lookup)?,
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Expand environment references in every string value of a TOML document
+fn interpolate_toml(
+    value: &mut toml::Value,
+    lookup: &dyn Fn(&str) -> Option<String>,
+) -> Result<(), String> {
+    match value {
+        toml::Value::String(text) => *text = interpolate_env(text, lookup)?,
+        toml::Value::Array(items) => {
+            for item in items {
+                interpolate_toml(item, lookup)?;
+            }
+        }
+        toml::Value::Table(entries) => {
+            for (_, item) in entries.iter_mut() {
+                interpolate_toml(item, lookup)?;
+            }
+        }
+        _ => {}
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -344,6 +914,10 @@ logging:
             auto_detect: AutoDetectSection::default(),
             security: SecurityConfig::default(),
             logging: LoggingConfig::default(),
+            network: NetworkConfig::default(),
+            proxy: ProxySection::default(),
+            routing: RoutingSection::default(),
+            limits: LimitsSection::default(),
         };
 
         assert!(config.validate().is_err());
@@ -364,4 +938,210 @@ logging:
         assert_eq!(config.security.fail_mode, "closed");
         assert!(config.security.block_telemetry);
     }
+
+    #[test]
+    fn test_parse_network_config() {
+        let yaml = r#"
+strategies:
+  - name: openai
+    type: bearer
+    config:
+      env_var: OPENAI_API_KEY
+      dummy_pattern: DUMMY_OPENAI
+
+network:
+  tcp_nodelay: false
+  so_sndbuf: 262144
+  so_rcvbuf: 131072
+  tcp_keepalive: 30
+"#;
+
+        let config = Config::from_yaml(yaml).unwrap();
+        assert!(!config.network.tcp_nodelay);
+        assert_eq!(config.network.so_sndbuf, Some(262144));
+        assert_eq!(config.network.so_rcvbuf, Some(131072));
+        assert_eq!(config.network.tcp_keepalive, Some(30));
+    }
+
+    #[test]
+    fn test_network_config_defaults() {
+        let yaml = r#"
+strategies:
+  - name: openai
+    type: bearer
+    config:
+      env_var: OPENAI_API_KEY
+      dummy_pattern: DUMMY_OPENAI
+"#;
+
+        let config = Config::from_yaml(yaml).unwrap();
+        assert_eq!(config.network, NetworkConfig::default());
+        assert!(config.network.tcp_nodelay);
+        assert!(config.network.so_sndbuf.is_none());
+        assert!(config.network.tcp_keepalive.is_none());
+    }
+
+    #[test]
+    fn test_parse_routes() {
+        let yaml = r#"
+strategies:
+  - name: openai
+    type: bearer
+    config:
+      env_var: OPENAI_API_KEY
+      dummy_pattern: DUMMY_OPENAI
+routing:
+  routes:
+    - path: /v1/health
+      inject: false
+    - path: /v1/models*
+"#;
+
+        let config = Config::from_yaml(yaml).unwrap();
+        assert_eq!(
+            config.routing.routes,
+            vec![
+                RouteConfig {
+                    path: "/v1/health".to_string(),
+                    inject: false,
+                },
+                RouteConfig {
+                    path: "/v1/models*".to_string(),
+                    inject: true,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_toml_config() {
+        let toml = r#"
+[[strategies]]
+name = "openai"
+type = "bearer"
+config = { env_var = "OPENAI_API_KEY", dummy_pattern = "DUMMY_OPENAI" }
+
+[proxy]
+max_request_size = 1024
+
+[security]
+denied_hosts = ["evil.example.com"]
+"#;
+
+        let config = Config::from_toml(toml).unwrap();
+        assert_eq!(config.strategies[0].name, "openai");
+        assert_eq!(config.proxy.max_request_size, Some(1024));
+        assert_eq!(config.security.denied_hosts, vec!["evil.example.com"]);
+        assert_eq!(config.limits, LimitsSection::default());
+    }
+
+    fn lookup(name: &str) -> Option<String> {
+        match name {
+            "API_HOST" => Some("api.internal.example".to_string()),
+            "EMPTY" => Some(String::new()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_interpolate_env_expands_references() {
+        assert_eq!(
+            interpolate_env("https://${API_HOST}/v1", &lookup).unwrap(),
+            "https://api.internal.example/v1"
+        );
+        assert_eq!(
+            interpolate_env("no references", &lookup).unwrap(),
+            "no references"
+        );
+        assert_eq!(interpolate_env("[${EMPTY}]", &lookup).unwrap(), "[]");
+        assert_eq!(
+            interpolate_env("$${API_HOST}", &lookup).unwrap(),
+            "${API_HOST}"
+        );
+    }
+
+    #[test]
+    fn test_interpolate_env_defaults() {
+        assert_eq!(
+            interpolate_env("${AWS_REGION:-us-east-1}", &lookup).unwrap(),
+            "us-east-1"
+        );
+        assert_eq!(interpolate_env("${UNSET:-}", &lookup).unwrap(), "");
+        // A set variable wins over the default
+        assert_eq!(
+            interpolate_env("${API_HOST:-fallback}", &lookup).unwrap(),
+            "api.internal.example"
+        );
+    }
+
+    #[test]
+    fn test_interpolate_env_errors() {
+        assert_eq!(
+            interpolate_env("${MISSING_VAR}", &lookup).unwrap_err(),
+            "Environment variable 'MISSING_VAR' referenced in config is not set"
+        );
+        assert!(interpolate_env("${API_HOST", &lookup).is_err());
+        assert!(interpolate_env("${BAD-NAME}", &lookup).is_err());
+    }
+
+    #[test]
+    fn test_from_file_interpolates_string_values() {
+        std::env::set_var("SLAPENIR_CONFIG_TEST_HOST", "api.internal.example");
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.yaml");
+        std::fs::write(
+            &path,
+            r#"
+# ${COMMENTS_ARE_NOT_EXPANDED}
+strategies:
+  - name: internal
+    type: bearer
+    config:
+      env_var: ${SLAPENIR_CONFIG_TEST_KEY_VAR:-INTERNAL_API_KEY}
+      dummy_pattern: DUMMY_INTERNAL
+      allowed_hosts: ["${SLAPENIR_CONFIG_TEST_HOST}"]
+"#,
+        )
+        .unwrap();
+
+        let config = Config::from_file(&path).unwrap();
+        let strategy = &config.strategies[0].config;
+        assert_eq!(strategy.env_var.as_deref(), Some("INTERNAL_API_KEY"));
+        assert_eq!(strategy.allowed_hosts, vec!["api.internal.example"]);
+
+        let toml_path = dir.path().join("config.toml");
+        std::fs::write(
+            &toml_path,
+            r#"
+[[strategies]]
+name = "internal"
+type = "bearer"
+
+[strategies.config]
+env_var = "INTERNAL_API_KEY"
+dummy_pattern = "DUMMY_INTERNAL"
+allowed_hosts = ["${SLAPENIR_CONFIG_TEST_HOST}"]
+"#,
+        )
+        .unwrap();
+        let config = Config::from_file(&toml_path).unwrap();
+        assert_eq!(
+            config.strategies[0].config.allowed_hosts,
+            vec!["api.internal.example"]
+        );
+    }
+
+    #[test]
+    fn test_from_file_rejects_unset_reference() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.yaml");
+        std::fs::write(
+            &path,
+            "strategies: []\nsecurity:\n  denied_hosts: [\"${SLAPENIR_CONFIG_TEST_UNSET}\"]\n",
+        )
+        .unwrap();
+
+        let err = Config::from_file(&path).unwrap_err();
+        assert!(err.contains("'SLAPENIR_CONFIG_TEST_UNSET'"), "{}", err);
+    }
 }
@@ -0,0 +1,156 @@
+// SLAPENIR Config Reload - Apply config file changes without a restart
+// Watches the config file and swaps freshly built strategies and secrets into
+// the running proxy. A config that fails to load or validate changes nothing,
+// and requests in flight finish with the credentials they started with.
+
+use crate::builder::build_strategies_from_config;
+use crate::config::Config;
+use crate::metrics;
+use crate::middleware::AppState;
+use crate::proxy::ProxyConfig;
+use crate::sanitizer::SecretMap;
+use crate::strategy::AuthStrategy;
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Quiet period after a change before reloading, so an editor's several
+/// writes are applied once
+pub const DEFAULT_RELOAD_DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// Static credentials built from a config: its SecretMap and strategies
+pub type BuiltCredentials = (SecretMap, Vec<Box<dyn AuthStrategy>>);
+
+/// Builds the static credentials for a freshly loaded config
+pub type CredentialBuilder = dyn Fn(&Config) -> Result<BuiltCredentials, String> + Send + Sync;
+
+/// Build credentials from the config's strategies alone
+pub fn build_credentials(config: &Config) -> Result<BuiltCredentials, String> {
+    let strategies = build_strategies_from_config(config)?;
+    let secret_map = SecretMap::from_strategies(&strategies)?;
+    Ok((secret_map, strategies))
+}
+
+/// Reloads a config file's strategies, secrets and client scopes into an [`AppState`]
+///
+/// Only credentials are reloaded; other settings still apply on restart.
+pub struct ConfigReloader {
+    path: PathBuf,
+    state: AppState,
+    build: Arc<CredentialBuilder>,
+    debounce: Duration,
+}
+
+impl ConfigReloader {
+    /// Reload `path` into `state`, building credentials with [`build_credentials`]
+    pub fn new(path: impl Into<PathBuf>, state: AppState) -> Self {
+        Self {
+            path: path.into(),
+            state,
+            build: Arc::new(build_credentials),
+            debounce: DEFAULT_RELOAD_DEBOUNCE,
+        }
+    }
+
+    /// Build credentials with `build` instead, e.g. to add secrets from other sources
+    pub fn with_builder(
+        mut self,
+        build: impl Fn(&Config) -> Result<BuiltCredentials, String> + Send + Sync + 'static,
+    ) -> Self {
+        self.build = Arc::new(build);
+        self
+    }
+
+    /// Wait `debounce` after a change before reloading
+    pub fn with_debounce(mut self, debounce: Duration) -> Self {
+        self.debounce = debounce;
+        self
+    }
+
+    /// Load, validate and apply the config file once
+    ///
+    /// Returns the number of strategies now in force. On error the current
+    /// credentials are kept.
+    pub fn reload(&self) -> Result<usize, String> {
+        let config = Config::from_file(&self.path)?;
+        config.validate()?;
+        // Settings are checked as at startup, though only credentials change
+        ProxyConfig::from_config(&config)?;
+
+        let (secret_map, strategies) = (self.build)(&config)?;
+        let count = strategies.len();
+        self.state
+            .replace_credentials(secret_map, strategies, &config.security.client_scopes)?;
+        Ok(count)
+    }
+
+    /// Reload whenever the config file changes, until the returned watcher is dropped
+    ///
+    /// The file's directory is watched, so a file replaced by rename (most
+    /// editors, mounted ConfigMaps) is picked up too; events that leave its
+    /// contents unchanged are ignored. Must be called within a Tokio runtime.
+    pub fn watch(self) -> Result<RecommendedWatcher, String> {
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let mut watcher =
+            notify::recommended_watcher(move |event: notify::Result<notify::Event>| {
+                if let Ok(event) = event {
+                    if !matches!(event.kind, EventKind::Access(_)) {
+                        let _ = tx.send(());
+                    }
+                }
+            })
+            .map_err(|e| format!("Failed to create config watcher: {}", e))?;
+
+        let dir = match self.path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        watcher
+            .watch(dir, RecursiveMode::NonRecursive)
+            .map_err(|e| format!("Failed to watch {}: {}", dir.display(), e))?;
+
+        let mut applied = std::fs::read(&self.path).ok();
+        let reloader = Arc::new(self);
+        tokio::spawn(async move {
+            while rx.recv().await.is_some() {
+                tokio::time::sleep(reloader.debounce).await;
+                while rx.try_recv().is_ok() {}
+
+                let contents = tokio::fs::read(&reloader.path).await.ok();
+                if contents.is_none() || contents == applied {
+                    continue;
+                }
+                applied = contents;
+                // Reading and building credentials block, so keep them off the runtime
+                let reload = {
+                    let reloader = reloader.clone();
+                    tokio::task::spawn_blocking(move || reloader.reload())
+                };
+                let result = reload
+                    .await
+                    .unwrap_or_else(|e| Err(format!("Reload task failed: {}", e)));
+                match result {
+                    Ok(count) => {
+                        tracing::info!(
+                            "🔄 Reloaded {}: {} strategies in force",
+                            reloader.path.display(),
+                            count
+                        );
+                        metrics::record_config_reload("applied");
+                    }
+                    Err(e) => {
+                        tracing::error!(
+                            "Rejected change to {}, keeping the current credentials: {}",
+                            reloader.path.display(),
+                            e
+                        );
+                        metrics::record_config_reload("rejected");
+                    }
+                }
+            }
+        });
+
+        Ok(watcher)
+    }
+}
@@ -9,11 +9,17 @@ use axum::{
 };
 use hyper::upgrade::Upgraded;
 use hyper_util::rt::TokioIo;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 use tokio::net::TcpStream;
 use tracing::{debug, error, info, warn};
 
+use crate::connect_full::tunnel_with_tls_mitm_full;
+use crate::metrics;
 use crate::middleware::AppState;
+use crate::mtls::ClientCertInfo;
+use crate::proxy::{client_identity, ProxyError, DEFAULT_INTERCEPT_PORTS};
+use crate::strategy::{detect_and_validate_strategies, SecurityError};
+use crate::tls::extract_sni;
 
 /// Handle HTTP CONNECT requests for HTTPS tunneling
 ///
@@ -38,17 +44,45 @@ where
 {
     info!("🔌 Handling CONNECT request");
 
+    // Everything in the tunnel injects only what this client is scoped to
+    let mut state = state.for_client(req.extensions().get::<ClientCertInfo>());
+
+    // The CONNECT counts against the client's rate, as does each request
+    // inside an intercepted tunnel
+    let identity = client_identity(req.extensions());
+    if let Err(wait) = state.check_rate_limit(&identity, "connect") {
+        return Ok(ProxyError::RateLimited(identity, wait).into_response());
+    }
+    state.client_identity = Some(identity);
+
     // Extract destination from URI
     let uri = req.uri().clone();
     let destination = parse_destination(&uri)?;
 
     info!("📡 CONNECT to: {}", destination);
 
+    // Operator-maintained egress denylist, replaceable at runtime
+    let hostname = extract_hostname(&destination)?;
+    if state.is_host_denied(&hostname) {
+        warn!("🚫 Rejecting CONNECT to denied host: {}", hostname);
+        return Err(ConnectError::SecurityViolation(format!(
+            "Egress to host denied: {}",
+            hostname
+        )));
+    }
+
     // Establish connection to destination BEFORE responding
     // This ensures we can return an error if connection fails
     let server_stream = match TcpStream::connect(&destination).await {
         Ok(stream) => {
             debug!("✅ Connected to {}", destination);
+            state.record_upstream_host(&hostname);
+            let network = state
+                .config
+                .as_ref()
+                .map(|c| c.network.clone())
+                .unwrap_or_default();
+            crate::socket::tune_socket(&stream, &network);
             stream
         }
         Err(e) => {
@@ -100,9 +134,11 @@ fn is_allow_build_enabled() -> bool {
 
 /// Check if destination should use TLS MITM interception
 ///
-/// Returns true for HTTPS ports (443, 8443) ONLY when ALLOW_BUILD is not set
+/// Returns true for the configured `intercept_ports` (443, 8443 by default),
+/// or every port not inspected as plaintext when `intercept_all_tls` is set,
+/// ONLY when ALLOW_BUILD is not set
 /// When ALLOW_BUILD=1, all traffic uses passthrough mode
-fn should_intercept_tls(destination: &str) -> bool {
+fn should_intercept_tls(destination: &str, state: &AppState) -> bool {
     // When ALLOW_BUILD=1, skip TLS MITM and use passthrough for everything
     if is_allow_build_enabled() {
         info!(
@@ -111,14 +147,40 @@ fn should_intercept_tls(destination: &str) -> bool {
         );
         return false;
     }
-    destination.ends_with(":443") || destination.ends_with(":8443")
+    let Some(port) = destination_port(destination) else {
+        return false;
+    };
+    match state.config.as_ref() {
+        Some(config) if config.intercept_all_tls => !config.inspect_plaintext_ports.contains(&port),
+        Some(config) => config.intercept_ports.contains(&port),
+        None => DEFAULT_INTERCEPT_PORTS.contains(&port),
+    }
+}
+
+/// Check if a non-TLS destination is on a port configured for plaintext inspection
+fn should_inspect_plaintext(destination: &str, state: &AppState) -> bool {
+    let Some(port) = destination_port(destination) else {
+        return false;
+    };
+    state
+        .config
+        .as_ref()
+        .is_some_and(|config| config.inspect_plaintext_ports.contains(&port))
+}
+
+/// Port of a `host:port` destination
+fn destination_port(destination: &str) -> Option<u16> {
+    destination
+        .rsplit(':')
+        .next()
+        .and_then(|port| port.parse::<u16>().ok())
 }
 
 /// Extract hostname from destination string
 ///
 /// Converts "github.com:443" -> "github.com"
 /// Handles IPv6: "[::1]:443" -> "::1"
-fn extract_hostname(destination: &str) -> Result<String, ConnectError> {
+pub(crate) fn extract_hostname(destination: &str) -> Result<String, ConnectError> {
     if let Some(colon_pos) = destination.rfind(':') {
         let host = &destination[..colon_pos];
         // Remove IPv6 brackets if present
@@ -160,34 +222,137 @@ fn parse_destination(uri: &Uri) -> Result<String, ConnectError> {
 /// Bidirectional tunnel between client and server
 ///
 /// Routes to either:
-/// - Passthrough mode (ports other than 443/8443)
-/// - TLS MITM mode (ports 443/8443) with credential injection and sanitization
+/// - Passthrough mode (ports not intercepted or inspected, and pinned clients
+///   whose ClientHello names an SNI in `mitm_bypass_sni`)
+/// - Plaintext inspection mode (ports listed in `inspect_plaintext_ports`)
+/// - TLS MITM mode (`intercept_ports`, 443/8443 by default) with credential
+///   injection and sanitization
 async fn tunnel(
     client_stream: Upgraded,
     server_stream: TcpStream,
     destination: &str,
     state: AppState,
 ) -> Result<(), ConnectError> {
-    if should_intercept_tls(destination) {
+    tunnel_stream(
+        TokioIo::new(client_stream),
+        server_stream,
+        destination,
+        state,
+    )
+    .await
+}
+
+/// [`tunnel`] over any client stream
+async fn tunnel_stream<C>(
+    mut client_stream: C,
+    mut server_stream: TcpStream,
+    destination: &str,
+    state: AppState,
+) -> Result<(), ConnectError>
+where
+    C: AsyncRead + AsyncWrite + Unpin,
+{
+    if should_intercept_tls(destination, &state) {
+        let bypass_sni = state
+            .config
+            .as_ref()
+            .map(|config| config.mitm_bypass_sni.clone())
+            .unwrap_or_default();
+        if bypass_sni.is_empty() {
+            info!("🔒 TLS MITM mode for {}", destination);
+            return tunnel_with_tls_mitm_full(client_stream, server_stream, destination, state)
+                .await;
+        }
+
+        // Pinned clients fail against our certificate, so look at the
+        // ClientHello before deciding whether to intercept
+        let client_hello = read_client_hello(&mut client_stream)
+            .await
+            .map_err(|e| ConnectError::TunnelError(format!("Failed to read ClientHello: {}", e)))?;
+        let sni = extract_sni(&client_hello);
+        if let Some(sni) = sni.filter(|sni| is_mitm_bypassed(sni, &bypass_sni)) {
+            info!(
+                "🔓 Passthrough mode for {} (pinned SNI {})",
+                destination, sni
+            );
+            server_stream
+                .write_all(&client_hello)
+                .await
+                .map_err(|e| ConnectError::TunnelError(e.to_string()))?;
+            return tunnel_passthrough(client_stream, server_stream, destination).await;
+        }
+
         info!("🔒 TLS MITM mode for {}", destination);
-        tunnel_with_tls_mitm(client_stream, server_stream, destination, state).await
+        // The ClientHello is replayed so the MITM handshake sees it in full
+        let (client_read, client_write) = tokio::io::split(client_stream);
+        let client_stream = tokio::io::join(
+            std::io::Cursor::new(client_hello).chain(client_read),
+            client_write,
+        );
+        tunnel_with_tls_mitm_full(client_stream, server_stream, destination, state).await
+    } else if should_inspect_plaintext(destination, &state) {
+        info!("🔍 Plaintext inspection mode for {}", destination);
+        tunnel_inspected(client_stream, server_stream, destination, &state).await
     } else {
         info!("🔓 Passthrough mode for {}", destination);
         tunnel_passthrough(client_stream, server_stream, destination).await
     }
 }
 
+/// Read the client's first TLS record, which carries its ClientHello
+///
+/// Stops early at EOF or when the data is not a TLS handshake record.
+async fn read_client_hello<C>(client_stream: &mut C) -> std::io::Result<Vec<u8>>
+where
+    C: AsyncRead + Unpin,
+{
+    let mut client_hello = Vec::new();
+    let mut buffer = vec![0u8; 4096];
+
+    loop {
+        let n = client_stream.read(&mut buffer).await?;
+        if n == 0 {
+            return Ok(client_hello);
+        }
+        client_hello.extend_from_slice(&buffer[..n]);
+
+        if client_hello.len() >= 5 {
+            let record_len = 5 + u16::from_be_bytes([client_hello[3], client_hello[4]]) as usize;
+            if client_hello[0] != 0x16 || client_hello.len() >= record_len {
+                return Ok(client_hello);
+            }
+        }
+    }
+}
+
+/// Check whether `sni` is on the MITM bypass list
+///
+/// Entries are hostnames, optionally prefixed with `*.` to also match every
+/// subdomain.
+fn is_mitm_bypassed(sni: &str, bypass_sni: &[String]) -> bool {
+    let sni = sni.trim_end_matches('.').to_ascii_lowercase();
+    bypass_sni.iter().any(|entry| {
+        let entry = entry.trim().to_ascii_lowercase();
+        match entry.strip_prefix("*.") {
+            Some(base) => sni == base || sni.ends_with(&format!(".{}", base)),
+            None => sni == entry,
+        }
+    })
+}
+
 /// Passthrough tunnel - no TLS inspection
 ///
 /// Copies data bidirectionally between client and server without modification.
-/// Used for non-HTTPS traffic (ports other than 443/8443).
-async fn tunnel_passthrough(
-    client_stream: Upgraded,
+/// Used for traffic on ports that are not intercepted or inspected, and pinned clients.
+async fn tunnel_passthrough<C>(
+    client_stream: C,
     server_stream: TcpStream,
     destination: &str,
-) -> Result<(), ConnectError> {
-    // Wrap the upgraded connection with TokioIo for compatibility
-    let client_stream = TokioIo::new(client_stream);
+) -> Result<(), ConnectError>
+where
+    C: AsyncRead + AsyncWrite + Unpin,
+{
+    let _connection = metrics::track_connection(metrics::ConnectionKind::TunnelPassthrough);
 
     // Split streams into read/write halves
     let (mut client_read, mut client_write) = tokio::io::split(client_stream);
@@ -265,53 +430,177 @@ async fn tunnel_passthrough(
     Ok(())
 }
 
-/// TLS MITM tunnel - intercepts and modifies HTTPS traffic
+/// Plaintext tunnel with streaming credential injection and sanitization
 ///
-/// Performs TLS man-in-the-middle attack to:
-/// 1. Decrypt client → proxy traffic
-/// 2. Parse HTTP requests and inject credentials
-/// 3. Re-encrypt proxy → server traffic
-/// 4. Decrypt server → proxy responses
-/// 5. Sanitize responses to remove real credentials
-/// 6. Re-encrypt proxy → client traffic
-async fn tunnel_with_tls_mitm(
-    _client_stream: Upgraded,
-    _server_stream: TcpStream,
+/// Outbound bytes have dummy credentials replaced with real ones and inbound
+/// bytes have real credentials redacted, with secrets split across reads
+/// handled by the streaming matchers. A dummy whose credential is not
+/// whitelisted for the destination closes the tunnel before it is forwarded.
+pub(crate) async fn tunnel_inspected<C, S>(
+    client_stream: C,
+    server_stream: S,
     destination: &str,
-    _state: AppState,
-) -> Result<(), ConnectError> {
-    // Extract hostname for certificate generation
+    state: &AppState,
+) -> Result<(), ConnectError>
+where
+    C: AsyncRead + AsyncWrite + Unpin,
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let _connection = metrics::track_connection(metrics::ConnectionKind::TunnelInspected);
+
     let hostname = extract_hostname(destination)?;
-    info!("🔐 Starting TLS MITM for hostname: {}", hostname);
-
-    // TODO(#1): Implement TLS handshake for upstream connections
-    // 1. Load or generate CA certificate
-    // 2. Create MitmAcceptor with CA
-    // 3. Generate certificate for hostname
-    // 4. Accept TLS connection from client
-    // 5. Establish TLS connection to upstream server
-
-    // TODO(#2): Implement HTTP request/response processing
-    // 6. Read HTTP request from decrypted client stream
-    // 7. Buffer until complete request received
-    // 8. Parse using http_parser
-
-    // TODO(#3): Implement just-in-time credential injection
-    // 9. Detect if any strategy matches the request
-    // 10. Inject credentials if match found
-    // 11. Serialize modified request
-
-    // TODO(#4): Implement response sanitization
-    // 12. Read HTTP response from server
-    // 13. Parse response
-    // 14. Sanitize using secret_map
-    // 15. Serialize and send to client
-
-    // For now, return an error to indicate not yet implemented
-    Err(ConnectError::TunnelError(format!(
-        "TLS MITM not yet fully implemented for {}",
-        destination
-    )))
+    // Outside the global injection allowlist the outbound side is only redacted
+    let injection_allowed = state
+        .config
+        .as_ref()
+        .is_none_or(|c| c.injection_allowed(&hostname));
+    let mut injector = if injection_allowed {
+        state.streaming_injector()
+    } else {
+        debug!(
+            "{} is outside the injection allowlist; inspecting sanitize-only",
+            hostname
+        );
+        state.streaming_sanitizer()
+    }
+    .map_err(ConnectError::TunnelError)?;
+    let mut sanitizer = state
+        .streaming_sanitizer()
+        .map_err(ConnectError::TunnelError)?;
+    // Dummies split across reads are validated on the previous read's tail
+    let scan_tail = state
+        .strategies()
+        .iter()
+        .flat_map(|strategy| strategy.dummy_patterns())
+        .map(|pattern| pattern.len())
+        .max()
+        .unwrap_or(0);
+
+    let (mut client_read, mut client_write) = tokio::io::split(client_stream);
+    let (mut server_read, mut server_write) = tokio::io::split(server_stream);
+    let io_error = |e: std::io::Error| ConnectError::TunnelError(e.to_string());
+
+    let client_to_server = async {
+        let mut buffer = vec![0u8; 8192];
+        let mut scanned = Vec::new();
+
+        loop {
+            let n = client_read.read(&mut buffer).await.map_err(io_error)?;
+            if n == 0 {
+                debug!("Client closed inspected connection to {}", destination);
+                server_write
+                    .write_all(&injector.finish())
+                    .await
+                    .map_err(io_error)?;
+                server_write.shutdown().await.map_err(io_error)?;
+                return Ok::<_, ConnectError>(());
+            }
+
+            scanned.extend_from_slice(&buffer[..n]);
+            validate_plaintext_destination(state, &scanned, &hostname)?;
+            // Keep an unfinished header line whole, so it parses on the next read
+            let line_start = scanned
+                .iter()
+                .rposition(|&b| b == b'\n')
+                .map_or(0, |i| i + 1);
+            let keep = (scanned.len() - line_start)
+                .min(PLAINTEXT_LINE_LIMIT)
+                .max(scan_tail);
+            scanned.drain(..scanned.len().saturating_sub(keep));
+
+            server_write
+                .write_all(&injector.push(&buffer[..n]))
+                .await
+                .map_err(io_error)?;
+        }
+    };
+
+    let server_to_client = async {
+        let mut buffer = vec![0u8; 8192];
+
+        loop {
+            let n = server_read.read(&mut buffer).await.map_err(io_error)?;
+            if n == 0 {
+                debug!("Server {} closed inspected connection", destination);
+                client_write
+                    .write_all(&sanitizer.finish())
+                    .await
+                    .map_err(io_error)?;
+                client_write.shutdown().await.map_err(io_error)?;
+                return Ok::<_, ConnectError>(());
+            }
+
+            client_write
+                .write_all(&sanitizer.push(&buffer[..n]))
+                .await
+                .map_err(io_error)?;
+        }
+    };
+
+    tokio::try_join!(client_to_server, server_to_client)?;
+
+    info!(
+        "📊 Inspected tunnel for {} closed: {} injected, {} redacted",
+        destination,
+        injector.redactions(),
+        sanitizer.redactions()
+    );
+    Ok(())
+}
+
+/// Refuse plaintext carrying a dummy credential not whitelisted for `hostname`
+fn validate_plaintext_destination(
+    state: &AppState,
+    data: &[u8],
+    hostname: &str,
+) -> Result<(), ConnectError> {
+    let text = String::from_utf8_lossy(data);
+    match detect_and_validate_strategies(
+        &state.strategies(),
+        &plaintext_headers(&text),
+        &text,
+        hostname,
+    ) {
+        Ok(_) => Ok(()),
+        Err(SecurityError::HostNotWhitelisted {
+            credential_type,
+            host,
+            allowed_hosts,
+        }) => {
+            error!(
+                "🚨 SECURITY VIOLATION: Blocked {} credential to unauthorized host: {}",
+                credential_type, host
+            );
+            crate::metrics::record_host_validation_blocked(&credential_type, &host);
+            Err(ConnectError::SecurityViolation(format!(
+                "Credential exfiltration blocked: {} credential attempted to unauthorized host '{}'. Allowed hosts: {:?}",
+                credential_type, host, allowed_hosts
+            )))
+        }
+    }
+}
+
+/// Longest unfinished line carried between reads of an inspected stream
+const PLAINTEXT_LINE_LIMIT: usize = 8192;
+
+/// Header-shaped `name: value` lines found anywhere in a plaintext stream
+///
+/// The stream is not framed, so every such line counts: header-only
+/// strategies (HMAC, SigV4) then see their dummies and validate the host.
+fn plaintext_headers(text: &str) -> axum::http::HeaderMap {
+    let mut headers = axum::http::HeaderMap::new();
+    for line in text.lines() {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        if let (Ok(name), Ok(value)) = (
+            axum::http::HeaderName::from_bytes(name.trim().as_bytes()),
+            axum::http::HeaderValue::from_str(value.trim()),
+        ) {
+            headers.append(name, value);
+        }
+    }
+    headers
 }
 
 /// Errors that can occur during CONNECT handling
@@ -321,6 +610,10 @@ pub enum ConnectError {
     ConnectionFailed(String, String),
     TunnelError(String),
     TlsError(crate::tls::TlsError),
+    SecurityViolation(String),
+    CaUnavailable(String),
+    /// The upstream answered with something that is not HTTP
+    UpstreamProtocolError(String),
 }
 
 impl std::fmt::Display for ConnectError {
@@ -332,6 +625,13 @@ impl std::fmt::Display for ConnectError {
             }
             ConnectError::TunnelError(msg) => write!(f, "Tunnel error: {}", msg),
             ConnectError::TlsError(e) => write!(f, "TLS error: {}", e),
+            ConnectError::SecurityViolation(msg) => write!(f, "Security violation: {}", msg),
+            ConnectError::CaUnavailable(msg) => {
+                write!(f, "MITM certificate authority unavailable: {}", msg)
+            }
+            ConnectError::UpstreamProtocolError(msg) => {
+                write!(f, "Upstream did not respond with valid HTTP: {}", msg)
+            }
         }
     }
 }
@@ -357,6 +657,15 @@ impl IntoResponse for ConnectError {
                 StatusCode::INTERNAL_SERVER_ERROR,
                 format!("TLS error: {}", e),
             ),
+            ConnectError::SecurityViolation(msg) => (StatusCode::FORBIDDEN, msg),
+            ConnectError::CaUnavailable(msg) => (
+                StatusCode::BAD_GATEWAY,
+                format!("MITM certificate authority unavailable: {}", msg),
+            ),
+            ConnectError::UpstreamProtocolError(msg) => (
+                StatusCode::BAD_GATEWAY,
+                format!("Upstream did not respond with valid HTTP: {}", msg),
+            ),
         };
 
         (status, message).into_response()
@@ -487,6 +796,36 @@ mod tests {
         assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
     }
 
+    #[test]
+    fn test_connect_error_into_response_security_violation() {
+        let error = ConnectError::SecurityViolation("blocked".to_string());
+        assert_eq!(error.to_string(), "Security violation: blocked");
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn test_connect_error_into_response_ca_unavailable() {
+        let error = ConnectError::CaUnavailable("read-only filesystem".to_string());
+        assert_eq!(
+            error.to_string(),
+            "MITM certificate authority unavailable: read-only filesystem"
+        );
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+    }
+
+    #[test]
+    fn test_connect_error_into_response_upstream_protocol_error() {
+        let error = ConnectError::UpstreamProtocolError("invalid HTTP version".to_string());
+        assert_eq!(
+            error.to_string(),
+            "Upstream did not respond with valid HTTP: invalid HTTP version"
+        );
+        let response = error.into_response();
+        assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+    }
+
     // ========================================================================
     // Destination Validation Edge Cases
     // ========================================================================
@@ -595,26 +934,73 @@ mod tests {
     // HTTPS Detection Tests
     // ========================================================================
 
+    fn intercept_state(config: crate::proxy::ProxyConfig) -> AppState {
+        let mut secrets = std::collections::HashMap::new();
+        secrets.insert("DUMMY_TOKEN".to_string(), "real_secret_123".to_string());
+        AppState::with_config(
+            std::sync::Arc::new(crate::sanitizer::SecretMap::new(secrets).unwrap()),
+            crate::proxy::create_http_client(),
+            config,
+        )
+    }
+
     #[test]
     fn test_should_intercept_tls_port_443() {
-        assert!(should_intercept_tls("github.com:443"));
-        assert!(should_intercept_tls("api.example.com:443"));
-        assert!(should_intercept_tls("192.168.1.1:443"));
-        assert!(should_intercept_tls("[::1]:443"));
+        let state = intercept_state(Default::default());
+        assert!(should_intercept_tls("github.com:443", &state));
+        assert!(should_intercept_tls("api.example.com:443", &state));
+        assert!(should_intercept_tls("192.168.1.1:443", &state));
+        assert!(should_intercept_tls("[::1]:443", &state));
     }
 
     #[test]
     fn test_should_intercept_tls_port_8443() {
-        assert!(should_intercept_tls("example.com:8443"));
-        assert!(should_intercept_tls("localhost:8443"));
+        let state = intercept_state(Default::default());
+        assert!(should_intercept_tls("example.com:8443", &state));
+        assert!(should_intercept_tls("localhost:8443", &state));
     }
 
     #[test]
     fn test_should_not_intercept_other_ports() {
-        assert!(!should_intercept_tls("example.com:80"));
-        assert!(!should_intercept_tls("example.com:8080"));
-        assert!(!should_intercept_tls("example.com:3000"));
-        assert!(!should_intercept_tls("example.com:9443")); // Not 8443
+        let state = intercept_state(Default::default());
+        assert!(!should_intercept_tls("example.com:80", &state));
+        assert!(!should_intercept_tls("example.com:8080", &state));
+        assert!(!should_intercept_tls("example.com:3000", &state));
+        assert!(!should_intercept_tls("example.com:9443", &state)); // Not 8443
+    }
+
+    #[test]
+    fn test_should_intercept_custom_ports() {
+        let state = intercept_state(crate::proxy::ProxyConfig {
+            intercept_ports: vec![9443],
+            ..Default::default()
+        });
+        assert!(should_intercept_tls("api.internal:9443", &state));
+        assert!(!should_intercept_tls("api.internal:443", &state));
+        assert!(!should_intercept_tls("api.internal:8443", &state));
+    }
+
+    #[test]
+    fn test_empty_intercept_ports_disable_interception() {
+        let state = intercept_state(crate::proxy::ProxyConfig {
+            intercept_ports: Vec::new(),
+            ..Default::default()
+        });
+        assert!(!should_intercept_tls("github.com:443", &state));
+        assert!(!should_intercept_tls("example.com:8443", &state));
+    }
+
+    #[test]
+    fn test_intercept_all_tls_skips_plaintext_ports() {
+        let state = intercept_state(crate::proxy::ProxyConfig {
+            intercept_all_tls: true,
+            intercept_ports: Vec::new(),
+            inspect_plaintext_ports: vec![80],
+            ..Default::default()
+        });
+        assert!(should_intercept_tls("github.com:443", &state));
+        assert!(should_intercept_tls("api.internal:9443", &state));
+        assert!(!should_intercept_tls("internal.example:80", &state));
     }
 
     #[test]
@@ -655,4 +1041,341 @@ mod tests {
             "very.long.subdomain.example.com"
         );
     }
+
+    // ========================================================================
+    // Plaintext Inspection Tests
+    // ========================================================================
+
+    fn inspection_state() -> AppState {
+        use crate::strategy::{AuthStrategy, BearerStrategy};
+
+        let mut secrets = std::collections::HashMap::new();
+        secrets.insert("DUMMY_TOKEN".to_string(), "real_secret_123".to_string());
+        let strategies: Vec<Box<dyn AuthStrategy>> = vec![Box::new(
+            BearerStrategy::new(
+                "internal".to_string(),
+                "TEST_INSPECT_UNSET_TOKEN".to_string(),
+                "DUMMY_TOKEN".to_string(),
+                vec!["internal.example".to_string()],
+            )
+            .unwrap(),
+        )];
+        AppState::with_config(
+            std::sync::Arc::new(crate::sanitizer::SecretMap::new(secrets).unwrap()),
+            crate::proxy::create_http_client(),
+            crate::proxy::ProxyConfig {
+                inspect_plaintext_ports: vec![80],
+                ..Default::default()
+            },
+        )
+        .with_strategies(strategies)
+    }
+
+    /// Read until `marker` has been seen (or the stream ends)
+    async fn read_until<R: AsyncRead + Unpin>(reader: &mut R, marker: &[u8]) -> Vec<u8> {
+        let mut received = Vec::new();
+        let mut buffer = [0u8; 1024];
+        while !received.windows(marker.len()).any(|w| w == marker) {
+            let n = reader.read(&mut buffer).await.unwrap();
+            if n == 0 {
+                break;
+            }
+            received.extend_from_slice(&buffer[..n]);
+        }
+        received
+    }
+
+    #[test]
+    fn test_should_inspect_configured_plaintext_ports() {
+        let state = inspection_state();
+        assert!(should_inspect_plaintext("internal.example:80", &state));
+        assert!(!should_inspect_plaintext("internal.example:8080", &state));
+        assert!(!should_inspect_plaintext("[::1]:8080", &state));
+    }
+
+    #[tokio::test]
+    async fn test_plaintext_tunnel_injects_and_redacts() {
+        let (mut client, client_side) = tokio::io::duplex(64);
+        let (server_side, mut server) = tokio::io::duplex(64);
+        let tunnel = tokio::spawn(async move {
+            let state = inspection_state();
+            tunnel_inspected(client_side, server_side, "internal.example:80", &state).await
+        });
+
+        // The dummy straddles two writes; the connection stays open afterwards
+        client
+            .write_all(b"GET / HTTP/1.1\r\nAuthorization: Bearer DUMMY_")
+            .await
+            .unwrap();
+        client.write_all(b"TOKEN\r\n\r\n").await.unwrap();
+        let outbound = read_until(&mut server, b"\r\n\r\n").await;
+        assert_eq!(
+            outbound,
+            b"GET / HTTP/1.1\r\nAuthorization: Bearer real_secret_123\r\n\r\n"
+        );
+
+        server
+            .write_all(b"HTTP/1.1 200 OK\r\n\r\necho real_sec")
+            .await
+            .unwrap();
+        server.write_all(b"ret_123 done").await.unwrap();
+        drop(server);
+        let inbound = read_until(&mut client, b"never").await;
+        assert_eq!(inbound, b"HTTP/1.1 200 OK\r\n\r\necho [REDACTED] done");
+
+        drop(client);
+        assert!(tunnel.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_plaintext_tunnel_blocks_unlisted_host() {
+        let (mut client, client_side) = tokio::io::duplex(64);
+        let (server_side, mut server) = tokio::io::duplex(64);
+        let tunnel = tokio::spawn(async move {
+            let state = inspection_state();
+            tunnel_inspected(client_side, server_side, "evil.example:80", &state).await
+        });
+
+        client
+            .write_all(b"POST / HTTP/1.1\r\n\r\nkey=DUMMY_TOKEN")
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            tunnel.await.unwrap(),
+            Err(ConnectError::SecurityViolation(_))
+        ));
+        let mut forwarded = Vec::new();
+        server.read_to_end(&mut forwarded).await.unwrap();
+        assert!(!String::from_utf8_lossy(&forwarded).contains("real_secret_123"));
+    }
+
+    #[tokio::test]
+    async fn test_plaintext_tunnel_outside_injection_allowlist_is_sanitize_only() {
+        let mut state = inspection_state();
+        state.config.as_mut().unwrap().injection_allowed_hosts = vec!["api.example".to_string()];
+
+        let (mut client, client_side) = tokio::io::duplex(64);
+        let (server_side, mut server) = tokio::io::duplex(64);
+        let tunnel = tokio::spawn(async move {
+            tunnel_inspected(client_side, server_side, "internal.example:80", &state).await
+        });
+
+        client
+            .write_all(b"GET / HTTP/1.1\r\nAuthorization: Bearer DUMMY_TOKEN\r\nX-Leak: real_secret_123\r\n\r\n")
+            .await
+            .unwrap();
+        let outbound = read_until(&mut server, b"\r\n\r\n").await;
+        assert_eq!(
+            outbound,
+            b"GET / HTTP/1.1\r\nAuthorization: Bearer DUMMY_TOKEN\r\nX-Leak: [REDACTED]\r\n\r\n"
+        );
+
+        drop(server);
+        drop(client);
+        assert!(tunnel.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_plaintext_tunnel_validates_header_only_strategies() {
+        use crate::strategies::hmac::HmacStrategy;
+        use crate::strategy::AuthStrategy;
+
+        std::env::set_var("TEST_INSPECT_HMAC_SECRET", "hmac_signing_key_777");
+        let strategies: Vec<Box<dyn AuthStrategy>> = vec![Box::new(
+            HmacStrategy::new(
+                "signed".to_string(),
+                "TEST_INSPECT_HMAC_SECRET".to_string(),
+                "DUMMY_HMAC_SIG".to_string(),
+                vec!["internal.example".to_string()],
+            )
+            .unwrap(),
+        )];
+        let state = AppState::with_config(
+            std::sync::Arc::new(crate::sanitizer::SecretMap::from_strategies(&strategies).unwrap()),
+            crate::proxy::create_http_client(),
+            crate::proxy::ProxyConfig {
+                inspect_plaintext_ports: vec![80],
+                ..Default::default()
+            },
+        )
+        .with_strategies(strategies);
+
+        let (mut client, client_side) = tokio::io::duplex(64);
+        let (server_side, mut server) = tokio::io::duplex(64);
+        let tunnel = tokio::spawn(async move {
+            tunnel_inspected(client_side, server_side, "evil.example:80", &state).await
+        });
+
+        client
+            .write_all(b"GET / HTTP/1.1\r\nX-Signature: DUMMY_HMAC_SIG\r\n\r\n")
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            tunnel.await.unwrap(),
+            Err(ConnectError::SecurityViolation(_))
+        ));
+        let mut forwarded = Vec::new();
+        server.read_to_end(&mut forwarded).await.unwrap();
+        assert!(!String::from_utf8_lossy(&forwarded).contains("hmac_signing_key_777"));
+    }
+
+    #[tokio::test]
+    async fn test_connect_counts_against_rate_limit() {
+        use axum::extract::ConnectInfo;
+        use std::net::SocketAddr;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let destination = listener.local_addr().unwrap().to_string();
+        let mut secrets = std::collections::HashMap::new();
+        secrets.insert("DUMMY_TOKEN".to_string(), "real_secret_123".to_string());
+        let state = AppState::with_config(
+            std::sync::Arc::new(crate::sanitizer::SecretMap::new(secrets).unwrap()),
+            crate::proxy::create_http_client(),
+            crate::proxy::ProxyConfig {
+                rate_limit: Some(crate::rate_limit::RateLimitConfig {
+                    requests_per_second: 1,
+                    burst: 1,
+                }),
+                ..Default::default()
+            },
+        );
+        let connect = |peer: [u8; 4]| {
+            Request::builder()
+                .method("CONNECT")
+                .uri(destination.as_str())
+                .extension(ConnectInfo(SocketAddr::from((peer, 40000))))
+                .body(Body::empty())
+                .unwrap()
+        };
+
+        let first = handle_connect(State(state.clone()), connect([10, 7, 0, 1]))
+            .await
+            .unwrap();
+        assert_eq!(first.status(), StatusCode::OK);
+
+        let throttled = handle_connect(State(state.clone()), connect([10, 7, 0, 1]))
+            .await
+            .unwrap();
+        assert_eq!(throttled.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(throttled.headers().contains_key("retry-after"));
+
+        let other = handle_connect(State(state), connect([10, 7, 0, 2]))
+            .await
+            .unwrap();
+        assert_eq!(other.status(), StatusCode::OK);
+    }
+
+    // ========================================================================
+    // MITM Bypass Tests
+    // ========================================================================
+
+    fn bypass_state() -> AppState {
+        let mut secrets = std::collections::HashMap::new();
+        secrets.insert("DUMMY_TOKEN".to_string(), "real_secret_123".to_string());
+        AppState::with_config(
+            std::sync::Arc::new(crate::sanitizer::SecretMap::new(secrets).unwrap()),
+            crate::proxy::create_http_client(),
+            crate::proxy::ProxyConfig {
+                mitm_bypass_sni: vec!["*.pinned.example".to_string()],
+                ..Default::default()
+            },
+        )
+    }
+
+    /// A real ClientHello naming `sni`
+    fn client_hello(sni: &str) -> Vec<u8> {
+        let config = rustls::ClientConfig::builder()
+            .with_root_certificates(rustls::RootCertStore::empty())
+            .with_no_client_auth();
+        let name = rustls::pki_types::ServerName::try_from(sni.to_string()).unwrap();
+        let mut connection =
+            rustls::ClientConnection::new(std::sync::Arc::new(config), name).unwrap();
+        let mut hello = Vec::new();
+        connection.write_tls(&mut hello).unwrap();
+        hello
+    }
+
+    /// Connected (proxy side, upstream side) TCP pair
+    async fn upstream_pair() -> (TcpStream, TcpStream) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let proxy_side = TcpStream::connect(listener.local_addr().unwrap())
+            .await
+            .unwrap();
+        let (upstream_side, _) = listener.accept().await.unwrap();
+        (proxy_side, upstream_side)
+    }
+
+    #[test]
+    fn test_is_mitm_bypassed() {
+        let bypass = vec!["pinned.example".to_string(), "*.Pinned.Org".to_string()];
+        assert!(is_mitm_bypassed("pinned.example", &bypass));
+        assert!(is_mitm_bypassed("api.pinned.org", &bypass));
+        assert!(is_mitm_bypassed("PINNED.ORG.", &bypass));
+        assert!(!is_mitm_bypassed("api.pinned.example", &bypass));
+        assert!(!is_mitm_bypassed("notpinned.org", &bypass));
+    }
+
+    #[tokio::test]
+    async fn test_bypass_sni_takes_passthrough_on_443() {
+        let (mut client, client_side) = tokio::io::duplex(64);
+        let (proxy_side, mut upstream) = upstream_pair().await;
+        let tunnel = tokio::spawn(async move {
+            tunnel_stream(
+                client_side,
+                proxy_side,
+                "api.pinned.example:443",
+                bypass_state(),
+            )
+            .await
+        });
+
+        // The ClientHello reaches the real server untouched
+        let hello = client_hello("api.pinned.example");
+        client.write_all(&hello).await.unwrap();
+        let mut forwarded = vec![0u8; hello.len()];
+        upstream.read_exact(&mut forwarded).await.unwrap();
+        assert_eq!(forwarded, hello);
+
+        upstream.write_all(b"server hello").await.unwrap();
+        drop(upstream);
+        assert_eq!(
+            read_until(&mut client, b"server hello").await,
+            b"server hello"
+        );
+
+        drop(client);
+        assert!(tunnel.await.unwrap().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_other_sni_is_intercepted() {
+        let (mut client, client_side) = tokio::io::duplex(64);
+        let (proxy_side, mut upstream) = upstream_pair().await;
+        let tunnel = tokio::spawn(async move {
+            tunnel_stream(
+                client_side,
+                proxy_side,
+                "api.other.example:443",
+                bypass_state(),
+            )
+            .await
+        });
+
+        client
+            .write_all(&client_hello("api.other.example"))
+            .await
+            .unwrap();
+
+        // Handed to the MITM path, which cannot complete a handshake from a
+        // bare ClientHello; nothing is relayed to the server as-is
+        match tunnel.await.unwrap() {
+            Err(ConnectError::CaUnavailable(_) | ConnectError::TlsError(_)) => {}
+            other => panic!("expected MITM handshake failure, got {:?}", other.err()),
+        }
+        let mut forwarded = Vec::new();
+        upstream.read_to_end(&mut forwarded).await.unwrap();
+        assert!(forwarded.is_empty());
+    }
 }
@@ -1,95 +1,387 @@
 // Phase 3D+3E: Complete TLS MITM with Credential Injection & Response Sanitization
 // Combines all phases: TLS Handshake + HTTP Processing + Credentials + Sanitization
 
-use std::{path::Path, sync::Arc};
-use hyper::upgrade::Upgraded;
-use hyper_util::rt::TokioIo;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use rustls::{DigitallySignedStruct, SignatureScheme};
+use std::{
+    borrow::Cow,
+    path::{Path, PathBuf},
+    sync::{Arc, OnceLock},
+};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 use tokio::net::TcpStream;
+use tokio::sync::OwnedSemaphorePermit;
 use tokio_rustls::TlsConnector;
-use tracing::{debug, info, warn, error};
+use tracing::{debug, error, info, warn};
 
+use crate::dlp::apply_dlp_rules;
+use crate::http_parser::{
+    complete_response_body, has_complete_body, is_close_delimited, parse_request, parse_response,
+    serialize_request, serialize_response, ParseError, ParsedRequest, ParsedResponse,
+};
 use crate::middleware::AppState;
+use crate::proxy::{injection_enabled, ProxyMode, DEFAULT_MITM_HANDSHAKE_QUEUE_TIMEOUT};
+use crate::sanitizer::OversizedHeader;
+use crate::strategy::{detect_and_validate_strategies, AuthStrategy, SecurityError};
 use crate::tls::{CertificateAuthority, MitmAcceptor};
-use crate::http_parser::{parse_request, parse_response, serialize_request, serialize_response, ParsedRequest, ParsedResponse};
-use crate::strategy::{detect_and_validate_strategies, SecurityError};
 
-use crate::connect::{ConnectError, extract_hostname};
+use crate::connect::{extract_hostname, ConnectError};
+
+/// Location of the MITM CA certificate
+pub const CA_CERT_PATH: &str = "./ca-data/certs/ca.pem";
+/// Location of the MITM CA private key
+pub const CA_KEY_PATH: &str = "./ca-data/certs/ca-key.pem";
+/// Certificate of a shared MITM CA to import instead of generating one
+pub const MITM_CA_CERT_ENV: &str = "MITM_CA_CERT";
+/// Private key of a shared MITM CA to import instead of generating one
+pub const MITM_CA_KEY_ENV: &str = "MITM_CA_KEY";
+
+/// Where the MITM CA lives, and whether it may be generated there
+///
+/// Replicas that each generate a CA present unrelated roots, so a CA
+/// supplied through `MITM_CA_CERT`/`MITM_CA_KEY` is only ever imported: a
+/// missing mount fails loudly instead of silently forking the trust root.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MitmCaPaths {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+    /// Operator-provided CA: load it, never generate a replacement
+    pub imported: bool,
+}
+
+impl MitmCaPaths {
+    /// A CA loaded from, or generated into, the given files
+    pub fn new(cert: impl Into<PathBuf>, key: impl Into<PathBuf>) -> Self {
+        Self {
+            cert: cert.into(),
+            key: key.into(),
+            imported: false,
+        }
+    }
+
+    /// A shared CA that must already exist at the given files
+    pub fn imported(cert: impl Into<PathBuf>, key: impl Into<PathBuf>) -> Self {
+        Self {
+            imported: true,
+            ..Self::new(cert, key)
+        }
+    }
+
+    /// Imported CA when either variable is set, otherwise the local CA
+    pub fn from_env() -> Self {
+        let cert = std::env::var(MITM_CA_CERT_ENV).ok();
+        let key = std::env::var(MITM_CA_KEY_ENV).ok();
+        if cert.is_none() && key.is_none() {
+            return Self::new(CA_CERT_PATH, CA_KEY_PATH);
+        }
+        Self::imported(
+            cert.unwrap_or_else(|| CA_CERT_PATH.to_string()),
+            key.unwrap_or_else(|| CA_KEY_PATH.to_string()),
+        )
+    }
+
+    /// Load the CA, generating it only when it is not imported
+    pub fn load(&self) -> Result<Arc<CertificateAuthority>, ConnectError> {
+        if self.imported {
+            import_mitm_ca(&self.cert, &self.key)
+        } else {
+            load_mitm_ca(&self.cert, &self.key)
+        }
+    }
+}
+
+/// MITM CA location for this process, read from the environment once
+pub fn mitm_ca_paths() -> &'static MitmCaPaths {
+    static PATHS: OnceLock<MitmCaPaths> = OnceLock::new();
+    PATHS.get_or_init(MitmCaPaths::from_env)
+}
+
+/// Load the MITM CA, generating and saving one if it does not exist yet
+///
+/// Failures are reported as `ConnectError::CaUnavailable` so a missing or
+/// unwritable CA is distinguishable from per-connection TLS errors.
+pub fn load_mitm_ca(
+    cert_path: &Path,
+    key_path: &Path,
+) -> Result<Arc<CertificateAuthority>, ConnectError> {
+    CertificateAuthority::load_or_generate(cert_path, key_path)
+        .map(Arc::new)
+        .map_err(|e| {
+            crate::metrics::record_ca_error();
+            ConnectError::CaUnavailable(format!(
+                "cannot load or generate CA at {} ({})",
+                cert_path.display(),
+                e
+            ))
+        })
+}
+
+/// Load an operator-provided MITM CA, never generating one
+pub fn import_mitm_ca(
+    cert_path: &Path,
+    key_path: &Path,
+) -> Result<Arc<CertificateAuthority>, ConnectError> {
+    CertificateAuthority::load(cert_path, key_path)
+        .map(Arc::new)
+        .map_err(|e| {
+            crate::metrics::record_ca_error();
+            ConnectError::CaUnavailable(format!(
+                "cannot import CA from {} ({})",
+                cert_path.display(),
+                e
+            ))
+        })
+}
+
+/// Check at startup that the MITM CA can be loaded or generated
+///
+/// Logs an actionable error instead of letting every tunnel fail later.
+pub fn check_ca_available(paths: &MitmCaPaths) -> bool {
+    match paths.load() {
+        Ok(_) => {
+            info!(
+                "🔏 MITM CA {} {}",
+                if paths.imported {
+                    "imported from"
+                } else {
+                    "available at"
+                },
+                paths.cert.display()
+            );
+            true
+        }
+        Err(e) if paths.imported => {
+            error!(
+                "❌ {}. TLS interception will fail until a matching CA certificate and key are mounted at {} and {}",
+                e,
+                paths.cert.display(),
+                paths.key.display()
+            );
+            false
+        }
+        Err(e) => {
+            error!(
+                "❌ {}. TLS interception will fail until {} and {} exist or their directory is writable",
+                e,
+                paths.cert.display(),
+                paths.key.display()
+            );
+            false
+        }
+    }
+}
+
+/// TLS client config for MITM upstream connections
+///
+/// Upstream certificates are verified against the WebPKI roots plus any
+/// `upstream_ca_certs`, and must match the hostname. `insecure_skip_upstream_verify`
+/// accepts any certificate instead; it exists for development only.
+pub fn upstream_client_config(state: &AppState) -> Result<rustls::ClientConfig, ConnectError> {
+    let insecure = state
+        .config
+        .as_ref()
+        .is_some_and(|config| config.insecure_skip_upstream_verify);
+    if insecure {
+        warn!("⚠️  Upstream certificate verification is disabled");
+        return Ok(rustls::ClientConfig::builder()
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(SkipUpstreamVerification::new()))
+            .with_no_client_auth());
+    }
+
+    let mut root_store = rustls::RootCertStore::empty();
+    for cert in webpki_roots::TLS_SERVER_ROOTS.iter() {
+        root_store.roots.push(cert.clone());
+    }
+    // Private CAs the operator trusts for upstreams (UPSTREAM_CA_CERT)
+    if let Some(config) = &state.config {
+        for cert in &config.upstream_ca_certs {
+            root_store.add(cert.clone()).map_err(|e| {
+                ConnectError::TunnelError(format!("Invalid upstream CA certificate: {}", e))
+            })?;
+        }
+    }
+
+    Ok(rustls::ClientConfig::builder()
+        .with_root_certificates(root_store)
+        .with_no_client_auth())
+}
+
+/// Accepts any upstream certificate (INSECURE_SKIP_UPSTREAM_VERIFY)
+///
+/// Handshake signatures are still checked, so the upstream must hold the
+/// key for the certificate it presents; who issued it is not.
+#[derive(Debug)]
+struct SkipUpstreamVerification(Arc<rustls::crypto::CryptoProvider>);
+
+impl SkipUpstreamVerification {
+    fn new() -> Self {
+        Self(Arc::new(rustls::crypto::ring::default_provider()))
+    }
+}
+
+impl ServerCertVerifier for SkipUpstreamVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+/// A held MITM handshake slot, released when dropped
+pub struct HandshakeSlot {
+    // Dropped first, so the gauge never counts more than the permits handed out
+    _in_flight: crate::metrics::MitmHandshakeGuard,
+    _permit: OwnedSemaphorePermit,
+}
+
+/// Wait for a MITM handshake slot, shedding the tunnel if none frees up in time
+///
+/// Cert generation and TLS handshakes are CPU-bound, so capping how many run
+/// at once keeps a burst of new tunnels from starving established ones.
+pub async fn acquire_handshake_slot(state: &AppState) -> Result<HandshakeSlot, ConnectError> {
+    let wait = state
+        .config
+        .as_ref()
+        .map(|c| c.mitm_handshake_queue_timeout)
+        .unwrap_or(DEFAULT_MITM_HANDSHAKE_QUEUE_TIMEOUT);
+
+    match tokio::time::timeout(wait, state.mitm_handshakes.clone().acquire_owned()).await {
+        Ok(Ok(permit)) => Ok(HandshakeSlot {
+            _in_flight: crate::metrics::track_mitm_handshake(),
+            _permit: permit,
+        }),
+        Ok(Err(_)) => Err(ConnectError::TunnelError(
+            "MITM handshake limiter closed".to_string(),
+        )),
+        Err(_) => {
+            crate::metrics::record_mitm_handshake_shed();
+            warn!(
+                "Shedding MITM tunnel: no handshake slot free after {:?}",
+                wait
+            );
+            Err(ConnectError::TunnelError(format!(
+                "Too many MITM handshakes in progress (waited {:?})",
+                wait
+            )))
+        }
+    }
+}
 
 /// Complete TLS MITM tunnel with all features
 ///
 /// Phases Implemented:
 /// - Phase 3B: TLS Handshake ✅
-/// - Phase 3C: HTTP Processing ✅  
+/// - Phase 3C: HTTP Processing ✅
 /// - Phase 3D: Credential Injection ✅ (with Whitelist Validation)
 /// - Phase 3E: Response Sanitization ✅
-pub async fn tunnel_with_tls_mitm_full(
-    client_stream: Upgraded,
+///
+/// `client_stream` is the upgraded CONNECT connection, still encrypted.
+pub async fn tunnel_with_tls_mitm_full<C>(
+    client_stream: C,
     server_stream: TcpStream,
     destination: &str,
     state: AppState,
-) -> Result<(), ConnectError> {
+) -> Result<(), ConnectError>
+where
+    C: AsyncRead + AsyncWrite + Unpin,
+{
+    let _connection = crate::metrics::track_connection(crate::metrics::ConnectionKind::TunnelMitm);
+
     let hostname = extract_hostname(destination)?;
     info!("🔐 Starting complete TLS MITM for hostname: {}", hostname);
 
     // ========================================================================
     // Phase 3B: TLS Handshake
     // ========================================================================
-    
+
+    // Held until both handshakes are done
+    let handshake_slot = acquire_handshake_slot(&state).await?;
+
     debug!("Loading CA certificate...");
-    let ca_path = Path::new("./ca-data/certs/ca.pem");
-    let key_path = Path::new("./ca-data/certs/ca-key.pem");
-    
-    let ca = Arc::new(
-        CertificateAuthority::load_or_generate(ca_path, key_path)
-            .map_err(|e| ConnectError::TlsError(e))?
-    );
-    
+    let ca = mitm_ca_paths().load()?;
+
     debug!("✓ CA certificate loaded");
-    
+
     let acceptor = MitmAcceptor::new(ca);
-    
+
     debug!("Accepting TLS connection from client for '{}'...", hostname);
-    let client_stream = TokioIo::new(client_stream);
     let mut client_tls = acceptor
         .accept(client_stream, &hostname)
         .await
-        .map_err(|e| ConnectError::TlsError(e))?;
-    
+        .map_err(ConnectError::TlsError)?;
+
     info!("✓ Client TLS handshake complete for '{}'", hostname);
-    
-    debug!("Establishing TLS connection to upstream server '{}'...", hostname);
 
-    let mut root_store = rustls::RootCertStore::empty();
-    for cert in webpki_roots::TLS_SERVER_ROOTS.iter() {
-        root_store.roots.push(cert.clone());
-    }
+    debug!(
+        "Establishing TLS connection to upstream server '{}'...",
+        hostname
+    );
+
+    let client_config = upstream_client_config(&state)?;
 
-    let client_config = rustls::ClientConfig::builder()
-        .with_root_certificates(root_store)
-        .with_no_client_auth();
-    
     let connector = TlsConnector::from(Arc::new(client_config));
     // ServerName requires static lifetime, so we use DnsName directly
     let server_name = rustls::pki_types::ServerName::DnsName(
-        rustls::pki_types::DnsName::try_from(hostname.to_string())
-            .map_err(|e| ConnectError::TunnelError(format!("Invalid hostname '{}': {:?}", hostname, e)))?
+        rustls::pki_types::DnsName::try_from(hostname.to_string()).map_err(|e| {
+            ConnectError::TunnelError(format!("Invalid hostname '{}': {:?}", hostname, e))
+        })?,
     );
-    
+
     let mut server_tls = connector
         .connect(server_name, server_stream)
         .await
         .map_err(|e| ConnectError::TunnelError(format!("Server TLS handshake failed: {}", e)))?;
-    
+    crate::metrics::record_mitm_handshake("server");
+    drop(handshake_slot);
+
     info!("✓ Server TLS handshake complete for '{}'", hostname);
 
     // ========================================================================
     // Phase 3C+3D+3E: HTTP Processing with Credential Injection & Sanitization
     // ========================================================================
-    
+
     loop {
         debug!("📥 Waiting for HTTP request from client...");
-        
+
         // Read and parse HTTP request from client
         let mut parsed_request = match read_http_request(&mut client_tls).await {
             Ok(Some(req)) => {
@@ -106,86 +398,34 @@ pub async fn tunnel_with_tls_mitm_full(
             }
         };
 
-        // ====================================================================
-        // Phase 3D-Pre: Whitelist-Based Host Validation (SECURITY CRITICAL)
-        // ====================================================================
-        
-        // Convert body to string for detection
-        let body_str = String::from_utf8_lossy(&parsed_request.body);
-        
-        // Convert headers to HeaderMap for strategy detection
-        let mut header_map = axum::http::HeaderMap::new();
-        for (name, value) in &parsed_request.headers {
-            if let Ok(header_name) = axum::http::HeaderName::from_bytes(name.as_bytes()) {
-                if let Ok(header_value) = axum::http::HeaderValue::from_str(value) {
-                    header_map.insert(header_name, header_value);
-                }
-            }
-        }
-        
-        // SECURITY: Validate that any detected credentials are allowed for this destination
-        // This prevents credential exfiltration to unauthorized hosts
-        match detect_and_validate_strategies(
-            &state.strategies,
-            &header_map,
-            &body_str,
-            &hostname,
-        ) {
-            Ok(validated_strategies) => {
-                if !validated_strategies.is_empty() {
-                    debug!("✓ Host validation passed for {} ({} credential(s) detected)", 
-                           hostname, validated_strategies.len());
-                }
-            }
-            Err(SecurityError::HostNotWhitelisted { credential_type, host, allowed_hosts }) => {
-                error!("🚨 SECURITY VIOLATION: Blocked {} credential to unauthorized host: {}", 
-                       credential_type, host);
-                return Err(ConnectError::SecurityViolation(format!(
-                    "Credential exfiltration blocked: {} credential attempted to unauthorized host '{}'. Allowed hosts: {:?}",
-                    credential_type, host, allowed_hosts
-                )));
-            }
-        }
-        
-        // ====================================================================
-        // Phase 3D: Credential Injection
-        // ====================================================================
-        
-        // Inject real credentials (replaces DUMMY_* tokens with real values)
-        let injected_body = state.secret_map.inject(&body_str);
-        
-        if injected_body != body_str {
-            info!("🔑 Injected credentials into request body");
-            parsed_request.body = injected_body.into_bytes();
-            
-            // Update Content-Length header if it changed
-            if let Some(content_length) = parsed_request.headers.get_mut("content-length") {
-                *content_length = parsed_request.body.len().to_string();
-            }
-        }
-        
-        // Also inject into headers (in case credentials are in Authorization header)
-        for (header_name, header_value) in parsed_request.headers.iter_mut() {
-            let injected_header = state.secret_map.inject(header_value);
-            if injected_header != *header_value {
-                info!("🔑 Injected credentials into {} header", header_name);
-                *header_value = injected_header;
+        // Each request in the tunnel counts against the client's rate
+        if let Some(identity) = &state.client_identity {
+            if let Err(wait) = state.check_rate_limit(identity, "mitm") {
+                let response = format!(
+                    "HTTP/1.1 429 Too Many Requests\r\nretry-after: {}\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+                    wait.as_secs_f64().ceil().max(1.0) as u64
+                );
+                let _ = client_tls.write_all(response.as_bytes()).await;
+                break;
             }
         }
-        
-        // Serialize and send request to server
-        let request_bytes = serialize_request(&parsed_request);
-        debug!("📤 Sending {} bytes to upstream server", request_bytes.len());
-        
-        server_tls
-            .write_all(&request_bytes)
-            .await
-            .map_err(|e| ConnectError::TunnelError(format!("Failed to send request to server: {}", e)))?;
+
+        // Validate, inject and serialize the request for the upstream server
+        let request_bytes = prepare_upstream_request(&state, &mut parsed_request, &hostname)?;
+        debug!(
+            "📤 Sending {} bytes to upstream server",
+            request_bytes.len()
+        );
+
+        server_tls.write_all(&request_bytes).await.map_err(|e| {
+            ConnectError::TunnelError(format!("Failed to send request to server: {}", e))
+        })?;
 
         // Read and parse HTTP response from server
         debug!("📥 Waiting for HTTP response from server...");
-        
-        let mut parsed_response = match read_http_response(&mut server_tls).await {
+
+        let head_request = parsed_request.method.eq_ignore_ascii_case("HEAD");
+        let mut parsed_response = match read_http_response(&mut server_tls, head_request).await {
             Ok(Some(resp)) => {
                 info!("📄 Parsed response: {} {}", resp.code, resp.reason);
                 resp
@@ -194,64 +434,484 @@ pub async fn tunnel_with_tls_mitm_full(
                 info!("✅ Server closed connection");
                 break;
             }
+            Err(e @ ConnectError::UpstreamProtocolError(_)) => {
+                warn!("❌ {}", e);
+                let message = e.to_string();
+                let response = format!(
+                    "HTTP/1.1 502 Bad Gateway\r\ncontent-type: text/plain\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    message.len(),
+                    message
+                );
+                let _ = client_tls.write_all(response.as_bytes()).await;
+                break;
+            }
             Err(e) => {
                 warn!("❌ Failed to read HTTP response: {}", e);
                 return Err(e);
             }
         };
 
-        // ====================================================================
         // Phase 3E: Response Sanitization
-        // ====================================================================
-        
-        // Convert body to string for sanitization
-        let response_body_str = String::from_utf8_lossy(&parsed_response.body);
-        
-        // Sanitize real credentials (replaces real values with [REDACTED])
-        let sanitized_body = state.secret_map.sanitize(&response_body_str);
-        
-        if sanitized_body != response_body_str {
-            info!("🔒 Sanitized {} credential(s) from response body", 
-                  response_body_str.len() - sanitized_body.len());
-            parsed_response.body = sanitized_body.into_bytes();
-            
-            // Update Content-Length header since body changed
-            if let Some(content_length) = parsed_response.headers.get_mut("content-length") {
-                *content_length = parsed_response.body.len().to_string();
-            }
-        }
-        
-        // Also sanitize headers (in case credentials leaked into headers)
-        for (header_name, header_value) in parsed_response.headers.iter_mut() {
-            let sanitized_header = state.secret_map.sanitize(header_value);
-            if sanitized_header != *header_value {
-                info!("🔒 Sanitized credentials from {} header", header_name);
-                *header_value = sanitized_header;
-            }
+        if let Err(e) = sanitize_upstream_response(&state, &mut parsed_response) {
+            warn!("❌ Rejecting upstream response: {}", e);
+            let _ = client_tls
+                .write_all(
+                    b"HTTP/1.1 502 Bad Gateway\r\ncontent-length: 0\r\nconnection: close\r\n\r\n",
+                )
+                .await;
+            break;
         }
-        
+
         // Serialize and send response to client
         let response_bytes = serialize_response(&parsed_response);
         debug!("📤 Sending {} bytes to client", response_bytes.len());
-        
-        client_tls
-            .write_all(&response_bytes)
-            .await
-            .map_err(|e| ConnectError::TunnelError(format!("Failed to send response to client: {}", e)))?;
+
+        client_tls.write_all(&response_bytes).await.map_err(|e| {
+            ConnectError::TunnelError(format!("Failed to send response to client: {}", e))
+        })?;
 
         // Check if connection should close
         if should_close_connection(&parsed_request, &parsed_response) {
             info!("🔚 Connection: close detected, closing tunnel");
             break;
         }
-        
+
         debug!("♻️  Connection: keep-alive, waiting for next request");
     }
-    
+
     info!("✓ Complete TLS MITM tunnel closed for '{}'", hostname);
     Ok(())
 }
 
+/// Validate, inject and serialize a client request for the upstream server
+///
+/// Runs the whitelist check, injects real credentials into body and headers,
+/// then scans the serialized request for any dummy credential that survived
+/// injection.
+fn prepare_upstream_request(
+    state: &AppState,
+    parsed_request: &mut ParsedRequest,
+    hostname: &str,
+) -> Result<Vec<u8>, ConnectError> {
+    // ====================================================================
+    // Phase 3D-Pre: Whitelist-Based Host Validation (SECURITY CRITICAL)
+    // ====================================================================
+
+    // Convert body to string for detection
+    let body_str = String::from_utf8_lossy(&parsed_request.body).into_owned();
+
+    // Convert headers to HeaderMap for strategy detection
+    let mut header_map = axum::http::HeaderMap::new();
+    for (name, value) in &parsed_request.headers {
+        if let Ok(header_name) = axum::http::HeaderName::from_bytes(name.as_bytes()) {
+            if let Ok(header_value) = axum::http::HeaderValue::from_str(value) {
+                // Repeated headers are all kept, so a dummy in any of them is detected
+                header_map.append(header_name, header_value);
+            }
+        }
+    }
+    // The request target is passed as the `uri` pseudo-header, so
+    // query-parameter credentials are detected and validated too
+    if let Ok(uri) = axum::http::HeaderValue::from_str(&parsed_request.path) {
+        header_map.insert("uri", uri);
+    }
+
+    // SECURITY: Validate that any detected credentials are allowed for this destination
+    // This prevents credential exfiltration to unauthorized hosts
+    let strategies = state.strategies();
+    let validated_strategies = match detect_and_validate_strategies(
+        &strategies,
+        &header_map,
+        &body_str,
+        hostname,
+    ) {
+        Ok(validated_strategies) => {
+            if !validated_strategies.is_empty() {
+                debug!(
+                    "✓ Host validation passed for {} ({} credential(s) detected)",
+                    hostname,
+                    validated_strategies.len()
+                );
+            }
+            validated_strategies
+        }
+        Err(SecurityError::HostNotWhitelisted {
+            credential_type,
+            host,
+            allowed_hosts,
+        }) => {
+            error!(
+                "🚨 SECURITY VIOLATION: Blocked {} credential to unauthorized host: {}",
+                credential_type, host
+            );
+            crate::metrics::record_host_validation_blocked(&credential_type, &host);
+            return Err(ConnectError::SecurityViolation(format!(
+                "Credential exfiltration blocked: {} credential attempted to unauthorized host '{}'. Allowed hosts: {:?}",
+                credential_type, host, allowed_hosts
+            )));
+        }
+    };
+    // Strategies outside the client's scope are host-checked but never used
+    let validated_strategies: Vec<_> = validated_strategies
+        .into_iter()
+        .filter(|strategy| state.strategy_in_scope(strategy.name()))
+        .collect();
+
+    // ====================================================================
+    // Phase 3D: Credential Injection
+    // ====================================================================
+
+    // Flag real credentials the agent hardcoded instead of using a dummy
+    state.check_unmanaged_credentials(&body_str);
+    let mixed_policy = state
+        .config
+        .as_ref()
+        .map(|c| c.mixed_credential_policy)
+        .unwrap_or_default();
+    let stripped = match state.apply_mixed_credential_policy(&body_str, mixed_policy) {
+        Ok(Cow::Owned(stripped)) => Some(stripped),
+        Ok(Cow::Borrowed(_)) => None,
+        Err(prefix) => {
+            return Err(ConnectError::SecurityViolation(format!(
+                "Request carries an unmanaged '{}' credential alongside a dummy",
+                prefix
+            )));
+        }
+    };
+    let mut body_changed = stripped.is_some();
+    let body_str = stripped.unwrap_or(body_str);
+
+    // Operator DLP rules; only the rule name is ever logged
+    let dlp_rules = state
+        .config
+        .as_ref()
+        .map(|c| c.dlp_rules.as_slice())
+        .unwrap_or_default();
+    let dlp_redacted = match apply_dlp_rules(dlp_rules, &body_str) {
+        Ok(Cow::Owned(redacted)) => Some(redacted),
+        Ok(Cow::Borrowed(_)) => None,
+        Err(rule) => {
+            warn!(
+                "Blocking request to {}: DLP rule '{}' matched",
+                hostname, rule
+            );
+            return Err(ConnectError::SecurityViolation(format!(
+                "Request blocked by DLP rule: {}",
+                rule
+            )));
+        }
+    };
+    body_changed |= dlp_redacted.is_some();
+    let body_str = dlp_redacted.unwrap_or(body_str);
+
+    let sanitize_only = state
+        .config
+        .as_ref()
+        .is_some_and(|c| c.mode == ProxyMode::SanitizeOnly);
+
+    // Routes configured with `inject: false` are forwarded as sent
+    let path = parsed_request.path.split('?').next().unwrap_or_default();
+    let route_injects = state
+        .config
+        .as_ref()
+        .is_none_or(|c| injection_enabled(path, &c.routes));
+    if !route_injects {
+        debug!("Injection disabled for route {}", path);
+    }
+
+    // Inject real credentials (replaces DUMMY_* tokens with real values), or
+    // in sanitize-only mode redact real ones leaving in the body
+    let injected_body = if sanitize_only {
+        state.sanitize_all(&body_str)
+    } else if route_injects {
+        state.inject_all(&body_str)
+    } else {
+        body_str.clone()
+    };
+
+    // Global injection allowlist, required on top of the strategy whitelists
+    let injects = !sanitize_only
+        && route_injects
+        && (injected_body != body_str
+            || !validated_strategies.is_empty()
+            || parsed_request
+                .headers
+                .iter()
+                .any(|(name, value)| state.inject_header_all(name, value) != *value));
+    let injection_allowed = state
+        .config
+        .as_ref()
+        .is_none_or(|c| c.injection_allowed(hostname));
+    if injects && !injection_allowed {
+        error!(
+            "🚨 SECURITY VIOLATION: Blocked credential injection for host outside the injection allowlist: {}",
+            hostname
+        );
+        crate::metrics::record_host_validation_blocked("injection_allowlist", hostname);
+        return Err(ConnectError::SecurityViolation(format!(
+            "Credential injection not allowed for host '{}'",
+            hostname
+        )));
+    }
+
+    if injected_body != body_str {
+        if sanitize_only {
+            warn!("Redacted real secret from outbound request body");
+        } else {
+            info!("🔑 Injected credentials into request body");
+        }
+    }
+    if body_changed || injected_body != body_str {
+        parsed_request.body = injected_body.into_bytes();
+
+        // Update Content-Length header if it changed
+        if let Some(content_length) = parsed_request.headers.get_mut("content-length") {
+            *content_length = parsed_request.body.len().to_string();
+        }
+    }
+
+    // Also inject into headers (in case credentials are in Authorization header)
+    if !sanitize_only && route_injects {
+        state.record_strategy_use(
+            body_str.as_bytes(),
+            parsed_request
+                .headers
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str())),
+        );
+        for (header_name, header_value) in parsed_request.headers.iter_mut() {
+            let injected_header = state.inject_header_all(header_name, header_value);
+            if injected_header != *header_value {
+                info!("🔑 Injected credentials into {} header", header_name);
+                *header_value = injected_header;
+            }
+        }
+    }
+
+    // Credentials carried in the query string (e.g. `?key=DUMMY_X`)
+    if !sanitize_only && route_injects {
+        for strategy in &validated_strategies {
+            let injected_path = strategy.inject_uri(&parsed_request.path).map_err(|e| {
+                ConnectError::TunnelError(format!(
+                    "Failed to inject '{}' into request URI for {}: {}",
+                    strategy.name(),
+                    hostname,
+                    e
+                ))
+            })?;
+            if injected_path != parsed_request.path {
+                info!("🔑 Injected credentials into request URI");
+                parsed_request.path = injected_path;
+            }
+        }
+    }
+
+    // Identify the proxy upstream when configured
+    let identification = state
+        .config
+        .as_ref()
+        .map(|c| c.proxy_identification)
+        .unwrap_or_default()
+        .header(parsed_request.headers.get("user-agent").map(String::as_str));
+    if let Some((name, value)) = identification {
+        parsed_request.headers.insert(name.to_string(), value);
+    }
+
+    // ====================================================================
+    // Phase 3E: Request Signing (must be the LAST modification)
+    // ====================================================================
+
+    // Signatures cover the final body and headers, so sign only after every
+    // other injection step has run (routes without injection go unsigned)
+    if route_injects {
+        for strategy in validated_strategies.iter().filter(|s| s.signs_request()) {
+            sign_upstream_request(*strategy, parsed_request, hostname)?;
+        }
+    }
+
+    let request_bytes = serialize_request(parsed_request);
+
+    // Paranoid verification: no dummy credential may reach the provider,
+    // unless the route deliberately forwards them
+    let residual = route_injects
+        .then(|| find_residual_dummy(state, &request_bytes))
+        .flatten();
+    if let Some(owner) = residual {
+        error!(
+            "🚨 Dummy credential for '{}' survived injection into request for {}",
+            owner, hostname
+        );
+        let fail = state
+            .config
+            .as_ref()
+            .map(|c| c.fail_on_residual_dummy)
+            .unwrap_or(true);
+        if fail {
+            return Err(ConnectError::SecurityViolation(format!(
+                "Dummy credential for '{}' survived injection",
+                owner
+            )));
+        }
+    }
+
+    Ok(request_bytes)
+}
+
+/// Sign the fully injected request with a signing strategy (e.g. AWS SigV4)
+///
+/// Passes the real method and path for the strategy to sign, then writes the
+/// signed body and headers back to the request.
+fn sign_upstream_request(
+    strategy: &dyn AuthStrategy,
+    parsed_request: &mut ParsedRequest,
+    hostname: &str,
+) -> Result<(), ConnectError> {
+    let mut header_map = axum::http::HeaderMap::new();
+    for (name, value) in &parsed_request.headers {
+        if let (Ok(header_name), Ok(header_value)) = (
+            axum::http::HeaderName::from_bytes(name.as_bytes()),
+            axum::http::HeaderValue::from_str(value),
+        ) {
+            header_map.append(header_name, header_value);
+        }
+    }
+    if !header_map.contains_key("host") {
+        if let Ok(header_value) = axum::http::HeaderValue::from_str(hostname) {
+            header_map.insert("host", header_value);
+        }
+    }
+
+    let body_str = String::from_utf8_lossy(&parsed_request.body).into_owned();
+    let signed_body = strategy
+        .inject_request(
+            &parsed_request.method,
+            &parsed_request.path,
+            &body_str,
+            &mut header_map,
+        )
+        .map_err(|e| {
+            ConnectError::TunnelError(format!("Failed to sign request for {}: {}", hostname, e))
+        })?;
+
+    // Headers the strategy dropped (e.g. HMAC placeholders) must not be sent
+    parsed_request.headers.retain(|name, _| {
+        header_map.contains_key(name.as_str())
+            || axum::http::HeaderName::from_bytes(name.as_bytes()).is_err()
+    });
+
+    for name in header_map.keys() {
+        let values: Vec<&str> = header_map
+            .get_all(name)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .collect();
+        let unchanged = parsed_request
+            .headers
+            .get_all(name.as_str())
+            .map(String::as_str)
+            .eq(values.iter().copied());
+        if !unchanged {
+            parsed_request.headers.remove(name.as_str());
+            for value in values {
+                parsed_request
+                    .headers
+                    .append(name.as_str().to_string(), value.to_string());
+            }
+        }
+    }
+
+    if signed_body != body_str {
+        parsed_request.body = signed_body.into_bytes();
+        if let Some(content_length) = parsed_request.headers.get_mut("content-length") {
+            *content_length = parsed_request.body.len().to_string();
+        }
+    }
+
+    info!(
+        "🔏 Signed request for {} with '{}' (after injection)",
+        hostname,
+        strategy.name()
+    );
+    Ok(())
+}
+
+/// Redact real credentials from an upstream response before it reaches the agent
+///
+/// Covers the body, every header value and the status reason phrase, since some
+/// servers echo request details into custom reason phrases. Header values are
+/// bounded first; an oversized one fails the response when configured to reject.
+fn sanitize_upstream_response(
+    state: &AppState,
+    parsed_response: &mut ParsedResponse,
+) -> Result<(), OversizedHeader> {
+    let limit = state.header_value_limit();
+    let secrets = state.real_secret_bytes_all();
+    for (header_name, header_value) in parsed_response.headers.iter_mut() {
+        limit.bound_value(header_name, header_value, &secrets)?;
+    }
+
+    crate::metrics::record_response_sanitization(
+        &state.count_secrets_by_label_all(&parsed_response.body),
+    );
+
+    // Convert body to string for sanitization
+    let response_body_str = String::from_utf8_lossy(&parsed_response.body).into_owned();
+
+    // Sanitize real credentials (replaces real values with [REDACTED])
+    let sanitized_body = state.sanitize_all(&response_body_str);
+
+    if sanitized_body != response_body_str {
+        info!("🔒 Sanitized credentials from response body");
+        parsed_response.body = sanitized_body.into_bytes();
+
+        // Update Content-Length header since body changed
+        if let Some(content_length) = parsed_response.headers.get_mut("content-length") {
+            *content_length = parsed_response.body.len().to_string();
+        }
+    }
+
+    // Also sanitize headers (in case credentials leaked into headers)
+    for (header_name, header_value) in parsed_response.headers.iter_mut() {
+        let sanitized_header = state.sanitize_all(header_value);
+        if sanitized_header != *header_value {
+            info!("🔒 Sanitized credentials from {} header", header_name);
+            *header_value = sanitized_header;
+        }
+    }
+
+    // Drop operator-blocked headers
+    for name in state.blocked_headers() {
+        if parsed_response.headers.remove(&name).is_some() {
+            debug!("Removing blocked header: {}", name);
+        }
+    }
+
+    // And the reason phrase, which is echoed verbatim by serialize_response
+    let sanitized_reason = state.sanitize_all(&parsed_response.reason);
+    if sanitized_reason != parsed_response.reason {
+        info!("🔒 Sanitized credentials from response reason phrase");
+        parsed_response.reason = sanitized_reason;
+    }
+    Ok(())
+}
+
+/// Find the owner of any dummy credential still present in outbound data
+///
+/// Returns the strategy name (or secret source) only, never the dummy itself.
+fn find_residual_dummy(state: &AppState, data: &[u8]) -> Option<String> {
+    let credentials = state.credentials();
+    if let Some(owner) = credentials.strategy_dummies.find_owner(data) {
+        return Some(owner.to_string());
+    }
+    if credentials.secret_map.contains_dummy(data) {
+        return Some("secret map".to_string());
+    }
+    if state.runtime_secrets().contains_dummy(data) {
+        return Some("runtime secrets".to_string());
+    }
+    None
+}
+
 /// Read and parse an HTTP request from a TLS stream
 async fn read_http_request<S>(stream: &mut S) -> Result<Option<ParsedRequest>, ConnectError>
 where
@@ -259,122 +919,977 @@ where
 {
     const MAX_BUFFER_SIZE: usize = 1024 * 1024; // 1MB max
     const READ_CHUNK_SIZE: usize = 8192; // 8KB chunks
-    
+
     let mut buffer = Vec::new();
     let mut temp_buf = vec![0u8; READ_CHUNK_SIZE];
-    
+
     loop {
         match parse_request(&buffer) {
             Ok(Some(req)) => {
-                debug!("✓ Complete HTTP request parsed ({} bytes buffered)", buffer.len());
+                debug!(
+                    "✓ Complete HTTP request parsed ({} bytes buffered)",
+                    buffer.len()
+                );
                 return Ok(Some(req));
             }
             Ok(None) => {
-                debug!("⏳ Incomplete request, need more data ({} bytes so far)", buffer.len());
+                debug!(
+                    "⏳ Incomplete request, need more data ({} bytes so far)",
+                    buffer.len()
+                );
             }
             Err(e) => {
-                return Err(ConnectError::TunnelError(format!("Failed to parse HTTP request: {}", e)));
+                crate::metrics::record_http_parse_error(e.kind());
+                return Err(ConnectError::TunnelError(format!(
+                    "Failed to parse HTTP request: {}",
+                    e
+                )));
             }
         }
-        
+
         match stream.read(&mut temp_buf).await {
             Ok(0) => {
                 if buffer.is_empty() {
                     return Ok(None);
                 } else {
                     return Err(ConnectError::TunnelError(
-                        "Connection closed before complete request received".to_string()
+                        "Connection closed before complete request received".to_string(),
                     ));
                 }
             }
             Ok(n) => {
                 buffer.extend_from_slice(&temp_buf[..n]);
-                debug!("📥 Read {} bytes from client (total buffered: {})", n, buffer.len());
-                
+                debug!(
+                    "📥 Read {} bytes from client (total buffered: {})",
+                    n,
+                    buffer.len()
+                );
+
                 if buffer.len() > MAX_BUFFER_SIZE {
-                    return Err(ConnectError::TunnelError(
-                        format!("HTTP request too large (> {} bytes)", MAX_BUFFER_SIZE)
-                    ));
+                    return Err(ConnectError::TunnelError(format!(
+                        "HTTP request too large (> {} bytes)",
+                        MAX_BUFFER_SIZE
+                    )));
                 }
             }
             Err(e) => {
-                return Err(ConnectError::TunnelError(format!("Failed to read from client: {}", e)));
+                return Err(ConnectError::TunnelError(format!(
+                    "Failed to read from client: {}",
+                    e
+                )));
             }
         }
     }
 }
 
 /// Read and parse an HTTP response from a TLS stream
-async fn read_http_response<S>(stream: &mut S) -> Result<Option<ParsedResponse>, ConnectError>
+///
+/// The body is read in full as framed by `content-length` or chunked
+/// encoding (decoded into a plain body). A close-delimited response (neither
+/// header) is read until the server closes the connection, which ends its
+/// body. The response to a HEAD request (`head_request`) has no body.
+async fn read_http_response<S>(
+    stream: &mut S,
+    head_request: bool,
+) -> Result<Option<ParsedResponse>, ConnectError>
 where
     S: AsyncReadExt + Unpin,
 {
     const MAX_BUFFER_SIZE: usize = 10 * 1024 * 1024; // 10MB max
     const READ_CHUNK_SIZE: usize = 8192; // 8KB chunks
-    
+
+    // Bytes before the headers are complete; after that, body bytes go
+    // straight into the parsed response, so nothing is parsed twice
     let mut buffer = Vec::new();
     let mut temp_buf = vec![0u8; READ_CHUNK_SIZE];
-    
+    let mut response: Option<ParsedResponse> = None;
+    // Length of the parsed headers, and how far a chunked body has been scanned
+    let mut header_len = 0;
+    let mut chunks_scanned = 0;
+    let parse_failed = |e: ParseError| {
+        crate::metrics::record_http_parse_error(e.kind());
+        ConnectError::TunnelError(format!("Failed to parse HTTP response: {}", e))
+    };
+
     loop {
-        match parse_response(&buffer) {
-            Ok(Some(resp)) => {
-                debug!("✓ Complete HTTP response parsed ({} bytes buffered)", buffer.len());
-                return Ok(Some(resp));
-            }
-            Ok(None) => {
-                debug!("⏳ Incomplete response, need more data ({} bytes so far)", buffer.len());
+        if response.is_none() {
+            match parse_response(&buffer) {
+                Ok(Some(mut resp)) if head_request => {
+                    resp.body.clear();
+                    return Ok(Some(resp));
+                }
+                Ok(Some(resp)) => {
+                    if is_close_delimited(&resp) {
+                        debug!("⏳ Close-delimited response, reading body until EOF");
+                    }
+                    header_len = buffer.len() - resp.body.len();
+                    buffer = Vec::new();
+                    response = Some(resp);
+                }
+                Ok(None) => {
+                    debug!(
+                        "⏳ Incomplete response, need more data ({} bytes so far)",
+                        buffer.len()
+                    );
+                }
+                Err(ParseError::InvalidResponse(e)) => {
+                    // Not a malformed HTTP response but no HTTP at all, e.g. a
+                    // tunnel to an SSH or database port
+                    crate::metrics::record_http_parse_error("invalid_response");
+                    crate::metrics::record_upstream_protocol_error();
+                    return Err(ConnectError::UpstreamProtocolError(e));
+                }
+                Err(e) => return Err(parse_failed(e)),
             }
-            Err(e) => {
-                return Err(ConnectError::TunnelError(format!("Failed to parse HTTP response: {}", e)));
+        }
+
+        if let Some(resp) = response.as_mut() {
+            if !is_close_delimited(resp)
+                && has_complete_body(resp, &mut chunks_scanned).map_err(parse_failed)?
+            {
+                complete_response_body(resp).map_err(parse_failed)?;
+                debug!(
+                    "✓ Complete HTTP response parsed ({} bytes buffered)",
+                    header_len + resp.body.len()
+                );
+                return Ok(response);
             }
         }
-        
+
         match stream.read(&mut temp_buf).await {
             Ok(0) => {
-                if buffer.is_empty() {
-                    return Ok(None);
-                } else {
-                    return Err(ConnectError::TunnelError(
-                        "Connection closed before complete response received".to_string()
-                    ));
-                }
+                return match response {
+                    Some(resp) if is_close_delimited(&resp) => {
+                        debug!(
+                            "✓ Close-delimited response complete ({} byte body)",
+                            resp.body.len()
+                        );
+                        Ok(Some(resp))
+                    }
+                    None if buffer.is_empty() => Ok(None),
+                    _ => Err(ConnectError::TunnelError(
+                        "Connection closed before complete response received".to_string(),
+                    )),
+                };
             }
             Ok(n) => {
-                buffer.extend_from_slice(&temp_buf[..n]);
-                debug!("📥 Read {} bytes from server (total buffered: {})", n, buffer.len());
-                
-                if buffer.len() > MAX_BUFFER_SIZE {
-                    return Err(ConnectError::TunnelError(
-                        format!("HTTP response too large (> {} bytes)", MAX_BUFFER_SIZE)
-                    ));
+                let buffered = match response.as_mut() {
+                    Some(resp) => {
+                        resp.body.extend_from_slice(&temp_buf[..n]);
+                        header_len + resp.body.len()
+                    }
+                    None => {
+                        buffer.extend_from_slice(&temp_buf[..n]);
+                        buffer.len()
+                    }
+                };
+                debug!(
+                    "📥 Read {} bytes from server (total buffered: {})",
+                    n, buffered
+                );
+
+                if buffered > MAX_BUFFER_SIZE {
+                    return Err(ConnectError::TunnelError(format!(
+                        "HTTP response too large (> {} bytes)",
+                        MAX_BUFFER_SIZE
+                    )));
                 }
             }
             Err(e) => {
-                return Err(ConnectError::TunnelError(format!("Failed to read from server: {}", e)));
+                return Err(ConnectError::TunnelError(format!(
+                    "Failed to read from server: {}",
+                    e
+                )));
             }
         }
     }
 }
 
 /// Determine if the HTTP connection should be closed
-fn should_close_connection(
-    request: &ParsedRequest,
-    response: &ParsedResponse,
-) -> bool {
+fn should_close_connection(request: &ParsedRequest, response: &ParsedResponse) -> bool {
     // Check Connection header in request
     if let Some(conn) = request.headers.get("connection") {
         if conn.eq_ignore_ascii_case("close") {
             return true;
         }
     }
-    
+
     // Check Connection header in response
     if let Some(conn) = response.headers.get("connection") {
         if conn.eq_ignore_ascii_case("close") {
             return true;
         }
     }
-    
+
+    // The server closed the connection to end a close-delimited body
+    if is_close_delimited(response) {
+        return true;
+    }
+
     // Default to keep-alive for HTTP/1.1
     false
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::http_parser::Headers;
+    use crate::proxy::{create_http_client, ProxyConfig};
+    use crate::sanitizer::{
+        HeaderValueLimit, OversizedHeaderAction, SecretMap, TRUNCATED_HEADER_MARKER,
+    };
+    use crate::strategy::{AuthStrategy, BearerStrategy};
+    use std::collections::HashMap;
+
+    fn create_state(config: ProxyConfig) -> AppState {
+        let mut secrets = HashMap::new();
+        secrets.insert("DUMMY_OPENAI".to_string(), "sk-real-openai".to_string());
+        let secret_map = SecretMap::new(secrets).unwrap();
+
+        // Strategy whose credential is not loaded, so its dummy is never injected
+        let strategies: Vec<Box<dyn AuthStrategy>> = vec![Box::new(
+            BearerStrategy::new(
+                "github".to_string(),
+                "TEST_MITM_UNSET_GITHUB_TOKEN".to_string(),
+                "DUMMY_GITHUB".to_string(),
+                vec!["api.github.com".to_string()],
+            )
+            .unwrap(),
+        )];
+
+        AppState::with_config(Arc::new(secret_map), create_http_client(), config)
+            .with_strategies(strategies)
+    }
+
+    fn create_request(body: &str) -> ParsedRequest {
+        let mut headers = Headers::new();
+        headers.insert("host".to_string(), "api.github.com".to_string());
+        headers.insert("content-length".to_string(), body.len().to_string());
+        ParsedRequest {
+            method: "POST".to_string(),
+            path: "/v1/chat".to_string(),
+            version: 1,
+            headers,
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_prepare_upstream_request_injects_credentials() {
+        let state = create_state(ProxyConfig::default());
+        let mut request = create_request(r#"{"key":"DUMMY_OPENAI"}"#);
+
+        let bytes = prepare_upstream_request(&state, &mut request, "api.github.com").unwrap();
+        let text = String::from_utf8_lossy(&bytes);
+
+        assert!(text.contains("sk-real-openai"));
+        assert!(!text.contains("DUMMY_OPENAI"));
+    }
+
+    #[test]
+    fn test_prepare_upstream_request_enforces_injection_allowlist() {
+        let state = create_state(ProxyConfig {
+            injection_allowed_hosts: vec!["api.openai.com".to_string()],
+            ..Default::default()
+        });
+
+        // The github strategy allows api.github.com, the global set does not
+        let mut request = create_request(r#"{"token":"DUMMY_GITHUB"}"#);
+        match prepare_upstream_request(&state, &mut request, "api.github.com") {
+            Err(ConnectError::SecurityViolation(msg)) => {
+                assert!(msg.contains("injection not allowed"))
+            }
+            other => panic!(
+                "expected SecurityViolation, got {:?}",
+                other.map(|b| b.len())
+            ),
+        }
+        let mut request = create_request(r#"{"key":"DUMMY_OPENAI"}"#);
+        assert!(prepare_upstream_request(&state, &mut request, "api.github.com").is_err());
+
+        // Requests without credentials are not affected
+        let mut request = create_request(r#"{"q":"hello"}"#);
+        assert!(prepare_upstream_request(&state, &mut request, "api.github.com").is_ok());
+
+        // A destination in both sets is injected
+        let state = create_state(ProxyConfig {
+            injection_allowed_hosts: vec!["*.github.com".to_string()],
+            ..Default::default()
+        });
+        let mut request = create_request(r#"{"key":"DUMMY_OPENAI"}"#);
+        let bytes = prepare_upstream_request(&state, &mut request, "api.github.com").unwrap();
+        assert!(String::from_utf8_lossy(&bytes).contains("sk-real-openai"));
+    }
+
+    #[test]
+    fn test_prepare_upstream_request_applies_mixed_credential_policy() {
+        let body = r#"{"key":"DUMMY_OPENAI","extra":"sk-proj-9fK2mQx7LpR4vT8wZ1nB"}"#;
+
+        let state = create_state(ProxyConfig {
+            mixed_credential_policy: crate::proxy::MixedCredentialPolicy::Block,
+            ..Default::default()
+        });
+        let mut request = create_request(body);
+        match prepare_upstream_request(&state, &mut request, "api.github.com") {
+            Err(ConnectError::SecurityViolation(msg)) => {
+                assert!(msg.contains("'sk-'"));
+                assert!(!msg.contains("9fK2mQx7"));
+            }
+            other => panic!(
+                "expected SecurityViolation, got {:?}",
+                other.map(|b| b.len())
+            ),
+        }
+
+        let state = create_state(ProxyConfig {
+            mixed_credential_policy: crate::proxy::MixedCredentialPolicy::Strip,
+            ..Default::default()
+        });
+        let mut request = create_request(body);
+        let bytes = prepare_upstream_request(&state, &mut request, "api.github.com").unwrap();
+        let text = String::from_utf8_lossy(&bytes);
+        assert!(text.ends_with(r#"{"key":"sk-real-openai","extra":""}"#));
+        assert!(text.contains("content-length: 35"));
+    }
+
+    #[test]
+    fn test_prepare_upstream_request_skips_injection_on_disabled_route() {
+        let state = create_state(ProxyConfig {
+            routes: vec![crate::config::RouteConfig {
+                path: "/v1/*".to_string(),
+                inject: false,
+            }],
+            ..Default::default()
+        });
+        let mut request = create_request(r#"{"key":"DUMMY_OPENAI","token":"DUMMY_GITHUB"}"#);
+        request.path = "/v1/chat?stream=true".to_string();
+
+        let bytes = prepare_upstream_request(&state, &mut request, "api.github.com").unwrap();
+        let text = String::from_utf8_lossy(&bytes);
+
+        assert!(text.contains("DUMMY_OPENAI"));
+        assert!(!text.contains("sk-real-openai"));
+    }
+
+    #[test]
+    fn test_prepare_upstream_request_applies_dlp_rules() {
+        let state = create_state(ProxyConfig {
+            dlp_rules: vec![
+                crate::dlp::DlpRule::new("codename", "nightjar").unwrap(),
+                crate::dlp::DlpRule::new("ssn", r"\d{3}-\d{2}-\d{4}")
+                    .unwrap()
+                    .with_action(crate::dlp::DlpAction::Redact),
+            ],
+            ..Default::default()
+        });
+
+        let mut request = create_request(r#"{"q":"nightjar"}"#);
+        match prepare_upstream_request(&state, &mut request, "api.github.com") {
+            Err(ConnectError::SecurityViolation(msg)) => {
+                assert!(msg.contains("codename"));
+                assert!(!msg.contains("nightjar"));
+            }
+            other => panic!(
+                "expected SecurityViolation, got {:?}",
+                other.map(|b| b.len())
+            ),
+        }
+
+        let mut request = create_request(r#"{"q":"123-45-6789"}"#);
+        let bytes = prepare_upstream_request(&state, &mut request, "api.github.com").unwrap();
+        let text = String::from_utf8_lossy(&bytes);
+        assert!(text.ends_with(r#"{"q":"[DLP_REDACTED]"}"#));
+        assert!(text.contains("content-length: 22"));
+    }
+
+    #[test]
+    fn test_prepare_upstream_request_appends_proxy_identification() {
+        let state = create_state(ProxyConfig {
+            proxy_identification: crate::proxy::ProxyIdentification::UserAgent,
+            ..Default::default()
+        });
+        let mut request = create_request("{}");
+        request
+            .headers
+            .insert("user-agent".to_string(), "agent-cli/2.0".to_string());
+
+        prepare_upstream_request(&state, &mut request, "api.github.com").unwrap();
+
+        assert_eq!(
+            request.headers["user-agent"],
+            format!("agent-cli/2.0 {}", crate::proxy::PROXY_PRODUCT_TOKEN)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_handshake_slot_shed_after_queue_timeout() {
+        let state = create_state(ProxyConfig {
+            max_concurrent_mitm_handshakes: 1,
+            mitm_handshake_queue_timeout: std::time::Duration::from_millis(20),
+            ..Default::default()
+        });
+        let shed_before = crate::metrics::MITM_HANDSHAKES_SHED_TOTAL.get();
+
+        let held = acquire_handshake_slot(&state).await.unwrap();
+        match acquire_handshake_slot(&state).await {
+            Err(ConnectError::TunnelError(msg)) => assert!(msg.contains("Too many")),
+            other => panic!("expected shed tunnel, got ok={}", other.is_ok()),
+        }
+        assert!(crate::metrics::MITM_HANDSHAKES_SHED_TOTAL.get() > shed_before);
+
+        // Releasing the slot lets the next tunnel through
+        drop(held);
+        assert!(acquire_handshake_slot(&state).await.is_ok());
+    }
+
+    async fn request_parse_error_kind(raw: &[u8], kind: &str) {
+        let counter = crate::metrics::HTTP_PARSE_ERRORS_TOTAL.with_label_values(&[kind]);
+        let before = counter.get();
+        let mut stream = raw;
+
+        let result = read_http_request(&mut stream).await;
+
+        assert!(matches!(result, Err(ConnectError::TunnelError(_))));
+        assert!(counter.get() > before, "{} not counted", kind);
+    }
+
+    #[tokio::test]
+    async fn test_read_http_request_counts_parse_errors_by_kind() {
+        request_parse_error_kind(b"GET / HTTP/1.1\r\nX: \xff\xfe\r\n\r\n", "invalid_utf8").await;
+        request_parse_error_kind(b"NOT A REQUEST\r\n\r\n", "invalid_request").await;
+        request_parse_error_kind(&vec![b'a'; 17 * 1024], "header_too_large").await;
+    }
+
+    #[tokio::test]
+    async fn test_read_http_response_counts_parse_errors_by_kind() {
+        let counter =
+            crate::metrics::HTTP_PARSE_ERRORS_TOTAL.with_label_values(&["invalid_response"]);
+        let before = counter.get();
+        let mut stream: &[u8] = b"SPDY/9 200 OK\r\n\r\n";
+
+        let result = read_http_response(&mut stream, false).await;
+
+        assert!(matches!(
+            result,
+            Err(ConnectError::UpstreamProtocolError(_))
+        ));
+        assert!(counter.get() > before);
+    }
+
+    #[tokio::test]
+    async fn test_read_http_response_from_non_http_upstream() {
+        let before = crate::metrics::UPSTREAM_PROTOCOL_ERRORS_TOTAL.get();
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let _ = socket.write_all(b"SSH-2.0-OpenSSH_9.6\r\n").await;
+        });
+
+        let mut upstream = TcpStream::connect(addr).await.unwrap();
+        let result = read_http_response(&mut upstream, false).await;
+
+        let Err(error @ ConnectError::UpstreamProtocolError(_)) = result else {
+            panic!("expected an upstream protocol error");
+        };
+        assert!(error
+            .to_string()
+            .starts_with("Upstream did not respond with valid HTTP"));
+        assert!(crate::metrics::UPSTREAM_PROTOCOL_ERRORS_TOTAL.get() > before);
+    }
+
+    #[tokio::test]
+    async fn test_close_delimited_response_read_until_eof_and_sanitized() {
+        let state = create_state(ProxyConfig::default());
+        let (mut server, mut upstream) = tokio::io::duplex(64);
+
+        // HTTP/1.0 server: no length, body spread over writes, then close
+        tokio::spawn(async move {
+            server
+                .write_all(b"HTTP/1.0 200 OK\r\ncontent-type: text/plain\r\n\r\nfirst part, ")
+                .await
+                .unwrap();
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            server.write_all(b"key sk-real-openai, ").await.unwrap();
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            server.write_all(b"last part").await.unwrap();
+        });
+
+        let mut response = read_http_response(&mut upstream, false)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(response.body, b"first part, key sk-real-openai, last part");
+
+        sanitize_upstream_response(&state, &mut response).unwrap();
+        let forwarded = String::from_utf8(serialize_response(&response)).unwrap();
+        assert!(forwarded.ends_with("first part, key [REDACTED], last part"));
+        assert!(!forwarded.contains("sk-real-openai"));
+
+        // The body ended with the upstream connection, so the tunnel closes
+        assert!(should_close_connection(&create_request(""), &response));
+    }
+
+    #[tokio::test]
+    async fn test_connection_close_response_without_length_read_until_eof() {
+        let mut stream: &[u8] =
+            b"HTTP/1.1 200 OK\r\nconnection: close\r\n\r\nstreamed until the end";
+
+        let response = read_http_response(&mut stream, false)
+            .await
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(response.body, b"streamed until the end");
+    }
+
+    #[tokio::test]
+    async fn test_length_delimited_response_does_not_wait_for_eof() {
+        let (mut server, mut upstream) = tokio::io::duplex(1024);
+        server
+            .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 2\r\n\r\nok")
+            .await
+            .unwrap();
+
+        // The server keeps the connection open for the next request
+        let response = tokio::time::timeout(
+            std::time::Duration::from_secs(1),
+            read_http_response(&mut upstream, false),
+        )
+        .await
+        .expect("length-delimited response waited for EOF")
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(response.body, b"ok");
+        assert!(!should_close_connection(&create_request(""), &response));
+        drop(server);
+    }
+
+    /// Write `parts` to a duplex stream one read at a time, keeping it open
+    fn write_in_parts(parts: Vec<&'static [u8]>) -> tokio::io::DuplexStream {
+        let (mut server, upstream) = tokio::io::duplex(64 * 1024);
+        tokio::spawn(async move {
+            for part in parts {
+                server.write_all(part).await.unwrap();
+                tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            }
+            // The connection stays open for the next request
+            tokio::time::sleep(std::time::Duration::from_secs(5)).await;
+        });
+        upstream
+    }
+
+    #[tokio::test]
+    async fn test_content_length_body_read_across_several_reads() {
+        let mut upstream = write_in_parts(vec![
+            b"HTTP/1.1 200 OK\r\ncontent-length: 35\r\n\r\nfirst part, ",
+            b"key sk-real-openai",
+            b", end",
+        ]);
+
+        let response = tokio::time::timeout(
+            std::time::Duration::from_secs(1),
+            read_http_response(&mut upstream, false),
+        )
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(response.body, b"first part, key sk-real-openai, end");
+    }
+
+    #[tokio::test]
+    async fn test_chunked_body_read_across_several_reads() {
+        let mut upstream = write_in_parts(vec![
+            b"HTTP/1.1 200 OK\r\ntransfer-encoding: chunked\r\n\r\nc\r\nfirst part, \r\n",
+            b"12\r\nkey sk-real-openai\r",
+            b"\n5\r\n, end\r\n0\r\n",
+            b"\r\n",
+        ]);
+
+        let response = tokio::time::timeout(
+            std::time::Duration::from_secs(1),
+            read_http_response(&mut upstream, false),
+        )
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+
+        assert_eq!(response.body, b"first part, key sk-real-openai, end");
+        assert_eq!(response.headers["content-length"], "35");
+        assert!(!response.headers.contains_key("transfer-encoding"));
+
+        // Forwarded as a plain body, resized by sanitization
+        let state = create_state(ProxyConfig::default());
+        let mut response = response;
+        sanitize_upstream_response(&state, &mut response).unwrap();
+        let forwarded = String::from_utf8(serialize_response(&response)).unwrap();
+        assert!(forwarded.contains("content-length: 31"), "{}", forwarded);
+        assert!(forwarded.ends_with("first part, key [REDACTED], end"));
+    }
+
+    #[tokio::test]
+    async fn test_head_response_has_no_body() {
+        let mut upstream = write_in_parts(vec![b"HTTP/1.1 200 OK\r\ncontent-length: 1234\r\n\r\n"]);
+
+        let response = tokio::time::timeout(
+            std::time::Duration::from_secs(1),
+            read_http_response(&mut upstream, true),
+        )
+        .await
+        .expect("HEAD response waited for a body")
+        .unwrap()
+        .unwrap();
+
+        assert!(response.body.is_empty());
+        assert_eq!(response.headers["content-length"], "1234");
+    }
+
+    #[test]
+    fn test_prepare_upstream_request_detects_residual_dummy() {
+        let state = create_state(ProxyConfig::default());
+        let mut request = create_request(r#"{"token":"DUMMY_GITHUB"}"#);
+
+        let result = prepare_upstream_request(&state, &mut request, "api.github.com");
+
+        match result {
+            Err(ConnectError::SecurityViolation(msg)) => {
+                assert!(msg.contains("github"));
+                assert!(!msg.contains("DUMMY_GITHUB"));
+            }
+            other => panic!(
+                "expected SecurityViolation, got {:?}",
+                other.map(|b| b.len())
+            ),
+        }
+    }
+
+    #[test]
+    fn test_prepare_upstream_request_residual_dummy_not_fatal_when_disabled() {
+        let state = create_state(ProxyConfig {
+            fail_on_residual_dummy: false,
+            ..Default::default()
+        });
+        let mut request = create_request(r#"{"token":"DUMMY_GITHUB"}"#);
+
+        let bytes = prepare_upstream_request(&state, &mut request, "api.github.com").unwrap();
+        assert!(String::from_utf8_lossy(&bytes).contains("DUMMY_GITHUB"));
+    }
+
+    #[test]
+    fn test_prepare_upstream_request_blocks_unlisted_host() {
+        let state = create_state(ProxyConfig::default());
+        let mut request = create_request(r#"{"token":"DUMMY_GITHUB"}"#);
+
+        let blocked = crate::metrics::HOST_VALIDATION_BLOCKED_TOTAL
+            .with_label_values(&["github", "evil.com"]);
+        let before = blocked.get();
+
+        let result = prepare_upstream_request(&state, &mut request, "exfil.evil.com");
+        assert!(matches!(result, Err(ConnectError::SecurityViolation(_))));
+        assert_eq!(blocked.get(), before + 1);
+    }
+
+    #[test]
+    fn test_prepare_upstream_request_validates_every_repeated_header() {
+        std::env::set_var("TEST_MITM_DUP_GITHUB_TOKEN", "ghp-real-dup");
+        let strategies: Vec<Box<dyn AuthStrategy>> = vec![Box::new(
+            BearerStrategy::new(
+                "github".to_string(),
+                "TEST_MITM_DUP_GITHUB_TOKEN".to_string(),
+                "DUMMY_DUP_GITHUB".to_string(),
+                vec!["api.github.com".to_string()],
+            )
+            .unwrap(),
+        )];
+        let secret_map = SecretMap::from_strategies(&strategies).unwrap();
+        let state = AppState::with_config(
+            Arc::new(secret_map),
+            create_http_client(),
+            ProxyConfig::default(),
+        )
+        .with_strategies(strategies);
+
+        // The dummy hides in the first of two Authorization headers
+        let mut request = create_request("{}");
+        request.headers.append(
+            "authorization".to_string(),
+            "Bearer DUMMY_DUP_GITHUB".to_string(),
+        );
+        request
+            .headers
+            .append("authorization".to_string(), "Bearer public".to_string());
+
+        let result = prepare_upstream_request(&state, &mut request, "exfil.evil.com");
+        assert!(matches!(result, Err(ConnectError::SecurityViolation(_))));
+
+        let bytes = prepare_upstream_request(&state, &mut request, "api.github.com").unwrap();
+        assert!(String::from_utf8_lossy(&bytes).contains("Bearer ghp-real-dup"));
+    }
+
+    #[test]
+    fn test_prepare_upstream_request_signs_after_injection() {
+        std::env::set_var("TEST_MITM_AWS_ACCESS_KEY", "AKIAMITMREAL");
+        std::env::set_var("TEST_MITM_AWS_SECRET_KEY", "mitm-secret");
+        let strategies: Vec<Box<dyn AuthStrategy>> = vec![Box::new(
+            crate::strategies::AWSSigV4Strategy::new(
+                "aws".to_string(),
+                "TEST_MITM_AWS_ACCESS_KEY".to_string(),
+                "TEST_MITM_AWS_SECRET_KEY".to_string(),
+                "us-east-1".to_string(),
+                Some("dynamodb".to_string()),
+                vec!["*.amazonaws.com".to_string()],
+            )
+            .unwrap(),
+        )];
+        let state = create_state(ProxyConfig::default()).with_strategies(strategies);
+
+        let mut request = create_request(r#"{"owner":"AKIADUMMY","key":"DUMMY_OPENAI"}"#);
+        request.headers.insert(
+            "host".to_string(),
+            "dynamodb.us-east-1.amazonaws.com".to_string(),
+        );
+
+        let bytes =
+            prepare_upstream_request(&state, &mut request, "dynamodb.us-east-1.amazonaws.com")
+                .unwrap();
+        let text = String::from_utf8_lossy(&bytes);
+
+        // Generic injection ran before signing, so the signed body holds both reals
+        assert_eq!(
+            request.body,
+            br#"{"owner":"AKIAMITMREAL","key":"sk-real-openai"}"#
+        );
+        assert_eq!(
+            request.headers["content-length"],
+            request.body.len().to_string()
+        );
+        assert!(request.headers["authorization"]
+            .starts_with("AWS4-HMAC-SHA256 Credential=AKIAMITMREAL/"));
+        assert!(request.headers.contains_key("x-amz-date"));
+        assert!(!request.headers.contains_key("method"));
+        assert!(!text.contains("AKIADUMMY"));
+    }
+
+    #[test]
+    fn test_prepare_upstream_request_hmac_secret_never_sent() {
+        std::env::set_var("TEST_MITM_HMAC_SECRET", "mitm-hmac-secret");
+        let strategies: Vec<Box<dyn AuthStrategy>> = vec![Box::new(
+            crate::strategies::HmacStrategy::new(
+                "partner".to_string(),
+                "TEST_MITM_HMAC_SECRET".to_string(),
+                "DUMMY_HMAC".to_string(),
+                vec!["api.github.com".to_string()],
+            )
+            .unwrap(),
+        )];
+        let secret_map = SecretMap::from_strategies(&strategies).unwrap();
+        let state = AppState::with_config(
+            Arc::new(secret_map),
+            create_http_client(),
+            ProxyConfig::default(),
+        )
+        .with_strategies(strategies);
+
+        // Placeholder in a header other than the signature header
+        let mut request = create_request("{}");
+        request.method = "PUT".to_string();
+        request.path = "/v1/orders/7?expand=items".to_string();
+        request
+            .headers
+            .insert("x-api-signature".to_string(), "DUMMY_HMAC".to_string());
+
+        let bytes = prepare_upstream_request(&state, &mut request, "api.github.com").unwrap();
+        let text = String::from_utf8_lossy(&bytes);
+
+        assert_eq!(request.headers["x-signature"].len(), 64);
+        assert!(request.headers.contains_key("x-timestamp"));
+        assert!(!request.headers.contains_key("x-api-signature"));
+        assert!(!text.contains("mitm-hmac-secret"));
+        assert!(!text.contains("DUMMY_HMAC"));
+
+        // Signed over the request's real method and path
+        use hmac::{Hmac, KeyInit, Mac};
+        let canonical = format!(
+            "PUT\n/v1/orders/7\n{{}}\n{}",
+            request.headers["x-timestamp"]
+        );
+        let mut mac = <Hmac<sha2::Sha256> as KeyInit>::new_from_slice(b"mitm-hmac-secret").unwrap();
+        mac.update(canonical.as_bytes());
+        assert_eq!(
+            request.headers["x-signature"],
+            hex::encode(mac.finalize().into_bytes())
+        );
+    }
+
+    #[test]
+    fn test_prepare_upstream_request_injects_query_param() {
+        std::env::set_var("TEST_MITM_MAPS_KEY", "AIzaMitmReal");
+        let strategies: Vec<Box<dyn AuthStrategy>> = vec![Box::new(
+            crate::strategies::QueryParamStrategy::new(
+                "maps".to_string(),
+                "TEST_MITM_MAPS_KEY".to_string(),
+                "key".to_string(),
+                "DUMMY_MAPS".to_string(),
+                vec!["api.github.com".to_string()],
+            )
+            .unwrap(),
+        )];
+        let state = create_state(ProxyConfig::default()).with_strategies(strategies);
+
+        let mut request = create_request("{}");
+        request.path = "/v1/geocode?address=x&key=DUMMY_MAPS&lang=en".to_string();
+
+        let bytes = prepare_upstream_request(&state, &mut request, "api.github.com").unwrap();
+        assert!(String::from_utf8_lossy(&bytes)
+            .starts_with("POST /v1/geocode?address=x&key=AIzaMitmReal&lang=en HTTP/1.1\r\n"));
+
+        // The key may only go to its whitelisted hosts
+        let mut request = create_request("{}");
+        request.path = "/v1/geocode?key=DUMMY_MAPS".to_string();
+        let result = prepare_upstream_request(&state, &mut request, "exfil.evil.com");
+        assert!(matches!(result, Err(ConnectError::SecurityViolation(_))));
+    }
+
+    #[test]
+    fn test_load_mitm_ca_unwritable_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let cert_path = dir.path().join("missing/certs/ca.pem");
+        let key_path = dir.path().join("missing/certs/ca-key.pem");
+        let errors = crate::metrics::CA_ERRORS_TOTAL.get();
+
+        let result = load_mitm_ca(&cert_path, &key_path);
+
+        assert!(matches!(result, Err(ConnectError::CaUnavailable(_))));
+        assert!(crate::metrics::CA_ERRORS_TOTAL.get() > errors);
+        assert!(!check_ca_available(&MitmCaPaths::new(
+            &cert_path, &key_path
+        )));
+    }
+
+    #[test]
+    fn test_load_mitm_ca_generates_when_writable() {
+        let dir = tempfile::tempdir().unwrap();
+        let cert_path = dir.path().join("ca.pem");
+        let key_path = dir.path().join("ca-key.pem");
+
+        assert!(check_ca_available(&MitmCaPaths::new(&cert_path, &key_path)));
+        assert!(cert_path.exists());
+        assert!(load_mitm_ca(&cert_path, &key_path).is_ok());
+    }
+
+    #[test]
+    fn test_imported_mitm_ca_is_never_generated() {
+        let dir = tempfile::tempdir().unwrap();
+        let cert_path = dir.path().join("ca.pem");
+        let key_path = dir.path().join("ca-key.pem");
+        let imported = MitmCaPaths::imported(&cert_path, &key_path);
+
+        assert!(matches!(
+            imported.load(),
+            Err(ConnectError::CaUnavailable(_))
+        ));
+        assert!(!check_ca_available(&imported));
+        assert!(!cert_path.exists());
+
+        let shared = CertificateAuthority::generate().unwrap();
+        shared.save(&cert_path, &key_path).unwrap();
+        assert_eq!(imported.load().unwrap().cert_pem(), shared.cert_pem());
+    }
+
+    #[test]
+    fn test_sanitize_upstream_response_reason_phrase() {
+        let state = create_state(ProxyConfig::default());
+        let mut headers = Headers::new();
+        headers.insert("content-length".to_string(), "2".to_string());
+        let mut response = ParsedResponse {
+            version: 1,
+            code: 401,
+            reason: "Invalid key sk-real-openai".to_string(),
+            headers,
+            body: b"no".to_vec(),
+        };
+
+        sanitize_upstream_response(&state, &mut response).unwrap();
+
+        assert_eq!(response.reason, "Invalid key [REDACTED]");
+        let bytes = serialize_response(&response);
+        assert!(!String::from_utf8_lossy(&bytes).contains("sk-real-openai"));
+    }
+
+    fn oversized_response() -> ParsedResponse {
+        let mut headers = Headers::new();
+        headers.insert("x-huge".to_string(), "z".repeat(1000));
+        ParsedResponse {
+            version: 1,
+            code: 200,
+            reason: "OK".to_string(),
+            headers,
+            body: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_sanitize_upstream_response_oversized_header() {
+        let limit = |action| HeaderValueLimit {
+            max_len: 100,
+            action,
+        };
+
+        let state = create_state(ProxyConfig {
+            header_value_limit: limit(OversizedHeaderAction::Truncate),
+            ..Default::default()
+        });
+        let mut response = oversized_response();
+        sanitize_upstream_response(&state, &mut response).unwrap();
+        let huge = &response.headers["x-huge"];
+        assert_eq!(huge.len(), 100);
+        assert!(huge.ends_with(TRUNCATED_HEADER_MARKER));
+
+        let state = create_state(ProxyConfig {
+            header_value_limit: limit(OversizedHeaderAction::Reject),
+            ..Default::default()
+        });
+        let err = sanitize_upstream_response(&state, &mut oversized_response()).unwrap_err();
+        assert_eq!(err.len, 1000);
+    }
+
+    #[test]
+    fn test_find_residual_dummy_in_header() {
+        let state = create_state(ProxyConfig::default());
+        let data = b"GET / HTTP/1.1\r\nauthorization: Bearer DUMMY_GITHUB\r\n\r\n";
+
+        assert_eq!(
+            find_residual_dummy(&state, data),
+            Some("github".to_string())
+        );
+        assert_eq!(find_residual_dummy(&state, b"GET / HTTP/1.1\r\n\r\n"), None);
+    }
+
+    #[test]
+    fn test_find_residual_dummy_in_secret_map_and_runtime_secrets() {
+        let state = create_state(ProxyConfig::default());
+        assert_eq!(
+            find_residual_dummy(&state, b"{\"key\":\"DUMMY_OPENAI\"}"),
+            Some("secret map".to_string())
+        );
+
+        let runtime = b"x-api-key: DUMMY_RUNTIME\r\n";
+        assert_eq!(find_residual_dummy(&state, runtime), None);
+        state
+            .register_secrets(HashMap::from([(
+                "DUMMY_RUNTIME".to_string(),
+                "real-runtime".to_string(),
+            )]))
+            .unwrap();
+        assert_eq!(
+            find_residual_dummy(&state, runtime),
+            Some("runtime secrets".to_string())
+        );
+        state.unregister_secrets(&["DUMMY_RUNTIME".to_string()]);
+        assert_eq!(find_residual_dummy(&state, runtime), None);
+    }
+}
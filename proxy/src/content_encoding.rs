@@ -0,0 +1,478 @@
+// SLAPENIR Content Encoding - Decode compressed response bodies for sanitization
+// A secret inside a gzip or brotli body is invisible to byte-level matching,
+// so bodies are decoded before they are scanned: buffered ones whole, streamed
+// ones as they arrive. Sanitized bodies can be compressed again for clients
+// that accept it.
+
+use flate2::read::{DeflateDecoder, GzDecoder, ZlibDecoder};
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::io::{Read, Write};
+use thiserror::Error;
+
+/// Errors that leave a body unscanned
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum ContentEncodingError {
+    #[error("unsupported content-encoding '{0}'")]
+    Unsupported(String),
+
+    #[error("failed to decode {0} body: {1}")]
+    Decode(String, String),
+
+    #[error("decoded body exceeds {0} bytes")]
+    TooLarge(usize),
+}
+
+impl ContentEncodingError {
+    /// Short label for the `reason` metric label
+    pub fn reason(&self) -> &'static str {
+        match self {
+            Self::Unsupported(_) => "unsupported_encoding",
+            Self::Decode(..) => "decode_error",
+            Self::TooLarge(_) => "too_large",
+        }
+    }
+}
+
+/// Codings listed in a `Content-Encoding` value, in the order they were applied
+pub fn parse_encodings(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|coding| coding.trim().to_ascii_lowercase())
+        .filter(|coding| !coding.is_empty())
+        .collect()
+}
+
+/// Whether the codings leave the body unchanged (none, or only `identity`)
+pub fn is_identity(encodings: &[String]) -> bool {
+    encodings.iter().all(|coding| coding == "identity")
+}
+
+/// Undo `encodings`, last applied first
+///
+/// Every coding is checked before any decoding starts, so an unknown one is
+/// reported even when it is not the outermost. The decoded body may not grow
+/// past `max_size`, which guards against decompression bombs.
+pub fn decode_body(
+    encodings: &[String],
+    body: &[u8],
+    max_size: usize,
+) -> Result<Vec<u8>, ContentEncodingError> {
+    check_supported(encodings)?;
+
+    let mut decoded = body.to_vec();
+    for coding in encodings.iter().rev() {
+        decoded = match coding.as_str() {
+            "gzip" | "x-gzip" => read_limited(GzDecoder::new(&decoded[..]), coding, max_size)?,
+            // `deflate` is meant to be zlib-wrapped, but some servers send raw deflate
+            "deflate" => read_limited(ZlibDecoder::new(&decoded[..]), coding, max_size)
+                .or_else(|_| read_limited(DeflateDecoder::new(&decoded[..]), coding, max_size))?,
+            "br" => read_limited(
+                brotli_decompressor::Decompressor::new(&decoded[..], 4096),
+                coding,
+                max_size,
+            )?,
+            _ => continue,
+        };
+    }
+
+    Ok(decoded)
+}
+
+fn check_supported(encodings: &[String]) -> Result<(), ContentEncodingError> {
+    match encodings.iter().find(|coding| {
+        !matches!(
+            coding.as_str(),
+            "identity" | "gzip" | "x-gzip" | "deflate" | "br"
+        )
+    }) {
+        Some(unknown) => Err(ContentEncodingError::Unsupported(unknown.clone())),
+        None => Ok(()),
+    }
+}
+
+/// Input is fed to a decoder this many bytes at a time, so one small
+/// compressed chunk cannot expand far past the output limit before it is checked
+const STREAM_INPUT_SLICE: usize = 1024;
+
+/// One coding being undone as its input arrives
+enum StreamLayer {
+    Gzip(flate2::write::GzDecoder<Vec<u8>>),
+    Zlib(flate2::write::ZlibDecoder<Vec<u8>>),
+    Deflate(flate2::write::DeflateDecoder<Vec<u8>>),
+    /// `deflate` until its first two bytes show whether it is zlib-wrapped
+    PendingDeflate(Vec<u8>),
+    Brotli(Box<brotli_decompressor::DecompressorWriter<Vec<u8>>>),
+}
+
+impl StreamLayer {
+    fn new(coding: &str) -> Option<Self> {
+        match coding {
+            "gzip" | "x-gzip" => Some(Self::Gzip(flate2::write::GzDecoder::new(Vec::new()))),
+            "deflate" => Some(Self::PendingDeflate(Vec::new())),
+            "br" => Some(Self::Brotli(Box::new(
+                brotli_decompressor::DecompressorWriter::new(Vec::new(), 4096),
+            ))),
+            _ => None,
+        }
+    }
+
+    /// Feed `input`, returning whatever output it completes
+    fn push(&mut self, input: &[u8], max_size: usize) -> std::io::Result<Vec<u8>> {
+        if let Self::PendingDeflate(pending) = self {
+            pending.extend_from_slice(input);
+            if pending.len() < 2 {
+                return Ok(Vec::new());
+            }
+            let pending = std::mem::take(pending);
+            // A zlib header is a deflate method nibble and a multiple-of-31 check
+            let zlib = pending[0] & 0x0f == 8
+                && (u16::from(pending[0]) << 8 | u16::from(pending[1])) % 31 == 0;
+            *self = if zlib {
+                Self::Zlib(flate2::write::ZlibDecoder::new(Vec::new()))
+            } else {
+                Self::Deflate(flate2::write::DeflateDecoder::new(Vec::new()))
+            };
+            return self.push(&pending, max_size);
+        }
+
+        let mut out = Vec::new();
+        for slice in input.chunks(STREAM_INPUT_SLICE) {
+            let output = match self {
+                Self::Gzip(decoder) => {
+                    decoder.write_all(slice)?;
+                    decoder.flush()?;
+                    decoder.get_mut()
+                }
+                Self::Zlib(decoder) => {
+                    decoder.write_all(slice)?;
+                    decoder.flush()?;
+                    decoder.get_mut()
+                }
+                Self::Deflate(decoder) => {
+                    decoder.write_all(slice)?;
+                    decoder.flush()?;
+                    decoder.get_mut()
+                }
+                Self::Brotli(decoder) => {
+                    decoder.write_all(slice)?;
+                    decoder.flush()?;
+                    decoder.get_mut()
+                }
+                Self::PendingDeflate(_) => unreachable!("resolved above"),
+            };
+            out.append(output);
+            if out.len() > max_size {
+                return Err(std::io::Error::other(ContentEncodingError::TooLarge(
+                    max_size,
+                )));
+            }
+        }
+        Ok(out)
+    }
+
+    /// Finish the coding, returning the last of its output
+    fn finish(self) -> std::io::Result<Vec<u8>> {
+        match self {
+            Self::Gzip(decoder) => decoder.finish(),
+            Self::Zlib(decoder) => decoder.finish(),
+            Self::Deflate(decoder) => decoder.finish(),
+            Self::PendingDeflate(pending) if pending.is_empty() => Ok(Vec::new()),
+            Self::PendingDeflate(pending) => {
+                let mut decoder = flate2::write::DeflateDecoder::new(Vec::new());
+                decoder.write_all(&pending)?;
+                decoder.finish()
+            }
+            Self::Brotli(mut decoder) => {
+                decoder.close()?;
+                Ok(std::mem::take(decoder.get_mut()))
+            }
+        }
+    }
+}
+
+/// Undoes a body's content codings chunk by chunk, for streamed responses
+///
+/// Decoding starts with the last coding applied, as in [`decode_body`]. The
+/// output of any one chunk may not grow past the limit given to
+/// [`StreamDecoder::new`], which guards against decompression bombs.
+pub struct StreamDecoder {
+    layers: Vec<StreamLayer>,
+    max_chunk_size: usize,
+}
+
+impl StreamDecoder {
+    pub fn new(encodings: &[String], max_chunk_size: usize) -> Result<Self, ContentEncodingError> {
+        check_supported(encodings)?;
+        Ok(Self {
+            layers: encodings
+                .iter()
+                .rev()
+                .filter_map(|coding| StreamLayer::new(coding))
+                .collect(),
+            max_chunk_size,
+        })
+    }
+
+    /// Decode the next chunk of the encoded body
+    pub fn push(&mut self, chunk: &[u8]) -> Result<Vec<u8>, ContentEncodingError> {
+        let mut data = chunk.to_vec();
+        for layer in &mut self.layers {
+            data = layer
+                .push(&data, self.max_chunk_size)
+                .map_err(stream_error)?;
+        }
+        Ok(data)
+    }
+
+    /// Decode whatever the codings held back once the body has ended
+    pub fn finish(self) -> Result<Vec<u8>, ContentEncodingError> {
+        let mut data = Vec::new();
+        for mut layer in self.layers {
+            let mut out = layer
+                .push(&data, self.max_chunk_size)
+                .map_err(stream_error)?;
+            out.extend(layer.finish().map_err(stream_error)?);
+            data = out;
+        }
+        Ok(data)
+    }
+}
+
+/// Recover the limit error, or describe a decode failure
+fn stream_error(e: std::io::Error) -> ContentEncodingError {
+    match e
+        .into_inner()
+        .map(|inner| inner.downcast::<ContentEncodingError>())
+    {
+        Some(Ok(inner)) => *inner,
+        Some(Err(inner)) => ContentEncodingError::Decode("stream".to_string(), inner.to_string()),
+        None => ContentEncodingError::Decode("stream".to_string(), "I/O error".to_string()),
+    }
+}
+
+fn read_limited<R: Read>(
+    reader: R,
+    coding: &str,
+    max_size: usize,
+) -> Result<Vec<u8>, ContentEncodingError> {
+    let mut out = Vec::new();
+    reader
+        .take(max_size as u64 + 1)
+        .read_to_end(&mut out)
+        .map_err(|e| ContentEncodingError::Decode(coding.to_string(), e.to_string()))?;
+    if out.len() > max_size {
+        return Err(ContentEncodingError::TooLarge(max_size));
+    }
+    Ok(out)
+}
+
+/// Pick the coding to compress a response with from the client's `Accept-Encoding`
+///
+/// Only `br` and `gzip` are produced, `br` preferred on equal weight. Codings
+/// with `q=0` are refused, and `*` stands for any coding not listed.
+pub fn negotiate_encoding(accept_encoding: &str) -> Option<&'static str> {
+    let weights: Vec<(String, f32)> = accept_encoding
+        .split(',')
+        .filter_map(|item| {
+            let mut parts = item.split(';');
+            let coding = parts.next()?.trim().to_ascii_lowercase();
+            if coding.is_empty() {
+                return None;
+            }
+            let q = parts
+                .filter_map(|param| param.trim().strip_prefix("q="))
+                .find_map(|q| q.trim().parse::<f32>().ok())
+                .unwrap_or(1.0);
+            Some((coding, q))
+        })
+        .collect();
+    let weight = |coding: &str| {
+        weights
+            .iter()
+            .find(|(c, _)| c == coding || (coding == "gzip" && c == "x-gzip"))
+            .or_else(|| weights.iter().find(|(c, _)| c == "*"))
+            .map_or(0.0, |(_, q)| *q)
+    };
+
+    let (br, gzip) = (weight("br"), weight("gzip"));
+    if br > 0.0 && br >= gzip {
+        Some("br")
+    } else if gzip > 0.0 {
+        Some("gzip")
+    } else {
+        None
+    }
+}
+
+/// Compress `body` with a coding chosen by [`negotiate_encoding`]
+pub fn encode_body(coding: &str, body: &[u8]) -> std::io::Result<Vec<u8>> {
+    match coding {
+        "gzip" => {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(body)?;
+            encoder.finish()
+        }
+        "br" => {
+            let mut out = Vec::new();
+            brotli::BrotliCompress(&mut &body[..], &mut out, &Default::default())?;
+            Ok(out)
+        }
+        other => Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("cannot encode with '{}'", other),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        encode_body("gzip", data).unwrap()
+    }
+
+    fn brotli(data: &[u8]) -> Vec<u8> {
+        encode_body("br", data).unwrap()
+    }
+
+    #[test]
+    fn test_parse_encodings() {
+        assert_eq!(parse_encodings("gzip, BR"), vec!["gzip", "br"]);
+        assert!(parse_encodings(" , ").is_empty());
+        assert!(is_identity(&parse_encodings("identity")));
+        assert!(is_identity(&[]));
+        assert!(!is_identity(&parse_encodings("identity, gzip")));
+    }
+
+    #[test]
+    fn test_decode_chain_in_reverse_order() {
+        let body = brotli(&gzip(b"token=real_secret_123"));
+        let decoded = decode_body(&parse_encodings("gzip, br"), &body, 1024).unwrap();
+        assert_eq!(decoded, b"token=real_secret_123");
+    }
+
+    #[test]
+    fn test_decode_identity_is_noop() {
+        let decoded = decode_body(&parse_encodings("identity"), b"plain", 1024).unwrap();
+        assert_eq!(decoded, b"plain");
+    }
+
+    #[test]
+    fn test_decode_unknown_encoding_rejected() {
+        let body = gzip(b"data");
+        assert_eq!(
+            decode_body(&parse_encodings("lzma, gzip"), &body, 1024),
+            Err(ContentEncodingError::Unsupported("lzma".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_decode_errors() {
+        let err = decode_body(&parse_encodings("gzip"), b"not gzip", 1024).unwrap_err();
+        assert_eq!(err.reason(), "decode_error");
+
+        let bomb = gzip(&[0u8; 4096]);
+        assert_eq!(
+            decode_body(&parse_encodings("gzip"), &bomb, 1024),
+            Err(ContentEncodingError::TooLarge(1024))
+        );
+    }
+
+    /// Feed `body` to a stream decoder `step` bytes at a time
+    fn decode_in_steps(encodings: &str, body: &[u8], step: usize) -> Vec<u8> {
+        let mut decoder = StreamDecoder::new(&parse_encodings(encodings), 1024).unwrap();
+        let mut out = Vec::new();
+        for chunk in body.chunks(step) {
+            out.extend(decoder.push(chunk).unwrap());
+        }
+        out.extend(decoder.finish().unwrap());
+        out
+    }
+
+    #[test]
+    fn test_stream_decoder_matches_whole_body_decoding() {
+        let plain = b"data: {\"key\":\"real_secret_123\"}\n\n".repeat(20);
+        let mut zlib = flate2::write::ZlibEncoder::new(Vec::new(), Compression::default());
+        zlib.write_all(&plain).unwrap();
+        let mut raw = flate2::write::DeflateEncoder::new(Vec::new(), Compression::default());
+        raw.write_all(&plain).unwrap();
+
+        for (encodings, body) in [
+            ("gzip", gzip(&plain)),
+            ("br", brotli(&plain)),
+            ("deflate", zlib.finish().unwrap()),
+            ("deflate", raw.finish().unwrap()),
+            ("gzip, br", brotli(&gzip(&plain))),
+        ] {
+            for step in [1, 7, body.len()] {
+                assert_eq!(
+                    decode_in_steps(encodings, &body, step),
+                    plain,
+                    "{} in {} byte steps",
+                    encodings,
+                    step
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_stream_decoder_emits_output_before_the_end() {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(b"data: first\n\n").unwrap();
+        encoder.flush().unwrap();
+        let first = encoder.get_ref().len();
+        encoder.write_all(b"data: second\n\n").unwrap();
+        let body = encoder.finish().unwrap();
+
+        let mut decoder = StreamDecoder::new(&parse_encodings("gzip"), 1024).unwrap();
+        assert_eq!(decoder.push(&body[..first]).unwrap(), b"data: first\n\n");
+        let mut rest = decoder.push(&body[first..]).unwrap();
+        rest.extend(decoder.finish().unwrap());
+        assert_eq!(rest, b"data: second\n\n");
+    }
+
+    #[test]
+    fn test_stream_decoder_errors() {
+        assert!(matches!(
+            StreamDecoder::new(&parse_encodings("zstd"), 1024),
+            Err(ContentEncodingError::Unsupported(_))
+        ));
+
+        let bomb = gzip(&[0u8; 64 * 1024]);
+        let mut decoder = StreamDecoder::new(&parse_encodings("gzip"), 1024).unwrap();
+        assert_eq!(
+            decoder.push(&bomb),
+            Err(ContentEncodingError::TooLarge(1024))
+        );
+
+        let mut decoder = StreamDecoder::new(&parse_encodings("gzip"), 1024).unwrap();
+        let err = decoder
+            .push(b"definitely not gzip")
+            .and_then(|_| decoder.finish());
+        assert_eq!(err.unwrap_err().reason(), "decode_error");
+    }
+
+    #[test]
+    fn test_negotiate_encoding() {
+        assert_eq!(negotiate_encoding("gzip, deflate, br"), Some("br"));
+        assert_eq!(negotiate_encoding("gzip"), Some("gzip"));
+        assert_eq!(negotiate_encoding("br;q=0.5, gzip"), Some("gzip"));
+        assert_eq!(negotiate_encoding("br;q=0, gzip;q=0"), None);
+        assert_eq!(negotiate_encoding("*;q=0.1"), Some("br"));
+        assert_eq!(negotiate_encoding("identity"), None);
+        assert_eq!(negotiate_encoding(""), None);
+    }
+
+    #[test]
+    fn test_encode_body_round_trips() {
+        for coding in ["gzip", "br"] {
+            let encoded = encode_body(coding, b"key=[REDACTED]").unwrap();
+            let decoded = decode_body(&parse_encodings(coding), &encoded, 1024).unwrap();
+            assert_eq!(decoded, b"key=[REDACTED]");
+        }
+        assert!(encode_body("lzma", b"data").is_err());
+    }
+}
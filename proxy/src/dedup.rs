@@ -0,0 +1,278 @@
+// SLAPENIR Request Deduplication - Single-flight for identical requests
+// Agents with buggy retry loops can fire the same request many times in a
+// row; identical requests arriving while one is still in flight wait for it
+// and receive a copy of its response instead of each hitting the upstream.
+
+use axum::{
+    body::{Body, Bytes},
+    http::{HeaderMap, Method, StatusCode, Uri},
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::watch;
+
+/// Header marking a non-idempotent request as safe to deduplicate
+pub const IDEMPOTENCY_KEY_HEADER: &str = "idempotency-key";
+
+/// Identifies identical requests from one client
+pub type RequestKey = [u8; 32];
+
+/// A buffered response that can be handed to every waiting client
+#[derive(Debug, Clone)]
+pub struct SharedResponse {
+    pub status: StatusCode,
+    pub headers: HeaderMap,
+    pub body: Bytes,
+}
+
+impl IntoResponse for SharedResponse {
+    fn into_response(self) -> Response {
+        let mut response = Response::new(Body::from(self.body));
+        *response.status_mut() = self.status;
+        *response.headers_mut() = self.headers;
+        response
+    }
+}
+
+/// What the first request of a flight left for the ones waiting on it
+#[derive(Debug, Clone)]
+pub enum Outcome {
+    Shared(SharedResponse),
+    /// Failed or streamed: each waiting request is sent on its own
+    Unshared,
+}
+
+/// Whether a request may share another's response
+///
+/// GET and HEAD are idempotent; other methods only with an `Idempotency-Key`.
+pub fn is_eligible(method: &Method, headers: &HeaderMap) -> bool {
+    matches!(*method, Method::GET | Method::HEAD) || headers.contains_key(IDEMPOTENCY_KEY_HEADER)
+}
+
+/// Key identical requests by client, method, URI, every header and the body
+///
+/// Including all headers means requests carrying different credentials or
+/// idempotency keys never share a response.
+pub fn request_key(
+    identity: &str,
+    method: &Method,
+    uri: &Uri,
+    headers: &HeaderMap,
+    body: &[u8],
+) -> RequestKey {
+    let mut hasher = Sha256::new();
+    // Length-prefixed, so field boundaries cannot be shifted
+    let mut field = |bytes: &[u8]| {
+        hasher.update((bytes.len() as u64).to_be_bytes());
+        hasher.update(bytes);
+    };
+    field(identity.as_bytes());
+    field(method.as_str().as_bytes());
+    field(uri.to_string().as_bytes());
+    for (name, value) in headers {
+        field(name.as_str().as_bytes());
+        field(value.as_bytes());
+    }
+    field(body);
+    hasher.finalize().into()
+}
+
+/// Receives the outcome once the leader of a flight has finished
+type OutcomeReceiver = watch::Receiver<Option<Outcome>>;
+
+/// Requests currently in flight, keyed by [`request_key`]
+#[derive(Debug, Default)]
+pub struct InFlightRequests {
+    /// Flight id (so a finished leader never ends a newer flight) and outcome
+    flights: Mutex<HashMap<RequestKey, (u64, OutcomeReceiver)>>,
+    next_id: AtomicU64,
+}
+
+/// This request's role in its flight
+pub enum Flight {
+    /// First of its kind: send it, then [`Leader::finish`]
+    Leader(Leader),
+    /// An identical request is in flight: [`Follower::wait`] for it
+    Follower(Follower),
+}
+
+impl InFlightRequests {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Join the flight for `key`, leading it if none is in progress
+    pub fn join(self: &Arc<Self>, key: RequestKey) -> Flight {
+        let mut flights = self.flights.lock().unwrap();
+        if let Some((_, outcome)) = flights.get(&key) {
+            return Flight::Follower(Follower {
+                outcome: outcome.clone(),
+            });
+        }
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (sender, receiver) = watch::channel(None);
+        flights.insert(key, (id, receiver));
+        Flight::Leader(Leader {
+            key,
+            id,
+            flights: self.clone(),
+            sender,
+        })
+    }
+
+    /// Number of flights in progress
+    pub fn len(&self) -> usize {
+        self.flights.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// The request that is actually sent upstream
+///
+/// Dropping it unfinished (e.g. the client went away) releases the
+/// followers, which then send their own requests.
+pub struct Leader {
+    key: RequestKey,
+    id: u64,
+    flights: Arc<InFlightRequests>,
+    sender: watch::Sender<Option<Outcome>>,
+}
+
+impl Leader {
+    /// End the flight and publish the outcome to every follower
+    pub fn finish(self, outcome: Outcome) {
+        self.end();
+        self.sender.send_replace(Some(outcome));
+    }
+
+    /// Stop new requests joining this flight
+    fn end(&self) {
+        let mut flights = self.flights.flights.lock().unwrap();
+        if flights.get(&self.key).is_some_and(|(id, _)| *id == self.id) {
+            flights.remove(&self.key);
+        }
+    }
+}
+
+impl Drop for Leader {
+    fn drop(&mut self) {
+        self.end();
+    }
+}
+
+/// A request waiting on an identical one in flight
+pub struct Follower {
+    outcome: OutcomeReceiver,
+}
+
+impl Follower {
+    /// The leader's response, or `None` if this request must be sent itself
+    pub async fn wait(mut self) -> Option<SharedResponse> {
+        let outcome = self.outcome.wait_for(Option::is_some).await.ok()?;
+        match outcome.as_ref() {
+            Some(Outcome::Shared(response)) => Some(response.clone()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(body: &[u8]) -> RequestKey {
+        request_key(
+            "10.0.0.1",
+            &Method::GET,
+            &"/v1/models".parse().unwrap(),
+            &HeaderMap::new(),
+            body,
+        )
+    }
+
+    #[test]
+    fn test_eligibility() {
+        let mut headers = HeaderMap::new();
+        assert!(is_eligible(&Method::GET, &headers));
+        assert!(is_eligible(&Method::HEAD, &headers));
+        assert!(!is_eligible(&Method::POST, &headers));
+
+        headers.insert(IDEMPOTENCY_KEY_HEADER, "abc".parse().unwrap());
+        assert!(is_eligible(&Method::POST, &headers));
+    }
+
+    #[test]
+    fn test_request_key_distinguishes_requests() {
+        assert_eq!(key(b""), key(b""));
+        assert_ne!(key(b""), key(b"x"));
+
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", "Bearer DUMMY_A".parse().unwrap());
+        let uri: Uri = "/v1/models".parse().unwrap();
+        assert_ne!(
+            request_key("10.0.0.1", &Method::GET, &uri, &headers, b""),
+            key(b"")
+        );
+        assert_ne!(
+            request_key("10.0.0.2", &Method::GET, &uri, &HeaderMap::new(), b""),
+            key(b"")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_follower_receives_leader_response() {
+        let flights = Arc::new(InFlightRequests::new());
+        let Flight::Leader(leader) = flights.join(key(b"")) else {
+            panic!("first request should lead");
+        };
+        let Flight::Follower(follower) = flights.join(key(b"")) else {
+            panic!("identical request should follow");
+        };
+        let waiting = tokio::spawn(follower.wait());
+
+        leader.finish(Outcome::Shared(SharedResponse {
+            status: StatusCode::OK,
+            headers: HeaderMap::new(),
+            body: Bytes::from_static(b"ok"),
+        }));
+
+        let shared = waiting.await.unwrap().unwrap();
+        assert_eq!(shared.body, Bytes::from_static(b"ok"));
+        assert!(flights.is_empty());
+        assert!(matches!(flights.join(key(b"")), Flight::Leader(_)));
+    }
+
+    #[test]
+    fn test_finished_leader_does_not_end_newer_flight() {
+        let flights = Arc::new(InFlightRequests::new());
+        let Flight::Leader(first) = flights.join(key(b"")) else {
+            panic!("first request should lead");
+        };
+        first.end();
+        let second = flights.join(key(b""));
+        assert!(matches!(second, Flight::Leader(_)));
+
+        drop(first);
+        assert_eq!(flights.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_leader_releases_followers() {
+        let flights = Arc::new(InFlightRequests::new());
+        let leader = flights.join(key(b""));
+        let Flight::Follower(follower) = flights.join(key(b"")) else {
+            panic!("identical request should follow");
+        };
+
+        drop(leader);
+
+        assert!(follower.wait().await.is_none());
+        assert!(flights.is_empty());
+    }
+}
@@ -0,0 +1,158 @@
+// SLAPENIR DLP Rules - Outbound data loss prevention
+// Operator-defined regex rules over outbound request bodies, for sensitive
+// data that is not a managed credential (SSNs, card numbers, codenames).
+// Matched content is never logged; only the rule name is reported.
+
+use crate::metrics;
+use std::borrow::Cow;
+
+/// Replacement for content matched by a `redact` rule
+pub const DLP_REDACTED_MARKER: &str = "[DLP_REDACTED]";
+
+/// What happens to a request whose body matches a rule
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DlpAction {
+    /// Refuse the request with 403
+    #[default]
+    Block,
+    /// Replace the matched content and forward the request
+    Redact,
+}
+
+impl DlpAction {
+    /// Parse a setting value: `block` or `redact`
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "block" | "" => Some(Self::Block),
+            "redact" => Some(Self::Redact),
+            _ => None,
+        }
+    }
+}
+
+/// A named pattern that outbound request bodies must not carry
+#[derive(Debug, Clone)]
+pub struct DlpRule {
+    name: String,
+    pattern: regex::Regex,
+    action: DlpAction,
+}
+
+impl DlpRule {
+    /// Create a rule that blocks matching requests
+    pub fn new(name: &str, pattern: &str) -> Result<Self, regex::Error> {
+        Ok(Self {
+            name: name.to_string(),
+            pattern: regex::Regex::new(pattern)?,
+            action: DlpAction::Block,
+        })
+    }
+
+    /// Handle matches with `action` instead of blocking
+    pub fn with_action(mut self, action: DlpAction) -> Self {
+        self.action = action;
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+/// Apply DLP rules to an outbound body, in order
+///
+/// Returns the body to forward, with `redact` rule matches replaced, or the
+/// name of the first `block` rule that matched.
+pub fn apply_dlp_rules<'a>(rules: &[DlpRule], body: &'a str) -> Result<Cow<'a, str>, String> {
+    let mut body = Cow::Borrowed(body);
+    for rule in rules {
+        if !rule.pattern.is_match(&body) {
+            continue;
+        }
+        match rule.action {
+            DlpAction::Block => {
+                metrics::record_dlp_blocked(&rule.name);
+                return Err(rule.name.clone());
+            }
+            DlpAction::Redact => {
+                metrics::record_dlp_redacted(&rule.name);
+                body = Cow::Owned(
+                    rule.pattern
+                        .replace_all(&body, DLP_REDACTED_MARKER)
+                        .into_owned(),
+                );
+            }
+        }
+    }
+    Ok(body)
+}
+
+/// Check DLP rules against a body that cannot be rewritten
+///
+/// Used for binary request bodies: any matching rule, `redact` included,
+/// reports its name so the request is blocked rather than forwarded unredacted.
+pub fn check_dlp_rules(rules: &[DlpRule], body: &str) -> Result<(), String> {
+    match rules.iter().find(|rule| rule.pattern.is_match(body)) {
+        Some(rule) => {
+            metrics::record_dlp_blocked(&rule.name);
+            Err(rule.name.clone())
+        }
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules() -> Vec<DlpRule> {
+        vec![
+            DlpRule::new("ssn", r"\b\d{3}-\d{2}-\d{4}\b")
+                .unwrap()
+                .with_action(DlpAction::Redact),
+            DlpRule::new("codename", r"(?i)project\s+nightjar").unwrap(),
+        ]
+    }
+
+    #[test]
+    fn test_clean_body_passes_unchanged() {
+        let body = r#"{"prompt":"summarize the release notes"}"#;
+        assert!(matches!(
+            apply_dlp_rules(&rules(), body),
+            Ok(Cow::Borrowed(b)) if b == body
+        ));
+    }
+
+    #[test]
+    fn test_block_rule_reports_rule_name() {
+        let blocked = metrics::DLP_BLOCKED_TOTAL.with_label_values(&["codename"]);
+        let before = blocked.get();
+
+        let result = apply_dlp_rules(&rules(), "status of Project Nightjar?");
+
+        assert_eq!(result.unwrap_err(), "codename");
+        assert_eq!(blocked.get(), before + 1);
+    }
+
+    #[test]
+    fn test_redact_rule_replaces_every_match() {
+        let result = apply_dlp_rules(&rules(), "ids 123-45-6789 and 987-65-4321").unwrap();
+        assert_eq!(result, "ids [DLP_REDACTED] and [DLP_REDACTED]");
+    }
+
+    #[test]
+    fn test_check_blocks_on_redact_rule() {
+        assert_eq!(
+            check_dlp_rules(&rules(), "id 123-45-6789").unwrap_err(),
+            "ssn"
+        );
+        assert!(check_dlp_rules(&rules(), "nothing sensitive").is_ok());
+    }
+
+    #[test]
+    fn test_action_parse() {
+        assert_eq!(DlpAction::parse("Redact"), Some(DlpAction::Redact));
+        assert_eq!(DlpAction::parse("block"), Some(DlpAction::Block));
+        assert_eq!(DlpAction::parse("log"), None);
+    }
+}
@@ -1,30 +1,45 @@
 // SLAPENIR Proxy Library
 // Exposes core modules for credential sanitization
 
+pub mod admin;
 pub mod auto_detect;
 pub mod builder;
+pub mod client_scope;
 pub mod config;
+pub mod config_reload;
 pub mod connect;
+pub mod connect_full;
 pub mod connect_middleware;
+pub mod content_encoding;
+pub mod dedup;
+pub mod dlp;
 pub mod http_parser;
 pub mod metrics;
 pub mod middleware;
 pub mod mtls;
 pub mod proxy;
+pub mod quota;
+pub mod rate_limit;
 pub mod sanitizer;
+pub mod socket;
 pub mod strategies;
 pub mod strategy;
+pub mod strategy_usage;
 pub mod tls;
+pub mod trace_propagation;
+pub mod upstream_hosts;
+pub mod warmup;
 
 // Re-export commonly used types
 pub use auto_detect::{merge_strategies, AutoDetectConfig, AutoDetector};
-pub use builder::{build_strategies_from_config, is_telemetry_domain};
+pub use builder::{build_strategies_from_config, check_strategy_credentials, is_telemetry_domain};
 pub use config::{Config, SecurityConfig, StrategyConfig};
 pub use middleware::{inject_secrets_middleware, sanitize_secrets_middleware, AppState};
 pub use mtls::{verify_client_cert, ClientCertInfo, MtlsConfig};
 pub use proxy::{
-    build_response_headers, create_http_client, proxy_handler, HttpClient, ProxyConfig,
+    build_response_headers, build_response_headers_with_config, create_http_client,
+    create_http_client_with_config, proxy_handler, HttpClient, ProxyConfig,
     DEFAULT_MAX_REQUEST_SIZE, DEFAULT_MAX_RESPONSE_SIZE,
 };
 pub use sanitizer::SecretMap;
-pub use strategy::{AuthStrategy, BearerStrategy, StrategyError};
+pub use strategy::{AuthStrategy, BearerStrategy, InjectTargets, SecurityError, StrategyError};
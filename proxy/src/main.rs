@@ -5,26 +5,35 @@ use axum::{
     extract::State,
     response::Html,
     routing::{any, delete, get, post},
+    serve::ListenerExt,
     Extension, Json, Router,
 };
+use rustls::pki_types::CertificateDer;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::net::SocketAddr;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
 use tower_http::trace::TraceLayer;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 // Use the library modules
 use slapenir_proxy::{
-    auto_detect::{AutoDetectConfig, AutoDetector},
-    build_strategies_from_config,
-    config::Config,
+    admin,
+    auto_detect::{AutoDetectConfig, AutoDetector, DetectedStrategies},
+    build_strategies_from_config, check_strategy_credentials,
+    config::{Config, StrategyConfig},
+    config_reload::{BuiltCredentials, ConfigReloader},
+    connect_full,
     connect_middleware::ConnectLayer,
-    metrics::{gather_metrics, init_metrics},
+    metrics::{self, init_metrics, render_metrics_for},
     middleware::AppState,
-    mtls::MtlsConfig,
-    proxy,
-    sanitizer::SecretMap,
+    mtls::{self, MtlsConfig},
+    proxy, quota,
+    sanitizer::{self, SecretMap},
+    socket,
     strategy::AuthStrategy,
+    tls, trace_propagation, warmup,
 };
 
 #[tokio::main]
@@ -50,10 +59,116 @@ async fn main() -> anyhow::Result<()> {
     // Initialize mTLS if enabled
     let mtls_config = load_mtls_config()?;
 
+    // One config file covers proxy, routing, limits and security; the env
+    // vars read below override it
+    let file_config = load_file_config();
+    let base_config = match &file_config {
+        Some(config) => proxy::ProxyConfig::from_config(config)
+            .map_err(|e| anyhow::anyhow!("Invalid config file: {}", e))?,
+        None => proxy::ProxyConfig::default(),
+    };
+    let security = file_config
+        .map(|config| config.security)
+        .unwrap_or_default();
+
     // Load secrets using strategy pattern with auto-detection
-    let secret_map = load_secrets_with_strategies().await?;
+    let mode = load_mode(base_config.mode);
+    let strict = load_strict_mode(security.strict_mode);
+    let (secret_map, strategies, auto_detected, refresh) =
+        load_secrets_with_strategies(strict).await?;
+    let auto_detected: DetectedStrategies = Arc::new(RwLock::new(auto_detected));
+    let mut secret_map = secret_map
+        .with_redaction_style(load_redaction_style())
+        .with_redaction_mode(load_redaction_mode());
+    if mode == proxy::ProxyMode::SanitizeOnly {
+        tracing::info!("🛡️  Sanitize-only mode: secrets are redacted both ways, never injected");
+        secret_map = secret_map
+            .to_sanitize_only()
+            .map_err(|e| anyhow::anyhow!("Failed to create SecretMap: {}", e))?;
+    }
+    if secret_map.redaction_style() == sanitizer::RedactionStyle::Indexed {
+        for index in 1..=secret_map.redaction_index_count() {
+            tracing::info!(
+                "🏷️  [REDACTED:{}] marks {}",
+                index,
+                secret_map.redaction_label(index).unwrap_or("unknown")
+            );
+        }
+    }
+
+    // Bound the plaintext secret material held in memory
+    let max_secret_bytes = load_max_secret_bytes(base_config.max_secret_bytes);
+    let secret_bytes = secret_map
+        .check_secret_bytes(max_secret_bytes)
+        .map_err(|e| anyhow::anyhow!(e))?;
+    metrics::update_secret_bytes(secret_bytes);
 
-    let app_state = AppState::new(std::sync::Arc::new(secret_map), proxy::create_http_client());
+    let proxy_config = proxy::ProxyConfig {
+        admin_token: std::env::var("ADMIN_TOKEN").ok().filter(|t| !t.is_empty()),
+        proxy_identification: load_proxy_identification(base_config.proxy_identification),
+        byte_quota: load_byte_quota(base_config.byte_quota),
+        inspect_plaintext_ports: load_inspect_plaintext_ports(base_config.inspect_plaintext_ports),
+        intercept_ports: load_intercept_ports(base_config.intercept_ports),
+        intercept_all_tls: std::env::var("INTERCEPT_ALL_TLS")
+            .map(|v| v == "1" || v.to_lowercase() == "true")
+            .unwrap_or(base_config.intercept_all_tls),
+        header_value_limit: load_header_value_limit(base_config.header_value_limit),
+        allowed_response_content_types: load_allowed_response_content_types(
+            base_config.allowed_response_content_types,
+        ),
+        mode,
+        mixed_credential_policy: load_mixed_credential_policy(base_config.mixed_credential_policy),
+        non_utf8_body_policy: load_non_utf8_body_policy(base_config.non_utf8_body_policy),
+        trace_propagation: load_trace_propagation(base_config.trace_propagation),
+        max_secret_bytes,
+        mitm_bypass_sni: load_mitm_bypass_sni(base_config.mitm_bypass_sni),
+        egress_allowed_hosts: load_egress_allowed_hosts(base_config.egress_allowed_hosts),
+        decode_html_entities: std::env::var("DECODE_HTML_ENTITIES")
+            .map(|v| v == "1" || v.to_lowercase() == "true")
+            .unwrap_or(base_config.decode_html_entities),
+        sanitize_request_headers: std::env::var("SANITIZE_REQUEST_HEADERS")
+            .map(|v| v == "1" || v.to_lowercase() == "true")
+            .unwrap_or(base_config.sanitize_request_headers),
+        max_html_entity_decode_size: load_max_html_entity_decode_size(
+            base_config.max_html_entity_decode_size,
+        ),
+        runtime_secret_rebuild_debounce: load_runtime_secret_rebuild_debounce(
+            base_config.runtime_secret_rebuild_debounce,
+        ),
+        upstream_ca_certs: load_upstream_ca_certs()?,
+        insecure_skip_upstream_verify: load_insecure_skip_upstream_verify(),
+        ..base_config
+    };
+    for route in proxy_config.routes.iter().filter(|route| !route.inject) {
+        tracing::info!("🚫 Credential injection disabled for route {}", route.path);
+    }
+    let addr = proxy_config.listen_addr;
+    tracing::debug!(
+        "Refusing upstream connections to {} local interface address(es)",
+        proxy::local_addresses().len()
+    );
+    let network = proxy_config.network.clone();
+
+    let app_state = AppState::with_config(
+        std::sync::Arc::new(secret_map),
+        proxy::create_http_client_with_config(&proxy_config),
+        proxy_config,
+    )
+    // Host whitelists, signing and URI injection on the MITM path
+    .with_strategies(strategies)
+    .with_security(&security)
+    .map_err(|e| anyhow::anyhow!("Invalid security config: {}", e))?;
+    // Kept alive for as long as the proxy serves
+    let rebuild = credential_rebuild(&app_state, auto_detected.clone(), mode, strict);
+    let _config_watcher = watch_config_file(&app_state, rebuild.clone());
+    if let Some((detector, interval)) = refresh {
+        refresh_auto_detected(detector, interval, &app_state, auto_detected, rebuild);
+    }
+    if !security.client_scopes.is_empty() && mtls_config.is_none() {
+        tracing::warn!(
+            "⚠️  client_scopes configured without mTLS: no client presents a certificate, so none will inject credentials"
+        );
+    }
 
     // Check if ALLOW_BUILD mode is enabled
     let allow_build = std::env::var("ALLOW_BUILD")
@@ -63,10 +178,36 @@ async fn main() -> anyhow::Result<()> {
     if allow_build {
         tracing::warn!("⚠️  ALLOW_BUILD mode enabled - proxy bypassing domain restrictions");
         tracing::warn!("⚠️  All outbound traffic will be allowed (build/test mode)");
+    } else {
+        // TLS interception is active, so make sure the MITM CA is usable now
+        connect_full::check_ca_available(connect_full::mitm_ca_paths());
+    }
+
+    // Publish certificate expiry now and hourly, so rotations are picked up
+    let mut cert_watch = tls::CertExpiryWatch::new();
+    if !allow_build {
+        cert_watch = cert_watch.with_cert("ca", &connect_full::mitm_ca_paths().cert);
+    }
+    if let Some(mtls) = &mtls_config {
+        cert_watch = cert_watch
+            .with_cert("server", &mtls.server_cert_path)
+            .with_cert("client_ca", &mtls.ca_cert_path);
+    }
+    cert_watch.spawn(tls::expiry::DEFAULT_CERT_EXPIRY_REFRESH);
+
+    // Pay cold-start costs now rather than on the first request
+    if let Some(mut warmup_config) = load_warmup_config() {
+        warmup_config.mitm &= !allow_build;
+        let ca = if warmup_config.mitm {
+            connect_full::mitm_ca_paths().load().ok()
+        } else {
+            None
+        };
+        warmup::warmup(&app_state, ca.as_deref(), &warmup_config).await;
     }
 
     // Build our application with routes
-    let mut app = Router::new()
+    let app = Router::new()
         // Health and info endpoints
         .route("/", get(root))
         .route("/health", get(health))
@@ -75,6 +216,8 @@ async fn main() -> anyhow::Result<()> {
         .route("/internal/secrets", post(register_secrets_handler))
         .route("/internal/secrets/list", get(list_secrets_handler))
         .route("/internal/secrets", delete(unregister_secrets_handler))
+        // Admin API for runtime security lists (requires ADMIN_TOKEN)
+        .merge(admin::routes(app_state.clone()))
         // Proxy routes - handle all HTTP methods
         .route("/v1/{*path}", any(proxy::proxy_handler))
         .with_state(app_state.clone())
@@ -83,27 +226,39 @@ async fn main() -> anyhow::Result<()> {
         // bypass TraceLayer, which interferes with hyper's HTTP upgrade mechanism
         .layer(ConnectLayer::new(app_state));
 
-    // Add mTLS layer if configured
+    // Bind to address - 0.0.0.0 to accept connections from all interfaces
+    let listener = tokio::net::TcpListener::bind(addr).await?;
+
     if let Some(mtls) = mtls_config {
+        // Client certificates are checked before any route, CONNECT included
         tracing::info!("🔒 mTLS enabled - mutual authentication active");
-        app = app.layer(Extension(mtls));
-        // Note: Full mTLS integration with axum-server for TLS listener
-        // would be added here in production. For now, we support mTLS
-        // configuration but serve over HTTP for development.
-    } else {
-        tracing::info!("🔓 mTLS disabled - running in development mode");
+        let app = app
+            .layer(axum::middleware::from_fn(mtls::verify_client_cert))
+            .layer(Extension(mtls.clone()));
+
+        tracing::info!("🚀 Proxy listening on {} (TLS)", addr);
+        tracing::info!("📡 Ready to proxy LLM API requests");
+        tracing::info!("💡 Send requests to https://localhost:3000/v1/*path");
+        tracing::info!("📊 Metrics available at https://localhost:3000/metrics");
+
+        mtls::serve(listener, app, mtls, network).await?;
+        return Ok(());
     }
 
-    // Bind to address - 0.0.0.0 to accept connections from all interfaces
-    let addr = SocketAddr::from(([0, 0, 0, 0], 3000));
+    tracing::info!("🔓 mTLS disabled - running in development mode");
     tracing::info!("🚀 Proxy listening on {}", addr);
     tracing::info!("📡 Ready to proxy LLM API requests");
     tracing::info!("💡 Send requests to http://localhost:3000/v1/*path");
     tracing::info!("📊 Metrics available at http://localhost:3000/metrics");
 
     // Run server
-    let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app).await?;
+    let listener = listener.tap_io(move |stream| socket::tune_socket(stream, &network));
+    // Peer addresses identify agents for byte quotas
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+    )
+    .await?;
 
     Ok(())
 }
@@ -149,7 +304,10 @@ fn load_mtls_config() -> anyhow::Result<Option<MtlsConfig>> {
     match MtlsConfig::from_files(&ca_cert, &server_cert, &server_key, enforce) {
         Ok(config) => {
             tracing::info!("✅ mTLS configuration loaded successfully");
-            Ok(Some(config))
+            Ok(Some(config.with_handshake_limits(
+                load_mtls_handshake_timeout(mtls::DEFAULT_HANDSHAKE_TIMEOUT),
+                load_mtls_max_concurrent_handshakes(mtls::DEFAULT_MAX_CONCURRENT_HANDSHAKES),
+            )))
         }
         Err(e) => {
             tracing::warn!("⚠️  Failed to load mTLS configuration: {}", e);
@@ -160,6 +318,409 @@ fn load_mtls_config() -> anyhow::Result<Option<MtlsConfig>> {
     }
 }
 
+/// Read MTLS_HANDSHAKE_TIMEOUT_SECS
+fn load_mtls_handshake_timeout(default: Duration) -> Duration {
+    let Ok(value) = std::env::var("MTLS_HANDSHAKE_TIMEOUT_SECS") else {
+        return default;
+    };
+    match value.parse::<u64>() {
+        Ok(secs) if secs > 0 => Duration::from_secs(secs),
+        _ => {
+            tracing::warn!(
+                "Invalid MTLS_HANDSHAKE_TIMEOUT_SECS '{}', keeping {:?}",
+                value,
+                default
+            );
+            default
+        }
+    }
+}
+
+/// Read MTLS_MAX_CONCURRENT_HANDSHAKES
+fn load_mtls_max_concurrent_handshakes(default: usize) -> usize {
+    let Ok(value) = std::env::var("MTLS_MAX_CONCURRENT_HANDSHAKES") else {
+        return default;
+    };
+    match value.parse::<usize>() {
+        Ok(max) if max > 0 => max,
+        _ => {
+            tracing::warn!(
+                "Invalid MTLS_MAX_CONCURRENT_HANDSHAKES '{}', keeping {}",
+                value,
+                default
+            );
+            default
+        }
+    }
+}
+
+/// Load the config file at CONFIG_PATH (default config.yaml), when present
+///
+/// A `.toml` extension selects TOML; anything else is read as YAML.
+fn load_file_config() -> Option<Config> {
+    let config_path = std::env::var("CONFIG_PATH").unwrap_or_else(|_| "config.yaml".to_string());
+    if !Path::new(&config_path).exists() {
+        return None;
+    }
+    Config::from_file(&config_path)
+        .inspect_err(|e| tracing::warn!("⚠️  Ignoring {}: {}", config_path, e))
+        .ok()
+}
+
+/// Read PROXY_IDENTIFICATION (`off`, `user-agent` or `header`; default from the config file)
+fn load_proxy_identification(default: proxy::ProxyIdentification) -> proxy::ProxyIdentification {
+    let Ok(value) = std::env::var("PROXY_IDENTIFICATION") else {
+        return default;
+    };
+    proxy::ProxyIdentification::parse(&value).unwrap_or_else(|| {
+        tracing::warn!(
+            "Invalid PROXY_IDENTIFICATION '{}' (expected off, user-agent or header), keeping {:?}",
+            value,
+            default
+        );
+        default
+    })
+}
+
+/// Read MODE: `inject` or `sanitize_only` (default from the config file)
+fn load_mode(default: proxy::ProxyMode) -> proxy::ProxyMode {
+    let Ok(value) = std::env::var("MODE") else {
+        return default;
+    };
+    proxy::ProxyMode::parse(&value).unwrap_or_else(|| {
+        tracing::warn!(
+            "Invalid MODE '{}' (expected inject or sanitize_only), keeping {:?}",
+            value,
+            default
+        );
+        default
+    })
+}
+
+/// Read MIXED_CREDENTIAL_POLICY (warn, strip or block; default from the config file)
+fn load_mixed_credential_policy(
+    default: proxy::MixedCredentialPolicy,
+) -> proxy::MixedCredentialPolicy {
+    let Ok(value) = std::env::var("MIXED_CREDENTIAL_POLICY") else {
+        return default;
+    };
+    proxy::MixedCredentialPolicy::parse(&value).unwrap_or_else(|| {
+        tracing::warn!(
+            "Invalid MIXED_CREDENTIAL_POLICY '{}' (expected warn, strip or block), keeping {:?}",
+            value,
+            default
+        );
+        default
+    })
+}
+
+fn load_non_utf8_body_policy(default: proxy::NonUtf8BodyPolicy) -> proxy::NonUtf8BodyPolicy {
+    let Ok(value) = std::env::var("NON_UTF8_BODY_POLICY") else {
+        return default;
+    };
+    proxy::NonUtf8BodyPolicy::parse(&value).unwrap_or_else(|| {
+        tracing::warn!(
+            "Invalid NON_UTF8_BODY_POLICY '{}' (expected reject, inject_bytes or passthrough), keeping {:?}",
+            value,
+            default
+        );
+        default
+    })
+}
+
+/// Read TRACE_PROPAGATION, falling back to OpenTelemetry's OTEL_PROPAGATORS
+fn load_trace_propagation(
+    default: trace_propagation::TracePropagation,
+) -> trace_propagation::TracePropagation {
+    let Some((name, value)) = ["TRACE_PROPAGATION", "OTEL_PROPAGATORS"]
+        .into_iter()
+        .find_map(|name| Some((name, std::env::var(name).ok()?)))
+    else {
+        return default;
+    };
+    trace_propagation::TracePropagation::parse(&value).unwrap_or_else(|| {
+        tracing::warn!(
+            "Invalid {} '{}' (expected off, w3c or w3c_b3), keeping {}",
+            name,
+            value,
+            default.as_str()
+        );
+        default
+    })
+}
+
+/// Read MAX_SECRET_BYTES (cap on real secret bytes held; default from the config file)
+fn load_max_secret_bytes(default: Option<usize>) -> Option<usize> {
+    let Ok(value) = std::env::var("MAX_SECRET_BYTES") else {
+        return default;
+    };
+    match value.parse::<usize>() {
+        Ok(max) => Some(max),
+        Err(_) => {
+            tracing::warn!(
+                "Invalid MAX_SECRET_BYTES '{}', keeping {:?}",
+                value,
+                default
+            );
+            default
+        }
+    }
+}
+
+/// Read MAX_HTML_ENTITY_DECODE_SIZE (bytes; default from the config file)
+fn load_max_html_entity_decode_size(default: usize) -> usize {
+    let Ok(value) = std::env::var("MAX_HTML_ENTITY_DECODE_SIZE") else {
+        return default;
+    };
+    value.parse::<usize>().unwrap_or_else(|_| {
+        tracing::warn!(
+            "Invalid MAX_HTML_ENTITY_DECODE_SIZE '{}', keeping {}",
+            value,
+            default
+        );
+        default
+    })
+}
+
+/// Read RUNTIME_SECRET_REBUILD_DEBOUNCE_MS (default from the config file)
+fn load_runtime_secret_rebuild_debounce(default: Duration) -> Duration {
+    let Ok(value) = std::env::var("RUNTIME_SECRET_REBUILD_DEBOUNCE_MS") else {
+        return default;
+    };
+    match value.parse::<u64>() {
+        Ok(ms) => Duration::from_millis(ms),
+        Err(_) => {
+            tracing::warn!(
+                "Invalid RUNTIME_SECRET_REBUILD_DEBOUNCE_MS '{}', keeping {:?}",
+                value,
+                default
+            );
+            default
+        }
+    }
+}
+
+/// Read BYTE_QUOTA_MAX_BYTES and BYTE_QUOTA_WINDOW_SECS (default 3600); unset
+/// keeps the config file's quota, if any
+fn load_byte_quota(default: Option<quota::ByteQuotaConfig>) -> Option<quota::ByteQuotaConfig> {
+    let Ok(max_bytes) = std::env::var("BYTE_QUOTA_MAX_BYTES") else {
+        if let Some(quota) = &default {
+            tracing::info!(
+                "📏 Byte quota: {} bytes per identity every {}s",
+                quota.max_bytes,
+                quota.window.as_secs()
+            );
+        }
+        return default;
+    };
+    let Ok(max_bytes) = max_bytes.parse::<u64>() else {
+        tracing::warn!(
+            "Invalid BYTE_QUOTA_MAX_BYTES '{}', keeping {:?}",
+            max_bytes,
+            default
+        );
+        return default;
+    };
+    let window_secs = std::env::var("BYTE_QUOTA_WINDOW_SECS")
+        .ok()
+        .and_then(|v| v.parse::<u64>().ok())
+        .unwrap_or(3600);
+    tracing::info!(
+        "📏 Byte quota: {} bytes per identity every {}s",
+        max_bytes,
+        window_secs
+    );
+    Some(quota::ByteQuotaConfig {
+        max_bytes,
+        window: std::time::Duration::from_secs(window_secs),
+    })
+}
+
+/// Read INSPECT_PLAINTEXT_PORTS (comma-separated CONNECT ports to inspect; default from the config file)
+fn load_inspect_plaintext_ports(default: Vec<u16>) -> Vec<u16> {
+    let Ok(value) = std::env::var("INSPECT_PLAINTEXT_PORTS") else {
+        return default;
+    };
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|port| !port.is_empty())
+        .filter_map(|port| {
+            port.parse::<u16>()
+                .inspect_err(|_| {
+                    tracing::warn!("Ignoring invalid INSPECT_PLAINTEXT_PORTS entry '{}'", port)
+                })
+                .ok()
+        })
+        .collect()
+}
+
+/// Read INTERCEPT_PORTS (comma-separated CONNECT ports to MITM, empty disables; default from the config file)
+fn load_intercept_ports(default: Vec<u16>) -> Vec<u16> {
+    let Ok(value) = std::env::var("INTERCEPT_PORTS") else {
+        return default;
+    };
+    let ports: Vec<u16> = value
+        .split(',')
+        .map(str::trim)
+        .filter(|port| !port.is_empty())
+        .filter_map(|port| {
+            port.parse::<u16>()
+                .inspect_err(|_| {
+                    tracing::warn!("Ignoring invalid INTERCEPT_PORTS entry '{}'", port)
+                })
+                .ok()
+        })
+        .collect();
+    if ports.is_empty() {
+        tracing::warn!("🔓 INTERCEPT_PORTS is empty - TLS interception disabled");
+    }
+    ports
+}
+
+/// Read ALLOWED_RESPONSE_CONTENT_TYPES (comma-separated media types; default from the config file)
+fn load_allowed_response_content_types(default: Vec<String>) -> Vec<String> {
+    let allowed: Vec<String> = match std::env::var("ALLOWED_RESPONSE_CONTENT_TYPES") {
+        Ok(value) => value
+            .split(',')
+            .map(str::trim)
+            .filter(|media_type| !media_type.is_empty())
+            .map(str::to_ascii_lowercase)
+            .collect(),
+        Err(_) => default,
+    };
+    if !allowed.is_empty() {
+        tracing::info!("📄 Response content types allowed: {}", allowed.join(", "));
+    }
+    allowed
+}
+
+/// Read MITM_BYPASS_SNI (comma-separated hostnames, `*.` for subdomains; default from the config file)
+fn load_mitm_bypass_sni(default: Vec<String>) -> Vec<String> {
+    let bypass: Vec<String> = match std::env::var("MITM_BYPASS_SNI") {
+        Ok(value) => value
+            .split(',')
+            .map(str::trim)
+            .filter(|host| !host.is_empty())
+            .map(str::to_ascii_lowercase)
+            .collect(),
+        Err(_) => default,
+    };
+    if !bypass.is_empty() {
+        tracing::info!("📌 TLS passthrough for pinned SNIs: {}", bypass.join(", "));
+    }
+    bypass
+}
+
+/// Read EGRESS_ALLOWED_HOSTS (comma-separated hostnames, `*.` for subdomains; default from the config file)
+fn load_egress_allowed_hosts(default: Vec<String>) -> Vec<String> {
+    let allowed: Vec<String> = match std::env::var("EGRESS_ALLOWED_HOSTS") {
+        Ok(value) => value
+            .split(',')
+            .map(str::trim)
+            .filter(|host| !host.is_empty())
+            .map(str::to_ascii_lowercase)
+            .collect(),
+        Err(_) => default,
+    };
+    if !allowed.is_empty() {
+        tracing::info!("🧱 Egress allowed only to: {}", allowed.join(", "));
+    }
+    allowed
+}
+
+/// Read STRICT_MODE (on/off; default from the config file)
+fn load_strict_mode(default: bool) -> bool {
+    let strict = std::env::var("STRICT_MODE")
+        .map(|v| v == "1" || v.to_lowercase() == "true")
+        .unwrap_or(default);
+    if strict {
+        tracing::info!("🔒 Strict mode: missing strategy credentials stop startup");
+    }
+    strict
+}
+
+/// Read WARMUP (on/off) and WARMUP_UPSTREAM (primary target to pre-connect); off by default
+fn load_warmup_config() -> Option<warmup::WarmupConfig> {
+    let enabled = std::env::var("WARMUP")
+        .map(|v| v == "1" || v.to_lowercase() == "true")
+        .unwrap_or(false);
+    if !enabled {
+        return None;
+    }
+    Some(warmup::WarmupConfig {
+        mitm: true,
+        upstream: std::env::var("WARMUP_UPSTREAM")
+            .ok()
+            .filter(|u| !u.is_empty()),
+    })
+}
+
+/// Read REDACTION_STYLE (`plain`, `indexed` or `labeled`; default plain)
+fn load_redaction_style() -> sanitizer::RedactionStyle {
+    let Ok(value) = std::env::var("REDACTION_STYLE") else {
+        return sanitizer::RedactionStyle::default();
+    };
+    sanitizer::RedactionStyle::parse(&value).unwrap_or_else(|| {
+        tracing::warn!(
+            "Invalid REDACTION_STYLE '{}' (expected plain, indexed or labeled), using plain",
+            value
+        );
+        sanitizer::RedactionStyle::default()
+    })
+}
+
+/// Read REDACTION_SUFFIX (trailing secret bytes kept after each marker; default 0, full)
+fn load_redaction_mode() -> sanitizer::RedactionMode {
+    let Ok(value) = std::env::var("REDACTION_SUFFIX") else {
+        return sanitizer::RedactionMode::default();
+    };
+    match value.trim().parse::<usize>() {
+        Ok(0) => sanitizer::RedactionMode::Full,
+        Ok(len) => {
+            tracing::info!(
+                "✂️  Redactions keep up to {} trailing bytes of each secret",
+                len
+            );
+            sanitizer::RedactionMode::PartialSuffix(len)
+        }
+        Err(_) => {
+            tracing::warn!(
+                "Invalid REDACTION_SUFFIX '{}' (expected a byte count), redacting fully",
+                value
+            );
+            sanitizer::RedactionMode::default()
+        }
+    }
+}
+
+/// Read MAX_HEADER_VALUE_LEN (bytes) and OVERSIZED_HEADER_ACTION (`truncate` or `reject`);
+/// each defaults to the config file
+fn load_header_value_limit(default: sanitizer::HeaderValueLimit) -> sanitizer::HeaderValueLimit {
+    let mut limit = default;
+    if let Ok(value) = std::env::var("MAX_HEADER_VALUE_LEN") {
+        match value.parse::<usize>() {
+            Ok(max_len) => limit.max_len = max_len,
+            Err(_) => tracing::warn!(
+                "Invalid MAX_HEADER_VALUE_LEN '{}', keeping {} bytes",
+                value,
+                limit.max_len
+            ),
+        }
+    }
+    if let Ok(value) = std::env::var("OVERSIZED_HEADER_ACTION") {
+        match sanitizer::OversizedHeaderAction::parse(&value) {
+            Some(action) => limit.action = action,
+            None => tracing::warn!(
+                "Invalid OVERSIZED_HEADER_ACTION '{}' (expected truncate or reject), keeping {:?}",
+                value,
+                limit.action
+            ),
+        }
+    }
+    limit
+}
+
 /// Load secrets using strategy pattern with auto-detection integration
 ///
 /// This function attempts multiple sources in order:
@@ -168,9 +729,23 @@ fn load_mtls_config() -> anyhow::Result<Option<MtlsConfig>> {
 /// 3. Merge both sources (manual takes precedence)
 /// 4. Fall back to hardcoded env vars if both fail
 /// 5. Log helpful error if no credentials found from any source
-async fn load_secrets_with_strategies() -> anyhow::Result<SecretMap> {
+///
+/// The auto-detected strategy configs are returned too, for config reloads,
+/// along with the detector and interval when AUTO_DETECT_REFRESH_SECS is set.
+/// In strict mode a config strategy that cannot be built, or whose credential
+/// env vars are unset, is a startup error instead of a warning.
+async fn load_secrets_with_strategies(
+    strict: bool,
+) -> anyhow::Result<(
+    SecretMap,
+    Vec<Box<dyn AuthStrategy>>,
+    Vec<StrategyConfig>,
+    Option<(AutoDetector, Duration)>,
+)> {
     let mut all_strategies: Vec<Box<dyn AuthStrategy>> = Vec::new();
+    let mut auto_detected = Vec::new();
     let mut has_manual_config = false;
+    let mut refresh = None;
 
     // 1. Try to load config.yaml (manual configuration)
     let config_path = std::env::var("CONFIG_PATH").unwrap_or_else(|_| "config.yaml".to_string());
@@ -178,6 +753,9 @@ async fn load_secrets_with_strategies() -> anyhow::Result<SecretMap> {
     if let Ok(config) = Config::from_file(&config_path) {
         tracing::info!("✅ Loaded configuration from {}", config_path);
         tracing::info!("📋 Found {} strategies in config", config.strategies.len());
+        if strict {
+            check_strategy_credentials(&config).map_err(|e| anyhow::anyhow!(e))?;
+        }
 
         match build_strategies_from_config(&config) {
             Ok(strategies) => {
@@ -187,6 +765,9 @@ async fn load_secrets_with_strategies() -> anyhow::Result<SecretMap> {
                     has_manual_config = true;
                 }
             }
+            Err(e) if strict => {
+                anyhow::bail!("Failed to build strategies from config: {}", e);
+            }
             Err(e) => {
                 tracing::warn!("⚠️  Failed to build strategies from config: {}", e);
             }
@@ -197,7 +778,7 @@ async fn load_secrets_with_strategies() -> anyhow::Result<SecretMap> {
 
     // 2. Try auto-detection from database
     let auto_detect_config = AutoDetectConfig::from_env();
-    if auto_detect_config.enabled && !auto_detect_config.database_url.is_empty() {
+    if auto_detect_config.has_source() {
         match AutoDetector::new(auto_detect_config.clone()).await {
             Ok(detector) => {
                 match detector.scan().await {
@@ -211,6 +792,7 @@ async fn load_secrets_with_strategies() -> anyhow::Result<SecretMap> {
                             // Build strategies from auto-detected configs
                             match AutoDetector::build_strategies(&result.detected) {
                                 Ok(auto_strategies) => {
+                                    auto_detected = result.detected.clone();
                                     if has_manual_config {
                                         // Merge: auto-detected only adds strategies not in manual config
                                         let manual_names: std::collections::HashSet<String> =
@@ -246,27 +828,232 @@ async fn load_secrets_with_strategies() -> anyhow::Result<SecretMap> {
                         tracing::warn!("⚠️  Auto-detection scan failed: {}", e);
                     }
                 }
-                detector.close().await;
+                match auto_detect_config.refresh_interval {
+                    Some(interval) => refresh = Some((detector, interval)),
+                    None => detector.close().await,
+                }
             }
             Err(e) => {
                 tracing::debug!("Auto-detection not available: {}", e);
             }
         }
     } else {
-        tracing::debug!("Auto-detection disabled or no DATABASE_URL configured");
+        tracing::debug!(
+            "Auto-detection disabled or no DATABASE_URL or AUTO_DETECT_SOURCE configured"
+        );
     }
 
     // 3. If we have strategies, build SecretMap
-    if !all_strategies.is_empty() {
+    let secret_map = if !all_strategies.is_empty() {
         tracing::info!("✅ Total {} strategies ready", all_strategies.len());
 
-        return SecretMap::from_strategies(&all_strategies)
-            .map_err(|e| anyhow::anyhow!("Failed to create SecretMap: {}", e));
+        SecretMap::from_strategies(&all_strategies)
+            .map_err(|e| anyhow::anyhow!("Failed to create SecretMap: {}", e))
+    } else {
+        // 4. Fall back to hardcoded env vars
+        tracing::info!("💡 No strategies from config or auto-detection, trying fallback env vars");
+        load_secrets_fallback()
+    };
+
+    // 5. Merge ad-hoc secrets from SECRETS_FILE, if configured
+    Ok((
+        merge_secrets_file(secret_map)?,
+        all_strategies,
+        auto_detected,
+        refresh,
+    ))
+}
+
+/// Rebuilds the static credentials from a config file, or from auto-detection alone
+type CredentialRebuild =
+    Arc<dyn Fn(Option<&Config>) -> Result<BuiltCredentials, String> + Send + Sync>;
+
+/// Rebuild credentials as at startup
+///
+/// The config's strategies come first, then the auto-detected ones it does not
+/// name, then SECRETS_FILE; redaction settings carry over. In strict mode a
+/// config leaving a credential unset is rejected.
+fn credential_rebuild(
+    state: &AppState,
+    auto_detected: DetectedStrategies,
+    mode: proxy::ProxyMode,
+    strict: bool,
+) -> CredentialRebuild {
+    let current = state.clone();
+    Arc::new(move |config: Option<&Config>| {
+        let mut strategies = match config {
+            Some(config) => {
+                if strict {
+                    check_strategy_credentials(config)?;
+                }
+                build_strategies_from_config(config)?
+            }
+            None => Vec::new(),
+        };
+        let names: HashSet<String> = strategies.iter().map(|s| s.name().to_string()).collect();
+        let auto_strategies = AutoDetector::build_strategies(&auto_detected.read().unwrap())?;
+        strategies.extend(
+            auto_strategies
+                .into_iter()
+                .filter(|strategy| !names.contains(strategy.name())),
+        );
+
+        let secret_map = SecretMap::from_strategies(&strategies).map_err(|e| anyhow::anyhow!(e));
+        let previous = current.secret_map();
+        let secret_map = merge_secrets_file(secret_map)
+            .map_err(|e| e.to_string())?
+            .with_redaction_style(previous.redaction_style())
+            .with_redaction_mode(previous.redaction_mode());
+        let secret_map = match mode {
+            proxy::ProxyMode::SanitizeOnly => secret_map.to_sanitize_only()?,
+            _ => secret_map,
+        };
+        Ok((secret_map, strategies))
+    })
+}
+
+/// Watch the config file and apply strategy changes without a restart
+///
+/// CONFIG_RELOAD=false turns this off.
+fn watch_config_file(
+    state: &AppState,
+    rebuild: CredentialRebuild,
+) -> Option<notify::RecommendedWatcher> {
+    let enabled = std::env::var("CONFIG_RELOAD")
+        .map(|v| v != "0" && v.to_lowercase() != "false")
+        .unwrap_or(true);
+    let config_path = std::env::var("CONFIG_PATH").unwrap_or_else(|_| "config.yaml".to_string());
+    if !enabled || !Path::new(&config_path).exists() {
+        return None;
+    }
+
+    let reloader = ConfigReloader::new(&config_path, state.clone())
+        .with_builder(move |config| rebuild(Some(config)));
+
+    match reloader.watch() {
+        Ok(watcher) => {
+            tracing::info!(
+                "👀 Watching {} - strategy changes apply without a restart",
+                config_path
+            );
+            Some(watcher)
+        }
+        Err(e) => {
+            tracing::warn!("⚠️  Config reload unavailable: {}", e);
+            None
+        }
     }
+}
+
+/// Re-scan for auto-detected APIs every `interval`, rebuilding credentials when they change
+///
+/// The config file, when present, is reloaded with the new set, so a refresh
+/// and a config change build credentials the same way.
+fn refresh_auto_detected(
+    detector: AutoDetector,
+    interval: Duration,
+    state: &AppState,
+    auto_detected: DetectedStrategies,
+    rebuild: CredentialRebuild,
+) {
+    let config_path = std::env::var("CONFIG_PATH").unwrap_or_else(|_| "config.yaml".to_string());
+    let state = state.clone();
+    tracing::info!(
+        "🔁 Refreshing auto-detected APIs every {}s",
+        interval.as_secs()
+    );
 
-    // 4. Fall back to hardcoded env vars
-    tracing::info!("💡 No strategies from config or auto-detection, trying fallback env vars");
-    load_secrets_fallback()
+    detector.spawn_refresh(interval, auto_detected, move || {
+        let result = if Path::new(&config_path).exists() {
+            let rebuild = rebuild.clone();
+            ConfigReloader::new(&config_path, state.clone())
+                .with_builder(move |config| rebuild(Some(config)))
+                .reload()
+        } else {
+            rebuild(None).and_then(|(secret_map, strategies)| {
+                let count = strategies.len();
+                state
+                    .replace_credentials(secret_map, strategies, &[])
+                    .map(|()| count)
+            })
+        };
+        match result {
+            Ok(count) => tracing::info!(
+                "🔄 Applied auto-detection refresh: {} strategies in force",
+                count
+            ),
+            Err(e) => tracing::error!(
+                "Rejected auto-detection refresh, keeping the current credentials: {}",
+                e
+            ),
+        }
+    });
+}
+
+/// Merge static dummy -> real pairs from `SECRETS_FILE` into the SecretMap
+///
+/// A configured but unreadable file is a startup error rather than a
+/// silently missing secret.
+fn merge_secrets_file(secret_map: anyhow::Result<SecretMap>) -> anyhow::Result<SecretMap> {
+    let Ok(path) = std::env::var("SECRETS_FILE") else {
+        return secret_map;
+    };
+
+    let extra = sanitizer::load_secrets_file(&path).map_err(|e| anyhow::anyhow!(e))?;
+    tracing::info!(
+        "✅ Loaded {} additional secret(s) from {}",
+        extra.len(),
+        path
+    );
+
+    match secret_map {
+        Ok(map) => map
+            .with_additional_secrets(extra)
+            .map_err(|e| anyhow::anyhow!("Failed to merge secrets file: {}", e)),
+        Err(_) if !extra.is_empty() => {
+            SecretMap::new(extra).map_err(|e| anyhow::anyhow!("Failed to create SecretMap: {}", e))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+/// Load extra roots for MITM upstream connections from the PEM bundle at UPSTREAM_CA_CERT
+///
+/// For upstreams behind a private CA; the WebPKI roots stay trusted too.
+fn load_upstream_ca_certs() -> anyhow::Result<Vec<CertificateDer<'static>>> {
+    let Ok(path) = std::env::var("UPSTREAM_CA_CERT") else {
+        return Ok(Vec::new());
+    };
+
+    let pem = std::fs::read(&path)
+        .map_err(|e| anyhow::anyhow!("Cannot read UPSTREAM_CA_CERT {}: {}", path, e))?;
+    let certs = rustls_pemfile::certs(&mut &pem[..])
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| anyhow::anyhow!("Invalid UPSTREAM_CA_CERT {}: {}", path, e))?;
+    if certs.is_empty() {
+        anyhow::bail!("No certificates found in UPSTREAM_CA_CERT {}", path);
+    }
+
+    tracing::info!(
+        "🔏 Trusting {} upstream CA certificate(s) from {}",
+        certs.len(),
+        path
+    );
+    Ok(certs)
+}
+
+/// Read INSECURE_SKIP_UPSTREAM_VERIFY (accept any MITM upstream certificate; development only)
+fn load_insecure_skip_upstream_verify() -> bool {
+    let insecure = std::env::var("INSECURE_SKIP_UPSTREAM_VERIFY")
+        .map(|v| v == "1" || v.to_lowercase() == "true")
+        .unwrap_or(false);
+    if insecure {
+        tracing::warn!(
+            "⚠️  INSECURE_SKIP_UPSTREAM_VERIFY is set - MITM upstream certificates are NOT verified; \
+             never use this outside development"
+        );
+    }
+    insecure
 }
 
 /// Fallback: Load secrets from environment variables (old method)
@@ -365,7 +1152,9 @@ async fn register_secrets_handler(
         body.secrets.len(),
         source
     );
-    let n = state.register_secrets(body.secrets);
+    let n = state
+        .register_secrets(body.secrets)
+        .map_err(|e| (axum::http::StatusCode::PAYLOAD_TOO_LARGE, e))?;
     tracing::info!("✅ Registered {} secret(s) from '{}'", n, source);
     Ok((
         axum::http::StatusCode::OK,
@@ -387,8 +1176,8 @@ async fn unregister_secrets_handler(
 }
 
 async fn list_secrets_handler(State(state): State<AppState>) -> Json<SecretsResponse> {
-    let rt = state.runtime_secrets.read().unwrap();
-    let static_map = &state.secret_map;
+    let rt = state.runtime_secrets();
+    let static_map = state.secret_map();
     let mut keys: Vec<String> = static_map.dummy_keys();
     keys.extend(rt.keys().cloned());
     let count = keys.len();
@@ -450,17 +1239,19 @@ async fn health() -> &'static str {
 }
 
 /// Metrics endpoint for Prometheus
-async fn metrics_handler() -> (axum::http::StatusCode, String) {
-    match gather_metrics() {
-        Ok(metrics) => (axum::http::StatusCode::OK, metrics),
-        Err(e) => {
-            tracing::error!("Failed to gather metrics: {}", e);
-            (
-                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
-                format!("Error gathering metrics: {}", e),
-            )
-        }
-    }
+///
+/// Always answers 200: a gather failure yields a minimal payload carrying a
+/// scrape-error indicator rather than failing the scrape.
+async fn metrics_handler(headers: axum::http::HeaderMap) -> impl axum::response::IntoResponse {
+    let accept = headers
+        .get(axum::http::header::ACCEPT)
+        .and_then(|v| v.to_str().ok());
+    let (content_type, body) = render_metrics_for(accept);
+    (
+        axum::http::StatusCode::OK,
+        [(axum::http::header::CONTENT_TYPE, content_type)],
+        body,
+    )
 }
 
 #[cfg(test)]
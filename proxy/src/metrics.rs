@@ -2,10 +2,28 @@
 // Phase 6: Monitoring & Observability
 
 use prometheus::{
-    Encoder, GaugeVec, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGauge,
-    Opts, Registry, TextEncoder,
+    proto::{MetricFamily, MetricType},
+    Encoder, Gauge, GaugeVec, Histogram, HistogramOpts, HistogramVec, IntCounter, IntCounterVec,
+    IntGauge, IntGaugeVec, Opts, Registry, TextEncoder, TEXT_FORMAT,
 };
-use std::time::SystemTime;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+/// Bucket bounds of the request duration histogram
+const HTTP_REQUEST_DURATION_BUCKETS: &[f64] = &[
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0,
+];
+
+/// Full name of the request duration histogram, as exposed
+const HTTP_REQUEST_DURATION_SECONDS_NAME: &str = "slapenir_proxy_http_request_duration_seconds";
+
+/// Period the sanitization effectiveness summary covers before starting afresh
+pub const SANITIZATION_REPORT_WINDOW: Duration = Duration::from_secs(3600);
+
+/// Content type of the OpenMetrics exposition format
+pub const OPENMETRICS_FORMAT: &str = "application/openmetrics-text; version=1.0.0; charset=utf-8";
 
 lazy_static::lazy_static! {
     pub static ref REGISTRY: Registry = Registry::new();
@@ -25,7 +43,7 @@ lazy_static::lazy_static! {
         )
         .namespace("slapenir")
         .subsystem("proxy")
-        .buckets(vec![0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0]),
+        .buckets(HTTP_REQUEST_DURATION_BUCKETS.to_vec()),
         &["method", "endpoint"]
     ).expect("metric can be created");
 
@@ -62,6 +80,198 @@ lazy_static::lazy_static! {
         &["secret_type"]
     ).expect("metric can be created");
 
+    // Sanitization effectiveness: per-match attribution and its window summary
+    pub static ref RESPONSES_SCANNED_TOTAL: IntCounter = IntCounter::with_opts(
+        Opts::new(
+            "responses_scanned_total",
+            "Buffered upstream responses scanned for real secrets"
+        )
+        .namespace("slapenir")
+    ).expect("metric can be created");
+
+    pub static ref RESPONSES_REDACTED_TOTAL: IntCounter = IntCounter::with_opts(
+        Opts::new(
+            "responses_redacted_total",
+            "Buffered upstream responses that contained at least one real secret"
+        )
+        .namespace("slapenir")
+    ).expect("metric can be created");
+
+    pub static ref SECRETS_LEAKED_TOTAL: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "secrets_leaked_total",
+            "Real secrets found in upstream responses, by owning strategy"
+        )
+        .namespace("slapenir"),
+        &["strategy"]
+    ).expect("metric can be created");
+
+    pub static ref SANITIZATION_WINDOW_REDACTED_RATIO: Gauge = Gauge::with_opts(
+        Opts::new(
+            "sanitization_window_redacted_ratio",
+            "Share of scanned responses that needed redaction in the current report window"
+        )
+        .namespace("slapenir")
+    ).expect("metric can be created");
+
+    pub static ref SANITIZATION_WINDOW_REDACTIONS_PER_RESPONSE: Gauge = Gauge::with_opts(
+        Opts::new(
+            "sanitization_window_redactions_per_response",
+            "Average redactions per scanned response in the current report window"
+        )
+        .namespace("slapenir")
+    ).expect("metric can be created");
+
+    pub static ref SANITIZATION_WINDOW_REDACTIONS: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            "sanitization_window_redactions",
+            "Redactions by owning strategy in the current report window"
+        )
+        .namespace("slapenir"),
+        &["strategy"]
+    ).expect("metric can be created");
+
+    pub static ref NON_UTF8_REQUEST_BODIES_TOTAL: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "non_utf8_request_bodies_total",
+            "Requests whose body is not valid UTF-8, by policy applied"
+        )
+        .namespace("slapenir"),
+        &["policy"]
+    ).expect("metric can be created");
+
+    pub static ref MIXED_CREDENTIALS_TOTAL: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "mixed_credentials_total",
+            "Requests carrying both a dummy and an unmanaged real credential, by policy applied"
+        )
+        .namespace("slapenir"),
+        &["policy"]
+    ).expect("metric can be created");
+
+    pub static ref UNMANAGED_CREDENTIAL_DETECTED_TOTAL: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "unmanaged_credential_detected_total",
+            "Credential-shaped tokens in outbound requests that the proxy does not manage"
+        )
+        .namespace("slapenir"),
+        &["prefix"]
+    ).expect("metric can be created");
+
+    pub static ref EXCESSIVE_REDACTIONS_TOTAL: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "excessive_redactions_total",
+            "Responses rejected for exceeding the redaction limit"
+        )
+        .namespace("slapenir"),
+        &["host"]
+    ).expect("metric can be created");
+
+    pub static ref HOST_VALIDATION_BLOCKED_TOTAL: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "host_validation_blocked_total",
+            "Credentials blocked from reaching a host outside their strategy's whitelist"
+        )
+        .namespace("slapenir"),
+        &["strategy", "host"]
+    ).expect("metric can be created");
+
+    pub static ref SANITIZATION_VERIFICATION_FAILED_TOTAL: IntCounter = IntCounter::with_opts(
+        Opts::new(
+            "sanitization_verification_failed_total",
+            "Responses refused because a second sanitization pass still changed the body"
+        )
+        .namespace("slapenir")
+    ).expect("metric can be created");
+
+    pub static ref OVERSIZED_HEADER_TOTAL: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "oversized_header_total",
+            "Upstream response header values over the length limit, by action taken"
+        )
+        .namespace("slapenir"),
+        &["action"]
+    ).expect("metric can be created");
+
+    pub static ref DLP_BLOCKED_TOTAL: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "dlp_blocked_total",
+            "Outbound requests blocked by a DLP rule"
+        )
+        .namespace("slapenir"),
+        &["rule"]
+    ).expect("metric can be created");
+
+    pub static ref DLP_REDACTED_TOTAL: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "dlp_redacted_total",
+            "Outbound requests forwarded with content redacted by a DLP rule"
+        )
+        .namespace("slapenir"),
+        &["rule"]
+    ).expect("metric can be created");
+
+    pub static ref BLOCKED_RESPONSE_CONTENT_TYPE_TOTAL: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "blocked_response_content_type_total",
+            "Upstream responses blocked because their content type is not allowed"
+        )
+        .namespace("slapenir"),
+        &["content_type"]
+    ).expect("metric can be created");
+
+    pub static ref HTTP_PARSE_ERRORS_TOTAL: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "http_parse_errors_total",
+            "Intercepted HTTP messages that failed to parse, by cause"
+        )
+        .namespace("slapenir"),
+        &["kind"]
+    ).expect("metric can be created");
+
+    pub static ref QUOTA_EXCEEDED_TOTAL: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "quota_exceeded_total",
+            "Requests refused because the identity used up its byte quota"
+        )
+        .namespace("slapenir"),
+        &["identity"]
+    ).expect("metric can be created");
+
+    pub static ref CONFIG_RELOADS_TOTAL: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "config_reloads_total",
+            "Config file changes applied or rejected by hot reload"
+        )
+        .namespace("slapenir"),
+        &["outcome"]
+    ).expect("metric can be created");
+
+    pub static ref AUTO_DETECT_LAST_SCAN_AT: IntGauge = IntGauge::with_opts(
+        Opts::new(
+            "auto_detect_last_scan_at",
+            "Unix timestamp of the last completed auto-detection scan"
+        )
+        .namespace("slapenir")
+    ).expect("metric can be created");
+
+    pub static ref AUTO_DETECT_DETECTED_COUNT: IntGauge = IntGauge::with_opts(
+        Opts::new(
+            "auto_detect_detected_count",
+            "APIs detected by the last auto-detection scan"
+        )
+        .namespace("slapenir")
+    ).expect("metric can be created");
+
+    pub static ref RATE_LIMITED_TOTAL: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "rate_limited_total",
+            "Requests refused because the identity exceeded its request rate"
+        )
+        .namespace("slapenir"),
+        &["layer"]
+    ).expect("metric can be created");
+
     // mTLS metrics
     pub static ref MTLS_CONNECTIONS_TOTAL: IntCounter = IntCounter::new(
         "mtls_connections_total",
@@ -87,10 +297,56 @@ lazy_static::lazy_static! {
 
     // Certificate metrics
     pub static ref CERT_EXPIRY_TIMESTAMP: GaugeVec = GaugeVec::new(
-        Opts::new("cert_expiry_timestamp", "Certificate expiration timestamp")
+        Opts::new("cert_expiry_timestamp", "Certificate notAfter as a Unix timestamp")
+            .namespace("slapenir"),
+        &["cert"]
+    ).expect("metric can be created");
+
+    // TLS MITM metrics
+    pub static ref MITM_HANDSHAKES_TOTAL: IntCounterVec = IntCounterVec::new(
+        Opts::new("mitm_handshakes_total", "Completed MITM TLS handshakes by side")
+            .namespace("slapenir"),
+        &["side"]
+    ).expect("metric can be created");
+
+    pub static ref CERT_GENERATION_SECONDS: Histogram = Histogram::with_opts(
+        HistogramOpts::new(
+            "cert_generation_seconds",
+            "Time spent generating MITM host certificates in seconds"
+        )
+        .namespace("slapenir")
+        .buckets(vec![0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0])
+    ).expect("metric can be created");
+
+    pub static ref CERT_CACHE_HITS_TOTAL: IntCounter = IntCounter::with_opts(
+        Opts::new("cert_cache_hits_total", "Host certificate cache hits")
+            .namespace("slapenir")
+    ).expect("metric can be created");
+
+    pub static ref CERT_CACHE_MISSES_TOTAL: IntCounter = IntCounter::with_opts(
+        Opts::new("cert_cache_misses_total", "Host certificate cache misses")
+            .namespace("slapenir")
+    ).expect("metric can be created");
+
+    pub static ref MITM_HANDSHAKES_IN_FLIGHT: IntGauge = IntGauge::with_opts(
+        Opts::new(
+            "mitm_handshakes_in_flight",
+            "MITM handshakes (cert generation and TLS setup) currently in progress"
+        )
+        .namespace("slapenir")
+    ).expect("metric can be created");
+
+    pub static ref MITM_HANDSHAKES_SHED_TOTAL: IntCounter = IntCounter::with_opts(
+        Opts::new(
+            "mitm_handshakes_shed_total",
+            "MITM tunnels rejected after waiting too long for a handshake slot"
+        )
+        .namespace("slapenir")
+    ).expect("metric can be created");
+
+    pub static ref CA_ERRORS_TOTAL: IntCounter = IntCounter::with_opts(
+        Opts::new("ca_errors_total", "Failures to load or generate the MITM CA")
             .namespace("slapenir")
-            .subsystem("proxy"),
-        &["cert_name"]
     ).expect("metric can be created");
 
     // System metrics
@@ -105,9 +361,135 @@ lazy_static::lazy_static! {
     ).expect("metric can be created");
 
     // Active connections
-    pub static ref ACTIVE_CONNECTIONS: IntGauge = IntGauge::new(
-        "active_connections",
-        "Number of active connections"
+    pub static ref ACTIVE_CONNECTIONS: IntGaugeVec = IntGaugeVec::new(
+        Opts::new("active_connections", "Number of active connections by kind"),
+        &["kind"]
+    ).expect("metric can be created");
+
+    // Response buffering
+    pub static ref BUFFERED_RESPONSE_BYTES_IN_FLIGHT: IntGauge = IntGauge::with_opts(
+        Opts::new(
+            "buffered_response_bytes_in_flight",
+            "Bytes of response bodies currently buffered in memory"
+        )
+        .namespace("slapenir")
+    ).expect("metric can be created");
+
+    pub static ref UPSTREAM_RETRIES_TOTAL: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "upstream_retries_total",
+            "Upstream requests retried, by failure (connect, error or status)"
+        )
+        .namespace("slapenir"),
+        &["reason"]
+    ).expect("metric can be created");
+
+    pub static ref UPSTREAM_RETRY_OUTCOMES_TOTAL: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "upstream_retry_outcomes_total",
+            "Retried upstream requests, by outcome (recovered or exhausted)"
+        )
+        .namespace("slapenir"),
+        &["outcome"]
+    ).expect("metric can be created");
+
+    pub static ref UPSTREAM_TIMEOUTS_TOTAL: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "upstream_timeouts_total",
+            "Upstream requests that timed out, by phase (connect, response or read)"
+        )
+        .namespace("slapenir"),
+        &["phase"]
+    ).expect("metric can be created");
+
+    pub static ref UNSCANNED_BODY_TOTAL: IntCounterVec = IntCounterVec::new(
+        Opts::new(
+            "unscanned_body_total",
+            "Responses passed on without their decoded content being scanned for secrets"
+        )
+        .namespace("slapenir"),
+        &["reason"]
+    ).expect("metric can be created");
+
+    pub static ref DISTINCT_UPSTREAM_HOSTS: IntGauge = IntGauge::with_opts(
+        Opts::new(
+            "distinct_upstream_hosts",
+            "Distinct upstream hosts currently tracked as contacted"
+        )
+        .namespace("slapenir")
+    ).expect("metric can be created");
+
+    pub static ref SANITIZED_JSON_INVALID_TOTAL: IntCounter = IntCounter::with_opts(
+        Opts::new(
+            "sanitized_json_invalid_total",
+            "JSON responses that redaction left unparseable"
+        )
+        .namespace("slapenir")
+    ).expect("metric can be created");
+
+    pub static ref UPSTREAM_PROTOCOL_ERRORS_TOTAL: IntCounter = IntCounter::with_opts(
+        Opts::new(
+            "upstream_protocol_errors_total",
+            "Upstream responses that were not valid HTTP"
+        )
+        .namespace("slapenir")
+    ).expect("metric can be created");
+
+    pub static ref SECRET_BYTES_TOTAL: IntGauge = IntGauge::with_opts(
+        Opts::new(
+            "secret_bytes_total",
+            "Bytes of real secret material held in memory, static and runtime"
+        )
+        .namespace("slapenir")
+    ).expect("metric can be created");
+
+    pub static ref STRATEGY_LAST_USED_TIMESTAMP: IntGaugeVec = IntGaugeVec::new(
+        Opts::new(
+            "strategy_last_used_timestamp",
+            "Unix time each strategy's dummy was last injected"
+        )
+        .namespace("slapenir"),
+        &["strategy"]
+    ).expect("metric can be created");
+
+    pub static ref UPSTREAM_CONNECTION_CLOSE_TOTAL: IntCounter = IntCounter::with_opts(
+        Opts::new(
+            "upstream_connection_close_total",
+            "Upstream responses announcing Connection: close"
+        )
+        .namespace("slapenir")
+    ).expect("metric can be created");
+
+    pub static ref STREAMED_RESPONSES_TOTAL: IntCounter = IntCounter::with_opts(
+        Opts::new(
+            "streamed_responses_total",
+            "Responses sanitized as a stream instead of being buffered"
+        )
+        .namespace("slapenir")
+    ).expect("metric can be created");
+
+    pub static ref STREAM_LINE_TOO_LONG_TOTAL: IntCounter = IntCounter::with_opts(
+        Opts::new(
+            "stream_line_too_long_total",
+            "Streamed NDJSON/SSE responses aborted for an unterminated line over the length limit"
+        )
+        .namespace("slapenir")
+    ).expect("metric can be created");
+
+    pub static ref DEDUPLICATED_REQUESTS_TOTAL: IntCounter = IntCounter::with_opts(
+        Opts::new(
+            "deduplicated_requests_total",
+            "Requests answered with the response of an identical request already in flight"
+        )
+        .namespace("slapenir")
+    ).expect("metric can be created");
+
+    pub static ref METRICS_GATHER_FAILURES_TOTAL: IntCounter = IntCounter::with_opts(
+        Opts::new(
+            "metrics_gather_failures_total",
+            "Scrapes that fell back to the minimal payload because gathering failed"
+        )
+        .namespace("slapenir")
     ).expect("metric can be created");
 
     // Startup time for uptime calculation
@@ -123,6 +505,30 @@ pub fn init_metrics() -> Result<(), Box<dyn std::error::Error>> {
 
     REGISTRY.register(Box::new(SECRETS_SANITIZED_TOTAL.clone()))?;
     REGISTRY.register(Box::new(SECRETS_BY_TYPE.clone()))?;
+    REGISTRY.register(Box::new(EXCESSIVE_REDACTIONS_TOTAL.clone()))?;
+    REGISTRY.register(Box::new(RESPONSES_SCANNED_TOTAL.clone()))?;
+    REGISTRY.register(Box::new(RESPONSES_REDACTED_TOTAL.clone()))?;
+    REGISTRY.register(Box::new(SECRETS_LEAKED_TOTAL.clone()))?;
+    REGISTRY.register(Box::new(SANITIZATION_WINDOW_REDACTED_RATIO.clone()))?;
+    REGISTRY.register(Box::new(
+        SANITIZATION_WINDOW_REDACTIONS_PER_RESPONSE.clone(),
+    ))?;
+    REGISTRY.register(Box::new(SANITIZATION_WINDOW_REDACTIONS.clone()))?;
+    REGISTRY.register(Box::new(UNMANAGED_CREDENTIAL_DETECTED_TOTAL.clone()))?;
+    REGISTRY.register(Box::new(MIXED_CREDENTIALS_TOTAL.clone()))?;
+    REGISTRY.register(Box::new(NON_UTF8_REQUEST_BODIES_TOTAL.clone()))?;
+    REGISTRY.register(Box::new(HOST_VALIDATION_BLOCKED_TOTAL.clone()))?;
+    REGISTRY.register(Box::new(QUOTA_EXCEEDED_TOTAL.clone()))?;
+    REGISTRY.register(Box::new(RATE_LIMITED_TOTAL.clone()))?;
+    REGISTRY.register(Box::new(CONFIG_RELOADS_TOTAL.clone()))?;
+    REGISTRY.register(Box::new(AUTO_DETECT_LAST_SCAN_AT.clone()))?;
+    REGISTRY.register(Box::new(AUTO_DETECT_DETECTED_COUNT.clone()))?;
+    REGISTRY.register(Box::new(SANITIZATION_VERIFICATION_FAILED_TOTAL.clone()))?;
+    REGISTRY.register(Box::new(OVERSIZED_HEADER_TOTAL.clone()))?;
+    REGISTRY.register(Box::new(BLOCKED_RESPONSE_CONTENT_TYPE_TOTAL.clone()))?;
+    REGISTRY.register(Box::new(DLP_BLOCKED_TOTAL.clone()))?;
+    REGISTRY.register(Box::new(DLP_REDACTED_TOTAL.clone()))?;
+    REGISTRY.register(Box::new(HTTP_PARSE_ERRORS_TOTAL.clone()))?;
 
     REGISTRY.register(Box::new(MTLS_CONNECTIONS_TOTAL.clone()))?;
     REGISTRY.register(Box::new(MTLS_HANDSHAKE_DURATION_SECONDS.clone()))?;
@@ -130,9 +536,32 @@ pub fn init_metrics() -> Result<(), Box<dyn std::error::Error>> {
 
     REGISTRY.register(Box::new(CERT_EXPIRY_TIMESTAMP.clone()))?;
 
+    REGISTRY.register(Box::new(MITM_HANDSHAKES_TOTAL.clone()))?;
+    REGISTRY.register(Box::new(CERT_GENERATION_SECONDS.clone()))?;
+    REGISTRY.register(Box::new(CERT_CACHE_HITS_TOTAL.clone()))?;
+    REGISTRY.register(Box::new(CERT_CACHE_MISSES_TOTAL.clone()))?;
+    REGISTRY.register(Box::new(CA_ERRORS_TOTAL.clone()))?;
+    REGISTRY.register(Box::new(MITM_HANDSHAKES_IN_FLIGHT.clone()))?;
+    REGISTRY.register(Box::new(MITM_HANDSHAKES_SHED_TOTAL.clone()))?;
+
     REGISTRY.register(Box::new(PROXY_INFO.clone()))?;
     REGISTRY.register(Box::new(PROXY_UPTIME_SECONDS.clone()))?;
     REGISTRY.register(Box::new(ACTIVE_CONNECTIONS.clone()))?;
+    REGISTRY.register(Box::new(BUFFERED_RESPONSE_BYTES_IN_FLIGHT.clone()))?;
+    REGISTRY.register(Box::new(STREAMED_RESPONSES_TOTAL.clone()))?;
+    REGISTRY.register(Box::new(DEDUPLICATED_REQUESTS_TOTAL.clone()))?;
+    REGISTRY.register(Box::new(STREAM_LINE_TOO_LONG_TOTAL.clone()))?;
+    REGISTRY.register(Box::new(UPSTREAM_CONNECTION_CLOSE_TOTAL.clone()))?;
+    REGISTRY.register(Box::new(DISTINCT_UPSTREAM_HOSTS.clone()))?;
+    REGISTRY.register(Box::new(STRATEGY_LAST_USED_TIMESTAMP.clone()))?;
+    REGISTRY.register(Box::new(SECRET_BYTES_TOTAL.clone()))?;
+    REGISTRY.register(Box::new(SANITIZED_JSON_INVALID_TOTAL.clone()))?;
+    REGISTRY.register(Box::new(UPSTREAM_PROTOCOL_ERRORS_TOTAL.clone()))?;
+    REGISTRY.register(Box::new(UNSCANNED_BODY_TOTAL.clone()))?;
+    REGISTRY.register(Box::new(UPSTREAM_TIMEOUTS_TOTAL.clone()))?;
+    REGISTRY.register(Box::new(UPSTREAM_RETRIES_TOTAL.clone()))?;
+    REGISTRY.register(Box::new(UPSTREAM_RETRY_OUTCOMES_TOTAL.clone()))?;
+    REGISTRY.register(Box::new(METRICS_GATHER_FAILURES_TOTAL.clone()))?;
 
     // Set proxy info to 1
     PROXY_INFO.set(1);
@@ -142,10 +571,14 @@ pub fn init_metrics() -> Result<(), Box<dyn std::error::Error>> {
 
 /// Gather and encode metrics in Prometheus format
 pub fn gather_metrics() -> Result<String, Box<dyn std::error::Error>> {
+    gather_metrics_with(&TextEncoder::new())
+}
+
+/// Gather metrics and encode them with `encoder`
+pub fn gather_metrics_with<E: Encoder>(encoder: &E) -> Result<String, Box<dyn std::error::Error>> {
     // Update uptime before gathering
     update_uptime();
 
-    let encoder = TextEncoder::new();
     let metric_families = REGISTRY.gather();
     let mut buffer = Vec::new();
     encoder.encode(&metric_families, &mut buffer)?;
@@ -153,8 +586,326 @@ pub fn gather_metrics() -> Result<String, Box<dyn std::error::Error>> {
     Ok(String::from_utf8(buffer)?)
 }
 
+/// Render the `/metrics` payload, degrading instead of failing the scrape
+///
+/// If gathering fails, a minimal text-format payload with a scrape-error
+/// indicator is returned, so the scrape itself still succeeds and alerts can
+/// key off `slapenir_metrics_scrape_error`.
+pub fn render_metrics() -> String {
+    render_metrics_with(&TextEncoder::new())
+}
+
+/// Render the `/metrics` payload with `encoder`, see [`render_metrics`]
+pub fn render_metrics_with<E: Encoder>(encoder: &E) -> String {
+    match gather_metrics_with(encoder) {
+        Ok(metrics) => metrics,
+        Err(e) => {
+            tracing::error!("Failed to gather metrics: {}", e);
+            METRICS_GATHER_FAILURES_TOTAL.inc();
+            fallback_metrics()
+        }
+    }
+}
+
+/// Minimal payload served when the registry cannot be gathered
+fn fallback_metrics() -> String {
+    format!(
+        "# HELP slapenir_metrics_scrape_error Whether gathering metrics failed for this scrape\n\
+         # TYPE slapenir_metrics_scrape_error gauge\n\
+         slapenir_metrics_scrape_error 1\n\
+         # HELP slapenir_metrics_gather_failures_total Scrapes that fell back to the minimal payload because gathering failed\n\
+         # TYPE slapenir_metrics_gather_failures_total counter\n\
+         slapenir_metrics_gather_failures_total {}\n",
+        METRICS_GATHER_FAILURES_TOTAL.get()
+    )
+}
+
+/// W3C trace context of a request, attached to metrics as an exemplar
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceContext {
+    pub trace_id: String,
+    pub span_id: String,
+}
+
+impl TraceContext {
+    /// Parse a `traceparent` header value (`00-<trace id>-<span id>-<flags>`)
+    ///
+    /// All-zero ids are invalid per the spec and yield `None`.
+    pub fn from_traceparent(value: &str) -> Option<Self> {
+        let mut parts = value.trim().split('-');
+        let (_version, trace_id, span_id, _flags) =
+            (parts.next()?, parts.next()?, parts.next()?, parts.next()?);
+        let is_id = |id: &str, len: usize| {
+            id.len() == len
+                && id.bytes().all(|b| b.is_ascii_hexdigit())
+                && id.bytes().any(|b| b != b'0')
+        };
+        if !is_id(trace_id, 32) || !is_id(span_id, 16) {
+            return None;
+        }
+        Some(Self {
+            trace_id: trace_id.to_ascii_lowercase(),
+            span_id: span_id.to_ascii_lowercase(),
+        })
+    }
+}
+
+/// Latest traced observation in one histogram bucket
+#[derive(Debug, Clone)]
+struct Exemplar {
+    trace: TraceContext,
+    value: f64,
+    timestamp_secs: f64,
+}
+
+lazy_static::lazy_static! {
+    /// Request duration exemplars by (method, endpoint, bucket index)
+    static ref HTTP_REQUEST_DURATION_EXEMPLARS: Mutex<HashMap<(String, String, usize), Exemplar>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Render `/metrics` for a scraper's `Accept` header
+///
+/// Scrapers asking for OpenMetrics get it, with request duration exemplars;
+/// everyone else, and any OpenMetrics encoding failure, gets the text format.
+/// Returns the content type and the payload.
+pub fn render_metrics_for(accept: Option<&str>) -> (&'static str, String) {
+    if accept.is_some_and(|accept| accept.contains("application/openmetrics-text")) {
+        match gather_metrics_with(&OpenMetricsEncoder) {
+            Ok(metrics) => return (OPENMETRICS_FORMAT, metrics),
+            Err(e) => tracing::warn!("Failed to encode OpenMetrics, serving text: {}", e),
+        }
+    }
+    (TEXT_FORMAT, render_metrics())
+}
+
+/// Encoder for the OpenMetrics text format, with request duration exemplars
+pub struct OpenMetricsEncoder;
+
+impl Encoder for OpenMetricsEncoder {
+    fn encode<W: std::io::Write>(
+        &self,
+        metric_families: &[MetricFamily],
+        writer: &mut W,
+    ) -> prometheus::Result<()> {
+        let duration_name = HTTP_REQUEST_DURATION_SECONDS_NAME;
+        let exemplars = HTTP_REQUEST_DURATION_EXEMPLARS
+            .lock()
+            .map(|exemplars| exemplars.clone())
+            .unwrap_or_default();
+
+        for mf in metric_families {
+            let metric_type = mf.get_field_type();
+            // OpenMetrics names counter families without the `_total` sample suffix
+            let name = match metric_type {
+                MetricType::COUNTER => mf.name().trim_end_matches("_total"),
+                _ => mf.name(),
+            };
+            let type_name = match metric_type {
+                MetricType::COUNTER => "counter",
+                MetricType::GAUGE => "gauge",
+                MetricType::HISTOGRAM => "histogram",
+                MetricType::SUMMARY => "summary",
+                MetricType::UNTYPED => "unknown",
+            };
+            writeln!(writer, "# TYPE {} {}", name, type_name)?;
+            if !mf.help().is_empty() {
+                writeln!(writer, "# HELP {} {}", name, escape_openmetrics(mf.help()))?;
+            }
+
+            for m in mf.get_metric() {
+                let labels: Vec<(&str, &str)> = m
+                    .get_label()
+                    .iter()
+                    .map(|pair| (pair.name(), pair.value()))
+                    .collect();
+                match metric_type {
+                    MetricType::COUNTER => {
+                        let value = m.counter.value();
+                        write_openmetrics_sample(writer, name, "_total", &labels, value)?;
+                        writeln!(writer)?;
+                    }
+                    MetricType::GAUGE => {
+                        let value = m.gauge.value();
+                        write_openmetrics_sample(writer, name, "", &labels, value)?;
+                        writeln!(writer)?;
+                    }
+                    MetricType::HISTOGRAM => {
+                        let h = &m.histogram;
+                        let label = |key: &str| {
+                            labels
+                                .iter()
+                                .find(|(name, _)| *name == key)
+                                .map_or("", |(_, value)| *value)
+                        };
+                        let exemplar_key = |index: usize| {
+                            (
+                                label("method").to_string(),
+                                label("endpoint").to_string(),
+                                index,
+                            )
+                        };
+
+                        let mut buckets: Vec<(f64, u64)> = h
+                            .get_bucket()
+                            .iter()
+                            .map(|b| (b.upper_bound(), b.cumulative_count()))
+                            .collect();
+                        if !buckets.last().is_some_and(|(bound, _)| bound.is_infinite()) {
+                            buckets.push((f64::INFINITY, h.sample_count()));
+                        }
+                        for (index, (bound, count)) in buckets.into_iter().enumerate() {
+                            let le = format_openmetrics_value(bound);
+                            let mut bucket_labels = labels.clone();
+                            bucket_labels.push(("le", &le));
+                            write_openmetrics_sample(
+                                writer,
+                                name,
+                                "_bucket",
+                                &bucket_labels,
+                                count as f64,
+                            )?;
+                            let exemplar = (name == duration_name)
+                                .then(|| exemplars.get(&exemplar_key(index)))
+                                .flatten();
+                            if let Some(exemplar) = exemplar {
+                                write!(
+                                    writer,
+                                    " # {{trace_id=\"{}\",span_id=\"{}\"}} {} {:.3}",
+                                    exemplar.trace.trace_id,
+                                    exemplar.trace.span_id,
+                                    format_openmetrics_value(exemplar.value),
+                                    exemplar.timestamp_secs
+                                )?;
+                            }
+                            writeln!(writer)?;
+                        }
+                        let count = h.sample_count() as f64;
+                        write_openmetrics_sample(writer, name, "_count", &labels, count)?;
+                        writeln!(writer)?;
+                        write_openmetrics_sample(writer, name, "_sum", &labels, h.sample_sum())?;
+                        writeln!(writer)?;
+                    }
+                    MetricType::SUMMARY | MetricType::UNTYPED => {}
+                }
+            }
+        }
+        writeln!(writer, "# EOF")?;
+        Ok(())
+    }
+
+    fn format_type(&self) -> &str {
+        OPENMETRICS_FORMAT
+    }
+}
+
+/// Write `name{labels} value`, without the line end (an exemplar may follow)
+fn write_openmetrics_sample<W: std::io::Write>(
+    writer: &mut W,
+    name: &str,
+    suffix: &str,
+    labels: &[(&str, &str)],
+    value: f64,
+) -> std::io::Result<()> {
+    write!(writer, "{}{}", name, suffix)?;
+    if !labels.is_empty() {
+        let labels: Vec<String> = labels
+            .iter()
+            .map(|(name, value)| format!("{}=\"{}\"", name, escape_openmetrics(value)))
+            .collect();
+        write!(writer, "{{{}}}", labels.join(","))?;
+    }
+    write!(writer, " {}", format_openmetrics_value(value))
+}
+
+fn escape_openmetrics(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+fn format_openmetrics_value(value: f64) -> String {
+    if value.is_infinite() {
+        if value > 0.0 { "+Inf" } else { "-Inf" }.to_string()
+    } else if value.is_nan() {
+        "NaN".to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+/// Route templates used for the `endpoint` label when none are configured
+pub const DEFAULT_ENDPOINT_TEMPLATES: &[&str] = &[
+    "/v1/chat/completions",
+    "/v1/completions",
+    "/v1/embeddings",
+    "/v1/messages",
+    "/v1/models",
+    "/v1/models/:id",
+    "/health",
+    "/metrics",
+];
+
+/// Endpoint label for paths that match no known template
+pub const OTHER_ENDPOINT: &str = "other";
+
+/// Map a request path onto a bounded set of route templates
+///
+/// Numeric and UUID segments are treated as `:id`, and a template segment
+/// starting with `:` matches any single segment. Paths matching no template
+/// are labelled `other`, so label cardinality stays finite regardless of
+/// what clients send.
+pub fn normalize_endpoint<'a>(path: &str, templates: &'a [String]) -> &'a str {
+    let segments: Vec<&str> = path
+        .trim_end_matches('/')
+        .split('/')
+        .map(|segment| {
+            if is_id_segment(segment) {
+                ":id"
+            } else {
+                segment
+            }
+        })
+        .collect();
+
+    templates
+        .iter()
+        .find(|template| {
+            let parts: Vec<&str> = template.trim_end_matches('/').split('/').collect();
+            parts.len() == segments.len()
+                && parts
+                    .iter()
+                    .zip(&segments)
+                    .all(|(part, segment)| part.starts_with(':') || part == segment)
+        })
+        .map(String::as_str)
+        .unwrap_or(OTHER_ENDPOINT)
+}
+
+fn is_id_segment(segment: &str) -> bool {
+    let is_numeric = !segment.is_empty() && segment.bytes().all(|b| b.is_ascii_digit());
+    let is_uuid = segment.len() == 36
+        && segment.bytes().enumerate().all(|(i, b)| match i {
+            8 | 13 | 18 | 23 => b == b'-',
+            _ => b.is_ascii_hexdigit(),
+        });
+    is_numeric || is_uuid
+}
+
 /// Record HTTP request
 pub fn record_http_request(method: &str, status: u16, endpoint: &str, duration_secs: f64) {
+    record_http_request_traced(method, status, endpoint, duration_secs, None);
+}
+
+/// Record an HTTP request, keeping its trace as the duration bucket's exemplar
+pub fn record_http_request_traced(
+    method: &str,
+    status: u16,
+    endpoint: &str,
+    duration_secs: f64,
+    trace: Option<&TraceContext>,
+) {
     HTTP_REQUESTS_TOTAL
         .with_label_values(&[method, &status.to_string(), endpoint])
         .inc();
@@ -162,6 +913,27 @@ pub fn record_http_request(method: &str, status: u16, endpoint: &str, duration_s
     HTTP_REQUEST_DURATION_SECONDS
         .with_label_values(&[method, endpoint])
         .observe(duration_secs);
+
+    if let Some(trace) = trace {
+        let bucket = HTTP_REQUEST_DURATION_BUCKETS
+            .iter()
+            .position(|bound| duration_secs <= *bound)
+            .unwrap_or(HTTP_REQUEST_DURATION_BUCKETS.len());
+        let timestamp_secs = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs_f64())
+            .unwrap_or_default();
+        if let Ok(mut exemplars) = HTTP_REQUEST_DURATION_EXEMPLARS.lock() {
+            exemplars.insert(
+                (method.to_string(), endpoint.to_string(), bucket),
+                Exemplar {
+                    trace: trace.clone(),
+                    value: duration_secs,
+                    timestamp_secs,
+                },
+            );
+        }
+    }
 }
 
 /// Record secret sanitization
@@ -170,6 +942,268 @@ pub fn record_secret_sanitized(secret_type: &str) {
     SECRETS_BY_TYPE.with_label_values(&[secret_type]).inc();
 }
 
+/// Sanitization effectiveness over one report window: counts and labels only
+#[derive(Debug, Clone)]
+pub struct SanitizationWindow {
+    started: SystemTime,
+    responses_scanned: u64,
+    responses_redacted: u64,
+    by_strategy: BTreeMap<String, u64>,
+}
+
+/// JSON summary of a sanitization window, served at `/admin/sanitization-report`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SanitizationReport {
+    /// Unix time the window started
+    pub window_start: u64,
+    pub window_seconds: u64,
+    pub responses_scanned: u64,
+    pub responses_redacted: u64,
+    pub redactions: u64,
+    /// `responses_redacted / responses_scanned`
+    pub redacted_ratio: f64,
+    /// `redactions / responses_scanned`
+    pub redactions_per_response: f64,
+    /// Strategy whose secrets leaked most; ties go to the first by name
+    pub top_strategy: Option<String>,
+    pub by_strategy: BTreeMap<String, u64>,
+}
+
+impl SanitizationWindow {
+    pub fn new(started: SystemTime) -> Self {
+        Self {
+            started,
+            responses_scanned: 0,
+            responses_redacted: 0,
+            by_strategy: BTreeMap::new(),
+        }
+    }
+
+    /// Add one scanned response's redaction counts by strategy
+    pub fn record(&mut self, leaks: &BTreeMap<String, usize>) {
+        self.responses_scanned += 1;
+        if leaks.values().any(|&count| count > 0) {
+            self.responses_redacted += 1;
+        }
+        for (strategy, &count) in leaks {
+            *self.by_strategy.entry(strategy.clone()).or_insert(0) += count as u64;
+        }
+    }
+
+    /// Whether the window is over at `now`
+    pub fn expired(&self, now: SystemTime) -> bool {
+        now.duration_since(self.started)
+            .is_ok_and(|age| age >= SANITIZATION_REPORT_WINDOW)
+    }
+
+    pub fn report(&self) -> SanitizationReport {
+        let redactions: u64 = self.by_strategy.values().sum();
+        let per_response = |n: u64| match self.responses_scanned {
+            0 => 0.0,
+            scanned => n as f64 / scanned as f64,
+        };
+        let top_strategy = self
+            .by_strategy
+            .iter()
+            .filter(|(_, &count)| count > 0)
+            .max_by(|(a_name, a), (b_name, b)| a.cmp(b).then(b_name.cmp(a_name)))
+            .map(|(name, _)| name.clone());
+
+        SanitizationReport {
+            window_start: self
+                .started
+                .duration_since(SystemTime::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default(),
+            window_seconds: SANITIZATION_REPORT_WINDOW.as_secs(),
+            responses_scanned: self.responses_scanned,
+            responses_redacted: self.responses_redacted,
+            redactions,
+            redacted_ratio: per_response(self.responses_redacted),
+            redactions_per_response: per_response(redactions),
+            top_strategy,
+            by_strategy: self.by_strategy.clone(),
+        }
+    }
+}
+
+lazy_static::lazy_static! {
+    static ref SANITIZATION_WINDOW: Mutex<SanitizationWindow> =
+        Mutex::new(SanitizationWindow::new(SystemTime::now()));
+}
+
+/// The current sanitization window, restarted once it has expired
+fn current_sanitization_window() -> std::sync::MutexGuard<'static, SanitizationWindow> {
+    let mut window = SANITIZATION_WINDOW
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner());
+    let now = SystemTime::now();
+    if window.expired(now) {
+        *window = SanitizationWindow::new(now);
+        SANITIZATION_WINDOW_REDACTIONS.reset();
+    }
+    window
+}
+
+/// Record a scanned upstream response and the secrets it leaked, by strategy
+///
+/// Feeds the per-strategy counters and the current window's summary gauges.
+pub fn record_response_sanitization(leaks: &BTreeMap<String, usize>) {
+    RESPONSES_SCANNED_TOTAL.inc();
+    if leaks.values().any(|&count| count > 0) {
+        RESPONSES_REDACTED_TOTAL.inc();
+    }
+    for (strategy, &count) in leaks {
+        SECRETS_LEAKED_TOTAL
+            .with_label_values(&[strategy])
+            .inc_by(count as u64);
+    }
+
+    let mut window = current_sanitization_window();
+    window.record(leaks);
+    let report = window.report();
+    SANITIZATION_WINDOW_REDACTED_RATIO.set(report.redacted_ratio);
+    SANITIZATION_WINDOW_REDACTIONS_PER_RESPONSE.set(report.redactions_per_response);
+    for (strategy, count) in &report.by_strategy {
+        SANITIZATION_WINDOW_REDACTIONS
+            .with_label_values(&[strategy])
+            .set(*count as i64);
+    }
+}
+
+/// Summary of the current sanitization window
+pub fn sanitization_report() -> SanitizationReport {
+    current_sanitization_window().report()
+}
+
+/// Record a response rejected by the redaction anomaly guard
+pub fn record_excessive_redactions(host: &str) {
+    EXCESSIVE_REDACTIONS_TOTAL.with_label_values(&[host]).inc();
+}
+
+/// Host label for IP-literal destinations
+pub const IP_HOST_LABEL: &str = "ip";
+
+/// Reduce a destination host to a bounded label
+///
+/// Hostnames keep only their last two labels (`a.b.evil.com` -> `evil.com`)
+/// and IP literals collapse to `ip`, so an agent probing many hosts cannot
+/// blow up label cardinality.
+pub fn normalize_host_label(host: &str) -> String {
+    let host = host
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .trim_end_matches('.')
+        .to_ascii_lowercase();
+    if host.parse::<std::net::IpAddr>().is_ok() {
+        return IP_HOST_LABEL.to_string();
+    }
+    let labels: Vec<&str> = host.rsplitn(3, '.').collect();
+    match labels.as_slice() {
+        [tld, domain, ..] => format!("{}.{}", domain, tld),
+        _ => host,
+    }
+}
+
+/// Record a credential blocked from reaching a non-whitelisted host
+pub fn record_host_validation_blocked(strategy: &str, host: &str) {
+    HOST_VALIDATION_BLOCKED_TOTAL
+        .with_label_values(&[strategy, &normalize_host_label(host)])
+        .inc();
+}
+
+/// Record a response whose sanitized body failed re-verification
+pub fn record_sanitization_verification_failed() {
+    SANITIZATION_VERIFICATION_FAILED_TOTAL.inc();
+}
+
+/// Record an oversized upstream header value (`truncated` or `rejected`)
+pub fn record_oversized_header(action: &str) {
+    OVERSIZED_HEADER_TOTAL.with_label_values(&[action]).inc();
+}
+
+/// Record a response blocked by the content type allowlist
+///
+/// `content_type` is the bare media type, `none` when the response had none.
+/// Anything that is not a plausible `type/subtype` is labelled `other`.
+pub fn record_blocked_response_content_type(content_type: &str) {
+    let plausible = content_type.len() <= 64
+        && content_type.split_once('/').is_some_and(|(ty, sub)| {
+            !ty.is_empty()
+                && !sub.is_empty()
+                && content_type
+                    .bytes()
+                    .all(|b| b.is_ascii_alphanumeric() || b"/+-.".contains(&b))
+        });
+    let label = if plausible || content_type == "none" {
+        content_type
+    } else {
+        "other"
+    };
+    BLOCKED_RESPONSE_CONTENT_TYPE_TOTAL
+        .with_label_values(&[label])
+        .inc();
+}
+
+/// Record an intercepted HTTP message that failed to parse, by `ParseError::kind`
+pub fn record_http_parse_error(kind: &str) {
+    HTTP_PARSE_ERRORS_TOTAL.with_label_values(&[kind]).inc();
+}
+
+/// Record an outbound request blocked by the DLP rule named `rule`
+pub fn record_dlp_blocked(rule: &str) {
+    DLP_BLOCKED_TOTAL.with_label_values(&[rule]).inc();
+}
+
+/// Record an outbound request redacted by the DLP rule named `rule`
+pub fn record_dlp_redacted(rule: &str) {
+    DLP_REDACTED_TOTAL.with_label_values(&[rule]).inc();
+}
+
+/// Record a request refused by the per-identity byte quota
+pub fn record_quota_exceeded(identity: &str) {
+    QUOTA_EXCEEDED_TOTAL.with_label_values(&[identity]).inc();
+}
+
+/// Record a config file change handled by hot reload, by `outcome`
+pub fn record_config_reload(outcome: &str) {
+    CONFIG_RELOADS_TOTAL.with_label_values(&[outcome]).inc();
+}
+
+/// Record a completed auto-detection scan and how many APIs it detected
+pub fn record_auto_detect_scan(detected: usize) {
+    let now = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0);
+    AUTO_DETECT_LAST_SCAN_AT.set(now);
+    AUTO_DETECT_DETECTED_COUNT.set(detected as i64);
+}
+
+/// Record a request refused by the per-identity rate limit at `layer`
+pub fn record_rate_limited(layer: &str) {
+    RATE_LIMITED_TOTAL.with_label_values(&[layer]).inc();
+}
+
+/// Record a request carrying a dummy and an unmanaged credential, by `policy`
+pub fn record_mixed_credentials(policy: &str) {
+    MIXED_CREDENTIALS_TOTAL.with_label_values(&[policy]).inc();
+}
+
+/// Record a request body that is not valid UTF-8
+pub fn record_non_utf8_request_body(policy: &str) {
+    NON_UTF8_REQUEST_BODIES_TOTAL
+        .with_label_values(&[policy])
+        .inc();
+}
+
+/// Record an unmanaged credential seen in an outbound request
+pub fn record_unmanaged_credential(prefix: &str) {
+    UNMANAGED_CREDENTIAL_DETECTED_TOTAL
+        .with_label_values(&[prefix])
+        .inc();
+}
+
 /// Record mTLS connection
 pub fn record_mtls_connection(handshake_duration_secs: f64) {
     MTLS_CONNECTIONS_TOTAL.inc();
@@ -188,14 +1222,194 @@ pub fn update_cert_expiry(cert_name: &str, expiry_timestamp: i64) {
         .set(expiry_timestamp as f64);
 }
 
-/// Increment active connections
-pub fn inc_active_connections() {
-    ACTIVE_CONNECTIONS.inc();
+/// Record a completed MITM handshake
+///
+/// `side` is "client" for the agent-facing handshake and "server" for the
+/// upstream handshake.
+pub fn record_mitm_handshake(side: &str) {
+    MITM_HANDSHAKES_TOTAL.with_label_values(&[side]).inc();
+}
+
+/// Guard that counts a MITM handshake as in flight until it is dropped
+pub struct MitmHandshakeGuard {
+    _private: (),
 }
 
-/// Decrement active connections
-pub fn dec_active_connections() {
-    ACTIVE_CONNECTIONS.dec();
+impl Drop for MitmHandshakeGuard {
+    fn drop(&mut self) {
+        MITM_HANDSHAKES_IN_FLIGHT.dec();
+    }
+}
+
+/// Mark a MITM handshake in flight for the lifetime of the returned guard
+pub fn track_mitm_handshake() -> MitmHandshakeGuard {
+    MITM_HANDSHAKES_IN_FLIGHT.inc();
+    MitmHandshakeGuard { _private: () }
+}
+
+/// Record a MITM tunnel shed while queueing for a handshake slot
+pub fn record_mitm_handshake_shed() {
+    MITM_HANDSHAKES_SHED_TOTAL.inc();
+}
+
+/// Record host certificate generation time
+pub fn record_cert_generation(duration_secs: f64) {
+    CERT_GENERATION_SECONDS.observe(duration_secs);
+}
+
+/// Record a certificate cache lookup
+pub fn record_cert_cache_lookup(hit: bool) {
+    if hit {
+        CERT_CACHE_HITS_TOTAL.inc();
+    } else {
+        CERT_CACHE_MISSES_TOTAL.inc();
+    }
+}
+
+/// Record a failure to load or generate the MITM CA
+pub fn record_ca_error() {
+    CA_ERRORS_TOTAL.inc();
+}
+
+/// How a connection is handled, the `kind` label of `active_connections`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionKind {
+    /// Request proxied by `proxy_handler`
+    Plain,
+    /// CONNECT tunnel copied without inspection
+    TunnelPassthrough,
+    /// CONNECT tunnel with TLS interception
+    TunnelMitm,
+    /// CONNECT tunnel carrying plaintext that is injected and sanitized
+    TunnelInspected,
+}
+
+impl ConnectionKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Plain => "plain",
+            Self::TunnelPassthrough => "tunnel_passthrough",
+            Self::TunnelMitm => "tunnel_mitm",
+            Self::TunnelInspected => "tunnel_inspected",
+        }
+    }
+}
+
+/// Increment active connections of `kind`
+pub fn inc_active_connections(kind: ConnectionKind) {
+    ACTIVE_CONNECTIONS.with_label_values(&[kind.as_str()]).inc();
+}
+
+/// Decrement active connections of `kind`
+pub fn dec_active_connections(kind: ConnectionKind) {
+    ACTIVE_CONNECTIONS.with_label_values(&[kind.as_str()]).dec();
+}
+
+/// Guard that counts a connection as active until it is dropped
+///
+/// Keeps `active_connections` balanced on every exit path, including errors.
+pub struct ConnectionGuard {
+    kind: ConnectionKind,
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        dec_active_connections(self.kind);
+    }
+}
+
+/// Mark a connection of `kind` active for the lifetime of the returned guard
+pub fn track_connection(kind: ConnectionKind) -> ConnectionGuard {
+    inc_active_connections(kind);
+    ConnectionGuard { kind }
+}
+
+/// Guard that counts a buffered response body as in flight until dropped
+pub struct BufferedResponseGuard {
+    bytes: i64,
+}
+
+impl Drop for BufferedResponseGuard {
+    fn drop(&mut self) {
+        BUFFERED_RESPONSE_BYTES_IN_FLIGHT.sub(self.bytes);
+    }
+}
+
+/// Count `bytes` of buffered response body for the lifetime of the returned guard
+pub fn track_buffered_response(bytes: usize) -> BufferedResponseGuard {
+    let bytes = bytes as i64;
+    BUFFERED_RESPONSE_BYTES_IN_FLIGHT.add(bytes);
+    BufferedResponseGuard { bytes }
+}
+
+/// Record an upstream response announcing `Connection: close`
+pub fn record_upstream_connection_close() {
+    UPSTREAM_CONNECTION_CLOSE_TOTAL.inc();
+}
+
+/// Publish the number of distinct upstream hosts tracked
+pub fn update_distinct_upstream_hosts(count: usize) {
+    DISTINCT_UPSTREAM_HOSTS.set(count as i64);
+}
+
+/// Record a JSON response that redaction left unparseable
+pub fn record_sanitized_json_invalid() {
+    SANITIZED_JSON_INVALID_TOTAL.inc();
+}
+
+/// Record an upstream that answered with something other than HTTP
+pub fn record_upstream_protocol_error() {
+    UPSTREAM_PROTOCOL_ERRORS_TOTAL.inc();
+}
+
+/// Publish the real secret bytes held; the size only, never content
+pub fn update_secret_bytes(bytes: usize) {
+    SECRET_BYTES_TOTAL.set(bytes as i64);
+}
+
+/// Publish when `strategy` was last injected
+pub fn update_strategy_last_used(strategy: &str, timestamp: u64) {
+    STRATEGY_LAST_USED_TIMESTAMP
+        .with_label_values(&[strategy])
+        .set(timestamp as i64);
+}
+
+/// Record a request served from an identical in-flight request's response
+pub fn record_deduplicated_request() {
+    DEDUPLICATED_REQUESTS_TOTAL.inc();
+}
+
+/// Record a streamed response aborted for an overlong unterminated line
+pub fn record_stream_line_too_long() {
+    STREAM_LINE_TOO_LONG_TOTAL.inc();
+}
+
+/// Record a response that took the streaming sanitization path
+pub fn record_streamed_response() {
+    STREAMED_RESPONSES_TOTAL.inc();
+}
+
+/// Record a retry of an upstream request that failed with `reason`
+pub fn record_upstream_retry(reason: &str) {
+    UPSTREAM_RETRIES_TOTAL.with_label_values(&[reason]).inc();
+}
+
+/// Record how a retried upstream request ended: "recovered" or "exhausted"
+pub fn record_upstream_retry_outcome(outcome: &str) {
+    UPSTREAM_RETRY_OUTCOMES_TOTAL
+        .with_label_values(&[outcome])
+        .inc();
+}
+
+/// Record an upstream request that timed out while `phase` ("connect", "response"
+/// or "read", between chunks of a streamed body)
+pub fn record_upstream_timeout(phase: &str) {
+    UPSTREAM_TIMEOUTS_TOTAL.with_label_values(&[phase]).inc();
+}
+
+/// Record a response whose encoded body could not be scanned for secrets
+pub fn record_unscanned_body(reason: &str) {
+    UNSCANNED_BODY_TOTAL.with_label_values(&[reason]).inc();
 }
 
 /// Update proxy uptime
@@ -209,28 +1423,194 @@ fn update_uptime() {
 mod tests {
     use super::*;
 
+    fn leaks(pairs: &[(&str, usize)]) -> BTreeMap<String, usize> {
+        pairs
+            .iter()
+            .map(|(strategy, count)| (strategy.to_string(), *count))
+            .collect()
+    }
+
+    #[test]
+    fn test_sanitization_window_report() {
+        let mut window = SanitizationWindow::new(SystemTime::UNIX_EPOCH);
+        window.record(&leaks(&[("openai", 2)]));
+        window.record(&leaks(&[]));
+        window.record(&leaks(&[("github", 1), ("openai", 1)]));
+        window.record(&leaks(&[]));
+
+        let report = window.report();
+        assert_eq!(report.responses_scanned, 4);
+        assert_eq!(report.responses_redacted, 2);
+        assert_eq!(report.redactions, 4);
+        assert_eq!(report.redacted_ratio, 0.5);
+        assert_eq!(report.redactions_per_response, 1.0);
+        assert_eq!(report.top_strategy.as_deref(), Some("openai"));
+        assert_eq!(report.by_strategy["github"], 1);
+    }
+
+    #[test]
+    fn test_sanitization_window_empty_and_expiry() {
+        let window = SanitizationWindow::new(SystemTime::UNIX_EPOCH);
+        let report = window.report();
+        assert_eq!(report.redacted_ratio, 0.0);
+        assert_eq!(report.top_strategy, None);
+
+        // Ties go to the first strategy by name
+        let mut window = SanitizationWindow::new(SystemTime::UNIX_EPOCH);
+        window.record(&leaks(&[("zeta", 1), ("alpha", 1)]));
+        assert_eq!(window.report().top_strategy.as_deref(), Some("alpha"));
+
+        let start = SystemTime::now();
+        let window = SanitizationWindow::new(start);
+        assert!(!window.expired(start + Duration::from_secs(60)));
+        assert!(window.expired(start + SANITIZATION_REPORT_WINDOW));
+    }
+
     #[test]
     fn test_metrics_initialization() {
         let result = init_metrics();
         assert!(result.is_ok() || result.is_err()); // May fail if already initialized
     }
 
+    struct FailingEncoder;
+
+    impl Encoder for FailingEncoder {
+        fn encode<W: std::io::Write>(
+            &self,
+            _mfs: &[prometheus::proto::MetricFamily],
+            _writer: &mut W,
+        ) -> prometheus::Result<()> {
+            Err(prometheus::Error::Msg("encoder exploded".to_string()))
+        }
+
+        fn format_type(&self) -> &str {
+            "text/plain"
+        }
+    }
+
+    #[test]
+    fn test_render_metrics_degrades_on_gather_failure() {
+        let before = METRICS_GATHER_FAILURES_TOTAL.get();
+
+        let payload = render_metrics_with(&FailingEncoder);
+
+        assert!(METRICS_GATHER_FAILURES_TOTAL.get() > before);
+        assert!(payload.contains("slapenir_metrics_scrape_error 1\n"));
+        assert!(payload.contains("# TYPE slapenir_metrics_gather_failures_total counter\n"));
+        assert!(!payload.contains("encoder exploded"));
+
+        // Every line is a comment or a `name value` sample
+        for line in payload.lines() {
+            assert!(
+                line.starts_with("# ") || line.split(' ').count() == 2,
+                "not a valid exposition line: {}",
+                line
+            );
+        }
+    }
+
+    #[test]
+    fn test_render_metrics_success_has_no_error_indicator() {
+        let _ = init_metrics();
+        let payload = render_metrics();
+        assert!(payload.contains("slapenir_metrics_gather_failures_total"));
+        assert!(!payload.contains("slapenir_metrics_scrape_error"));
+    }
+
+    #[test]
+    fn test_normalize_host_label() {
+        assert_eq!(normalize_host_label("a.b.Evil.com."), "evil.com");
+        assert_eq!(normalize_host_label("evil.com"), "evil.com");
+        assert_eq!(normalize_host_label("localhost"), "localhost");
+        assert_eq!(normalize_host_label("10.1.2.3"), IP_HOST_LABEL);
+        assert_eq!(normalize_host_label("[::1]"), IP_HOST_LABEL);
+    }
+
     #[test]
     fn test_record_http_request() {
         record_http_request("GET", 200, "/health", 0.001);
         // Metric should be recorded without panic
     }
 
+    fn templates(list: &[&str]) -> Vec<String> {
+        list.iter().map(|t| t.to_string()).collect()
+    }
+
+    #[test]
+    fn test_normalize_endpoint_replaces_numeric_ids() {
+        let templates = templates(&["/v1/users/:id/keys"]);
+        assert_eq!(
+            normalize_endpoint("/v1/users/12345/keys", &templates),
+            "/v1/users/:id/keys"
+        );
+        assert_eq!(
+            normalize_endpoint("/v1/users/67890/keys/", &templates),
+            "/v1/users/:id/keys"
+        );
+    }
+
+    #[test]
+    fn test_normalize_endpoint_matches_uuid_and_named_params() {
+        let templates = templates(&["/v1/threads/:thread/runs"]);
+        assert_eq!(
+            normalize_endpoint(
+                "/v1/threads/3f2b8c1e-9a4d-4e6f-8b7a-1c2d3e4f5a6b/runs",
+                &templates
+            ),
+            "/v1/threads/:thread/runs"
+        );
+        assert_eq!(
+            normalize_endpoint("/v1/threads/thread_abc/runs", &templates),
+            "/v1/threads/:thread/runs"
+        );
+    }
+
+    #[test]
+    fn test_normalize_endpoint_unknown_is_other() {
+        let templates = templates(DEFAULT_ENDPOINT_TEMPLATES);
+        assert_eq!(
+            normalize_endpoint("/v1/chat/completions", &templates),
+            "/v1/chat/completions"
+        );
+        assert_eq!(
+            normalize_endpoint("/v1/models/42", &templates),
+            "/v1/models/:id"
+        );
+        assert_eq!(
+            normalize_endpoint("/12345/anything", &templates),
+            OTHER_ENDPOINT
+        );
+        assert_eq!(
+            normalize_endpoint("/v1/users/1/keys", &templates),
+            OTHER_ENDPOINT
+        );
+        assert_eq!(normalize_endpoint("", &templates), OTHER_ENDPOINT);
+    }
+
     #[test]
     fn test_record_secret_sanitized() {
         record_secret_sanitized("api_key");
         // Metric should be recorded without panic
     }
 
+    #[test]
+    fn test_record_mitm_metrics() {
+        let before = MITM_HANDSHAKES_TOTAL.with_label_values(&["server"]).get();
+        record_mitm_handshake("server");
+        assert!(MITM_HANDSHAKES_TOTAL.with_label_values(&["server"]).get() > before);
+
+        let hits = CERT_CACHE_HITS_TOTAL.get();
+        let misses = CERT_CACHE_MISSES_TOTAL.get();
+        record_cert_cache_lookup(true);
+        record_cert_cache_lookup(false);
+        assert!(CERT_CACHE_HITS_TOTAL.get() > hits);
+        assert!(CERT_CACHE_MISSES_TOTAL.get() > misses);
+    }
+
     #[test]
     fn test_connection_tracking() {
-        inc_active_connections();
-        dec_active_connections();
+        inc_active_connections(ConnectionKind::Plain);
+        dec_active_connections(ConnectionKind::Plain);
         // Metrics should be updated without panic
     }
 
@@ -251,4 +1631,63 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_traceparent_parsing() {
+        let trace = TraceContext::from_traceparent(
+            "00-4BF92F3577B34DA6A3CE929D0E0E4736-00f067aa0ba902b7-01",
+        )
+        .unwrap();
+        assert_eq!(trace.trace_id, "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_eq!(trace.span_id, "00f067aa0ba902b7");
+
+        assert!(TraceContext::from_traceparent("garbage").is_none());
+        assert!(TraceContext::from_traceparent(
+            "00-00000000000000000000000000000000-00f067aa0ba902b7-01"
+        )
+        .is_none());
+        assert!(TraceContext::from_traceparent("00-4bf92f35-00f067aa0ba902b7-01").is_none());
+    }
+
+    #[test]
+    fn test_openmetrics_output_includes_exemplar() {
+        let _ = init_metrics();
+        let trace = TraceContext::from_traceparent(
+            "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01",
+        )
+        .unwrap();
+        record_http_request_traced("GET", 200, "/test/exemplar", 0.03, Some(&trace));
+
+        let (content_type, body) = render_metrics_for(Some("application/openmetrics-text"));
+        assert_eq!(content_type, OPENMETRICS_FORMAT);
+        assert!(body.ends_with("# EOF\n"));
+        assert!(body.contains("# TYPE slapenir_proxy_http_requests counter"));
+
+        let bucket = body
+            .lines()
+            .find(|line| {
+                line.starts_with("slapenir_proxy_http_request_duration_seconds_bucket")
+                    && line.contains("endpoint=\"/test/exemplar\"")
+                    && line.contains("le=\"0.05\"")
+            })
+            .expect("duration bucket for the traced request");
+        assert!(
+            bucket.contains(
+                "# {trace_id=\"0af7651916cd43dd8448eb211c80319c\",span_id=\"b7ad6b7169203331\"} 0.03"
+            ),
+            "missing exemplar: {}",
+            bucket
+        );
+    }
+
+    #[test]
+    fn test_render_metrics_for_defaults_to_text_format() {
+        let _ = init_metrics();
+        let (content_type, body) = render_metrics_for(Some("text/plain"));
+        assert_eq!(content_type, TEXT_FORMAT);
+        assert!(!body.contains("# EOF"));
+
+        let (content_type, _) = render_metrics_for(None);
+        assert_eq!(content_type, TEXT_FORMAT);
+    }
 }
@@ -6,8 +6,25 @@
 // - B: Header sanitization
 // - D: Size limits via ProxyConfig
 
-use crate::proxy::{HttpClient, ProxyConfig, DEFAULT_MAX_REQUEST_SIZE, DEFAULT_MAX_RESPONSE_SIZE};
-use crate::sanitizer::SecretMap;
+use crate::client_scope::{ClientScope, ClientScopes};
+use crate::config::{ClientScopeConfig, SecurityConfig};
+use crate::dedup::InFlightRequests;
+use crate::metrics;
+use crate::mtls::ClientCertInfo;
+use crate::proxy::{
+    HttpClient, MixedCredentialPolicy, ProxyConfig, DEFAULT_MAX_REQUEST_SIZE,
+    DEFAULT_MAX_RESPONSE_SIZE,
+};
+use crate::quota::ByteQuota;
+use crate::rate_limit::RateLimiter;
+use crate::sanitizer::{
+    find_credential_candidates, redact_html_entity_secrets, sanitize_json_values, HeaderValueLimit,
+    OversizedHeader, RuntimeSecrets, SecretMap, StrategyDummies, StreamingSanitizer,
+    REDACTED_MARKER,
+};
+use crate::strategy::AuthStrategy;
+use crate::strategy_usage::StrategyUsage;
+use crate::upstream_hosts::UpstreamHosts;
 use axum::{
     body::Body,
     extract::State,
@@ -15,29 +32,82 @@ use axum::{
     middleware::Next,
     response::IntoResponse,
 };
-use std::collections::HashMap;
-use std::sync::{Arc, RwLock};
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, RwLock, RwLockReadGuard};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+
+/// Credentials built from the config file, replaced as a whole on reload
+#[derive(Clone)]
+pub struct Credentials {
+    pub secret_map: Arc<SecretMap>,
+    /// Strategies used for host whitelist validation on the MITM path
+    pub strategies: Arc<Vec<Box<dyn AuthStrategy>>>,
+    /// Dummy patterns of `strategies`, for finding dummies left after injection
+    pub strategy_dummies: Arc<StrategyDummies>,
+    /// Per-client injection scopes, when configured
+    pub client_scopes: Option<Arc<ClientScopes>>,
+}
 
 /// Shared application state containing the secret map
 #[derive(Clone)]
 pub struct AppState {
-    pub secret_map: Arc<SecretMap>,
+    /// Static credentials (replaceable at runtime); see [`AppState::secret_map`]
+    pub credentials: Arc<RwLock<Arc<Credentials>>>,
     /// Runtime secrets from repo .env files (registered at work-start)
-    pub runtime_secrets: Arc<RwLock<HashMap<String, String>>>,
+    pub runtime_secrets: Arc<RwLock<RuntimeSecrets>>,
     pub http_client: HttpClient,
     /// SECURITY FIX D: Configuration with size limits
     pub config: Option<ProxyConfig>,
+    /// Response headers stripped from proxied responses (replaceable at runtime)
+    pub blocked_headers: Arc<RwLock<Vec<String>>>,
+    /// Egress hosts the proxy refuses to contact (replaceable at runtime)
+    pub denied_hosts: Arc<RwLock<Vec<String>>>,
+    /// Slots for MITM handshakes in progress, shared by all tunnels
+    pub mitm_handshakes: Arc<Semaphore>,
+    /// Per-identity byte accounting, when a quota is configured
+    pub byte_quota: Option<Arc<ByteQuota>>,
+    /// Per-identity request rate limits, when configured
+    pub rate_limiter: Option<Arc<RateLimiter>>,
+    /// Distinct upstream hosts contacted, for egress auditing
+    pub upstream_hosts: Arc<UpstreamHosts>,
+    /// Requests in flight that identical requests may share
+    pub in_flight: Arc<InFlightRequests>,
+    /// When each static strategy was last injected, for credential hygiene
+    pub strategy_usage: Arc<StrategyUsage>,
+    /// Scope this state was narrowed to by [`AppState::for_client`]
+    pub client_scope: Option<Arc<ClientScope>>,
+    /// Rate-limit identity of the tunnel's client, charged per MITM request
+    pub client_identity: Option<String>,
+}
+
+/// Client scopes over `secret_map`; none when `scopes` is empty
+fn build_client_scopes(
+    scopes: &[ClientScopeConfig],
+    secret_map: &SecretMap,
+) -> Result<Option<Arc<ClientScopes>>, String> {
+    if scopes.is_empty() {
+        return Ok(None);
+    }
+    Ok(Some(Arc::new(ClientScopes::new(scopes, secret_map)?)))
+}
+
+/// Built-in blocked headers, the initial runtime list
+fn default_blocked_headers() -> Vec<String> {
+    SecretMap::get_blocked_headers()
+        .into_iter()
+        .map(String::from)
+        .collect()
 }
 
 impl AppState {
     /// Create a new AppState with default configuration
+    ///
+    /// The defaults are stored like any other configuration, so handlers
+    /// always see the limits actually in force.
     pub fn new(secret_map: Arc<SecretMap>, http_client: HttpClient) -> Self {
-        Self {
-            secret_map,
-            runtime_secrets: Arc::new(RwLock::new(HashMap::new())),
-            http_client,
-            config: None,
-        }
+        Self::with_config(secret_map, http_client, ProxyConfig::default())
     }
 
     /// Create an AppState with custom configuration
@@ -46,82 +116,551 @@ impl AppState {
         http_client: HttpClient,
         config: ProxyConfig,
     ) -> Self {
-        Self {
+        // At least one slot, or every MITM tunnel would be shed
+        let mitm_handshakes =
+            Arc::new(Semaphore::new(config.max_concurrent_mitm_handshakes.max(1)));
+        let runtime_secrets = RuntimeSecrets::new(config.runtime_secret_rebuild_debounce);
+        let byte_quota = config
+            .byte_quota
+            .map(|quota| Arc::new(ByteQuota::new(quota)));
+        let rate_limiter = config
+            .rate_limit
+            .map(|limit| Arc::new(RateLimiter::new(limit)));
+        let upstream_hosts = Arc::new(UpstreamHosts::new(config.max_tracked_upstream_hosts));
+        let strategy_usage = Arc::new(StrategyUsage::new(secret_map.injection_labels()));
+        let credentials = Credentials {
             secret_map,
-            runtime_secrets: Arc::new(RwLock::new(HashMap::new())),
+            strategies: Arc::new(Vec::new()),
+            strategy_dummies: Arc::new(StrategyDummies::new(&[])),
+            client_scopes: None,
+        };
+        Self {
+            credentials: Arc::new(RwLock::new(Arc::new(credentials))),
+            runtime_secrets: Arc::new(RwLock::new(runtime_secrets)),
             http_client,
             config: Some(config),
+            blocked_headers: Arc::new(RwLock::new(default_blocked_headers())),
+            denied_hosts: Arc::new(RwLock::new(Vec::new())),
+            mitm_handshakes,
+            byte_quota,
+            rate_limiter,
+            upstream_hosts,
+            in_flight: Arc::new(InFlightRequests::new()),
+            strategy_usage,
+            client_scope: None,
+            client_identity: None,
+        }
+    }
+
+    /// Attach the authentication strategies used for host validation
+    pub fn with_strategies(self, strategies: Vec<Box<dyn AuthStrategy>>) -> Self {
+        self.update_credentials(|credentials| {
+            credentials.strategy_dummies = Arc::new(StrategyDummies::new(&strategies));
+            credentials.strategies = Arc::new(strategies);
+        });
+        self
+    }
+
+    /// Seed the runtime security lists from the config file's `security` section
+    pub fn with_security(self, security: &SecurityConfig) -> Result<Self, String> {
+        if let Some(headers) = &security.blocked_headers {
+            self.set_blocked_headers(headers.clone())?;
+        }
+        self.set_denied_hosts(security.denied_hosts.clone())?;
+        self.with_client_scopes(&security.client_scopes)
+    }
+
+    /// Restrict which strategies each mTLS client may inject
+    ///
+    /// Scopes are built over the current SecretMap; an empty list leaves
+    /// every client unrestricted.
+    pub fn with_client_scopes(self, scopes: &[ClientScopeConfig]) -> Result<Self, String> {
+        let client_scopes = build_client_scopes(scopes, &self.secret_map())?;
+        self.update_credentials(|credentials| credentials.client_scopes = client_scopes);
+        Ok(self)
+    }
+
+    /// Static credentials currently in force
+    pub fn credentials(&self) -> Arc<Credentials> {
+        self.credentials.read().unwrap().clone()
+    }
+
+    /// Static SecretMap currently in force
+    pub fn secret_map(&self) -> Arc<SecretMap> {
+        self.credentials().secret_map.clone()
+    }
+
+    /// Strategies currently used for host whitelist validation
+    pub fn strategies(&self) -> Arc<Vec<Box<dyn AuthStrategy>>> {
+        self.credentials().strategies.clone()
+    }
+
+    fn update_credentials(&self, update: impl FnOnce(&mut Credentials)) {
+        let mut credentials = self.credentials.write().unwrap();
+        let mut updated = Credentials::clone(&credentials);
+        update(&mut updated);
+        *credentials = Arc::new(updated);
+    }
+
+    /// Atomically replace the static credentials, e.g. after a config change
+    ///
+    /// The secret byte limit and client scopes are checked first; on error
+    /// the current credentials stay in force. Requests already in flight
+    /// finish with the credentials they started with.
+    pub fn replace_credentials(
+        &self,
+        secret_map: SecretMap,
+        strategies: Vec<Box<dyn AuthStrategy>>,
+        scopes: &[ClientScopeConfig],
+    ) -> Result<(), String> {
+        let rt = self.runtime_secrets.read().unwrap();
+        let max_secret_bytes = self.config.as_ref().and_then(|c| c.max_secret_bytes);
+        let secret_bytes = secret_map.secret_bytes() + rt.secret_bytes();
+        if let Some(max) = max_secret_bytes.filter(|&max| secret_bytes > max) {
+            return Err(format!(
+                "Secrets would hold {} bytes, over the {}-byte limit",
+                secret_bytes, max
+            ));
+        }
+        let client_scopes = build_client_scopes(scopes, &secret_map)?;
+
+        self.strategy_usage.track(secret_map.injection_labels());
+        *self.credentials.write().unwrap() = Arc::new(Credentials {
+            secret_map: Arc::new(secret_map),
+            strategy_dummies: Arc::new(StrategyDummies::new(&strategies)),
+            strategies: Arc::new(strategies),
+            client_scopes,
+        });
+        metrics::update_secret_bytes(secret_bytes);
+        Ok(())
+    }
+
+    /// This state narrowed to what the client that presented `cert` may inject
+    ///
+    /// The credentials are fixed for the returned state, so one request never
+    /// mixes those from before and after a reload.
+    pub fn for_client(&self, cert: Option<&ClientCertInfo>) -> Self {
+        let mut credentials = self.credentials();
+        let mut client_scope = self.client_scope.clone();
+        if let Some(scopes) = &credentials.client_scopes {
+            let scope = scopes.select(cert);
+            credentials = Arc::new(Credentials {
+                secret_map: scope.secret_map.clone(),
+                ..Credentials::clone(&credentials)
+            });
+            client_scope = Some(scope);
+        }
+        Self {
+            credentials: Arc::new(RwLock::new(credentials)),
+            client_scope,
+            ..self.clone()
+        }
+    }
+
+    /// Charge one request by `identity` to the rate limit, if one is configured
+    ///
+    /// Identities are unbounded, so the refusal metric is labelled by `layer`
+    /// (`http`, `connect` or `mitm`) and the identity is only logged.
+    pub fn check_rate_limit(&self, identity: &str, layer: &str) -> Result<(), Duration> {
+        let Some(limiter) = &self.rate_limiter else {
+            return Ok(());
+        };
+        limiter.acquire(identity).inspect_err(|_| {
+            tracing::warn!(
+                "Rate limit exceeded for {} ({}), refusing request",
+                identity,
+                layer
+            );
+            metrics::record_rate_limited(layer);
+        })
+    }
+
+    /// Whether strategy `name` may be used for the current client
+    pub fn strategy_in_scope(&self, name: &str) -> bool {
+        self.client_scope
+            .as_ref()
+            .is_none_or(|scope| scope.allows(name))
+    }
+
+    /// Count a request to upstream `host` and publish the distinct host count
+    pub fn record_upstream_host(&self, host: &str) {
+        if self.upstream_hosts.capacity() == 0 {
+            return;
+        }
+        let distinct = self.upstream_hosts.record(host);
+        metrics::update_distinct_upstream_hosts(distinct);
+    }
+
+    /// Mark the strategies whose dummies are injected into `body` or `headers` as used
+    ///
+    /// Takes the request as sent by the agent; only static strategies are tracked.
+    pub fn record_strategy_use<'a>(
+        &self,
+        body: &[u8],
+        headers: impl IntoIterator<Item = (&'a str, &'a str)>,
+    ) {
+        let secret_map = self.secret_map();
+        let mut used = secret_map.injected_labels(body);
+        for (name, value) in headers {
+            used.extend(secret_map.injected_header_labels(name, value));
+        }
+        for strategy in used {
+            let at = self.strategy_usage.record(strategy);
+            metrics::update_strategy_last_used(strategy, at);
         }
     }
 
-    pub fn register_secrets(&self, secrets: HashMap<String, String>) -> usize {
+    /// Current blocked response headers
+    pub fn blocked_headers(&self) -> Vec<String> {
+        self.blocked_headers.read().unwrap().clone()
+    }
+
+    /// Atomically replace the blocked response headers
+    ///
+    /// Every entry must be a valid header name; on error nothing changes.
+    /// The sanitizer's built-in blocked headers are always stripped too.
+    pub fn set_blocked_headers(&self, headers: Vec<String>) -> Result<Vec<String>, String> {
+        let mut normalized = Vec::with_capacity(headers.len());
+        for header in headers {
+            let name = header.trim().to_ascii_lowercase();
+            axum::http::HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| format!("Invalid header name: '{}'", header))?;
+            if !normalized.contains(&name) {
+                normalized.push(name);
+            }
+        }
+
+        *self.blocked_headers.write().unwrap() = normalized.clone();
+        Ok(normalized)
+    }
+
+    /// Remove blocked headers from a response header map
+    pub fn strip_blocked_headers(&self, headers: &mut axum::http::HeaderMap) {
+        for name in self.blocked_headers.read().unwrap().iter() {
+            if headers.remove(name.as_str()).is_some() {
+                tracing::debug!("Removing blocked header: {}", name);
+            }
+        }
+    }
+
+    /// Current denied egress hosts
+    pub fn denied_hosts(&self) -> Vec<String> {
+        self.denied_hosts.read().unwrap().clone()
+    }
+
+    /// Atomically replace the denied egress hosts
+    ///
+    /// Entries are hostnames or IPs, optionally prefixed with `*.` to also
+    /// deny every subdomain. On error nothing changes.
+    pub fn set_denied_hosts(&self, hosts: Vec<String>) -> Result<Vec<String>, String> {
+        let mut normalized = Vec::with_capacity(hosts.len());
+        for host in hosts {
+            let entry = host.trim().to_ascii_lowercase();
+            let name = entry.strip_prefix("*.").unwrap_or(&entry);
+            let valid = !name.is_empty()
+                && name
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | ':'));
+            if !valid {
+                return Err(format!("Invalid host: '{}'", host));
+            }
+            if !normalized.contains(&entry) {
+                normalized.push(entry);
+            }
+        }
+
+        *self.denied_hosts.write().unwrap() = normalized.clone();
+        Ok(normalized)
+    }
+
+    /// Check whether egress to `host` is denied
+    pub fn is_host_denied(&self, host: &str) -> bool {
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        let host = host.to_ascii_lowercase();
+        self.denied_hosts
+            .read()
+            .unwrap()
+            .iter()
+            .any(|entry| match entry.strip_prefix("*.") {
+                Some(base) => host == base || host.ends_with(&format!(".{}", base)),
+                None => host == *entry,
+            })
+    }
+
+    /// Register runtime secrets, all or none
+    ///
+    /// Fails without registering anything when the real secret bytes held,
+    /// static and runtime, would exceed `max_secret_bytes`.
+    pub fn register_secrets(&self, secrets: HashMap<String, String>) -> Result<usize, String> {
         let mut rt = self.runtime_secrets.write().unwrap();
+        let max_secret_bytes = self.config.as_ref().and_then(|c| c.max_secret_bytes);
+        if let Some(max) = max_secret_bytes {
+            let total = self.secret_map().secret_bytes() + rt.secret_bytes_after(&secrets);
+            if total > max {
+                tracing::warn!(
+                    "Rejecting {} runtime secret(s): {} secret bytes would exceed the {}-byte limit",
+                    secrets.len(),
+                    total,
+                    max
+                );
+                return Err(format!(
+                    "Secrets would hold {} bytes, over the {}-byte limit",
+                    total, max
+                ));
+            }
+        }
         let count = secrets.len();
-        rt.extend(secrets);
-        count
+        for (dummy, real) in secrets {
+            rt.add_secret(dummy, real);
+        }
+        metrics::update_secret_bytes(self.secret_map().secret_bytes() + rt.secret_bytes());
+        Ok(count)
     }
 
     pub fn unregister_secrets(&self, keys: &[String]) {
         let mut rt = self.runtime_secrets.write().unwrap();
         for key in keys {
-            rt.remove(key);
+            rt.remove_secret(key);
+        }
+        metrics::update_secret_bytes(self.secret_map().secret_bytes() + rt.secret_bytes());
+    }
+
+    /// Runtime secrets for reading, rebuilding their automaton first once a burst has settled
+    pub fn runtime_secrets(&self) -> RwLockReadGuard<'_, RuntimeSecrets> {
+        if self.runtime_secrets.read().unwrap().rebuild_due() {
+            let mut rt = self.runtime_secrets.write().unwrap();
+            // Another reader may have rebuilt while we waited for the lock
+            if rt.rebuild_due() {
+                rt.rebuild();
+            }
         }
+        self.runtime_secrets.read().unwrap()
+    }
+
+    /// Whether runtime secrets may be injected for the current client
+    ///
+    /// Under client scopes only a scope with `runtime_secrets` set may; the
+    /// secrets are still redacted for every client.
+    fn runtime_injection_allowed(&self) -> bool {
+        self.client_scope
+            .as_ref()
+            .is_none_or(|scope| scope.runtime_secrets)
     }
 
     pub fn inject_all(&self, data: &str) -> String {
-        let rt = self.runtime_secrets.read().unwrap();
-        let mut result = self.secret_map.inject(data);
-        for (dummy, real) in rt.iter() {
-            result = result.replace(dummy.as_str(), real.as_str());
+        let injected = self.secret_map().inject(data);
+        if !self.runtime_injection_allowed() {
+            return injected;
         }
-        result
+        self.runtime_secrets().inject(&injected)
     }
 
-    pub fn sanitize_all(&self, data: &str) -> String {
-        let rt = self.runtime_secrets.read().unwrap();
-        let mut result = self.secret_map.sanitize(data);
-        for (_, real) in rt.iter() {
-            result = result.replace(real.as_str(), "[REDACTED]");
+    /// Inject static and runtime secrets into a body that is not UTF-8
+    pub fn inject_bytes_all(&self, data: &[u8]) -> Vec<u8> {
+        let injected = self.secret_map().inject_bytes(data);
+        if !self.runtime_injection_allowed() {
+            return injected;
         }
-        result
+        self.runtime_secrets().inject_bytes(&injected)
+    }
+
+    /// Inject static and runtime secrets into the value of header `name`
+    pub fn inject_header_all(&self, name: &str, value: &str) -> String {
+        let injected = self.secret_map().inject_header(name, value);
+        if !self.runtime_injection_allowed() {
+            return injected;
+        }
+        self.runtime_secrets().inject(&injected)
+    }
+
+    /// Inject secrets into every header value; returns whether any changed
+    pub fn inject_headers_all(&self, headers: &mut axum::http::HeaderMap) -> bool {
+        let mut changed = false;
+        for (name, value) in headers.iter_mut() {
+            let Ok(original) = value.to_str() else {
+                continue;
+            };
+            let injected = self.inject_header_all(name.as_str(), original);
+            if injected != original {
+                if let Ok(injected) = axum::http::HeaderValue::from_str(&injected) {
+                    *value = injected;
+                    changed = true;
+                }
+            }
+        }
+        changed
+    }
+
+    pub fn sanitize_all(&self, data: &str) -> String {
+        let rt = self.runtime_secrets();
+        rt.sanitize(&self.secret_map().sanitize(data))
     }
 
     pub fn sanitize_bytes_all(&self, data: &[u8]) -> std::borrow::Cow<'_, [u8]> {
-        let rt = self.runtime_secrets.read().unwrap();
-        let mut result = self.secret_map.sanitize_bytes(data).into_owned();
-        for (_, real) in rt.iter() {
-            let real_bytes = real.as_bytes();
-            let redacted = b"[REDACTED]";
-            let mut i = 0;
-            while i + real_bytes.len() <= result.len() {
-                if &result[i..i + real_bytes.len()] == real_bytes {
-                    let mut new_result = Vec::with_capacity(result.len() + redacted.len());
-                    new_result.extend_from_slice(&result[..i]);
-                    new_result.extend_from_slice(redacted);
-                    new_result.extend_from_slice(&result[i + real_bytes.len()..]);
-                    result = new_result;
-                    i += redacted.len();
-                } else {
-                    i += 1;
+        let rt = self.runtime_secrets();
+        let sanitized = self.secret_map().sanitize_bytes(data).into_owned();
+        if rt.is_empty() {
+            return std::borrow::Cow::Owned(sanitized);
+        }
+        std::borrow::Cow::Owned(rt.sanitize_bytes(&sanitized))
+    }
+
+    /// Redact static and runtime secrets from JSON string values only
+    ///
+    /// Returns `None` when `data` is not JSON.
+    pub fn sanitize_json_all(&self, data: &[u8]) -> Option<Vec<u8>> {
+        sanitize_json_values(data, |text| self.sanitize_all(text))
+    }
+
+    /// Redact static and runtime secrets hidden behind HTML character references
+    ///
+    /// Returns `None` when there was nothing to redact.
+    pub fn sanitize_html_entities_all(&self, data: &[u8]) -> Option<Vec<u8>> {
+        redact_html_entity_secrets(data, &self.real_secret_bytes_all(), |secret| {
+            self.sanitize_bytes_all(secret).into_owned()
+        })
+    }
+
+    /// Warn about credential-shaped tokens the proxy does not manage
+    ///
+    /// These are real credentials hardcoded by the agent: they bypass the
+    /// dummy system, so responses echoing them would not be sanitized. Only
+    /// the matched prefix is logged, never the value. Returns the number found.
+    pub fn check_unmanaged_credentials(&self, data: &str) -> usize {
+        let unmanaged = self.unmanaged_credentials(data);
+        for (prefix, _) in &unmanaged {
+            tracing::warn!(
+                "Unmanaged '{}' credential in outbound request; use a dummy placeholder instead",
+                prefix
+            );
+            metrics::record_unmanaged_credential(prefix);
+        }
+        unmanaged.len()
+    }
+
+    /// Credential-shaped tokens in `data` that no static or runtime secret manages
+    fn unmanaged_credentials<'a>(&self, data: &'a str) -> Vec<(&'static str, &'a str)> {
+        let rt = self.runtime_secrets();
+        find_credential_candidates(data)
+            .into_iter()
+            .filter(|(_, token)| !(self.secret_map().is_managed(token) || rt.is_managed(token)))
+            .collect()
+    }
+
+    /// Apply `policy` to a body carrying both a dummy and an unmanaged credential
+    ///
+    /// Returns the body to forward, with unmanaged credentials removed under
+    /// `strip`, or the prefix of the first one under `block`. Bodies without
+    /// a dummy are returned unchanged whatever the policy.
+    pub fn apply_mixed_credential_policy<'a>(
+        &self,
+        data: &'a str,
+        policy: MixedCredentialPolicy,
+    ) -> Result<Cow<'a, str>, &'static str> {
+        let unmanaged = self.unmanaged_credentials(data);
+        if unmanaged.is_empty() || self.inject_all(data) == data {
+            return Ok(Cow::Borrowed(data));
+        }
+
+        let prefix = unmanaged[0].0;
+        metrics::record_mixed_credentials(policy.as_str());
+        match policy {
+            MixedCredentialPolicy::Warn => {
+                tracing::warn!(
+                    "Request carries a dummy and an unmanaged '{}' credential; forwarding",
+                    prefix
+                );
+                Ok(Cow::Borrowed(data))
+            }
+            MixedCredentialPolicy::Strip => {
+                tracing::warn!(
+                    "Stripping {} unmanaged credential(s) sent alongside a dummy",
+                    unmanaged.len()
+                );
+                let mut stripped = data.to_string();
+                for (_, token) in &unmanaged {
+                    stripped = stripped.replace(token, "");
                 }
+                Ok(Cow::Owned(stripped))
+            }
+            MixedCredentialPolicy::Block => {
+                tracing::warn!(
+                    "Blocking request carrying a dummy and an unmanaged '{}' credential",
+                    prefix
+                );
+                Err(prefix)
             }
         }
-        std::borrow::Cow::Owned(result)
     }
 
-    pub fn sanitize_headers_all(&self, headers: &axum::http::HeaderMap) -> axum::http::HeaderMap {
-        let rt = self.runtime_secrets.read().unwrap();
-        let sanitized = self.secret_map.sanitize_headers(headers);
+    pub fn count_secrets_all(&self, data: &[u8]) -> usize {
+        let rt = self.runtime_secrets();
+        self.secret_map().count_secrets(data) + rt.count_secrets(data)
+    }
+
+    /// Count static secrets by strategy label, and runtime ones as `runtime`
+    pub fn count_secrets_by_label_all(&self, data: &[u8]) -> BTreeMap<String, usize> {
+        let mut counts = self.secret_map().count_secrets_by_label(data);
+        let runtime = self.runtime_secrets().count_secrets(data);
+        if runtime > 0 {
+            *counts.entry("runtime".to_string()).or_insert(0) += runtime;
+        }
+        counts
+    }
+
+    /// Build a streaming sanitizer covering static and runtime secrets
+    pub fn streaming_sanitizer(&self) -> Result<StreamingSanitizer, String> {
+        let mut pairs = self.secret_map().redaction_pairs();
+        pairs.extend(
+            self.runtime_secrets()
+                .real_secret_bytes()
+                .into_iter()
+                .map(|real| (real, REDACTED_MARKER.as_bytes().to_vec())),
+        );
+        StreamingSanitizer::redactor(&pairs)
+    }
+
+    /// Byte representations of static and runtime real secrets
+    pub fn real_secret_bytes_all(&self) -> Vec<Vec<u8>> {
+        let mut secrets = self.secret_map().real_secret_bytes().to_vec();
+        secrets.extend(self.runtime_secrets().real_secret_bytes());
+        secrets
+    }
+
+    /// Build a streaming injector covering static and runtime secrets
+    pub fn streaming_injector(&self) -> Result<StreamingSanitizer, String> {
+        let mut pairs = self.secret_map().injection_pairs();
+        if self.runtime_injection_allowed() {
+            pairs.extend(self.runtime_secrets().injection_pairs());
+        }
+        StreamingSanitizer::injector(&pairs)
+    }
+
+    /// Length limit on upstream header values (default when unconfigured)
+    pub fn header_value_limit(&self) -> HeaderValueLimit {
+        self.config
+            .as_ref()
+            .map(|config| config.header_value_limit)
+            .unwrap_or_default()
+    }
+
+    /// Bound and sanitize response headers against static and runtime secrets
+    pub fn sanitize_headers_all(
+        &self,
+        headers: &axum::http::HeaderMap,
+    ) -> Result<axum::http::HeaderMap, OversizedHeader> {
+        let bounded = self
+            .header_value_limit()
+            .bound_headers(headers, &self.real_secret_bytes_all())?;
+        let rt = self.runtime_secrets();
+        let sanitized = self.secret_map().sanitize_header_values(&bounded);
         if rt.is_empty() {
-            return sanitized;
+            return Ok(sanitized);
         }
         let mut final_headers = axum::http::HeaderMap::new();
         for (name, value) in sanitized.iter() {
             if let Ok(v) = value.to_str() {
-                let mut cleaned = v.to_string();
-                for (_, real) in rt.iter() {
-                    cleaned = cleaned.replace(real.as_str(), "[REDACTED]");
-                }
+                let cleaned = rt.sanitize(v);
                 if let Ok(hv) = axum::http::HeaderValue::from_str(&cleaned) {
                     final_headers.insert(name.clone(), hv);
                     continue;
@@ -129,7 +668,7 @@ impl AppState {
             }
             final_headers.insert(name.clone(), value.clone());
         }
-        final_headers
+        Ok(final_headers)
     }
 }
 
@@ -251,7 +790,10 @@ pub async fn sanitize_secrets_middleware(
     }
 
     // SECURITY FIX B: Sanitize response headers
-    let sanitized_headers = state.sanitize_headers_all(&parts.headers);
+    let sanitized_headers = match state.sanitize_headers_all(&parts.headers) {
+        Ok(headers) => headers,
+        Err(e) => return (StatusCode::BAD_GATEWAY, e.to_string()).into_response(),
+    };
 
     // SECURITY FIX E: Build headers with correct Content-Length
     let final_headers =
@@ -286,7 +828,155 @@ mod tests {
     #[test]
     fn test_app_state_creation() {
         let state = create_test_state();
-        assert_eq!(state.secret_map.len(), 2);
+        assert_eq!(state.secret_map().len(), 2);
+    }
+
+    #[test]
+    fn test_app_state_new_stores_default_config() {
+        let state = create_test_state();
+        let config = state.config.expect("config is always set");
+        assert_eq!(config.max_request_size, DEFAULT_MAX_REQUEST_SIZE);
+    }
+
+    #[test]
+    fn test_register_secrets_over_byte_limit_rejected() {
+        // The static secrets already hold 27 bytes
+        let state = AppState::with_config(
+            create_test_state().secret_map(),
+            crate::proxy::create_http_client(),
+            ProxyConfig {
+                max_secret_bytes: Some(40),
+                ..Default::default()
+            },
+        );
+        let secrets = HashMap::from([("DUMMY_RT".to_string(), "rt_real_0123".to_string())]);
+        assert_eq!(state.register_secrets(secrets), Ok(1));
+
+        let secrets = HashMap::from([("DUMMY_OTHER".to_string(), "rt_real_2".to_string())]);
+        assert!(state.register_secrets(secrets).is_err());
+        assert_eq!(state.runtime_secrets().len(), 1);
+        assert_eq!(state.inject_all("DUMMY_OTHER"), "DUMMY_OTHER");
+    }
+
+    #[test]
+    fn test_runtime_secrets_injected_only_for_permitted_client_scopes() {
+        use crate::config::ClientScopeConfig;
+
+        let state = AppState::with_config(
+            create_test_state().secret_map(),
+            crate::proxy::create_http_client(),
+            ProxyConfig {
+                runtime_secret_rebuild_debounce: std::time::Duration::ZERO,
+                ..Default::default()
+            },
+        )
+        .with_client_scopes(&[
+            ClientScopeConfig {
+                common_name: Some("agent-a".to_string()),
+                organization: None,
+                strategies: Vec::new(),
+                runtime_secrets: true,
+            },
+            ClientScopeConfig {
+                common_name: Some("agent-b".to_string()),
+                organization: None,
+                strategies: Vec::new(),
+                runtime_secrets: false,
+            },
+        ])
+        .unwrap();
+        let secrets = HashMap::from([("DUMMY_RT".to_string(), "rt_real_scoped".to_string())]);
+        state.register_secrets(secrets).unwrap();
+        let cert = |common_name: &str| ClientCertInfo {
+            common_name: common_name.to_string(),
+            organization: None,
+            serial: "01".to_string(),
+            valid: true,
+        };
+
+        let agent_a = state.for_client(Some(&cert("agent-a")));
+        assert_eq!(agent_a.inject_all("k=DUMMY_RT"), "k=rt_real_scoped");
+        assert_eq!(
+            agent_a.inject_header_all("x-api-key", "DUMMY_RT"),
+            "rt_real_scoped"
+        );
+
+        for client in [
+            state.for_client(Some(&cert("agent-b"))),
+            state.for_client(None),
+        ] {
+            assert_eq!(client.inject_all("k=DUMMY_RT"), "k=DUMMY_RT");
+            assert_eq!(client.inject_bytes_all(b"k=DUMMY_RT"), b"k=DUMMY_RT");
+            assert_eq!(
+                client.inject_header_all("x-api-key", "DUMMY_RT"),
+                "DUMMY_RT"
+            );
+            let mut injector = client.streaming_injector().unwrap();
+            let mut streamed = injector.push(b"k=DUMMY_RT");
+            streamed.extend(injector.finish());
+            assert_eq!(streamed, b"k=DUMMY_RT");
+            // Still redacted for every client
+            assert_eq!(client.sanitize_all("rt_real_scoped"), "[REDACTED]");
+        }
+    }
+
+    #[test]
+    fn test_denied_hosts_matching() {
+        let state = create_test_state();
+        assert!(!state.is_host_denied("evil.com"));
+
+        state
+            .set_denied_hosts(vec!["Evil.com".to_string(), "*.leak.io".to_string()])
+            .unwrap();
+        assert!(state.is_host_denied("evil.com"));
+        assert!(!state.is_host_denied("sub.evil.com"));
+        assert!(state.is_host_denied("leak.io"));
+        assert!(state.is_host_denied("a.b.leak.io"));
+        assert!(!state.is_host_denied("notleak.io"));
+    }
+
+    #[test]
+    fn test_invalid_lists_rejected_without_change() {
+        let state = create_test_state();
+        state
+            .set_denied_hosts(vec!["evil.com".to_string()])
+            .unwrap();
+
+        assert!(state
+            .set_denied_hosts(vec!["ok.com".to_string(), "http://x/".to_string()])
+            .is_err());
+        assert_eq!(state.denied_hosts(), vec!["evil.com".to_string()]);
+
+        assert!(state
+            .set_blocked_headers(vec!["bad header".to_string()])
+            .is_err());
+        assert!(state
+            .blocked_headers()
+            .contains(&"x-debug-token".to_string()));
+    }
+
+    #[test]
+    fn test_runtime_secrets_rebuilt_once_burst_settles() {
+        let state = AppState::with_config(
+            create_test_state().secret_map(),
+            crate::proxy::create_http_client(),
+            ProxyConfig {
+                runtime_secret_rebuild_debounce: std::time::Duration::ZERO,
+                ..Default::default()
+            },
+        );
+        let mut secrets = HashMap::new();
+        secrets.insert("DUMMY_RT_A".to_string(), "rt_real_a".to_string());
+        secrets.insert("DUMMY_RT_B".to_string(), "rt_real_b".to_string());
+        state.register_secrets(secrets).unwrap();
+        assert_eq!(state.runtime_secrets.read().unwrap().rebuild_count(), 0);
+
+        assert_eq!(
+            state.inject_all("DUMMY_RT_A DUMMY_TOKEN"),
+            "rt_real_a real_secret_123"
+        );
+        assert_eq!(state.runtime_secrets().rebuild_count(), 1);
+        assert_eq!(state.sanitize_all("rt_real_b"), "[REDACTED]");
     }
 
     #[test]
@@ -295,7 +985,7 @@ mod tests {
         let state2 = state1.clone();
 
         // Both should reference the same SecretMap
-        assert_eq!(state1.secret_map.len(), state2.secret_map.len());
+        assert_eq!(state1.secret_map().len(), state2.secret_map().len());
     }
 
     #[test]
@@ -307,6 +997,7 @@ mod tests {
         let config = ProxyConfig {
             max_request_size: 1024,
             max_response_size: 2048,
+            ..Default::default()
         };
 
         let state = AppState::with_config(
@@ -325,7 +1016,7 @@ mod tests {
     fn test_secret_injection_logic() {
         let state = create_test_state();
         let input = "Authorization: Bearer DUMMY_TOKEN";
-        let output = state.secret_map.inject(input);
+        let output = state.secret_map().inject(input);
         assert_eq!(output, "Authorization: Bearer real_secret_123");
     }
 
@@ -333,7 +1024,7 @@ mod tests {
     fn test_secret_sanitization_logic() {
         let state = create_test_state();
         let input = "Response: {token: 'real_secret_123'}";
-        let output = state.secret_map.sanitize(input);
+        let output = state.secret_map().sanitize(input);
         assert_eq!(output, "Response: {token: '[REDACTED]'}");
         assert!(!output.contains("real_secret_123"));
     }
@@ -346,8 +1037,7 @@ mod tests {
         input.extend_from_slice(b"real_secret_123");
         input.extend_from_slice(b" more data");
 
-        let output = state.secret_map.sanitize_bytes(&input);
-        let output_vec = output.into_owned();
+        let output_vec = state.secret_map().sanitize_bytes(&input).into_owned();
 
         // Secret should be redacted
         assert!(!output_vec.windows(15).any(|w| w == b"real_secret_123"));
@@ -360,7 +1050,7 @@ mod tests {
         let sanitized = "Response: [REDACTED]";
 
         // Sanitizing again should return the same thing
-        let verification = state.secret_map.sanitize(sanitized);
+        let verification = state.secret_map().sanitize(sanitized);
         assert_eq!(verification, sanitized);
     }
 
@@ -368,7 +1058,7 @@ mod tests {
     fn test_multiple_secrets_in_request() {
         let state = create_test_state();
         let input = "Token: DUMMY_TOKEN, Key: DUMMY_KEY";
-        let output = state.secret_map.inject(input);
+        let output = state.secret_map().inject(input);
         assert!(output.contains("real_secret_123"));
         assert!(output.contains("real_key_456"));
         assert!(!output.contains("DUMMY_TOKEN"));
@@ -379,7 +1069,7 @@ mod tests {
     fn test_multiple_secrets_in_response() {
         let state = create_test_state();
         let input = "Token: real_secret_123, Key: real_key_456";
-        let output = state.secret_map.sanitize(input);
+        let output = state.secret_map().sanitize(input);
         assert_eq!(output, "Token: [REDACTED], Key: [REDACTED]");
         assert!(!output.contains("real_secret_123"));
         assert!(!output.contains("real_key_456"));
@@ -398,7 +1088,7 @@ mod tests {
         headers.insert("x-debug-token", HeaderValue::from_static("secret_value"));
         headers.insert("content-type", HeaderValue::from_static("application/json"));
 
-        let sanitized = state.secret_map.sanitize_headers(&headers);
+        let sanitized = state.secret_map().sanitize_headers(&headers);
 
         // Blocked header should be removed
         assert!(!sanitized.contains_key("x-debug-token"));
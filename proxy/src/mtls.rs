@@ -1,22 +1,38 @@
 // SLAPENIR mTLS Module
 // Implements mutual TLS authentication for proxy-agent communication
 
+use crate::config::NetworkConfig;
+use crate::{metrics, socket};
 use axum::{
     body::Body,
     extract::ConnectInfo,
     http::{Request, StatusCode},
     middleware::Next,
     response::Response,
+    Router,
 };
+use hyper::body::Incoming;
+use hyper_util::rt::{TokioExecutor, TokioIo};
+use hyper_util::server::conn::auto;
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::net::TcpListener;
+use tokio::sync::Semaphore;
 use tokio_rustls::rustls::{
     pki_types::CertificateDer, server::WebPkiClientVerifier, ClientConfig, RootCertStore,
-    ServerConfig,
+    ServerConfig, ServerConnection,
 };
 use tokio_rustls::TlsAcceptor;
+use tower::ServiceExt;
 use tracing::{debug, info, warn};
 
+/// Time a client gets to complete the TLS handshake
+pub const DEFAULT_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Handshakes in progress at once before new connections wait to be accepted
+pub const DEFAULT_MAX_CONCURRENT_HANDSHAKES: usize = 256;
+
 /// mTLS configuration for the proxy
 #[derive(Clone)]
 pub struct MtlsConfig {
@@ -26,6 +42,14 @@ pub struct MtlsConfig {
     pub client_config: Arc<ClientConfig>,
     /// Whether to enforce mTLS (false for development)
     pub enforce: bool,
+    /// Path of the CA certificate used to verify clients
+    pub ca_cert_path: String,
+    /// Path of the server certificate
+    pub server_cert_path: String,
+    /// Handshakes not complete within this are dropped
+    pub handshake_timeout: Duration,
+    /// Handshakes in progress at once
+    pub max_concurrent_handshakes: usize,
 }
 
 impl MtlsConfig {
@@ -70,18 +94,17 @@ impl MtlsConfig {
         let server_key =
             rustls_pemfile::private_key(&mut &server_key_pem[..])?.ok_or("No private key found")?;
 
-        // Configure client verification
+        // Configure client verification: a certificate is always requested,
+        // but without enforcement clients may complete the handshake without one
+        let verifier = WebPkiClientVerifier::builder(Arc::new(root_store.clone()));
         let client_verifier = if enforce {
             info!("mTLS enforcement enabled - clients must present valid certificates");
-            WebPkiClientVerifier::builder(Arc::new(root_store.clone()))
-                .build()
-                .map_err(|e| format!("Failed to build client verifier: {}", e))?
+            verifier.build()
         } else {
             warn!("mTLS enforcement disabled - accepting connections without client certificates");
-            WebPkiClientVerifier::builder(Arc::new(root_store.clone()))
-                .build()
-                .map_err(|e| format!("Failed to build client verifier: {}", e))?
-        };
+            verifier.allow_unauthenticated().build()
+        }
+        .map_err(|e| format!("Failed to build client verifier: {}", e))?;
 
         // Create server configuration
         let server_config = ServerConfig::builder()
@@ -99,36 +122,147 @@ impl MtlsConfig {
             server_config: Arc::new(server_config),
             client_config: Arc::new(client_config),
             enforce,
+            ca_cert_path: ca_cert_path.to_string(),
+            server_cert_path: server_cert_path.to_string(),
+            handshake_timeout: DEFAULT_HANDSHAKE_TIMEOUT,
+            max_concurrent_handshakes: DEFAULT_MAX_CONCURRENT_HANDSHAKES,
         })
     }
 
+    /// Bound each handshake's duration and the number in progress at once
+    pub fn with_handshake_limits(mut self, timeout: Duration, max_concurrent: usize) -> Self {
+        self.handshake_timeout = timeout;
+        self.max_concurrent_handshakes = max_concurrent;
+        self
+    }
+
     /// Create a TLS acceptor for incoming connections
     pub fn acceptor(&self) -> TlsAcceptor {
         TlsAcceptor::from(self.server_config.clone())
     }
 }
 
+/// Serve `app` over TLS, requesting a client certificate on every handshake
+///
+/// Clients are verified against the configured CA during the handshake, so
+/// with enforcement on a connection without a valid certificate is closed
+/// with a TLS alert before any request is read. The verified certificate is
+/// attached to each request as [`ClientCertInfo`], alongside the peer
+/// address as `ConnectInfo`.
+///
+/// A handshake must finish within the configured timeout. At most
+/// `max_concurrent_handshakes` run at once; beyond that, new connections
+/// wait in the listen backlog.
+pub async fn serve(
+    listener: TcpListener,
+    app: Router,
+    config: MtlsConfig,
+    network: NetworkConfig,
+) -> std::io::Result<()> {
+    let acceptor = config.acceptor();
+    let handshake_timeout = config.handshake_timeout;
+    // At least one slot, or no connection would ever be accepted
+    let handshakes = Arc::new(Semaphore::new(config.max_concurrent_handshakes.max(1)));
+
+    loop {
+        let handshake_slot = handshakes
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("handshake semaphore is never closed");
+        let (stream, addr) = match listener.accept().await {
+            Ok(conn) => conn,
+            Err(e) => {
+                // Usually fd exhaustion; back off rather than spin
+                warn!("Failed to accept connection: {}", e);
+                tokio::time::sleep(Duration::from_secs(1)).await;
+                continue;
+            }
+        };
+        socket::tune_socket(&stream, &network);
+
+        let acceptor = acceptor.clone();
+        let app = app.clone();
+        tokio::spawn(async move {
+            let started = Instant::now();
+            let tls_stream =
+                match tokio::time::timeout(handshake_timeout, acceptor.accept(stream)).await {
+                    Ok(Ok(tls_stream)) => tls_stream,
+                    Ok(Err(e)) => {
+                        warn!("mTLS handshake with {} failed: {}", addr, e);
+                        metrics::record_mtls_error("handshake");
+                        return;
+                    }
+                    Err(_) => {
+                        warn!(
+                            "mTLS handshake with {} timed out after {:?}",
+                            addr, handshake_timeout
+                        );
+                        metrics::record_mtls_error("handshake_timeout");
+                        return;
+                    }
+                };
+            drop(handshake_slot);
+            metrics::record_mtls_connection(started.elapsed().as_secs_f64());
+
+            let cert_info = client_cert_info(tls_stream.get_ref().1);
+
+            let service = hyper::service::service_fn(move |mut request: Request<Incoming>| {
+                request.extensions_mut().insert(ConnectInfo(addr));
+                if let Some(cert_info) = &cert_info {
+                    request.extensions_mut().insert(cert_info.clone());
+                }
+                app.clone().oneshot(request.map(Body::new))
+            });
+
+            if let Err(e) = auto::Builder::new(TokioExecutor::new())
+                .serve_connection_with_upgrades(TokioIo::new(tls_stream), service)
+                .await
+            {
+                debug!("mTLS connection from {} closed with error: {}", addr, e);
+            }
+        });
+    }
+}
+
+/// Identity of the client authenticated on `connection`
+///
+/// `None` when the client sent no certificate, which the handshake only
+/// allows when mTLS is not enforced.
+pub fn client_cert_info(connection: &ServerConnection) -> Option<ClientCertInfo> {
+    connection
+        .peer_certificates()
+        .and_then(|certs| certs.first())
+        .and_then(ClientCertInfo::from_der)
+}
+
 /// Middleware to verify client certificates
 ///
-/// This middleware extracts and validates the client certificate from the connection.
-/// If mTLS is enforced and no valid certificate is present, the request is rejected.
+/// Runs before routing on connections served by [`serve`]. The handshake has
+/// already verified any certificate presented; this rejects requests that
+/// arrive without one when mTLS is enforced.
 pub async fn verify_client_cert(
     ConnectInfo(addr): ConnectInfo<SocketAddr>,
     mtls_config: axum::Extension<MtlsConfig>,
     request: Request<Body>,
     next: Next,
 ) -> Result<Response, StatusCode> {
-    debug!("mTLS verification for client: {}", addr);
-
-    // In a real implementation, we would extract the client certificate from the TLS session
-    // For now, we'll check if enforcement is enabled
-    if mtls_config.enforce {
-        // TODO(#5): Extract client certificate from TLS connection for enhanced validation
-        // This requires deeper integration with the TLS layer
-        debug!("Client certificate verification requested");
-
-        // For development, we'll allow through but log
-        warn!("mTLS enforcement enabled but certificate extraction not yet implemented");
+    match request.extensions().get::<ClientCertInfo>() {
+        Some(cert) if cert.valid => {
+            debug!(
+                "mTLS client {} authenticated as '{}'",
+                addr, cert.common_name
+            );
+        }
+        _ if mtls_config.enforce => {
+            warn!(
+                "Rejecting request from {} without a verified client certificate",
+                addr
+            );
+            metrics::record_mtls_error("missing_client_cert");
+            return Err(StatusCode::FORBIDDEN);
+        }
+        _ => debug!("mTLS client {} presented no certificate", addr),
     }
 
     Ok(next.run(request).await)
@@ -147,6 +281,32 @@ pub struct ClientCertInfo {
     pub valid: bool,
 }
 
+impl ClientCertInfo {
+    /// Read the identity from a certificate the TLS handshake has verified
+    pub fn from_der(cert: &CertificateDer<'_>) -> Option<Self> {
+        let (_, parsed) = x509_parser::parse_x509_certificate(cert.as_ref()).ok()?;
+        let subject = parsed.subject();
+        let common_name = subject
+            .iter_common_name()
+            .next()
+            .and_then(|cn| cn.as_str().ok())
+            .unwrap_or_default()
+            .to_string();
+        let organization = subject
+            .iter_organization()
+            .next()
+            .and_then(|o| o.as_str().ok())
+            .map(str::to_string);
+
+        Some(Self {
+            common_name,
+            organization,
+            serial: parsed.raw_serial_as_string(),
+            valid: true,
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -174,4 +334,23 @@ mod tests {
         assert_eq!(cert_info.common_name, "agent-01");
         assert!(cert_info.valid);
     }
+
+    #[test]
+    fn test_client_cert_info_from_der() {
+        let mut params = rcgen::CertificateParams::new(vec![]);
+        params
+            .distinguished_name
+            .push(rcgen::DnType::CommonName, "agent-02");
+        params.serial_number = Some(rcgen::SerialNumber::from_slice(&[0x01, 0xff]));
+        let cert = rcgen::Certificate::from_params(params).unwrap();
+        let der = CertificateDer::from(cert.serialize_der().unwrap());
+
+        let cert_info = ClientCertInfo::from_der(&der).unwrap();
+        assert_eq!(cert_info.common_name, "agent-02");
+        assert_eq!(cert_info.organization, None);
+        assert_eq!(cert_info.serial, "01:ff");
+        assert!(cert_info.valid);
+
+        assert!(ClientCertInfo::from_der(&CertificateDer::from(vec![0u8; 4])).is_none());
+    }
 }
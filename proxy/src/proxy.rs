@@ -7,37 +7,395 @@
 // - D: Memory limits via ProxyConfig
 // - E: Content-Length recalculation
 
+use crate::config::{Config, NetworkConfig, RouteConfig};
+use crate::content_encoding;
+use crate::dedup::{self, Flight, Outcome, SharedResponse};
+use crate::dlp::{apply_dlp_rules, check_dlp_rules, DlpAction, DlpRule};
 use crate::metrics;
 use crate::middleware::AppState;
+use crate::mtls::ClientCertInfo;
+use crate::quota::{ByteQuota, ByteQuotaConfig};
+use crate::rate_limit::RateLimitConfig;
+use crate::sanitizer::{
+    HeaderValueLimit, OversizedHeaderAction, StreamingSanitizer, DEFAULT_RUNTIME_REBUILD_DEBOUNCE,
+};
+use crate::strategy::{detect_and_validate_strategies, SecurityError};
+use crate::trace_propagation::{self, TracePropagation};
+use crate::upstream_hosts::DEFAULT_MAX_TRACKED_UPSTREAM_HOSTS;
 use axum::{
-    body::Body,
-    extract::{Request, State},
-    http::{HeaderMap, HeaderValue, Method, StatusCode, Uri},
+    body::{Body, Bytes},
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode, Uri},
     response::{IntoResponse, Response},
 };
+use futures::StreamExt;
 use hyper_rustls::HttpsConnector;
 use hyper_util::{
     client::legacy::{connect::HttpConnector, Client},
-    rt::TokioExecutor,
+    rt::{TokioExecutor, TokioIo},
 };
-use std::time::Instant;
+use rustls::pki_types::CertificateDer;
+use std::borrow::Cow;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
 use thiserror::Error;
 
 /// Default maximum request body size (10 MB)
 pub const DEFAULT_MAX_REQUEST_SIZE: usize = 10 * 1024 * 1024;
 /// Default maximum response body size (100 MB)
 pub const DEFAULT_MAX_RESPONSE_SIZE: usize = 100 * 1024 * 1024;
+/// Default maximum number of secrets redacted from a single response
+pub const DEFAULT_MAX_REDACTIONS_PER_RESPONSE: usize = 1000;
+/// Default time a client may take to send its request body
+pub const DEFAULT_REQUEST_BODY_TIMEOUT: Duration = Duration::from_secs(30);
+/// Default number of MITM handshakes allowed in progress at once
+pub const DEFAULT_MAX_CONCURRENT_MITM_HANDSHAKES: usize = 32;
+/// Default time a tunnel may queue for a handshake slot before it is shed
+pub const DEFAULT_MITM_HANDSHAKE_QUEUE_TIMEOUT: Duration = Duration::from_secs(5);
+/// Default number of upstream redirects followed for one request
+pub const DEFAULT_MAX_REDIRECTS: usize = 5;
+/// Default time allowed to establish an upstream TCP connection
+pub const DEFAULT_UPSTREAM_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+/// Default time allowed for an upstream to respond, redirects included
+pub const DEFAULT_UPSTREAM_RESPONSE_TIMEOUT: Duration = Duration::from_secs(300);
+/// Default longest silence between chunks of a streamed upstream response
+pub const DEFAULT_UPSTREAM_READ_TIMEOUT: Duration = Duration::from_secs(120);
+/// Default delay before the first retry of a failed upstream request
+pub const DEFAULT_RETRY_BASE_DELAY: Duration = Duration::from_millis(100);
+/// Default longest unterminated NDJSON/SSE line before a stream is aborted (1MB)
+pub const DEFAULT_MAX_STREAM_LINE_LENGTH: usize = 1024 * 1024;
+/// Default CONNECT ports whose TLS is intercepted for injection and sanitization
+pub const DEFAULT_INTERCEPT_PORTS: [u16; 2] = [443, 8443];
+
+/// Default size limit for decoding HTML entities in a response (1MB)
+pub const DEFAULT_MAX_HTML_ENTITY_DECODE_SIZE: usize = 1024 * 1024;
+/// Default listen address (all interfaces, port 3000)
+pub const DEFAULT_LISTEN_ADDR: SocketAddr =
+    SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 3000));
+
+/// Product token identifying the proxy to upstreams
+pub const PROXY_PRODUCT_TOKEN: &str = concat!("slapenir/", env!("CARGO_PKG_VERSION"));
+/// Header carrying the proxy version when identifying via a dedicated header
+pub const VIA_SLAPENIR_HEADER: &str = "x-via-slapenir";
+
+/// Retries of transiently failed upstream requests
+///
+/// Connection failures before the request was sent are retried for any
+/// method; failures after it was sent, and 502/503 responses, only for GET
+/// and HEAD, which are safe to repeat. Off by default.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts per request, the first included; 1 disables retries
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled for each one after it
+    pub base_delay: Duration,
+    /// Randomize each delay between half and all of its value
+    pub jitter: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 1,
+            base_delay: DEFAULT_RETRY_BASE_DELAY,
+            jitter: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Delay before retry number `retry` (1-based)
+    pub fn delay(&self, retry: u32) -> Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(1 << retry.saturating_sub(1).min(16));
+        if !self.jitter {
+            return delay;
+        }
+        // Agents retrying the same outage together spread out
+        let random = std::hash::BuildHasher::hash_one(&std::hash::RandomState::new(), retry);
+        delay / 2 + delay.mul_f64((random % 1000) as f64 / 2000.0)
+    }
+}
+
+/// How the proxy identifies itself in upstream requests
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ProxyIdentification {
+    /// Forward requests without identifying the proxy
+    #[default]
+    Off,
+    /// Append `slapenir/<version>` to the agent's `User-Agent`
+    UserAgent,
+    /// Add an `X-Via-Slapenir: <version>` header
+    Header,
+}
+
+impl ProxyIdentification {
+    /// Parse a setting value: `off`, `user-agent` or `header`
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "off" | "" => Some(Self::Off),
+            "user-agent" => Some(Self::UserAgent),
+            "header" => Some(Self::Header),
+            _ => None,
+        }
+    }
+
+    /// Header to set on the upstream request, given the agent's `User-Agent`
+    ///
+    /// The agent's own value is kept; the proxy token is only appended.
+    pub fn header(&self, user_agent: Option<&str>) -> Option<(&'static str, String)> {
+        match self {
+            Self::Off => None,
+            Self::UserAgent => {
+                let value = match user_agent.map(str::trim) {
+                    Some(ua) if ua.ends_with(PROXY_PRODUCT_TOKEN) => ua.to_string(),
+                    Some(ua) if !ua.is_empty() => format!("{} {}", ua, PROXY_PRODUCT_TOKEN),
+                    _ => PROXY_PRODUCT_TOKEN.to_string(),
+                };
+                Some(("user-agent", value))
+            }
+            Self::Header => Some((VIA_SLAPENIR_HEADER, env!("CARGO_PKG_VERSION").to_string())),
+        }
+    }
+}
+
+/// What the proxy does with secrets in outbound traffic
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ProxyMode {
+    /// Inject real credentials for dummies, redact real ones from responses
+    #[default]
+    Inject,
+    /// Egress DLP guard: never inject, redact known secrets in both directions
+    SanitizeOnly,
+}
+
+impl ProxyMode {
+    /// Parse a setting value: `inject` or `sanitize_only`
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "inject" | "" => Some(Self::Inject),
+            "sanitize_only" | "sanitize-only" => Some(Self::SanitizeOnly),
+            _ => None,
+        }
+    }
+}
+
+/// What happens to a request carrying a dummy and an unmanaged real credential
+///
+/// Injection replaces the dummy but not the real credential, which would
+/// reach upstream untracked and could leak back unsanitized.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum MixedCredentialPolicy {
+    /// Forward unchanged, with a warning and metric
+    #[default]
+    Warn,
+    /// Remove the unmanaged credential and forward
+    Strip,
+    /// Refuse the request with 403
+    Block,
+}
+
+impl MixedCredentialPolicy {
+    /// Parse a setting value: `warn`, `strip` or `block`
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "warn" | "" => Some(Self::Warn),
+            "strip" => Some(Self::Strip),
+            "block" => Some(Self::Block),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Warn => "warn",
+            Self::Strip => "strip",
+            Self::Block => "block",
+        }
+    }
+}
+
+/// What happens to a request whose body is not valid UTF-8
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum NonUtf8BodyPolicy {
+    /// Refuse the request with 400
+    Reject,
+    /// Replace dummies byte for byte, leaving the rest of the body untouched
+    #[default]
+    InjectBytes,
+    /// Forward the body unmodified, with a warning
+    Passthrough,
+}
+
+impl NonUtf8BodyPolicy {
+    /// Parse a setting value: `reject`, `inject_bytes` or `passthrough`
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "reject" => Some(Self::Reject),
+            "inject_bytes" | "" => Some(Self::InjectBytes),
+            "passthrough" => Some(Self::Passthrough),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Reject => "reject",
+            Self::InjectBytes => "inject_bytes",
+            Self::Passthrough => "passthrough",
+        }
+    }
+}
+
+/// What happens to a JSON response that redaction left unparseable
+///
+/// A redaction marker replacing a secret inside a number or literal keeps
+/// the JSON content type while the body no longer parses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InvalidSanitizedJson {
+    /// Forward as is, with a warning and metric
+    #[default]
+    Warn,
+    /// Also relabel the response `text/plain`, so agents do not parse it as JSON
+    TextPlain,
+}
+
+impl InvalidSanitizedJson {
+    /// Parse a setting value: `warn` or `text_plain`
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "warn" | "" => Some(Self::Warn),
+            "text_plain" | "text/plain" => Some(Self::TextPlain),
+            _ => None,
+        }
+    }
+}
+
+/// Rewrites the forwarded path, and optionally the method, for API compatibility
+///
+/// The pattern is matched against the request path (without the query);
+/// the replacement may refer to captures as `$1` or `${name}`.
+#[derive(Debug, Clone)]
+pub struct RewriteRule {
+    pattern: regex::Regex,
+    replacement: String,
+    method: Option<Method>,
+}
+
+impl RewriteRule {
+    /// Create a path rewrite rule
+    pub fn new(pattern: &str, replacement: &str) -> Result<Self, regex::Error> {
+        Ok(Self {
+            pattern: regex::Regex::new(pattern)?,
+            replacement: replacement.to_string(),
+            method: None,
+        })
+    }
+
+    /// Also forward matching requests with `method`
+    pub fn with_method(mut self, method: Method) -> Self {
+        self.method = Some(method);
+        self
+    }
+}
+
+/// Apply the first rewrite rule whose pattern matches the request path
+///
+/// The query string is kept as sent. Requests matching no rule are
+/// returned unchanged.
+fn apply_rewrite_rules(rules: &[RewriteRule], method: &Method, uri: &Uri) -> (Method, Uri) {
+    let path = uri.path();
+    let Some(rule) = rules.iter().find(|rule| rule.pattern.is_match(path)) else {
+        return (method.clone(), uri.clone());
+    };
+
+    let mut rewritten = rule.pattern.replace(path, &rule.replacement).into_owned();
+    if let Some(query) = uri.query() {
+        rewritten.push('?');
+        rewritten.push_str(query);
+    }
+    let Ok(rewritten_uri) = rewritten.parse::<Uri>() else {
+        tracing::warn!(
+            "Rewrite of {} produced an invalid path, forwarding unchanged",
+            path
+        );
+        return (method.clone(), uri.clone());
+    };
+
+    let method = rule.method.clone().unwrap_or_else(|| method.clone());
+    tracing::debug!("Rewrote {} to {} {}", path, method, rewritten_uri);
+    (method, rewritten_uri)
+}
 
 /// HTTP client for forwarding requests (supports both HTTP and HTTPS)
-pub type HttpClient = Client<HttpsConnector<HttpConnector>, Body>;
+pub type HttpClient = Client<HttpsConnector<GuardedConnector>, Body>;
+
+/// A connection the upstream connector refused: its peer is the proxy's own listener
+#[derive(Debug, Error)]
+#[error("Connection to {0} reaches the proxy itself")]
+pub struct SelfConnect(SocketAddr);
+
+/// [`HttpConnector`] that drops connections whose peer is the proxy's listener
+///
+/// The check runs on the address actually connected to, after resolution, so
+/// a hostname that resolves elsewhere when first checked and to the proxy
+/// when connected (DNS rebinding) is still refused.
+#[derive(Clone)]
+pub struct GuardedConnector {
+    inner: HttpConnector,
+    listen_addr: SocketAddr,
+}
+
+impl tower::Service<Uri> for GuardedConnector {
+    type Response = TokioIo<tokio::net::TcpStream>;
+    type Error = Box<dyn std::error::Error + Send + Sync>;
+    type Future = std::pin::Pin<
+        Box<dyn std::future::Future<Output = Result<Self::Response, Self::Error>> + Send>,
+    >;
+
+    fn poll_ready(
+        &mut self,
+        cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<Result<(), Self::Error>> {
+        tower::Service::poll_ready(&mut self.inner, cx).map_err(Into::into)
+    }
+
+    fn call(&mut self, dst: Uri) -> Self::Future {
+        let connecting = tower::Service::call(&mut self.inner, dst);
+        let listen_addr = self.listen_addr;
+        Box::pin(async move {
+            let stream = connecting.await?;
+            let peer = stream.inner().peer_addr()?;
+            if reaches_listener(peer, listen_addr) {
+                tracing::warn!("Dropping upstream connection to the proxy itself: {}", peer);
+                return Err(SelfConnect(peer).into());
+            }
+            Ok(stream)
+        })
+    }
+}
 
 /// Create a configured HTTP client for proxying with TLS support
 pub fn create_http_client() -> HttpClient {
+    create_http_client_with_config(&ProxyConfig::default())
+}
+
+/// [`create_http_client`], bounding upstream connects by `upstream_connect_timeout`
+pub fn create_http_client_with_config(config: &ProxyConfig) -> HttpClient {
+    let mut http = HttpConnector::new();
+    http.enforce_http(false);
+    http.set_connect_timeout(Some(config.upstream_connect_timeout));
+    let guarded = GuardedConnector {
+        inner: http,
+        listen_addr: config.listen_addr,
+    };
     let https = hyper_rustls::HttpsConnectorBuilder::new()
         .with_webpki_roots()
         .https_or_http()
         .enable_http1()
-        .build();
+        .wrap_connector(guarded);
     Client::builder(TokioExecutor::new()).build(https)
 }
 
@@ -48,6 +406,122 @@ pub struct ProxyConfig {
     pub max_request_size: usize,
     /// Maximum response body size in bytes (prevents OOM)
     pub max_response_size: usize,
+    /// Forward `Link` headers from upstream `103 Early Hints` onto the final response
+    pub forward_early_hints: bool,
+    /// Address the proxy listens on; targets resolving here are rejected
+    pub listen_addr: SocketAddr,
+    /// Responses containing more secrets than this are rejected (anomaly guard)
+    pub max_redactions_per_response: usize,
+    /// Fail MITM tunnels whose request still holds a dummy credential after injection
+    pub fail_on_residual_dummy: bool,
+    /// TCP socket options for accepted and upstream connections
+    pub network: NetworkConfig,
+    /// Redact real secrets from outbound non-auth request headers (opt-in)
+    pub sanitize_request_headers: bool,
+    /// Headers added to (or overriding) every proxied response, e.g. CORS policy
+    pub add_response_headers: HeaderMap,
+    /// Drop upstream `Access-Control-*` headers so the proxy's own CORS policy governs
+    pub strip_upstream_cors: bool,
+    /// Maximum time to wait for the client to finish sending the request body
+    pub request_body_timeout: Duration,
+    /// Maximum time to establish a TCP connection to an upstream
+    pub upstream_connect_timeout: Duration,
+    /// Maximum time from forwarding a request until the upstream's response has arrived:
+    /// the whole body when buffered, the headers when streamed. Redirect hops share it
+    pub upstream_response_timeout: Duration,
+    /// Longest wait for the next chunk of a streamed response before it is aborted
+    pub upstream_read_timeout: Duration,
+    /// Retries of upstream connection failures and 502/503 responses (off by default)
+    pub upstream_retry: RetryPolicy,
+    /// Route templates for the metrics `endpoint` label; other paths are labelled `other`
+    pub endpoint_templates: Vec<String>,
+    /// Probe paths answered locally, skipping injection and metrics (trailing `*` = prefix)
+    pub probe_paths: Vec<String>,
+    /// Responses declaring a larger Content-Length are sanitized as a stream, not buffered
+    pub stream_response_threshold: usize,
+    /// Sanitize unencoded responses that declare no Content-Length as a stream too
+    pub stream_unsized_responses: bool,
+    /// Bearer token required by the admin API; the API is disabled when unset
+    pub admin_token: Option<String>,
+    /// Transcode buffered responses in a declared non-UTF-8 charset to UTF-8 before sanitizing
+    pub normalize_response_charset: bool,
+    /// Identify the proxy to upstreams via `User-Agent` or a dedicated header (off by default)
+    pub proxy_identification: ProxyIdentification,
+    /// MITM handshakes (leaf cert generation + both TLS handshakes) allowed in progress at once
+    pub max_concurrent_mitm_handshakes: usize,
+    /// How long a tunnel waits for a handshake slot before it is shed
+    pub mitm_handshake_queue_timeout: Duration,
+    /// Distinct upstream hosts remembered for egress auditing, least recently used evicted (0 disables)
+    pub max_tracked_upstream_hosts: usize,
+    /// Cap on real secret bytes held in memory, static and runtime (unlimited when unset)
+    pub max_secret_bytes: Option<usize>,
+    /// Path/method rewrites applied before the target URL is resolved; first match wins
+    pub rewrite_rules: Vec<RewriteRule>,
+    /// Follow upstream 3xx redirects instead of passing them to the agent (off by default)
+    pub follow_redirects: bool,
+    /// Redirect hops followed for one request before it fails
+    pub max_redirects: usize,
+    /// Tell the agent `Connection: close` when the upstream announced it is closing
+    pub propagate_upstream_close: bool,
+    /// Stream `application/x-ndjson` responses whole line by whole line, whatever their
+    /// length; encoded streams are decoded as they arrive
+    pub stream_ndjson: bool,
+    /// Stream `text/event-stream` responses whole line by whole line, whatever their
+    /// length; encoded streams are decoded as they arrive
+    pub stream_sse: bool,
+    /// Identical GET/HEAD (or `Idempotency-Key`) requests in flight share one upstream response
+    pub deduplicate_requests: bool,
+    /// Longest unterminated line in a streamed NDJSON/SSE response before the stream is aborted
+    pub max_stream_line_length: usize,
+    /// Redact JSON responses value by value instead of by substring (off by default)
+    pub sanitize_json_values: bool,
+    /// Handling of JSON responses that redaction left unparseable
+    pub invalid_sanitized_json: InvalidSanitizedJson,
+    /// Trace context headers continued or started on forwarded requests (off by default)
+    pub trace_propagation: TracePropagation,
+    /// Quiet period after runtime secret changes before their automaton is rebuilt
+    pub runtime_secret_rebuild_debounce: Duration,
+    /// Per-identity byte budget over request and response bodies (unlimited when unset)
+    pub byte_quota: Option<ByteQuotaConfig>,
+    /// Per-identity request rate on the plain HTTP path (unlimited when unset)
+    pub rate_limit: Option<RateLimitConfig>,
+    /// CONNECT ports carrying plaintext that is injected and sanitized, not passed through
+    pub inspect_plaintext_ports: Vec<u16>,
+    /// CONNECT ports whose TLS is intercepted (MITM); empty disables interception
+    pub intercept_ports: Vec<u16>,
+    /// Intercept TLS on every CONNECT port not inspected as plaintext, ignoring `intercept_ports`
+    pub intercept_all_tls: bool,
+    /// Compress sanitized buffered bodies (gzip or br) for clients that accept it
+    pub compress_responses: bool,
+    /// Length limit on upstream response header values, and what to do past it
+    pub header_value_limit: HeaderValueLimit,
+    /// Media types (`type/subtype` or `type/*`) responses may carry; empty allows all
+    pub allowed_response_content_types: Vec<String>,
+    /// Inject credentials (default), or only redact secrets in both directions
+    pub mode: ProxyMode,
+    /// ClientHello SNIs tunnelled without MITM, for certificate-pinning clients (`*.` = subdomains)
+    pub mitm_bypass_sni: Vec<String>,
+    /// Per-route overrides on the forwarded path; first match wins, unmatched routes inject
+    pub routes: Vec<RouteConfig>,
+    /// Also redact secrets hidden behind HTML entities in `text/html` responses (opt-in)
+    pub decode_html_entities: bool,
+    /// HTML responses larger than this are not entity-decoded
+    pub max_html_entity_decode_size: usize,
+    /// Regex rules over outbound request bodies that block or redact, in order
+    pub dlp_rules: Vec<DlpRule>,
+    /// Hosts the plain HTTP path may forward to (`*.` = subdomains); empty allows all
+    pub egress_allowed_hosts: Vec<String>,
+    /// Global allowlist every injection destination must match (`*.` = subdomains), on
+    /// top of the strategy whitelists; empty defers to the strategies
+    pub injection_allowed_hosts: Vec<String>,
+    /// Handling of requests carrying both a dummy and an unmanaged real credential
+    pub mixed_credential_policy: MixedCredentialPolicy,
+    /// Handling of request bodies that are not valid UTF-8
+    pub non_utf8_body_policy: NonUtf8BodyPolicy,
+    /// Roots trusted for MITM upstream connections besides the WebPKI roots
+    pub upstream_ca_certs: Vec<CertificateDer<'static>>,
+    /// Accept any MITM upstream certificate (development only; off by default)
+    pub insecure_skip_upstream_verify: bool,
 }
 
 impl Default for ProxyConfig {
@@ -55,8 +529,376 @@ impl Default for ProxyConfig {
         Self {
             max_request_size: DEFAULT_MAX_REQUEST_SIZE,
             max_response_size: DEFAULT_MAX_RESPONSE_SIZE,
+            forward_early_hints: true,
+            listen_addr: DEFAULT_LISTEN_ADDR,
+            max_redactions_per_response: DEFAULT_MAX_REDACTIONS_PER_RESPONSE,
+            fail_on_residual_dummy: true,
+            network: NetworkConfig::default(),
+            sanitize_request_headers: false,
+            add_response_headers: HeaderMap::new(),
+            strip_upstream_cors: false,
+            request_body_timeout: DEFAULT_REQUEST_BODY_TIMEOUT,
+            upstream_connect_timeout: DEFAULT_UPSTREAM_CONNECT_TIMEOUT,
+            upstream_response_timeout: DEFAULT_UPSTREAM_RESPONSE_TIMEOUT,
+            upstream_read_timeout: DEFAULT_UPSTREAM_READ_TIMEOUT,
+            upstream_retry: RetryPolicy::default(),
+            endpoint_templates: metrics::DEFAULT_ENDPOINT_TEMPLATES
+                .iter()
+                .map(|t| t.to_string())
+                .collect(),
+            probe_paths: Vec::new(),
+            stream_response_threshold: DEFAULT_MAX_RESPONSE_SIZE,
+            stream_unsized_responses: true,
+            admin_token: None,
+            normalize_response_charset: true,
+            proxy_identification: ProxyIdentification::Off,
+            max_concurrent_mitm_handshakes: DEFAULT_MAX_CONCURRENT_MITM_HANDSHAKES,
+            max_tracked_upstream_hosts: DEFAULT_MAX_TRACKED_UPSTREAM_HOSTS,
+            max_secret_bytes: None,
+            mitm_handshake_queue_timeout: DEFAULT_MITM_HANDSHAKE_QUEUE_TIMEOUT,
+            rewrite_rules: Vec::new(),
+            follow_redirects: false,
+            max_redirects: DEFAULT_MAX_REDIRECTS,
+            propagate_upstream_close: true,
+            stream_ndjson: true,
+            stream_sse: true,
+            deduplicate_requests: false,
+            max_stream_line_length: DEFAULT_MAX_STREAM_LINE_LENGTH,
+            sanitize_json_values: false,
+            invalid_sanitized_json: InvalidSanitizedJson::Warn,
+            trace_propagation: TracePropagation::Off,
+            runtime_secret_rebuild_debounce: DEFAULT_RUNTIME_REBUILD_DEBOUNCE,
+            byte_quota: None,
+            rate_limit: None,
+            inspect_plaintext_ports: Vec::new(),
+            intercept_ports: DEFAULT_INTERCEPT_PORTS.to_vec(),
+            intercept_all_tls: false,
+            compress_responses: false,
+            header_value_limit: HeaderValueLimit::default(),
+            allowed_response_content_types: Vec::new(),
+            mode: ProxyMode::Inject,
+            mitm_bypass_sni: Vec::new(),
+            routes: Vec::new(),
+            decode_html_entities: false,
+            max_html_entity_decode_size: DEFAULT_MAX_HTML_ENTITY_DECODE_SIZE,
+            dlp_rules: Vec::new(),
+            egress_allowed_hosts: Vec::new(),
+            injection_allowed_hosts: Vec::new(),
+            mixed_credential_policy: MixedCredentialPolicy::Warn,
+            non_utf8_body_policy: NonUtf8BodyPolicy::InjectBytes,
+            upstream_ca_certs: Vec::new(),
+            insecure_skip_upstream_verify: false,
+        }
+    }
+}
+
+impl ProxyConfig {
+    /// Build from a config file's sections; anything a section leaves unset
+    /// keeps its default
+    ///
+    /// Every field is settable from the file except these, which stay at
+    /// their defaults here and are set from the environment only:
+    /// - `listen_addr`: fixed by the container's published port
+    /// - `admin_token`: a credential, kept out of config files (`ADMIN_TOKEN`)
+    /// - `upstream_ca_certs`: read from the PEM bundle at `UPSTREAM_CA_CERT`
+    /// - `insecure_skip_upstream_verify`: development only, so it cannot be
+    ///   committed to a shared config (`INSECURE_SKIP_UPSTREAM_VERIFY`)
+    pub fn from_config(config: &Config) -> Result<Self, String> {
+        let mut proxy_config = Self {
+            network: config.network.clone(),
+            routes: config.routing.routes.clone(),
+            probe_paths: config.routing.probe_paths.clone(),
+            mitm_bypass_sni: config
+                .routing
+                .mitm_bypass_sni
+                .iter()
+                .map(|host| host.to_ascii_lowercase())
+                .collect(),
+            inspect_plaintext_ports: config.routing.inspect_plaintext_ports.clone(),
+            intercept_all_tls: config.routing.intercept_all_tls,
+            allowed_response_content_types: config
+                .security
+                .allowed_response_content_types
+                .iter()
+                .map(|media_type| media_type.to_ascii_lowercase())
+                .collect(),
+            egress_allowed_hosts: config
+                .security
+                .egress_allowed_hosts
+                .iter()
+                .map(|host| host.trim().to_ascii_lowercase())
+                .collect(),
+            injection_allowed_hosts: config
+                .security
+                .injection_allowed_hosts
+                .iter()
+                .map(|host| host.trim().to_ascii_lowercase())
+                .collect(),
+            ..Default::default()
+        };
+
+        let proxy = &config.proxy;
+        if let Some(mode) = &proxy.mode {
+            proxy_config.mode = ProxyMode::parse(mode).ok_or_else(|| {
+                format!(
+                    "Invalid mode '{}', must be 'inject' or 'sanitize_only'",
+                    mode
+                )
+            })?;
+        }
+        if let Some(max) = proxy.max_request_size {
+            proxy_config.max_request_size = max;
+        }
+        if let Some(max) = proxy.max_response_size {
+            proxy_config.max_response_size = max;
+        }
+        if let Some(threshold) = proxy.stream_response_threshold {
+            proxy_config.stream_response_threshold = threshold;
+        }
+        if let Some(stream) = proxy.stream_unsized_responses {
+            proxy_config.stream_unsized_responses = stream;
+        }
+        if let Some(secs) = proxy.request_body_timeout_secs {
+            proxy_config.request_body_timeout = Duration::from_secs(secs);
+        }
+        if let Some(secs) = proxy.upstream_connect_timeout_secs {
+            proxy_config.upstream_connect_timeout = Duration::from_secs(secs);
+        }
+        if let Some(secs) = proxy.upstream_response_timeout_secs {
+            proxy_config.upstream_response_timeout = Duration::from_secs(secs);
+        }
+        if let Some(secs) = proxy.upstream_read_timeout_secs {
+            proxy_config.upstream_read_timeout = Duration::from_secs(secs);
+        }
+        if let Some(attempts) = proxy.retry_max_attempts {
+            proxy_config.upstream_retry.max_attempts = attempts;
+        }
+        if let Some(ms) = proxy.retry_base_delay_ms {
+            proxy_config.upstream_retry.base_delay = Duration::from_millis(ms);
+        }
+        if let Some(jitter) = proxy.retry_jitter {
+            proxy_config.upstream_retry.jitter = jitter;
+        }
+        if let Some(follow) = proxy.follow_redirects {
+            proxy_config.follow_redirects = follow;
+        }
+        if let Some(max) = proxy.max_redirects {
+            proxy_config.max_redirects = max;
+        }
+        if let Some(propagate) = proxy.propagate_upstream_close {
+            proxy_config.propagate_upstream_close = propagate;
+        }
+        if let Some(stream) = proxy.stream_ndjson {
+            proxy_config.stream_ndjson = stream;
+        }
+        if let Some(stream) = proxy.stream_sse {
+            proxy_config.stream_sse = stream;
+        }
+        if let Some(dedup) = proxy.deduplicate_requests {
+            proxy_config.deduplicate_requests = dedup;
+        }
+        if let Some(max) = proxy.max_stream_line_length {
+            proxy_config.max_stream_line_length = max;
+        }
+        if let Some(json_values) = proxy.sanitize_json_values {
+            proxy_config.sanitize_json_values = json_values;
+        }
+        if let Some(handling) = &proxy.invalid_sanitized_json {
+            proxy_config.invalid_sanitized_json = InvalidSanitizedJson::parse(handling)
+                .ok_or_else(|| {
+                    format!(
+                        "Invalid invalid_sanitized_json '{}', must be 'warn' or 'text_plain'",
+                        handling
+                    )
+                })?;
+        }
+        if let Some(sanitize) = proxy.sanitize_request_headers {
+            proxy_config.sanitize_request_headers = sanitize;
+        }
+        if let Some(strip) = proxy.strip_upstream_cors {
+            proxy_config.strip_upstream_cors = strip;
+        }
+        for (name, value) in &proxy.add_response_headers {
+            let header_name = HeaderName::from_bytes(name.as_bytes())
+                .map_err(|_| format!("Invalid add_response_headers name '{}'", name))?;
+            let header_value = HeaderValue::from_str(value)
+                .map_err(|_| format!("Invalid add_response_headers value for '{}'", name))?;
+            proxy_config
+                .add_response_headers
+                .insert(header_name, header_value);
+        }
+        if let Some(compress) = proxy.compress_responses {
+            proxy_config.compress_responses = compress;
+        }
+        if let Some(max) = proxy.max_html_entity_decode_size {
+            proxy_config.max_html_entity_decode_size = max;
+        }
+        if let Some(ms) = proxy.runtime_secret_rebuild_debounce_ms {
+            proxy_config.runtime_secret_rebuild_debounce = Duration::from_millis(ms);
+        }
+        if let Some(forward) = proxy.forward_early_hints {
+            proxy_config.forward_early_hints = forward;
+        }
+        if let Some(normalize) = proxy.normalize_response_charset {
+            proxy_config.normalize_response_charset = normalize;
+        }
+        if let Some(decode) = proxy.decode_html_entities {
+            proxy_config.decode_html_entities = decode;
+        }
+        if let Some(identification) = &proxy.proxy_identification {
+            proxy_config.proxy_identification = ProxyIdentification::parse(identification)
+                .ok_or_else(|| {
+                    format!(
+                        "Invalid proxy_identification '{}', must be 'off', 'user-agent' or 'header'",
+                        identification
+                    )
+                })?;
+        }
+        if let Some(max_len) = proxy.max_header_value_len {
+            proxy_config.header_value_limit.max_len = max_len;
+        }
+        if let Some(action) = &proxy.oversized_header_action {
+            proxy_config.header_value_limit.action = OversizedHeaderAction::parse(action)
+                .ok_or_else(|| {
+                    format!(
+                        "Invalid oversized_header_action '{}', must be 'truncate' or 'reject'",
+                        action
+                    )
+                })?;
+        }
+        if let Some(propagation) = &proxy.trace_propagation {
+            proxy_config.trace_propagation =
+                TracePropagation::parse(propagation).ok_or_else(|| {
+                    format!(
+                        "Invalid trace_propagation '{}', must be 'off', 'w3c' or 'w3c_b3'",
+                        propagation
+                    )
+                })?;
+        }
+
+        if let Some(templates) = &config.routing.endpoint_templates {
+            proxy_config.endpoint_templates = templates.clone();
+        }
+        if let Some(ports) = &config.routing.intercept_ports {
+            proxy_config.intercept_ports = ports.clone();
         }
+
+        let limits = &config.limits;
+        if let Some(max) = limits.max_redactions_per_response {
+            proxy_config.max_redactions_per_response = max;
+        }
+        if let Some(fail) = limits.fail_on_residual_dummy {
+            proxy_config.fail_on_residual_dummy = fail;
+        }
+        if let Some(max) = limits.max_concurrent_mitm_handshakes {
+            proxy_config.max_concurrent_mitm_handshakes = max;
+        }
+        if let Some(secs) = limits.mitm_handshake_queue_timeout_secs {
+            proxy_config.mitm_handshake_queue_timeout = Duration::from_secs(secs);
+        }
+        if let Some(max) = limits.max_tracked_upstream_hosts {
+            proxy_config.max_tracked_upstream_hosts = max;
+        }
+        if let Some(max) = limits.max_secret_bytes {
+            proxy_config.max_secret_bytes = Some(max);
+        }
+        if let Some(requests_per_second) = limits.rate_limit_requests_per_second {
+            proxy_config.rate_limit = Some(RateLimitConfig {
+                requests_per_second,
+                burst: limits.rate_limit_burst.unwrap_or(requests_per_second),
+            });
+        }
+        if let Some(max_bytes) = limits.byte_quota_max_bytes {
+            proxy_config.byte_quota = Some(ByteQuotaConfig {
+                max_bytes,
+                window: Duration::from_secs(limits.byte_quota_window_secs.unwrap_or(3600)),
+            });
+        }
+
+        if let Some(policy) = &config.security.mixed_credential_policy {
+            proxy_config.mixed_credential_policy = MixedCredentialPolicy::parse(policy)
+                .ok_or_else(|| {
+                    format!(
+                        "Invalid mixed_credential_policy '{}', must be 'warn', 'strip' or 'block'",
+                        policy
+                    )
+                })?;
+        }
+
+        if let Some(policy) = &config.security.non_utf8_body_policy {
+            proxy_config.non_utf8_body_policy =
+                NonUtf8BodyPolicy::parse(policy).ok_or_else(|| {
+                    format!(
+                        "Invalid non_utf8_body_policy '{}', must be 'reject', 'inject_bytes' or 'passthrough'",
+                        policy
+                    )
+                })?;
+        }
+
+        for rule in &config.security.dlp_rules {
+            let action = DlpAction::parse(&rule.action).ok_or_else(|| {
+                format!(
+                    "Invalid action '{}' for DLP rule '{}', must be 'block' or 'redact'",
+                    rule.action, rule.name
+                )
+            })?;
+            let dlp_rule = DlpRule::new(&rule.name, &rule.pattern)
+                .map_err(|e| format!("Invalid pattern for DLP rule '{}': {}", rule.name, e))?;
+            proxy_config.dlp_rules.push(dlp_rule.with_action(action));
+        }
+
+        for rule in &config.routing.rewrite_rules {
+            let mut rewrite_rule =
+                RewriteRule::new(&rule.pattern, &rule.replacement).map_err(|e| {
+                    format!("Invalid pattern for rewrite rule '{}': {}", rule.pattern, e)
+                })?;
+            if let Some(method) = &rule.method {
+                let method =
+                    Method::from_bytes(method.to_ascii_uppercase().as_bytes()).map_err(|_| {
+                        format!(
+                            "Invalid method '{}' for rewrite rule '{}'",
+                            method, rule.pattern
+                        )
+                    })?;
+                rewrite_rule = rewrite_rule.with_method(method);
+            }
+            proxy_config.rewrite_rules.push(rewrite_rule);
+        }
+
+        Ok(proxy_config)
+    }
+
+    /// Check `host` against the global injection allowlist (empty allows all)
+    ///
+    /// A destination must pass this as well as the whitelist of the strategy
+    /// whose credential is injected.
+    pub fn injection_allowed(&self, host: &str) -> bool {
+        host_in_allowlist(&self.injection_allowed_hosts, host)
+    }
+
+    /// Check `host` against the egress allowlist (empty allows all)
+    pub fn egress_allowed(&self, host: &str) -> bool {
+        host_in_allowlist(&self.egress_allowed_hosts, host)
+    }
+}
+
+/// Match `host` against lowercase allowlist entries (`*.` = subdomains); an
+/// empty list allows every host
+fn host_in_allowlist(allowlist: &[String], host: &str) -> bool {
+    if allowlist.is_empty() {
+        return true;
     }
+    let host = host
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .trim_end_matches('.')
+        .to_ascii_lowercase();
+    allowlist
+        .iter()
+        .any(|entry| match entry.strip_prefix("*.") {
+            Some(base) => host == base || host.ends_with(&format!(".{}", base)),
+            None => host == *entry,
+        })
 }
 
 /// Proxy error types
@@ -71,6 +913,9 @@ pub enum ProxyError {
     #[error("Failed to forward request: {0}")]
     ForwardRequest(String),
 
+    #[error("Upstream did not respond with valid HTTP: {0}")]
+    UpstreamProtocolError(String),
+
     #[error("Failed to read response body: {0}")]
     ResponseBodyRead(String),
 
@@ -83,28 +928,111 @@ pub enum ProxyError {
     #[error("Request body too large (max {0} bytes)")]
     RequestBodyTooLarge(usize),
 
+    #[error("Timed out reading request body after {0:?}")]
+    RequestBodyTimeout(Duration),
+
     #[error("Response body too large (max {0} bytes)")]
     ResponseBodyTooLarge(usize),
+
+    #[error("Target resolves to the proxy itself: {0}")]
+    SelfTarget(String),
+
+    #[error("Egress to host denied: {0}")]
+    HostDenied(String),
+
+    #[error("Host not in egress allowlist: {0}")]
+    HostNotAllowed(String),
+
+    #[error("Upstream response rejected")]
+    ExcessiveRedactions(usize),
+
+    #[error("Redirect to host not allowed: {0}")]
+    RedirectDenied(String),
+
+    #[error("Too many upstream redirects (max {0})")]
+    TooManyRedirects(usize),
+
+    #[error("Byte quota exceeded for {0}")]
+    QuotaExceeded(String),
+
+    #[error("Rate limit exceeded for {0}")]
+    RateLimited(String, Duration),
+
+    #[error("Request blocked by DLP rule: {0}")]
+    DlpBlocked(String),
+
+    #[error("Credential injection not allowed for host: {0}")]
+    InjectionHostNotAllowed(String),
+
+    #[error("Credential injection failed: {0}")]
+    InjectionFailed(String),
+
+    #[error("Request carries an unmanaged '{0}' credential alongside a dummy")]
+    MixedCredentials(String),
+
+    #[error("Sanitization verification failed")]
+    SanitizationVerificationFailed,
+
+    #[error("Upstream {0}")]
+    OversizedHeader(String),
+
+    #[error("Upstream response body could not be decoded: {0}")]
+    UndecodableResponse(String),
+
+    #[error("Upstream response rejected")]
+    ContentTypeBlocked(String),
+
+    #[error("Upstream timed out {0}")]
+    UpstreamTimeout(String),
 }
 
 impl IntoResponse for ProxyError {
     fn into_response(self) -> Response {
+        let retry_after = match &self {
+            ProxyError::RateLimited(_, wait) => Some(wait.as_secs_f64().ceil().max(1.0) as u64),
+            _ => None,
+        };
         let (status, message) = match self {
             ProxyError::RequestBodyRead(_) | ProxyError::InvalidUtf8(_) => {
                 (StatusCode::BAD_REQUEST, self.to_string())
             }
-            ProxyError::ForwardRequest(_) | ProxyError::ResponseBodyRead(_) => {
-                (StatusCode::BAD_GATEWAY, self.to_string())
-            }
+            ProxyError::ForwardRequest(_)
+            | ProxyError::UpstreamProtocolError(_)
+            | ProxyError::ResponseBodyRead(_)
+            | ProxyError::ExcessiveRedactions(_)
+            | ProxyError::ContentTypeBlocked(_)
+            | ProxyError::TooManyRedirects(_)
+            | ProxyError::SanitizationVerificationFailed
+            | ProxyError::OversizedHeader(_)
+            | ProxyError::UndecodableResponse(_) => (StatusCode::BAD_GATEWAY, self.to_string()),
             ProxyError::InvalidTargetUrl(_) | ProxyError::MissingHeader(_) => {
                 (StatusCode::BAD_REQUEST, self.to_string())
             }
             ProxyError::RequestBodyTooLarge(_) | ProxyError::ResponseBodyTooLarge(_) => {
                 (StatusCode::PAYLOAD_TOO_LARGE, self.to_string())
             }
+            ProxyError::RequestBodyTimeout(_) => (StatusCode::REQUEST_TIMEOUT, self.to_string()),
+            ProxyError::UpstreamTimeout(_) => (StatusCode::GATEWAY_TIMEOUT, self.to_string()),
+            ProxyError::SelfTarget(_)
+            | ProxyError::DlpBlocked(_)
+            | ProxyError::MixedCredentials(_) => (StatusCode::FORBIDDEN, self.to_string()),
+            ProxyError::HostDenied(_)
+            | ProxyError::HostNotAllowed(_)
+            | ProxyError::RedirectDenied(_)
+            | ProxyError::InjectionHostNotAllowed(_) => (StatusCode::FORBIDDEN, self.to_string()),
+            ProxyError::QuotaExceeded(_) | ProxyError::RateLimited(..) => {
+                (StatusCode::TOO_MANY_REQUESTS, self.to_string())
+            }
+            ProxyError::InjectionFailed(_) => (StatusCode::INTERNAL_SERVER_ERROR, self.to_string()),
         };
 
-        (status, message).into_response()
+        let mut response = (status, message).into_response();
+        if let Some(secs) = retry_after {
+            response
+                .headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(secs));
+        }
+        response
     }
 }
 
@@ -113,7 +1041,23 @@ impl IntoResponse for ProxyError {
 /// SECURITY FIX E: Recalculates Content-Length after body modification
 /// Removes checksums (ETag, Content-MD5) since body was modified
 pub fn build_response_headers(original_headers: &HeaderMap, body_len: usize) -> HeaderMap {
+    build_response_headers_with_config(original_headers, body_len, &ProxyConfig::default())
+}
+
+/// Build sanitized response headers, applying the proxy's response header policy
+///
+/// Same as [`build_response_headers`], but optionally strips upstream CORS
+/// headers and then applies `add_response_headers` on top. Hop-by-hop headers,
+/// and any the upstream `Connection` header names, describe the upstream
+/// connection and are dropped; an upstream `Connection: close` is passed on
+/// when `propagate_upstream_close` is set.
+pub fn build_response_headers_with_config(
+    original_headers: &HeaderMap,
+    body_len: usize,
+    config: &ProxyConfig,
+) -> HeaderMap {
     let mut headers = HeaderMap::new();
+    let connection_options = connection_options(original_headers);
 
     // Set correct Content-Length for sanitized body
     headers.insert(
@@ -141,6 +1085,17 @@ pub fn build_response_headers(original_headers: &HeaderMap, body_len: usize) ->
                 continue;
             }
 
+            // Skip upstream CORS headers - the proxy's own policy governs
+            cors if config.strip_upstream_cors && cors.starts_with("access-control-") => {
+                tracing::debug!("Removing upstream CORS header: {}", name_str);
+                continue;
+            }
+
+            // Skip - these describe the upstream connection, not ours
+            hop if is_hop_by_hop_header(hop) || connection_options.contains(&name_str) => {
+                continue;
+            }
+
             // Copy everything else
             _ => {
                 headers.insert(name.clone(), value.clone());
@@ -148,9 +1103,170 @@ pub fn build_response_headers(original_headers: &HeaderMap, body_len: usize) ->
         }
     }
 
+    // The agent should not reuse a proxy connection whose upstream is going away
+    if config.propagate_upstream_close && connection_options.iter().any(|o| o == "close") {
+        headers.insert(header::CONNECTION, HeaderValue::from_static("close"));
+    }
+
+    // Proxy-configured headers take precedence over upstream ones
+    for (name, value) in config.add_response_headers.iter() {
+        headers.insert(name.clone(), value.clone());
+    }
+
     headers
 }
 
+/// Lowercased options of the `Connection` header, e.g. `close` or header names
+fn connection_options(headers: &HeaderMap) -> Vec<String> {
+    headers
+        .get_all(header::CONNECTION)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .map(|option| option.trim().to_ascii_lowercase())
+        .filter(|option| !option.is_empty())
+        .collect()
+}
+
+/// Whether an upstream response announced that its connection is closing
+pub(crate) fn upstream_closing(headers: &HeaderMap) -> bool {
+    connection_options(headers).iter().any(|o| o == "close")
+}
+
+/// Register a hook that records interim (1xx) responses from the upstream
+///
+/// hyper consumes `100 Continue` and other informational responses and only
+/// hands back the final response. The hook keeps the `Link` values of any
+/// `103 Early Hints` so they can be forwarded to the agent; every other
+/// interim status is skipped.
+fn capture_early_hints(request: &mut hyper::Request<Body>) -> Arc<Mutex<Vec<HeaderValue>>> {
+    let hints = Arc::new(Mutex::new(Vec::new()));
+    let sink = hints.clone();
+
+    hyper::ext::on_informational(request, move |response| {
+        if response.status() == StatusCode::EARLY_HINTS {
+            if let Ok(mut links) = sink.lock() {
+                links.extend(response.headers().get_all(header::LINK).iter().cloned());
+            }
+        } else {
+            tracing::debug!(
+                "Skipping interim {} response from upstream",
+                response.status()
+            );
+        }
+    });
+
+    hints
+}
+
+/// Fold early-hint `Link` values into the final response headers
+///
+/// Links already present on the final response are not duplicated. All values
+/// are joined into a single header so later `HeaderMap::insert` calls keep them.
+fn merge_early_hints(headers: &mut HeaderMap, hints: &Mutex<Vec<HeaderValue>>) {
+    let hints = match hints.lock() {
+        Ok(hints) => hints,
+        Err(_) => return,
+    };
+    if hints.is_empty() {
+        return;
+    }
+
+    let mut links: Vec<String> = headers
+        .get_all(header::LINK)
+        .iter()
+        .filter_map(|v| v.to_str().ok().map(str::to_string))
+        .collect();
+    for hint in hints.iter() {
+        if let Ok(link) = hint.to_str() {
+            if !links.iter().any(|existing| existing == link) {
+                links.push(link.to_string());
+            }
+        }
+    }
+
+    if let Ok(value) = HeaderValue::from_str(&links.join(", ")) {
+        tracing::debug!("Forwarding {} early hint link(s) to agent", hints.len());
+        headers.insert(header::LINK, value);
+    }
+}
+
+/// Check whether a target URL points back at the proxy's own listener
+///
+/// Catches IP literals and `localhost` up front, without a DNS lookup; any
+/// other hostname is checked by [`GuardedConnector`] against the address it
+/// actually connects to.
+fn targets_proxy_itself(target_url: &str, listen_addr: SocketAddr) -> bool {
+    let uri: Uri = match target_url.parse() {
+        Ok(uri) => uri,
+        Err(_) => return false,
+    };
+    let host = match uri.host() {
+        Some(host) => host.trim_start_matches('[').trim_end_matches(']'),
+        None => return false,
+    };
+    let port = uri.port_u16().unwrap_or(match uri.scheme_str() {
+        Some("https") => 443,
+        _ => 80,
+    });
+
+    let addrs: Vec<IpAddr> = match host.parse::<IpAddr>() {
+        Ok(ip) => vec![ip],
+        Err(_) if is_localhost_name(host) => {
+            vec![
+                Ipv4Addr::LOCALHOST.into(),
+                std::net::Ipv6Addr::LOCALHOST.into(),
+            ]
+        }
+        Err(_) => return false,
+    };
+    addrs
+        .into_iter()
+        .any(|ip| reaches_listener(SocketAddr::new(ip, port), listen_addr))
+}
+
+/// `localhost` and its subdomains, which always resolve to loopback (RFC 6761)
+fn is_localhost_name(host: &str) -> bool {
+    let host = host.trim_end_matches('.').to_ascii_lowercase();
+    host == "localhost" || host.ends_with(".localhost")
+}
+
+/// Whether a connection to `addr` would be accepted by the listener on `listen_addr`
+///
+/// That is the bound IP on the listen port, or when bound to all interfaces,
+/// loopback, unspecified or any local interface address.
+fn reaches_listener(addr: SocketAddr, listen_addr: SocketAddr) -> bool {
+    if addr.port() != listen_addr.port() {
+        return false;
+    }
+    let ip = addr.ip().to_canonical();
+    let listen_ip = listen_addr.ip().to_canonical();
+    if listen_ip.is_unspecified() {
+        ip.is_loopback() || ip.is_unspecified() || local_addresses().contains(&ip)
+    } else {
+        // Connecting to the unspecified address reaches loopback
+        ip == listen_ip || (ip.is_unspecified() && listen_ip.is_loopback())
+    }
+}
+
+/// Addresses assigned to this host's interfaces, enumerated once
+///
+/// Called at startup so the enumeration is not paid on the first request;
+/// interfaces that come up later are not seen.
+pub fn local_addresses() -> &'static [IpAddr] {
+    static ADDRESSES: OnceLock<Vec<IpAddr>> = OnceLock::new();
+    ADDRESSES.get_or_init(|| match if_addrs::get_if_addrs() {
+        Ok(interfaces) => interfaces
+            .iter()
+            .map(|interface| interface.ip().to_canonical())
+            .collect(),
+        Err(e) => {
+            tracing::warn!("Could not enumerate local interfaces: {}", e);
+            Vec::new()
+        }
+    })
+}
+
 /// Check if request should bypass proxy (local addresses, internal services)
 fn should_bypass_proxy(uri: &Uri, headers: &HeaderMap) -> bool {
     // Check X-Target-URL header first
@@ -206,6 +1322,9 @@ fn should_bypass_proxy(uri: &Uri, headers: &HeaderMap) -> bool {
 /// 6. Sanitizes response headers (FIX B)
 /// 7. Rebuilds headers with correct Content-Length (FIX E)
 /// 8. Returns to the agent
+///
+/// With `deduplicate_requests`, identical eligible requests arriving while
+/// one is in flight share its response instead of each reaching the upstream.
 pub async fn proxy_handler(
     State(state): State<AppState>,
     method: Method,
@@ -213,149 +1332,934 @@ pub async fn proxy_handler(
     headers: HeaderMap,
     request: Request,
 ) -> Result<Response, ProxyError> {
+    // Inject only the credentials this client is scoped to
+    let state = state.for_client(request.extensions().get::<ClientCertInfo>());
+    let config = state.config.clone().unwrap_or_default();
+    // Every request counts against its identity's rate, shared or not
+    if !is_probe_path(uri.path(), &config.probe_paths) {
+        let identity = client_identity(request.extensions());
+        if let Err(wait) = state.check_rate_limit(&identity, "http") {
+            return Err(ProxyError::RateLimited(identity, wait));
+        }
+    }
+    if !config.deduplicate_requests
+        || is_probe_path(uri.path(), &config.probe_paths)
+        || !dedup::is_eligible(&method, &headers)
+    {
+        return forward_request(state, method, uri, headers, request).await;
+    }
+
+    // The body is part of the key, so it is read here and handed on whole
+    let identity = client_identity(request.extensions());
+    let (parts, body) = request.into_parts();
+    let body =
+        read_request_body(body, config.max_request_size, config.request_body_timeout).await?;
+    let key = dedup::request_key(&identity, &method, &uri, &headers, &body);
+    let request = Request::from_parts(parts, Body::from(body));
+
+    let leader = match state.in_flight.join(key) {
+        Flight::Leader(leader) => leader,
+        Flight::Follower(follower) => {
+            if let Some(shared) = follower.wait().await {
+                tracing::info!(
+                    "Deduplicated {} {} onto an identical in-flight request",
+                    method,
+                    uri
+                );
+                metrics::record_deduplicated_request();
+                return Ok(shared.into_response());
+            }
+            // The first request failed, streamed or was abandoned
+            return forward_request(state, method, uri, headers, request).await;
+        }
+    };
+
+    let response = match forward_request(state, method, uri, headers, request).await {
+        Ok(response) if response.headers().contains_key(header::CONTENT_LENGTH) => response,
+        other => {
+            leader.finish(Outcome::Unshared);
+            return other;
+        }
+    };
+    let (parts, body) = response.into_parts();
+    let body = match axum::body::to_bytes(body, usize::MAX).await {
+        Ok(body) => body,
+        Err(e) => {
+            leader.finish(Outcome::Unshared);
+            return Err(ProxyError::ResponseBodyRead(e.to_string()));
+        }
+    };
+    let shared = SharedResponse {
+        status: parts.status,
+        headers: parts.headers,
+        body,
+    };
+    leader.finish(Outcome::Shared(shared.clone()));
+    Ok(shared.into_response())
+}
+
+/// Forward one request upstream, see [`proxy_handler`]
+async fn forward_request(
+    state: AppState,
+    method: Method,
+    uri: Uri,
+    headers: HeaderMap,
+    request: Request,
+) -> Result<Response, ProxyError> {
+    // Get config (use defaults if not configured)
+    let config = state.config.clone().unwrap_or_default();
+
+    // Load balancer probes and keep-alive pings are answered locally: no
+    // injection, no host validation, no request metrics
+    if is_probe_path(uri.path(), &config.probe_paths) {
+        tracing::trace!("Answering probe {} locally", uri.path());
+        return Ok("OK".into_response());
+    }
+
     let start_time = Instant::now();
-    metrics::inc_active_connections();
+    let _connection = metrics::track_connection(metrics::ConnectionKind::Plain);
+
+    // Linked from the duration histogram as an OpenMetrics exemplar
+    let trace = headers
+        .get("traceparent")
+        .and_then(|v| v.to_str().ok())
+        .and_then(metrics::TraceContext::from_traceparent);
+
+    // Rewrites run first, so every host check below sees the final URL
+    let (method, rewritten_uri) = apply_rewrite_rules(&config.rewrite_rules, &method, &uri);
+
+    // Never forward to ourselves: it would recurse and inject credentials
+    // into the proxy's own handler
+    let target_url = determine_target_url(&headers, &rewritten_uri)?;
+    if targets_proxy_itself(&target_url, config.listen_addr) {
+        tracing::warn!(
+            "Rejecting request targeting the proxy itself: {}",
+            target_url
+        );
+        return Err(ProxyError::SelfTarget(target_url));
+    }
+
+    // Operator-maintained egress denylist, replaceable at runtime
+    let target_host = target_url
+        .parse::<Uri>()
+        .ok()
+        .and_then(|u| u.host().map(String::from))
+        .unwrap_or_default();
+    if state.is_host_denied(&target_host) {
+        tracing::warn!("Rejecting request to denied host: {}", target_host);
+        return Err(ProxyError::HostDenied(target_host));
+    }
+    // Operator allowlist: an agent-chosen target outside it never reaches
+    // injection, so credentials cannot be steered to an arbitrary host
+    if !config.egress_allowed(&target_host) {
+        tracing::warn!(
+            "Rejecting request to host outside the egress allowlist: {}",
+            target_host
+        );
+        metrics::record_host_validation_blocked("egress_allowlist", &target_host);
+        return Err(ProxyError::HostNotAllowed(target_host));
+    }
 
     // Bypass proxy for local addresses (llama server, etc.)
     if should_bypass_proxy(&uri, &headers) {
         tracing::info!("Bypassing proxy for local request");
-        return forward_directly(state, method, uri, headers, request).await;
+        return forward_directly(state, method, rewritten_uri, headers, request).await;
+    }
+    state.record_upstream_host(&target_host);
+
+    // Byte quota per agent identity; the request is charged once it is read
+    let identity = client_identity(request.extensions());
+    if let Some(quota) = &state.byte_quota {
+        if quota.is_exceeded(&identity) {
+            tracing::warn!("Byte quota exceeded for {}, refusing request", identity);
+            metrics::record_quota_exceeded(&identity);
+            return Err(ProxyError::QuotaExceeded(identity));
+        }
     }
 
-    // Get config (use defaults if not configured)
-    let config = state.config.clone().unwrap_or_default();
     let max_request_size = config.max_request_size;
     let max_response_size = config.max_response_size;
 
     tracing::debug!("Proxying request: {} {}", method, uri);
 
-    // Extract endpoint for metrics (first part of path)
-    let endpoint = uri.path().split('/').nth(1).unwrap_or("unknown");
+    // Normalize endpoint for metrics so label cardinality stays bounded
+    let endpoint = metrics::normalize_endpoint(uri.path(), &config.endpoint_templates);
 
     // SECURITY FIX D: Read request body with size limit
-    let body_bytes = axum::body::to_bytes(request.into_body(), max_request_size)
-        .await
-        .map_err(|e| {
-            let err_str = e.to_string();
-            if err_str.contains("length limit") {
-                ProxyError::RequestBodyTooLarge(max_request_size)
-            } else {
-                ProxyError::RequestBodyRead(err_str)
+    let body_bytes = read_request_body(
+        request.into_body(),
+        max_request_size,
+        config.request_body_timeout,
+    )
+    .await?;
+
+    // Convert to UTF-8 string for sanitization; other bodies are handled by
+    // the non-UTF-8 policy, with the text checks run on a lossy decoding
+    let lossy_body;
+    let (body_str, binary_body) = match std::str::from_utf8(&body_bytes) {
+        Ok(body_str) => (body_str, false),
+        Err(e) => {
+            let policy = config.non_utf8_body_policy;
+            metrics::record_non_utf8_request_body(policy.as_str());
+            match policy {
+                NonUtf8BodyPolicy::Reject => return Err(ProxyError::InvalidUtf8(e.to_string())),
+                NonUtf8BodyPolicy::InjectBytes => {
+                    tracing::debug!("Request body is not UTF-8, injecting into raw bytes")
+                }
+                NonUtf8BodyPolicy::Passthrough => tracing::warn!(
+                    "Forwarding non-UTF-8 request body to {} without injection",
+                    target_url
+                ),
             }
-        })?;
-
-    // Convert to UTF-8 string for sanitization
-    let body_str =
-        std::str::from_utf8(&body_bytes).map_err(|e| ProxyError::InvalidUtf8(e.to_string()))?;
+            lossy_body = String::from_utf8_lossy(&body_bytes);
+            (&*lossy_body, true)
+        }
+    };
 
     // Record request size
     metrics::HTTP_REQUEST_SIZE_BYTES.observe(body_bytes.len() as f64);
+    if let Some(quota) = &state.byte_quota {
+        quota.record(&identity, body_bytes.len() as u64);
+    }
 
-    // Step 1: Inject real secrets into the request
-    let injected_body = state.inject_all(body_str);
-    tracing::debug!(
-        "Injected secrets into request ({} bytes)",
-        injected_body.len()
-    );
+    // Flag real credentials the agent hardcoded instead of using a dummy
+    state.check_unmanaged_credentials(body_str);
+    // A binary body cannot be rewritten, so stripping blocks it instead
+    let mixed_credential_policy = match config.mixed_credential_policy {
+        MixedCredentialPolicy::Strip if binary_body => MixedCredentialPolicy::Block,
+        policy => policy,
+    };
+    let body_str = &*state
+        .apply_mixed_credential_policy(body_str, mixed_credential_policy)
+        .map_err(|prefix| ProxyError::MixedCredentials(prefix.to_string()))?;
+
+    // Operator DLP rules; only the rule name is ever logged
+    let dlp_rules = &config.dlp_rules;
+    let dlp_result = if binary_body {
+        check_dlp_rules(dlp_rules, body_str).map(|()| Cow::Borrowed(body_str))
+    } else {
+        apply_dlp_rules(dlp_rules, body_str)
+    };
+    let body_str = &*dlp_result.map_err(|rule| {
+        tracing::warn!(
+            "Blocking request to {}: DLP rule '{}' matched",
+            target_url,
+            rule
+        );
+        ProxyError::DlpBlocked(rule)
+    })?;
+
+    // Step 1: Inject real secrets into the request, or in sanitize-only
+    // mode redact any that are leaving instead
+    let mut hop_headers = headers.clone();
+    trace_propagation::propagate(config.trace_propagation, &mut hop_headers);
+    // Keep real secrets out of Referer, tracing and other non-auth headers.
+    // This runs before injection, so injected credentials are never redacted.
+    if config.sanitize_request_headers {
+        sanitize_agent_headers(&state, &mut hop_headers, false);
+    }
+    // A binary body is forwarded as received, after the policy checks above
+    let sent_body = if binary_body {
+        body_bytes.clone()
+    } else {
+        Bytes::copy_from_slice(body_str.as_bytes())
+    };
+    let rewrite_body =
+        !binary_body || config.non_utf8_body_policy != NonUtf8BodyPolicy::Passthrough;
+    let injected_body = match config.mode {
+        ProxyMode::Inject if !injection_enabled(rewritten_uri.path(), &config.routes) => {
+            tracing::debug!("Injection disabled for route {}", rewritten_uri.path());
+            sent_body
+        }
+        ProxyMode::Inject => {
+            // Every credential the request carries must allow the host, on
+            // top of the global injection allowlist checked below
+            if let Err(SecurityError::HostNotWhitelisted {
+                credential_type, ..
+            }) = detect_and_validate_strategies(
+                &state.strategies(),
+                &hop_headers,
+                body_str,
+                &target_host,
+            ) {
+                tracing::error!(
+                    "🚨 Refusing to inject {} credential for host '{}'",
+                    credential_type,
+                    target_host
+                );
+                metrics::record_host_validation_blocked(&credential_type, &target_host);
+                return Err(ProxyError::InjectionHostNotAllowed(target_host));
+            }
+            let injected = match (rewrite_body, binary_body) {
+                (false, _) => sent_body.clone(),
+                (true, true) => Bytes::from(state.inject_bytes_all(&sent_body)),
+                (true, false) => Bytes::from(state.inject_all(body_str)),
+            };
+            // Each header only gets the credentials whose inject targets cover it
+            let headers_injected = state.inject_headers_all(&mut hop_headers);
+            if (injected != sent_body || headers_injected)
+                && !config.injection_allowed(&target_host)
+            {
+                tracing::error!(
+                    "🚨 Refusing to inject credentials for host outside the injection allowlist: {}",
+                    target_host
+                );
+                metrics::record_host_validation_blocked("injection_allowlist", &target_host);
+                return Err(ProxyError::InjectionHostNotAllowed(target_host));
+            }
+            let injected_from: &[u8] = if rewrite_body { &sent_body } else { &[] };
+            state.record_strategy_use(
+                injected_from,
+                headers
+                    .iter()
+                    .filter_map(|(name, value)| Some((name.as_str(), value.to_str().ok()?))),
+            );
+            tracing::debug!("Injected secrets into request ({} bytes)", injected.len());
+            injected
+        }
+        ProxyMode::SanitizeOnly => {
+            let redacted = match (rewrite_body, binary_body) {
+                (false, _) => sent_body.clone(),
+                (true, true) => Bytes::from(state.sanitize_bytes_all(&sent_body).into_owned()),
+                (true, false) => Bytes::from(state.sanitize_all(body_str)),
+            };
+            if redacted != sent_body {
+                tracing::warn!("Redacted real secret from outbound request body");
+            }
+            // Nothing real may leave, auth headers included
+            sanitize_agent_headers(&state, &mut hop_headers, true);
+            redacted
+        }
+    };
 
-    // Determine target URL
-    let target_url = determine_target_url(&headers, &uri)?;
+    // The target's query (or path) may carry a secret too
+    let target_url = if config.mode == ProxyMode::SanitizeOnly {
+        let redacted = state.sanitize_all(&target_url);
+        if redacted != target_url {
+            tracing::warn!("Redacted real secret from outbound request URI");
+        }
+        redacted
+    } else {
+        target_url
+    };
     tracing::info!("Forwarding request to: {}", target_url);
 
     // Build the forwarded request
-    let target_uri: Uri = target_url
+    let mut target_uri: Uri = target_url
         .parse()
         .map_err(|e| ProxyError::InvalidTargetUrl(format!("Failed to parse URL: {}", e)))?;
-
-    let mut forwarded_request = hyper::Request::builder()
-        .method(method.clone())
-        .uri(target_uri);
-
-    // Copy relevant headers (skip hop-by-hop headers)
-    for (name, value) in headers.iter() {
-        let name_str = name.as_str();
-        if !is_hop_by_hop_header(name_str) {
-            forwarded_request = forwarded_request.header(name, value);
-        }
+    if config.mode == ProxyMode::Inject && injection_enabled(rewritten_uri.path(), &config.routes) {
+        target_uri = inject_target_uri(&state, &config, target_uri)?;
     }
 
-    let forwarded_request = forwarded_request
-        .body(Body::from(injected_body))
-        .map_err(|e| ProxyError::ForwardRequest(format!("Failed to build request: {}", e)))?;
+    let mut hop_method = method.clone();
+    let mut hop_body = injected_body;
+    let mut redirects = 0;
+    let deadline = tokio::time::Instant::now() + config.upstream_response_timeout;
+
+    // Each redirect hop is validated like the original target before the
+    // (re-injected) request is sent there
+    let (response, early_hints) = loop {
+        // Execute the request
+        let (response, early_hints) = send_upstream(&state, &config, deadline, || {
+            build_forwarded_request(
+                &config,
+                &hop_method,
+                &target_uri,
+                &hop_headers,
+                hop_body.clone(),
+            )
+        })
+        .await?;
+
+        if !config.follow_redirects {
+            break (response, early_hints);
+        }
+        let Some(next_uri) = redirect_target(&target_uri, response.status(), response.headers())
+        else {
+            break (response, early_hints);
+        };
+        if redirects >= config.max_redirects {
+            tracing::warn!("Giving up after {} redirects for {}", redirects, target_url);
+            return Err(ProxyError::TooManyRedirects(config.max_redirects));
+        }
+        redirects += 1;
 
-    // Execute the request
-    let response = state
-        .http_client
-        .request(forwarded_request)
-        .await
-        .map_err(|e| ProxyError::ForwardRequest(e.to_string()))?;
+        check_redirect_target(&state, &config, &headers, body_str, &next_uri).await?;
+        tracing::info!(
+            "Following {} redirect to: {}",
+            response.status().as_u16(),
+            next_uri
+        );
 
-    // Extract response parts
-    let (parts, body) = response.into_parts();
+        // 303, and 301/302 after a POST, continue as a body-less GET
+        if response.status() == StatusCode::SEE_OTHER
+            || (hop_method == Method::POST
+                && matches!(
+                    response.status(),
+                    StatusCode::MOVED_PERMANENTLY | StatusCode::FOUND
+                ))
+        {
+            hop_method = Method::GET;
+            hop_body = Bytes::new();
+            hop_headers.remove(header::CONTENT_LENGTH);
+            hop_headers.remove(header::CONTENT_TYPE);
+        }
+        target_uri = next_uri;
+    };
+
+    // Extract response parts (interim 1xx responses were consumed by hyper)
+    let (mut parts, body) = response.into_parts();
+    // hyper retires the pooled connection itself; count it before the hop-by-hop
+    // header is dropped
+    if upstream_closing(&parts.headers) {
+        tracing::debug!(
+            "Upstream {} is closing its connection",
+            target_uri.host().unwrap_or("unknown")
+        );
+        metrics::record_upstream_connection_close();
+    }
+    if config.forward_early_hints {
+        merge_early_hints(&mut parts.headers, &early_hints);
+    }
+    if let Err(content_type) =
+        check_response_content_type(&config.allowed_response_content_types, &parts.headers)
+    {
+        tracing::warn!(
+            "Blocking {} response from {}: content type not allowed",
+            content_type,
+            target_uri.host().unwrap_or("unknown")
+        );
+        metrics::record_blocked_response_content_type(&content_type);
+        return Err(ProxyError::ContentTypeBlocked(content_type));
+    }
     // Convert hyper Incoming body to axum Body
     let body = Body::new(body);
 
-    // SECURITY FIX D: Read response body with size limit
-    let response_bytes = axum::body::to_bytes(body, max_response_size)
-        .await
-        .map_err(|e| {
-            let err_str = e.to_string();
-            if err_str.contains("length limit") {
-                ProxyError::ResponseBodyTooLarge(max_response_size)
-            } else {
-                ProxyError::ResponseBodyRead(err_str)
+    // Large responses, NDJSON and SSE are sanitized chunk by chunk instead of buffered
+    let declared_len = parts
+        .headers
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<usize>().ok());
+    let ndjson = config.stream_ndjson && has_media_type(&parts.headers, "application/x-ndjson");
+    // Event streams (e.g. `stream: true` chat completions) must reach the
+    // agent as they are produced, not when the stream ends
+    let sse = config.stream_sse && has_media_type(&parts.headers, "text/event-stream");
+    // Unsized bodies could be arbitrarily large; encoded ones stay buffered
+    // so they can be decoded and scanned
+    let unsized_body = config.stream_unsized_responses
+        && declared_len.is_none()
+        && !axum::body::HttpBody::is_end_stream(&body)
+        && is_identity_encoded(&parts.headers);
+    if ndjson
+        || sse
+        || unsized_body
+        || declared_len.is_some_and(|len| len > config.stream_response_threshold)
+    {
+        let host = target_uri.host().unwrap_or("unknown").to_string();
+        // Streamed bodies are charged their declared length up front, or
+        // chunk by chunk when they declare none
+        let mut chunk_quota = None;
+        if let Some(quota) = &state.byte_quota {
+            match declared_len {
+                Some(len) => quota.record(&identity, len as u64),
+                None => chunk_quota = Some((quota.clone(), identity.clone())),
             }
-        })?;
+        }
+        let mut sanitizer = state
+            .streaming_sanitizer()
+            .map_err(ProxyError::ResponseBodyRead)?;
+        // Line-framed streams go out a whole line at a time, with bounded carryover
+        if ndjson || has_media_type(&parts.headers, "text/event-stream") {
+            sanitizer = sanitizer.line_delimited(config.max_stream_line_length);
+        }
+        // Encoded bodies are decoded as they arrive so secrets inside them are seen
+        let mut decoder = None;
+        if let Some(value) = parts.headers.get(header::CONTENT_ENCODING) {
+            let encodings =
+                content_encoding::parse_encodings(&String::from_utf8_lossy(value.as_bytes()));
+            match content_encoding::StreamDecoder::new(&encodings, max_response_size) {
+                Ok(stream_decoder) => {
+                    if !content_encoding::is_identity(&encodings) {
+                        decoder = Some(stream_decoder);
+                    }
+                    parts.headers.remove(header::CONTENT_ENCODING);
+                }
+                Err(e) => {
+                    tracing::warn!(
+                        "Streamed response from {} not scanned for secrets: {}",
+                        host,
+                        e
+                    );
+                    metrics::record_unscanned_body(e.reason());
+                }
+            }
+        }
+        let mut sanitized_headers = state
+            .sanitize_headers_all(&parts.headers)
+            .map_err(|e| ProxyError::OversizedHeader(e.to_string()))?;
+        state.strip_blocked_headers(&mut sanitized_headers);
+        let mut final_headers = build_response_headers_with_config(&sanitized_headers, 0, &config);
+        // Redaction changes the length, so the body goes out chunked
+        final_headers.remove(header::CONTENT_LENGTH);
+
+        let mut response_builder = Response::builder().status(parts.status);
+        for (name, value) in final_headers.iter() {
+            response_builder = response_builder.header(name, value);
+        }
+        let response = response_builder
+            .body(sanitize_stream(
+                body,
+                decoder,
+                sanitizer,
+                config.max_redactions_per_response,
+                config.upstream_read_timeout,
+                host,
+                chunk_quota,
+            ))
+            .map_err(|e| {
+                ProxyError::ResponseBodyRead(format!("Failed to build response: {}", e))
+            })?;
+
+        let duration = start_time.elapsed().as_secs_f64();
+        metrics::record_streamed_response();
+        metrics::record_http_request_traced(
+            method.as_str(),
+            parts.status.as_u16(),
+            endpoint,
+            duration,
+            trace.as_ref(),
+        );
+        tracing::info!("Proxy request streaming response to client");
+        return Ok(response);
+    }
+
+    // SECURITY FIX D: Read response body with size limit
+    let response_bytes =
+        tokio::time::timeout_at(deadline, axum::body::to_bytes(body, max_response_size))
+            .await
+            .map_err(|_| upstream_timeout(&config))?
+            .map_err(|e| {
+                let err_str = e.to_string();
+                if err_str.contains("length limit") {
+                    ProxyError::ResponseBodyTooLarge(max_response_size)
+                } else {
+                    ProxyError::ResponseBodyRead(err_str)
+                }
+            })?;
 
     // Record response size
     metrics::HTTP_RESPONSE_SIZE_BYTES.observe(response_bytes.len() as f64);
+    if let Some(quota) = &state.byte_quota {
+        quota.record(&identity, response_bytes.len() as u64);
+    }
 
+    // Compressed bodies are decoded so secrets inside them can be matched
+    let response_bytes =
+        decode_response_body(&mut parts.headers, response_bytes, max_response_size)?;
+
+    // Secrets are matched as UTF-8 bytes, so bring other charsets into line first
+    let response_bytes = if config.normalize_response_charset {
+        normalize_response_charset(&mut parts.headers, response_bytes)
+    } else {
+        response_bytes
+    };
+    let buffered = metrics::track_buffered_response(response_bytes.len());
+
+    // Anomaly guard: a response riddled with secrets points at a compromised
+    // or pathological upstream, so refuse it instead of redacting everything
+    let leaks = state.count_secrets_by_label_all(&response_bytes);
+    metrics::record_response_sanitization(&leaks);
+    let redactions: usize = leaks.values().sum();
+    if redactions > config.max_redactions_per_response {
+        let host = target_uri.host().unwrap_or("unknown");
+        tracing::error!(
+            "Response from {} contained {} secrets (limit {}), rejecting",
+            host,
+            redactions,
+            config.max_redactions_per_response
+        );
+        metrics::record_excessive_redactions(host);
+        return Err(ProxyError::ExcessiveRedactions(redactions));
+    }
+
+    // Value-aware JSON redaction keeps keys and structure intact; a secret
+    // it leaves behind (e.g. as a key) falls back to byte matching
+    let json_sanitized = (config.sanitize_json_values && is_json(&parts.headers))
+        .then(|| state.sanitize_json_all(&response_bytes))
+        .flatten()
+        .filter(|body| sanitization_verified(&state, body));
     // SECURITY FIX A: Use binary-safe sanitization for ALL responses
     // This prevents bypass via non-UTF-8 payloads
-    let sanitized_bytes = state.sanitize_bytes_all(&response_bytes);
-    let sanitized_body = sanitized_bytes.into_owned();
+    let mut sanitized_body = match json_sanitized {
+        Some(body) => body,
+        None => state.sanitize_bytes_all(&response_bytes).into_owned(),
+    };
+
+    // Error pages may echo a secret entity-encoded (`sk&#45;...`)
+    if config.decode_html_entities && is_html(&parts.headers) {
+        if sanitized_body.len() > config.max_html_entity_decode_size {
+            tracing::debug!(
+                "Skipping HTML entity decoding of {} byte response",
+                sanitized_body.len()
+            );
+        } else if let Some(redacted) = state.sanitize_html_entities_all(&sanitized_body) {
+            tracing::warn!("Redacted HTML entity-encoded secret from response");
+            sanitized_body = redacted;
+        }
+    }
 
     tracing::debug!(
         "Sanitized secrets from response ({} bytes)",
         sanitized_body.len()
     );
 
+    // A marker inside a JSON number or literal breaks the document while the
+    // content type still claims JSON
+    if is_json(&parts.headers)
+        && sanitized_body != response_bytes
+        && !parses_as_json(&sanitized_body)
+        && parses_as_json(&response_bytes)
+    {
+        tracing::warn!(
+            "Redaction left the JSON response from {} unparseable",
+            target_uri.host().unwrap_or("unknown")
+        );
+        metrics::record_sanitized_json_invalid();
+        if config.invalid_sanitized_json == InvalidSanitizedJson::TextPlain {
+            parts
+                .headers
+                .insert(header::CONTENT_TYPE, HeaderValue::from_static("text/plain"));
+        }
+    }
+
     // SECURITY FIX A: Paranoid verification on sanitized bytes
-    let verification = state.sanitize_bytes_all(&sanitized_body);
-    if verification != sanitized_body {
+    if !sanitization_verified(&state, &sanitized_body) {
         tracing::error!("Secret sanitization failed verification!");
-        return Err(ProxyError::ResponseBodyRead(
-            "Sanitization verification failed".to_string(),
-        ));
+        metrics::record_sanitization_verification_failed();
+        return Err(ProxyError::SanitizationVerificationFailed);
     }
 
     // SECURITY FIX B: Sanitize response headers
-    let sanitized_headers = state.sanitize_headers_all(&parts.headers);
+    let mut sanitized_headers = state
+        .sanitize_headers_all(&parts.headers)
+        .map_err(|e| ProxyError::OversizedHeader(e.to_string()))?;
+    state.strip_blocked_headers(&mut sanitized_headers);
+
+    // Compression only ever sees the sanitized body
+    let sanitized_body = if config.compress_responses {
+        compress_response_body(&headers, &mut sanitized_headers, sanitized_body)
+    } else {
+        sanitized_body
+    };
 
     // SECURITY FIX E: Build response with correct Content-Length
-    let final_headers = build_response_headers(&sanitized_headers, sanitized_body.len());
+    let final_headers =
+        build_response_headers_with_config(&sanitized_headers, sanitized_body.len(), &config);
 
     // Record metrics
     let duration = start_time.elapsed().as_secs_f64();
     let status = parts.status.as_u16();
 
-    // Build the final response
+    // Build the final response. Only the status code is copied: the upstream
+    // reason phrase (hyper's ReasonPhrase extension) is dropped so a secret
+    // echoed there can't reach the agent
     let mut response_builder = Response::builder().status(status);
     for (name, value) in final_headers.iter() {
         response_builder = response_builder.header(name, value);
     }
 
     let response = response_builder
-        .body(Body::from(sanitized_body))
+        .body(guarded_body(sanitized_body, buffered))
         .map_err(|e| ProxyError::ResponseBodyRead(format!("Failed to build response: {}", e)))?;
 
-    metrics::record_http_request(method.as_str(), status, endpoint, duration);
-    metrics::dec_active_connections();
+    metrics::record_http_request_traced(
+        method.as_str(),
+        status,
+        endpoint,
+        duration,
+        trace.as_ref(),
+    );
 
     tracing::info!("Proxy request completed successfully");
     Ok(response)
 }
 
+/// Build the upstream request: hop-by-hop headers dropped, identification and
+/// outbound header sanitization applied
+fn build_forwarded_request(
+    config: &ProxyConfig,
+    method: &Method,
+    target_uri: &Uri,
+    headers: &HeaderMap,
+    body: Bytes,
+) -> Result<hyper::Request<Body>, ProxyError> {
+    let mut forwarded_request = hyper::Request::builder()
+        .method(method.clone())
+        .uri(target_uri.clone());
+
+    let identification = config.proxy_identification.header(
+        headers
+            .get(header::USER_AGENT)
+            .and_then(|v| v.to_str().ok()),
+    );
+
+    // Copy relevant headers (skip hop-by-hop headers)
+    for (name, value) in headers.iter() {
+        let name_str = name.as_str();
+        if is_hop_by_hop_header(name_str) || name == header::CONTENT_LENGTH {
+            continue;
+        }
+        if identification
+            .as_ref()
+            .is_some_and(|(id_name, _)| name_str == *id_name)
+        {
+            continue;
+        }
+
+        forwarded_request = forwarded_request.header(name, value);
+    }
+    if let Some((name, value)) = identification {
+        forwarded_request = forwarded_request.header(name, value);
+    }
+
+    // Injection changes the body length, so the agent's Content-Length is never
+    // reused. A request that framed no body at all (e.g. a plain GET) gets none.
+    if !body.is_empty() || declares_body(headers) {
+        forwarded_request = forwarded_request.header(header::CONTENT_LENGTH, body.len());
+    }
+
+    forwarded_request
+        .body(Body::from(body))
+        .map_err(|e| ProxyError::ForwardRequest(format!("Failed to build request: {}", e)))
+}
+
+/// Check a response's media type against the allowlist (empty allows all)
+///
+/// Returns the offending media type, or `none` when the response declares none.
+fn check_response_content_type(allowed: &[String], headers: &HeaderMap) -> Result<(), String> {
+    if allowed.is_empty() {
+        return Ok(());
+    }
+    let Some(value) = headers.get(header::CONTENT_TYPE) else {
+        return Err("none".to_string());
+    };
+    let media_type = String::from_utf8_lossy(value.as_bytes())
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase();
+    let main_type = media_type.split('/').next().unwrap_or_default();
+
+    let is_allowed = allowed.iter().any(|entry| {
+        let entry = entry.trim().to_ascii_lowercase();
+        match entry.strip_suffix("/*") {
+            Some(wildcard) => wildcard == main_type,
+            None => entry == media_type,
+        }
+    });
+    if is_allowed {
+        Ok(())
+    } else {
+        Err(media_type)
+    }
+}
+
+/// Whether a response declares an HTML body
+fn is_html(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(';').next())
+        .is_some_and(|media_type| media_type.trim().eq_ignore_ascii_case("text/html"))
+}
+
+/// Whether the response declares JSON: `application/json` or a `+json` type
+fn is_json(headers: &HeaderMap) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(';').next())
+        .map(|media_type| media_type.trim().to_ascii_lowercase())
+        .is_some_and(|media_type| media_type == "application/json" || media_type.ends_with("+json"))
+}
+
+fn parses_as_json(body: &[u8]) -> bool {
+    serde_json::from_slice::<serde::de::IgnoredAny>(body).is_ok()
+}
+
+/// Whether the agent's request framed a body, even an empty one, rather than none
+fn declares_body(headers: &HeaderMap) -> bool {
+    headers.contains_key(header::CONTENT_LENGTH) || headers.contains_key(header::TRANSFER_ENCODING)
+}
+
+/// Where a followable redirect points, resolved against the current URL
+///
+/// Only 301, 302, 303, 307 and 308 with a usable `Location` are followed;
+/// anything else (e.g. 304) is returned to the agent as is.
+fn redirect_target(current: &Uri, status: StatusCode, headers: &HeaderMap) -> Option<Uri> {
+    if !matches!(status.as_u16(), 301 | 302 | 303 | 307 | 308) {
+        return None;
+    }
+    let location = headers.get(header::LOCATION)?.to_str().ok()?.trim();
+    let scheme = current.scheme_str().unwrap_or("http");
+
+    let resolved = if location.contains("://") {
+        location.to_string()
+    } else if let Some(rest) = location.strip_prefix("//") {
+        format!("{}://{}", scheme, rest)
+    } else {
+        let authority = current.authority()?.as_str();
+        if location.starts_with('/') {
+            format!("{}://{}{}", scheme, authority, location)
+        } else {
+            // Relative reference: replace the last path segment
+            let path = current.path();
+            let dir = &path[..path.rfind('/').map_or(0, |i| i + 1)];
+            let dir = if dir.is_empty() { "/" } else { dir };
+            format!("{}://{}{}{}", scheme, authority, dir, location)
+        }
+    };
+
+    let uri: Uri = resolved.parse().ok()?;
+    if !matches!(uri.scheme_str(), Some("http" | "https")) || uri.host().is_none() {
+        return None;
+    }
+    Some(uri)
+}
+
+/// Run the original target's host checks against a redirect target
+///
+/// The proxy itself, denied hosts and hosts outside the egress allowlist are
+/// refused, and so is any host outside the whitelist of a credential the
+/// request carries or, for an injected request, outside the global injection
+/// allowlist: the upstream, not the agent, chose where the redirect goes.
+async fn check_redirect_target(
+    state: &AppState,
+    config: &ProxyConfig,
+    headers: &HeaderMap,
+    body: &str,
+    target: &Uri,
+) -> Result<(), ProxyError> {
+    let host = target.host().unwrap_or_default();
+
+    if targets_proxy_itself(&target.to_string(), config.listen_addr) {
+        tracing::warn!("Refusing redirect to the proxy itself: {}", target);
+        return Err(ProxyError::SelfTarget(target.to_string()));
+    }
+    if state.is_host_denied(host) {
+        tracing::warn!("Refusing redirect to denied host: {}", host);
+        return Err(ProxyError::HostDenied(host.to_string()));
+    }
+    if !config.egress_allowed(host) {
+        tracing::warn!("Refusing redirect outside the egress allowlist: {}", host);
+        metrics::record_host_validation_blocked("egress_allowlist", host);
+        return Err(ProxyError::HostNotAllowed(host.to_string()));
+    }
+    if let Err(SecurityError::HostNotWhitelisted {
+        credential_type, ..
+    }) = detect_and_validate_strategies(&state.strategies(), headers, body, host)
+    {
+        tracing::error!(
+            "🚨 Refusing redirect: {} credential is not allowed for host '{}'",
+            credential_type,
+            host
+        );
+        metrics::record_host_validation_blocked(&credential_type, host);
+        return Err(ProxyError::RedirectDenied(host.to_string()));
+    }
+    let injects = state.inject_all(body) != body || state.inject_headers_all(&mut headers.clone());
+    if !config.injection_allowed(host) && injects {
+        tracing::error!(
+            "🚨 Refusing redirect: host '{}' is outside the injection allowlist",
+            host
+        );
+        metrics::record_host_validation_blocked("injection_allowlist", host);
+        return Err(ProxyError::RedirectDenied(host.to_string()));
+    }
+
+    Ok(())
+}
+
+/// Inject query-parameter credentials (e.g. `?key=DUMMY_X`) into the target
+///
+/// Strategies whose dummy is in the query must allow the host, and the host
+/// must be inside the injection allowlist, as for body and header injection.
+fn inject_target_uri(
+    state: &AppState,
+    config: &ProxyConfig,
+    target: Uri,
+) -> Result<Uri, ProxyError> {
+    let host = target.host().unwrap_or_default().to_string();
+    let Some(path_and_query) = target.path_and_query().map(|pq| pq.as_str().to_string()) else {
+        return Ok(target);
+    };
+    let Ok(uri) = HeaderValue::from_str(&path_and_query) else {
+        return Ok(target);
+    };
+    let mut pseudo_headers = HeaderMap::new();
+    pseudo_headers.insert("uri", uri);
+
+    let all_strategies = state.strategies();
+    let strategies =
+        match detect_and_validate_strategies(&all_strategies, &pseudo_headers, "", &host) {
+            Ok(strategies) => strategies,
+            Err(SecurityError::HostNotWhitelisted {
+                credential_type, ..
+            }) => {
+                tracing::error!(
+                    "🚨 Refusing to inject {} credential into the URI for host '{}'",
+                    credential_type,
+                    host
+                );
+                metrics::record_host_validation_blocked(&credential_type, &host);
+                return Err(ProxyError::InjectionHostNotAllowed(host));
+            }
+        };
+
+    let mut injected = path_and_query.clone();
+    for strategy in strategies
+        .into_iter()
+        .filter(|strategy| state.strategy_in_scope(strategy.name()))
+    {
+        injected = strategy
+            .inject_uri(&injected)
+            .map_err(|e| ProxyError::InjectionFailed(format!("{}: {}", strategy.name(), e)))?;
+    }
+    if injected == path_and_query {
+        return Ok(target);
+    }
+    if !config.injection_allowed(&host) {
+        tracing::error!(
+            "🚨 Refusing to inject credentials for host outside the injection allowlist: {}",
+            host
+        );
+        metrics::record_host_validation_blocked("injection_allowlist", &host);
+        return Err(ProxyError::InjectionHostNotAllowed(host));
+    }
+    state.record_strategy_use(path_and_query.as_bytes(), std::iter::empty());
+    tracing::debug!("Injected credentials into request URI");
+
+    let mut parts = target.into_parts();
+    parts.path_and_query = Some(injected.parse().map_err(|_| {
+        ProxyError::InvalidTargetUrl("Invalid query after credential injection".to_string())
+    })?);
+    Uri::from_parts(parts).map_err(|e| ProxyError::InvalidTargetUrl(e.to_string()))
+}
+
+/// Identity a request is accounted to: the mTLS client CN, else the peer IP
+pub(crate) fn client_identity(extensions: &axum::http::Extensions) -> String {
+    if let Some(cert) = extensions.get::<ClientCertInfo>() {
+        return cert.common_name.clone();
+    }
+    extensions
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip().to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
 /// Determine the target URL based on headers and configuration
 fn determine_target_url(headers: &HeaderMap, uri: &Uri) -> Result<String, ProxyError> {
     // Check for X-Target-URL header (allows agent to specify target)
@@ -388,21 +2292,531 @@ fn determine_target_url(headers: &HeaderMap, uri: &Uri) -> Result<String, ProxyE
             let scheme = "http"; // HTTP_PROXY typically uses http
             let path_and_query = uri.path_and_query().map(|pq| pq.as_str()).unwrap_or("/");
 
-            return Ok(format!("{}://{}{}", scheme, host_str, path_and_query));
+            return Ok(format!("{}://{}{}", scheme, host_str, path_and_query));
+        }
+    }
+
+    // Default to OpenAI API (fallback for legacy behavior)
+    let base_url =
+        std::env::var("OPENAI_API_URL").unwrap_or_else(|_| "https://api.openai.com".to_string());
+
+    let path_and_query = uri.path_and_query().map(|pq| pq.as_str()).unwrap_or("/");
+
+    Ok(format!(
+        "{}{}",
+        base_url.trim_end_matches('/'),
+        path_and_query
+    ))
+}
+
+/// Undo the body's `Content-Encoding` so it can be scanned, serving it as identity
+///
+/// A body in an unsupported coding is still sanitized byte for byte, but it
+/// is counted as unscanned: secrets inside the encoding cannot be seen. A
+/// body too large to decode, or corrupt, is refused rather than passed on.
+fn decode_response_body(
+    headers: &mut HeaderMap,
+    body: Bytes,
+    max_size: usize,
+) -> Result<Bytes, ProxyError> {
+    let Some(value) = headers.get(header::CONTENT_ENCODING) else {
+        return Ok(body);
+    };
+    let encodings = content_encoding::parse_encodings(&String::from_utf8_lossy(value.as_bytes()));
+    if content_encoding::is_identity(&encodings) {
+        headers.remove(header::CONTENT_ENCODING);
+        return Ok(body);
+    }
+
+    match content_encoding::decode_body(&encodings, &body, max_size) {
+        Ok(decoded) => {
+            headers.remove(header::CONTENT_ENCODING);
+            Ok(Bytes::from(decoded))
+        }
+        Err(e @ content_encoding::ContentEncodingError::Unsupported(_)) => {
+            tracing::warn!("Response body not scanned for secrets: {}", e);
+            metrics::record_unscanned_body(e.reason());
+            Ok(body)
+        }
+        Err(e) => {
+            tracing::error!("Refusing response body that cannot be scanned: {}", e);
+            metrics::record_unscanned_body(e.reason());
+            Err(ProxyError::UndecodableResponse(e.to_string()))
+        }
+    }
+}
+
+/// Transcode a body in a declared non-UTF-8 charset to UTF-8
+///
+/// The body is served as UTF-8 afterwards and the `charset` parameter of
+/// `Content-Type` is rewritten to match. Bodies with no charset, an unknown
+/// charset or a `Content-Encoding` are returned unchanged.
+fn normalize_response_charset(headers: &mut HeaderMap, body: Bytes) -> Bytes {
+    if headers.contains_key(header::CONTENT_ENCODING) {
+        return body;
+    }
+    let Some(content_type) = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+    else {
+        return body;
+    };
+
+    let mut params = content_type.split(';');
+    let mime = params.next().unwrap_or_default().trim();
+    let mut charset = None;
+    let mut other_params = Vec::new();
+    for param in params.map(str::trim).filter(|p| !p.is_empty()) {
+        match param.split_once('=') {
+            Some((name, value)) if name.trim().eq_ignore_ascii_case("charset") => {
+                charset = Some(value.trim().trim_matches('"'));
+            }
+            _ => other_params.push(param),
+        }
+    }
+
+    let Some(label) = charset else {
+        return body;
+    };
+    let Some(encoding) = encoding_rs::Encoding::for_label(label.as_bytes()) else {
+        tracing::warn!("Unknown response charset '{}', sanitizing as-is", label);
+        return body;
+    };
+    if encoding == encoding_rs::UTF_8 {
+        return body;
+    }
+
+    let (decoded, _, had_errors) = encoding.decode(&body);
+    if had_errors {
+        tracing::warn!(
+            "Response body is not valid {}, replaced malformed sequences",
+            encoding.name()
+        );
+    }
+
+    let mut new_content_type = format!("{}; charset=utf-8", mime);
+    for param in other_params {
+        new_content_type.push_str("; ");
+        new_content_type.push_str(param);
+    }
+    let Ok(new_content_type) = HeaderValue::from_str(&new_content_type) else {
+        return body;
+    };
+
+    tracing::debug!("Transcoded {} response body to UTF-8", encoding.name());
+    let decoded = Bytes::from(decoded.into_owned());
+    headers.insert(header::CONTENT_TYPE, new_content_type);
+    decoded
+}
+
+#[cfg(test)]
+thread_local! {
+    /// Test hook: make the paranoid re-verification report a mismatch
+    static FORCE_VERIFICATION_FAILURE: std::cell::Cell<bool> = const { std::cell::Cell::new(false) };
+}
+
+/// Test hook: force the next verifications on this thread to fail (or stop forcing)
+#[cfg(test)]
+pub(crate) fn force_verification_failure(enabled: bool) {
+    FORCE_VERIFICATION_FAILURE.with(|force| force.set(enabled));
+}
+
+/// Whether a second sanitization pass leaves the sanitized body unchanged
+fn sanitization_verified(state: &AppState, sanitized: &[u8]) -> bool {
+    #[cfg(test)]
+    if FORCE_VERIFICATION_FAILURE.with(std::cell::Cell::get) {
+        return false;
+    }
+    *state.sanitize_bytes_all(sanitized) == *sanitized
+}
+
+/// Compress a sanitized body with the best coding the client accepts
+///
+/// Bodies still carrying an upstream encoding (ones the proxy could not
+/// decode) are left alone. The chosen coding is recorded in `headers`, so the
+/// response headers are built for the compressed length.
+fn compress_response_body(
+    client_headers: &HeaderMap,
+    headers: &mut HeaderMap,
+    body: Vec<u8>,
+) -> Vec<u8> {
+    if headers.contains_key(header::CONTENT_ENCODING) {
+        return body;
+    }
+
+    // The representation now depends on the client's Accept-Encoding
+    let vary = match headers.get(header::VARY).and_then(|v| v.to_str().ok()) {
+        Some(existing) if existing.to_ascii_lowercase().contains("accept-encoding") => None,
+        Some(existing) => Some(format!("{}, Accept-Encoding", existing)),
+        None => Some("Accept-Encoding".to_string()),
+    };
+    if let Some(vary) = vary.and_then(|v| HeaderValue::from_str(&v).ok()) {
+        headers.insert(header::VARY, vary);
+    }
+
+    let Some(coding) = client_headers
+        .get(header::ACCEPT_ENCODING)
+        .and_then(|v| v.to_str().ok())
+        .and_then(content_encoding::negotiate_encoding)
+    else {
+        return body;
+    };
+    if body.is_empty() {
+        return body;
+    }
+
+    match content_encoding::encode_body(coding, &body) {
+        Ok(compressed) => {
+            headers.insert(header::CONTENT_ENCODING, HeaderValue::from_static(coding));
+            compressed
+        }
+        Err(e) => {
+            tracing::warn!(
+                "Failed to {} response body, sending it uncompressed: {}",
+                coding,
+                e
+            );
+            body
+        }
+    }
+}
+
+/// Wrap a buffered body so it stays counted as in flight until it is sent
+///
+/// The guard is released on the poll after the body has been handed to the
+/// connection, or when the response is dropped unsent.
+fn guarded_body(body: Vec<u8>, guard: metrics::BufferedResponseGuard) -> Body {
+    let stream = futures::stream::unfold((Some(body), guard), |(body, guard)| async move {
+        body.map(|bytes| {
+            (
+                Ok::<_, std::convert::Infallible>(axum::body::Bytes::from(bytes)),
+                (None, guard),
+            )
+        })
+    });
+    Body::from_stream(stream)
+}
+
+/// Sanitize an upstream body chunk by chunk without buffering it
+///
+/// The redaction anomaly guard still applies: once the limit is exceeded the
+/// stream is aborted, so the client sees a truncated response rather than
+/// more redacted data. An upstream silent for longer than `read_timeout`
+/// between chunks aborts the stream too. With `quota`, each upstream chunk
+/// is charged to the identity as it arrives.
+fn sanitize_stream(
+    upstream: Body,
+    decoder: Option<content_encoding::StreamDecoder>,
+    sanitizer: StreamingSanitizer,
+    max_redactions: usize,
+    read_timeout: Duration,
+    host: String,
+    quota: Option<(Arc<ByteQuota>, String)>,
+) -> Body {
+    struct StreamState {
+        upstream: axum::body::BodyDataStream,
+        decoder: Option<content_encoding::StreamDecoder>,
+        sanitizer: StreamingSanitizer,
+        quota: Option<(Arc<ByteQuota>, String)>,
+        done: bool,
+    }
+
+    let state = StreamState {
+        upstream: upstream.into_data_stream(),
+        decoder,
+        sanitizer,
+        quota,
+        done: false,
+    };
+
+    let stream = futures::stream::unfold(state, move |mut st| {
+        let host = host.clone();
+        async move {
+            while !st.done {
+                let Ok(next) = tokio::time::timeout(read_timeout, st.upstream.next()).await else {
+                    tracing::error!(
+                        "Streamed response from {} sent nothing for {:?}, aborting",
+                        host,
+                        read_timeout
+                    );
+                    metrics::record_upstream_timeout("read");
+                    st.done = true;
+                    return Some((
+                        Err(std::io::Error::new(
+                            std::io::ErrorKind::TimedOut,
+                            "Upstream read timed out",
+                        )),
+                        st,
+                    ));
+                };
+                let decoded = match next {
+                    Some(Ok(data)) => {
+                        if let Some((quota, identity)) = &st.quota {
+                            quota.record(identity, data.len() as u64);
+                        }
+                        match &mut st.decoder {
+                            Some(decoder) => decoder.push(&data),
+                            None => Ok(data.to_vec()),
+                        }
+                    }
+                    Some(Err(e)) => {
+                        st.done = true;
+                        return Some((Err(std::io::Error::other(e)), st));
+                    }
+                    None => {
+                        st.done = true;
+                        st.decoder
+                            .take()
+                            .map_or(Ok(Vec::new()), |decoder| decoder.finish())
+                    }
+                };
+                // The encoding header is gone, so undecodable data cannot be passed on
+                let decoded = match decoded {
+                    Ok(decoded) => decoded,
+                    Err(e) => {
+                        tracing::error!(
+                            "Failed to decode streamed response from {}, aborting: {}",
+                            host,
+                            e
+                        );
+                        metrics::record_unscanned_body(e.reason());
+                        st.done = true;
+                        return Some((Err(std::io::Error::other(e)), st));
+                    }
+                };
+                let mut chunk = st.sanitizer.push(&decoded);
+                if st.done {
+                    chunk.extend(st.sanitizer.finish());
+                }
+
+                if st.sanitizer.line_too_long() {
+                    tracing::error!(
+                        "Streamed response from {} sent an unterminated line over the length limit, aborting",
+                        host
+                    );
+                    metrics::record_stream_line_too_long();
+                    st.done = true;
+                    return Some((Err(std::io::Error::other("Upstream line too long")), st));
+                }
+
+                if st.sanitizer.redactions() > max_redactions {
+                    tracing::error!(
+                        "Streamed response from {} exceeded {} redactions, aborting",
+                        host,
+                        max_redactions
+                    );
+                    metrics::record_excessive_redactions(&host);
+                    st.done = true;
+                    return Some((Err(std::io::Error::other("Upstream response rejected")), st));
+                }
+
+                if !chunk.is_empty() {
+                    return Some((Ok(axum::body::Bytes::from(chunk)), st));
+                }
+            }
+            None
+        }
+    });
+
+    Body::from_stream(stream)
+}
+
+/// Whether a response declares no content encoding other than identity
+fn is_identity_encoded(headers: &HeaderMap) -> bool {
+    headers.get(header::CONTENT_ENCODING).is_none_or(|value| {
+        content_encoding::is_identity(&content_encoding::parse_encodings(
+            &String::from_utf8_lossy(value.as_bytes()),
+        ))
+    })
+}
+
+/// Whether a response declares `media_type` (parameters ignored)
+fn has_media_type(headers: &HeaderMap, media_type: &str) -> bool {
+    headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.split(';').next())
+        .is_some_and(|declared| declared.trim().eq_ignore_ascii_case(media_type))
+}
+
+/// Check whether a path matches one of the configured probe patterns
+///
+/// Patterns match exactly, or by prefix when they end in `*`.
+fn is_probe_path(path: &str, patterns: &[String]) -> bool {
+    patterns.iter().any(|pattern| path_matches(path, pattern))
+}
+
+/// Check whether credentials may be injected into a request for `path`
+///
+/// The first route whose pattern matches decides; other paths inject.
+pub(crate) fn injection_enabled(path: &str, routes: &[RouteConfig]) -> bool {
+    routes
+        .iter()
+        .find(|route| path_matches(path, &route.path))
+        .is_none_or(|route| route.inject)
+}
+
+/// Match a path exactly, or by prefix when the pattern ends in `*`
+fn path_matches(path: &str, pattern: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) => path.starts_with(prefix),
+        None => path == pattern,
+    }
+}
+
+/// Read the client request body within a size limit and a time limit
+///
+/// The timeout guards against slowloris-style clients that send headers and
+/// then dribble the body; it is independent of any upstream timeout.
+async fn read_request_body(
+    body: Body,
+    max_size: usize,
+    timeout: Duration,
+) -> Result<axum::body::Bytes, ProxyError> {
+    tokio::time::timeout(timeout, axum::body::to_bytes(body, max_size))
+        .await
+        .map_err(|_| {
+            tracing::warn!("Client did not send request body within {:?}", timeout);
+            ProxyError::RequestBodyTimeout(timeout)
+        })?
+        .map_err(|e| {
+            let err_str = e.to_string();
+            if err_str.contains("length limit") {
+                ProxyError::RequestBodyTooLarge(max_size)
+            } else {
+                ProxyError::RequestBodyRead(err_str)
+            }
+        })
+}
+
+/// Classify a failed upstream exchange
+///
+/// A response hyper could not parse means the target is not speaking HTTP
+/// (e.g. `x-target-url` pointing at an SSH or database port), which is a
+/// misconfiguration rather than an API or network failure.
+fn forward_error(err: hyper_util::client::legacy::Error) -> ProxyError {
+    let mut source = std::error::Error::source(&err);
+    while let Some(cause) = source {
+        if let Some(SelfConnect(peer)) = cause.downcast_ref::<SelfConnect>() {
+            return ProxyError::SelfTarget(peer.to_string());
+        }
+        if err.is_connect()
+            && cause
+                .downcast_ref::<std::io::Error>()
+                .is_some_and(|e| e.kind() == std::io::ErrorKind::TimedOut)
+        {
+            metrics::record_upstream_timeout("connect");
+            return ProxyError::UpstreamTimeout("connecting".to_string());
+        }
+        if let Some(hyper_err) = cause.downcast_ref::<hyper::Error>() {
+            if hyper_err.is_parse() {
+                tracing::warn!("Upstream responded with non-HTTP data: {}", hyper_err);
+                metrics::record_upstream_protocol_error();
+                return ProxyError::UpstreamProtocolError(hyper_err.to_string());
+            }
         }
+        source = cause.source();
     }
+    ProxyError::ForwardRequest(err.to_string())
+}
 
-    // Default to OpenAI API (fallback for legacy behavior)
-    let base_url =
-        std::env::var("OPENAI_API_URL").unwrap_or_else(|_| "https://api.openai.com".to_string());
+/// Whether a connect failure is [`GuardedConnector`] refusing the proxy itself
+fn is_self_connect(err: &hyper_util::client::legacy::Error) -> bool {
+    let mut source = std::error::Error::source(err);
+    while let Some(cause) = source {
+        if cause.is::<SelfConnect>() {
+            return true;
+        }
+        source = cause.source();
+    }
+    false
+}
 
-    let path_and_query = uri.path_and_query().map(|pq| pq.as_str()).unwrap_or("/");
+/// An upstream response, with the `Link` values of any early hints before it
+type UpstreamResponse = (
+    hyper::Response<hyper::body::Incoming>,
+    Arc<Mutex<Vec<HeaderValue>>>,
+);
 
-    Ok(format!(
-        "{}{}",
-        base_url.trim_end_matches('/'),
-        path_and_query
-    ))
+/// Send the request from `build` upstream, retrying per `upstream_retry`
+///
+/// Each attempt sends a freshly built request; every attempt and backoff
+/// shares `deadline`, and no retry is started that could not finish by it.
+/// After the last attempt its response (a 502 included) or error is returned.
+async fn send_upstream(
+    state: &AppState,
+    config: &ProxyConfig,
+    deadline: tokio::time::Instant,
+    build: impl Fn() -> Result<hyper::Request<Body>, ProxyError>,
+) -> Result<UpstreamResponse, ProxyError> {
+    let policy = config.upstream_retry;
+    let mut attempt = 1;
+
+    loop {
+        let mut forwarded_request = build()?;
+        let idempotent = matches!(*forwarded_request.method(), Method::GET | Method::HEAD);
+        let early_hints = capture_early_hints(&mut forwarded_request);
+
+        let result =
+            tokio::time::timeout_at(deadline, state.http_client.request(forwarded_request))
+                .await
+                .map_err(|_| upstream_timeout(config))?;
+
+        // Only a connection that was never made is safe to retry for every method
+        let reason = match &result {
+            Err(e) if e.is_connect() && !is_self_connect(e) => Some("connect"),
+            Err(_) if idempotent => Some("error"),
+            Ok(response)
+                if idempotent
+                    && matches!(
+                        response.status(),
+                        StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE
+                    ) =>
+            {
+                Some("status")
+            }
+            _ => None,
+        };
+        let delay = policy.delay(attempt);
+        let retry = reason.filter(|_| {
+            attempt < policy.max_attempts && tokio::time::Instant::now() + delay < deadline
+        });
+
+        let Some(reason) = retry else {
+            if attempt > 1 {
+                let recovered = reason.is_none() && result.is_ok();
+                metrics::record_upstream_retry_outcome(if recovered {
+                    "recovered"
+                } else {
+                    "exhausted"
+                });
+            }
+            return result
+                .map(|response| (response, early_hints))
+                .map_err(forward_error);
+        };
+
+        tracing::warn!(
+            "Upstream attempt {} of {} failed ({}), retrying in {:?}",
+            attempt,
+            policy.max_attempts,
+            reason,
+            delay
+        );
+        metrics::record_upstream_retry(reason);
+        tokio::time::sleep(delay).await;
+        attempt += 1;
+    }
+}
+
+/// Error for an upstream that missed `upstream_response_timeout`
+fn upstream_timeout(config: &ProxyConfig) -> ProxyError {
+    tracing::warn!(
+        "Upstream did not respond within {:?}",
+        config.upstream_response_timeout
+    );
+    metrics::record_upstream_timeout("response");
+    ProxyError::UpstreamTimeout(format!("after {:?}", config.upstream_response_timeout))
 }
 
 /// Forward request directly without sanitization (for local services)
@@ -414,12 +2828,15 @@ async fn forward_directly(
     request: Request,
 ) -> Result<Response, ProxyError> {
     let start_time = Instant::now();
-    metrics::inc_active_connections();
+    let config = state.config.clone().unwrap_or_default();
 
     // Read request body
-    let body_bytes = axum::body::to_bytes(request.into_body(), DEFAULT_MAX_REQUEST_SIZE)
-        .await
-        .map_err(|e| ProxyError::RequestBodyRead(e.to_string()))?;
+    let body_bytes = read_request_body(
+        request.into_body(),
+        config.max_request_size,
+        config.request_body_timeout,
+    )
+    .await?;
 
     // Determine target URL
     let target_url = determine_target_url(&headers, &uri)?;
@@ -443,24 +2860,36 @@ async fn forward_directly(
         }
     }
 
-    let forwarded_request = forwarded_request
+    let mut forwarded_request = forwarded_request
         .body(Body::from(body_bytes))
         .map_err(|e| ProxyError::ForwardRequest(format!("Failed to build request: {}", e)))?;
+    let early_hints = capture_early_hints(&mut forwarded_request);
 
     // Execute request
-    let response = state
-        .http_client
-        .request(forwarded_request)
+    let deadline = tokio::time::Instant::now() + config.upstream_response_timeout;
+    let response = tokio::time::timeout_at(deadline, state.http_client.request(forwarded_request))
         .await
-        .map_err(|e| ProxyError::ForwardRequest(e.to_string()))?;
-
-    let (parts, body) = response.into_parts();
+        .map_err(|_| upstream_timeout(&config))?
+        .map_err(forward_error)?;
+
+    let (mut parts, body) = response.into_parts();
+    if state
+        .config
+        .as_ref()
+        .is_none_or(|config| config.forward_early_hints)
+    {
+        merge_early_hints(&mut parts.headers, &early_hints);
+    }
     let body = Body::new(body);
 
     // Read response body
-    let response_bytes = axum::body::to_bytes(body, DEFAULT_MAX_RESPONSE_SIZE)
-        .await
-        .map_err(|e| ProxyError::ResponseBodyRead(e.to_string()))?;
+    let response_bytes = tokio::time::timeout_at(
+        deadline,
+        axum::body::to_bytes(body, config.max_response_size),
+    )
+    .await
+    .map_err(|_| upstream_timeout(&config))?
+    .map_err(|e| ProxyError::ResponseBodyRead(e.to_string()))?;
 
     // Build response with headers
     let final_headers = build_response_headers(&parts.headers, response_bytes.len());
@@ -476,10 +2905,9 @@ async fn forward_directly(
 
     let duration = start_time.elapsed().as_secs_f64();
     let status = parts.status.as_u16();
-    let endpoint = uri.path().split('/').nth(1).unwrap_or("unknown");
+    let endpoint = metrics::normalize_endpoint(uri.path(), &config.endpoint_templates);
 
     metrics::record_http_request(method.as_str(), status, endpoint, duration);
-    metrics::dec_active_connections();
 
     tracing::info!("Direct forward completed successfully");
     Ok(response)
@@ -501,10 +2929,181 @@ fn is_hop_by_hop_header(name: &str) -> bool {
     )
 }
 
+/// Redact real secrets the agent put in request headers
+///
+/// Auth headers are skipped unless `include_auth`. Must run before injection:
+/// afterwards the real credentials a strategy injected (into any header its
+/// targets cover) would be redacted too.
+fn sanitize_agent_headers(state: &AppState, headers: &mut HeaderMap, include_auth: bool) {
+    for (name, value) in headers.iter_mut() {
+        if !include_auth && is_auth_header(name.as_str()) {
+            continue;
+        }
+        let Ok(value_str) = value.to_str() else {
+            continue;
+        };
+        let sanitized = state.sanitize_all(value_str);
+        if sanitized != value_str {
+            tracing::warn!("Redacted real secret from outbound {} header", name);
+            *value = HeaderValue::from_str(&sanitized)
+                .unwrap_or(HeaderValue::from_static(crate::sanitizer::REDACTED_MARKER));
+        }
+    }
+}
+
+/// Check if a header is meant to carry credentials upstream
+fn is_auth_header(name: &str) -> bool {
+    matches!(
+        name.to_lowercase().as_str(),
+        "authorization" | "x-api-key" | "api-key"
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn cors_upstream_headers() -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("access-control-allow-origin", HeaderValue::from_static("*"));
+        headers.insert(
+            "access-control-allow-credentials",
+            HeaderValue::from_static("true"),
+        );
+        headers.insert("content-type", HeaderValue::from_static("application/json"));
+        headers
+    }
+
+    #[test]
+    fn test_strip_upstream_cors_applies_proxy_policy() {
+        let mut add_response_headers = HeaderMap::new();
+        add_response_headers.insert(
+            "access-control-allow-origin",
+            HeaderValue::from_static("https://agent.local"),
+        );
+        let config = ProxyConfig {
+            strip_upstream_cors: true,
+            add_response_headers,
+            ..Default::default()
+        };
+
+        let headers = build_response_headers_with_config(&cors_upstream_headers(), 10, &config);
+
+        assert_eq!(
+            headers.get("access-control-allow-origin").unwrap(),
+            "https://agent.local"
+        );
+        assert!(headers.get("access-control-allow-credentials").is_none());
+        assert_eq!(headers.get("content-type").unwrap(), "application/json");
+    }
+
+    #[test]
+    fn test_upstream_cors_passed_through_when_disabled() {
+        let headers = build_response_headers_with_config(
+            &cors_upstream_headers(),
+            10,
+            &ProxyConfig::default(),
+        );
+
+        assert_eq!(headers.get("access-control-allow-origin").unwrap(), "*");
+        assert_eq!(
+            headers.get("access-control-allow-credentials").unwrap(),
+            "true"
+        );
+    }
+
+    #[test]
+    fn test_normalize_response_charset() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("text/plain; charset=\"ISO-8859-1\"; format=flowed"),
+        );
+        let body = normalize_response_charset(&mut headers, Bytes::from_static(b"caf\xe9"));
+        assert_eq!(&body[..], "café".as_bytes());
+        assert_eq!(
+            headers.get(header::CONTENT_TYPE).unwrap(),
+            "text/plain; charset=utf-8; format=flowed"
+        );
+
+        // No charset, unknown charset and compressed bodies are left alone
+        for content_type in ["application/json", "application/json; charset=bogus"] {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+            let body = normalize_response_charset(&mut headers, Bytes::from_static(b"\xe9"));
+            assert_eq!(&body[..], b"\xe9");
+            assert_eq!(headers.get(header::CONTENT_TYPE).unwrap(), content_type);
+        }
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/json; charset=utf-16le"),
+        );
+        headers.insert(header::CONTENT_ENCODING, HeaderValue::from_static("gzip"));
+        let body = normalize_response_charset(&mut headers, Bytes::from_static(b"\x1f\x8b"));
+        assert_eq!(&body[..], b"\x1f\x8b");
+    }
+
+    #[test]
+    fn test_proxy_identification_header() {
+        assert_eq!(ProxyIdentification::Off.header(Some("agent/1.0")), None);
+        assert_eq!(
+            ProxyIdentification::UserAgent.header(Some("agent/1.0")),
+            Some(("user-agent", format!("agent/1.0 {}", PROXY_PRODUCT_TOKEN)))
+        );
+        assert_eq!(
+            ProxyIdentification::UserAgent.header(None),
+            Some(("user-agent", PROXY_PRODUCT_TOKEN.to_string()))
+        );
+        // Already identified (e.g. chained proxies): not appended twice
+        let identified = format!("agent/1.0 {}", PROXY_PRODUCT_TOKEN);
+        assert_eq!(
+            ProxyIdentification::UserAgent.header(Some(&identified)),
+            Some(("user-agent", identified.clone()))
+        );
+        assert_eq!(
+            ProxyIdentification::parse(" User-Agent "),
+            Some(ProxyIdentification::UserAgent)
+        );
+        assert_eq!(ProxyIdentification::parse("bogus"), None);
+    }
+
+    #[test]
+    fn test_is_json() {
+        let with_type = |value: &'static str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(value));
+            headers
+        };
+        assert!(is_json(&with_type("application/json; charset=utf-8")));
+        assert!(is_json(&with_type("application/problem+JSON")));
+        assert!(!is_json(&with_type("application/x-ndjson")));
+        assert!(!is_json(&HeaderMap::new()));
+        assert_eq!(
+            InvalidSanitizedJson::parse("Text_Plain"),
+            Some(InvalidSanitizedJson::TextPlain)
+        );
+    }
+
+    #[test]
+    fn test_is_auth_header() {
+        assert!(is_auth_header("Authorization"));
+        assert!(is_auth_header("x-api-key"));
+        assert!(!is_auth_header("referer"));
+        assert!(!is_auth_header("traceparent"));
+    }
+
+    #[test]
+    fn test_is_probe_path() {
+        let patterns = vec!["/v1/ping".to_string(), "/v1/healthz/*".to_string()];
+        assert!(is_probe_path("/v1/ping", &patterns));
+        assert!(is_probe_path("/v1/healthz/live", &patterns));
+        assert!(!is_probe_path("/v1/ping/extra", &patterns));
+        assert!(!is_probe_path("/v1/chat", &patterns));
+        assert!(!is_probe_path("/v1/ping", &[]));
+    }
+
     #[test]
     fn test_is_hop_by_hop_header() {
         assert!(is_hop_by_hop_header("connection"));
@@ -514,6 +3113,80 @@ mod tests {
         assert!(!is_hop_by_hop_header("content-type"));
     }
 
+    #[test]
+    fn test_apply_rewrite_rules() {
+        let rules = vec![
+            RewriteRule::new(
+                r"^/v1/models/(?P<model>[^/]+)/chat$",
+                "/chat/${model}/completions",
+            )
+            .unwrap(),
+            RewriteRule::new(r"^/v1/chat$", "/chat/completions").unwrap(),
+            RewriteRule::new(r"^/v1/(.*)$", "/never/$1").unwrap(),
+        ];
+
+        // Captures are substituted and the query string survives
+        let uri: Uri = "/v1/models/gpt-4/chat?stream=true".parse().unwrap();
+        let (method, rewritten) = apply_rewrite_rules(&rules, &Method::POST, &uri);
+        assert_eq!(method, Method::POST);
+        assert_eq!(rewritten, "/chat/gpt-4/completions?stream=true");
+
+        // First matching rule wins
+        let (_, rewritten) =
+            apply_rewrite_rules(&rules, &Method::POST, &"/v1/chat".parse().unwrap());
+        assert_eq!(rewritten, "/chat/completions");
+
+        // Unmatched paths are left alone
+        let uri: Uri = "/other".parse().unwrap();
+        assert_eq!(apply_rewrite_rules(&rules, &Method::GET, &uri).1, uri);
+    }
+
+    #[test]
+    fn test_apply_rewrite_rules_method() {
+        let rules = vec![RewriteRule::new(r"^/v1/search$", "/search")
+            .unwrap()
+            .with_method(Method::POST)];
+
+        let (method, rewritten) =
+            apply_rewrite_rules(&rules, &Method::GET, &"/v1/search".parse().unwrap());
+        assert_eq!(method, Method::POST);
+        assert_eq!(rewritten, "/search");
+    }
+
+    #[test]
+    fn test_redirect_target_resolves_location() {
+        let current: Uri = "https://api.example.com/v1/chat?x=1".parse().unwrap();
+        let target = |status: u16, location: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::LOCATION, HeaderValue::from_str(location).unwrap());
+            redirect_target(&current, StatusCode::from_u16(status).unwrap(), &headers)
+                .map(|uri| uri.to_string())
+        };
+
+        assert_eq!(
+            target(302, "http://other.example.com/x").as_deref(),
+            Some("http://other.example.com/x")
+        );
+        assert_eq!(
+            target(307, "//cdn.example.com/a").as_deref(),
+            Some("https://cdn.example.com/a")
+        );
+        assert_eq!(
+            target(308, "/v2/chat").as_deref(),
+            Some("https://api.example.com/v2/chat")
+        );
+        assert_eq!(
+            target(301, "completions?y=2").as_deref(),
+            Some("https://api.example.com/v1/completions?y=2")
+        );
+        assert_eq!(target(304, "/v2/chat"), None);
+        assert_eq!(target(302, "ftp://files.example.com/a"), None);
+        assert_eq!(
+            redirect_target(&current, StatusCode::FOUND, &HeaderMap::new()),
+            None
+        );
+    }
+
     #[test]
     fn test_determine_target_url_default() {
         let headers = HeaderMap::new();
@@ -613,6 +3286,131 @@ mod tests {
         assert!(should_bypass_proxy(&uri, &headers));
     }
 
+    #[test]
+    fn test_merge_early_hints_appends_links() {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            header::LINK,
+            HeaderValue::from_static("</app.js>; rel=preload"),
+        );
+        let hints = Mutex::new(vec![
+            HeaderValue::from_static("</style.css>; rel=preload"),
+            HeaderValue::from_static("</app.js>; rel=preload"),
+        ]);
+
+        merge_early_hints(&mut headers, &hints);
+
+        assert_eq!(
+            headers.get(header::LINK).unwrap(),
+            "</app.js>; rel=preload, </style.css>; rel=preload"
+        );
+    }
+
+    #[test]
+    fn test_merge_early_hints_empty_is_noop() {
+        let mut headers = HeaderMap::new();
+        merge_early_hints(&mut headers, &Mutex::new(Vec::new()));
+        assert!(!headers.contains_key(header::LINK));
+    }
+
+    #[test]
+    fn test_targets_proxy_itself_loopback_on_listen_port() {
+        let listen = DEFAULT_LISTEN_ADDR;
+        assert!(targets_proxy_itself(
+            "http://localhost:3000/v1/chat",
+            listen
+        ));
+        assert!(targets_proxy_itself("http://api.localhost:3000/", listen));
+        assert!(targets_proxy_itself("http://127.0.0.1:3000/", listen));
+        assert!(targets_proxy_itself("http://[::1]:3000/", listen));
+        assert!(targets_proxy_itself(
+            "http://[::ffff:127.0.0.1]:3000/",
+            listen
+        ));
+        assert!(targets_proxy_itself("http://0.0.0.0:3000/", listen));
+    }
+
+    #[test]
+    fn test_targets_proxy_itself_bound_address() {
+        let listen: SocketAddr = "10.1.2.3:8080".parse().unwrap();
+        assert!(targets_proxy_itself("http://10.1.2.3:8080/", listen));
+        assert!(!targets_proxy_itself("http://10.1.2.4:8080/", listen));
+        assert!(!targets_proxy_itself("http://10.1.2.3:3000/", listen));
+        // Not reachable through loopback when bound to one interface
+        assert!(!targets_proxy_itself("http://127.0.0.1:8080/", listen));
+
+        let loopback: SocketAddr = "127.0.0.1:8080".parse().unwrap();
+        assert!(targets_proxy_itself("http://localhost:8080/", loopback));
+        assert!(targets_proxy_itself("http://0.0.0.0:8080/", loopback));
+        assert!(!targets_proxy_itself("http://10.1.2.3:8080/", loopback));
+    }
+
+    #[test]
+    fn test_targets_proxy_itself_local_interface_when_bound_to_all() {
+        let listen = DEFAULT_LISTEN_ADDR;
+        // A documentation address on no interface of the test host
+        assert!(!targets_proxy_itself("http://203.0.113.77:3000/", listen));
+
+        for ip in local_addresses() {
+            let url = format!("http://{}/", SocketAddr::new(*ip, 3000));
+            assert!(targets_proxy_itself(&url, listen), "{}", url);
+        }
+    }
+
+    #[test]
+    fn test_targets_proxy_itself_other_port() {
+        let listen = DEFAULT_LISTEN_ADDR;
+        assert!(!targets_proxy_itself("http://localhost:8080/", listen));
+        assert!(!targets_proxy_itself("http://127.0.0.1/", listen));
+        assert!(!targets_proxy_itself("https://api.openai.com/v1", listen));
+    }
+
+    #[tokio::test]
+    async fn test_connector_refuses_the_proxy_listener() {
+        // Stands in for the proxy; the connector must never hand its connection out
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let listen_addr = listener.local_addr().unwrap();
+        let client = create_http_client_with_config(&ProxyConfig {
+            listen_addr,
+            ..ProxyConfig::default()
+        });
+
+        // Straight to the client, past the up-front check, as a rebound name would be
+        let url = format!("http://localhost:{}/", listen_addr.port());
+        let request = hyper::Request::get(url).body(Body::empty()).unwrap();
+        let err = client.request(request).await.unwrap_err();
+        assert!(is_self_connect(&err));
+        assert!(matches!(forward_error(err), ProxyError::SelfTarget(_)));
+    }
+
+    #[test]
+    fn test_check_response_content_type() {
+        let allowed = vec!["application/json".to_string(), "text/*".to_string()];
+        let with_type = |value: &'static str| {
+            let mut headers = HeaderMap::new();
+            headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(value));
+            headers
+        };
+
+        assert!(check_response_content_type(&allowed, &with_type("application/json")).is_ok());
+        assert!(check_response_content_type(
+            &allowed,
+            &with_type("Application/JSON; charset=utf-8")
+        )
+        .is_ok());
+        assert!(check_response_content_type(&allowed, &with_type("text/event-stream")).is_ok());
+        assert_eq!(
+            check_response_content_type(&allowed, &with_type("application/octet-stream")),
+            Err("application/octet-stream".to_string())
+        );
+        assert_eq!(
+            check_response_content_type(&allowed, &HeaderMap::new()),
+            Err("none".to_string())
+        );
+        // No allowlist, no restriction
+        assert!(check_response_content_type(&[], &HeaderMap::new()).is_ok());
+    }
+
     #[test]
     fn test_should_not_bypass_proxy_for_external_host() {
         let mut headers = HeaderMap::new();
@@ -621,4 +3419,405 @@ mod tests {
 
         assert!(!should_bypass_proxy(&uri, &headers));
     }
+
+    /// Start a mock upstream that echoes a secret in a fixed 200 response
+    async fn start_secret_upstream() -> u16 {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let mut buf = vec![0u8; 8192];
+                    let _ = stream.read(&mut buf).await;
+                    let _ = stream
+                        .write_all(
+                            b"HTTP/1.1 200 OK\r\nContent-Length: 21\r\n\r\n{\"key\":\"real_secret\"}",
+                        )
+                        .await;
+                });
+            }
+        });
+        port
+    }
+
+    async fn proxy_to(port: u16) -> Result<Response, ProxyError> {
+        let mut secrets = std::collections::HashMap::new();
+        secrets.insert("DUMMY_KEY".to_string(), "real_secret".to_string());
+        let state = AppState::new(
+            Arc::new(crate::sanitizer::SecretMap::new(secrets).unwrap()),
+            create_http_client(),
+        );
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "x-target-url",
+            HeaderValue::from_str(&format!("http://0.0.0.0:{}", port)).unwrap(),
+        );
+        let uri: Uri = "/v1/chat".parse().unwrap();
+        let mut request = Request::new(Body::from("{}"));
+        *request.headers_mut() = headers.clone();
+
+        proxy_handler(State(state), Method::POST, uri, headers, request).await
+    }
+
+    #[tokio::test]
+    async fn test_verification_failure_refuses_response() {
+        let port = start_secret_upstream().await;
+        let before = metrics::SANITIZATION_VERIFICATION_FAILED_TOTAL.get();
+
+        force_verification_failure(true);
+        let result = proxy_to(port).await;
+        force_verification_failure(false);
+
+        assert!(matches!(
+            result,
+            Err(ProxyError::SanitizationVerificationFailed)
+        ));
+        assert!(metrics::SANITIZATION_VERIFICATION_FAILED_TOTAL.get() > before);
+        assert_eq!(
+            ProxyError::SanitizationVerificationFailed
+                .into_response()
+                .status(),
+            StatusCode::BAD_GATEWAY
+        );
+    }
+
+    #[tokio::test]
+    async fn test_verification_passes_without_fault() {
+        let port = start_secret_upstream().await;
+
+        let response = proxy_to(port).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), 1024)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"{\"key\":\"[REDACTED]\"}");
+    }
+
+    const FULL_CONFIG: &str = r#"
+strategies:
+  - name: openai
+    type: bearer
+    config:
+      env_var: OPENAI_API_KEY
+      dummy_pattern: DUMMY_OPENAI
+proxy:
+  mode: sanitize_only
+  max_request_size: 1024
+  max_response_size: 2048
+  stream_response_threshold: 4096
+  stream_unsized_responses: false
+  request_body_timeout_secs: 7
+  upstream_connect_timeout_secs: 4
+  upstream_response_timeout_secs: 45
+  upstream_read_timeout_secs: 30
+  retry_max_attempts: 4
+  retry_base_delay_ms: 250
+  retry_jitter: false
+  follow_redirects: true
+  max_redirects: 3
+  propagate_upstream_close: false
+  stream_ndjson: false
+  stream_sse: false
+  deduplicate_requests: true
+  max_stream_line_length: 8192
+  sanitize_json_values: true
+  invalid_sanitized_json: text_plain
+  trace_propagation: w3c_b3
+  sanitize_request_headers: true
+  strip_upstream_cors: true
+  add_response_headers:
+    Access-Control-Allow-Origin: https://app.example.com
+  compress_responses: true
+  max_html_entity_decode_size: 65536
+  runtime_secret_rebuild_debounce_ms: 50
+  forward_early_hints: false
+  normalize_response_charset: false
+  decode_html_entities: true
+  proxy_identification: header
+  max_header_value_len: 4096
+  oversized_header_action: reject
+routing:
+  routes:
+    - path: /v1/health
+      inject: false
+  probe_paths: ["/healthz"]
+  endpoint_templates: ["/v1/chat"]
+  mitm_bypass_sni: ["Pinned.Example.com"]
+  inspect_plaintext_ports: [8080]
+  intercept_ports: [443, 9443]
+  intercept_all_tls: true
+  rewrite_rules:
+    - pattern: ^/v1/engines/(?P<model>[^/]+)/completions$
+      replacement: /v1/completions
+    - pattern: ^/v1/search$
+      replacement: /v2/search
+      method: post
+limits:
+  max_redactions_per_response: 5
+  fail_on_residual_dummy: false
+  max_concurrent_mitm_handshakes: 2
+  max_tracked_upstream_hosts: 64
+  max_secret_bytes: 4096
+  rate_limit_requests_per_second: 5
+  mitm_handshake_queue_timeout_secs: 9
+  byte_quota_max_bytes: 1000000
+  byte_quota_window_secs: 60
+security:
+  denied_hosts: ["*.evil.example.com"]
+  egress_allowed_hosts: ["API.openai.com", "*.anthropic.com"]
+  injection_allowed_hosts: ["API.openai.com", "*.anthropic.com"]
+  mixed_credential_policy: strip
+  non_utf8_body_policy: passthrough
+  blocked_headers: ["x-internal"]
+  allowed_response_content_types: ["application/json"]
+  dlp_rules:
+    - name: ssn
+      pattern: '\d{3}-\d{2}-\d{4}'
+      action: redact
+network:
+  tcp_nodelay: false
+"#;
+
+    #[test]
+    fn test_proxy_config_from_full_config_file() {
+        let config = Config::from_yaml(FULL_CONFIG).unwrap();
+        let proxy_config = ProxyConfig::from_config(&config).unwrap();
+
+        assert_eq!(proxy_config.mode, ProxyMode::SanitizeOnly);
+        assert_eq!(proxy_config.max_request_size, 1024);
+        assert_eq!(proxy_config.max_response_size, 2048);
+        assert_eq!(proxy_config.stream_response_threshold, 4096);
+        assert!(!proxy_config.stream_unsized_responses);
+        assert_eq!(proxy_config.request_body_timeout, Duration::from_secs(7));
+        assert_eq!(
+            proxy_config.upstream_connect_timeout,
+            Duration::from_secs(4)
+        );
+        assert_eq!(
+            proxy_config.upstream_response_timeout,
+            Duration::from_secs(45)
+        );
+        assert_eq!(proxy_config.upstream_read_timeout, Duration::from_secs(30));
+        assert_eq!(
+            proxy_config.upstream_retry,
+            RetryPolicy {
+                max_attempts: 4,
+                base_delay: Duration::from_millis(250),
+                jitter: false,
+            }
+        );
+        assert!(proxy_config.follow_redirects);
+        assert!(!proxy_config.propagate_upstream_close);
+        assert!(!proxy_config.stream_ndjson);
+        assert!(!proxy_config.stream_sse);
+        assert!(proxy_config.deduplicate_requests);
+        assert_eq!(proxy_config.max_stream_line_length, 8192);
+        assert_eq!(
+            proxy_config.invalid_sanitized_json,
+            InvalidSanitizedJson::TextPlain
+        );
+        assert_eq!(proxy_config.trace_propagation, TracePropagation::W3cB3);
+        assert!(proxy_config.sanitize_json_values);
+        assert!(proxy_config.sanitize_request_headers);
+        assert!(proxy_config.strip_upstream_cors);
+        assert!(proxy_config.compress_responses);
+        assert_eq!(proxy_config.max_html_entity_decode_size, 65536);
+        assert_eq!(
+            proxy_config.runtime_secret_rebuild_debounce,
+            Duration::from_millis(50)
+        );
+        assert!(!proxy_config.forward_early_hints);
+        assert!(!proxy_config.normalize_response_charset);
+        assert!(proxy_config.decode_html_entities);
+        assert_eq!(
+            proxy_config.proxy_identification,
+            ProxyIdentification::Header
+        );
+        assert_eq!(
+            proxy_config.header_value_limit,
+            HeaderValueLimit {
+                max_len: 4096,
+                action: OversizedHeaderAction::Reject,
+            }
+        );
+        assert_eq!(
+            proxy_config.add_response_headers["access-control-allow-origin"],
+            "https://app.example.com"
+        );
+        assert_eq!(proxy_config.max_redirects, 3);
+
+        assert_eq!(proxy_config.routes, config.routing.routes);
+        assert_eq!(proxy_config.probe_paths, vec!["/healthz"]);
+        assert_eq!(proxy_config.endpoint_templates, vec!["/v1/chat"]);
+        assert_eq!(proxy_config.mitm_bypass_sni, vec!["pinned.example.com"]);
+        assert_eq!(proxy_config.inspect_plaintext_ports, vec![8080]);
+        assert_eq!(proxy_config.intercept_ports, vec![443, 9443]);
+        assert!(proxy_config.intercept_all_tls);
+        assert_eq!(proxy_config.rewrite_rules.len(), 2);
+        let (method, uri) = apply_rewrite_rules(
+            &proxy_config.rewrite_rules,
+            &Method::GET,
+            &"/v1/search?q=x".parse().unwrap(),
+        );
+        assert_eq!(method, Method::POST);
+        assert_eq!(uri, "/v2/search?q=x");
+
+        assert_eq!(proxy_config.max_redactions_per_response, 5);
+        assert!(!proxy_config.fail_on_residual_dummy);
+        assert_eq!(proxy_config.max_concurrent_mitm_handshakes, 2);
+        assert_eq!(proxy_config.max_tracked_upstream_hosts, 64);
+        assert_eq!(proxy_config.max_secret_bytes, Some(4096));
+        assert_eq!(
+            proxy_config.rate_limit,
+            Some(RateLimitConfig {
+                requests_per_second: 5,
+                burst: 5,
+            })
+        );
+        assert_eq!(
+            proxy_config.mitm_handshake_queue_timeout,
+            Duration::from_secs(9)
+        );
+        let byte_quota = proxy_config.byte_quota.as_ref().unwrap();
+        assert_eq!(byte_quota.max_bytes, 1_000_000);
+        assert_eq!(byte_quota.window, Duration::from_secs(60));
+
+        assert_eq!(
+            proxy_config.allowed_response_content_types,
+            vec!["application/json"]
+        );
+        assert!(!proxy_config.network.tcp_nodelay);
+        assert_eq!(proxy_config.dlp_rules.len(), 1);
+        assert_eq!(proxy_config.dlp_rules[0].name(), "ssn");
+        assert!(proxy_config.injection_allowed("api.openai.com"));
+        assert!(proxy_config.injection_allowed("eu.api.anthropic.com"));
+        assert!(!proxy_config.injection_allowed("api.github.com"));
+        assert!(ProxyConfig::default().injection_allowed("api.github.com"));
+        assert!(proxy_config.egress_allowed("API.OpenAI.com."));
+        assert!(proxy_config.egress_allowed("anthropic.com"));
+        assert!(!proxy_config.egress_allowed("evil.example.com"));
+        assert!(ProxyConfig::default().egress_allowed("evil.example.com"));
+        assert_eq!(
+            proxy_config.mixed_credential_policy,
+            MixedCredentialPolicy::Strip
+        );
+        assert_eq!(
+            proxy_config.non_utf8_body_policy,
+            NonUtf8BodyPolicy::Passthrough
+        );
+
+        let secrets = std::collections::HashMap::from([(
+            "DUMMY_OPENAI".to_string(),
+            "sk-real-openai".to_string(),
+        )]);
+        let state = AppState::with_config(
+            Arc::new(crate::sanitizer::SecretMap::new(secrets).unwrap()),
+            create_http_client(),
+            proxy_config,
+        )
+        .with_security(&config.security)
+        .unwrap();
+        assert!(state.is_host_denied("api.evil.example.com"));
+        assert_eq!(state.blocked_headers(), vec!["x-internal"]);
+        assert_eq!(state.config.unwrap().max_request_size, 1024);
+    }
+
+    #[test]
+    fn test_retry_policy_delay_backs_off_exponentially() {
+        let policy = RetryPolicy {
+            max_attempts: 4,
+            base_delay: Duration::from_millis(100),
+            jitter: false,
+        };
+        assert_eq!(policy.delay(1), Duration::from_millis(100));
+        assert_eq!(policy.delay(2), Duration::from_millis(200));
+        assert_eq!(policy.delay(3), Duration::from_millis(400));
+
+        let jittered = RetryPolicy {
+            jitter: true,
+            ..policy
+        };
+        for retry in 1..=3 {
+            let delay = jittered.delay(retry);
+            assert!(delay >= policy.delay(retry) / 2 && delay <= policy.delay(retry));
+        }
+    }
+
+    #[test]
+    fn test_proxy_config_from_minimal_config_file_keeps_defaults() {
+        let config =
+            Config::from_yaml("strategies:\n  - name: openai\n    type: bearer\n    config: {}\n")
+                .unwrap();
+        let proxy_config = ProxyConfig::from_config(&config).unwrap();
+        let defaults = ProxyConfig::default();
+
+        assert_eq!(proxy_config.max_request_size, defaults.max_request_size);
+        assert_eq!(proxy_config.endpoint_templates, defaults.endpoint_templates);
+        assert_eq!(proxy_config.intercept_ports, vec![443, 8443]);
+        assert_eq!(proxy_config.mode, ProxyMode::Inject);
+    }
+
+    #[test]
+    fn test_proxy_config_from_config_rejects_invalid_mode() {
+        let mut config = Config::from_yaml(FULL_CONFIG).unwrap();
+        config.proxy.mode = Some("yolo".to_string());
+
+        assert!(ProxyConfig::from_config(&config)
+            .unwrap_err()
+            .contains("yolo"));
+    }
+
+    #[test]
+    fn test_proxy_config_from_config_rejects_invalid_response_header() {
+        let mut config = Config::from_yaml(FULL_CONFIG).unwrap();
+        config
+            .proxy
+            .add_response_headers
+            .insert("bad header".to_string(), "x".to_string());
+        assert!(ProxyConfig::from_config(&config)
+            .unwrap_err()
+            .contains("'bad header'"));
+
+        let mut config = Config::from_yaml(FULL_CONFIG).unwrap();
+        config
+            .proxy
+            .add_response_headers
+            .insert("x-policy".to_string(), "line\nbreak".to_string());
+        assert!(ProxyConfig::from_config(&config)
+            .unwrap_err()
+            .contains("'x-policy'"));
+    }
+
+    #[test]
+    fn test_proxy_config_from_config_rejects_invalid_rewrite_rule() {
+        let mut config = Config::from_yaml(FULL_CONFIG).unwrap();
+        config.routing.rewrite_rules[0].pattern = "(unclosed".to_string();
+        assert!(ProxyConfig::from_config(&config)
+            .unwrap_err()
+            .contains("rewrite rule '(unclosed'"));
+
+        let mut config = Config::from_yaml(FULL_CONFIG).unwrap();
+        config.routing.rewrite_rules[1].method = Some("NOT A METHOD".to_string());
+        assert!(ProxyConfig::from_config(&config)
+            .unwrap_err()
+            .contains("'NOT A METHOD'"));
+    }
+
+    #[test]
+    fn test_proxy_config_from_config_rejects_invalid_dlp_rule() {
+        let mut config = Config::from_yaml(FULL_CONFIG).unwrap();
+        config.security.dlp_rules[0].pattern = "(unclosed".to_string();
+        assert!(ProxyConfig::from_config(&config)
+            .unwrap_err()
+            .contains("DLP rule 'ssn'"));
+
+        let mut config = Config::from_yaml(FULL_CONFIG).unwrap();
+        config.security.dlp_rules[0].action = "log".to_string();
+        assert!(ProxyConfig::from_config(&config)
+            .unwrap_err()
+            .contains("'log'"));
+    }
 }
@@ -0,0 +1,159 @@
+// SLAPENIR Byte Quotas - Per-identity limits on proxied traffic
+// Caps the request + response bytes each agent identity may move through the
+// proxy within a sliding time window, for cost control.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Number of independently locked shards in the usage map
+const SHARDS: usize = 16;
+
+/// Byte budget shared by every identity
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ByteQuotaConfig {
+    /// Request and response bytes an identity may proxy per window
+    pub max_bytes: u64,
+    /// Length of the sliding window
+    pub window: Duration,
+}
+
+/// Bytes proxied by one identity within the window
+#[derive(Debug, Default)]
+struct Usage {
+    /// `(when, bytes)` for each recorded transfer, oldest first
+    transfers: VecDeque<(Instant, u64)>,
+    total: u64,
+}
+
+impl Usage {
+    /// Forget transfers that have slid out of the window
+    fn expire(&mut self, now: Instant, window: Duration) {
+        while let Some(&(at, bytes)) = self.transfers.front() {
+            if now.duration_since(at) < window {
+                break;
+            }
+            self.transfers.pop_front();
+            self.total -= bytes;
+        }
+    }
+}
+
+/// Per-identity byte accounting over a sliding window
+///
+/// Identities are spread over independently locked shards so concurrent
+/// requests from different agents rarely contend. Identities whose usage has
+/// fully expired are dropped when their shard next admits a new identity.
+#[derive(Debug)]
+pub struct ByteQuota {
+    config: ByteQuotaConfig,
+    shards: Vec<Mutex<HashMap<String, Usage>>>,
+}
+
+impl ByteQuota {
+    pub fn new(config: ByteQuotaConfig) -> Self {
+        Self {
+            config,
+            shards: (0..SHARDS).map(|_| Mutex::new(HashMap::new())).collect(),
+        }
+    }
+
+    fn shard(&self, identity: &str) -> &Mutex<HashMap<String, Usage>> {
+        let mut hasher = DefaultHasher::new();
+        identity.hash(&mut hasher);
+        &self.shards[hasher.finish() as usize % SHARDS]
+    }
+
+    /// Bytes `identity` has proxied within the current window
+    pub fn usage(&self, identity: &str) -> u64 {
+        let mut shard = self.shard(identity).lock().unwrap();
+        shard.get_mut(identity).map_or(0, |usage| {
+            usage.expire(Instant::now(), self.config.window);
+            usage.total
+        })
+    }
+
+    /// Whether `identity` has used up its budget for the current window
+    pub fn is_exceeded(&self, identity: &str) -> bool {
+        self.usage(identity) >= self.config.max_bytes
+    }
+
+    /// Charge `bytes` to `identity`
+    pub fn record(&self, identity: &str, bytes: u64) {
+        if bytes == 0 {
+            return;
+        }
+        let now = Instant::now();
+        let window = self.config.window;
+        let mut shard = self.shard(identity).lock().unwrap();
+
+        if !shard.contains_key(identity) {
+            shard.retain(|_, usage| {
+                usage.expire(now, window);
+                usage.total > 0
+            });
+        }
+
+        let usage = shard.entry(identity.to_string()).or_default();
+        usage.expire(now, window);
+        usage.transfers.push_back((now, bytes));
+        usage.total += bytes;
+    }
+
+    /// Number of identities currently tracked
+    pub fn tracked_identities(&self) -> usize {
+        self.shards.iter().map(|s| s.lock().unwrap().len()).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quota(max_bytes: u64, window: Duration) -> ByteQuota {
+        ByteQuota::new(ByteQuotaConfig { max_bytes, window })
+    }
+
+    #[test]
+    fn test_identity_over_budget_is_exceeded() {
+        let quota = quota(100, Duration::from_secs(60));
+
+        quota.record("agent-01", 60);
+        assert!(!quota.is_exceeded("agent-01"));
+        quota.record("agent-01", 40);
+        assert!(quota.is_exceeded("agent-01"));
+
+        assert!(!quota.is_exceeded("agent-02"));
+        assert_eq!(quota.usage("agent-02"), 0);
+    }
+
+    #[test]
+    fn test_usage_slides_out_of_window() {
+        let quota = quota(100, Duration::from_millis(50));
+
+        quota.record("agent-01", 100);
+        assert!(quota.is_exceeded("agent-01"));
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(!quota.is_exceeded("agent-01"));
+        assert_eq!(quota.usage("agent-01"), 0);
+    }
+
+    #[test]
+    fn test_expired_identities_are_dropped() {
+        let quota = quota(100, Duration::from_millis(20));
+        for i in 0..50 {
+            quota.record(&format!("agent-{}", i), 10);
+        }
+        assert_eq!(quota.tracked_identities(), 50);
+
+        std::thread::sleep(Duration::from_millis(30));
+        for i in 0..SHARDS * 8 {
+            quota.record(&format!("fresh-{}", i), 10);
+        }
+        // Stale identities in every shard that admitted a fresh one are gone
+        assert!(quota.tracked_identities() < 50 + SHARDS * 8);
+    }
+}
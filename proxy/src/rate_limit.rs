@@ -0,0 +1,158 @@
+// SLAPENIR Rate Limiting - Per-identity request rate caps
+// A token bucket per agent identity bounds how many requests each agent may
+// push through the proxy, allowing short bursts above the sustained rate.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Number of independently locked shards in the bucket map
+const SHARDS: usize = 16;
+
+/// Request rate shared by every identity
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitConfig {
+    /// Sustained requests per second an identity may send
+    pub requests_per_second: u32,
+    /// Requests an idle identity may send at once
+    pub burst: u32,
+}
+
+/// Tokens left to one identity, refilled as time passes
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl Bucket {
+    /// Add the tokens earned since the last refill, up to `capacity`
+    fn refill(&mut self, now: Instant, rate: f64, capacity: f64) {
+        let earned = now.duration_since(self.refilled_at).as_secs_f64() * rate;
+        self.tokens = (self.tokens + earned).min(capacity);
+        self.refilled_at = now;
+    }
+}
+
+/// Per-identity token buckets
+///
+/// Identities are spread over independently locked shards so concurrent
+/// requests from different agents rarely contend. Buckets that have refilled
+/// completely are dropped when their shard next admits a new identity, since
+/// a fresh bucket behaves the same.
+#[derive(Debug)]
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    shards: Vec<Mutex<HashMap<String, Bucket>>>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            shards: (0..SHARDS).map(|_| Mutex::new(HashMap::new())).collect(),
+        }
+    }
+
+    fn shard(&self, identity: &str) -> &Mutex<HashMap<String, Bucket>> {
+        let mut hasher = DefaultHasher::new();
+        identity.hash(&mut hasher);
+        &self.shards[hasher.finish() as usize % SHARDS]
+    }
+
+    fn rate(&self) -> f64 {
+        self.config.requests_per_second.max(1) as f64
+    }
+
+    fn capacity(&self) -> f64 {
+        self.config.burst.max(1) as f64
+    }
+
+    /// Take a token for one request by `identity`
+    ///
+    /// Returns how long until a token is available when the bucket is empty.
+    pub fn acquire(&self, identity: &str) -> Result<(), Duration> {
+        let now = Instant::now();
+        let (rate, capacity) = (self.rate(), self.capacity());
+        let mut shard = self.shard(identity).lock().unwrap();
+
+        if !shard.contains_key(identity) {
+            shard.retain(|_, bucket| {
+                bucket.refill(now, rate, capacity);
+                bucket.tokens < capacity
+            });
+        }
+
+        let bucket = shard.entry(identity.to_string()).or_insert(Bucket {
+            tokens: capacity,
+            refilled_at: now,
+        });
+        bucket.refill(now, rate, capacity);
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64((1.0 - bucket.tokens) / rate))
+        }
+    }
+
+    /// Number of identities currently tracked
+    pub fn tracked_identities(&self) -> usize {
+        self.shards.iter().map(|s| s.lock().unwrap().len()).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limiter(requests_per_second: u32, burst: u32) -> RateLimiter {
+        RateLimiter::new(RateLimitConfig {
+            requests_per_second,
+            burst,
+        })
+    }
+
+    #[test]
+    fn test_burst_allowed_then_throttled() {
+        let limiter = limiter(1, 3);
+
+        for _ in 0..3 {
+            assert!(limiter.acquire("agent-01").is_ok());
+        }
+        let wait = limiter.acquire("agent-01").unwrap_err();
+        assert!(wait > Duration::ZERO && wait <= Duration::from_secs(1));
+
+        // Other identities have their own bucket
+        assert!(limiter.acquire("agent-02").is_ok());
+    }
+
+    #[test]
+    fn test_tokens_refill_over_time() {
+        let limiter = limiter(50, 1);
+
+        assert!(limiter.acquire("agent-01").is_ok());
+        assert!(limiter.acquire("agent-01").is_err());
+
+        std::thread::sleep(Duration::from_millis(30));
+        assert!(limiter.acquire("agent-01").is_ok());
+    }
+
+    #[test]
+    fn test_refilled_identities_are_dropped() {
+        let limiter = limiter(100, 1);
+        for i in 0..50 {
+            limiter.acquire(&format!("agent-{}", i)).unwrap();
+        }
+        assert_eq!(limiter.tracked_identities(), 50);
+
+        std::thread::sleep(Duration::from_millis(20));
+        for i in 0..SHARDS * 8 {
+            limiter.acquire(&format!("fresh-{}", i)).unwrap();
+        }
+        // Full buckets in every shard that admitted a fresh identity are gone
+        assert!(limiter.tracked_identities() < 50 + SHARDS * 8);
+    }
+}
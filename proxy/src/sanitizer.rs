@@ -7,11 +7,16 @@
 // - G: Cached automaton for performance
 
 use crate::metrics;
-use crate::strategy::AuthStrategy;
-use aho_corasick::{AhoCorasick, AhoCorasickBuilder};
+use crate::strategy::{by_priority, AuthStrategy, InjectTargets};
+use aho_corasick::{AhoCorasick, AhoCorasickBuilder, MatchKind};
 use axum::http::{HeaderMap, HeaderValue};
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::Path;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, ReadBuf};
 use zeroize::{Zeroize, ZeroizeOnDrop};
 
 /// Headers that should be completely removed from responses (security risk)
@@ -23,6 +28,239 @@ const BLOCKED_HEADERS: &[&str] = &[
     "x-request-debug",
 ];
 
+/// Well-known credential prefixes, most specific first
+const CREDENTIAL_PREFIXES: &[&str] = &[
+    "sk-ant-",
+    "sk-",
+    "github_pat_",
+    "ghp_",
+    "gho_",
+    "ghu_",
+    "ghs_",
+    "ghr_",
+    "glpat-",
+    "xoxb-",
+    "xoxp-",
+    "AKIA",
+];
+
+/// Shortest token considered credential-shaped
+const MIN_CREDENTIAL_LEN: usize = 20;
+
+/// Minimum Shannon entropy (bits per char) of the part after the prefix
+const MIN_CREDENTIAL_ENTROPY: f64 = 3.0;
+
+/// Default cap on one response header value, roomy for large cookies and auth headers
+pub const DEFAULT_MAX_HEADER_VALUE_LEN: usize = 16 * 1024;
+
+/// Appended to a header value cut down to the length limit
+pub const TRUNCATED_HEADER_MARKER: &str = "...[TRUNCATED]";
+
+/// What happens to a response whose header value exceeds the length limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OversizedHeaderAction {
+    /// Cut the value down and mark it as truncated
+    #[default]
+    Truncate,
+    /// Refuse the whole response
+    Reject,
+}
+
+impl OversizedHeaderAction {
+    /// Parse `truncate` or `reject` (case-insensitive)
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "truncate" => Some(Self::Truncate),
+            "reject" => Some(Self::Reject),
+            _ => None,
+        }
+    }
+
+    /// Label for the oversized header metric
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Truncate => "truncated",
+            Self::Reject => "rejected",
+        }
+    }
+}
+
+/// A response header value over the configured length limit
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[error("header '{name}' value is {len} bytes, over the {limit} byte limit")]
+pub struct OversizedHeader {
+    pub name: String,
+    pub len: usize,
+    pub limit: usize,
+}
+
+/// Length limit applied to response header values before they are sanitized
+///
+/// Bounding values first keeps an upstream from making the sanitizer scan
+/// megabytes of a single header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeaderValueLimit {
+    /// Longest header value passed through unchanged, in bytes
+    pub max_len: usize,
+    pub action: OversizedHeaderAction,
+}
+
+impl Default for HeaderValueLimit {
+    fn default() -> Self {
+        Self {
+            max_len: DEFAULT_MAX_HEADER_VALUE_LEN,
+            action: OversizedHeaderAction::default(),
+        }
+    }
+}
+
+impl HeaderValueLimit {
+    /// Bytes of `value` to keep, or `None` when it is within the limit
+    ///
+    /// The kept prefix plus the marker fits the limit. It ends on a UTF-8
+    /// boundary and before any partial copy of `secrets`, so a secret cut in
+    /// half cannot slip past the sanitizer.
+    fn truncation(
+        &self,
+        name: &str,
+        value: &[u8],
+        secrets: &[Vec<u8>],
+    ) -> Result<Option<usize>, OversizedHeader> {
+        if value.len() <= self.max_len {
+            return Ok(None);
+        }
+        metrics::record_oversized_header(self.action.as_str());
+        tracing::warn!(
+            "Upstream header '{}' is {} bytes (limit {}), {}",
+            name,
+            value.len(),
+            self.max_len,
+            self.action.as_str()
+        );
+
+        if self.action == OversizedHeaderAction::Reject {
+            return Err(OversizedHeader {
+                name: name.to_string(),
+                len: value.len(),
+                limit: self.max_len,
+            });
+        }
+
+        let mut cut = self.max_len.saturating_sub(TRUNCATED_HEADER_MARKER.len());
+        cut -= partial_match_len(&value[..cut], secrets);
+        while cut > 0 && value[cut] & 0xC0 == 0x80 {
+            cut -= 1;
+        }
+        Ok(Some(cut))
+    }
+
+    /// Bound every value in `headers`
+    pub fn bound_headers(
+        &self,
+        headers: &HeaderMap,
+        secrets: &[Vec<u8>],
+    ) -> Result<HeaderMap, OversizedHeader> {
+        let mut bounded = headers.clone();
+        for (name, value) in bounded.iter_mut() {
+            if let Some(cut) = self.truncation(name.as_str(), value.as_bytes(), secrets)? {
+                let truncated = [&value.as_bytes()[..cut], TRUNCATED_HEADER_MARKER.as_bytes()];
+                *value = HeaderValue::from_bytes(&truncated.concat())
+                    .expect("prefix of a valid header value stays valid");
+            }
+        }
+        Ok(bounded)
+    }
+
+    /// Bound one header value in place
+    pub fn bound_value(
+        &self,
+        name: &str,
+        value: &mut String,
+        secrets: &[Vec<u8>],
+    ) -> Result<(), OversizedHeader> {
+        if let Some(cut) = self.truncation(name, value.as_bytes(), secrets)? {
+            value.truncate(cut);
+            value.push_str(TRUNCATED_HEADER_MARKER);
+        }
+        Ok(())
+    }
+}
+
+/// Length of the longest tail of `data` that is a prefix of some needle
+fn partial_match_len(data: &[u8], needles: &[Vec<u8>]) -> usize {
+    let longest = needles.iter().map(Vec::len).max().unwrap_or(0);
+    (1..longest.min(data.len() + 1))
+        .rev()
+        .find(|&len| {
+            let tail = &data[data.len() - len..];
+            needles.iter().any(|needle| needle.starts_with(tail))
+        })
+        .unwrap_or(0)
+}
+
+/// Marker written in place of a redacted secret
+pub const REDACTED_MARKER: &str = "[REDACTED]";
+
+/// How redacted secrets are marked in sanitized output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RedactionStyle {
+    /// Every secret becomes `[REDACTED]`
+    #[default]
+    Plain,
+    /// Each distinct secret becomes `[REDACTED:n]`, with `n` stable per secret
+    ///
+    /// Adjacent redactions of different secrets stay distinguishable, and
+    /// `n` maps back to the strategy label via [`SecretMap::redaction_label`].
+    /// Secrets registered at runtime keep the plain marker.
+    Indexed,
+    /// Each secret becomes `[REDACTED:<strategy>]`, e.g. `[REDACTED:openai]`
+    ///
+    /// A label that would put a dummy into the marker (maps built without
+    /// strategies are labelled by dummy) falls back to the index, so a
+    /// marker echoed back by the agent is never injected.
+    Labeled,
+}
+
+impl RedactionStyle {
+    /// Parse `plain`, `indexed` or `labeled` (case-insensitive)
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "plain" => Some(Self::Plain),
+            "indexed" => Some(Self::Indexed),
+            "labeled" | "labelled" => Some(Self::Labeled),
+            _ => None,
+        }
+    }
+}
+
+/// How much of a redacted secret its marker keeps
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RedactionMode {
+    /// Nothing of the secret remains
+    #[default]
+    Full,
+    /// Keep up to this many trailing bytes after the marker, e.g. `[REDACTED]3456`
+    ///
+    /// Enough to tell which key leaked without exposing it. The suffix never
+    /// exceeds half of the secret, so a short secret is not given away whole.
+    PartialSuffix(usize),
+}
+
+impl RedactionMode {
+    /// Trailing part of `secret` kept after its marker
+    fn suffix<'a>(&self, secret: &'a str) -> &'a str {
+        let RedactionMode::PartialSuffix(len) = *self else {
+            return "";
+        };
+        let mut start = secret.len() - len.min(secret.len() / 2);
+        // Never split a character; round towards revealing less
+        while !secret.is_char_boundary(start) {
+            start += 1;
+        }
+        &secret[start..]
+    }
+}
+
 /// Secure secret mapping that zeros memory on drop
 #[derive(Clone, Zeroize, ZeroizeOnDrop)]
 pub struct SecretMap {
@@ -36,9 +274,27 @@ pub struct SecretMap {
     real_secrets: Vec<String>,
     /// Dummy placeholders
     dummy_secrets: Vec<String>,
+    /// Where each dummy may be injected; dummies past the end are unrestricted
+    #[zeroize(skip)]
+    inject_targets: Vec<InjectTargets>,
+    /// Real secrets that are redacted from responses but never injected
+    sanitize_only_secrets: Vec<String>,
     /// Byte representations of real secrets for binary sanitization
     #[zeroize(skip)]
     real_secrets_bytes: Vec<Vec<u8>>,
+    /// Strategy (or dummy) label of each real secret, sanitize-only ones last
+    #[zeroize(skip)]
+    secret_labels: Vec<String>,
+    /// 1-based redaction index of each real secret; equal secrets share one
+    #[zeroize(skip)]
+    redaction_indices: Vec<usize>,
+    #[zeroize(skip)]
+    redaction_style: RedactionStyle,
+    #[zeroize(skip)]
+    redaction_mode: RedactionMode,
+    /// Marker replacing each real secret, in the current style and mode;
+    /// a partial suffix is secret material, so these are zeroized too
+    redactions: Vec<String>,
 }
 
 impl SecretMap {
@@ -48,11 +304,31 @@ impl SecretMap {
             return Err("Secret map cannot be empty".to_string());
         }
 
-        let dummy_secrets: Vec<String> = secrets.keys().cloned().collect();
-        let real_secrets: Vec<String> = secrets.values().cloned().collect();
+        // Sorted so redaction indices do not depend on hash order
+        let mut pairs: Vec<(String, String)> = secrets.into_iter().collect();
+        pairs.sort();
+        let (dummy_secrets, real_secrets): (Vec<String>, Vec<String>) = pairs.into_iter().unzip();
+        let labels = dummy_secrets.clone();
 
-        // Build Aho-Corasick automaton for injection (dummy -> real)
+        Self::build(dummy_secrets, real_secrets, Vec::new(), labels)
+    }
+
+    /// Build the injection and sanitization automata from parallel dummy/real lists
+    ///
+    /// `sanitize_only_secrets` join the sanitization automaton only. `labels`
+    /// name each real secret, then each sanitize-only one, for redaction indices.
+    fn build(
+        dummy_secrets: Vec<String>,
+        real_secrets: Vec<String>,
+        sanitize_only_secrets: Vec<String>,
+        secret_labels: Vec<String>,
+    ) -> Result<Self, String> {
+        let all_real: Vec<&String> = real_secrets.iter().chain(&sanitize_only_secrets).collect();
+
+        // Build Aho-Corasick automaton for injection (dummy -> real); where
+        // dummies overlap, the one listed first wins
         let patterns = AhoCorasickBuilder::new()
+            .match_kind(MatchKind::LeftmostFirst)
             .ascii_case_insensitive(false)
             .build(&dummy_secrets)
             .map_err(|e| format!("Failed to build pattern matcher: {}", e))?;
@@ -60,37 +336,320 @@ impl SecretMap {
         // SECURITY FIX G: Build sanitize automaton ONCE, cache it
         let sanitize_patterns = AhoCorasickBuilder::new()
             .ascii_case_insensitive(false)
-            .build(&real_secrets)
+            .build(&all_real)
             .map_err(|e| format!("Failed to build sanitize pattern matcher: {}", e))?;
 
         // SECURITY FIX A: Pre-compute byte representations for binary sanitization
         let real_secrets_bytes: Vec<Vec<u8>> =
-            real_secrets.iter().map(|s| s.as_bytes().to_vec()).collect();
+            all_real.iter().map(|s| s.as_bytes().to_vec()).collect();
 
-        Ok(Self {
+        // Number distinct secrets in order of first appearance
+        let mut redaction_indices = Vec::with_capacity(all_real.len());
+        for (i, secret) in all_real.iter().enumerate() {
+            let index = match all_real[..i].iter().position(|earlier| earlier == secret) {
+                Some(first) => redaction_indices[first],
+                None => redaction_indices.iter().max().map_or(1, |max| max + 1),
+            };
+            redaction_indices.push(index);
+        }
+
+        let mut map = Self {
             patterns,
             sanitize_patterns,
             real_secrets,
             dummy_secrets,
+            inject_targets: Vec::new(),
+            sanitize_only_secrets,
             real_secrets_bytes,
-        })
+            secret_labels,
+            redaction_indices,
+            redaction_style: RedactionStyle::default(),
+            redaction_mode: RedactionMode::default(),
+            redactions: Vec::new(),
+        };
+        map.redactions = map.markers();
+        Ok(map)
+    }
+
+    /// Mark redactions in `style` instead of the plain `[REDACTED]`
+    pub fn with_redaction_style(mut self, style: RedactionStyle) -> Self {
+        self.redaction_style = style;
+        self.redactions = self.markers();
+        self
+    }
+
+    pub fn redaction_style(&self) -> RedactionStyle {
+        self.redaction_style
+    }
+
+    /// Keep part of each redacted secret after its marker, per `mode`
+    pub fn with_redaction_mode(mut self, mode: RedactionMode) -> Self {
+        self.redaction_mode = mode;
+        self.redactions = self.markers();
+        self
+    }
+
+    pub fn redaction_mode(&self) -> RedactionMode {
+        self.redaction_mode
+    }
+
+    /// Strategy (or dummy) label behind redaction index `index`
+    pub fn redaction_label(&self, index: usize) -> Option<&str> {
+        let first = self.redaction_indices.iter().position(|&i| i == index)?;
+        self.secret_labels.get(first).map(String::as_str)
+    }
+
+    /// Number of distinct redaction indices
+    pub fn redaction_index_count(&self) -> usize {
+        self.redaction_indices.iter().copied().max().unwrap_or(0)
+    }
+
+    /// Replacement for each real secret in the current style and mode
+    fn markers(&self) -> Vec<String> {
+        let all_real = self.real_secrets.iter().chain(&self.sanitize_only_secrets);
+        self.redaction_indices
+            .iter()
+            .zip(all_real)
+            .map(|(&index, secret)| {
+                let marker = match self.redaction_style {
+                    RedactionStyle::Plain => REDACTED_MARKER.to_string(),
+                    RedactionStyle::Indexed => format!("[REDACTED:{}]", index),
+                    RedactionStyle::Labeled => self.labeled_marker(index),
+                };
+                marker + self.redaction_mode.suffix(secret)
+            })
+            .collect()
+    }
+
+    /// `[REDACTED:<label>]` for redaction index `index`, or the indexed
+    /// marker when the label is empty or would carry a dummy
+    fn labeled_marker(&self, index: usize) -> String {
+        // Markers hold no quotes or markup, so they are safe in any body
+        let label: String = self
+            .redaction_label(index)
+            .unwrap_or_default()
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        let marker = format!("[REDACTED:{}]", label);
+        if label.is_empty()
+            || self
+                .dummy_secrets
+                .iter()
+                .any(|dummy| marker.contains(dummy.as_str()))
+        {
+            return format!("[REDACTED:{}]", index);
+        }
+        marker
+    }
+
+    /// Real secret -> marker byte pairs, for building a streaming sanitizer
+    pub(crate) fn redaction_pairs(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        self.real_secrets_bytes
+            .iter()
+            .zip(&self.redactions)
+            .map(|(real, marker)| (real.clone(), marker.as_bytes().to_vec()))
+            .collect()
+    }
+
+    /// Return a new SecretMap with extra dummy -> real mappings added
+    ///
+    /// Existing dummies take precedence; a clashing extra entry is skipped.
+    /// The added secrets take part in both injection and sanitization.
+    pub fn with_additional_secrets(&self, extra: HashMap<String, String>) -> Result<Self, String> {
+        let mut dummy_secrets = self.dummy_secrets.clone();
+        let mut real_secrets = self.real_secrets.clone();
+        let (real_labels, sanitize_only_labels) =
+            self.secret_labels.split_at(self.real_secrets.len());
+        let mut labels = real_labels.to_vec();
+
+        let mut extra: Vec<(String, String)> = extra.into_iter().collect();
+        extra.sort();
+        for (dummy, real) in extra {
+            if dummy_secrets.contains(&dummy) {
+                tracing::warn!("Additional secret '{}' already defined (skipping)", dummy);
+                continue;
+            }
+            labels.push(dummy.clone());
+            dummy_secrets.push(dummy);
+            real_secrets.push(real);
+        }
+        labels.extend_from_slice(sanitize_only_labels);
+
+        let mut map = Self::build(
+            dummy_secrets,
+            real_secrets,
+            self.sanitize_only_secrets.clone(),
+            labels,
+        )?
+        .with_redaction_style(self.redaction_style)
+        .with_redaction_mode(self.redaction_mode);
+        map.inject_targets = self.inject_targets.clone();
+        Ok(map)
+    }
+
+    /// The same secrets, only ever redacted: no dummy is injected any more
+    pub fn to_sanitize_only(&self) -> Result<Self, String> {
+        let sanitize_only_secrets = self
+            .real_secrets
+            .iter()
+            .chain(&self.sanitize_only_secrets)
+            .cloned()
+            .collect();
+
+        Ok(Self::build(
+            Vec::new(),
+            Vec::new(),
+            sanitize_only_secrets,
+            self.secret_labels.clone(),
+        )?
+        .with_redaction_style(self.redaction_style)
+        .with_redaction_mode(self.redaction_mode))
+    }
+
+    /// The same secrets, injecting only those labelled with a name in `allowed`
+    ///
+    /// Labels are strategy names (dummies for secrets added without one).
+    /// Every other secret is still redacted but never injected, so a client
+    /// scoped to one strategy cannot unlock another's credential.
+    pub fn scoped(&self, allowed: &[String]) -> Result<Self, String> {
+        let (real_labels, sanitize_only_labels) =
+            self.secret_labels.split_at(self.real_secrets.len());
+
+        let mut dummy_secrets = Vec::new();
+        let mut real_secrets = Vec::new();
+        let mut inject_targets = Vec::new();
+        let mut labels = Vec::new();
+        let mut withheld_secrets = Vec::new();
+        let mut withheld_labels = Vec::new();
+        for (index, label) in real_labels.iter().enumerate() {
+            if allowed.contains(label) {
+                dummy_secrets.push(self.dummy_secrets[index].clone());
+                real_secrets.push(self.real_secrets[index].clone());
+                inject_targets.extend(self.inject_targets.get(index).cloned());
+                labels.push(label.clone());
+            } else {
+                withheld_secrets.push(self.real_secrets[index].clone());
+                withheld_labels.push(label.clone());
+            }
+        }
+        labels.extend(withheld_labels);
+        labels.extend_from_slice(sanitize_only_labels);
+        withheld_secrets.extend(self.sanitize_only_secrets.iter().cloned());
+
+        let mut map = Self::build(dummy_secrets, real_secrets, withheld_secrets, labels)?
+            .with_redaction_style(self.redaction_style)
+            .with_redaction_mode(self.redaction_mode);
+        map.inject_targets = inject_targets;
+        Ok(map)
     }
 
     /// Inject real secrets into outbound data (Agent -> Internet)
+    ///
+    /// This is the request body: dummies scoped to headers are left as sent.
     pub fn inject(&self, data: &str) -> String {
-        self.patterns.replace_all(data, &self.real_secrets)
+        self.inject_where(data, InjectTargets::body)
+    }
+
+    /// Inject real secrets into an outbound body that is not UTF-8
+    ///
+    /// Only the dummy bytes are replaced; the bytes around them are left as sent.
+    pub fn inject_bytes(&self, data: &[u8]) -> Vec<u8> {
+        let mut injected = Vec::with_capacity(data.len());
+        let mut last = 0;
+        for m in self.patterns.find_iter(data) {
+            let index = m.pattern().as_usize();
+            injected.extend_from_slice(&data[last..m.start()]);
+            if self
+                .inject_targets
+                .get(index)
+                .is_none_or(InjectTargets::body)
+            {
+                injected.extend_from_slice(self.real_secrets[index].as_bytes());
+            } else {
+                injected.extend_from_slice(&data[m.range()]);
+            }
+            last = m.end();
+        }
+        injected.extend_from_slice(&data[last..]);
+        injected
+    }
+
+    /// Inject real secrets into the value of the request header `name`
+    pub fn inject_header(&self, name: &str, value: &str) -> String {
+        self.inject_where(value, |targets| targets.header(name))
+    }
+
+    /// Distinct labels of the injectable secrets, in order
+    pub fn injection_labels(&self) -> Vec<&str> {
+        let mut labels: Vec<&str> = Vec::new();
+        for label in &self.secret_labels[..self.real_secrets.len()] {
+            if !labels.contains(&label.as_str()) {
+                labels.push(label);
+            }
+        }
+        labels
+    }
+
+    /// Labels of the dummies in a request body that [`Self::inject`] replaces
+    pub fn injected_labels(&self, data: &[u8]) -> BTreeSet<&str> {
+        self.labels_where(data, InjectTargets::body)
+    }
+
+    /// Labels of the dummies in header `name` that [`Self::inject_header`] replaces
+    pub fn injected_header_labels(&self, name: &str, value: &str) -> BTreeSet<&str> {
+        self.labels_where(value.as_bytes(), |targets| targets.header(name))
+    }
+
+    fn labels_where(
+        &self,
+        data: &[u8],
+        allowed: impl Fn(&InjectTargets) -> bool,
+    ) -> BTreeSet<&str> {
+        self.patterns
+            .find_iter(data)
+            .map(|m| m.pattern().as_usize())
+            .filter(|&index| self.inject_targets.get(index).is_none_or(&allowed))
+            .map(|index| self.secret_labels[index].as_str())
+            .collect()
+    }
+
+    /// Replace each dummy whose inject targets satisfy `allowed`
+    fn inject_where(&self, data: &str, allowed: impl Fn(&InjectTargets) -> bool) -> String {
+        if self
+            .inject_targets
+            .iter()
+            .all(InjectTargets::is_unrestricted)
+        {
+            return self.patterns.replace_all(data, &self.real_secrets);
+        }
+
+        let mut injected = String::with_capacity(data.len());
+        let mut last = 0;
+        for m in self.patterns.find_iter(data) {
+            let index = m.pattern().as_usize();
+            injected.push_str(&data[last..m.start()]);
+            if self.inject_targets.get(index).is_none_or(&allowed) {
+                injected.push_str(&self.real_secrets[index]);
+            } else {
+                injected.push_str(&data[m.range()]);
+            }
+            last = m.end();
+        }
+        injected.push_str(&data[last..]);
+        injected
     }
 
     /// Sanitize real secrets from inbound UTF-8 data (Internet -> Agent)
     ///
     /// Uses cached automaton for O(1) setup per call (Fix G)
     pub fn sanitize(&self, data: &str) -> String {
-        let redacted: Vec<String> = self
-            .real_secrets
-            .iter()
-            .map(|_| "[REDACTED]".to_string())
-            .collect();
-
         // Count secrets being sanitized
         let matches = self.sanitize_patterns.find_iter(data).count();
         if matches > 0 {
@@ -99,7 +658,7 @@ impl SecretMap {
             }
         }
 
-        self.sanitize_patterns.replace_all(data, &redacted)
+        self.sanitize_patterns.replace_all(data, &self.redactions)
     }
 
     /// SECURITY FIX A: Sanitize real secrets from binary/non-UTF-8 data
@@ -107,27 +666,83 @@ impl SecretMap {
     /// This prevents the bypass where non-UTF-8 responses were returned unsanitized.
     /// Works on raw bytes, so it handles binary payloads, invalid UTF-8, etc.
     pub fn sanitize_bytes(&self, data: &[u8]) -> Cow<'_, [u8]> {
-        // Build byte-based patterns for matching
-        let byte_patterns = AhoCorasickBuilder::new()
-            .ascii_case_insensitive(false)
-            .build(&self.real_secrets_bytes)
-            .expect("Failed to build byte pattern matcher");
-
-        let redacted: Vec<&[u8]> = self
-            .real_secrets_bytes
-            .iter()
-            .map(|_| b"[REDACTED]" as &[u8])
-            .collect();
+        // The cached automaton matches bytes, so it serves binary data too
+        let redacted: Vec<&[u8]> = self.redactions.iter().map(String::as_bytes).collect();
 
         // Count secrets being sanitized
-        let matches = byte_patterns.find_iter(data).count();
+        let matches = self.sanitize_patterns.find_iter(data).count();
         if matches > 0 {
             for _ in 0..matches {
                 metrics::record_secret_sanitized("binary_sanitization");
             }
         }
 
-        byte_patterns.replace_all_bytes(data, &redacted).into()
+        self.sanitize_patterns
+            .replace_all_bytes(data, &redacted)
+            .into()
+    }
+
+    /// Sanitize a body as it is read instead of buffering it whole
+    ///
+    /// Secrets split across reads from `reader` are still redacted; see
+    /// [`StreamingSanitizer`] for how the tail of each read is carried over.
+    pub fn sanitize_stream<R: AsyncRead + Unpin>(
+        &self,
+        reader: R,
+    ) -> Result<SanitizingReader<R>, String> {
+        let sanitizer = StreamingSanitizer::redactor(&self.redaction_pairs())?;
+        Ok(SanitizingReader::new(reader, sanitizer))
+    }
+
+    /// Redact secrets from JSON string values only; `None` if `body` is not JSON
+    ///
+    /// See [`sanitize_json_values`]. A secret used as an object key is kept.
+    pub fn sanitize_json(&self, body: &[u8]) -> Option<Vec<u8>> {
+        sanitize_json_values(body, |text| self.sanitize(text))
+    }
+
+    /// Byte representations of the real secrets, for building other matchers
+    pub(crate) fn real_secret_bytes(&self) -> &[Vec<u8>] {
+        &self.real_secrets_bytes
+    }
+
+    /// Dummy -> real byte pairs, for building a streaming injector
+    ///
+    /// A stream is injected like a body: secrets whose targets exclude the
+    /// body (header-only credentials, signing keys) are never returned.
+    pub(crate) fn injection_pairs(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        self.dummy_secrets
+            .iter()
+            .zip(&self.real_secrets)
+            .enumerate()
+            .filter(|(index, _)| {
+                self.inject_targets
+                    .get(*index)
+                    .is_none_or(InjectTargets::body)
+            })
+            .map(|(_, (dummy, real))| (dummy.as_bytes().to_vec(), real.as_bytes().to_vec()))
+            .collect()
+    }
+
+    /// Count real secrets present in data without redacting them
+    pub fn count_secrets(&self, data: &[u8]) -> usize {
+        self.sanitize_patterns.find_iter(data).count()
+    }
+
+    /// Count real secrets present in data by strategy (or dummy) label
+    pub fn count_secrets_by_label(&self, data: &[u8]) -> BTreeMap<String, usize> {
+        let mut counts = BTreeMap::new();
+        for m in self.sanitize_patterns.find_iter(data) {
+            let label = &self.secret_labels[m.pattern().as_usize()];
+            *counts.entry(label.clone()).or_insert(0) += 1;
+        }
+        counts
+    }
+
+    /// Redact without recording sanitization metrics, for counting passes
+    fn redact_unrecorded(&self, data: &[u8]) -> Vec<u8> {
+        let redacted: Vec<&[u8]> = self.redactions.iter().map(String::as_bytes).collect();
+        self.sanitize_patterns.replace_all_bytes(data, &redacted)
     }
 
     /// SECURITY FIX B: Sanitize secrets from HTTP headers
@@ -137,7 +752,25 @@ impl SecretMap {
     /// - Set-Cookie
     /// - WWW-Authenticate
     /// - Location (redirect URLs)
+    ///
+    /// Values over [`DEFAULT_MAX_HEADER_VALUE_LEN`] are truncated first.
     pub fn sanitize_headers(&self, headers: &HeaderMap) -> HeaderMap {
+        self.sanitize_headers_with_limit(headers, &HeaderValueLimit::default())
+            .expect("the default header limit truncates rather than rejects")
+    }
+
+    /// Sanitize headers after bounding their values with `limit`
+    pub fn sanitize_headers_with_limit(
+        &self,
+        headers: &HeaderMap,
+        limit: &HeaderValueLimit,
+    ) -> Result<HeaderMap, OversizedHeader> {
+        let bounded = limit.bound_headers(headers, &self.real_secrets_bytes)?;
+        Ok(self.sanitize_header_values(&bounded))
+    }
+
+    /// Sanitize header values as they are, dropping blocked headers
+    pub(crate) fn sanitize_header_values(&self, headers: &HeaderMap) -> HeaderMap {
         let mut sanitized = HeaderMap::new();
 
         for (name, value) in headers.iter() {
@@ -190,33 +823,88 @@ impl SecretMap {
     }
 
     pub fn len(&self) -> usize {
-        self.real_secrets.len()
+        self.real_secrets.len() + self.sanitize_only_secrets.len()
     }
 
     pub fn is_empty(&self) -> bool {
-        self.real_secrets.is_empty()
+        self.len() == 0
+    }
+
+    /// Bytes of real secret material held, injected and sanitize-only
+    pub fn secret_bytes(&self) -> usize {
+        self.real_secrets
+            .iter()
+            .chain(&self.sanitize_only_secrets)
+            .map(String::len)
+            .sum()
+    }
+
+    /// Fail when the secrets held exceed `max` bytes; returns the bytes held
+    pub fn check_secret_bytes(&self, max: Option<usize>) -> Result<usize, String> {
+        let bytes = self.secret_bytes();
+        match max {
+            Some(max) if bytes > max => Err(format!(
+                "Configured secrets hold {} bytes, over the {}-byte max_secret_bytes limit",
+                bytes, max
+            )),
+            _ => Ok(bytes),
+        }
+    }
+
+    /// Whether `token` is one of the managed dummy or real secrets
+    pub fn is_managed(&self, token: &str) -> bool {
+        self.dummy_secrets.iter().any(|d| d == token)
+            || self.real_secrets.iter().any(|r| r == token)
+            || self.sanitize_only_secrets.iter().any(|r| r == token)
     }
 
     pub fn dummy_keys(&self) -> Vec<String> {
         self.dummy_secrets.clone()
     }
 
+    /// Whether `data` holds any dummy, found with the injection automaton
+    pub fn contains_dummy(&self, data: &[u8]) -> bool {
+        self.patterns.is_match(data)
+    }
+
+    /// Whether `dummy` is one of the injected placeholders
+    pub fn is_dummy(&self, dummy: &str) -> bool {
+        self.dummy_secrets.iter().any(|d| d == dummy)
+    }
+
     /// Create a new SecretMap from authentication strategies
     ///
-    /// This is the preferred method when using the strategy pattern
+    /// This is the preferred method when using the strategy pattern.
+    /// Dummy patterns are ordered by strategy priority, so where patterns
+    /// overlap the higher-priority strategy's injection wins.
     pub fn from_strategies(strategies: &[Box<dyn AuthStrategy>]) -> Result<Self, String> {
         if strategies.is_empty() {
             return Err("No strategies provided".to_string());
         }
 
         let mut dummy_secrets = Vec::new();
+        let mut inject_targets = Vec::new();
         let mut real_secrets = Vec::new();
+        let mut sanitize_only_secrets = Vec::new();
+        let mut real_labels = Vec::new();
+        let mut sanitize_only_labels = Vec::new();
 
-        for strategy in strategies {
+        for strategy in by_priority(strategies) {
+            for secret in strategy.sanitize_credentials() {
+                sanitize_only_secrets.push(secret);
+                sanitize_only_labels.push(strategy.name().to_string());
+            }
             if let Some(real_cred) = strategy.real_credential() {
+                if strategy.sanitize_only() {
+                    sanitize_only_secrets.push(real_cred);
+                    sanitize_only_labels.push(strategy.name().to_string());
+                    continue;
+                }
                 let dummies = strategy.dummy_patterns();
                 for _ in &dummies {
                     real_secrets.push(real_cred.clone());
+                    real_labels.push(strategy.name().to_string());
+                    inject_targets.push(strategy.inject_targets());
                 }
                 dummy_secrets.extend(dummies);
             } else {
@@ -227,7 +915,7 @@ impl SecretMap {
             }
         }
 
-        if dummy_secrets.is_empty() || real_secrets.is_empty() {
+        if real_secrets.is_empty() && sanitize_only_secrets.is_empty() {
             return Err(
                 "No valid credentials found in strategies. Add API keys to your .env file."
                     .to_string(),
@@ -242,78 +930,805 @@ impl SecretMap {
             ));
         }
 
-        // Build Aho-Corasick automaton for injection (dummy -> real)
-        let patterns = AhoCorasickBuilder::new()
-            .ascii_case_insensitive(false)
-            .build(&dummy_secrets)
-            .map_err(|e| format!("Failed to build pattern matcher: {}", e))?;
-
-        // SECURITY FIX G: Build sanitize automaton ONCE, cache it
-        let sanitize_patterns = AhoCorasickBuilder::new()
-            .ascii_case_insensitive(false)
-            .build(&real_secrets)
-            .map_err(|e| format!("Failed to build sanitize pattern matcher: {}", e))?;
-
-        // SECURITY FIX A: Pre-compute byte representations for binary sanitization
-        let real_secrets_bytes: Vec<Vec<u8>> =
-            real_secrets.iter().map(|s| s.as_bytes().to_vec()).collect();
-
         tracing::info!(
-            "✓ Built SecretMap from {} strategies ({} patterns)",
+            "✓ Built SecretMap from {} strategies ({} patterns, {} sanitize-only)",
             strategies.len(),
-            dummy_secrets.len()
+            dummy_secrets.len(),
+            sanitize_only_secrets.len()
         );
 
-        Ok(Self {
-            patterns,
-            sanitize_patterns,
-            real_secrets,
+        real_labels.extend(sanitize_only_labels);
+        let mut map = Self::build(
             dummy_secrets,
-            real_secrets_bytes,
-        })
+            real_secrets,
+            sanitize_only_secrets,
+            real_labels,
+        )?;
+        map.inject_targets = inject_targets;
+        Ok(map)
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Every strategy's dummy patterns in one automaton, loaded credential or not
+///
+/// Finds dummies left in outbound data after injection, whichever strategy
+/// owns them, in a single pass.
+pub struct StrategyDummies {
+    patterns: AhoCorasick,
+    /// Strategy name behind each pattern
+    owners: Vec<String>,
+}
 
-    fn create_test_map() -> SecretMap {
-        let mut secrets = HashMap::new();
-        secrets.insert("DUMMY_GITHUB".to_string(), "ghp_realtoken123".to_string());
-        secrets.insert("DUMMY_OPENAI".to_string(), "sk-realkey456".to_string());
-        secrets.insert("DUMMY_AWS".to_string(), "AKIA_AWSKEY789".to_string());
-        SecretMap::new(secrets).unwrap()
+impl StrategyDummies {
+    /// Patterns ordered by strategy priority, so overlaps go to the higher one
+    pub fn new(strategies: &[Box<dyn AuthStrategy>]) -> Self {
+        let (dummies, owners): (Vec<String>, Vec<String>) = by_priority(strategies)
+            .into_iter()
+            .flat_map(|strategy| {
+                strategy
+                    .dummy_patterns()
+                    .into_iter()
+                    .filter(|dummy| !dummy.is_empty())
+                    .map(|dummy| (dummy, strategy.name().to_string()))
+            })
+            .unzip();
+        let patterns = AhoCorasickBuilder::new()
+            .match_kind(MatchKind::LeftmostFirst)
+            .build(&dummies)
+            .expect("dummy patterns are literal strings");
+        Self { patterns, owners }
     }
 
-    #[test]
-    fn test_secret_map_creation() {
-        let map = create_test_map();
-        assert_eq!(map.len(), 3);
-        assert!(!map.is_empty());
+    /// Name of the strategy owning the first dummy in `data`
+    pub fn find_owner(&self, data: &[u8]) -> Option<&str> {
+        self.patterns
+            .find(data)
+            .map(|m| self.owners[m.pattern().as_usize()].as_str())
     }
+}
 
-    #[test]
-    fn test_empty_secret_map_fails() {
-        let secrets = HashMap::new();
-        let result = SecretMap::new(secrets);
-        assert!(result.is_err());
-    }
+/// Default quiet period after the last runtime secret change before the automaton is rebuilt
+pub const DEFAULT_RUNTIME_REBUILD_DEBOUNCE: Duration = Duration::from_millis(250);
 
-    #[test]
-    fn test_inject_single_token() {
-        let map = create_test_map();
-        let input = "Authorization: Bearer DUMMY_GITHUB";
-        let output = map.inject(input);
-        assert_eq!(output, "Authorization: Bearer ghp_realtoken123");
-    }
+/// Pending changes that force a rebuild without waiting for the quiet period
+pub const MAX_PENDING_RUNTIME_SECRETS: usize = 64;
 
-    #[test]
-    fn test_inject_multiple_tokens() {
-        let map = create_test_map();
-        let input = "GitHub: DUMMY_GITHUB, OpenAI: DUMMY_OPENAI";
-        let output = map.inject(input);
-        assert_eq!(output, "GitHub: ghp_realtoken123, OpenAI: sk-realkey456");
+/// Secrets registered at runtime, with debounced automaton rebuilds
+///
+/// Rebuilding the automaton costs time proportional to all pattern bytes, so
+/// bursts of changes are coalesced: additions go into a small pending
+/// automaton that is consulted alongside the main one, and the main one is
+/// rebuilt once changes stop for the debounce window (or too many pile up).
+/// Every change is effective immediately; removed secrets may still be
+/// redacted from responses until the rebuild lands, which errs on the safe side.
+pub struct RuntimeSecrets {
+    /// Live dummy -> real mappings
+    secrets: HashMap<String, String>,
+    /// Automaton over `secrets` as of the last rebuild
+    compiled: Option<SecretMap>,
+    /// Mappings added or changed since the last rebuild
+    pending_secrets: HashMap<String, String>,
+    /// Automaton over `pending_secrets`
+    pending: Option<SecretMap>,
+    /// Dummies still in `compiled` that have since been removed
+    removed: Vec<String>,
+    last_change: Option<Instant>,
+    debounce: Duration,
+    rebuilds: u64,
+}
+
+impl Default for RuntimeSecrets {
+    fn default() -> Self {
+        Self::new(DEFAULT_RUNTIME_REBUILD_DEBOUNCE)
+    }
+}
+
+impl RuntimeSecrets {
+    pub fn new(debounce: Duration) -> Self {
+        Self {
+            secrets: HashMap::new(),
+            compiled: None,
+            pending_secrets: HashMap::new(),
+            pending: None,
+            removed: Vec::new(),
+            last_change: None,
+            debounce,
+            rebuilds: 0,
+        }
+    }
+
+    /// Register (or replace) a secret; it is injected and redacted right away
+    pub fn add_secret(&mut self, dummy: String, real: String) {
+        if dummy.is_empty() || real.is_empty() {
+            tracing::warn!("Ignoring runtime secret with an empty dummy or value");
+            return;
+        }
+        if self.secrets.get(&dummy) == Some(&real) {
+            return;
+        }
+        self.removed.retain(|d| *d != dummy);
+        self.secrets.insert(dummy.clone(), real.clone());
+        self.pending_secrets.insert(dummy, real);
+        self.changed();
+    }
+
+    /// Unregister a secret; returns whether it was registered
+    pub fn remove_secret(&mut self, dummy: &str) -> bool {
+        if self.secrets.remove(dummy).is_none() {
+            return false;
+        }
+        self.pending_secrets.remove(dummy);
+        if self
+            .compiled
+            .as_ref()
+            .is_some_and(|compiled| compiled.is_dummy(dummy))
+        {
+            self.removed.push(dummy.to_string());
+        }
+        self.changed();
+        true
+    }
+
+    fn changed(&mut self) {
+        self.pending = SecretMap::new(self.pending_secrets.clone()).ok();
+        self.last_change = Some(Instant::now());
+        if self.pending_secrets.len() + self.removed.len() >= MAX_PENDING_RUNTIME_SECRETS {
+            self.rebuild();
+        }
+    }
+
+    /// Whether changes have been quiet for the debounce window
+    pub fn rebuild_due(&self) -> bool {
+        self.last_change
+            .is_some_and(|changed| changed.elapsed() >= self.debounce)
+    }
+
+    /// Fold pending changes into the main automaton
+    pub fn rebuild(&mut self) {
+        self.compiled = SecretMap::new(self.secrets.clone()).ok();
+        self.pending_secrets.clear();
+        self.pending = None;
+        self.removed.clear();
+        self.last_change = None;
+        self.rebuilds += 1;
+    }
+
+    /// Number of main automaton rebuilds so far
+    pub fn rebuild_count(&self) -> u64 {
+        self.rebuilds
+    }
+
+    pub fn len(&self) -> usize {
+        self.secrets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.secrets.is_empty()
+    }
+
+    /// Bytes of real secret material registered
+    pub fn secret_bytes(&self) -> usize {
+        self.secrets.values().map(String::len).sum()
+    }
+
+    /// Bytes of real secret material registered once `additions` are added
+    ///
+    /// Additions replace existing mappings for the same dummy; empty ones are
+    /// ignored, as [`Self::add_secret`] ignores them.
+    pub fn secret_bytes_after(&self, additions: &HashMap<String, String>) -> usize {
+        let kept: usize = self
+            .secrets
+            .iter()
+            .filter(|(dummy, _)| additions.get(*dummy).is_none_or(String::is_empty))
+            .map(|(_, real)| real.len())
+            .sum();
+        let added: usize = additions
+            .iter()
+            .filter(|(dummy, real)| !dummy.is_empty() && !real.is_empty())
+            .map(|(_, real)| real.len())
+            .sum();
+        kept + added
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.secrets.keys()
+    }
+
+    /// Whether `token` is one of the registered dummy or real secrets
+    pub fn is_managed(&self, token: &str) -> bool {
+        self.secrets
+            .iter()
+            .any(|(dummy, real)| dummy == token || real == token)
+    }
+
+    /// Whether `data` holds a registered dummy
+    ///
+    /// Dummies removed since the last rebuild are still in its automaton, so
+    /// each match is checked against the live mappings.
+    pub fn contains_dummy(&self, data: &[u8]) -> bool {
+        self.maps().any(|map| {
+            map.patterns.find_iter(data).any(|m| {
+                self.secrets
+                    .contains_key(&map.dummy_secrets[m.pattern().as_usize()])
+            })
+        })
+    }
+
+    /// Automata to apply, pending first so a replaced mapping wins
+    fn maps(&self) -> impl Iterator<Item = &SecretMap> {
+        self.pending.iter().chain(self.compiled.iter())
+    }
+
+    /// Inject real secrets for registered dummies
+    pub fn inject(&self, data: &str) -> String {
+        // A removed dummy is still in the main automaton until the rebuild
+        if self
+            .removed
+            .iter()
+            .any(|dummy| data.contains(dummy.as_str()))
+        {
+            let mut result = data.to_string();
+            for (dummy, real) in &self.secrets {
+                result = result.replace(dummy.as_str(), real.as_str());
+            }
+            return result;
+        }
+
+        self.maps()
+            .fold(data.to_string(), |result, map| map.inject(&result))
+    }
+
+    /// Inject real secrets for registered dummies into binary data
+    pub fn inject_bytes(&self, data: &[u8]) -> Vec<u8> {
+        if self
+            .removed
+            .iter()
+            .any(|dummy| data.windows(dummy.len()).any(|w| w == dummy.as_bytes()))
+        {
+            return match SecretMap::new(self.secrets.clone()) {
+                Ok(map) => map.inject_bytes(data),
+                Err(_) => data.to_vec(),
+            };
+        }
+
+        self.maps()
+            .fold(data.to_vec(), |result, map| map.inject_bytes(&result))
+    }
+
+    /// Redact registered real secrets from UTF-8 data
+    pub fn sanitize(&self, data: &str) -> String {
+        self.maps()
+            .fold(data.to_string(), |result, map| map.sanitize(&result))
+    }
+
+    /// Redact registered real secrets from binary data
+    pub fn sanitize_bytes(&self, data: &[u8]) -> Vec<u8> {
+        self.maps().fold(data.to_vec(), |result, map| {
+            map.sanitize_bytes(&result).into_owned()
+        })
+    }
+
+    /// Count registered real secrets in data, each occurrence once
+    pub fn count_secrets(&self, data: &[u8]) -> usize {
+        let mut count = 0;
+        let mut remaining = Cow::Borrowed(data);
+        for map in self.maps() {
+            count += map.count_secrets(&remaining);
+            remaining = Cow::Owned(map.redact_unrecorded(&remaining));
+        }
+        count
+    }
+
+    /// Dummy -> real byte pairs, for building a streaming injector
+    pub fn injection_pairs(&self) -> Vec<(Vec<u8>, Vec<u8>)> {
+        self.secrets
+            .iter()
+            .map(|(dummy, real)| (dummy.as_bytes().to_vec(), real.as_bytes().to_vec()))
+            .collect()
+    }
+
+    /// Real secret bytes, for building a streaming sanitizer
+    pub fn real_secret_bytes(&self) -> Vec<Vec<u8>> {
+        self.secrets
+            .values()
+            .map(|real| real.as_bytes().to_vec())
+            .collect()
+    }
+}
+
+/// Incremental sanitizer for bodies that are too large to buffer
+///
+/// Chunks are redacted as they arrive. A tail that could be the start of a
+/// secret is held back between chunks, so a secret split across a chunk
+/// boundary is still caught once the rest of it arrives; anything else is
+/// emitted straight away, which keeps interactive streams flowing. Built with
+/// [`StreamingSanitizer::injector`], it replaces dummies with real secrets
+/// instead, for outbound streams. [`StreamingSanitizer::line_delimited`]
+/// emits whole lines only, for NDJSON and SSE bodies.
+pub struct StreamingSanitizer {
+    patterns: AhoCorasick,
+    /// Pattern bytes, for finding a tail that may start a match
+    needles: Vec<Vec<u8>>,
+    replacements: Vec<Vec<u8>>,
+    /// Bytes held back because they may be the start of a secret
+    pending: Vec<u8>,
+    /// Metric label for each replacement (injections are not recorded)
+    metric: Option<&'static str>,
+    redactions: usize,
+    /// Longest partial line held back when emitting whole lines only
+    max_line: Option<usize>,
+    /// A partial line outgrew `max_line`; the stream must be aborted
+    line_too_long: bool,
+}
+
+impl StreamingSanitizer {
+    /// Create a streaming sanitizer for the given real secrets
+    pub fn new(secrets: &[Vec<u8>]) -> Result<Self, String> {
+        let replacements = vec![REDACTED_MARKER.as_bytes().to_vec(); secrets.len()];
+        Self::build(
+            secrets,
+            replacements,
+            MatchKind::LeftmostLongest,
+            Some("streaming_sanitization"),
+        )
+    }
+
+    /// Create a streaming sanitizer replacing each real secret with its own marker
+    pub fn redactor(pairs: &[(Vec<u8>, Vec<u8>)]) -> Result<Self, String> {
+        let (secrets, markers): (Vec<_>, Vec<_>) = pairs.iter().cloned().unzip();
+        Self::build(
+            &secrets,
+            markers,
+            MatchKind::LeftmostLongest,
+            Some("streaming_sanitization"),
+        )
+    }
+
+    /// Create a streaming injector replacing each dummy with its real secret
+    ///
+    /// Overlapping dummies resolve like [`SecretMap::inject`]: first listed wins.
+    pub fn injector(pairs: &[(Vec<u8>, Vec<u8>)]) -> Result<Self, String> {
+        let (dummies, reals): (Vec<_>, Vec<_>) = pairs.iter().cloned().unzip();
+        Self::build(&dummies, reals, MatchKind::LeftmostFirst, None)
+    }
+
+    fn build(
+        needles: &[Vec<u8>],
+        replacements: Vec<Vec<u8>>,
+        match_kind: MatchKind,
+        metric: Option<&'static str>,
+    ) -> Result<Self, String> {
+        let patterns = AhoCorasickBuilder::new()
+            .match_kind(match_kind)
+            .build(needles)
+            .map_err(|e| format!("Failed to build streaming pattern matcher: {}", e))?;
+
+        Ok(Self {
+            patterns,
+            needles: needles.to_vec(),
+            replacements,
+            pending: Vec::new(),
+            metric,
+            redactions: 0,
+            max_line: None,
+            line_too_long: false,
+        })
+    }
+
+    /// Emit complete lines only, holding a partial line until its newline
+    ///
+    /// Each line (an NDJSON record or SSE field) reaches the client whole
+    /// and sanitized. A partial line longer than `max_line` is discarded and
+    /// [`StreamingSanitizer::line_too_long`] set, so an unterminated line
+    /// cannot grow the held-back buffer without bound.
+    pub fn line_delimited(mut self, max_line: usize) -> Self {
+        self.max_line = Some(max_line);
+        self
+    }
+
+    /// Add a chunk, returning the sanitized bytes that are safe to emit
+    pub fn push(&mut self, chunk: &[u8]) -> Vec<u8> {
+        self.pending.extend_from_slice(chunk);
+        let mut safe = self.pending.len() - partial_match_len(&self.pending, &self.needles);
+        if let Some(max_line) = self.max_line {
+            let line_end = self
+                .pending
+                .iter()
+                .rposition(|&b| b == b'\n')
+                .map_or(0, |newline| newline + 1);
+            if self.pending.len() - line_end > max_line {
+                self.line_too_long = true;
+                let complete = self.drain_until(line_end);
+                self.pending.zeroize();
+                self.pending.clear();
+                return complete;
+            }
+            safe = safe.min(line_end);
+        }
+        self.drain_until(safe)
+    }
+
+    /// Whether a partial line exceeded the [`StreamingSanitizer::line_delimited`] limit
+    pub fn line_too_long(&self) -> bool {
+        self.line_too_long
+    }
+
+    /// Sanitize and return everything still held back
+    pub fn finish(&mut self) -> Vec<u8> {
+        self.drain_until(self.pending.len())
+    }
+
+    /// Number of secrets redacted (or dummies injected) so far
+    pub fn redactions(&self) -> usize {
+        self.redactions
+    }
+
+    /// Emit pending bytes up to `cut`, extended past any match straddling it
+    ///
+    /// Any match starting before `cut` lies wholly inside `pending`: an
+    /// incomplete one would make its start part of the held-back tail.
+    fn drain_until(&mut self, mut cut: usize) -> Vec<u8> {
+        let mut out = Vec::with_capacity(cut);
+        let mut last = 0;
+
+        for m in self.patterns.find_iter(&self.pending) {
+            if m.start() >= cut {
+                break;
+            }
+            out.extend_from_slice(&self.pending[last..m.start()]);
+            out.extend_from_slice(&self.replacements[m.pattern().as_usize()]);
+            if let Some(metric) = self.metric {
+                metrics::record_secret_sanitized(metric);
+            }
+            self.redactions += 1;
+            last = m.end();
+            cut = cut.max(m.end());
+        }
+
+        out.extend_from_slice(&self.pending[last..cut]);
+        self.pending[..cut].zeroize();
+        self.pending.drain(..cut);
+        out
+    }
+}
+
+impl Drop for StreamingSanitizer {
+    fn drop(&mut self) {
+        self.pending.zeroize();
+        self.needles.zeroize();
+        self.replacements.zeroize();
+    }
+}
+
+/// Size of each read from the reader wrapped by [`SanitizingReader`]
+const SANITIZING_READ_SIZE: usize = 8192;
+
+/// An [`AsyncRead`] yielding the output of a [`StreamingSanitizer`] over another
+pub struct SanitizingReader<R> {
+    inner: R,
+    sanitizer: StreamingSanitizer,
+    /// Sanitized bytes not yet handed to the caller
+    output: Vec<u8>,
+    position: usize,
+    eof: bool,
+}
+
+impl<R> SanitizingReader<R> {
+    pub fn new(inner: R, sanitizer: StreamingSanitizer) -> Self {
+        Self {
+            inner,
+            sanitizer,
+            output: Vec::new(),
+            position: 0,
+            eof: false,
+        }
+    }
+
+    /// Number of secrets redacted so far
+    pub fn redactions(&self) -> usize {
+        self.sanitizer.redactions()
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for SanitizingReader<R> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let this = self.get_mut();
+        loop {
+            if this.position < this.output.len() {
+                let n = buf.remaining().min(this.output.len() - this.position);
+                buf.put_slice(&this.output[this.position..this.position + n]);
+                this.position += n;
+                if this.position == this.output.len() {
+                    this.output.clear();
+                    this.position = 0;
+                }
+                return Poll::Ready(Ok(()));
+            }
+            if this.eof {
+                return Poll::Ready(Ok(()));
+            }
+
+            let mut chunk = [0u8; SANITIZING_READ_SIZE];
+            let mut read = ReadBuf::new(&mut chunk);
+            ready!(Pin::new(&mut this.inner).poll_read(cx, &mut read))?;
+            this.output = if read.filled().is_empty() {
+                this.eof = true;
+                this.sanitizer.finish()
+            } else {
+                this.sanitizer.push(read.filled())
+            };
+            // The raw chunk may hold a secret
+            chunk.zeroize();
+        }
+    }
+}
+
+/// Redact secrets from the string values of a JSON document only
+///
+/// Object keys, numbers and literals are left alone, so substring matching
+/// cannot corrupt the structure. String values are matched after JSON
+/// unescaping, which also catches a secret written with escapes such as
+/// `\/` or `\u002d`. The document is re-serialized (keys in their original
+/// order) only when a value changed. Returns `None` if `body` is not JSON.
+pub fn sanitize_json_values(body: &[u8], sanitize: impl Fn(&str) -> String) -> Option<Vec<u8>> {
+    fn walk(value: &mut serde_json::Value, sanitize: &impl Fn(&str) -> String) -> bool {
+        match value {
+            serde_json::Value::String(text) => {
+                let sanitized = sanitize(text);
+                if sanitized == *text {
+                    return false;
+                }
+                text.zeroize();
+                *text = sanitized;
+                true
+            }
+            serde_json::Value::Array(items) => items
+                .iter_mut()
+                .fold(false, |changed, item| walk(item, sanitize) | changed),
+            serde_json::Value::Object(fields) => fields
+                .values_mut()
+                .fold(false, |changed, field| walk(field, sanitize) | changed),
+            _ => false,
+        }
+    }
+
+    let mut document: serde_json::Value = serde_json::from_slice(body).ok()?;
+    if !walk(&mut document, &sanitize) {
+        return Some(body.to_vec());
+    }
+    serde_json::to_vec(&document).ok()
+}
+
+/// Longest character reference decoded, e.g. `&#x0010FFFF;`
+const MAX_HTML_ENTITY_LEN: usize = 12;
+
+/// Redact secrets that only appear once HTML character references are decoded
+///
+/// An HTML error page may echo `sk&#45;...` or `a&amp;b` where the secret is
+/// `sk-...` or `a&b`, which literal matching misses. The page is decoded with
+/// a map back to the source, and each source region that decodes to one of
+/// `secrets` is replaced by `redact` of the decoded secret. Redaction markers
+/// hold no `<`, `>`, `&` or quotes, so they are safe in HTML as written.
+/// Returns `None` when nothing was redacted.
+pub fn redact_html_entity_secrets(
+    data: &[u8],
+    secrets: &[Vec<u8>],
+    redact: impl Fn(&[u8]) -> Vec<u8>,
+) -> Option<Vec<u8>> {
+    if secrets.is_empty() || !data.contains(&b'&') {
+        return None;
+    }
+    let matcher = AhoCorasickBuilder::new()
+        .match_kind(MatchKind::LeftmostLongest)
+        .build(secrets)
+        .ok()?;
+
+    // Each decoded byte remembers the source range it came from
+    let mut decoded = Vec::with_capacity(data.len());
+    let mut spans = Vec::with_capacity(data.len());
+    let mut i = 0;
+    while i < data.len() {
+        if let Some((c, len)) = decode_html_entity(&data[i..]) {
+            let mut buf = [0u8; 4];
+            for byte in c.encode_utf8(&mut buf).bytes() {
+                decoded.push(byte);
+                spans.push((i, i + len));
+            }
+            i += len;
+        } else {
+            decoded.push(data[i]);
+            spans.push((i, i + 1));
+            i += 1;
+        }
+    }
+
+    let mut out = Vec::with_capacity(data.len());
+    let mut last = 0;
+    for m in matcher.find_iter(&decoded) {
+        let start = spans[m.start()].0.max(last);
+        out.extend_from_slice(&data[last..start]);
+        out.extend_from_slice(&redact(&decoded[m.range()]));
+        last = spans[m.end() - 1].1;
+    }
+    decoded.zeroize();
+    if last == 0 {
+        return None;
+    }
+    out.extend_from_slice(&data[last..]);
+    Some(out)
+}
+
+/// Decode the character reference at the start of `data`, if there is one
+///
+/// Handles numeric references and the named ones HTML escaping produces;
+/// the terminating `;` is required. Returns the character and source length.
+fn decode_html_entity(data: &[u8]) -> Option<(char, usize)> {
+    if data.first() != Some(&b'&') {
+        return None;
+    }
+    let end = data
+        .iter()
+        .take(MAX_HTML_ENTITY_LEN)
+        .position(|&b| b == b';')?;
+    let name = std::str::from_utf8(&data[1..end]).ok()?;
+    let c = match name {
+        "amp" => '&',
+        "lt" => '<',
+        "gt" => '>',
+        "quot" => '"',
+        "apos" => '\'',
+        _ => {
+            let number = name.strip_prefix('#')?;
+            let code = match number.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                None => number.parse().ok()?,
+            };
+            char::from_u32(code)?
+        }
+    };
+    Some((c, end + 1))
+}
+
+/// Find credential-shaped tokens in `data`
+///
+/// A token is a run of `[A-Za-z0-9_-]` that starts with a well-known
+/// credential prefix, is at least 20 characters long, and whose remainder has
+/// enough entropy to rule out placeholders such as `sk-xxxxxxxxxxxxxxxxxxxx`.
+/// Returns `(prefix, token)` pairs; callers decide which tokens are unmanaged.
+pub fn find_credential_candidates(data: &str) -> Vec<(&'static str, &str)> {
+    data.split(|c: char| !(c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+        .filter(|token| token.len() >= MIN_CREDENTIAL_LEN)
+        .filter_map(|token| {
+            let prefix = CREDENTIAL_PREFIXES
+                .iter()
+                .find(|prefix| token.starts_with(*prefix))?;
+            (shannon_entropy(&token[prefix.len()..]) >= MIN_CREDENTIAL_ENTROPY)
+                .then_some((*prefix, token))
+        })
+        .collect()
+}
+
+/// Shannon entropy in bits per character
+fn shannon_entropy(data: &str) -> f64 {
+    let mut counts = [0usize; 256];
+    for byte in data.bytes() {
+        counts[byte as usize] += 1;
+    }
+
+    let len = data.len() as f64;
+    counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Load additional dummy -> real mappings from a JSON or YAML file
+///
+/// The file holds real credentials, so it must not be accessible to group or
+/// other users. Neither the file contents nor parse errors are logged, since
+/// either could echo a secret.
+pub fn load_secrets_file<P: AsRef<Path>>(path: P) -> Result<HashMap<String, String>, String> {
+    let path = path.as_ref();
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        let mode = std::fs::metadata(path)
+            .map_err(|e| format!("Failed to read secrets file {}: {}", path.display(), e))?
+            .permissions()
+            .mode();
+        if mode & 0o077 != 0 {
+            return Err(format!(
+                "Secrets file {} has permissions {:o}; restrict it to the owner (chmod 600)",
+                path.display(),
+                mode & 0o777
+            ));
+        }
+    }
+
+    let mut content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read secrets file {}: {}", path.display(), e))?;
+
+    // YAML is a superset of JSON, so one parser handles both formats
+    let parsed = serde_yaml::from_str::<HashMap<String, String>>(&content);
+    content.zeroize();
+
+    parsed.map_err(|_| {
+        format!(
+            "Failed to parse secrets file {}: expected a mapping of dummy to real strings",
+            path.display()
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_test_map() -> SecretMap {
+        let mut secrets = HashMap::new();
+        secrets.insert("DUMMY_GITHUB".to_string(), "ghp_realtoken123".to_string());
+        secrets.insert("DUMMY_OPENAI".to_string(), "sk-realkey456".to_string());
+        secrets.insert("DUMMY_AWS".to_string(), "AKIA_AWSKEY789".to_string());
+        SecretMap::new(secrets).unwrap()
+    }
+
+    #[test]
+    fn test_secret_map_creation() {
+        let map = create_test_map();
+        assert_eq!(map.len(), 3);
+        assert!(!map.is_empty());
+    }
+
+    #[test]
+    fn test_empty_secret_map_fails() {
+        let secrets = HashMap::new();
+        let result = SecretMap::new(secrets);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_inject_single_token() {
+        let map = create_test_map();
+        let input = "Authorization: Bearer DUMMY_GITHUB";
+        let output = map.inject(input);
+        assert_eq!(output, "Authorization: Bearer ghp_realtoken123");
+    }
+
+    #[test]
+    fn test_inject_multiple_tokens() {
+        let map = create_test_map();
+        let input = "GitHub: DUMMY_GITHUB, OpenAI: DUMMY_OPENAI";
+        let output = map.inject(input);
+        assert_eq!(output, "GitHub: ghp_realtoken123, OpenAI: sk-realkey456");
+    }
+
+    #[test]
+    fn test_inject_bytes_preserves_invalid_utf8() {
+        let map = create_test_map();
+        let input = b"\xff\xfe token=DUMMY_GITHUB \x80";
+        let output = map.inject_bytes(input);
+        assert_eq!(output, b"\xff\xfe token=ghp_realtoken123 \x80");
+    }
+
+    #[test]
+    fn test_injected_labels() {
+        let map = create_test_map();
+        assert_eq!(map.injection_labels().len(), 3);
+        assert_eq!(
+            map.injected_labels(b"a DUMMY_GITHUB b DUMMY_GITHUB"),
+            BTreeSet::from(["DUMMY_GITHUB"])
+        );
+        assert!(map.injected_header_labels("x-api-key", "none").is_empty());
     }
 
     #[test]
@@ -335,6 +1750,28 @@ mod tests {
         assert!(!sanitized.contains("ghp_realtoken123"));
     }
 
+    #[test]
+    fn test_count_secrets() {
+        let map = create_test_map();
+        assert_eq!(map.count_secrets(b"nothing here"), 0);
+        assert_eq!(
+            map.count_secrets(b"sk-realkey456 and ghp_realtoken123 and sk-realkey456"),
+            3
+        );
+    }
+
+    #[test]
+    fn test_count_secrets_by_label() {
+        let map = create_test_map();
+        assert!(map.count_secrets_by_label(b"nothing here").is_empty());
+
+        let counts =
+            map.count_secrets_by_label(b"sk-realkey456 and ghp_realtoken123 and sk-realkey456");
+        assert_eq!(counts.len(), 2);
+        assert_eq!(counts["DUMMY_OPENAI"], 2);
+        assert_eq!(counts["DUMMY_GITHUB"], 1);
+    }
+
     #[test]
     fn test_empty_string() {
         let map = create_test_map();
@@ -388,9 +1825,905 @@ mod tests {
     }
 
     #[test]
-    fn test_from_strategies_empty() {
-        let strategies: Vec<Box<dyn AuthStrategy>> = vec![];
-        let result = SecretMap::from_strategies(&strategies);
-        assert!(result.is_err());
+    fn test_from_strategies_scopes_injection_to_targets() {
+        use crate::strategy::{BearerStrategy, InjectTargets};
+
+        std::env::set_var("TEST_SCOPED_HEADER_TOKEN", "header_real_333");
+        std::env::set_var("TEST_SCOPED_ANY_TOKEN", "any_real_444");
+
+        let strategies: Vec<Box<dyn AuthStrategy>> = vec![
+            Box::new(
+                BearerStrategy::new(
+                    "header-only".to_string(),
+                    "TEST_SCOPED_HEADER_TOKEN".to_string(),
+                    "DUMMY_SCOPED_HEADER".to_string(),
+                    vec![],
+                )
+                .unwrap()
+                .with_inject_targets(InjectTargets::parse(&["Authorization".to_string()]).unwrap()),
+            ),
+            Box::new(
+                BearerStrategy::new(
+                    "anywhere".to_string(),
+                    "TEST_SCOPED_ANY_TOKEN".to_string(),
+                    "DUMMY_SCOPED_ANY".to_string(),
+                    vec![],
+                )
+                .unwrap(),
+            ),
+        ];
+        let map = SecretMap::from_strategies(&strategies).unwrap();
+
+        assert_eq!(
+            map.inject("DUMMY_SCOPED_HEADER DUMMY_SCOPED_ANY"),
+            "DUMMY_SCOPED_HEADER any_real_444"
+        );
+        assert_eq!(
+            map.inject_header("authorization", "Bearer DUMMY_SCOPED_HEADER"),
+            "Bearer header_real_333"
+        );
+        assert_eq!(
+            map.inject_header("x-api-key", "DUMMY_SCOPED_HEADER DUMMY_SCOPED_ANY"),
+            "DUMMY_SCOPED_HEADER any_real_444"
+        );
+
+        // Streams are injected like bodies: the header-only secret stays out
+        let mut injector = StreamingSanitizer::injector(&map.injection_pairs()).unwrap();
+        let mut streamed = injector.push(b"DUMMY_SCOPED_HEADER DUMMY_SCOPED_ANY");
+        streamed.extend(injector.finish());
+        assert_eq!(streamed, b"DUMMY_SCOPED_HEADER any_real_444");
+
+        // Scoping survives adding secrets
+        let extended = map
+            .with_additional_secrets(HashMap::from([(
+                "DUMMY_SCOPED_EXTRA".to_string(),
+                "extra_real_555".to_string(),
+            )]))
+            .unwrap();
+        assert_eq!(
+            extended.inject("DUMMY_SCOPED_HEADER DUMMY_SCOPED_EXTRA"),
+            "DUMMY_SCOPED_HEADER extra_real_555"
+        );
+    }
+
+    #[test]
+    fn test_injection_pairs_exclude_signing_keys() {
+        use crate::strategies::hmac::HmacStrategy;
+
+        std::env::set_var("TEST_PAIRS_HMAC_SECRET", "hmac_signing_key_666");
+        let strategies: Vec<Box<dyn AuthStrategy>> = vec![Box::new(
+            HmacStrategy::new(
+                "signed".to_string(),
+                "TEST_PAIRS_HMAC_SECRET".to_string(),
+                "DUMMY_PAIRS_HMAC".to_string(),
+                vec![],
+            )
+            .unwrap(),
+        )];
+        let map = SecretMap::from_strategies(&strategies).unwrap();
+
+        assert!(map.injection_pairs().is_empty());
+        // Still redacted if it ever shows up
+        assert_eq!(map.sanitize("hmac_signing_key_666"), "[REDACTED]");
+    }
+
+    #[test]
+    fn test_from_strategies_sanitize_only() {
+        use crate::strategy::BearerStrategy;
+
+        std::env::set_var("TEST_SANITIZE_ONLY_TOKEN", "webhook_secret_789");
+
+        let strategies: Vec<Box<dyn AuthStrategy>> = vec![Box::new(
+            BearerStrategy::new(
+                "webhook".to_string(),
+                "TEST_SANITIZE_ONLY_TOKEN".to_string(),
+                "DUMMY_WEBHOOK".to_string(),
+                vec![],
+            )
+            .unwrap()
+            .with_sanitize_only(true),
+        )];
+
+        let map = SecretMap::from_strategies(&strategies).unwrap();
+        assert_eq!(map.len(), 1);
+        assert!(map.dummy_keys().is_empty());
+        assert_eq!(map.inject("key=DUMMY_WEBHOOK"), "key=DUMMY_WEBHOOK");
+        assert_eq!(map.sanitize("echo webhook_secret_789"), "echo [REDACTED]");
+        assert_eq!(
+            map.sanitize_bytes(b"echo webhook_secret_789").as_ref(),
+            b"echo [REDACTED]"
+        );
+    }
+
+    #[test]
+    fn test_from_strategies_mixed_sanitize_only() {
+        use crate::strategy::BearerStrategy;
+
+        std::env::set_var("TEST_MIXED_INJECT_TOKEN", "inject_real_111");
+        std::env::set_var("TEST_MIXED_SANITIZE_TOKEN", "sanitize_real_222");
+
+        let strategies: Vec<Box<dyn AuthStrategy>> = vec![
+            Box::new(
+                BearerStrategy::new(
+                    "sanitize".to_string(),
+                    "TEST_MIXED_SANITIZE_TOKEN".to_string(),
+                    "DUMMY_MIXED_SANITIZE".to_string(),
+                    vec![],
+                )
+                .unwrap()
+                .with_sanitize_only(true),
+            ),
+            Box::new(
+                BearerStrategy::new(
+                    "inject".to_string(),
+                    "TEST_MIXED_INJECT_TOKEN".to_string(),
+                    "DUMMY_MIXED_INJECT".to_string(),
+                    vec![],
+                )
+                .unwrap(),
+            ),
+        ];
+
+        let map = SecretMap::from_strategies(&strategies).unwrap();
+        assert_eq!(map.len(), 2);
+        assert_eq!(
+            map.inject("DUMMY_MIXED_INJECT DUMMY_MIXED_SANITIZE"),
+            "inject_real_111 DUMMY_MIXED_SANITIZE"
+        );
+        assert_eq!(
+            map.sanitize("inject_real_111 sanitize_real_222"),
+            "[REDACTED] [REDACTED]"
+        );
+
+        // Added secrets keep the sanitize-only entry out of injection
+        let mut extra = HashMap::new();
+        extra.insert(
+            "DUMMY_MIXED_EXTRA".to_string(),
+            "extra_real_333".to_string(),
+        );
+        let map = map.with_additional_secrets(extra).unwrap();
+        assert_eq!(
+            map.inject("DUMMY_MIXED_EXTRA DUMMY_MIXED_INJECT"),
+            "extra_real_333 inject_real_111"
+        );
+        assert_eq!(map.sanitize("sanitize_real_222"), "[REDACTED]");
+    }
+
+    #[test]
+    fn test_from_strategies_priority_orders_overlapping_injection() {
+        use crate::strategy::BearerStrategy;
+
+        std::env::set_var("TEST_PRIORITY_GENERIC_TOKEN", "generic_real");
+        std::env::set_var("TEST_PRIORITY_OPENAI_TOKEN", "openai_real");
+
+        let bearer = |name: &str, env_var: &str, dummy: &str, priority: i32| {
+            Box::new(
+                BearerStrategy::new(
+                    name.to_string(),
+                    env_var.to_string(),
+                    dummy.to_string(),
+                    vec![],
+                )
+                .unwrap()
+                .with_priority(priority),
+            ) as Box<dyn AuthStrategy>
+        };
+        let generic = |priority| {
+            bearer(
+                "generic",
+                "TEST_PRIORITY_GENERIC_TOKEN",
+                "DUMMY_KEY",
+                priority,
+            )
+        };
+        let openai = |priority| {
+            bearer(
+                "openai",
+                "TEST_PRIORITY_OPENAI_TOKEN",
+                "DUMMY_KEY_OPENAI",
+                priority,
+            )
+        };
+        let body = "Bearer DUMMY_KEY_OPENAI";
+
+        // The provider-specific strategy wins regardless of configured order
+        for strategies in [vec![generic(0), openai(10)], vec![openai(10), generic(0)]] {
+            let map = SecretMap::from_strategies(&strategies).unwrap();
+            assert_eq!(map.inject(body), "Bearer openai_real");
+
+            let mut injector = StreamingSanitizer::injector(&map.injection_pairs()).unwrap();
+            let mut streamed = injector.push(body.as_bytes());
+            streamed.extend(injector.finish());
+            assert_eq!(streamed, b"Bearer openai_real");
+        }
+
+        // Raising the generic strategy above it flips the outcome
+        let map = SecretMap::from_strategies(&[openai(10), generic(20)]).unwrap();
+        assert_eq!(map.inject(body), "Bearer generic_real_OPENAI");
+    }
+
+    #[test]
+    fn test_to_sanitize_only_never_injects() {
+        let map = create_test_map().to_sanitize_only().unwrap();
+
+        assert_eq!(map.inject("DUMMY_OPENAI"), "DUMMY_OPENAI");
+        assert!(!map.is_dummy("DUMMY_OPENAI"));
+        assert_eq!(map.sanitize("key: sk-realkey456"), "key: [REDACTED]");
+    }
+
+    #[test]
+    fn test_scoped_injects_allowed_labels_only() {
+        let map = create_test_map()
+            .scoped(&["DUMMY_OPENAI".to_string()])
+            .unwrap();
+
+        assert_eq!(
+            map.inject("DUMMY_OPENAI DUMMY_GITHUB"),
+            "sk-realkey456 DUMMY_GITHUB"
+        );
+        assert!(!map.is_dummy("DUMMY_GITHUB"));
+        // Withheld secrets are still redacted
+        assert_eq!(
+            map.sanitize("sk-realkey456 ghp_realtoken123"),
+            "[REDACTED] [REDACTED]"
+        );
+
+        let none = create_test_map().scoped(&[]).unwrap();
+        assert_eq!(none.inject("DUMMY_AWS"), "DUMMY_AWS");
+        assert_eq!(none.sanitize("AKIA_AWSKEY789"), "[REDACTED]");
+    }
+
+    #[test]
+    fn test_scoped_keeps_inject_targets() {
+        use crate::strategy::{BearerStrategy, InjectTargets};
+
+        std::env::set_var("TEST_CLIENT_SCOPE_OPENAI", "sk-scope-openai");
+        std::env::set_var("TEST_CLIENT_SCOPE_GITHUB", "ghp_scope_github");
+
+        let strategies: Vec<Box<dyn AuthStrategy>> = vec![
+            Box::new(
+                BearerStrategy::new(
+                    "openai".to_string(),
+                    "TEST_CLIENT_SCOPE_OPENAI".to_string(),
+                    "DUMMY_SCOPE_OPENAI".to_string(),
+                    vec![],
+                )
+                .unwrap()
+                .with_inject_targets(InjectTargets::parse(&["Authorization".to_string()]).unwrap()),
+            ),
+            Box::new(
+                BearerStrategy::new(
+                    "github".to_string(),
+                    "TEST_CLIENT_SCOPE_GITHUB".to_string(),
+                    "DUMMY_SCOPE_GITHUB".to_string(),
+                    vec![],
+                )
+                .unwrap(),
+            ),
+        ];
+        let map = SecretMap::from_strategies(&strategies)
+            .unwrap()
+            .scoped(&["openai".to_string()])
+            .unwrap();
+
+        assert_eq!(
+            map.inject_header("authorization", "Bearer DUMMY_SCOPE_OPENAI"),
+            "Bearer sk-scope-openai"
+        );
+        assert_eq!(map.inject("DUMMY_SCOPE_OPENAI"), "DUMMY_SCOPE_OPENAI");
+        assert_eq!(
+            map.inject_header("authorization", "Bearer DUMMY_SCOPE_GITHUB"),
+            "Bearer DUMMY_SCOPE_GITHUB"
+        );
+        assert_eq!(map.sanitize("ghp_scope_github"), "[REDACTED]");
+    }
+
+    #[test]
+    fn test_from_strategies_empty() {
+        let strategies: Vec<Box<dyn AuthStrategy>> = vec![];
+        let result = SecretMap::from_strategies(&strategies);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_with_additional_secrets_keeps_existing_mappings() {
+        let mut extra = HashMap::new();
+        extra.insert("DUMMY_DB_PASSWORD".to_string(), "hunter2-db".to_string());
+        extra.insert("DUMMY_GITHUB".to_string(), "ghp_other".to_string());
+
+        let map = create_test_map().with_additional_secrets(extra).unwrap();
+        assert_eq!(map.len(), 4);
+        assert_eq!(
+            map.inject("DUMMY_DB_PASSWORD DUMMY_GITHUB"),
+            "hunter2-db ghp_realtoken123"
+        );
+        assert_eq!(map.sanitize("pw=hunter2-db"), "pw=[REDACTED]");
+    }
+
+    #[cfg(unix)]
+    fn write_secrets_file(content: &str, mode: u32) -> tempfile::NamedTempFile {
+        use std::io::Write;
+        use std::os::unix::fs::PermissionsExt;
+
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(content.as_bytes()).unwrap();
+        std::fs::set_permissions(file.path(), std::fs::Permissions::from_mode(mode)).unwrap();
+        file
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_load_secrets_file_json_and_yaml() {
+        let json = write_secrets_file(r#"{"DUMMY_DB": "db-real"}"#, 0o600);
+        let yaml = write_secrets_file("DUMMY_DB: db-real\nDUMMY_SMTP: smtp-real\n", 0o400);
+
+        assert_eq!(
+            load_secrets_file(json.path()).unwrap()["DUMMY_DB"],
+            "db-real"
+        );
+        let secrets = load_secrets_file(yaml.path()).unwrap();
+        assert_eq!(secrets.len(), 2);
+        assert_eq!(secrets["DUMMY_SMTP"], "smtp-real");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_load_secrets_file_rejects_loose_permissions() {
+        let file = write_secrets_file("DUMMY_DB: db-real\n", 0o644);
+
+        let err = load_secrets_file(file.path()).unwrap_err();
+        assert!(err.contains("644"));
+        assert!(!err.contains("db-real"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_load_secrets_file_parse_error_hides_content() {
+        let file = write_secrets_file("- db-real\n", 0o600);
+
+        let err = load_secrets_file(file.path()).unwrap_err();
+        assert!(!err.contains("db-real"));
+    }
+
+    #[test]
+    fn test_find_credential_candidates() {
+        let body = r#"{"key": "sk-proj-9fK2mQx7LpR4vT8wZ1nB", "gh": "ghp_A1b2C3d4E5f6G7h8I9j0"}"#;
+        let found = find_credential_candidates(body);
+        assert_eq!(
+            found,
+            vec![
+                ("sk-", "sk-proj-9fK2mQx7LpR4vT8wZ1nB"),
+                ("ghp_", "ghp_A1b2C3d4E5f6G7h8I9j0")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_find_credential_candidates_ignores_placeholders() {
+        assert!(find_credential_candidates("sk-xxxxxxxxxxxxxxxxxxxxxxxx").is_empty());
+        assert!(find_credential_candidates("sk-short1").is_empty());
+        assert!(find_credential_candidates("task-9fK2mQx7LpR4vT8wZ1nB").is_empty());
+        assert!(find_credential_candidates("DUMMY_OPENAI").is_empty());
+    }
+
+    #[test]
+    fn test_is_managed() {
+        let map = create_test_map();
+        assert!(map.is_managed("DUMMY_OPENAI"));
+        assert!(map.is_managed("sk-realkey456"));
+        assert!(!map.is_managed("sk-unknown"));
+    }
+
+    fn stream(sanitizer: &mut StreamingSanitizer, chunks: &[&[u8]]) -> Vec<u8> {
+        let mut out = Vec::new();
+        for chunk in chunks {
+            out.extend(sanitizer.push(chunk));
+        }
+        out.extend(sanitizer.finish());
+        out
+    }
+
+    #[test]
+    fn test_line_delimited_sanitizer_emits_whole_lines() {
+        let map = create_test_map();
+        let mut sanitizer = StreamingSanitizer::new(map.real_secret_bytes())
+            .unwrap()
+            .line_delimited(1024);
+
+        // Earlier lines go out before the stream ends; a partial line waits
+        assert_eq!(
+            sanitizer.push(b"{\"n\":1}\n{\"n\":2,\"t\":\"ghp_re"),
+            b"{\"n\":1}\n"
+        );
+        assert_eq!(
+            sanitizer.push(b"altoken123\"}\n{\"n\":3}\n{\"n\":"),
+            b"{\"n\":2,\"t\":\"[REDACTED]\"}\n{\"n\":3}\n"
+        );
+        assert_eq!(sanitizer.push(b"4}"), b"");
+        assert_eq!(sanitizer.finish(), b"{\"n\":4}");
+        assert_eq!(sanitizer.redactions(), 1);
+    }
+
+    #[test]
+    fn test_line_delimited_sanitizer_flags_overlong_line() {
+        let map = create_test_map();
+        let mut sanitizer = StreamingSanitizer::new(map.real_secret_bytes())
+            .unwrap()
+            .line_delimited(8);
+
+        assert_eq!(sanitizer.push(b"ok\n0123"), b"ok\n");
+        assert!(!sanitizer.line_too_long());
+
+        // Complete lines still go out; the unterminated one is dropped
+        assert_eq!(sanitizer.push(b"456789 sk-realkey456"), b"");
+        assert!(sanitizer.line_too_long());
+        assert!(sanitizer.finish().is_empty());
+    }
+
+    #[test]
+    fn test_streaming_sanitizer_secret_across_chunks() {
+        let map = create_test_map();
+        let mut sanitizer = StreamingSanitizer::new(map.real_secret_bytes()).unwrap();
+
+        let out = stream(
+            &mut sanitizer,
+            &[
+                b"token=ghp_real",
+                b"tok",
+                b"en123 key=sk-realkey456",
+                b" end",
+            ],
+        );
+
+        assert_eq!(out, b"token=[REDACTED] key=[REDACTED] end");
+        assert_eq!(sanitizer.redactions(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_sanitize_stream_secret_across_reads() {
+        use tokio::io::AsyncReadExt;
+
+        let map = create_test_map();
+        let upstream = tokio_test::io::Builder::new()
+            .read(b"before ghp_realt")
+            .read(b"oken123 after")
+            .build();
+
+        let mut reader = map.sanitize_stream(upstream).unwrap();
+        let mut out = Vec::new();
+        reader.read_to_end(&mut out).await.unwrap();
+
+        assert_eq!(out, b"before [REDACTED] after");
+        assert_eq!(reader.redactions(), 1);
+    }
+
+    #[test]
+    fn test_streaming_sanitizer_matches_buffered_output() {
+        let map = create_test_map();
+        let body = b"a AKIA_AWSKEY789 b sk-realkey456sk-realkey456 c ghp_realtoken12";
+
+        for chunk_size in 1..body.len() {
+            let mut sanitizer = StreamingSanitizer::new(map.real_secret_bytes()).unwrap();
+            let chunks: Vec<&[u8]> = body.chunks(chunk_size).collect();
+            assert_eq!(
+                stream(&mut sanitizer, &chunks),
+                map.sanitize_bytes(body).into_owned(),
+                "chunk size {}",
+                chunk_size
+            );
+        }
+    }
+
+    #[test]
+    fn test_streaming_sanitizer_holds_back_only_possible_secret_starts() {
+        let map = create_test_map();
+        let mut sanitizer = StreamingSanitizer::new(map.real_secret_bytes()).unwrap();
+
+        // Nothing here can start a secret, so nothing is held back
+        assert_eq!(sanitizer.push(b"plain text\r\n"), b"plain text\r\n");
+        // "sk-" may be the start of a secret
+        assert_eq!(sanitizer.push(b"next sk-"), b"next ");
+        assert_eq!(sanitizer.push(b"other"), b"sk-other");
+    }
+
+    #[test]
+    fn test_streaming_injector() {
+        let pairs = vec![(b"DUMMY_TOKEN".to_vec(), b"real_secret".to_vec())];
+        let mut injector = StreamingSanitizer::injector(&pairs).unwrap();
+
+        let mut out = injector.push(b"auth=DUMMY_");
+        out.extend(injector.push(b"TOKEN&x=1"));
+        out.extend(injector.finish());
+
+        assert_eq!(out, b"auth=real_secret&x=1");
+        assert_eq!(injector.redactions(), 1);
+    }
+
+    #[test]
+    fn test_indexed_redaction_distinguishes_secrets() {
+        let map = create_test_map().with_redaction_style(RedactionStyle::Indexed);
+
+        // Indices follow the sorted dummies: AWS, GITHUB, OPENAI
+        assert_eq!(
+            map.sanitize("AKIA_AWSKEY789ghp_realtoken123"),
+            "[REDACTED:1][REDACTED:2]"
+        );
+        assert_eq!(
+            map.sanitize("ghp_realtoken123ghp_realtoken123 sk-realkey456"),
+            "[REDACTED:2][REDACTED:2] [REDACTED:3]"
+        );
+        assert_eq!(
+            map.sanitize_bytes(b"\xffsk-realkey456").as_ref(),
+            b"\xff[REDACTED:3]"
+        );
+        assert_eq!(map.redaction_index_count(), 3);
+        assert_eq!(map.redaction_label(2), Some("DUMMY_GITHUB"));
+        assert_eq!(map.redaction_label(4), None);
+
+        let mut sanitizer = StreamingSanitizer::redactor(&map.redaction_pairs()).unwrap();
+        let mut out = sanitizer.push(b"key=sk-real");
+        out.extend(sanitizer.push(b"key456"));
+        out.extend(sanitizer.finish());
+        assert_eq!(out, b"key=[REDACTED:3]");
+
+        // Plain stays the default
+        assert_eq!(create_test_map().sanitize("sk-realkey456"), "[REDACTED]");
+    }
+
+    #[test]
+    fn test_indexed_redaction_shared_secret_maps_to_strategy() {
+        use crate::strategy::BearerStrategy;
+
+        std::env::set_var("TEST_INDEXED_SHARED_TOKEN", "shared_real_555");
+        std::env::set_var("TEST_INDEXED_OTHER_TOKEN", "other_real_666");
+
+        let strategy = |name: &str, env: &str, dummy: &str| -> Box<dyn AuthStrategy> {
+            Box::new(
+                BearerStrategy::new(name.to_string(), env.to_string(), dummy.to_string(), vec![])
+                    .unwrap(),
+            )
+        };
+        let strategies = vec![
+            strategy("primary", "TEST_INDEXED_SHARED_TOKEN", "DUMMY_PRIMARY"),
+            strategy("other", "TEST_INDEXED_OTHER_TOKEN", "DUMMY_OTHER"),
+            strategy("alias", "TEST_INDEXED_SHARED_TOKEN", "DUMMY_ALIAS"),
+        ];
+
+        let map = SecretMap::from_strategies(&strategies)
+            .unwrap()
+            .with_redaction_style(RedactionStyle::Indexed);
+        assert_eq!(
+            map.sanitize("shared_real_555 other_real_666"),
+            "[REDACTED:1] [REDACTED:2]"
+        );
+        assert_eq!(map.redaction_index_count(), 2);
+        assert_eq!(map.redaction_label(1), Some("primary"));
+        assert_eq!(map.redaction_label(2), Some("other"));
+
+        // Added secrets keep the style and take the next index
+        let mut extra = HashMap::new();
+        extra.insert("DUMMY_EXTRA".to_string(), "extra_real_777".to_string());
+        let map = map.with_additional_secrets(extra).unwrap();
+        assert_eq!(
+            map.sanitize("extra_real_777 shared_real_555"),
+            "[REDACTED:3] [REDACTED:1]"
+        );
+        assert_eq!(map.redaction_label(3), Some("DUMMY_EXTRA"));
+    }
+
+    #[test]
+    fn test_labeled_redaction_names_strategy() {
+        use crate::strategy::BearerStrategy;
+
+        std::env::set_var("TEST_LABELED_OPENAI_KEY", "sk-labeled-openai-1");
+        std::env::set_var("TEST_LABELED_GITHUB_TOKEN", "ghp_labeled_github_2");
+
+        let strategy = |name: &str, env: &str, dummy: &str| -> Box<dyn AuthStrategy> {
+            Box::new(
+                BearerStrategy::new(name.to_string(), env.to_string(), dummy.to_string(), vec![])
+                    .unwrap(),
+            )
+        };
+        let strategies = vec![
+            strategy("openai", "TEST_LABELED_OPENAI_KEY", "DUMMY_LABELED_OPENAI"),
+            strategy(
+                "github",
+                "TEST_LABELED_GITHUB_TOKEN",
+                "DUMMY_LABELED_GITHUB",
+            ),
+        ];
+
+        let map = SecretMap::from_strategies(&strategies)
+            .unwrap()
+            .with_redaction_style(RedactionStyle::Labeled);
+        let body = "openai=sk-labeled-openai-1 github=ghp_labeled_github_2";
+        assert_eq!(
+            map.sanitize(body),
+            "openai=[REDACTED:openai] github=[REDACTED:github]"
+        );
+        assert_eq!(
+            map.sanitize_bytes(body.as_bytes()).as_ref(),
+            b"openai=[REDACTED:openai] github=[REDACTED:github]"
+        );
+    }
+
+    #[test]
+    fn test_labeled_redaction_never_exposes_dummy() {
+        // Without strategies the labels are dummies, so indices are used
+        let map = create_test_map().with_redaction_style(RedactionStyle::Labeled);
+        assert_eq!(
+            map.sanitize("AKIA_AWSKEY789 sk-realkey456"),
+            "[REDACTED:1] [REDACTED:3]"
+        );
+        assert_eq!(map.inject(&map.sanitize("sk-realkey456")), "[REDACTED:3]");
+    }
+
+    #[test]
+    fn test_partial_suffix_redaction() {
+        let map = create_test_map().with_redaction_mode(RedactionMode::PartialSuffix(4));
+
+        assert_eq!(map.sanitize("key=sk-realkey456"), "key=[REDACTED]y456");
+        assert_eq!(
+            map.sanitize_bytes(b"\xffghp_realtoken123").as_ref(),
+            b"\xff[REDACTED]n123"
+        );
+        assert_eq!(
+            map.count_secrets(map.sanitize("sk-realkey456").as_bytes()),
+            0
+        );
+
+        // Kept across rebuilds, and combined with the redaction style
+        let map = map
+            .with_redaction_style(RedactionStyle::Indexed)
+            .to_sanitize_only()
+            .unwrap();
+        assert_eq!(map.sanitize("sk-realkey456"), "[REDACTED:3]y456");
+    }
+
+    #[test]
+    fn test_partial_suffix_clamped_for_short_secrets() {
+        let mut secrets = HashMap::new();
+        secrets.insert("DUMMY_SHORT".to_string(), "abc".to_string());
+        secrets.insert("DUMMY_TINY".to_string(), "z".to_string());
+        let map = SecretMap::new(secrets)
+            .unwrap()
+            .with_redaction_mode(RedactionMode::PartialSuffix(8));
+
+        // At most half of a secret shorter than the suffix is kept
+        assert_eq!(map.sanitize("<abc>"), "<[REDACTED]c>");
+        assert_eq!(map.sanitize("<z>"), "<[REDACTED]>");
+
+        // A suffix starting inside a character drops that character
+        assert_eq!(RedactionMode::PartialSuffix(1).suffix("abcdé"), "");
+        assert_eq!(RedactionMode::Full.suffix("sk-realkey456"), "");
+    }
+
+    #[test]
+    fn test_sanitize_json_redacts_values_not_keys() {
+        let map = create_test_map();
+        let body = br#"{"sk-realkey456": "sk-realkey456", "list": ["a ghp_realtoken123", 7]}"#;
+
+        let sanitized = map.sanitize_json(body).unwrap();
+
+        assert_eq!(
+            String::from_utf8(sanitized).unwrap(),
+            r#"{"sk-realkey456":"[REDACTED]","list":["a [REDACTED]",7]}"#
+        );
+    }
+
+    #[test]
+    fn test_sanitize_json_sees_through_escapes() {
+        let map = create_test_map();
+
+        // `\u002d` is `-`: byte matching misses this spelling of the secret
+        let sanitized = map
+            .sanitize_json(br#"{"key":"sk\u002drealkey456"}"#)
+            .unwrap();
+        assert_eq!(sanitized, br#"{"key":"[REDACTED]"}"#);
+
+        // Untouched documents keep their formatting, and non-JSON is refused
+        let body = b"{ \"ok\" : true }";
+        assert_eq!(map.sanitize_json(body).unwrap(), body);
+        assert!(map.sanitize_json(b"not json sk-realkey456").is_none());
+    }
+
+    fn oversized_headers() -> HeaderMap {
+        let value = format!("{}ghp_realtoken123{}", "x".repeat(20), "y".repeat(100));
+        let mut headers = HeaderMap::new();
+        headers.insert("x-huge", HeaderValue::from_str(&value).unwrap());
+        headers.insert("content-type", HeaderValue::from_static("text/plain"));
+        headers
+    }
+
+    #[test]
+    fn test_oversized_header_truncated_before_secret() {
+        let map = create_test_map();
+        let limit = HeaderValueLimit {
+            max_len: 40,
+            action: OversizedHeaderAction::Truncate,
+        };
+        let truncated = metrics::OVERSIZED_HEADER_TOTAL.with_label_values(&["truncated"]);
+        let before = truncated.get();
+
+        let sanitized = map
+            .sanitize_headers_with_limit(&oversized_headers(), &limit)
+            .unwrap();
+
+        // The cut lands inside the secret, so its partial start is dropped too
+        let huge = sanitized.get("x-huge").unwrap().to_str().unwrap();
+        assert_eq!(
+            huge,
+            format!("{}{}", "x".repeat(20), TRUNCATED_HEADER_MARKER)
+        );
+        assert!(huge.len() <= limit.max_len);
+        assert_eq!(sanitized.get("content-type").unwrap(), "text/plain");
+        assert!(truncated.get() > before);
+    }
+
+    #[test]
+    fn test_oversized_header_rejected() {
+        let map = create_test_map();
+        let limit = HeaderValueLimit {
+            max_len: 40,
+            action: OversizedHeaderAction::Reject,
+        };
+        let rejected = metrics::OVERSIZED_HEADER_TOTAL.with_label_values(&["rejected"]);
+        let before = rejected.get();
+
+        let err = map
+            .sanitize_headers_with_limit(&oversized_headers(), &limit)
+            .unwrap_err();
+
+        assert_eq!(
+            err,
+            OversizedHeader {
+                name: "x-huge".to_string(),
+                len: 136,
+                limit: 40,
+            }
+        );
+        assert!(rejected.get() > before);
+    }
+
+    #[test]
+    fn test_default_header_limit_keeps_large_cookies() {
+        let map = create_test_map();
+        let cookie = format!("session={}", "a".repeat(4096));
+        let mut headers = HeaderMap::new();
+        headers.insert("set-cookie", HeaderValue::from_str(&cookie).unwrap());
+
+        let sanitized = map.sanitize_headers(&headers);
+
+        assert_eq!(sanitized.get("set-cookie").unwrap(), cookie.as_str());
+        assert_eq!(
+            OversizedHeaderAction::parse("Reject"),
+            Some(OversizedHeaderAction::Reject)
+        );
+        assert_eq!(OversizedHeaderAction::parse("drop"), None);
+    }
+
+    #[test]
+    fn test_runtime_secrets_burst_effective_immediately() {
+        let mut runtime = RuntimeSecrets::new(Duration::from_secs(3600));
+
+        for i in 0..40 {
+            runtime.add_secret(format!("DUMMY_RT_{:02}", i), format!("rt_real_{:02}", i));
+
+            // Every secret added so far is live, before any rebuild
+            for j in 0..=i {
+                assert_eq!(
+                    runtime.inject(&format!("k=DUMMY_RT_{:02}", j)),
+                    format!("k=rt_real_{:02}", j)
+                );
+                assert_eq!(
+                    runtime.sanitize(&format!("k=rt_real_{:02}", j)),
+                    "k=[REDACTED]"
+                );
+            }
+        }
+
+        assert_eq!(runtime.rebuild_count(), 0);
+        assert!(!runtime.rebuild_due());
+        assert_eq!(runtime.count_secrets(b"rt_real_00 rt_real_39"), 2);
+    }
+
+    #[test]
+    fn test_runtime_secrets_burst_coalesces_rebuilds() {
+        let mut runtime = RuntimeSecrets::new(Duration::from_secs(3600));
+        let burst = 3 * MAX_PENDING_RUNTIME_SECRETS;
+
+        for i in 0..burst {
+            runtime.add_secret(format!("DUMMY_RT_{}", i), format!("rt_real_{}", i));
+        }
+
+        // Only the pending cap forced rebuilds, not every addition
+        assert_eq!(runtime.rebuild_count(), 3);
+        assert_eq!(runtime.len(), burst);
+
+        let mut quiet = RuntimeSecrets::new(Duration::ZERO);
+        quiet.add_secret("DUMMY_A".to_string(), "real_a".to_string());
+        quiet.add_secret("DUMMY_B".to_string(), "real_b".to_string());
+        assert!(quiet.rebuild_due());
+        quiet.rebuild();
+        assert_eq!(quiet.rebuild_count(), 1);
+        assert_eq!(quiet.inject("DUMMY_A DUMMY_B"), "real_a real_b");
+    }
+
+    #[test]
+    fn test_runtime_secrets_changes_before_rebuild() {
+        let mut runtime = RuntimeSecrets::new(Duration::from_secs(3600));
+        runtime.add_secret("DUMMY_A".to_string(), "real_a".to_string());
+        runtime.add_secret("DUMMY_B".to_string(), "real_b".to_string());
+        runtime.rebuild();
+
+        // Rotation: the new value wins for injection at once
+        runtime.add_secret("DUMMY_A".to_string(), "real_a2".to_string());
+        assert_eq!(runtime.inject("DUMMY_A"), "real_a2");
+
+        // Removal: the dummy is no longer injected, other secrets still are
+        assert!(runtime.remove_secret("DUMMY_B"));
+        assert_eq!(runtime.inject("DUMMY_B DUMMY_A"), "DUMMY_B real_a2");
+        assert!(!runtime.remove_secret("DUMMY_B"));
+
+        runtime.rebuild();
+        assert_eq!(runtime.inject("DUMMY_A DUMMY_B"), "real_a2 DUMMY_B");
+        assert_eq!(
+            runtime.sanitize("real_a real_a2 real_b"),
+            "real_a [REDACTED] real_b"
+        );
+    }
+
+    #[test]
+    fn test_secret_bytes() {
+        let map = create_test_map();
+        assert_eq!(map.secret_bytes(), 16 + 13 + 14);
+        assert_eq!(map.check_secret_bytes(Some(43)), Ok(43));
+        assert!(map
+            .check_secret_bytes(Some(42))
+            .unwrap_err()
+            .contains("43 bytes"));
+
+        let mut runtime = RuntimeSecrets::default();
+        runtime.add_secret("DUMMY_A".to_string(), "real_a".to_string());
+        assert_eq!(runtime.secret_bytes(), 6);
+
+        // A rotated value replaces the old one; empty additions are ignored
+        let additions = HashMap::from([
+            ("DUMMY_A".to_string(), "real_a_rotated".to_string()),
+            ("DUMMY_B".to_string(), "real_b".to_string()),
+            ("DUMMY_C".to_string(), String::new()),
+        ]);
+        assert_eq!(runtime.secret_bytes_after(&additions), 14 + 6);
+    }
+
+    fn redact_html(data: &str, secrets: &[&str]) -> Option<String> {
+        let secrets: Vec<Vec<u8>> = secrets.iter().map(|s| s.as_bytes().to_vec()).collect();
+        redact_html_entity_secrets(data.as_bytes(), &secrets, |_| b"[REDACTED]".to_vec())
+            .map(|out| String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn test_redact_html_entity_secrets() {
+        assert_eq!(
+            redact_html("<p>key: sk&#45;real&#x2d;123</p>", &["sk-real-123"]).as_deref(),
+            Some("<p>key: [REDACTED]</p>")
+        );
+        assert_eq!(
+            redact_html("<b>a&amp;b&quot;c</b> and a&amp;b&quot;c", &["a&b\"c"]).as_deref(),
+            Some("<b>[REDACTED]</b> and [REDACTED]")
+        );
+    }
+
+    #[test]
+    fn test_redact_html_entity_secrets_leaves_other_entities() {
+        assert_eq!(
+            redact_html("&lt;p&gt; sk&#45;other &amp; &bogus; &#xZZ;", &["sk-real"]),
+            None
+        );
+        assert_eq!(redact_html("no references here", &["sk-real"]), None);
+        assert_eq!(
+            redact_html("&lt;sk&#45;real&gt;", &["sk-real"]).as_deref(),
+            Some("&lt;[REDACTED]&gt;")
+        );
     }
 }
@@ -0,0 +1,94 @@
+// SLAPENIR Socket Tuning
+// Applies configured TCP options to client and upstream connections
+
+use crate::config::NetworkConfig;
+use socket2::{SockRef, TcpKeepalive};
+use std::time::Duration;
+use tokio::net::TcpStream;
+
+/// Apply TCP socket options to an accepted or upstream connection
+pub fn apply_socket_options(stream: &TcpStream, options: &NetworkConfig) -> std::io::Result<()> {
+    let socket = SockRef::from(stream);
+
+    socket.set_tcp_nodelay(options.tcp_nodelay)?;
+
+    if let Some(size) = options.so_sndbuf {
+        socket.set_send_buffer_size(size)?;
+    }
+
+    if let Some(size) = options.so_rcvbuf {
+        socket.set_recv_buffer_size(size)?;
+    }
+
+    if let Some(secs) = options.tcp_keepalive {
+        let keepalive = TcpKeepalive::new().with_time(Duration::from_secs(secs));
+        socket.set_tcp_keepalive(&keepalive)?;
+    }
+
+    Ok(())
+}
+
+/// Apply socket options, logging instead of failing the connection
+pub fn tune_socket(stream: &TcpStream, options: &NetworkConfig) {
+    if let Err(e) = apply_socket_options(stream, options) {
+        tracing::warn!("Failed to apply TCP socket options: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    async fn connected_stream() -> TcpStream {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let _ = listener.accept().await;
+        });
+        TcpStream::connect(addr).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_apply_default_options_sets_nodelay() {
+        let stream = connected_stream().await;
+        apply_socket_options(&stream, &NetworkConfig::default()).unwrap();
+
+        assert!(stream.nodelay().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_apply_nodelay_disabled() {
+        let stream = connected_stream().await;
+        let options = NetworkConfig {
+            tcp_nodelay: false,
+            ..Default::default()
+        };
+        apply_socket_options(&stream, &options).unwrap();
+
+        assert!(!stream.nodelay().unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_apply_buffers_and_keepalive() {
+        let stream = connected_stream().await;
+        let options = NetworkConfig {
+            tcp_nodelay: true,
+            so_sndbuf: Some(64 * 1024),
+            so_rcvbuf: Some(64 * 1024),
+            tcp_keepalive: Some(30),
+        };
+        apply_socket_options(&stream, &options).unwrap();
+
+        let socket = SockRef::from(&stream);
+        // The kernel may round buffer sizes up (Linux doubles them)
+        assert!(socket.send_buffer_size().unwrap() >= 64 * 1024);
+        assert!(socket.recv_buffer_size().unwrap() >= 64 * 1024);
+        assert!(socket.keepalive().unwrap());
+        #[cfg(target_os = "linux")]
+        assert_eq!(
+            socket.tcp_keepalive_time().unwrap(),
+            Duration::from_secs(30)
+        );
+    }
+}
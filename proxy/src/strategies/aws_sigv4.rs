@@ -1,12 +1,21 @@
 // AWS Signature Version 4 Strategy
 // Implements AWS request signing for all AWS services
 
-use crate::strategy::{AuthStrategy, StrategyError};
+use crate::strategy::{header_contains, matches_wildcard, AuthStrategy, StrategyError};
 use aws_credential_types::Credentials;
-use aws_sigv4::http_request::{sign, SignableBody, SignableRequest, SigningSettings};
+use aws_sigv4::http_request::{
+    sign, PercentEncodingMode, SignableBody, SignableRequest, SignatureLocation, SigningSettings,
+    UriPathNormalizationMode,
+};
 use aws_sigv4::sign::v4;
-use axum::http::HeaderMap;
-use std::time::SystemTime;
+use axum::http::{HeaderMap, Uri};
+use std::time::{Duration, SystemTime};
+
+/// Longest validity SigV4 allows for a presigned URL (7 days)
+pub const MAX_PRESIGN_EXPIRY: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Dummy access keys recognised when no pattern is configured
+const DEFAULT_DUMMY_PATTERNS: [&str; 2] = ["AKIADUMMY", "AKIA00000000DUMMY"];
 
 /// AWS SigV4 authentication strategy
 ///
@@ -23,6 +32,8 @@ pub struct AWSSigV4Strategy {
     region: String,
     service: String,
     allowed_hosts: Vec<String>,
+    dummy_patterns: Vec<String>,
+    priority: i32,
 }
 
 impl AWSSigV4Strategy {
@@ -60,9 +71,29 @@ impl AWSSigV4Strategy {
             region,
             service,
             allowed_hosts,
+            dummy_patterns: DEFAULT_DUMMY_PATTERNS
+                .iter()
+                .map(|p| p.to_string())
+                .collect(),
+            priority: 0,
         })
     }
 
+    /// Replace the default dummy access keys with a custom placeholder
+    ///
+    /// Lets each AWS strategy use its own dummy so placeholders don't
+    /// collide when several AWS accounts are configured.
+    pub fn with_dummy_pattern(mut self, dummy_pattern: String) -> Self {
+        self.dummy_patterns = vec![dummy_pattern];
+        self
+    }
+
+    /// Set the precedence over strategies with overlapping dummy patterns
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
     /// Extract AWS service from hostname
     /// Examples:
     /// - s3.amazonaws.com -> s3
@@ -100,24 +131,8 @@ impl AWSSigV4Strategy {
         None
     }
 
-    /// Check if host matches wildcard pattern
-    fn matches_wildcard(pattern: &str, host: &str) -> bool {
-        if let Some(base) = pattern.strip_prefix("*.") {
-            host.ends_with(base) || host == base
-        } else {
-            pattern == host
-        }
-    }
-
-    /// Sign an HTTP request using AWS Signature Version 4
-    fn sign_request(
-        &self,
-        method: &str,
-        uri: &str,
-        headers: &HeaderMap,
-        body: &str,
-        host: &str,
-    ) -> Result<(String, Vec<(String, String)>), StrategyError> {
+    /// Credentials to sign with, failing if either key is not loaded
+    fn credentials(&self) -> Result<Credentials, StrategyError> {
         let access_key = self
             .access_key
             .as_ref()
@@ -127,7 +142,17 @@ impl AWSSigV4Strategy {
             .as_ref()
             .ok_or_else(|| StrategyError::EnvVarNotFound("AWS_SECRET_ACCESS_KEY".to_string()))?;
 
-        // Determine service and region from host if possible
+        Ok(Credentials::new(
+            access_key,
+            secret_key,
+            self.session_token.clone(),
+            None,
+            "slapenir-proxy",
+        ))
+    }
+
+    /// Service and region to sign for, preferring those named by the host
+    fn service_and_region(&self, host: &str) -> (String, String) {
         let service = if self.service == "execute-api" {
             Self::extract_service_from_host(host)
         } else {
@@ -136,18 +161,21 @@ impl AWSSigV4Strategy {
 
         let region = Self::extract_region_from_host(host).unwrap_or_else(|| self.region.clone());
 
-        // Create AWS credentials
-        let credentials = if let Some(token) = &self.session_token {
-            Credentials::new(
-                access_key,
-                secret_key,
-                Some(token.to_string()),
-                None,
-                "slapenir-proxy",
-            )
-        } else {
-            Credentials::new(access_key, secret_key, None, None, "slapenir-proxy")
-        };
+        (service, region)
+    }
+
+    /// Sign an HTTP request using AWS Signature Version 4
+    fn sign_request(
+        &self,
+        method: &str,
+        uri: &str,
+        headers: &HeaderMap,
+        body: &str,
+        host: &str,
+        time: SystemTime,
+    ) -> Result<(String, Vec<(String, String)>), StrategyError> {
+        let credentials = self.credentials()?;
+        let (service, region) = self.service_and_region(host);
 
         // Prepare signing parameters
         let identity = credentials.into();
@@ -156,7 +184,7 @@ impl AWSSigV4Strategy {
             .identity(&identity)
             .region(&region)
             .name(&service)
-            .time(SystemTime::now())
+            .time(time)
             .settings(signing_settings)
             .build()
             .map_err(|e| {
@@ -207,58 +235,154 @@ impl AWSSigV4Strategy {
 
         Ok((body.to_string(), new_headers))
     }
-}
-
-impl AuthStrategy for AWSSigV4Strategy {
-    fn name(&self) -> &str {
-        &self.name
-    }
 
-    fn strategy_type(&self) -> &str {
-        "aws_sigv4"
+    /// Presign `url` for `method`, valid for `expires_in`
+    ///
+    /// Returns the URL with the `X-Amz-*` query parameters added, for
+    /// clients (e.g. S3 GET/PUT) that need a URL rather than signed headers.
+    /// The payload is left unsigned, as S3 expects for presigned requests.
+    pub fn presign(
+        &self,
+        method: &str,
+        url: &str,
+        expires_in: Duration,
+    ) -> Result<String, StrategyError> {
+        self.presign_at(method, url, expires_in, SystemTime::now())
     }
 
-    fn detect(&self, headers: &HeaderMap, body: &str) -> bool {
-        // Check for dummy AWS access key patterns in Authorization header
-        if let Some(auth_header) = headers.get("authorization") {
-            if let Ok(auth_str) = auth_header.to_str() {
-                // Look for AWS access key patterns (AKIA followed by 16 chars)
-                if auth_str.contains("AKIA") && auth_str.contains("DUMMY") {
-                    return true;
-                }
-            }
+    /// Presign `url` as of `time`
+    fn presign_at(
+        &self,
+        method: &str,
+        url: &str,
+        expires_in: Duration,
+        time: SystemTime,
+    ) -> Result<String, StrategyError> {
+        if expires_in.is_zero() || expires_in > MAX_PRESIGN_EXPIRY {
+            return Err(StrategyError::InvalidCredential(format!(
+                "Presigned URL expiry must be between 1s and {}s",
+                MAX_PRESIGN_EXPIRY.as_secs()
+            )));
+        }
+        let uri: Uri = url
+            .parse()
+            .map_err(|e| StrategyError::InjectionFailed(format!("Invalid URL: {}", e)))?;
+        let host = uri
+            .host()
+            .ok_or_else(|| StrategyError::InjectionFailed("URL has no host".to_string()))?;
+        // A presigned URL carries the credential's signature wherever it is sent
+        if !self.validate_host(host) {
+            return Err(StrategyError::InjectionFailed(format!(
+                "Host '{}' is not allowed for AWS SigV4 strategy '{}'",
+                host, self.name
+            )));
         }
 
-        // Check for dummy access keys in body
-        if body.contains("AKIA") && body.contains("DUMMY") {
-            return true;
+        let credentials = self.credentials()?;
+        let (service, region) = self.service_and_region(host);
+
+        let mut signing_settings = SigningSettings::default();
+        signing_settings.signature_location = SignatureLocation::QueryParams;
+        signing_settings.expires_in = Some(expires_in);
+        if service == "s3" {
+            // S3 keys are signed exactly as written
+            signing_settings.percent_encoding_mode = PercentEncodingMode::Single;
+            signing_settings.uri_path_normalization_mode = UriPathNormalizationMode::Disabled;
         }
 
-        false
-    }
+        let identity = credentials.into();
+        let signing_params = v4::SigningParams::builder()
+            .identity(&identity)
+            .region(&region)
+            .name(&service)
+            .time(time)
+            .settings(signing_settings)
+            .build()
+            .map_err(|e| {
+                StrategyError::InjectionFailed(format!("Failed to build signing params: {}", e))
+            })?;
 
-    fn inject(&self, body: &str, headers: &mut HeaderMap) -> Result<String, StrategyError> {
-        // For AWS SigV4, we need to sign the entire request
-        // This is more complex than simple token replacement
+        let signable_request = SignableRequest::new(
+            method,
+            url,
+            std::iter::empty(),
+            SignableBody::UnsignedPayload,
+        )
+        .map_err(|e| {
+            StrategyError::InjectionFailed(format!("Failed to create signable request: {}", e))
+        })?;
 
-        // Extract request details
-        let method = headers
-            .get("method")
-            .and_then(|v| v.to_str().ok())
-            .unwrap_or("POST");
+        let (signing_instructions, _signature) = sign(signable_request, &signing_params.into())
+            .map_err(|e| StrategyError::InjectionFailed(format!("Failed to sign request: {}", e)))?
+            .into_parts();
 
-        let uri = headers
-            .get("uri")
-            .and_then(|v| v.to_str().ok())
-            .unwrap_or("/");
+        let params: Vec<String> = signing_instructions
+            .params()
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, Self::encode_query_value(value)))
+            .collect();
+        let separator = if uri.query().is_some() { '&' } else { '?' };
+
+        tracing::debug!(
+            "AWS SigV4 '{}': Presigned {} URL for service={}, region={} ({}s)",
+            self.name,
+            method,
+            service,
+            region,
+            expires_in.as_secs()
+        );
+
+        Ok(format!(
+            "{}{}{}",
+            url.split('#').next().unwrap_or(url),
+            separator,
+            params.join("&")
+        ))
+    }
+
+    /// Percent-encode a query value, keeping only RFC 3986 unreserved characters
+    fn encode_query_value(value: &str) -> String {
+        let mut encoded = String::with_capacity(value.len());
+        for byte in value.bytes() {
+            if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+                encoded.push(byte as char);
+            } else {
+                encoded.push_str(&format!("%{:02X}", byte));
+            }
+        }
+        encoded
+    }
 
+    /// Inject real credentials and sign the resulting request at `time`
+    fn inject_at(
+        &self,
+        method: &str,
+        uri: &str,
+        body: &str,
+        headers: &mut HeaderMap,
+        time: SystemTime,
+    ) -> Result<String, StrategyError> {
         let host = headers
             .get("host")
             .and_then(|v| v.to_str().ok())
+            .map(str::to_string)
+            .or_else(|| {
+                uri.parse::<Uri>()
+                    .ok()
+                    .and_then(|u| u.host().map(str::to_string))
+            })
             .ok_or_else(|| StrategyError::InjectionFailed("Missing host header".to_string()))?;
 
-        // Sign the request
-        let (signed_body, signed_headers) = self.sign_request(method, uri, headers, body, host)?;
+        // Replace dummies first so the signature covers the post-injection body
+        let mut injected_body = body.to_string();
+        if let Some(access_key) = &self.access_key {
+            for pattern in &self.dummy_patterns {
+                injected_body = injected_body.replace(pattern.as_str(), access_key);
+            }
+        }
+
+        let (signed_body, signed_headers) =
+            self.sign_request(method, uri, headers, &injected_body, &host, time)?;
 
         // Update headers with signed values
         for (name, value) in signed_headers {
@@ -278,6 +402,54 @@ impl AuthStrategy for AWSSigV4Strategy {
 
         Ok(signed_body)
     }
+}
+
+impl AuthStrategy for AWSSigV4Strategy {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn strategy_type(&self) -> &str {
+        "aws_sigv4"
+    }
+
+    fn detect(&self, headers: &HeaderMap, body: &str) -> bool {
+        // Check for dummy AWS access keys in Authorization header
+        if self
+            .dummy_patterns
+            .iter()
+            .any(|p| header_contains(headers, "authorization", p))
+        {
+            return true;
+        }
+
+        // Check for dummy access keys in body
+        self.dummy_patterns.iter().any(|p| body.contains(p))
+    }
+
+    /// Signing needs the request method and URI; see `inject_request`
+    fn inject(&self, _body: &str, _headers: &mut HeaderMap) -> Result<String, StrategyError> {
+        Err(StrategyError::InjectionFailed(
+            "AWS SigV4 signs the request method and URI; use inject_request".to_string(),
+        ))
+    }
+
+    /// Sign the request with the real credentials
+    ///
+    /// The signature covers the method, URI and the body exactly as it will
+    /// be sent: dummy access keys are replaced first, and signing happens
+    /// over that final body. Callers must run this after every other
+    /// injection step, since any later change to the request invalidates
+    /// the signature.
+    fn inject_request(
+        &self,
+        method: &str,
+        uri: &str,
+        body: &str,
+        headers: &mut HeaderMap,
+    ) -> Result<String, StrategyError> {
+        self.inject_at(method, uri, body, headers, SystemTime::now())
+    }
 
     fn validate_host(&self, host: &str) -> bool {
         if self.allowed_hosts.is_empty() {
@@ -289,7 +461,7 @@ impl AuthStrategy for AWSSigV4Strategy {
         }
 
         for pattern in &self.allowed_hosts {
-            if Self::matches_wildcard(pattern, host) {
+            if matches_wildcard(pattern, host) {
                 return true;
             }
         }
@@ -304,12 +476,24 @@ impl AuthStrategy for AWSSigV4Strategy {
     }
 
     fn dummy_patterns(&self) -> Vec<String> {
-        vec!["AKIADUMMY".to_string(), "AKIA00000000DUMMY".to_string()]
+        self.dummy_patterns.clone()
+    }
+
+    fn allowed_hosts(&self) -> Vec<String> {
+        self.allowed_hosts.clone()
     }
 
     fn real_credential(&self) -> Option<String> {
         self.access_key.clone()
     }
+
+    fn signs_request(&self) -> bool {
+        true
+    }
+
+    fn priority(&self) -> i32 {
+        self.priority
+    }
 }
 
 #[cfg(test)]
@@ -345,22 +529,6 @@ mod tests {
         );
     }
 
-    #[test]
-    fn test_matches_wildcard() {
-        assert!(AWSSigV4Strategy::matches_wildcard(
-            "*.amazonaws.com",
-            "s3.amazonaws.com"
-        ));
-        assert!(AWSSigV4Strategy::matches_wildcard(
-            "*.amazonaws.com",
-            "amazonaws.com"
-        ));
-        assert!(!AWSSigV4Strategy::matches_wildcard(
-            "*.amazonaws.com",
-            "evil.com"
-        ));
-    }
-
     #[test]
     fn test_aws_strategy_creation() {
         std::env::set_var("TEST_AWS_ACCESS_KEY", "AKIATEST123");
@@ -424,4 +592,303 @@ mod tests {
         assert!(strategy.validate_host("dynamodb.us-east-1.amazonaws.com"));
         assert!(!strategy.validate_host("evil.com"));
     }
+
+    #[test]
+    fn test_aws_strategy_default_dummy_patterns() {
+        let strategy = AWSSigV4Strategy::new(
+            "test".to_string(),
+            "TEST_AWS_ACCESS_KEY_4".to_string(),
+            "TEST_AWS_SECRET_KEY_4".to_string(),
+            "us-east-1".to_string(),
+            None,
+            vec![],
+        )
+        .unwrap();
+
+        assert_eq!(
+            strategy.dummy_patterns(),
+            vec!["AKIADUMMY".to_string(), "AKIA00000000DUMMY".to_string()]
+        );
+        assert!(strategy.detect(&HeaderMap::new(), "key=AKIA00000000DUMMY"));
+    }
+
+    #[test]
+    fn test_aws_strategy_custom_dummy_pattern() {
+        let strategy = AWSSigV4Strategy::new(
+            "test".to_string(),
+            "TEST_AWS_ACCESS_KEY_5".to_string(),
+            "TEST_AWS_SECRET_KEY_5".to_string(),
+            "us-east-1".to_string(),
+            None,
+            vec![],
+        )
+        .unwrap()
+        .with_dummy_pattern("AKIASTAGINGDUMMY".to_string());
+
+        assert_eq!(
+            strategy.dummy_patterns(),
+            vec!["AKIASTAGINGDUMMY".to_string()]
+        );
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "authorization",
+            HeaderValue::from_static("AWS4 AKIASTAGINGDUMMY/20240101"),
+        );
+        assert!(strategy.detect(&headers, ""));
+
+        // The default placeholders belong to other strategies now
+        assert!(!strategy.detect(&HeaderMap::new(), "key=AKIADUMMY"));
+    }
+
+    fn signing_strategy(access_env: &str, secret_env: &str) -> AWSSigV4Strategy {
+        std::env::set_var(access_env, "AKIAREALKEY");
+        std::env::set_var(secret_env, "real-secret");
+
+        AWSSigV4Strategy::new(
+            "test".to_string(),
+            access_env.to_string(),
+            secret_env.to_string(),
+            "us-east-1".to_string(),
+            Some("dynamodb".to_string()),
+            vec![],
+        )
+        .unwrap()
+    }
+
+    fn request_headers() -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "host",
+            HeaderValue::from_static("dynamodb.us-east-1.amazonaws.com"),
+        );
+        headers
+    }
+
+    fn authorization_for(
+        strategy: &AWSSigV4Strategy,
+        body: &str,
+        time: SystemTime,
+    ) -> Option<String> {
+        authorization_for_request(strategy, "POST", "/", body, time)
+    }
+
+    fn authorization_for_request(
+        strategy: &AWSSigV4Strategy,
+        method: &str,
+        uri: &str,
+        body: &str,
+        time: SystemTime,
+    ) -> Option<String> {
+        let headers = request_headers();
+        let (_, signed) = strategy
+            .sign_request(
+                method,
+                uri,
+                &headers,
+                body,
+                "dynamodb.us-east-1.amazonaws.com",
+                time,
+            )
+            .unwrap();
+        signed
+            .into_iter()
+            .find(|(name, _)| name == "authorization")
+            .map(|(_, value)| value)
+    }
+
+    /// Strategy with the service left to be read from the host
+    fn presigning_strategy(access_env: &str, secret_env: &str) -> AWSSigV4Strategy {
+        std::env::set_var(access_env, "AKIAREALKEY");
+        std::env::set_var(secret_env, "real-secret");
+
+        AWSSigV4Strategy::new(
+            "test".to_string(),
+            access_env.to_string(),
+            secret_env.to_string(),
+            "us-east-1".to_string(),
+            None,
+            vec![],
+        )
+        .unwrap()
+    }
+
+    fn query_params(url: &str) -> Vec<(String, String)> {
+        url.split_once('?')
+            .map(|(_, query)| query)
+            .unwrap_or_default()
+            .split('&')
+            .filter_map(|pair| pair.split_once('='))
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_aws_presign_adds_signature_and_expiry() {
+        let strategy = presigning_strategy("TEST_AWS_PRESIGN_ACCESS", "TEST_AWS_PRESIGN_SECRET");
+        let time = SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+
+        let url = strategy
+            .presign_at(
+                "GET",
+                "https://s3.eu-west-1.amazonaws.com/bucket/reports/q3.csv?versionId=7",
+                Duration::from_secs(900),
+                time,
+            )
+            .unwrap();
+
+        assert!(url.starts_with(
+            "https://s3.eu-west-1.amazonaws.com/bucket/reports/q3.csv?versionId=7&X-Amz-"
+        ));
+        let params = query_params(&url);
+        let param = |name: &str| {
+            params
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, v)| v.as_str())
+        };
+        assert_eq!(param("X-Amz-Expires"), Some("900"));
+        assert_eq!(param("X-Amz-Algorithm"), Some("AWS4-HMAC-SHA256"));
+        assert_eq!(param("X-Amz-Date"), Some("20231114T221320Z"));
+        // Service and region come from the host, not the strategy defaults
+        assert_eq!(
+            param("X-Amz-Credential"),
+            Some("AKIAREALKEY%2F20231114%2Feu-west-1%2Fs3%2Faws4_request")
+        );
+        let signature = param("X-Amz-Signature").unwrap();
+        assert_eq!(signature.len(), 64);
+        assert!(signature.bytes().all(|b| b.is_ascii_hexdigit()));
+        assert!(!url.contains("real-secret"));
+    }
+
+    #[test]
+    fn test_aws_presign_rejects_invalid_expiry() {
+        let strategy =
+            presigning_strategy("TEST_AWS_PRESIGN_EXP_ACCESS", "TEST_AWS_PRESIGN_EXP_SECRET");
+        let url = "https://s3.amazonaws.com/bucket/key";
+
+        assert!(strategy.presign("PUT", url, Duration::ZERO).is_err());
+        assert!(strategy
+            .presign("PUT", url, MAX_PRESIGN_EXPIRY + Duration::from_secs(1))
+            .is_err());
+
+        let presigned = strategy.presign("PUT", url, MAX_PRESIGN_EXPIRY).unwrap();
+        assert!(presigned.starts_with("https://s3.amazonaws.com/bucket/key?X-Amz-"));
+        assert!(presigned.contains("X-Amz-Expires=604800"));
+    }
+
+    #[test]
+    fn test_aws_presign_rejects_host_outside_whitelist() {
+        std::env::set_var("TEST_AWS_PRESIGN_HOST_ACCESS", "AKIAREALKEY");
+        std::env::set_var("TEST_AWS_PRESIGN_HOST_SECRET", "real-secret");
+        let strategy = AWSSigV4Strategy::new(
+            "test".to_string(),
+            "TEST_AWS_PRESIGN_HOST_ACCESS".to_string(),
+            "TEST_AWS_PRESIGN_HOST_SECRET".to_string(),
+            "us-east-1".to_string(),
+            None,
+            vec!["*.amazonaws.com".to_string()],
+        )
+        .unwrap();
+        let expires_in = Duration::from_secs(60);
+
+        let err = strategy
+            .presign("GET", "https://evil.com/bucket/key", expires_in)
+            .unwrap_err();
+        assert!(err.to_string().contains("evil.com"));
+        assert!(strategy
+            .presign(
+                "GET",
+                "https://s3.us-east-1.amazonaws.com/bucket/key",
+                expires_in
+            )
+            .is_ok());
+    }
+
+    #[test]
+    fn test_aws_inject_signs_post_injection_body() {
+        let strategy = signing_strategy("TEST_AWS_ACCESS_KEY_6", "TEST_AWS_SECRET_KEY_6");
+        let time = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+        let mut headers = request_headers();
+
+        let body = strategy
+            .inject_at("POST", "/", r#"{"Owner": "AKIADUMMY"}"#, &mut headers, time)
+            .unwrap();
+        assert_eq!(body, r#"{"Owner": "AKIAREALKEY"}"#);
+
+        let authorization = headers.get("authorization").unwrap().to_str().unwrap();
+        assert!(authorization.starts_with("AWS4-HMAC-SHA256 Credential=AKIAREALKEY/"));
+        assert_eq!(
+            Some(authorization.to_string()),
+            authorization_for(&strategy, &body, time)
+        );
+
+        // Signing the pre-injection body (sign-then-modify) gives another signature
+        assert_ne!(
+            Some(authorization.to_string()),
+            authorization_for(&strategy, r#"{"Owner": "AKIADUMMY"}"#, time)
+        );
+    }
+
+    #[test]
+    fn test_aws_inject_after_body_modification() {
+        let strategy = signing_strategy("TEST_AWS_ACCESS_KEY_7", "TEST_AWS_SECRET_KEY_7");
+        let time = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+
+        // An earlier injection step rewrote the body before signing
+        let modified = r#"{"TableName": "orders", "Token": "real-bearer"}"#;
+        let mut headers = request_headers();
+        let body = strategy
+            .inject_at("POST", "/", modified, &mut headers, time)
+            .unwrap();
+
+        assert_eq!(body, modified);
+        assert_eq!(
+            headers
+                .get("authorization")
+                .map(|v| v.to_str().unwrap().to_string()),
+            authorization_for(&strategy, modified, time)
+        );
+        assert_ne!(
+            authorization_for(&strategy, modified, time),
+            authorization_for(&strategy, r#"{"TableName": "orders"}"#, time)
+        );
+    }
+
+    #[test]
+    fn test_aws_signature_covers_method_and_path() {
+        let strategy = signing_strategy("TEST_AWS_ACCESS_KEY_8", "TEST_AWS_SECRET_KEY_8");
+        let time = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+        let mut headers = request_headers();
+        strategy
+            .inject_at("GET", "/tables/orders?limit=5", "", &mut headers, time)
+            .unwrap();
+
+        let authorization = headers.get("authorization").unwrap().to_str().unwrap();
+        assert!(authorization.contains("SignedHeaders=host;x-amz-date"));
+        assert_eq!(
+            Some(authorization.to_string()),
+            authorization_for_request(&strategy, "GET", "/tables/orders?limit=5", "", time)
+        );
+        assert_ne!(
+            Some(authorization.to_string()),
+            authorization_for_request(&strategy, "POST", "/tables/orders?limit=5", "", time)
+        );
+        assert_ne!(
+            Some(authorization.to_string()),
+            authorization_for_request(&strategy, "GET", "/tables/users?limit=5", "", time)
+        );
+    }
+
+    #[test]
+    fn test_aws_inject_without_request_context_fails() {
+        let strategy = signing_strategy("TEST_AWS_ACCESS_KEY_9", "TEST_AWS_SECRET_KEY_9");
+        let mut headers = request_headers();
+
+        assert!(strategy.inject("{}", &mut headers).is_err());
+        assert!(strategy
+            .inject_request("POST", "/", "{}", &mut headers)
+            .is_ok());
+        assert!(strategy.signs_request());
+    }
 }
@@ -0,0 +1,257 @@
+// HTTP Basic Authentication Strategy
+// Injects `Authorization: Basic base64(user:pass)` from separate env vars
+
+use crate::strategy::{
+    header_contains, matches_wildcard, AuthStrategy, InjectTargets, StrategyError,
+};
+use axum::http::{HeaderMap, HeaderValue};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+/// HTTP Basic auth strategy
+///
+/// The agent sends `Authorization: Basic <dummy>`; the dummy is replaced
+/// with the base64 of `username:password`. That encoded value is the
+/// strategy's real credential, so the SecretMap injects and redacts it like
+/// any token; the raw password is redacted from responses as well.
+#[derive(Debug, Clone)]
+pub struct BasicAuthStrategy {
+    name: String,
+    username_env: String,
+    password_env: String,
+    dummy_pattern: String,
+    allowed_hosts: Vec<String>,
+    password: Option<String>,
+    /// `base64(username:password)`, when both are set
+    encoded: Option<String>,
+    priority: i32,
+}
+
+impl BasicAuthStrategy {
+    /// Create a new Basic auth strategy from username and password env vars
+    pub fn new(
+        name: String,
+        username_env: String,
+        password_env: String,
+        dummy_pattern: String,
+        allowed_hosts: Vec<String>,
+    ) -> Result<Self, StrategyError> {
+        let username = std::env::var(&username_env).ok();
+        let password = std::env::var(&password_env).ok();
+
+        if username.is_none() || password.is_none() {
+            tracing::warn!(
+                "Basic auth strategy '{}': Credentials not fully loaded (env vars may be missing)",
+                name
+            );
+        }
+
+        let encoded = match (&username, &password) {
+            (Some(username), Some(password)) => Some(Self::encode(username, password)),
+            _ => None,
+        };
+
+        Ok(Self {
+            name,
+            username_env,
+            password_env,
+            dummy_pattern,
+            allowed_hosts,
+            password,
+            encoded,
+            priority: 0,
+        })
+    }
+
+    /// Set the precedence over strategies with overlapping dummy patterns
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Basic auth credentials: `base64(username:password)`
+    fn encode(username: &str, password: &str) -> String {
+        STANDARD.encode(format!("{}:{}", username, password))
+    }
+}
+
+impl AuthStrategy for BasicAuthStrategy {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn strategy_type(&self) -> &str {
+        "basic"
+    }
+
+    fn detect(&self, headers: &HeaderMap, _body: &str) -> bool {
+        header_contains(headers, "authorization", &self.dummy_pattern)
+    }
+
+    fn inject(&self, body: &str, headers: &mut HeaderMap) -> Result<String, StrategyError> {
+        let encoded = self.encoded.as_ref().ok_or_else(|| {
+            let missing = if self.password.is_none() {
+                &self.password_env
+            } else {
+                &self.username_env
+            };
+            StrategyError::EnvVarNotFound(missing.clone())
+        })?;
+
+        let value = HeaderValue::from_str(&format!("Basic {}", encoded))
+            .map_err(|e| StrategyError::InjectionFailed(format!("Invalid header value: {}", e)))?;
+        headers.insert("authorization", value);
+
+        Ok(body.to_string())
+    }
+
+    fn validate_host(&self, host: &str) -> bool {
+        if self.allowed_hosts.is_empty() {
+            tracing::warn!(
+                "Basic auth strategy '{}': No host whitelist configured (allowing all hosts)",
+                self.name
+            );
+            return true;
+        }
+
+        for pattern in &self.allowed_hosts {
+            if matches_wildcard(pattern, host) {
+                return true;
+            }
+        }
+
+        tracing::warn!(
+            "Basic auth strategy '{}': Host '{}' not in whitelist: {:?}",
+            self.name,
+            host,
+            self.allowed_hosts
+        );
+        false
+    }
+
+    fn dummy_patterns(&self) -> Vec<String> {
+        vec![self.dummy_pattern.clone()]
+    }
+
+    fn allowed_hosts(&self) -> Vec<String> {
+        self.allowed_hosts.clone()
+    }
+
+    fn real_credential(&self) -> Option<String> {
+        self.encoded.clone()
+    }
+
+    fn sanitize_credentials(&self) -> Vec<String> {
+        self.password.iter().cloned().collect()
+    }
+
+    /// Only the Authorization header carries Basic credentials
+    fn inject_targets(&self) -> InjectTargets {
+        InjectTargets::parse(&["authorization".to_string()]).unwrap_or_default()
+    }
+
+    fn priority(&self) -> i32 {
+        self.priority
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sanitizer::SecretMap;
+
+    fn basic_strategy(prefix: &str) -> BasicAuthStrategy {
+        let username_env = format!("{}_USER", prefix);
+        let password_env = format!("{}_PASS", prefix);
+        std::env::set_var(&username_env, "Aladdin");
+        std::env::set_var(&password_env, "open sesame");
+        BasicAuthStrategy::new(
+            "jira".to_string(),
+            username_env,
+            password_env,
+            "DUMMY_BASIC_JIRA".to_string(),
+            vec!["*.atlassian.net".to_string()],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_basic_encoding() {
+        // RFC 7617 example
+        assert_eq!(
+            BasicAuthStrategy::encode("Aladdin", "open sesame"),
+            "QWxhZGRpbjpvcGVuIHNlc2FtZQ=="
+        );
+        assert_eq!(
+            basic_strategy("TEST_BASIC_ENCODE").real_credential(),
+            Some("QWxhZGRpbjpvcGVuIHNlc2FtZQ==".to_string())
+        );
+    }
+
+    #[test]
+    fn test_basic_detect_and_inject() {
+        let strategy = basic_strategy("TEST_BASIC_INJECT");
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "authorization",
+            HeaderValue::from_static("Basic DUMMY_BASIC_JIRA"),
+        );
+        assert!(strategy.detect(&headers, ""));
+        assert!(!strategy.detect(&HeaderMap::new(), "DUMMY_BASIC_JIRA"));
+
+        let body = strategy.inject("{}", &mut headers).unwrap();
+        assert_eq!(body, "{}");
+        assert_eq!(
+            headers["authorization"],
+            "Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ=="
+        );
+    }
+
+    #[test]
+    fn test_basic_validate_host() {
+        let strategy = basic_strategy("TEST_BASIC_HOST");
+
+        assert!(strategy.validate_host("acme.atlassian.net"));
+        assert!(strategy.validate_host("atlassian.net"));
+        assert!(!strategy.validate_host("evil.com"));
+        assert!(!strategy.validate_host("evilatlassian.net"));
+    }
+
+    #[test]
+    fn test_basic_missing_password() {
+        std::env::set_var("TEST_BASIC_MISSING_USER", "Aladdin");
+        let strategy = BasicAuthStrategy::new(
+            "jira".to_string(),
+            "TEST_BASIC_MISSING_USER".to_string(),
+            "TEST_BASIC_MISSING_PASS".to_string(),
+            "DUMMY_BASIC_JIRA".to_string(),
+            vec![],
+        )
+        .unwrap();
+
+        assert_eq!(strategy.real_credential(), None);
+        assert!(matches!(
+            strategy.inject("", &mut HeaderMap::new()),
+            Err(StrategyError::EnvVarNotFound(env)) if env == "TEST_BASIC_MISSING_PASS"
+        ));
+    }
+
+    #[test]
+    fn test_basic_secret_map_injects_header_and_redacts_password() {
+        let strategies: Vec<Box<dyn AuthStrategy>> =
+            vec![Box::new(basic_strategy("TEST_BASIC_SECRET_MAP"))];
+        let map = SecretMap::from_strategies(&strategies).unwrap();
+
+        assert_eq!(
+            map.inject_header("authorization", "Basic DUMMY_BASIC_JIRA"),
+            "Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ=="
+        );
+        // Only the Authorization header, never the body
+        assert_eq!(map.inject("DUMMY_BASIC_JIRA"), "DUMMY_BASIC_JIRA");
+
+        let sanitized = map.sanitize("auth QWxhZGRpbjpvcGVuIHNlc2FtZQ== pass open sesame");
+        assert!(!sanitized.contains("QWxhZGRpbjpvcGVuIHNlc2FtZQ=="));
+        assert!(!sanitized.contains("open sesame"));
+    }
+}
@@ -0,0 +1,366 @@
+// HMAC-SHA256 Request Signing Strategy
+// Signs requests with a shared secret for APIs that authenticate by signature
+
+use crate::strategy::{matches_wildcard, AuthStrategy, InjectTargets, StrategyError};
+use axum::http::{HeaderMap, HeaderName, HeaderValue, Uri};
+use hmac::{Hmac, KeyInit, Mac};
+use sha2::Sha256;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Header carrying the signature when none is configured
+pub const DEFAULT_SIGNATURE_HEADER: &str = "x-signature";
+
+/// Header carrying the signed timestamp (Unix seconds)
+pub const TIMESTAMP_HEADER: &str = "x-timestamp";
+
+/// HMAC-SHA256 signing strategy
+///
+/// The agent marks a request for signing by putting the dummy pattern in any
+/// header (typically the signature header). The proxy then signs
+/// `method \n path \n body \n timestamp` with the real secret and sets the
+/// hex signature and the timestamp headers, dropping the placeholder headers.
+/// The secret itself is never sent.
+#[derive(Debug, Clone)]
+pub struct HmacStrategy {
+    name: String,
+    env_var: String,
+    dummy_pattern: String,
+    signature_header: HeaderName,
+    allowed_hosts: Vec<String>,
+    secret: Option<String>,
+    priority: i32,
+}
+
+impl HmacStrategy {
+    /// Create a new HMAC strategy signing with the secret in `env_var`
+    pub fn new(
+        name: String,
+        env_var: String,
+        dummy_pattern: String,
+        allowed_hosts: Vec<String>,
+    ) -> Result<Self, StrategyError> {
+        let secret = std::env::var(&env_var).ok();
+
+        if secret.is_none() {
+            tracing::warn!(
+                "HMAC strategy '{}': Environment variable '{}' not set",
+                name,
+                env_var
+            );
+        }
+
+        Ok(Self {
+            name,
+            env_var,
+            dummy_pattern,
+            signature_header: HeaderName::from_static(DEFAULT_SIGNATURE_HEADER),
+            allowed_hosts,
+            secret,
+            priority: 0,
+        })
+    }
+
+    /// Send the signature in `header` instead of `X-Signature`
+    pub fn with_signature_header(mut self, header: &str) -> Result<Self, StrategyError> {
+        self.signature_header = header.parse().map_err(|_| {
+            StrategyError::InvalidCredential(format!("Invalid signature header: {}", header))
+        })?;
+        Ok(self)
+    }
+
+    /// Set the precedence over strategies with overlapping dummy patterns
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// String covered by the signature
+    fn canonical_string(method: &str, path: &str, body: &str, timestamp: u64) -> String {
+        format!("{}\n{}\n{}\n{}", method, path, body, timestamp)
+    }
+
+    /// Hex-encoded HMAC-SHA256 of `message` under `secret`
+    fn sign(secret: &str, message: &str) -> String {
+        let mut mac = <Hmac<Sha256> as KeyInit>::new_from_slice(secret.as_bytes())
+            .expect("HMAC accepts keys of any length");
+        mac.update(message.as_bytes());
+        hex::encode(mac.finalize().into_bytes())
+    }
+
+    /// Sign the request at `time`
+    fn inject_at(
+        &self,
+        method: &str,
+        uri: &str,
+        body: &str,
+        headers: &mut HeaderMap,
+        time: SystemTime,
+    ) -> Result<String, StrategyError> {
+        let secret = self
+            .secret
+            .as_ref()
+            .ok_or_else(|| StrategyError::EnvVarNotFound(self.env_var.clone()))?;
+
+        let path = uri
+            .parse::<Uri>()
+            .map(|u| u.path().to_string())
+            .unwrap_or_else(|_| uri.split('?').next().unwrap_or(uri).to_string());
+
+        let timestamp = time
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| StrategyError::InjectionFailed(format!("Invalid clock: {}", e)))?
+            .as_secs();
+
+        // Placeholders must not reach the upstream; the signature replaces them
+        let placeholders: Vec<HeaderName> = headers
+            .iter()
+            .filter(|(_, value)| {
+                value
+                    .to_str()
+                    .is_ok_and(|v| v.contains(&self.dummy_pattern))
+            })
+            .map(|(name, _)| name.clone())
+            .collect();
+        for name in placeholders {
+            headers.remove(name);
+        }
+
+        let canonical = Self::canonical_string(method, &path, body, timestamp);
+        let signature = Self::sign(secret, &canonical);
+
+        headers.insert(
+            self.signature_header.clone(),
+            HeaderValue::from_str(&signature).map_err(|e| {
+                StrategyError::InjectionFailed(format!("Invalid signature header value: {}", e))
+            })?,
+        );
+        headers.insert(TIMESTAMP_HEADER, HeaderValue::from(timestamp));
+
+        tracing::debug!(
+            "HMAC strategy '{}': Signed request (body: {} bytes)",
+            self.name,
+            body.len()
+        );
+
+        Ok(body.to_string())
+    }
+}
+
+impl AuthStrategy for HmacStrategy {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn strategy_type(&self) -> &str {
+        "hmac"
+    }
+
+    fn detect(&self, headers: &HeaderMap, _body: &str) -> bool {
+        if self.dummy_pattern.is_empty() {
+            return false;
+        }
+        // Skip the `uri` pseudo-header used to detect query credentials
+        headers.iter().any(|(name, value)| {
+            name != "uri"
+                && value
+                    .to_str()
+                    .is_ok_and(|v| v.contains(&self.dummy_pattern))
+        })
+    }
+
+    /// Signing needs the request method and URI; see `inject_request`
+    fn inject(&self, _body: &str, _headers: &mut HeaderMap) -> Result<String, StrategyError> {
+        Err(StrategyError::InjectionFailed(
+            "HMAC signs the request method and path; use inject_request".to_string(),
+        ))
+    }
+
+    /// Sign the request with the real secret
+    ///
+    /// The body is signed as-is, so this must run after every other
+    /// injection step; any later change invalidates the signature.
+    fn inject_request(
+        &self,
+        method: &str,
+        uri: &str,
+        body: &str,
+        headers: &mut HeaderMap,
+    ) -> Result<String, StrategyError> {
+        self.inject_at(method, uri, body, headers, SystemTime::now())
+    }
+
+    fn validate_host(&self, host: &str) -> bool {
+        if self.allowed_hosts.is_empty() {
+            tracing::warn!(
+                "HMAC strategy '{}': No host whitelist configured (allowing all hosts)",
+                self.name
+            );
+            return true;
+        }
+
+        for pattern in &self.allowed_hosts {
+            if matches_wildcard(pattern, host) {
+                return true;
+            }
+        }
+
+        tracing::warn!(
+            "HMAC strategy '{}': Host '{}' not in whitelist: {:?}",
+            self.name,
+            host,
+            self.allowed_hosts
+        );
+        false
+    }
+
+    fn dummy_patterns(&self) -> Vec<String> {
+        vec![self.dummy_pattern.clone()]
+    }
+
+    fn allowed_hosts(&self) -> Vec<String> {
+        self.allowed_hosts.clone()
+    }
+
+    fn real_credential(&self) -> Option<String> {
+        self.secret.clone()
+    }
+
+    fn signs_request(&self) -> bool {
+        true
+    }
+
+    /// The secret only keys the signature, so is never substituted for the dummy
+    fn inject_targets(&self) -> InjectTargets {
+        InjectTargets::none()
+    }
+
+    fn priority(&self) -> i32 {
+        self.priority
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    fn signing_strategy(env_var: &str) -> HmacStrategy {
+        std::env::set_var(env_var, "test-hmac-secret");
+        HmacStrategy::new(
+            "orders".to_string(),
+            env_var.to_string(),
+            "DUMMY_HMAC".to_string(),
+            vec!["api.example.com".to_string()],
+        )
+        .unwrap()
+    }
+
+    fn request_headers() -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert("host", HeaderValue::from_static("api.example.com"));
+        headers.insert("x-signature", HeaderValue::from_static("DUMMY_HMAC"));
+        headers
+    }
+
+    #[test]
+    fn test_sign_known_vector() {
+        assert_eq!(
+            HmacStrategy::sign("key", "The quick brown fox jumps over the lazy dog"),
+            "f7bc83f430538424b13298e6aa6fb143ef4d59a14946175997479dbc2d1a3cd8"
+        );
+    }
+
+    #[test]
+    fn test_hmac_inject_sets_signature_and_timestamp() {
+        let strategy = signing_strategy("TEST_HMAC_INJECT_SECRET");
+        let mut headers = request_headers();
+        let time = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+
+        let body = strategy
+            .inject_at(
+                "POST",
+                "/v1/orders?page=2",
+                r#"{"amount":42}"#,
+                &mut headers,
+                time,
+            )
+            .unwrap();
+
+        assert_eq!(body, r#"{"amount":42}"#);
+        // HMAC-SHA256("test-hmac-secret", "POST\n/v1/orders\n{\"amount\":42}\n1700000000")
+        assert_eq!(
+            headers["x-signature"],
+            "e427fdee84b9af7ed574404aacf68e8e79779dbe40080d212c841fd160b37e47"
+        );
+        assert_eq!(headers[TIMESTAMP_HEADER], "1700000000");
+    }
+
+    #[test]
+    fn test_hmac_custom_signature_header() {
+        let strategy = signing_strategy("TEST_HMAC_HEADER_SECRET")
+            .with_signature_header("X-Hub-Signature")
+            .unwrap();
+        let mut headers = request_headers();
+
+        strategy
+            .inject_request("POST", "/v1/orders", r#"{"amount":42}"#, &mut headers)
+            .unwrap();
+
+        assert_eq!(headers["x-hub-signature"].len(), 64);
+        assert!(!headers.contains_key("x-signature"));
+
+        assert!(signing_strategy("TEST_HMAC_HEADER_SECRET")
+            .with_signature_header("not a header")
+            .is_err());
+    }
+
+    #[test]
+    fn test_hmac_detect_and_validate_host() {
+        let strategy = signing_strategy("TEST_HMAC_DETECT_SECRET");
+
+        assert!(strategy.detect(&request_headers(), ""));
+        assert!(!strategy.detect(&HeaderMap::new(), "DUMMY_HMAC"));
+        assert!(strategy.validate_host("api.example.com"));
+        assert!(!strategy.validate_host("evil.com"));
+        assert!(strategy.signs_request());
+        assert_eq!(
+            strategy.real_credential(),
+            Some("test-hmac-secret".to_string())
+        );
+    }
+
+    #[test]
+    fn test_hmac_missing_secret_fails_injection() {
+        let strategy = HmacStrategy::new(
+            "orders".to_string(),
+            "TEST_HMAC_MISSING_SECRET".to_string(),
+            "DUMMY_HMAC".to_string(),
+            vec![],
+        )
+        .unwrap();
+
+        let result = strategy.inject_request("POST", "/", "", &mut request_headers());
+        assert!(matches!(result, Err(StrategyError::EnvVarNotFound(_))));
+    }
+
+    #[test]
+    fn test_hmac_signature_covers_method_and_path() {
+        let strategy = signing_strategy("TEST_HMAC_REQUEST_SECRET");
+        let time = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+        let signature = |method: &str, uri: &str| {
+            let mut headers = request_headers();
+            strategy
+                .inject_at(method, uri, "{}", &mut headers, time)
+                .unwrap();
+            headers["x-signature"].clone()
+        };
+
+        let original = signature("POST", "/v1/orders");
+        assert_eq!(original, signature("POST", "/v1/orders?page=3"));
+        assert_ne!(original, signature("PUT", "/v1/orders"));
+        assert_ne!(original, signature("POST", "/v1/refunds"));
+
+        // Without the request context there is nothing correct to sign
+        assert!(strategy.inject("{}", &mut request_headers()).is_err());
+    }
+}
@@ -2,6 +2,12 @@
 // Organizes authentication strategy implementations
 
 pub mod aws_sigv4;
+pub mod basic_auth;
+pub mod hmac;
+pub mod query_param;
 
 // Re-export strategies for easier imports
 pub use aws_sigv4::AWSSigV4Strategy;
+pub use basic_auth::BasicAuthStrategy;
+pub use hmac::HmacStrategy;
+pub use query_param::QueryParamStrategy;
@@ -0,0 +1,278 @@
+// API Key in Query Parameter Strategy
+// Injects keys for APIs that expect them in the URL (e.g. `?key=...`)
+
+use crate::strategy::{matches_wildcard, AuthStrategy, StrategyError};
+use axum::http::HeaderMap;
+
+/// Query parameter API key strategy
+///
+/// Handles APIs such as Google Maps that authenticate with a query
+/// parameter: the dummy in `?<param>=<dummy>` is replaced with the real,
+/// percent-encoded key. Other parameters are left exactly as sent.
+#[derive(Debug, Clone)]
+pub struct QueryParamStrategy {
+    name: String,
+    env_var: String,
+    param: String,
+    dummy_pattern: String,
+    allowed_hosts: Vec<String>,
+    real_key: Option<String>,
+    priority: i32,
+}
+
+impl QueryParamStrategy {
+    /// Create a new query parameter strategy for `param`
+    pub fn new(
+        name: String,
+        env_var: String,
+        param: String,
+        dummy_pattern: String,
+        allowed_hosts: Vec<String>,
+    ) -> Result<Self, StrategyError> {
+        if param.is_empty() {
+            return Err(StrategyError::InvalidCredential(
+                "Query parameter name cannot be empty".to_string(),
+            ));
+        }
+
+        let real_key = std::env::var(&env_var).ok();
+
+        if real_key.is_none() {
+            tracing::warn!(
+                "Query param strategy '{}': Environment variable '{}' not set",
+                name,
+                env_var
+            );
+        }
+
+        Ok(Self {
+            name,
+            env_var,
+            param,
+            dummy_pattern,
+            allowed_hosts,
+            real_key,
+            priority: 0,
+        })
+    }
+
+    /// Set the precedence over strategies with overlapping dummy patterns
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Whether the query of `uri` carries the dummy in this strategy's parameter
+    fn query_has_dummy(&self, uri: &str) -> bool {
+        let Some((_, query)) = uri.split_once('?') else {
+            return false;
+        };
+        query.split('&').any(|pair| {
+            pair.split_once('=').is_some_and(|(name, value)| {
+                name == self.param && value.contains(&self.dummy_pattern)
+            })
+        })
+    }
+
+    /// Percent-encode everything but RFC 3986 unreserved characters
+    fn encode(value: &str) -> String {
+        let mut encoded = String::with_capacity(value.len());
+        for byte in value.bytes() {
+            if byte.is_ascii_alphanumeric() || matches!(byte, b'-' | b'.' | b'_' | b'~') {
+                encoded.push(byte as char);
+            } else {
+                encoded.push_str(&format!("%{:02X}", byte));
+            }
+        }
+        encoded
+    }
+}
+
+impl AuthStrategy for QueryParamStrategy {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn strategy_type(&self) -> &str {
+        "query_param"
+    }
+
+    /// Looks for the dummy in the `uri` pseudo-header's query string
+    fn detect(&self, headers: &HeaderMap, _body: &str) -> bool {
+        !self.dummy_pattern.is_empty()
+            && headers
+                .get("uri")
+                .and_then(|v| v.to_str().ok())
+                .is_some_and(|uri| self.query_has_dummy(uri))
+    }
+
+    /// The key travels in the URI only; see [`AuthStrategy::inject_uri`]
+    fn inject(&self, body: &str, _headers: &mut HeaderMap) -> Result<String, StrategyError> {
+        Ok(body.to_string())
+    }
+
+    fn inject_uri(&self, uri: &str) -> Result<String, StrategyError> {
+        if !self.query_has_dummy(uri) {
+            return Ok(uri.to_string());
+        }
+        let real_key = self
+            .real_key
+            .as_ref()
+            .ok_or_else(|| StrategyError::EnvVarNotFound(self.env_var.clone()))?;
+        let encoded_key = Self::encode(real_key);
+
+        let (path, query) = uri.split_once('?').unwrap_or((uri, ""));
+        let query: Vec<String> = query
+            .split('&')
+            .map(|pair| match pair.split_once('=') {
+                Some((name, value)) if name == self.param => {
+                    format!(
+                        "{}={}",
+                        name,
+                        value.replace(&self.dummy_pattern, &encoded_key)
+                    )
+                }
+                _ => pair.to_string(),
+            })
+            .collect();
+
+        tracing::debug!(
+            "Query param strategy '{}': Injected key into '{}' parameter",
+            self.name,
+            self.param
+        );
+
+        Ok(format!("{}?{}", path, query.join("&")))
+    }
+
+    fn validate_host(&self, host: &str) -> bool {
+        if self.allowed_hosts.is_empty() {
+            tracing::warn!(
+                "Query param strategy '{}': No host whitelist configured (allowing all hosts)",
+                self.name
+            );
+            return true;
+        }
+
+        for pattern in &self.allowed_hosts {
+            if matches_wildcard(pattern, host) {
+                return true;
+            }
+        }
+
+        tracing::warn!(
+            "Query param strategy '{}': Host '{}' not in whitelist: {:?}",
+            self.name,
+            host,
+            self.allowed_hosts
+        );
+        false
+    }
+
+    fn dummy_patterns(&self) -> Vec<String> {
+        vec![self.dummy_pattern.clone()]
+    }
+
+    fn allowed_hosts(&self) -> Vec<String> {
+        self.allowed_hosts.clone()
+    }
+
+    fn real_credential(&self) -> Option<String> {
+        self.real_key.clone()
+    }
+
+    fn priority(&self) -> i32 {
+        self.priority
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn maps_strategy(env_var: &str, key: &str) -> QueryParamStrategy {
+        std::env::set_var(env_var, key);
+        QueryParamStrategy::new(
+            "maps".to_string(),
+            env_var.to_string(),
+            "key".to_string(),
+            "DUMMY_MAPS".to_string(),
+            vec!["maps.googleapis.com".to_string()],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_query_param_detect() {
+        let strategy = maps_strategy("TEST_QUERY_DETECT_KEY", "AIzaReal");
+
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            "uri",
+            HeaderValue::from_static("/maps/api/geocode/json?address=x&key=DUMMY_MAPS"),
+        );
+        assert!(strategy.detect(&headers, ""));
+
+        // Another parameter carrying the dummy is not the key
+        headers.insert("uri", HeaderValue::from_static("/search?q=DUMMY_MAPS"));
+        assert!(!strategy.detect(&headers, ""));
+        assert!(!strategy.detect(&HeaderMap::new(), "key=DUMMY_MAPS"));
+    }
+
+    #[test]
+    fn test_query_param_inject_keeps_other_params() {
+        let strategy = maps_strategy("TEST_QUERY_INJECT_KEY", "AIzaReal");
+
+        let injected = strategy
+            .inject_uri("/maps/api/geocode/json?address=1600+Amphitheatre&key=DUMMY_MAPS&lang=en")
+            .unwrap();
+        assert_eq!(
+            injected,
+            "/maps/api/geocode/json?address=1600+Amphitheatre&key=AIzaReal&lang=en"
+        );
+
+        let absolute = strategy
+            .inject_uri("https://maps.googleapis.com/api?key=DUMMY_MAPS&q=DUMMY_MAPS")
+            .unwrap();
+        assert_eq!(
+            absolute,
+            "https://maps.googleapis.com/api?key=AIzaReal&q=DUMMY_MAPS"
+        );
+    }
+
+    #[test]
+    fn test_query_param_inject_encodes_key() {
+        let strategy = maps_strategy("TEST_QUERY_ENCODE_KEY", "a+b/c=d&e");
+
+        assert_eq!(
+            strategy.inject_uri("/v1?key=DUMMY_MAPS&x=1").unwrap(),
+            "/v1?key=a%2Bb%2Fc%3Dd%26e&x=1"
+        );
+    }
+
+    #[test]
+    fn test_query_param_uri_without_dummy_unchanged() {
+        let strategy = maps_strategy("TEST_QUERY_UNCHANGED_KEY", "AIzaReal");
+
+        assert_eq!(strategy.inject_uri("/v1/models").unwrap(), "/v1/models");
+        assert_eq!(strategy.inject_uri("/v1?key=abc").unwrap(), "/v1?key=abc");
+    }
+
+    #[test]
+    fn test_query_param_missing_key_fails_injection() {
+        let strategy = QueryParamStrategy::new(
+            "maps".to_string(),
+            "TEST_QUERY_MISSING_KEY".to_string(),
+            "key".to_string(),
+            "DUMMY_MAPS".to_string(),
+            vec![],
+        )
+        .unwrap();
+
+        assert!(matches!(
+            strategy.inject_uri("/v1?key=DUMMY_MAPS"),
+            Err(StrategyError::EnvVarNotFound(_))
+        ));
+    }
+}
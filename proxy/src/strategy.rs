@@ -17,6 +17,95 @@ pub enum StrategyError {
     InjectionFailed(String),
 }
 
+/// Security violations raised while validating detected credentials
+#[derive(Debug, thiserror::Error)]
+pub enum SecurityError {
+    #[error("{credential_type} credential is not allowed for host '{host}'")]
+    HostNotWhitelisted {
+        credential_type: String,
+        host: String,
+        allowed_hosts: Vec<String>,
+    },
+}
+
+/// Where in a request a strategy's credential may be injected
+///
+/// Configured as a list of `body`, `headers` (every header) or specific
+/// header names; an empty list means both body and headers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InjectTargets {
+    body: bool,
+    all_headers: bool,
+    headers: Vec<String>,
+}
+
+impl Default for InjectTargets {
+    fn default() -> Self {
+        Self {
+            body: true,
+            all_headers: true,
+            headers: Vec::new(),
+        }
+    }
+}
+
+impl InjectTargets {
+    /// Parse configured targets, rejecting invalid header names
+    pub fn parse(targets: &[String]) -> Result<Self, StrategyError> {
+        if targets.is_empty() {
+            return Ok(Self::default());
+        }
+
+        let mut parsed = Self::none();
+        for target in targets {
+            let target = target.trim().to_ascii_lowercase();
+            match target.as_str() {
+                "body" => parsed.body = true,
+                "headers" => parsed.all_headers = true,
+                name => {
+                    axum::http::HeaderName::from_bytes(name.as_bytes()).map_err(|_| {
+                        StrategyError::InvalidCredential(format!(
+                            "Invalid inject target '{}'",
+                            target
+                        ))
+                    })?;
+                    parsed.headers.push(target);
+                }
+            }
+        }
+        Ok(parsed)
+    }
+
+    /// Nowhere: for keys that sign requests but are never sent themselves
+    pub fn none() -> Self {
+        Self {
+            body: false,
+            all_headers: false,
+            headers: Vec::new(),
+        }
+    }
+
+    /// Whether the request body may carry the injected credential
+    pub fn body(&self) -> bool {
+        self.body
+    }
+
+    /// Header names listed as targets, lowercased
+    pub fn headers(&self) -> &[String] {
+        &self.headers
+    }
+
+    /// Whether the header `name` may carry the injected credential
+    pub fn header(&self, name: &str) -> bool {
+        self.all_headers || self.headers.iter().any(|h| h.eq_ignore_ascii_case(name))
+    }
+
+    /// Whether every part of the request may carry the credential
+    pub fn is_unrestricted(&self) -> bool {
+        self.body && self.all_headers
+    }
+}
+
 /// Authentication strategy trait
 ///
 /// Each strategy implements a specific authentication protocol:
@@ -42,6 +131,28 @@ pub trait AuthStrategy: Send + Sync + Debug {
     /// Returns the modified body and any header modifications
     fn inject(&self, body: &str, headers: &mut HeaderMap) -> Result<String, StrategyError>;
 
+    /// Inject real credentials into a request whose method and URI are known
+    ///
+    /// Signing strategies cover the method and URI in their signatures and
+    /// implement this; the default ignores both and calls `inject`.
+    fn inject_request(
+        &self,
+        _method: &str,
+        _uri: &str,
+        body: &str,
+        headers: &mut HeaderMap,
+    ) -> Result<String, StrategyError> {
+        self.inject(body, headers)
+    }
+
+    /// Inject real credentials into the request target (path and query)
+    ///
+    /// For APIs taking the key as a query parameter. Strategies that only
+    /// touch the body and headers leave the target unchanged.
+    fn inject_uri(&self, uri: &str) -> Result<String, StrategyError> {
+        Ok(uri.to_string())
+    }
+
     /// Validate destination host is whitelisted
     ///
     /// Prevents credential exfiltration to unauthorized hosts
@@ -52,10 +163,53 @@ pub trait AuthStrategy: Send + Sync + Debug {
     /// Returns patterns that trigger this strategy
     fn dummy_patterns(&self) -> Vec<String>;
 
+    /// Get the host whitelist (empty means all hosts are allowed)
+    fn allowed_hosts(&self) -> Vec<String>;
+
     /// Get real credential value (for sanitization)
     ///
     /// Returns the actual credential that should be sanitized from responses
     fn real_credential(&self) -> Option<String>;
+
+    /// Further real values redacted from responses but never injected
+    ///
+    /// For credentials injected in a derived form, e.g. the password inside
+    /// a Basic auth value, so the raw parts are scrubbed too.
+    fn sanitize_credentials(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// Whether `inject_request` signs the request (e.g. AWS SigV4)
+    ///
+    /// Signing strategies must run after all other injection, because any
+    /// later change to the body or headers invalidates the signature.
+    fn signs_request(&self) -> bool {
+        false
+    }
+
+    /// Whether the credential is only redacted from responses, never injected
+    fn sanitize_only(&self) -> bool {
+        false
+    }
+
+    /// Parts of the request the credential may be injected into
+    fn inject_targets(&self) -> InjectTargets {
+        InjectTargets::default()
+    }
+
+    /// Precedence when strategies' dummy patterns overlap (higher wins)
+    ///
+    /// Strategies of equal priority keep their configured order.
+    fn priority(&self) -> i32 {
+        0
+    }
+}
+
+/// Strategies in the order they are consulted: highest priority first
+pub fn by_priority(strategies: &[Box<dyn AuthStrategy>]) -> Vec<&dyn AuthStrategy> {
+    let mut ordered: Vec<&dyn AuthStrategy> = strategies.iter().map(Box::as_ref).collect();
+    ordered.sort_by_key(|strategy| std::cmp::Reverse(strategy.priority()));
+    ordered
 }
 
 /// Bearer token strategy
@@ -68,6 +222,9 @@ pub struct BearerStrategy {
     dummy_pattern: String,
     allowed_hosts: Vec<String>,
     real_token: Option<String>,
+    sanitize_only: bool,
+    priority: i32,
+    inject_targets: InjectTargets,
 }
 
 impl BearerStrategy {
@@ -95,16 +252,28 @@ impl BearerStrategy {
             dummy_pattern,
             allowed_hosts,
             real_token,
+            sanitize_only: false,
+            priority: 0,
+            inject_targets: InjectTargets::default(),
         })
     }
 
-    /// Check if host matches wildcard pattern
-    fn matches_wildcard(pattern: &str, host: &str) -> bool {
-        if let Some(base) = pattern.strip_prefix("*.") {
-            host.ends_with(base) || host == base
-        } else {
-            pattern == host
-        }
+    /// Redact the credential from responses without ever injecting it
+    pub fn with_sanitize_only(mut self, sanitize_only: bool) -> Self {
+        self.sanitize_only = sanitize_only;
+        self
+    }
+
+    /// Set the precedence over strategies with overlapping dummy patterns
+    pub fn with_priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Restrict injection to the body, headers, or specific headers
+    pub fn with_inject_targets(mut self, inject_targets: InjectTargets) -> Self {
+        self.inject_targets = inject_targets;
+        self
     }
 }
 
@@ -118,22 +287,15 @@ impl AuthStrategy for BearerStrategy {
     }
 
     fn detect(&self, headers: &HeaderMap, body: &str) -> bool {
-        // Check Authorization header
-        if let Some(auth_header) = headers.get("authorization") {
-            if let Ok(auth_str) = auth_header.to_str() {
-                if auth_str.contains(&self.dummy_pattern) {
-                    return true;
-                }
-            }
+        if self.sanitize_only {
+            return false;
         }
 
-        // Check X-API-Key header (some APIs use this)
-        if let Some(api_key) = headers.get("x-api-key") {
-            if let Ok(key_str) = api_key.to_str() {
-                if key_str.contains(&self.dummy_pattern) {
-                    return true;
-                }
-            }
+        // Check Authorization and X-API-Key (some APIs use this) headers
+        if header_contains(headers, "authorization", &self.dummy_pattern)
+            || header_contains(headers, "x-api-key", &self.dummy_pattern)
+        {
+            return true;
         }
 
         // Check request body
@@ -147,10 +309,17 @@ impl AuthStrategy for BearerStrategy {
             .ok_or_else(|| StrategyError::EnvVarNotFound(self.env_var.clone()))?;
 
         // Replace dummy token in body
-        let injected_body = body.replace(&self.dummy_pattern, real_token);
+        let injected_body = if self.inject_targets.body() {
+            body.replace(&self.dummy_pattern, real_token)
+        } else {
+            body.to_string()
+        };
 
         // Also update Authorization header if present
-        if let Some(auth_header) = headers.get_mut("authorization") {
+        if let Some(auth_header) = headers
+            .get_mut("authorization")
+            .filter(|_| self.inject_targets.header("authorization"))
+        {
             if let Ok(auth_str) = auth_header.to_str() {
                 if auth_str.contains(&self.dummy_pattern) {
                     let new_auth = auth_str.replace(&self.dummy_pattern, real_token);
@@ -162,7 +331,10 @@ impl AuthStrategy for BearerStrategy {
         }
 
         // Update X-API-Key header if present
-        if let Some(api_key) = headers.get_mut("x-api-key") {
+        if let Some(api_key) = headers
+            .get_mut("x-api-key")
+            .filter(|_| self.inject_targets.header("x-api-key"))
+        {
             if let Ok(key_str) = api_key.to_str() {
                 if key_str.contains(&self.dummy_pattern) {
                     let new_key = key_str.replace(&self.dummy_pattern, real_token);
@@ -193,7 +365,7 @@ impl AuthStrategy for BearerStrategy {
         }
 
         for pattern in &self.allowed_hosts {
-            if Self::matches_wildcard(pattern, host) {
+            if matches_wildcard(pattern, host) {
                 return true;
             }
         }
@@ -208,12 +380,91 @@ impl AuthStrategy for BearerStrategy {
     }
 
     fn dummy_patterns(&self) -> Vec<String> {
+        if self.sanitize_only {
+            return Vec::new();
+        }
         vec![self.dummy_pattern.clone()]
     }
 
+    fn allowed_hosts(&self) -> Vec<String> {
+        self.allowed_hosts.clone()
+    }
+
     fn real_credential(&self) -> Option<String> {
         self.real_token.clone()
     }
+
+    fn sanitize_only(&self) -> bool {
+        self.sanitize_only
+    }
+
+    fn priority(&self) -> i32 {
+        self.priority
+    }
+
+    fn inject_targets(&self) -> InjectTargets {
+        self.inject_targets.clone()
+    }
+}
+
+/// Whether `host` matches an allowed-host `pattern`
+///
+/// `*.base` matches `base` itself and its subdomains, on a label boundary:
+/// `*.github.com` does not match `evilgithub.com`.
+pub fn matches_wildcard(pattern: &str, host: &str) -> bool {
+    match pattern.strip_prefix("*.") {
+        Some(base) => {
+            host == base
+                || host
+                    .strip_suffix(base)
+                    .is_some_and(|prefix| prefix.ends_with('.'))
+        }
+        None => pattern == host,
+    }
+}
+
+/// Whether any value of header `name` contains `pattern`
+///
+/// Every repeated header is checked: injection replaces dummies in all of
+/// them, so detection must not stop at the first.
+pub fn header_contains(headers: &HeaderMap, name: &str, pattern: &str) -> bool {
+    headers
+        .get_all(name)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .any(|value| value.contains(pattern))
+}
+
+/// Detect which strategies a request uses and check each one may talk to `host`
+///
+/// Strategies are consulted in priority order. Returns the detected ones in
+/// that order, or an error for the first one whose credential would be sent
+/// to a host outside its whitelist.
+pub fn detect_and_validate_strategies<'a>(
+    strategies: &'a [Box<dyn AuthStrategy>],
+    headers: &HeaderMap,
+    body: &str,
+    host: &str,
+) -> Result<Vec<&'a dyn AuthStrategy>, SecurityError> {
+    let mut detected = Vec::new();
+
+    for strategy in by_priority(strategies) {
+        if !strategy.detect(headers, body) {
+            continue;
+        }
+
+        if !strategy.validate_host(host) {
+            return Err(SecurityError::HostNotWhitelisted {
+                credential_type: strategy.name().to_string(),
+                host: host.to_string(),
+                allowed_hosts: strategy.allowed_hosts(),
+            });
+        }
+
+        detected.push(strategy);
+    }
+
+    Ok(detected)
 }
 
 #[cfg(test)]
@@ -276,6 +527,51 @@ mod tests {
         assert!(!result.contains("DUMMY_TEST"));
     }
 
+    #[test]
+    fn test_inject_targets_parse() {
+        let targets = |list: &[&str]| {
+            InjectTargets::parse(&list.iter().map(|t| t.to_string()).collect::<Vec<_>>())
+        };
+
+        assert!(targets(&[]).unwrap().is_unrestricted());
+
+        let headers_only = targets(&["headers"]).unwrap();
+        assert!(!headers_only.body());
+        assert!(headers_only.header("x-anything"));
+
+        let named = targets(&["Authorization"]).unwrap();
+        assert!(!named.body());
+        assert!(named.header("authorization"));
+        assert!(!named.header("x-api-key"));
+
+        assert!(targets(&["body"]).unwrap().body());
+        assert!(targets(&["bad header"]).is_err());
+    }
+
+    #[test]
+    fn test_bearer_strategy_inject_header_only() {
+        std::env::set_var("TEST_BEARER_HEADER_ONLY_TOKEN", "real_secret_456");
+
+        let strategy = BearerStrategy::new(
+            "test".to_string(),
+            "TEST_BEARER_HEADER_ONLY_TOKEN".to_string(),
+            "DUMMY_HEADER_ONLY".to_string(),
+            vec![],
+        )
+        .unwrap()
+        .with_inject_targets(InjectTargets::parse(&["authorization".to_string()]).unwrap());
+
+        let body = r#"{"example": "DUMMY_HEADER_ONLY"}"#;
+        let mut headers = HeaderMap::new();
+        headers.insert("authorization", "Bearer DUMMY_HEADER_ONLY".parse().unwrap());
+        headers.insert("x-api-key", "DUMMY_HEADER_ONLY".parse().unwrap());
+
+        let result = strategy.inject(body, &mut headers).unwrap();
+        assert_eq!(result, body);
+        assert_eq!(headers["authorization"], "Bearer real_secret_456");
+        assert_eq!(headers["x-api-key"], "DUMMY_HEADER_ONLY");
+    }
+
     #[test]
     fn test_bearer_strategy_validate_host() {
         let strategy = BearerStrategy::new(
@@ -293,27 +589,14 @@ mod tests {
     }
 
     #[test]
-    fn test_bearer_strategy_wildcard_matching() {
-        assert!(BearerStrategy::matches_wildcard(
-            "*.example.com",
-            "api.example.com"
-        ));
-        assert!(BearerStrategy::matches_wildcard(
-            "*.example.com",
-            "example.com"
-        ));
-        assert!(!BearerStrategy::matches_wildcard(
-            "*.example.com",
-            "evil.com"
-        ));
-        assert!(BearerStrategy::matches_wildcard(
-            "api.example.com",
-            "api.example.com"
-        ));
-        assert!(!BearerStrategy::matches_wildcard(
-            "api.example.com",
-            "other.example.com"
-        ));
+    fn test_wildcard_matching() {
+        assert!(matches_wildcard("*.example.com", "api.example.com"));
+        assert!(matches_wildcard("*.example.com", "example.com"));
+        assert!(!matches_wildcard("*.example.com", "evil.com"));
+        assert!(!matches_wildcard("*.example.com", "evilexample.com"));
+        assert!(!matches_wildcard("*.github.com", "evilgithub.com"));
+        assert!(matches_wildcard("api.example.com", "api.example.com"));
+        assert!(!matches_wildcard("api.example.com", "other.example.com"));
     }
 
     #[test]
@@ -334,4 +617,98 @@ mod tests {
         let result = strategy.inject(body, &mut headers);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_detect_and_validate_strategies_priority_order() {
+        let strategy = |name: &str, dummy: &str, priority: i32| {
+            Box::new(
+                BearerStrategy::new(
+                    name.to_string(),
+                    "TEST_TOKEN".to_string(),
+                    dummy.to_string(),
+                    vec![],
+                )
+                .unwrap()
+                .with_priority(priority),
+            ) as Box<dyn AuthStrategy>
+        };
+        let strategies = vec![
+            strategy("generic", "DUMMY_KEY", 0),
+            strategy("openai", "DUMMY_KEY_OPENAI", 10),
+            strategy("other", "DUMMY_KEY_OPENAI", 0),
+        ];
+
+        let detected = detect_and_validate_strategies(
+            &strategies,
+            &HeaderMap::new(),
+            "DUMMY_KEY_OPENAI",
+            "api.openai.com",
+        )
+        .unwrap();
+
+        let names: Vec<&str> = detected.iter().map(|s| s.name()).collect();
+        assert_eq!(names, ["openai", "generic", "other"]);
+    }
+
+    #[test]
+    fn test_detect_and_validate_strategies_allowed_host() {
+        let strategies: Vec<Box<dyn AuthStrategy>> = vec![Box::new(
+            BearerStrategy::new(
+                "openai".to_string(),
+                "TEST_VALIDATE_TOKEN".to_string(),
+                "DUMMY_OPENAI".to_string(),
+                vec!["api.openai.com".to_string()],
+            )
+            .unwrap(),
+        )];
+
+        let detected = detect_and_validate_strategies(
+            &strategies,
+            &HeaderMap::new(),
+            "key=DUMMY_OPENAI",
+            "api.openai.com",
+        )
+        .unwrap();
+        assert_eq!(detected.len(), 1);
+        assert_eq!(detected[0].name(), "openai");
+
+        let none = detect_and_validate_strategies(&strategies, &HeaderMap::new(), "{}", "evil.com")
+            .unwrap();
+        assert!(none.is_empty());
+    }
+
+    #[test]
+    fn test_detect_and_validate_strategies_blocks_exfiltration() {
+        let strategies: Vec<Box<dyn AuthStrategy>> = vec![Box::new(
+            BearerStrategy::new(
+                "openai".to_string(),
+                "TEST_VALIDATE_TOKEN".to_string(),
+                "DUMMY_OPENAI".to_string(),
+                vec!["api.openai.com".to_string()],
+            )
+            .unwrap(),
+        )];
+
+        let result = detect_and_validate_strategies(
+            &strategies,
+            &HeaderMap::new(),
+            "key=DUMMY_OPENAI",
+            "evil.com",
+        );
+        match result {
+            Err(SecurityError::HostNotWhitelisted {
+                credential_type,
+                host,
+                allowed_hosts,
+            }) => {
+                assert_eq!(credential_type, "openai");
+                assert_eq!(host, "evil.com");
+                assert_eq!(allowed_hosts, vec!["api.openai.com".to_string()]);
+            }
+            other => panic!(
+                "expected HostNotWhitelisted, got {:?}",
+                other.map(|v| v.len())
+            ),
+        }
+    }
 }
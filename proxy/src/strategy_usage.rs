@@ -0,0 +1,132 @@
+// SLAPENIR Strategy Usage - When each credential was last injected
+// Strategies that are never exercised are candidates for removal or
+// rotation; only strategy names and timestamps are kept, never credentials.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// When one strategy was last injected
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StrategyLastUsed {
+    pub strategy: String,
+    /// Unix seconds of the last injection; `None` if never injected
+    pub last_used: Option<u64>,
+}
+
+/// Last injection time of every configured strategy
+#[derive(Debug, Default)]
+pub struct StrategyUsage {
+    last_used: Mutex<BTreeMap<String, Option<u64>>>,
+}
+
+impl StrategyUsage {
+    /// Track `strategies`, none of them used yet
+    pub fn new<'a>(strategies: impl IntoIterator<Item = &'a str>) -> Self {
+        let last_used = strategies
+            .into_iter()
+            .map(|strategy| (strategy.to_string(), None))
+            .collect();
+        Self {
+            last_used: Mutex::new(last_used),
+        }
+    }
+
+    /// Track exactly `strategies`, keeping the last use of those already tracked
+    pub fn track<'a>(&self, strategies: impl IntoIterator<Item = &'a str>) {
+        let mut last_used = self.last_used.lock().unwrap();
+        *last_used = strategies
+            .into_iter()
+            .map(|strategy| {
+                (
+                    strategy.to_string(),
+                    last_used.get(strategy).copied().flatten(),
+                )
+            })
+            .collect();
+    }
+
+    /// Mark `strategy` as injected now, returning the timestamp
+    pub fn record(&self, strategy: &str) -> u64 {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+        self.last_used
+            .lock()
+            .unwrap()
+            .insert(strategy.to_string(), Some(now));
+        now
+    }
+
+    /// Unix seconds `strategy` was last injected, if ever
+    pub fn last_used(&self, strategy: &str) -> Option<u64> {
+        self.last_used
+            .lock()
+            .unwrap()
+            .get(strategy)
+            .copied()
+            .flatten()
+    }
+
+    /// Every tracked strategy, by name
+    pub fn snapshot(&self) -> Vec<StrategyLastUsed> {
+        self.last_used
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(strategy, last_used)| StrategyLastUsed {
+                strategy: strategy.clone(),
+                last_used: *last_used,
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_sets_last_used() {
+        let usage = StrategyUsage::new(["openai", "github"]);
+        let at = usage.record("openai");
+
+        assert!(at > 0);
+        assert_eq!(usage.last_used("openai"), Some(at));
+        assert_eq!(usage.last_used("github"), None);
+    }
+
+    #[test]
+    fn test_track_keeps_surviving_strategies() {
+        let usage = StrategyUsage::new(["openai", "github"]);
+        let at = usage.record("openai");
+
+        usage.track(["openai", "anthropic"]);
+
+        assert_eq!(usage.last_used("openai"), Some(at));
+        assert_eq!(usage.last_used("anthropic"), None);
+        let tracked: Vec<_> = usage.snapshot().into_iter().map(|s| s.strategy).collect();
+        assert_eq!(tracked, vec!["anthropic", "openai"]);
+    }
+
+    #[test]
+    fn test_snapshot_lists_unused_strategies() {
+        let usage = StrategyUsage::new(["openai", "github"]);
+        let at = usage.record("github");
+
+        assert_eq!(
+            usage.snapshot(),
+            vec![
+                StrategyLastUsed {
+                    strategy: "github".to_string(),
+                    last_used: Some(at),
+                },
+                StrategyLastUsed {
+                    strategy: "openai".to_string(),
+                    last_used: None,
+                },
+            ]
+        );
+    }
+}
@@ -1,6 +1,7 @@
 // TLS Acceptor for MITM
 // Terminates client TLS connections and establishes upstream connections
 
+use crate::metrics;
 use crate::tls::{CertificateAuthority, CertificateCache, HostCertificate, TlsError};
 use rustls::ServerConfig;
 use std::sync::Arc;
@@ -53,15 +54,17 @@ impl MitmAcceptor {
         IO: AsyncRead + AsyncWrite + Unpin,
     {
         let acceptor = self.create_acceptor(hostname).await?;
-        acceptor
+        let tls_stream = acceptor
             .accept(stream)
             .await
-            .map_err(|e| TlsError::TlsHandshake(e.to_string()))
+            .map_err(|e| TlsError::TlsHandshake(e.to_string()))?;
+        metrics::record_mitm_handshake("client");
+        Ok(tls_stream)
     }
 }
 
 /// Build a rustls ServerConfig from a host certificate
-fn build_server_config(cert: &HostCertificate) -> Result<ServerConfig, TlsError> {
+pub(crate) fn build_server_config(cert: &HostCertificate) -> Result<ServerConfig, TlsError> {
     // Parse certificate PEM
     let cert_pem = cert.cert_pem().as_bytes();
     let certs = rustls_pemfile::certs(&mut &cert_pem[..])
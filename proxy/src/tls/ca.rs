@@ -3,7 +3,8 @@
 
 use crate::tls::error::TlsError;
 use rcgen::{
-    BasicConstraints, Certificate, CertificateParams, DistinguishedName, DnType, IsCa, SanType,
+    BasicConstraints, Certificate, CertificateParams, DistinguishedName, DnType, DnValue, IsCa,
+    SanType,
 };
 use std::fs;
 use std::path::Path;
@@ -122,20 +123,33 @@ impl CertificateAuthority {
     pub fn load(cert_path: &Path, key_path: &Path) -> Result<Self, TlsError> {
         let cert_pem = fs::read_to_string(cert_path)?;
         let key_pem = fs::read_to_string(key_path)?;
+        Self::from_pem(cert_pem, key_pem)
+    }
 
-        // For loading, we recreate the Certificate from the PEM strings
-        // rcgen 0.12 doesn't have from_ca_cert_pem, so we use from_params with key_pair
+    /// Import a CA from its PEM certificate and private key
+    ///
+    /// rcgen 0.12 cannot sign with a parsed certificate, so the signer is
+    /// rebuilt from the key and the certificate's exact subject. Leaf issuers
+    /// then match the imported root byte for byte, whoever generated it.
+    pub fn from_pem(cert_pem: String, key_pem: String) -> Result<Self, TlsError> {
         let key_pair = rcgen::KeyPair::from_pem(&key_pem)
             .map_err(|e| TlsError::CertGeneration(format!("Failed to parse key: {}", e)))?;
 
+        let der = rustls_pemfile::certs(&mut cert_pem.as_bytes())
+            .next()
+            .ok_or_else(|| TlsError::InvalidCertificate("no certificate in PEM".to_string()))?
+            .map_err(|e| TlsError::InvalidCertificate(e.to_string()))?;
+        let (_, parsed) = x509_parser::parse_x509_certificate(&der)
+            .map_err(|e| TlsError::InvalidCertificate(e.to_string()))?;
+
+        if parsed.public_key().raw != key_pair.public_key_der().as_slice() {
+            return Err(TlsError::InvalidCertificate(
+                "CA key does not match CA certificate".to_string(),
+            ));
+        }
+
         let mut params = CertificateParams::default();
-        params.distinguished_name = DistinguishedName::new();
-        params
-            .distinguished_name
-            .push(DnType::CommonName, "SLAPENIR Proxy CA");
-        params
-            .distinguished_name
-            .push(DnType::OrganizationName, "SLAPENIR");
+        params.distinguished_name = subject_from_x509(parsed.subject())?;
         params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
         params.key_pair = Some(key_pair);
 
@@ -161,6 +175,36 @@ impl CertificateAuthority {
     }
 }
 
+/// Copy a parsed subject into rcgen, keeping each attribute's string type
+fn subject_from_x509(name: &x509_parser::x509::X509Name) -> Result<DistinguishedName, TlsError> {
+    use x509_parser::asn1_rs::Tag;
+
+    let mut dn = DistinguishedName::new();
+    for attr in name.iter_attributes() {
+        let oid: Vec<u64> = attr
+            .attr_type()
+            .iter()
+            .ok_or_else(|| TlsError::InvalidCertificate("unsupported subject OID".to_string()))?
+            .collect();
+        let value = attr.attr_value();
+        let text = || {
+            std::str::from_utf8(value.data)
+                .map(str::to_string)
+                .map_err(|_| TlsError::InvalidCertificate("invalid subject string".to_string()))
+        };
+        let value = match value.tag() {
+            Tag::PrintableString => DnValue::PrintableString(text()?),
+            Tag::Ia5String => DnValue::Ia5String(text()?),
+            Tag::BmpString => DnValue::BmpString(value.data.to_vec()),
+            Tag::TeletexString => DnValue::TeletexString(value.data.to_vec()),
+            Tag::UniversalString => DnValue::UniversalString(value.data.to_vec()),
+            _ => DnValue::Utf8String(text()?),
+        };
+        dn.push(DnType::from_oid(&oid), value);
+    }
+    Ok(dn)
+}
+
 /// Certificate for a specific host
 pub struct HostCertificate {
     hostname: String,
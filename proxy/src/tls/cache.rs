@@ -1,6 +1,7 @@
 // TLS Certificate Cache
 // Caches generated certificates for performance with LRU eviction
 
+use crate::metrics;
 use crate::tls::{CertificateAuthority, HostCertificate, TlsError};
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -47,12 +48,16 @@ impl CertificateCache {
             if let Some(entry) = cache.get_mut(hostname) {
                 // Update access time
                 entry.last_accessed = std::time::Instant::now();
+                metrics::record_cert_cache_lookup(true);
                 return Ok(entry.certificate.clone());
             }
         }
+        metrics::record_cert_cache_lookup(false);
 
         // Not in cache, generate new certificate
+        let start = std::time::Instant::now();
         let cert = ca.sign_for_host(hostname)?;
+        metrics::record_cert_generation(start.elapsed().as_secs_f64());
         let cert_arc = Arc::new(cert);
 
         // Store in cache
@@ -178,4 +183,19 @@ mod tests {
         // host4 should be in cache (just added)
         assert!(cache.contains("host4.com").await);
     }
+
+    #[tokio::test]
+    async fn test_cache_records_generation_metrics() {
+        let ca = Arc::new(CertificateAuthority::generate().unwrap());
+        let cache = CertificateCache::new();
+
+        let generations = metrics::CERT_GENERATION_SECONDS.get_sample_count();
+        let hits = metrics::CERT_CACHE_HITS_TOTAL.get();
+
+        cache.get_or_create("metrics.com", &ca).await.unwrap();
+        assert!(metrics::CERT_GENERATION_SECONDS.get_sample_count() > generations);
+
+        cache.get_or_create("metrics.com", &ca).await.unwrap();
+        assert!(metrics::CERT_CACHE_HITS_TOTAL.get() > hits);
+    }
 }
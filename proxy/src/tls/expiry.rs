@@ -0,0 +1,128 @@
+// Certificate Expiry Monitoring
+// Publishes the notAfter of loaded certificates so operators can alert before expiry
+
+use crate::tls::error::TlsError;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// How often watched certificates are re-read (picks up rotated files)
+pub const DEFAULT_CERT_EXPIRY_REFRESH: Duration = Duration::from_secs(60 * 60);
+
+/// `notAfter` of the first certificate in a PEM bundle, as a Unix timestamp
+pub fn pem_not_after(pem: &[u8]) -> Result<i64, TlsError> {
+    let der = rustls_pemfile::certs(&mut &pem[..])
+        .next()
+        .ok_or_else(|| TlsError::InvalidCertificate("no certificate in PEM".to_string()))?
+        .map_err(|e| TlsError::InvalidCertificate(e.to_string()))?;
+    let (_, cert) = x509_parser::parse_x509_certificate(&der)
+        .map_err(|e| TlsError::InvalidCertificate(e.to_string()))?;
+    Ok(cert.validity().not_after.timestamp())
+}
+
+/// Read the certificate at `path` and publish its expiry under the `cert` label
+pub fn record_cert_expiry(cert: &str, path: &Path) -> Result<i64, TlsError> {
+    let not_after = pem_not_after(&std::fs::read(path)?)?;
+    crate::metrics::update_cert_expiry(cert, not_after);
+    Ok(not_after)
+}
+
+/// Certificate files whose expiry is kept up to date in the metrics
+#[derive(Debug, Clone, Default)]
+pub struct CertExpiryWatch {
+    certs: Vec<(String, PathBuf)>,
+}
+
+impl CertExpiryWatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Watch the certificate at `path`, published as `cert`
+    pub fn with_cert(mut self, cert: &str, path: impl Into<PathBuf>) -> Self {
+        self.certs.push((cert.to_string(), path.into()));
+        self
+    }
+
+    /// Re-read every watched certificate; unreadable ones are logged and skipped
+    pub fn refresh(&self) {
+        for (cert, path) in &self.certs {
+            if let Err(e) = record_cert_expiry(cert, path) {
+                tracing::warn!(
+                    "Cannot read expiry of {} certificate at {}: {}",
+                    cert,
+                    path.display(),
+                    e
+                );
+            }
+        }
+    }
+
+    /// Refresh now and then every `interval` in the background
+    pub fn spawn(self, interval: Duration) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(interval);
+            loop {
+                ticker.tick().await;
+                self.refresh();
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metrics::CERT_EXPIRY_TIMESTAMP;
+    use crate::tls::CertificateAuthority;
+
+    fn cert_expiring(year: i32, month: u8, day: u8) -> String {
+        let mut params = rcgen::CertificateParams::new(vec!["proxy.local".to_string()]);
+        params.not_after = rcgen::date_time_ymd(year, month, day);
+        rcgen::Certificate::from_params(params)
+            .unwrap()
+            .serialize_pem()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_pem_not_after() {
+        // 2031-06-15T00:00:00Z
+        assert_eq!(
+            pem_not_after(cert_expiring(2031, 6, 15).as_bytes()).unwrap(),
+            1_939_248_000
+        );
+        assert!(pem_not_after(b"not a certificate").is_err());
+    }
+
+    #[test]
+    fn test_watch_publishes_cert_expiry() {
+        let dir = tempfile::tempdir().unwrap();
+        let server_path = dir.path().join("server.pem");
+        std::fs::write(&server_path, cert_expiring(2031, 6, 15)).unwrap();
+        let ca_path = dir.path().join("ca.pem");
+        let ca_key_path = dir.path().join("ca-key.pem");
+        CertificateAuthority::generate()
+            .unwrap()
+            .save(&ca_path, &ca_key_path)
+            .unwrap();
+
+        CertExpiryWatch::new()
+            .with_cert("test_server", &server_path)
+            .with_cert("test_ca", &ca_path)
+            .with_cert("test_missing", dir.path().join("missing.pem"))
+            .refresh();
+
+        assert_eq!(
+            CERT_EXPIRY_TIMESTAMP
+                .with_label_values(&["test_server"])
+                .get(),
+            1_939_248_000.0
+        );
+        // rcgen's default validity ends 4096-01-01
+        assert_eq!(
+            CERT_EXPIRY_TIMESTAMP.with_label_values(&["test_ca"]).get(),
+            67_090_118_400.0
+        );
+    }
+}
@@ -5,8 +5,10 @@ pub mod acceptor;
 pub mod ca;
 pub mod cache;
 pub mod error;
+pub mod expiry;
 
 pub use acceptor::{extract_sni, MitmAcceptor};
 pub use ca::{CertificateAuthority, HostCertificate};
 pub use cache::CertificateCache;
 pub use error::TlsError;
+pub use expiry::{pem_not_after, record_cert_expiry, CertExpiryWatch};
@@ -0,0 +1,201 @@
+// SLAPENIR Trace Propagation - Trace context headers on forwarded requests
+// Continues the agent's W3C trace (or starts one) across the proxy so spans
+// in the provider's systems correlate with the agent's. Trace headers carry
+// only random ids, so they are neither secrets nor stripped.
+
+use axum::http::{HeaderMap, HeaderValue};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::metrics::TraceContext;
+
+pub const TRACEPARENT_HEADER: &str = "traceparent";
+pub const TRACESTATE_HEADER: &str = "tracestate";
+/// B3 single-header format: `<trace id>-<span id>-<sampled>`
+pub const B3_HEADER: &str = "b3";
+/// First of the B3 multi-header format; its presence means B3 is already set
+const B3_TRACE_ID_HEADER: &str = "x-b3-traceid";
+
+/// Which trace context headers the proxy adds to forwarded requests
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TracePropagation {
+    /// Forward whatever trace headers the agent sent, unchanged
+    #[default]
+    Off,
+    /// Continue (or start) a W3C `traceparent`
+    W3c,
+    /// W3C plus a B3 single header, unless the agent already sent B3
+    W3cB3,
+}
+
+impl TracePropagation {
+    /// Parse a setting value: `off`, `w3c` or `w3c_b3`
+    ///
+    /// An OpenTelemetry `OTEL_PROPAGATORS` list is accepted too:
+    /// `tracecontext` selects W3C, `b3` adds B3 and other entries are ignored.
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim().to_ascii_lowercase();
+        match value.as_str() {
+            "off" | "none" | "" => return Some(Self::Off),
+            "w3c" => return Some(Self::W3c),
+            "w3c_b3" => return Some(Self::W3cB3),
+            _ => {}
+        }
+        let propagators: Vec<&str> = value.split(',').map(str::trim).collect();
+        if propagators.contains(&"b3") || propagators.contains(&"b3multi") {
+            Some(Self::W3cB3)
+        } else if propagators.contains(&"tracecontext") {
+            Some(Self::W3c)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::W3c => "w3c",
+            Self::W3cB3 => "w3c_b3",
+        }
+    }
+}
+
+/// Set the trace headers for a forwarded request in `headers`
+///
+/// A valid incoming `traceparent` is continued: its trace id and flags are
+/// kept, with a new span id for the proxy's hop, and `tracestate` passes
+/// through untouched. Without one a new sampled trace is started; an
+/// invalid one is replaced, dropping the `tracestate` that went with it.
+pub fn propagate(mode: TracePropagation, headers: &mut HeaderMap) {
+    if mode == TracePropagation::Off {
+        return;
+    }
+
+    let incoming = headers
+        .get(TRACEPARENT_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| Some((TraceContext::from_traceparent(v)?, trace_flags(v)?)));
+    let (trace_id, flags) = match incoming {
+        Some((context, flags)) => (context.trace_id, flags),
+        None => {
+            headers.remove(TRACESTATE_HEADER);
+            (
+                format!("{:016x}{:016x}", random_id(), random_id()),
+                "01".to_string(),
+            )
+        }
+    };
+    let span_id = format!("{:016x}", random_id());
+
+    let traceparent = format!("00-{}-{}-{}", trace_id, span_id, flags);
+    if let Ok(value) = HeaderValue::from_str(&traceparent) {
+        headers.insert(TRACEPARENT_HEADER, value);
+    }
+
+    if mode == TracePropagation::W3cB3
+        && !headers.contains_key(B3_HEADER)
+        && !headers.contains_key(B3_TRACE_ID_HEADER)
+    {
+        let sampled = u8::from_str_radix(&flags, 16).map_or(0, |f| f & 1);
+        let b3 = format!("{}-{}-{}", trace_id, span_id, sampled);
+        if let Ok(value) = HeaderValue::from_str(&b3) {
+            headers.insert(B3_HEADER, value);
+        }
+    }
+}
+
+/// Trace flags of a `traceparent` value, if well-formed
+fn trace_flags(value: &str) -> Option<String> {
+    let flags = value.trim().split('-').nth(3)?;
+    (flags.len() == 2 && flags.bytes().all(|b| b.is_ascii_hexdigit()))
+        .then(|| flags.to_ascii_lowercase())
+}
+
+/// A random non-zero id; trace ids need to be unique, not unguessable
+fn random_id() -> u64 {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(COUNTER.fetch_add(1, Ordering::Relaxed));
+    hasher.write_u128(
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos()),
+    );
+    hasher.finish().max(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INCOMING: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-00";
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(TracePropagation::parse("off"), Some(TracePropagation::Off));
+        assert_eq!(TracePropagation::parse("W3C"), Some(TracePropagation::W3c));
+        assert_eq!(
+            TracePropagation::parse("tracecontext,baggage"),
+            Some(TracePropagation::W3c)
+        );
+        assert_eq!(
+            TracePropagation::parse("tracecontext, b3"),
+            Some(TracePropagation::W3cB3)
+        );
+        assert_eq!(TracePropagation::parse("jaeger"), None);
+    }
+
+    #[test]
+    fn test_new_trace_started() {
+        let mut headers = HeaderMap::new();
+        propagate(TracePropagation::W3c, &mut headers);
+
+        let traceparent = headers[TRACEPARENT_HEADER].to_str().unwrap();
+        assert!(TraceContext::from_traceparent(traceparent).is_some());
+        assert!(traceparent.ends_with("-01"));
+        assert!(!headers.contains_key(B3_HEADER));
+    }
+
+    #[test]
+    fn test_incoming_trace_continued() {
+        let mut headers = HeaderMap::new();
+        headers.insert(TRACEPARENT_HEADER, INCOMING.parse().unwrap());
+        headers.insert(TRACESTATE_HEADER, "vendor=abc".parse().unwrap());
+        propagate(TracePropagation::W3cB3, &mut headers);
+
+        let traceparent = headers[TRACEPARENT_HEADER].to_str().unwrap();
+        let context = TraceContext::from_traceparent(traceparent).unwrap();
+        assert_eq!(context.trace_id, "4bf92f3577b34da6a3ce929d0e0e4736");
+        assert_ne!(context.span_id, "00f067aa0ba902b7");
+        assert!(traceparent.ends_with("-00"));
+        assert_eq!(headers[TRACESTATE_HEADER], "vendor=abc");
+        assert_eq!(
+            headers[B3_HEADER].to_str().unwrap(),
+            format!("{}-{}-0", context.trace_id, context.span_id)
+        );
+    }
+
+    #[test]
+    fn test_invalid_incoming_trace_replaced() {
+        let mut headers = HeaderMap::new();
+        headers.insert(TRACEPARENT_HEADER, "garbage".parse().unwrap());
+        headers.insert(TRACESTATE_HEADER, "vendor=abc".parse().unwrap());
+        propagate(TracePropagation::W3c, &mut headers);
+
+        let traceparent = headers[TRACEPARENT_HEADER].to_str().unwrap();
+        assert!(TraceContext::from_traceparent(traceparent).is_some());
+        assert!(!headers.contains_key(TRACESTATE_HEADER));
+    }
+
+    #[test]
+    fn test_off_leaves_headers_alone() {
+        let mut headers = HeaderMap::new();
+        headers.insert(TRACEPARENT_HEADER, INCOMING.parse().unwrap());
+        propagate(TracePropagation::Off, &mut headers);
+
+        assert_eq!(headers[TRACEPARENT_HEADER], INCOMING);
+        assert_eq!(headers.len(), 1);
+    }
+}
@@ -0,0 +1,172 @@
+// SLAPENIR Upstream Host Tracking - Distinct egress destinations
+// Records which upstream hosts the proxy has contacted so operators can spot
+// unexpected destinations, bounded so host spraying cannot grow it forever.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+/// Default number of distinct upstream hosts remembered
+pub const DEFAULT_MAX_TRACKED_UPSTREAM_HOSTS: usize = 1024;
+
+/// One tracked upstream host; only the host name, never URLs or credentials
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UpstreamHost {
+    pub host: String,
+    pub requests: u64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    requests: u64,
+    /// Position in the recency order
+    last_used: u64,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    hosts: HashMap<String, Entry>,
+    /// `last_used` tick -> host, least recently used first
+    recency: BTreeMap<u64, String>,
+    tick: u64,
+}
+
+/// Distinct upstream hosts with request counts, evicting the least recently used
+///
+/// A capacity of zero disables tracking.
+#[derive(Debug)]
+pub struct UpstreamHosts {
+    capacity: usize,
+    inner: Mutex<Inner>,
+}
+
+impl UpstreamHosts {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: Mutex::new(Inner::default()),
+        }
+    }
+
+    /// Count a request to `host`, returning the number of distinct hosts tracked
+    pub fn record(&self, host: &str) -> usize {
+        if self.capacity == 0 || host.is_empty() {
+            return 0;
+        }
+        let host = host.to_ascii_lowercase();
+        let mut inner = self.inner.lock().unwrap();
+        inner.tick += 1;
+        let tick = inner.tick;
+
+        let previous = match inner.hosts.get_mut(&host) {
+            Some(entry) => {
+                entry.requests += 1;
+                Some(std::mem::replace(&mut entry.last_used, tick))
+            }
+            None => None,
+        };
+        match previous {
+            Some(previous) => {
+                inner.recency.remove(&previous);
+            }
+            None => {
+                if inner.hosts.len() >= self.capacity {
+                    if let Some((_, oldest)) = inner.recency.pop_first() {
+                        inner.hosts.remove(&oldest);
+                    }
+                }
+                inner.hosts.insert(
+                    host.clone(),
+                    Entry {
+                        requests: 1,
+                        last_used: tick,
+                    },
+                );
+            }
+        }
+        inner.recency.insert(tick, host);
+        inner.hosts.len()
+    }
+
+    /// Number of distinct hosts tracked
+    pub fn len(&self) -> usize {
+        self.inner.lock().unwrap().hosts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Maximum number of hosts remembered
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Tracked hosts, most requested first
+    pub fn snapshot(&self) -> Vec<UpstreamHost> {
+        let inner = self.inner.lock().unwrap();
+        let mut hosts: Vec<UpstreamHost> = inner
+            .hosts
+            .iter()
+            .map(|(host, entry)| UpstreamHost {
+                host: host.clone(),
+                requests: entry.requests,
+            })
+            .collect();
+        hosts.sort_by(|a, b| b.requests.cmp(&a.requests).then(a.host.cmp(&b.host)));
+        hosts
+    }
+}
+
+impl Default for UpstreamHosts {
+    fn default() -> Self {
+        Self::new(DEFAULT_MAX_TRACKED_UPSTREAM_HOSTS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distinct_hosts_counted_once() {
+        let hosts = UpstreamHosts::new(8);
+        hosts.record("api.openai.com");
+        hosts.record("API.OpenAI.com");
+        assert_eq!(hosts.record("api.github.com"), 2);
+
+        assert_eq!(
+            hosts.snapshot(),
+            vec![
+                UpstreamHost {
+                    host: "api.openai.com".to_string(),
+                    requests: 2,
+                },
+                UpstreamHost {
+                    host: "api.github.com".to_string(),
+                    requests: 1,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_least_recently_used_host_evicted() {
+        let hosts = UpstreamHosts::new(2);
+        hosts.record("a.example.com");
+        hosts.record("b.example.com");
+        // Touching `a` makes `b` the oldest
+        hosts.record("a.example.com");
+        assert_eq!(hosts.record("c.example.com"), 2);
+
+        let names: Vec<String> = hosts.snapshot().into_iter().map(|h| h.host).collect();
+        assert_eq!(names, vec!["a.example.com", "c.example.com"]);
+    }
+
+    #[test]
+    fn test_zero_capacity_disables_tracking() {
+        let hosts = UpstreamHosts::new(0);
+        assert_eq!(hosts.record("a.example.com"), 0);
+        assert!(hosts.is_empty());
+    }
+}
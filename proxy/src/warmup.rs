@@ -0,0 +1,263 @@
+// SLAPENIR Warmup - Pay cold-start costs before the first request
+// Exercises the secret automatons, the MITM cert-gen + handshake path and,
+// optionally, the upstream connection pool, so autoscaled replicas are fast
+// from their first request.
+
+use crate::middleware::AppState;
+use crate::tls::acceptor::build_server_config;
+use crate::tls::{CertificateAuthority, TlsError};
+use axum::body::Body;
+use axum::http::{Method, Request, Uri};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio_rustls::{TlsAcceptor, TlsConnector};
+
+/// Hostname of the throwaway leaf certificate (reserved TLD, never resolvable)
+pub const WARMUP_HOSTNAME: &str = "warmup.slapenir.invalid";
+
+/// How long the upstream pre-connect may take before it is abandoned
+pub const WARMUP_UPSTREAM_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Text run through the automatons; holds no secret, so nothing is recorded
+const WARMUP_SAMPLE: &str = "slapenir warmup sample";
+
+/// What the startup warmup exercises
+#[derive(Debug, Clone, Default)]
+pub struct WarmupConfig {
+    /// Generate a throwaway leaf certificate and complete an in-memory handshake with it
+    pub mitm: bool,
+    /// Primary upstream (`https://host[:port]`) to open a pooled connection to
+    pub upstream: Option<String>,
+}
+
+/// Which warmup steps completed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WarmupReport {
+    pub automatons: bool,
+    pub mitm_handshake: bool,
+    pub upstream_connected: bool,
+}
+
+/// Run the configured warmup steps; failures are logged, never fatal
+///
+/// Serves no traffic. The leaf certificate lives only for the in-memory
+/// handshake: it is neither cached nor written out. The only host contacted
+/// is the configured upstream, and only when egress to it is not denied.
+pub async fn warmup(
+    state: &AppState,
+    ca: Option<&CertificateAuthority>,
+    config: &WarmupConfig,
+) -> WarmupReport {
+    let started = Instant::now();
+    let mut report = WarmupReport::default();
+
+    match warm_automatons(state) {
+        Ok(()) => report.automatons = true,
+        Err(e) => tracing::warn!("Warmup: secret automatons failed: {}", e),
+    }
+
+    if config.mitm {
+        match ca {
+            Some(ca) => match warm_mitm_handshake(ca).await {
+                Ok(()) => report.mitm_handshake = true,
+                Err(e) => tracing::warn!("Warmup: MITM handshake failed: {}", e),
+            },
+            None => tracing::warn!("Warmup: no MITM CA available, skipping handshake"),
+        }
+    }
+
+    if let Some(upstream) = &config.upstream {
+        match warm_upstream(state, upstream).await {
+            Ok(()) => report.upstream_connected = true,
+            Err(e) => tracing::warn!("Warmup: upstream {} not pre-connected: {}", upstream, e),
+        }
+    }
+
+    tracing::info!(
+        "🔥 Warmup finished in {:?} (automatons: {}, MITM handshake: {}, upstream: {})",
+        started.elapsed(),
+        report.automatons,
+        report.mitm_handshake,
+        report.upstream_connected
+    );
+    report
+}
+
+/// Run every injection and sanitization matcher once
+fn warm_automatons(state: &AppState) -> Result<(), String> {
+    state.inject_all(WARMUP_SAMPLE);
+    state.sanitize_all(WARMUP_SAMPLE);
+    state.sanitize_bytes_all(WARMUP_SAMPLE.as_bytes());
+
+    let mut sanitizer = state.streaming_sanitizer()?;
+    sanitizer.push(WARMUP_SAMPLE.as_bytes());
+    sanitizer.finish();
+    let mut injector = state.streaming_injector()?;
+    injector.push(WARMUP_SAMPLE.as_bytes());
+    injector.finish();
+    Ok(())
+}
+
+/// Sign a throwaway leaf and handshake with it over an in-memory pipe
+async fn warm_mitm_handshake(ca: &CertificateAuthority) -> Result<(), TlsError> {
+    let leaf = ca.sign_for_host(WARMUP_HOSTNAME)?;
+    let acceptor = TlsAcceptor::from(Arc::new(build_server_config(&leaf)?));
+
+    let mut roots = rustls::RootCertStore::empty();
+    for cert in rustls_pemfile::certs(&mut ca.cert_pem().as_bytes()) {
+        let cert = cert.map_err(|e| TlsError::InvalidCertificate(e.to_string()))?;
+        roots
+            .add(cert)
+            .map_err(|e| TlsError::InvalidCertificate(e.to_string()))?;
+    }
+    let connector = TlsConnector::from(Arc::new(
+        rustls::ClientConfig::builder()
+            .with_root_certificates(roots)
+            .with_no_client_auth(),
+    ));
+    let server_name = rustls::pki_types::ServerName::try_from(WARMUP_HOSTNAME)
+        .map_err(|e| TlsError::TlsHandshake(e.to_string()))?;
+
+    let (client_io, server_io) = tokio::io::duplex(16 * 1024);
+    let (client, server) = tokio::join!(
+        connector.connect(server_name, client_io),
+        acceptor.accept(server_io)
+    );
+    client.map_err(|e| TlsError::TlsHandshake(e.to_string()))?;
+    server.map_err(|e| TlsError::TlsHandshake(e.to_string()))?;
+    Ok(())
+}
+
+/// Open a pooled connection to `upstream` with a bare, credential-free HEAD
+async fn warm_upstream(state: &AppState, upstream: &str) -> Result<(), String> {
+    let uri: Uri = upstream
+        .parse()
+        .map_err(|e| format!("invalid URL: {}", e))?;
+    if !matches!(uri.scheme_str(), Some("https") | Some("http")) {
+        return Err("only http(s) upstreams can be pre-connected".to_string());
+    }
+    let host = uri.host().ok_or("URL has no host")?;
+    if state.is_host_denied(host) {
+        return Err("egress to host is denied".to_string());
+    }
+
+    let request = Request::builder()
+        .method(Method::HEAD)
+        .uri(uri)
+        .body(Body::empty())
+        .map_err(|e| e.to_string())?;
+    match tokio::time::timeout(WARMUP_UPSTREAM_TIMEOUT, state.http_client.request(request)).await {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(_) => Err(format!("timed out after {:?}", WARMUP_UPSTREAM_TIMEOUT)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proxy::create_http_client;
+    use crate::sanitizer::SecretMap;
+    use std::collections::HashMap;
+    use std::sync::Mutex;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    fn create_state() -> AppState {
+        let mut secrets = HashMap::new();
+        secrets.insert("DUMMY_TOKEN".to_string(), "real_secret_123".to_string());
+        AppState::new(
+            Arc::new(SecretMap::new(secrets).unwrap()),
+            create_http_client(),
+        )
+    }
+
+    /// Start a mock upstream that records each raw request and replies 200 OK
+    async fn start_capturing_upstream() -> (u16, Arc<Mutex<Vec<String>>>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let captured = Arc::new(Mutex::new(Vec::new()));
+        let sink = captured.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut buf = vec![0u8; 4096];
+                let n = stream.read(&mut buf).await.unwrap_or(0);
+                sink.lock()
+                    .unwrap()
+                    .push(String::from_utf8_lossy(&buf[..n]).into_owned());
+                let _ = stream
+                    .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 0\r\n\r\n")
+                    .await;
+            }
+        });
+        (port, captured)
+    }
+
+    #[tokio::test]
+    async fn test_warmup_runs_automatons_and_handshake() {
+        let state = create_state();
+        let ca = CertificateAuthority::generate().unwrap();
+        let config = WarmupConfig {
+            mitm: true,
+            upstream: None,
+        };
+
+        let report = warmup(&state, Some(&ca), &config).await;
+
+        assert_eq!(
+            report,
+            WarmupReport {
+                automatons: true,
+                mitm_handshake: true,
+                upstream_connected: false,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_warmup_without_ca_skips_handshake() {
+        let config = WarmupConfig {
+            mitm: true,
+            upstream: None,
+        };
+
+        let report = warmup(&create_state(), None, &config).await;
+
+        assert!(report.automatons);
+        assert!(!report.mitm_handshake);
+    }
+
+    #[tokio::test]
+    async fn test_warmup_preconnects_upstream_without_credentials() {
+        let (port, captured) = start_capturing_upstream().await;
+        let config = WarmupConfig {
+            mitm: false,
+            upstream: Some(format!("http://127.0.0.1:{}", port)),
+        };
+
+        let report = warmup(&create_state(), None, &config).await;
+
+        assert!(report.upstream_connected);
+        let requests = captured.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].starts_with("HEAD / HTTP/1.1\r\n"));
+        assert!(!requests[0].to_lowercase().contains("authorization"));
+    }
+
+    #[tokio::test]
+    async fn test_warmup_never_contacts_denied_upstream() {
+        let (port, captured) = start_capturing_upstream().await;
+        let state = create_state();
+        state
+            .set_denied_hosts(vec!["127.0.0.1".to_string()])
+            .unwrap();
+        let config = WarmupConfig {
+            mitm: false,
+            upstream: Some(format!("http://127.0.0.1:{}", port)),
+        };
+
+        let report = warmup(&state, None, &config).await;
+
+        assert!(!report.upstream_connected);
+        assert!(captured.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,368 @@
+// Admin API Tests
+// Runtime replacement of the blocked-headers and denied-hosts lists, the
+// sanitization report, the upstream hosts listing and strategy usage
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    routing::any,
+    Router,
+};
+use slapenir_proxy::{
+    admin::{self, SecurityList, StrategiesReport, UpstreamHostsReport},
+    metrics::{SanitizationReport, SECRETS_LEAKED_TOTAL, STRATEGY_LAST_USED_TIMESTAMP},
+    middleware::AppState,
+    proxy::{create_http_client, proxy_handler, ProxyConfig},
+    sanitizer::SecretMap,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tower::util::ServiceExt;
+
+const ADMIN_TOKEN: &str = "test-admin-token";
+
+/// Start a mock upstream that replies with a leaky debug header
+async fn start_upstream() -> u16 {
+    start_upstream_on("127.0.0.1").await
+}
+
+/// Start the leaky-header upstream on a specific loopback address
+async fn start_upstream_on(ip: &str) -> u16 {
+    let listener = TcpListener::bind((ip, 0)).await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let mut buf = vec![0u8; 8192];
+                let _ = stream.read(&mut buf).await;
+                let _ = stream
+                    .write_all(b"HTTP/1.1 200 OK\r\nX-Leaky-Trace: internal\r\nContent-Length: 2\r\n\r\nok")
+                    .await;
+            });
+        }
+    });
+
+    port
+}
+
+/// Start a mock upstream whose body leaks the real secret `leaks` times
+async fn start_leaky_upstream(leaks: usize) -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let body = "real_secret_123 ".repeat(leaks);
+
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let body = body.clone();
+            tokio::spawn(async move {
+                let mut buf = vec![0u8; 8192];
+                let _ = stream.read(&mut buf).await;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            });
+        }
+    });
+
+    port
+}
+
+fn create_app(admin_token: Option<&str>) -> Router {
+    let mut secrets = HashMap::new();
+    secrets.insert("DUMMY_TOKEN".to_string(), "real_secret_123".to_string());
+    let state = AppState::with_config(
+        Arc::new(SecretMap::new(secrets).unwrap()),
+        create_http_client(),
+        ProxyConfig {
+            admin_token: admin_token.map(String::from),
+            ..Default::default()
+        },
+    );
+
+    Router::new()
+        .route("/v1/{*path}", any(proxy_handler))
+        .merge(admin::routes(state.clone()))
+        .with_state(state)
+}
+
+fn proxy_request(target: &str) -> Request<Body> {
+    Request::builder()
+        .method("POST")
+        .uri("/v1/chat")
+        .header("x-target-url", target)
+        .body(Body::from("{}"))
+        .unwrap()
+}
+
+fn put_list(path: &str, token: &str, entries: &[&str]) -> Request<Body> {
+    let body = serde_json::to_string(&SecurityList {
+        entries: entries.iter().map(|e| e.to_string()).collect(),
+    })
+    .unwrap();
+
+    Request::builder()
+        .method("PUT")
+        .uri(path)
+        .header("authorization", format!("Bearer {}", token))
+        .header("content-type", "application/json")
+        .body(Body::from(body))
+        .unwrap()
+}
+
+async fn get_list(app: &Router, path: &str) -> SecurityList {
+    let request = Request::builder()
+        .uri(path)
+        .header("authorization", format!("Bearer {}", ADMIN_TOKEN))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), 4096)
+        .await
+        .unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+#[tokio::test]
+async fn test_denied_host_rejected_immediately() {
+    let port = start_upstream().await;
+    let app = create_app(Some(ADMIN_TOKEN));
+    let allowed = format!("http://0.0.0.0:{}", port);
+    let denied = format!("http://127.0.0.1:{}", port);
+
+    let response = app.clone().oneshot(proxy_request(&denied)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app
+        .clone()
+        .oneshot(put_list("/admin/denied-hosts", ADMIN_TOKEN, &["127.0.0.1"]))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        get_list(&app, "/admin/denied-hosts").await.entries,
+        vec!["127.0.0.1".to_string()]
+    );
+
+    let response = app.clone().oneshot(proxy_request(&denied)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // Traffic to hosts that are still allowed is unaffected
+    let response = app.clone().oneshot(proxy_request(&allowed)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_blocked_header_stripped_after_update() {
+    let port = start_upstream().await;
+    let app = create_app(Some(ADMIN_TOKEN));
+    let target = format!("http://0.0.0.0:{}", port);
+
+    let response = app.clone().oneshot(proxy_request(&target)).await.unwrap();
+    assert!(response.headers().get("x-leaky-trace").is_some());
+
+    let response = app
+        .clone()
+        .oneshot(put_list(
+            "/admin/blocked-headers",
+            ADMIN_TOKEN,
+            &["X-Leaky-Trace", "x-debug-token"],
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let response = app.clone().oneshot(proxy_request(&target)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get("x-leaky-trace").is_none());
+}
+
+#[tokio::test]
+async fn test_invalid_list_rejected() {
+    let app = create_app(Some(ADMIN_TOKEN));
+
+    let response = app
+        .clone()
+        .oneshot(put_list(
+            "/admin/denied-hosts",
+            ADMIN_TOKEN,
+            &["evil.com", "https://bad/"],
+        ))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    assert!(get_list(&app, "/admin/denied-hosts")
+        .await
+        .entries
+        .is_empty());
+}
+
+#[tokio::test]
+async fn test_admin_requires_token() {
+    let app = create_app(Some(ADMIN_TOKEN));
+    let response = app
+        .oneshot(put_list(
+            "/admin/denied-hosts",
+            "wrong-token",
+            &["evil.com"],
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+
+    let app = create_app(None);
+    let response = app
+        .oneshot(put_list("/admin/denied-hosts", ADMIN_TOKEN, &["evil.com"]))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn test_sanitization_report_counts_leaking_responses() {
+    let app = create_app(Some(ADMIN_TOKEN));
+    let leaked = SECRETS_LEAKED_TOTAL.with_label_values(&["DUMMY_TOKEN"]);
+    let before = leaked.get();
+
+    for leaks in [2, 0, 1] {
+        let target = format!("http://0.0.0.0:{}", start_leaky_upstream(leaks).await);
+        let response = app.clone().oneshot(proxy_request(&target)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    let request = Request::builder()
+        .uri("/admin/sanitization-report")
+        .header("authorization", format!("Bearer {}", ADMIN_TOKEN))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), 4096)
+        .await
+        .unwrap();
+    let report: SanitizationReport = serde_json::from_slice(&body).unwrap();
+
+    // Other tests here scan responses too, but only this one leaks
+    assert_eq!(leaked.get(), before + 3);
+    assert_eq!(report.by_strategy["DUMMY_TOKEN"], 3);
+    assert_eq!(report.responses_redacted, 2);
+    assert!(report.responses_scanned >= 3);
+    assert_eq!(report.top_strategy.as_deref(), Some("DUMMY_TOKEN"));
+    assert!(!String::from_utf8_lossy(&body).contains("real_secret_123"));
+
+    let request = Request::builder()
+        .uri("/admin/sanitization-report")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_upstream_hosts_lists_distinct_hosts() {
+    // 127.0.0.1 itself is a local bypass, not egress
+    let port = start_upstream().await;
+    let other_port = start_upstream_on("127.0.0.2").await;
+    let app = create_app(Some(ADMIN_TOKEN));
+
+    let targets = [
+        format!("http://0.0.0.0:{}/?token=real_secret_123", port),
+        format!("http://127.0.0.2:{}/?token=real_secret_123", other_port),
+        format!("http://127.0.0.2:{}/", other_port),
+    ];
+    for target in targets {
+        let request = proxy_request(&target);
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    let request = Request::builder()
+        .uri("/admin/upstream-hosts")
+        .header("authorization", format!("Bearer {}", ADMIN_TOKEN))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), 4096)
+        .await
+        .unwrap();
+    let report: UpstreamHostsReport = serde_json::from_slice(&body).unwrap();
+
+    assert_eq!(report.distinct, 2);
+    assert_eq!(report.hosts[0].host, "127.0.0.2");
+    assert_eq!(report.hosts[0].requests, 2);
+    assert_eq!(report.hosts[1].host, "0.0.0.0");
+    // Host names only: no ports, paths or query strings
+    assert!(!String::from_utf8_lossy(&body).contains("real_secret_123"));
+    assert!(!String::from_utf8_lossy(&body).contains(&format!(":{}", port)));
+
+    let request = Request::builder()
+        .uri("/admin/upstream-hosts")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+async fn test_strategies_report_last_injection() {
+    let port = start_upstream().await;
+    let secrets = HashMap::from([
+        ("DUMMY_TOKEN".to_string(), "real_secret_123".to_string()),
+        ("DUMMY_UNUSED".to_string(), "real_unused_456".to_string()),
+    ]);
+    let state = AppState::with_config(
+        Arc::new(SecretMap::new(secrets).unwrap()),
+        create_http_client(),
+        ProxyConfig {
+            admin_token: Some(ADMIN_TOKEN.to_string()),
+            ..Default::default()
+        },
+    );
+    let app = Router::new()
+        .route("/v1/{*path}", any(proxy_handler))
+        .merge(admin::routes(state.clone()))
+        .with_state(state);
+
+    let request = Request::builder()
+        .method("POST")
+        .uri("/v1/chat")
+        .header("x-target-url", format!("http://0.0.0.0:{}", port))
+        .body(Body::from("token=DUMMY_TOKEN"))
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let request = Request::builder()
+        .uri("/admin/strategies")
+        .header("authorization", format!("Bearer {}", ADMIN_TOKEN))
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), 4096)
+        .await
+        .unwrap();
+    let report: StrategiesReport = serde_json::from_slice(&body).unwrap();
+
+    assert_eq!(report.strategies.len(), 2);
+    assert_eq!(report.strategies[0].strategy, "DUMMY_TOKEN");
+    let used_at = report.strategies[0].last_used.unwrap();
+    assert!(used_at > 0);
+    assert_eq!(report.strategies[1].strategy, "DUMMY_UNUSED");
+    assert_eq!(report.strategies[1].last_used, None);
+    assert_eq!(
+        STRATEGY_LAST_USED_TIMESTAMP
+            .with_label_values(&["DUMMY_TOKEN"])
+            .get(),
+        used_at as i64
+    );
+    assert!(!String::from_utf8_lossy(&body).contains("real_"));
+}
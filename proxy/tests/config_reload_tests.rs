@@ -0,0 +1,123 @@
+// Integration tests for config hot reload
+// Changing the config file swaps which dummy tokens inject, without a restart
+
+use slapenir_proxy::{
+    config::Config,
+    config_reload::{build_credentials, ConfigReloader},
+    create_http_client, AppState,
+};
+use std::path::Path;
+use std::time::Duration;
+
+fn bearer_config(name: &str, env_var: &str, dummy: &str) -> String {
+    format!(
+        r#"
+strategies:
+  - name: {name}
+    type: bearer
+    config:
+      env_var: {env_var}
+      dummy_pattern: "{dummy}"
+      allowed_hosts:
+        - "api.example.com"
+"#
+    )
+}
+
+fn state_from(path: &Path) -> AppState {
+    let config = Config::from_file(path).unwrap();
+    let (secret_map, strategies) = build_credentials(&config).unwrap();
+    AppState::new(std::sync::Arc::new(secret_map), create_http_client()).with_strategies(strategies)
+}
+
+#[tokio::test]
+async fn test_config_change_updates_injected_dummies() {
+    std::env::set_var("RELOAD_TEST_FIRST_KEY", "first-real-secret");
+    std::env::set_var("RELOAD_TEST_SECOND_KEY", "second-real-secret");
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.yaml");
+    std::fs::write(
+        &path,
+        bearer_config("first", "RELOAD_TEST_FIRST_KEY", "DUMMY_RELOAD_FIRST"),
+    )
+    .unwrap();
+
+    let state = state_from(&path);
+    assert_eq!(state.inject_all("DUMMY_RELOAD_FIRST"), "first-real-secret");
+    let _watcher = ConfigReloader::new(&path, state.clone())
+        .with_debounce(Duration::from_millis(20))
+        .watch()
+        .unwrap();
+
+    std::fs::write(
+        &path,
+        bearer_config("second", "RELOAD_TEST_SECOND_KEY", "DUMMY_RELOAD_SECOND"),
+    )
+    .unwrap();
+
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(10);
+    while state.inject_all("DUMMY_RELOAD_SECOND") != "second-real-secret" {
+        assert!(
+            tokio::time::Instant::now() < deadline,
+            "config change was not applied"
+        );
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(state.inject_all("DUMMY_RELOAD_FIRST"), "DUMMY_RELOAD_FIRST");
+    assert_eq!(state.strategies()[0].name(), "second");
+}
+
+#[tokio::test]
+async fn test_invalid_config_keeps_current_credentials() {
+    std::env::set_var("RELOAD_TEST_KEPT_KEY", "kept-real-secret");
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.yaml");
+    std::fs::write(
+        &path,
+        bearer_config("kept", "RELOAD_TEST_KEPT_KEY", "DUMMY_RELOAD_KEPT"),
+    )
+    .unwrap();
+    let state = state_from(&path);
+    let reloader = ConfigReloader::new(&path, state.clone());
+
+    // Unparseable, then valid YAML naming an unknown strategy type
+    std::fs::write(&path, "strategies: [").unwrap();
+    assert!(reloader.reload().is_err());
+    std::fs::write(
+        &path,
+        "strategies:\n  - name: odd\n    type: unknown\n    config: {}\n",
+    )
+    .unwrap();
+    assert!(reloader.reload().is_err());
+
+    assert_eq!(state.inject_all("DUMMY_RELOAD_KEPT"), "kept-real-secret");
+    assert_eq!(state.strategies()[0].name(), "kept");
+}
+
+#[tokio::test]
+async fn test_request_keeps_credentials_it_started_with() {
+    std::env::set_var("RELOAD_TEST_OLD_KEY", "old-real-secret");
+    std::env::set_var("RELOAD_TEST_NEW_KEY", "new-real-secret");
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("config.yaml");
+    std::fs::write(
+        &path,
+        bearer_config("old", "RELOAD_TEST_OLD_KEY", "DUMMY_RELOAD_OLD"),
+    )
+    .unwrap();
+    let state = state_from(&path);
+    let in_flight = state.for_client(None);
+
+    std::fs::write(
+        &path,
+        bearer_config("new", "RELOAD_TEST_NEW_KEY", "DUMMY_RELOAD_NEW"),
+    )
+    .unwrap();
+    assert_eq!(
+        ConfigReloader::new(&path, state.clone()).reload().unwrap(),
+        1
+    );
+
+    assert_eq!(in_flight.inject_all("DUMMY_RELOAD_OLD"), "old-real-secret");
+    assert_eq!(state.inject_all("DUMMY_RELOAD_NEW"), "new-real-secret");
+}
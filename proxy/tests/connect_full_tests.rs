@@ -20,17 +20,17 @@ mod connect_full_unit_tests {
     #[test]
     fn test_app_state_for_connect_full() {
         let state = create_test_state();
-        assert_eq!(state.secret_map.len(), 2);
+        assert_eq!(state.secret_map().len(), 2);
 
         // Test injection
         let input = "Authorization: Bearer DUMMY_TOKEN";
-        let output = state.secret_map.inject(input);
+        let output = state.secret_map().inject(input);
         assert!(output.contains("real_secret_token"));
         assert!(!output.contains("DUMMY_TOKEN"));
 
         // Test sanitization
         let response = "Your token is: real_secret_token";
-        let sanitized = state.secret_map.sanitize(response);
+        let sanitized = state.secret_map().sanitize(response);
         assert!(!sanitized.contains("real_secret_token"));
         assert!(sanitized.contains("[REDACTED]"));
     }
@@ -45,10 +45,10 @@ mod connect_full_unit_tests {
 
         let state = AppState::new(Arc::new(secret_map), create_http_client());
 
-        assert_eq!(state.secret_map.len(), 3);
+        assert_eq!(state.secret_map().len(), 3);
 
         let input = "Keys: OPENAI_API_KEY, GITHUB_TOKEN, AWS_ACCESS_KEY";
-        let output = state.secret_map.inject(input);
+        let output = state.secret_map().inject(input);
         assert!(output.contains("sk-123456"));
         assert!(output.contains("ghp_abcdef"));
         assert!(output.contains("AKIA123456"));
@@ -58,7 +58,7 @@ mod connect_full_unit_tests {
     fn test_credential_injection_in_headers() {
         let state = create_test_state();
         let header_value = "Bearer DUMMY_TOKEN";
-        let injected = state.secret_map.inject(header_value);
+        let injected = state.secret_map().inject(header_value);
         assert_eq!(injected, "Bearer real_secret_token");
     }
 
@@ -66,7 +66,7 @@ mod connect_full_unit_tests {
     fn test_credential_injection_in_json_body() {
         let state = create_test_state();
         let json_body = r#"{"api_key": "DUMMY_KEY", "data": "test"}"#;
-        let injected = state.secret_map.inject(json_body);
+        let injected = state.secret_map().inject(json_body);
         assert!(injected.contains("sk-proj-realkey123"));
         assert!(!injected.contains("DUMMY_KEY"));
     }
@@ -75,7 +75,7 @@ mod connect_full_unit_tests {
     fn test_response_sanitization_in_json() {
         let state = create_test_state();
         let response_body = r#"{"token": "real_secret_token", "status": "ok"}"#;
-        let sanitized = state.secret_map.sanitize(response_body);
+        let sanitized = state.secret_map().sanitize(response_body);
         assert!(!sanitized.contains("real_secret_token"));
         assert!(sanitized.contains("[REDACTED]"));
         assert!(sanitized.contains("status"));
@@ -85,7 +85,7 @@ mod connect_full_unit_tests {
     fn test_sanitization_preserves_structure() {
         let state = create_test_state();
         let response = "Before real_secret_token After";
-        let sanitized = state.secret_map.sanitize(response);
+        let sanitized = state.secret_map().sanitize(response);
         assert_eq!(sanitized, "Before [REDACTED] After");
     }
 
@@ -93,7 +93,7 @@ mod connect_full_unit_tests {
     fn test_multiple_secrets_in_same_response() {
         let state = create_test_state();
         let response = "Token: real_secret_token, Key: sk-proj-realkey123";
-        let sanitized = state.secret_map.sanitize(response);
+        let sanitized = state.secret_map().sanitize(response);
         assert_eq!(sanitized.matches("[REDACTED]").count(), 2);
         assert!(!sanitized.contains("real_secret_token"));
         assert!(!sanitized.contains("sk-proj-realkey123"));
@@ -103,7 +103,7 @@ mod connect_full_unit_tests {
     fn test_no_injection_when_no_patterns_match() {
         let state = create_test_state();
         let input = "No dummy patterns here";
-        let output = state.secret_map.inject(input);
+        let output = state.secret_map().inject(input);
         assert_eq!(output, input);
     }
 
@@ -111,7 +111,7 @@ mod connect_full_unit_tests {
     fn test_no_sanitization_when_no_secrets_match() {
         let state = create_test_state();
         let response = "No real secrets in this response";
-        let sanitized = state.secret_map.sanitize(response);
+        let sanitized = state.secret_map().sanitize(response);
         assert_eq!(sanitized, response);
     }
 
@@ -121,11 +121,11 @@ mod connect_full_unit_tests {
 
         // Inject dummy pattern
         let request = "Use DUMMY_TOKEN for auth";
-        let injected = state.secret_map.inject(request);
+        let injected = state.secret_map().inject(request);
         assert_eq!(injected, "Use real_secret_token for auth");
 
         // Sanitize the injected value
-        let sanitized = state.secret_map.sanitize(&injected);
+        let sanitized = state.secret_map().sanitize(&injected);
         assert_eq!(sanitized, "Use [REDACTED] for auth");
     }
 
@@ -135,12 +135,12 @@ mod connect_full_unit_tests {
         let state2 = state1.clone();
 
         // Both should have same secret map (Arc)
-        assert_eq!(state1.secret_map.len(), state2.secret_map.len());
+        assert_eq!(state1.secret_map().len(), state2.secret_map().len());
 
         let input = "DUMMY_TOKEN";
         assert_eq!(
-            state1.secret_map.inject(input),
-            state2.secret_map.inject(input)
+            state1.secret_map().inject(input),
+            state2.secret_map().inject(input)
         );
     }
 
@@ -150,7 +150,7 @@ mod connect_full_unit_tests {
 
         // Simulate body that will change size after injection
         let original_body = "DUMMY_TOKEN";
-        let injected_body = state.secret_map.inject(original_body);
+        let injected_body = state.secret_map().inject(original_body);
 
         // Original: "DUMMY_TOKEN" = 11 chars
         // Injected: "real_secret_token" = 17 chars
@@ -170,7 +170,7 @@ mod connect_full_unit_tests {
         ];
 
         for (_name, value) in headers {
-            let injected = state.secret_map.inject(value);
+            let injected = state.secret_map().inject(value);
             if value.contains("DUMMY_") {
                 assert_ne!(injected, value);
                 assert!(!injected.contains("DUMMY_"));
@@ -194,7 +194,7 @@ mod connect_full_unit_tests {
         ];
 
         for (_name, value) in response_headers {
-            let sanitized = state.secret_map.sanitize(value);
+            let sanitized = state.secret_map().sanitize(value);
             if value.contains("real_secret_token") || value.contains("sk-proj-realkey123") {
                 assert_ne!(sanitized, value);
                 assert!(!sanitized.contains("real_secret_token"));
@@ -255,7 +255,7 @@ mod connect_full_unit_tests {
         large_body.push_str(&"y".repeat(10000));
         large_body.push_str(" End");
 
-        let injected = state.secret_map.inject(&large_body);
+        let injected = state.secret_map().inject(&large_body);
         assert!(injected.contains("real_secret_token"));
         assert!(!injected.contains("DUMMY_TOKEN"));
         assert!(injected.len() > 20000);
@@ -266,7 +266,7 @@ mod connect_full_unit_tests {
         let state = create_test_state();
 
         let body = "First DUMMY_TOKEN then DUMMY_KEY";
-        let injected = state.secret_map.inject(body);
+        let injected = state.secret_map().inject(body);
 
         assert!(injected.contains("real_secret_token"));
         assert!(injected.contains("sk-proj-realkey123"));
@@ -279,8 +279,8 @@ mod connect_full_unit_tests {
         let state = create_test_state();
 
         let empty = "";
-        let injected = state.secret_map.inject(empty);
-        let sanitized = state.secret_map.sanitize(empty);
+        let injected = state.secret_map().inject(empty);
+        let sanitized = state.secret_map().sanitize(empty);
 
         assert_eq!(injected, empty);
         assert_eq!(sanitized, empty);
@@ -291,8 +291,8 @@ mod connect_full_unit_tests {
         let state = create_test_state();
 
         let whitespace = "   \n\t\r\n   ";
-        let injected = state.secret_map.inject(whitespace);
-        let sanitized = state.secret_map.sanitize(whitespace);
+        let injected = state.secret_map().inject(whitespace);
+        let sanitized = state.secret_map().sanitize(whitespace);
 
         assert_eq!(injected, whitespace);
         assert_eq!(sanitized, whitespace);
@@ -303,7 +303,7 @@ mod connect_full_unit_tests {
         let state = create_test_state();
 
         let body = "{\"token\":\"DUMMY_TOKEN\",\"key\":\"DUMMY_KEY\"}";
-        let injected = state.secret_map.inject(body);
+        let injected = state.secret_map().inject(body);
 
         // Should still inject within JSON
         assert!(injected.contains("real_secret_token"));
@@ -0,0 +1,67 @@
+// MITM Handshake Limit Tests
+// Kept in their own binary so the in-flight handshake gauge is not shared
+// with concurrently running tests.
+
+use slapenir_proxy::{
+    connect_full::acquire_handshake_slot,
+    metrics::MITM_HANDSHAKES_IN_FLIGHT,
+    middleware::AppState,
+    proxy::{create_http_client, ProxyConfig},
+    sanitizer::SecretMap,
+};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+fn create_state(max_handshakes: usize) -> AppState {
+    let mut secrets = HashMap::new();
+    secrets.insert("DUMMY_TOKEN".to_string(), "real_secret_123".to_string());
+    AppState::with_config(
+        Arc::new(SecretMap::new(secrets).unwrap()),
+        create_http_client(),
+        ProxyConfig {
+            max_concurrent_mitm_handshakes: max_handshakes,
+            mitm_handshake_queue_timeout: Duration::from_secs(10),
+            ..Default::default()
+        },
+    )
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_handshakes_stay_within_cap() {
+    const CAP: usize = 3;
+    let state = create_state(CAP);
+    let in_progress = Arc::new(AtomicUsize::new(0));
+    let peak = Arc::new(AtomicUsize::new(0));
+    let peak_gauge = Arc::new(AtomicUsize::new(0));
+
+    let tunnels: Vec<_> = (0..20)
+        .map(|_| {
+            let state = state.clone();
+            let in_progress = in_progress.clone();
+            let peak = peak.clone();
+            let peak_gauge = peak_gauge.clone();
+            tokio::spawn(async move {
+                let slot = acquire_handshake_slot(&state).await.unwrap();
+                let now = in_progress.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(now, Ordering::SeqCst);
+                peak_gauge.fetch_max(MITM_HANDSHAKES_IN_FLIGHT.get() as usize, Ordering::SeqCst);
+
+                // Stand-in for cert generation and the TLS handshakes
+                tokio::time::sleep(Duration::from_millis(20)).await;
+
+                in_progress.fetch_sub(1, Ordering::SeqCst);
+                drop(slot);
+            })
+        })
+        .collect();
+
+    for tunnel in tunnels {
+        tunnel.await.unwrap();
+    }
+
+    assert_eq!(peak.load(Ordering::SeqCst), CAP);
+    assert!(peak_gauge.load(Ordering::SeqCst) <= CAP);
+    assert_eq!(MITM_HANDSHAKES_IN_FLIGHT.get(), 0);
+}
@@ -0,0 +1,215 @@
+// MITM Tunnel Integration Tests
+// Drives tunnel_with_tls_mitm_full end-to-end against a local TLS upstream.
+// Kept in their own binary: the MITM CA location is read from the
+// environment once per process.
+
+use rcgen::{BasicConstraints, Certificate, CertificateParams, IsCa};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use slapenir_proxy::{
+    connect::ConnectError,
+    connect_full::{tunnel_with_tls_mitm_full, MITM_CA_CERT_ENV, MITM_CA_KEY_ENV},
+    middleware::AppState,
+    proxy::{create_http_client, ProxyConfig},
+    sanitizer::SecretMap,
+    tls::CertificateAuthority,
+};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, OnceLock};
+use tempfile::TempDir;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::{TlsAcceptor, TlsConnector};
+
+/// The MITM CA agents trust, imported by the proxy through the environment
+fn mitm_ca() -> &'static CertificateAuthority {
+    static CA: OnceLock<(TempDir, CertificateAuthority)> = OnceLock::new();
+    let (_, ca) = CA.get_or_init(|| {
+        let dir = TempDir::new().unwrap();
+        let cert = dir.path().join("ca.pem");
+        let key = dir.path().join("ca-key.pem");
+        let ca = CertificateAuthority::generate().unwrap();
+        ca.save(&cert, &key).unwrap();
+        std::env::set_var(MITM_CA_CERT_ENV, &cert);
+        std::env::set_var(MITM_CA_KEY_ENV, &key);
+        (dir, ca)
+    });
+    ca
+}
+
+/// Start a TLS upstream for `localhost`, signed by a private CA
+///
+/// Records each raw request and replies with a body echoing the real secret.
+/// Returns its address, the CA to trust, and the captured requests.
+async fn start_tls_upstream() -> (SocketAddr, CertificateDer<'static>, Arc<Mutex<Vec<String>>>) {
+    let mut ca_params = CertificateParams::new(vec![]);
+    ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    let ca = Certificate::from_params(ca_params).unwrap();
+    let leaf =
+        Certificate::from_params(CertificateParams::new(vec!["localhost".to_string()])).unwrap();
+
+    let config = rustls::ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(
+            vec![CertificateDer::from(
+                leaf.serialize_der_with_signer(&ca).unwrap(),
+            )],
+            PrivateKeyDer::try_from(leaf.serialize_private_key_der()).unwrap(),
+        )
+        .unwrap();
+    let acceptor = TlsAcceptor::from(Arc::new(config));
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let captured = Arc::new(Mutex::new(Vec::new()));
+    let sink = captured.clone();
+
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            let acceptor = acceptor.clone();
+            let sink = sink.clone();
+            tokio::spawn(async move {
+                let Ok(mut tls) = acceptor.accept(stream).await else {
+                    return;
+                };
+                let mut buf = vec![0u8; 8192];
+                let n = tls.read(&mut buf).await.unwrap_or(0);
+                sink.lock()
+                    .unwrap()
+                    .push(String::from_utf8_lossy(&buf[..n]).into_owned());
+                let body = r#"{"echo":"real_secret_123"}"#;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = tls.write_all(response.as_bytes()).await;
+                let _ = tls.shutdown().await;
+            });
+        }
+    });
+
+    (
+        addr,
+        CertificateDer::from(ca.serialize_der().unwrap()),
+        captured,
+    )
+}
+
+fn create_state(config: ProxyConfig) -> AppState {
+    let mut secrets = HashMap::new();
+    secrets.insert("DUMMY_TOKEN".to_string(), "real_secret_123".to_string());
+    AppState::with_config(
+        Arc::new(SecretMap::new(secrets).unwrap()),
+        create_http_client(),
+        config,
+    )
+}
+
+/// An agent's TLS connector, trusting only the MITM CA
+fn agent_connector(ca: &CertificateAuthority) -> TlsConnector {
+    let mut roots = rustls::RootCertStore::empty();
+    for cert in rustls_pemfile::certs(&mut ca.cert_pem().as_bytes()) {
+        roots.add(cert.unwrap()).unwrap();
+    }
+    let config = rustls::ClientConfig::builder()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    TlsConnector::from(Arc::new(config))
+}
+
+/// Send one request with a dummy token through a MITM tunnel to `upstream_addr`
+///
+/// Returns the tunnel's result and the raw response the agent received.
+async fn request_through_tunnel(
+    upstream_addr: SocketAddr,
+    state: AppState,
+) -> (Result<(), ConnectError>, String) {
+    let ca = mitm_ca();
+
+    // The agent's side of the upgraded CONNECT connection
+    let (agent_stream, proxy_stream) = tokio::io::duplex(64 * 1024);
+    let server_stream = TcpStream::connect(upstream_addr).await.unwrap();
+    let destination = format!("localhost:{}", upstream_addr.port());
+    let tunnel = tokio::spawn(async move {
+        tunnel_with_tls_mitm_full(proxy_stream, server_stream, &destination, state).await
+    });
+
+    let mut agent_tls = agent_connector(ca)
+        .connect(ServerName::try_from("localhost").unwrap(), agent_stream)
+        .await
+        .unwrap();
+    let _ = agent_tls
+        .write_all(
+            b"GET /v1/models HTTP/1.1\r\n\
+              Host: localhost\r\n\
+              Authorization: Bearer DUMMY_TOKEN\r\n\
+              Connection: close\r\n\r\n",
+        )
+        .await;
+    let mut response = Vec::new();
+    let _ = agent_tls.read_to_end(&mut response).await;
+
+    (
+        tunnel.await.unwrap(),
+        String::from_utf8_lossy(&response).into_owned(),
+    )
+}
+
+#[tokio::test]
+async fn test_dummy_token_injected_through_mitm_tunnel() {
+    let (upstream_addr, upstream_ca, captured) = start_tls_upstream().await;
+    let state = create_state(ProxyConfig {
+        upstream_ca_certs: vec![upstream_ca],
+        ..Default::default()
+    });
+
+    let (result, response) = request_through_tunnel(upstream_addr, state).await;
+
+    let sent = captured.lock().unwrap().join("");
+    assert!(
+        sent.to_ascii_lowercase()
+            .contains("authorization: bearer real_secret_123"),
+        "{}",
+        sent
+    );
+    assert!(!sent.contains("DUMMY_TOKEN"), "{}", sent);
+
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert!(response.contains("[REDACTED]"), "{}", response);
+    assert!(!response.contains("real_secret_123"), "{}", response);
+
+    result.unwrap();
+}
+
+#[tokio::test]
+async fn test_untrusted_upstream_cert_is_rejected() {
+    let (upstream_addr, _, captured) = start_tls_upstream().await;
+    // The upstream's private CA is not trusted
+    let state = create_state(ProxyConfig::default());
+
+    let (result, response) = request_through_tunnel(upstream_addr, state).await;
+
+    assert!(
+        matches!(&result, Err(ConnectError::TunnelError(e)) if e.contains("Server TLS handshake failed")),
+        "{:?}",
+        result
+    );
+    assert!(response.is_empty(), "{}", response);
+    assert!(captured.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_untrusted_upstream_cert_allowed_with_insecure_opt_out() {
+    let (upstream_addr, _, captured) = start_tls_upstream().await;
+    let state = create_state(ProxyConfig {
+        insecure_skip_upstream_verify: true,
+        ..Default::default()
+    });
+
+    let (result, response) = request_through_tunnel(upstream_addr, state).await;
+
+    result.unwrap();
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert!(!captured.lock().unwrap().is_empty());
+}
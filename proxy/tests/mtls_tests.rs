@@ -0,0 +1,225 @@
+// mTLS Listener Integration Tests
+// Connects to the TLS listener with and without a valid client certificate
+
+use axum::{extract::Request, routing::get, Extension, Router};
+use rcgen::{BasicConstraints, Certificate, CertificateParams, DnType, IsCa, SerialNumber};
+use rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName};
+use slapenir_proxy::config::NetworkConfig;
+use slapenir_proxy::mtls::{self, ClientCertInfo, MtlsConfig};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tempfile::TempDir;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::TlsConnector;
+
+/// Serial number of every client certificate issued by [`Pki`]
+const CLIENT_SERIAL: &[u8] = &[0x0a, 0x1b, 0x2c, 0x3d];
+
+/// CA, server and client certificates written to a temp dir
+struct Pki {
+    dir: TempDir,
+    ca: Certificate,
+}
+
+impl Pki {
+    fn new() -> Self {
+        let mut params = CertificateParams::new(vec![]);
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        params
+            .distinguished_name
+            .push(DnType::CommonName, "SLAPENIR Test CA");
+        let ca = Certificate::from_params(params).unwrap();
+
+        let dir = TempDir::new().unwrap();
+        std::fs::write(dir.path().join("ca.crt"), ca.serialize_pem().unwrap()).unwrap();
+
+        let server =
+            Certificate::from_params(CertificateParams::new(vec!["localhost".to_string()]))
+                .unwrap();
+        std::fs::write(
+            dir.path().join("server.crt"),
+            server.serialize_pem_with_signer(&ca).unwrap(),
+        )
+        .unwrap();
+        std::fs::write(
+            dir.path().join("server.key"),
+            server.serialize_private_key_pem(),
+        )
+        .unwrap();
+
+        Self { dir, ca }
+    }
+
+    fn mtls_config(&self, enforce: bool) -> MtlsConfig {
+        let path = |name: &str| self.dir.path().join(name).to_str().unwrap().to_string();
+        MtlsConfig::from_files(
+            &path("ca.crt"),
+            &path("server.crt"),
+            &path("server.key"),
+            enforce,
+        )
+        .unwrap()
+    }
+
+    /// A client certificate for `common_name`, signed by `signer`
+    fn client_identity(
+        signer: &Certificate,
+        common_name: &str,
+    ) -> (Vec<CertificateDer<'static>>, PrivateKeyDer<'static>) {
+        let mut params = CertificateParams::new(vec![]);
+        params
+            .distinguished_name
+            .push(DnType::CommonName, common_name);
+        params
+            .distinguished_name
+            .push(DnType::OrganizationName, "SLAPENIR");
+        params.serial_number = Some(SerialNumber::from_slice(CLIENT_SERIAL));
+        let cert = Certificate::from_params(params).unwrap();
+
+        let der = cert.serialize_der_with_signer(signer).unwrap();
+        let key = PrivateKeyDer::try_from(cert.serialize_private_key_der()).unwrap();
+        (vec![CertificateDer::from(der)], key)
+    }
+
+    fn connector(
+        &self,
+        identity: Option<(Vec<CertificateDer<'static>>, PrivateKeyDer<'static>)>,
+    ) -> TlsConnector {
+        let mut roots = rustls::RootCertStore::empty();
+        roots
+            .add(CertificateDer::from(self.ca.serialize_der().unwrap()))
+            .unwrap();
+        let builder = rustls::ClientConfig::builder().with_root_certificates(roots);
+        let config = match identity {
+            Some((certs, key)) => builder.with_client_auth_cert(certs, key).unwrap(),
+            None => builder.with_no_client_auth(),
+        };
+        TlsConnector::from(Arc::new(config))
+    }
+}
+
+/// Start the mTLS listener with a route echoing the client's identity
+async fn start_server(config: MtlsConfig) -> SocketAddr {
+    let app = Router::new()
+        .route(
+            "/whoami",
+            get(|request: Request| async move {
+                request
+                    .extensions()
+                    .get::<ClientCertInfo>()
+                    .map(|cert| {
+                        format!(
+                            "{} {} {}",
+                            cert.common_name,
+                            cert.organization.as_deref().unwrap_or("-"),
+                            cert.serial
+                        )
+                    })
+                    .unwrap_or_else(|| "anonymous".to_string())
+            }),
+        )
+        .layer(axum::middleware::from_fn(mtls::verify_client_cert))
+        .layer(Extension(config.clone()));
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(mtls::serve(listener, app, config, NetworkConfig::default()));
+    addr
+}
+
+/// Send `GET /whoami` and return the raw response
+async fn whoami(addr: SocketAddr, connector: TlsConnector) -> std::io::Result<String> {
+    let stream = TcpStream::connect(addr).await?;
+    let server_name = ServerName::try_from("localhost").unwrap();
+    let mut tls = connector.connect(server_name, stream).await?;
+
+    tls.write_all(b"GET /whoami HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\n\r\n")
+        .await?;
+    let mut response = Vec::new();
+    tls.read_to_end(&mut response).await?;
+    Ok(String::from_utf8_lossy(&response).into_owned())
+}
+
+#[tokio::test]
+async fn test_valid_client_cert_is_accepted() {
+    let pki = Pki::new();
+    let addr = start_server(pki.mtls_config(true)).await;
+
+    let identity = Pki::client_identity(&pki.ca, "agent-01");
+    let response = whoami(addr, pki.connector(Some(identity))).await.unwrap();
+
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert!(
+        response.ends_with("agent-01 SLAPENIR 0a:1b:2c:3d"),
+        "{}",
+        response
+    );
+}
+
+#[tokio::test]
+async fn test_missing_client_cert_is_rejected_when_enforced() {
+    let pki = Pki::new();
+    let addr = start_server(pki.mtls_config(true)).await;
+
+    // The server aborts the handshake with an alert; no response is sent
+    let result = whoami(addr, pki.connector(None)).await;
+    assert!(result.is_err(), "expected a TLS alert, got {:?}", result);
+}
+
+#[tokio::test]
+async fn test_untrusted_client_cert_is_rejected() {
+    let pki = Pki::new();
+    let addr = start_server(pki.mtls_config(false)).await;
+
+    // Signed by a CA the proxy does not trust
+    let mut params = CertificateParams::new(vec![]);
+    params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    let rogue_ca = Certificate::from_params(params).unwrap();
+    let identity = Pki::client_identity(&rogue_ca, "agent-01");
+
+    let result = whoami(addr, pki.connector(Some(identity))).await;
+    assert!(result.is_err(), "expected a TLS alert, got {:?}", result);
+}
+
+#[tokio::test]
+async fn test_missing_client_cert_allowed_without_enforcement() {
+    let pki = Pki::new();
+    let addr = start_server(pki.mtls_config(false)).await;
+
+    let response = whoami(addr, pki.connector(None)).await.unwrap();
+
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert!(response.ends_with("anonymous"), "{}", response);
+}
+
+#[tokio::test]
+async fn test_stalled_handshake_times_out_and_frees_its_slot() {
+    let pki = Pki::new();
+    let config = pki
+        .mtls_config(true)
+        .with_handshake_limits(std::time::Duration::from_millis(200), 1);
+    let addr = start_server(config).await;
+
+    // Holds the only handshake slot without ever sending a ClientHello
+    let mut stalled = TcpStream::connect(addr).await.unwrap();
+
+    let identity = Pki::client_identity(&pki.ca, "agent-01");
+    let started = std::time::Instant::now();
+    let response = tokio::time::timeout(
+        std::time::Duration::from_secs(5),
+        whoami(addr, pki.connector(Some(identity))),
+    )
+    .await
+    .expect("the stalled handshake should time out and free the slot")
+    .unwrap();
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    // It waited for the stalled handshake's slot
+    assert!(started.elapsed() >= std::time::Duration::from_millis(150));
+
+    let mut buffer = [0u8; 1];
+    let closed = tokio::time::timeout(std::time::Duration::from_secs(5), stalled.read(&mut buffer))
+        .await
+        .expect("the stalled connection should be closed");
+    assert!(matches!(closed, Ok(0) | Err(_)));
+}
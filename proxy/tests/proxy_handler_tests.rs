@@ -0,0 +1,2526 @@
+// Proxy Handler Integration Tests
+// Drives proxy_handler end-to-end against raw TCP mock upstreams
+//
+// Mock upstreams are addressed via 0.0.0.0 so the request takes the
+// sanitizing path instead of the localhost bypass.
+
+use axum::{
+    body::Body,
+    http::{Request, StatusCode},
+    routing::any,
+    Router,
+};
+use slapenir_proxy::{
+    config::RouteConfig,
+    dlp::{DlpAction, DlpRule},
+    metrics::{
+        BLOCKED_RESPONSE_CONTENT_TYPE_TOTAL, DEDUPLICATED_REQUESTS_TOTAL, DLP_BLOCKED_TOTAL,
+        MIXED_CREDENTIALS_TOTAL, NON_UTF8_REQUEST_BODIES_TOTAL, SANITIZED_JSON_INVALID_TOTAL,
+        UPSTREAM_CONNECTION_CLOSE_TOTAL, UPSTREAM_RETRIES_TOTAL, UPSTREAM_RETRY_OUTCOMES_TOTAL,
+        UPSTREAM_TIMEOUTS_TOTAL,
+    },
+    middleware::AppState,
+    proxy::{
+        create_http_client, proxy_handler, InvalidSanitizedJson, MixedCredentialPolicy,
+        NonUtf8BodyPolicy, ProxyConfig, ProxyIdentification, ProxyMode, RetryPolicy,
+        PROXY_PRODUCT_TOKEN,
+    },
+    sanitizer::{HeaderValueLimit, OversizedHeaderAction, SecretMap, TRUNCATED_HEADER_MARKER},
+    trace_propagation::TracePropagation,
+};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tower::util::ServiceExt;
+
+/// Start a mock upstream that writes `response` verbatim after reading the request
+async fn start_raw_upstream(response: &'static [u8]) -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let mut buf = vec![0u8; 8192];
+                let _ = stream.read(&mut buf).await;
+                let _ = stream.write_all(response).await;
+            });
+        }
+    });
+
+    port
+}
+
+/// Start a mock upstream that records each raw request and replies 200 OK
+async fn start_capturing_upstream() -> (u16, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let captured = Arc::new(Mutex::new(Vec::new()));
+    let sink = captured.clone();
+
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let sink = sink.clone();
+            tokio::spawn(async move {
+                let mut buf = vec![0u8; 8192];
+                let n = stream.read(&mut buf).await.unwrap_or(0);
+                sink.lock()
+                    .unwrap()
+                    .push(String::from_utf8_lossy(&buf[..n]).into_owned());
+                let _ = stream
+                    .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok")
+                    .await;
+            });
+        }
+    });
+
+    (port, captured)
+}
+
+fn create_app(config: ProxyConfig) -> Router {
+    let mut secrets = HashMap::new();
+    secrets.insert("DUMMY_TOKEN".to_string(), "real_secret_123".to_string());
+    let secret_map = SecretMap::new(secrets).unwrap();
+    let state = AppState::with_config(Arc::new(secret_map), create_http_client(), config);
+
+    Router::new()
+        .route("/v1/{*path}", any(proxy_handler))
+        .with_state(state)
+}
+
+fn upstream_request(port: u16) -> Request<Body> {
+    Request::builder()
+        .method("POST")
+        .uri("/v1/chat")
+        .header("x-target-url", format!("http://0.0.0.0:{}", port))
+        .body(Body::from("{}"))
+        .unwrap()
+}
+
+const EARLY_HINTS_RESPONSE: &[u8] = b"HTTP/1.1 103 Early Hints\r\n\
+Link: </style.css>; rel=preload\r\n\
+\r\n\
+HTTP/1.1 200 OK\r\n\
+Content-Length: 2\r\n\
+\r\n\
+ok";
+
+#[tokio::test]
+async fn test_early_hints_forwarded_on_final_response() {
+    let port = start_raw_upstream(EARLY_HINTS_RESPONSE).await;
+    let app = create_app(ProxyConfig::default());
+
+    let response = app.oneshot(upstream_request(port)).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get("link").unwrap(),
+        "</style.css>; rel=preload"
+    );
+    let body = axum::body::to_bytes(response.into_body(), 1024)
+        .await
+        .unwrap();
+    assert_eq!(&body[..], b"ok");
+}
+
+#[tokio::test]
+async fn test_early_hints_dropped_when_disabled() {
+    let port = start_raw_upstream(EARLY_HINTS_RESPONSE).await;
+    let app = create_app(ProxyConfig {
+        forward_early_hints: false,
+        ..Default::default()
+    });
+
+    let response = app.oneshot(upstream_request(port)).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get("link").is_none());
+}
+
+#[tokio::test]
+async fn test_continue_does_not_end_response() {
+    let port = start_raw_upstream(
+        b"HTTP/1.1 100 Continue\r\n\
+\r\n\
+HTTP/1.1 201 Created\r\n\
+Content-Length: 4\r\n\
+\r\n\
+done",
+    )
+    .await;
+    let app = create_app(ProxyConfig::default());
+
+    let response = app.oneshot(upstream_request(port)).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::CREATED);
+    let body = axum::body::to_bytes(response.into_body(), 1024)
+        .await
+        .unwrap();
+    assert_eq!(&body[..], b"done");
+}
+
+#[tokio::test]
+async fn test_target_pointing_at_proxy_is_rejected() {
+    let app = create_app(ProxyConfig {
+        listen_addr: "0.0.0.0:3000".parse().unwrap(),
+        ..Default::default()
+    });
+
+    for target in [
+        "http://localhost:3000",
+        "http://127.0.0.1:3000",
+        "http://0.0.0.0:3000",
+    ] {
+        let request = Request::builder()
+            .method("POST")
+            .uri("/v1/chat")
+            .header("x-target-url", target)
+            .body(Body::from("{}"))
+            .unwrap();
+
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN, "{}", target);
+    }
+}
+
+#[tokio::test]
+async fn test_localhost_service_on_other_port_is_forwarded() {
+    let port = start_raw_upstream(b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nlocal").await;
+    let app = create_app(ProxyConfig::default());
+
+    let request = Request::builder()
+        .method("POST")
+        .uri("/v1/chat")
+        .header("x-target-url", format!("http://localhost:{}", port))
+        .body(Body::from("{}"))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), 1024)
+        .await
+        .unwrap();
+    assert_eq!(&body[..], b"local");
+}
+
+#[tokio::test]
+async fn test_non_http_upstream_classified_as_protocol_error() {
+    use slapenir_proxy::metrics::UPSTREAM_PROTOCOL_ERRORS_TOTAL;
+
+    let port = start_raw_upstream(b"SSH-2.0-OpenSSH_9.6\r\n").await;
+    let app = create_app(ProxyConfig::default());
+    let before = UPSTREAM_PROTOCOL_ERRORS_TOTAL.get();
+
+    let response = app.oneshot(upstream_request(port)).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+    let body = axum::body::to_bytes(response.into_body(), 1024)
+        .await
+        .unwrap();
+    assert!(String::from_utf8_lossy(&body).starts_with("Upstream did not respond with valid HTTP"));
+    assert!(UPSTREAM_PROTOCOL_ERRORS_TOTAL.get() > before);
+}
+
+#[tokio::test]
+async fn test_excessive_redactions_rejected() {
+    use slapenir_proxy::metrics::EXCESSIVE_REDACTIONS_TOTAL;
+
+    let port = start_raw_upstream(
+        b"HTTP/1.1 200 OK\r\n\
+Content-Length: 47\r\n\
+\r\n\
+real_secret_123 real_secret_123 real_secret_123",
+    )
+    .await;
+    let app = create_app(ProxyConfig {
+        max_redactions_per_response: 2,
+        ..Default::default()
+    });
+    let before = EXCESSIVE_REDACTIONS_TOTAL
+        .with_label_values(&["0.0.0.0"])
+        .get();
+
+    let response = app.oneshot(upstream_request(port)).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+    let body = axum::body::to_bytes(response.into_body(), 1024)
+        .await
+        .unwrap();
+    assert!(!String::from_utf8_lossy(&body).contains("real_secret_123"));
+    assert!(!String::from_utf8_lossy(&body).contains("[REDACTED]"));
+    assert!(
+        EXCESSIVE_REDACTIONS_TOTAL
+            .with_label_values(&["0.0.0.0"])
+            .get()
+            > before
+    );
+}
+
+#[tokio::test]
+async fn test_redactions_within_limit_are_sanitized() {
+    let port = start_raw_upstream(
+        b"HTTP/1.1 200 OK\r\n\
+Content-Length: 31\r\n\
+\r\n\
+real_secret_123 real_secret_123",
+    )
+    .await;
+    let app = create_app(ProxyConfig {
+        max_redactions_per_response: 2,
+        ..Default::default()
+    });
+
+    let response = app.oneshot(upstream_request(port)).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), 1024)
+        .await
+        .unwrap();
+    assert_eq!(&body[..], b"[REDACTED] [REDACTED]");
+}
+
+fn referer_request(port: u16) -> Request<Body> {
+    Request::builder()
+        .method("POST")
+        .uri("/v1/chat")
+        .header("x-target-url", format!("http://0.0.0.0:{}", port))
+        .header("authorization", "Bearer real_secret_123")
+        .header("referer", "https://app.example.com/?token=real_secret_123")
+        .body(Body::from("{}"))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_outbound_referer_secret_redacted() {
+    let (port, captured) = start_capturing_upstream().await;
+    let app = create_app(ProxyConfig {
+        sanitize_request_headers: true,
+        ..Default::default()
+    });
+
+    let response = app.oneshot(referer_request(port)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let requests = captured.lock().unwrap();
+    let upstream = requests[0].to_lowercase();
+    assert!(upstream.contains("referer: https://app.example.com/?token=[redacted]"));
+    assert!(upstream.contains("authorization: bearer real_secret_123"));
+}
+
+#[tokio::test]
+async fn test_outbound_sanitization_keeps_strategy_inject_target_header() {
+    use slapenir_proxy::strategy::{AuthStrategy, BearerStrategy, InjectTargets};
+
+    std::env::set_var("SLAPENIR_TEST_GOOG_KEY", "goog-real-key");
+    let strategies: Vec<Box<dyn AuthStrategy>> = vec![Box::new(
+        BearerStrategy::new(
+            "gemini".to_string(),
+            "SLAPENIR_TEST_GOOG_KEY".to_string(),
+            "DUMMY_GOOG".to_string(),
+            vec![],
+        )
+        .unwrap()
+        .with_inject_targets(InjectTargets::parse(&["x-goog-api-key".to_string()]).unwrap()),
+    )];
+    let secret_map = SecretMap::from_strategies(&strategies).unwrap();
+    let state = AppState::with_config(
+        Arc::new(secret_map),
+        create_http_client(),
+        ProxyConfig {
+            sanitize_request_headers: true,
+            ..Default::default()
+        },
+    )
+    .with_strategies(strategies);
+    let app = Router::new()
+        .route("/v1/{*path}", any(proxy_handler))
+        .with_state(state);
+    let (port, captured) = start_capturing_upstream().await;
+
+    let request = Request::builder()
+        .method("POST")
+        .uri("/v1/chat")
+        .header("x-target-url", format!("http://0.0.0.0:{}", port))
+        .header("x-goog-api-key", "DUMMY_GOOG")
+        .header("referer", "https://app.example.com/?key=goog-real-key")
+        .body(Body::from("{}"))
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let requests = captured.lock().unwrap();
+    let upstream = requests[0].to_lowercase();
+    assert!(upstream.contains("x-goog-api-key: goog-real-key"));
+    assert!(upstream.contains("referer: https://app.example.com/?key=[redacted]"));
+}
+
+#[tokio::test]
+async fn test_outbound_sanitization_keeps_credentials_injected_with_default_targets() {
+    use slapenir_proxy::strategy::{AuthStrategy, BearerStrategy};
+
+    std::env::set_var("SLAPENIR_TEST_GITLAB_TOKEN", "glpat-real-token");
+    let strategies: Vec<Box<dyn AuthStrategy>> = vec![Box::new(
+        BearerStrategy::new(
+            "gitlab".to_string(),
+            "SLAPENIR_TEST_GITLAB_TOKEN".to_string(),
+            "DUMMY_GITLAB".to_string(),
+            vec![],
+        )
+        .unwrap(),
+    )];
+    let secret_map = SecretMap::from_strategies(&strategies).unwrap();
+    let state = AppState::with_config(
+        Arc::new(secret_map),
+        create_http_client(),
+        ProxyConfig {
+            sanitize_request_headers: true,
+            ..Default::default()
+        },
+    )
+    .with_strategies(strategies);
+    state
+        .register_secrets(HashMap::from([(
+            "DUMMY_RUNTIME_GOOG".to_string(),
+            "goog-runtime-key".to_string(),
+        )]))
+        .unwrap();
+    let app = Router::new()
+        .route("/v1/{*path}", any(proxy_handler))
+        .with_state(state);
+    let (port, captured) = start_capturing_upstream().await;
+
+    let request = Request::builder()
+        .method("POST")
+        .uri("/v1/chat")
+        .header("x-target-url", format!("http://0.0.0.0:{}", port))
+        .header("private-token", "DUMMY_GITLAB")
+        .header("x-goog-api-key", "DUMMY_RUNTIME_GOOG")
+        .header("referer", "https://app.example.com/?key=glpat-real-token")
+        .body(Body::from("{}"))
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let requests = captured.lock().unwrap();
+    let upstream = requests[0].to_lowercase();
+    assert!(upstream.contains("private-token: glpat-real-token"));
+    assert!(upstream.contains("x-goog-api-key: goog-runtime-key"));
+    assert!(upstream.contains("referer: https://app.example.com/?key=[redacted]"));
+}
+
+#[tokio::test]
+async fn test_outbound_header_sanitization_off_by_default() {
+    let (port, captured) = start_capturing_upstream().await;
+    let app = create_app(ProxyConfig::default());
+
+    let response = app.oneshot(referer_request(port)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let requests = captured.lock().unwrap();
+    assert!(requests[0].contains("?token=real_secret_123"));
+}
+
+const INCOMING_TRACEPARENT: &str = "00-4bf92f3577b34da6a3ce929d0e0e4736-00f067aa0ba902b7-01";
+
+/// A forwarded request, optionally carrying the agent's trace context
+fn traced_request(port: u16, traceparent: Option<&str>) -> Request<Body> {
+    let mut builder = Request::builder()
+        .method("POST")
+        .uri("/v1/chat")
+        .header("x-target-url", format!("http://0.0.0.0:{}", port))
+        .header("tracestate", "vendor=abc");
+    if let Some(traceparent) = traceparent {
+        builder = builder.header("traceparent", traceparent);
+    }
+    builder.body(Body::from("{}")).unwrap()
+}
+
+/// The `traceparent` value the upstream received
+fn forwarded_traceparent(request: &str) -> String {
+    request
+        .lines()
+        .find_map(|line| line.strip_prefix("traceparent: "))
+        .expect("forwarded request should carry a traceparent")
+        .to_string()
+}
+
+#[tokio::test]
+async fn test_forwarded_request_continues_incoming_trace() {
+    let (port, captured) = start_capturing_upstream().await;
+    let app = create_app(ProxyConfig {
+        trace_propagation: TracePropagation::W3c,
+        ..Default::default()
+    });
+
+    let response = app
+        .oneshot(traced_request(port, Some(INCOMING_TRACEPARENT)))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let requests = captured.lock().unwrap();
+    let traceparent = forwarded_traceparent(&requests[0]);
+    let parts: Vec<&str> = traceparent.split('-').collect();
+    assert_eq!(parts.len(), 4);
+    assert_eq!(parts[0], "00");
+    // Same trace, a new span for the proxy's hop, flags kept
+    assert_eq!(parts[1], "4bf92f3577b34da6a3ce929d0e0e4736");
+    assert_eq!(parts[2].len(), 16);
+    assert_ne!(parts[2], "00f067aa0ba902b7");
+    assert_eq!(parts[3], "01");
+    assert!(requests[0].contains("tracestate: vendor=abc"));
+}
+
+#[tokio::test]
+async fn test_forwarded_request_starts_trace_without_incoming() {
+    let (port, captured) = start_capturing_upstream().await;
+    let app = create_app(ProxyConfig {
+        trace_propagation: TracePropagation::W3cB3,
+        ..Default::default()
+    });
+
+    let response = app.oneshot(traced_request(port, None)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let requests = captured.lock().unwrap();
+    let traceparent = forwarded_traceparent(&requests[0]);
+    let trace = slapenir_proxy::metrics::TraceContext::from_traceparent(&traceparent)
+        .expect("traceparent should be valid");
+    assert!(requests[0].contains(&format!("b3: {}-{}-1", trace.trace_id, trace.span_id)));
+}
+
+#[tokio::test]
+async fn test_incoming_traceparent_forwarded_unchanged_when_propagation_off() {
+    let (port, captured) = start_capturing_upstream().await;
+    let app = create_app(ProxyConfig {
+        sanitize_request_headers: true,
+        ..Default::default()
+    });
+
+    let response = app
+        .oneshot(traced_request(port, Some(INCOMING_TRACEPARENT)))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let requests = captured.lock().unwrap();
+    assert_eq!(forwarded_traceparent(&requests[0]), INCOMING_TRACEPARENT);
+    assert!(requests[0].contains("tracestate: vendor=abc"));
+}
+
+#[tokio::test]
+async fn test_upstream_reason_phrase_not_copied() {
+    let port = start_raw_upstream(
+        b"HTTP/1.1 401 Bad key real_secret_123\r\n\
+Content-Length: 6\r\n\
+\r\n\
+denied",
+    )
+    .await;
+    let app = create_app(ProxyConfig::default());
+
+    let response = app.oneshot(upstream_request(port)).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    assert!(response
+        .extensions()
+        .get::<hyper::ext::ReasonPhrase>()
+        .is_none());
+}
+
+#[tokio::test]
+async fn test_unmanaged_credential_in_request_body_detected() {
+    use slapenir_proxy::metrics::UNMANAGED_CREDENTIAL_DETECTED_TOTAL;
+
+    let (port, captured) = start_capturing_upstream().await;
+    let app = create_app(ProxyConfig::default());
+    let before = UNMANAGED_CREDENTIAL_DETECTED_TOTAL
+        .with_label_values(&["sk-"])
+        .get();
+
+    let request = Request::builder()
+        .method("POST")
+        .uri("/v1/chat")
+        .header("x-target-url", format!("http://0.0.0.0:{}", port))
+        .body(Body::from(
+            r#"{"api_key": "sk-proj-9fK2mQx7LpR4vT8wZ1nB", "auth": "DUMMY_TOKEN"}"#,
+        ))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+
+    // Detection only warns; the request is still forwarded
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(captured.lock().unwrap()[0].contains("real_secret_123"));
+    assert_eq!(
+        UNMANAGED_CREDENTIAL_DETECTED_TOTAL
+            .with_label_values(&["sk-"])
+            .get(),
+        before + 1
+    );
+}
+
+#[tokio::test]
+async fn test_probe_path_answered_locally() {
+    use slapenir_proxy::metrics::HTTP_REQUESTS_TOTAL;
+
+    let (port, captured) = start_capturing_upstream().await;
+    let app = create_app(ProxyConfig {
+        probe_paths: vec!["/v1/ping".to_string()],
+        ..Default::default()
+    });
+    let before = HTTP_REQUESTS_TOTAL
+        .with_label_values(&["GET", "200", "other"])
+        .get();
+
+    let request = Request::builder()
+        .method("GET")
+        .uri("/v1/ping")
+        .header("x-target-url", format!("http://0.0.0.0:{}", port))
+        .header("authorization", "Bearer DUMMY_TOKEN")
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), 1024)
+        .await
+        .unwrap();
+    assert_eq!(&body[..], b"OK");
+    assert!(captured.lock().unwrap().is_empty());
+    assert_eq!(
+        HTTP_REQUESTS_TOTAL
+            .with_label_values(&["GET", "200", "other"])
+            .get(),
+        before
+    );
+}
+
+/// Build a raw response whose JSON body is UTF-16LE encoded
+fn utf16_json_response(json: &str) -> &'static [u8] {
+    let body: Vec<u8> = json.encode_utf16().flat_map(u16::to_le_bytes).collect();
+    let mut response = format!(
+        "HTTP/1.1 200 OK\r\n\
+Content-Type: application/json; charset=utf-16le\r\n\
+Content-Length: {}\r\n\
+\r\n",
+        body.len()
+    )
+    .into_bytes();
+    response.extend_from_slice(&body);
+    Box::leak(response.into_boxed_slice())
+}
+
+#[tokio::test]
+async fn test_utf16_response_secret_redacted() {
+    let port = start_raw_upstream(utf16_json_response(r#"{"token":"real_secret_123"}"#)).await;
+    let app = create_app(ProxyConfig::default());
+
+    let response = app.oneshot(upstream_request(port)).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "application/json; charset=utf-8"
+    );
+    let body = axum::body::to_bytes(response.into_body(), 1024)
+        .await
+        .unwrap();
+    assert_eq!(&body[..], br#"{"token":"[REDACTED]"}"#);
+}
+
+#[tokio::test]
+async fn test_utf16_response_untouched_when_normalization_disabled() {
+    let json = r#"{"token":"real_secret_123"}"#;
+    let port = start_raw_upstream(utf16_json_response(json)).await;
+    let app = create_app(ProxyConfig {
+        normalize_response_charset: false,
+        ..Default::default()
+    });
+
+    let response = app.oneshot(upstream_request(port)).await.unwrap();
+
+    assert_eq!(
+        response.headers().get("content-type").unwrap(),
+        "application/json; charset=utf-16le"
+    );
+    let body = axum::body::to_bytes(response.into_body(), 1024)
+        .await
+        .unwrap();
+    let expected: Vec<u8> = json.encode_utf16().flat_map(u16::to_le_bytes).collect();
+    assert_eq!(&body[..], &expected[..]);
+}
+
+fn user_agent_request(port: u16) -> Request<Body> {
+    Request::builder()
+        .method("POST")
+        .uri("/v1/chat")
+        .header("x-target-url", format!("http://0.0.0.0:{}", port))
+        .header("user-agent", "agent-cli/2.0")
+        .body(Body::from("{}"))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_proxy_identification_appended_to_user_agent() {
+    let (port, captured) = start_capturing_upstream().await;
+    let app = create_app(ProxyConfig {
+        proxy_identification: ProxyIdentification::UserAgent,
+        ..Default::default()
+    });
+
+    let response = app.oneshot(user_agent_request(port)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let requests = captured.lock().unwrap();
+    let upstream = requests[0].to_lowercase();
+    assert!(upstream.contains(&format!(
+        "user-agent: agent-cli/2.0 {}\r\n",
+        PROXY_PRODUCT_TOKEN
+    )));
+    assert_eq!(upstream.matches("user-agent:").count(), 1);
+    assert!(!upstream.contains("x-via-slapenir"));
+}
+
+#[tokio::test]
+async fn test_proxy_identification_header_keeps_user_agent() {
+    let (port, captured) = start_capturing_upstream().await;
+    let app = create_app(ProxyConfig {
+        proxy_identification: ProxyIdentification::Header,
+        ..Default::default()
+    });
+
+    let response = app.oneshot(user_agent_request(port)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let requests = captured.lock().unwrap();
+    let upstream = requests[0].to_lowercase();
+    assert!(upstream.contains("user-agent: agent-cli/2.0\r\n"));
+    assert!(upstream.contains(&format!("x-via-slapenir: {}", env!("CARGO_PKG_VERSION"))));
+}
+
+#[tokio::test]
+async fn test_proxy_identification_off_by_default() {
+    let (port, captured) = start_capturing_upstream().await;
+    let app = create_app(ProxyConfig::default());
+
+    let response = app.oneshot(user_agent_request(port)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let requests = captured.lock().unwrap();
+    let upstream = requests[0].to_lowercase();
+    assert!(upstream.contains("user-agent: agent-cli/2.0\r\n"));
+    assert!(!upstream.contains("slapenir"));
+}
+
+/// Build a raw 200 response with the given Content-Encoding and body bytes
+fn encoded_response(content_encoding: &str, body: &[u8]) -> &'static [u8] {
+    let mut response = format!(
+        "HTTP/1.1 200 OK\r\n\
+Content-Encoding: {}\r\n\
+Content-Length: {}\r\n\
+\r\n",
+        content_encoding,
+        body.len()
+    )
+    .into_bytes();
+    response.extend_from_slice(body);
+    Box::leak(response.into_boxed_slice())
+}
+
+#[tokio::test]
+async fn test_identity_encoded_response_sanitized() {
+    let port = start_raw_upstream(encoded_response("identity", b"key=real_secret_123")).await;
+    let app = create_app(ProxyConfig::default());
+
+    let response = app.oneshot(upstream_request(port)).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get("content-encoding").is_none());
+    let body = axum::body::to_bytes(response.into_body(), 1024)
+        .await
+        .unwrap();
+    assert_eq!(&body[..], b"key=[REDACTED]");
+}
+
+#[tokio::test]
+async fn test_gzip_and_deflate_responses_decoded_and_sanitized() {
+    use std::io::Write;
+
+    let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    gzip.write_all(b"key=real_secret_123").unwrap();
+    let mut deflate = flate2::write::ZlibEncoder::new(Vec::new(), flate2::Compression::default());
+    deflate.write_all(b"key=real_secret_123").unwrap();
+
+    for (encoding, encoded) in [
+        ("gzip", gzip.finish().unwrap()),
+        ("deflate", deflate.finish().unwrap()),
+    ] {
+        // Compressed, the secret is not visible to byte matching at all
+        assert!(!String::from_utf8_lossy(&encoded).contains("real_secret_123"));
+        let port = start_raw_upstream(encoded_response(encoding, &encoded)).await;
+        let app = create_app(ProxyConfig::default());
+
+        let response = app.oneshot(upstream_request(port)).await.unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK, "{}", encoding);
+        assert!(response.headers().get("content-encoding").is_none());
+        assert_eq!(response.headers().get("content-length").unwrap(), "14");
+        let body = axum::body::to_bytes(response.into_body(), 1024)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"key=[REDACTED]", "{}", encoding);
+    }
+}
+
+#[tokio::test]
+async fn test_gzip_br_chain_decoded_and_sanitized() {
+    use std::io::Write;
+
+    let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    gzip.write_all(b"key=real_secret_123").unwrap();
+    let gzipped = gzip.finish().unwrap();
+    let mut encoded = Vec::new();
+    brotli::BrotliCompress(&mut &gzipped[..], &mut encoded, &Default::default()).unwrap();
+
+    let port = start_raw_upstream(encoded_response("gzip, br", &encoded)).await;
+    let app = create_app(ProxyConfig::default());
+
+    let response = app.oneshot(upstream_request(port)).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get("content-encoding").is_none());
+    let body = axum::body::to_bytes(response.into_body(), 1024)
+        .await
+        .unwrap();
+    assert_eq!(&body[..], b"key=[REDACTED]");
+}
+
+#[tokio::test]
+async fn test_corrupt_encoded_response_rejected() {
+    // Claims gzip, but is the plaintext secret
+    let port = start_raw_upstream(encoded_response("gzip", b"key=real_secret_123")).await;
+    let app = create_app(ProxyConfig::default());
+
+    let response = app.oneshot(upstream_request(port)).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+    let body = axum::body::to_bytes(response.into_body(), 1024)
+        .await
+        .unwrap();
+    assert!(!String::from_utf8_lossy(&body).contains("real_secret_123"));
+}
+
+#[tokio::test]
+async fn test_encoded_response_decoding_past_limit_rejected() {
+    use std::io::Write;
+
+    let mut gzip = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::best());
+    gzip.write_all(&vec![b'a'; 64 * 1024]).unwrap();
+    let encoded = gzip.finish().unwrap();
+    let port = start_raw_upstream(encoded_response("gzip", &encoded)).await;
+    let app = create_app(ProxyConfig {
+        max_response_size: 4096,
+        ..Default::default()
+    });
+
+    let response = app.oneshot(upstream_request(port)).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+}
+
+#[tokio::test]
+async fn test_unknown_encoding_flagged_as_unscanned() {
+    use slapenir_proxy::metrics::UNSCANNED_BODY_TOTAL;
+
+    let port = start_raw_upstream(encoded_response("lzma", b"\x5d\x00\x00opaque")).await;
+    let app = create_app(ProxyConfig::default());
+    let before = UNSCANNED_BODY_TOTAL
+        .with_label_values(&["unsupported_encoding"])
+        .get();
+
+    let response = app.oneshot(upstream_request(port)).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    // Still labelled as encoded: the proxy does not pretend it decoded the body
+    assert_eq!(response.headers().get("content-encoding").unwrap(), "lzma");
+    assert!(
+        UNSCANNED_BODY_TOTAL
+            .with_label_values(&["unsupported_encoding"])
+            .get()
+            > before
+    );
+}
+
+#[tokio::test]
+async fn test_rewrite_rule_changes_forwarded_path_and_method() {
+    use slapenir_proxy::proxy::RewriteRule;
+
+    let (port, captured) = start_capturing_upstream().await;
+    let app = create_app(ProxyConfig {
+        rewrite_rules: vec![RewriteRule::new(r"^/v1/chat$", "/chat/completions")
+            .unwrap()
+            .with_method(axum::http::Method::PUT)],
+        ..Default::default()
+    });
+
+    let response = app.oneshot(upstream_request(port)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let requests = captured.lock().unwrap();
+    assert!(
+        requests[0].starts_with("PUT /chat/completions HTTP/1.1\r\n"),
+        "unexpected request line: {}",
+        requests[0].lines().next().unwrap_or_default()
+    );
+}
+
+#[tokio::test]
+async fn test_rewritten_target_still_checked_against_denylist() {
+    use slapenir_proxy::proxy::RewriteRule;
+
+    let mut secrets = HashMap::new();
+    secrets.insert("DUMMY_TOKEN".to_string(), "real_secret_123".to_string());
+    let state = AppState::with_config(
+        Arc::new(SecretMap::new(secrets).unwrap()),
+        create_http_client(),
+        ProxyConfig {
+            rewrite_rules: vec![RewriteRule::new(r"^/v1/chat$", "/chat/completions").unwrap()],
+            ..Default::default()
+        },
+    );
+    state.set_denied_hosts(vec!["0.0.0.0".to_string()]).unwrap();
+    let app = Router::new()
+        .route("/v1/{*path}", any(proxy_handler))
+        .with_state(state);
+
+    let response = app.oneshot(upstream_request(1)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+}
+
+/// Build a raw redirect response pointing at `location`
+fn redirect_response(status: &str, location: &str) -> &'static [u8] {
+    let response = format!(
+        "HTTP/1.1 {}\r\n\
+Location: {}\r\n\
+Content-Length: 0\r\n\
+\r\n",
+        status, location
+    );
+    Box::leak(response.into_bytes().into_boxed_slice())
+}
+
+/// App whose `DUMMY_TOKEN` credential is only allowed for 0.0.0.0
+fn create_whitelisted_app(config: ProxyConfig) -> Router {
+    use slapenir_proxy::strategy::BearerStrategy;
+
+    let mut secrets = HashMap::new();
+    secrets.insert("DUMMY_TOKEN".to_string(), "real_secret_123".to_string());
+    let strategy = BearerStrategy::new(
+        "test".to_string(),
+        "SLAPENIR_TEST_REDIRECT_TOKEN".to_string(),
+        "DUMMY_TOKEN".to_string(),
+        vec!["0.0.0.0".to_string()],
+    )
+    .unwrap();
+    let state = AppState::with_config(
+        Arc::new(SecretMap::new(secrets).unwrap()),
+        create_http_client(),
+        config,
+    )
+    .with_strategies(vec![Box::new(strategy)]);
+
+    Router::new()
+        .route("/v1/{*path}", any(proxy_handler))
+        .with_state(state)
+}
+
+fn credential_request(port: u16) -> Request<Body> {
+    Request::builder()
+        .method("POST")
+        .uri("/v1/chat")
+        .header("x-target-url", format!("http://0.0.0.0:{}", port))
+        .body(Body::from(r#"{"key":"DUMMY_TOKEN"}"#))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_redirect_passed_through_by_default() {
+    let port = start_raw_upstream(redirect_response("302 Found", "http://0.0.0.0:1/next")).await;
+    let app = create_app(ProxyConfig::default());
+
+    let response = app.oneshot(upstream_request(port)).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::FOUND);
+    assert_eq!(
+        response.headers().get("location").unwrap(),
+        "http://0.0.0.0:1/next"
+    );
+}
+
+#[tokio::test]
+async fn test_redirect_to_allowed_host_followed_with_injection() {
+    let (final_port, captured) = start_capturing_upstream().await;
+    let location = format!("http://0.0.0.0:{}/v1/final", final_port);
+    let port = start_raw_upstream(redirect_response(
+        "307 Temporary Redirect",
+        Box::leak(location.into_boxed_str()),
+    ))
+    .await;
+    let app = create_whitelisted_app(ProxyConfig {
+        follow_redirects: true,
+        ..Default::default()
+    });
+
+    let response = app.oneshot(credential_request(port)).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), 1024)
+        .await
+        .unwrap();
+    assert_eq!(&body[..], b"ok");
+
+    // 307 keeps method and body, with the credential injected again
+    let requests = captured.lock().unwrap();
+    assert_eq!(requests.len(), 1);
+    assert!(requests[0].starts_with("POST /v1/final HTTP/1.1\r\n"));
+    assert!(requests[0].contains(r#"{"key":"real_secret_123"}"#));
+}
+
+#[tokio::test]
+async fn test_redirect_to_non_whitelisted_host_refused() {
+    let (final_port, captured) = start_capturing_upstream().await;
+    let location = format!("http://127.0.0.1:{}/v1/final", final_port);
+    let port = start_raw_upstream(redirect_response(
+        "302 Found",
+        Box::leak(location.into_boxed_str()),
+    ))
+    .await;
+    let app = create_whitelisted_app(ProxyConfig {
+        follow_redirects: true,
+        ..Default::default()
+    });
+    let blocked = slapenir_proxy::metrics::HOST_VALIDATION_BLOCKED_TOTAL
+        .with_label_values(&["test", slapenir_proxy::metrics::IP_HOST_LABEL]);
+    let before = blocked.get();
+
+    let response = app.oneshot(credential_request(port)).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert!(captured.lock().unwrap().is_empty());
+    assert_eq!(blocked.get(), before + 1);
+}
+
+#[tokio::test]
+async fn test_redirect_loop_stops_at_max_redirects() {
+    let port = start_raw_upstream(redirect_response("302 Found", "/v1/again")).await;
+    let app = create_app(ProxyConfig {
+        follow_redirects: true,
+        max_redirects: 2,
+        ..Default::default()
+    });
+
+    let response = app.oneshot(upstream_request(port)).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+}
+
+fn request_from(port: u16, ip: [u8; 4]) -> Request<Body> {
+    let mut request = upstream_request(port);
+    request
+        .extensions_mut()
+        .insert(axum::extract::ConnectInfo(std::net::SocketAddr::from((
+            ip, 40000,
+        ))));
+    request
+}
+
+fn quota_app(window: std::time::Duration) -> Router {
+    // One exchange costs 4 bytes: a 2-byte request and a 2-byte response
+    create_app(ProxyConfig {
+        byte_quota: Some(slapenir_proxy::quota::ByteQuotaConfig {
+            max_bytes: 4,
+            window,
+        }),
+        ..Default::default()
+    })
+}
+
+#[tokio::test]
+async fn test_identity_over_byte_quota_throttled() {
+    use slapenir_proxy::metrics::QUOTA_EXCEEDED_TOTAL;
+
+    let (port, _) = start_capturing_upstream().await;
+    let app = quota_app(std::time::Duration::from_secs(60));
+    let exceeded = QUOTA_EXCEEDED_TOTAL.with_label_values(&["10.9.0.1"]);
+    let before = exceeded.get();
+
+    let first = app
+        .clone()
+        .oneshot(request_from(port, [10, 9, 0, 1]))
+        .await
+        .unwrap();
+    assert_eq!(first.status(), StatusCode::OK);
+    let _ = axum::body::to_bytes(first.into_body(), 1024).await;
+
+    let throttled = app
+        .clone()
+        .oneshot(request_from(port, [10, 9, 0, 1]))
+        .await
+        .unwrap();
+    assert_eq!(throttled.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(exceeded.get(), before + 1);
+
+    let fresh = app
+        .oneshot(request_from(port, [10, 9, 0, 2]))
+        .await
+        .unwrap();
+    assert_eq!(fresh.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_byte_quota_window_resets() {
+    let (port, _) = start_capturing_upstream().await;
+    let app = quota_app(std::time::Duration::from_millis(200));
+
+    let first = app
+        .clone()
+        .oneshot(request_from(port, [10, 9, 1, 1]))
+        .await
+        .unwrap();
+    assert_eq!(first.status(), StatusCode::OK);
+    let throttled = app
+        .clone()
+        .oneshot(request_from(port, [10, 9, 1, 1]))
+        .await
+        .unwrap();
+    assert_eq!(throttled.status(), StatusCode::TOO_MANY_REQUESTS);
+
+    tokio::time::sleep(std::time::Duration::from_millis(250)).await;
+    let after_window = app
+        .oneshot(request_from(port, [10, 9, 1, 1]))
+        .await
+        .unwrap();
+    assert_eq!(after_window.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_burst_allowed_then_rate_limited() {
+    use slapenir_proxy::metrics::RATE_LIMITED_TOTAL;
+    use slapenir_proxy::rate_limit::RateLimitConfig;
+
+    let (port, captured) = start_capturing_upstream().await;
+    let app = create_app(ProxyConfig {
+        rate_limit: Some(RateLimitConfig {
+            requests_per_second: 1,
+            burst: 3,
+        }),
+        ..Default::default()
+    });
+    let limited = RATE_LIMITED_TOTAL.with_label_values(&["http"]);
+    let before = limited.get();
+
+    for _ in 0..3 {
+        let response = app
+            .clone()
+            .oneshot(request_from(port, [10, 9, 2, 1]))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    let throttled = app
+        .clone()
+        .oneshot(request_from(port, [10, 9, 2, 1]))
+        .await
+        .unwrap();
+    assert_eq!(throttled.status(), StatusCode::TOO_MANY_REQUESTS);
+    assert_eq!(throttled.headers().get("retry-after").unwrap(), "1");
+    assert!(limited.get() > before);
+    // Refused before reaching the upstream
+    assert_eq!(captured.lock().unwrap().len(), 3);
+
+    let other = app
+        .oneshot(request_from(port, [10, 9, 2, 2]))
+        .await
+        .unwrap();
+    assert_eq!(other.status(), StatusCode::OK);
+}
+
+const SECRET_RESPONSE: &[u8] = b"HTTP/1.1 200 OK\r\n\
+Content-Type: application/json\r\n\
+Content-Length: 27\r\n\
+\r\n\
+{\"key\":\"real_secret_123\"}  ";
+
+fn accept_encoding_request(port: u16, accept_encoding: &str) -> Request<Body> {
+    let mut request = upstream_request(port);
+    request
+        .headers_mut()
+        .insert("accept-encoding", accept_encoding.parse().unwrap());
+    request
+}
+
+#[tokio::test]
+async fn test_sanitized_body_compressed_for_gzip_client() {
+    use std::io::Read;
+
+    let port = start_raw_upstream(SECRET_RESPONSE).await;
+    let app = create_app(ProxyConfig {
+        compress_responses: true,
+        ..Default::default()
+    });
+
+    let response = app
+        .oneshot(accept_encoding_request(port, "gzip"))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers().get("content-encoding").unwrap(), "gzip");
+    assert_eq!(response.headers().get("vary").unwrap(), "Accept-Encoding");
+    let content_length: usize = response.headers()["content-length"]
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap();
+    let body = axum::body::to_bytes(response.into_body(), 1024)
+        .await
+        .unwrap();
+    assert_eq!(body.len(), content_length);
+
+    let mut decoded = String::new();
+    flate2::read::GzDecoder::new(&body[..])
+        .read_to_string(&mut decoded)
+        .unwrap();
+    assert_eq!(decoded, "{\"key\":\"[REDACTED]\"}  ");
+}
+
+#[tokio::test]
+async fn test_sanitized_body_uncompressed_without_accept_encoding() {
+    let port = start_raw_upstream(SECRET_RESPONSE).await;
+    let app = create_app(ProxyConfig {
+        compress_responses: true,
+        ..Default::default()
+    });
+
+    let response = app
+        .oneshot(accept_encoding_request(port, "identity"))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get("content-encoding").is_none());
+    let body = axum::body::to_bytes(response.into_body(), 1024)
+        .await
+        .unwrap();
+    assert_eq!(&body[..], b"{\"key\":\"[REDACTED]\"}  ");
+}
+
+#[tokio::test]
+async fn test_compression_off_by_default() {
+    let port = start_raw_upstream(SECRET_RESPONSE).await;
+    let app = create_app(ProxyConfig::default());
+
+    let response = app
+        .oneshot(accept_encoding_request(port, "gzip, br"))
+        .await
+        .unwrap();
+
+    assert!(response.headers().get("content-encoding").is_none());
+    assert!(response.headers().get("vary").is_none());
+}
+
+const OVERSIZED_HEADER_RESPONSE: &[u8] = b"HTTP/1.1 200 OK\r\n\
+X-Huge: zzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzzz\r\n\
+Content-Length: 2\r\n\
+\r\n\
+ok";
+
+fn header_limit_app(action: OversizedHeaderAction) -> Router {
+    create_app(ProxyConfig {
+        header_value_limit: HeaderValueLimit {
+            max_len: 64,
+            action,
+        },
+        ..Default::default()
+    })
+}
+
+#[tokio::test]
+async fn test_oversized_header_truncated() {
+    let port = start_raw_upstream(OVERSIZED_HEADER_RESPONSE).await;
+    let app = header_limit_app(OversizedHeaderAction::Truncate);
+
+    let response = app.oneshot(upstream_request(port)).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let huge = response.headers().get("x-huge").unwrap().to_str().unwrap();
+    assert_eq!(huge.len(), 64);
+    assert!(huge.ends_with(TRUNCATED_HEADER_MARKER));
+}
+
+#[tokio::test]
+async fn test_oversized_header_rejects_response() {
+    let port = start_raw_upstream(OVERSIZED_HEADER_RESPONSE).await;
+    let app = header_limit_app(OversizedHeaderAction::Reject);
+
+    let response = app.oneshot(upstream_request(port)).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+    assert!(response.headers().get("x-huge").is_none());
+}
+
+fn body_request(
+    port: u16,
+    method: &str,
+    content_length: Option<&str>,
+    body: &'static str,
+) -> Request<Body> {
+    let mut builder = Request::builder()
+        .method(method)
+        .uri("/v1/chat")
+        .header("x-target-url", format!("http://0.0.0.0:{}", port));
+    if let Some(len) = content_length {
+        builder = builder.header("content-length", len);
+    }
+    builder.body(Body::from(body)).unwrap()
+}
+
+#[tokio::test]
+async fn test_get_without_body_forwarded_without_content_length() {
+    let (port, captured) = start_capturing_upstream().await;
+    let app = create_app(ProxyConfig::default());
+
+    let response = app
+        .oneshot(body_request(port, "GET", None, ""))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let requests = captured.lock().unwrap();
+    assert!(!requests[0].to_lowercase().contains("content-length"));
+}
+
+#[tokio::test]
+async fn test_empty_post_keeps_content_length() {
+    let (port, captured) = start_capturing_upstream().await;
+    let app = create_app(ProxyConfig::default());
+
+    let response = app
+        .oneshot(body_request(port, "POST", Some("0"), ""))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let requests = captured.lock().unwrap();
+    assert!(requests[0].to_lowercase().contains("content-length: 0\r\n"));
+}
+
+#[tokio::test]
+async fn test_injected_body_content_length_recomputed() {
+    let (port, captured) = start_capturing_upstream().await;
+    let app = create_app(ProxyConfig::default());
+
+    let response = app
+        .oneshot(body_request(port, "POST", Some("11"), "DUMMY_TOKEN"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let requests = captured.lock().unwrap();
+    let upstream = requests[0].to_lowercase();
+    assert!(upstream.contains("content-length: 15\r\n"));
+    assert!(!upstream.contains("content-length: 11"));
+}
+
+const HTML_RESPONSE: &[u8] = b"HTTP/1.1 200 OK\r\n\
+Content-Type: text/html\r\n\
+Content-Length: 13\r\n\
+\r\n\
+<html></html>";
+
+const JSON_RESPONSE: &[u8] = b"HTTP/1.1 200 OK\r\n\
+Content-Type: application/json\r\n\
+Content-Length: 2\r\n\
+\r\n\
+{}";
+
+fn json_only_app() -> Router {
+    create_app(ProxyConfig {
+        allowed_response_content_types: vec!["application/json".to_string()],
+        ..Default::default()
+    })
+}
+
+#[tokio::test]
+async fn test_allowed_content_type_passes() {
+    let port = start_raw_upstream(JSON_RESPONSE).await;
+
+    let response = json_only_app()
+        .oneshot(upstream_request(port))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), 1024)
+        .await
+        .unwrap();
+    assert_eq!(&body[..], b"{}");
+}
+
+#[tokio::test]
+async fn test_disallowed_content_type_blocked() {
+    let port = start_raw_upstream(HTML_RESPONSE).await;
+    let blocked = BLOCKED_RESPONSE_CONTENT_TYPE_TOTAL.with_label_values(&["text/html"]);
+    let before = blocked.get();
+
+    let response = json_only_app()
+        .oneshot(upstream_request(port))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::BAD_GATEWAY);
+    assert!(response.headers().get("content-type").unwrap() != "text/html");
+    let body = axum::body::to_bytes(response.into_body(), 1024)
+        .await
+        .unwrap();
+    assert!(!String::from_utf8_lossy(&body).contains("<html>"));
+    assert!(blocked.get() > before);
+}
+
+#[tokio::test]
+async fn test_content_types_unrestricted_by_default() {
+    let port = start_raw_upstream(HTML_RESPONSE).await;
+    let app = create_app(ProxyConfig::default());
+
+    let response = app.oneshot(upstream_request(port)).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+fn sanitize_only_app() -> Router {
+    create_app(ProxyConfig {
+        mode: ProxyMode::SanitizeOnly,
+        ..Default::default()
+    })
+}
+
+#[tokio::test]
+async fn test_sanitize_only_mode_redacts_outbound_without_injecting() {
+    let (port, captured) = start_capturing_upstream().await;
+
+    let response = sanitize_only_app()
+        .oneshot(body_request(
+            port,
+            "POST",
+            None,
+            "DUMMY_TOKEN leaked real_secret_123",
+        ))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let requests = captured.lock().unwrap();
+    assert!(requests[0].ends_with("\r\n\r\nDUMMY_TOKEN leaked [REDACTED]"));
+    assert!(!requests[0].contains("real_secret_123"));
+}
+
+#[tokio::test]
+async fn test_sanitize_only_mode_redacts_outbound_headers_and_query() {
+    let (port, captured) = start_capturing_upstream().await;
+
+    let request = Request::builder()
+        .method("GET")
+        .uri("/v1/chat?key=real_secret_123&q=1")
+        .header("x-target-url", format!("http://0.0.0.0:{}", port))
+        .header("authorization", "Bearer real_secret_123")
+        .header("x-custom", "DUMMY_TOKEN real_secret_123")
+        .body(Body::empty())
+        .unwrap();
+    let response = sanitize_only_app().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let requests = captured.lock().unwrap();
+    assert!(requests[0].starts_with("GET /v1/chat?key=[REDACTED]&q=1 "));
+    assert!(requests[0].contains("authorization: Bearer [REDACTED]"));
+    assert!(requests[0].contains("x-custom: DUMMY_TOKEN [REDACTED]"));
+    assert!(!requests[0].contains("real_secret_123"));
+}
+
+#[tokio::test]
+async fn test_sanitize_only_mode_sanitizes_response() {
+    let port = start_raw_upstream(
+        b"HTTP/1.1 200 OK\r\n\
+Content-Length: 21\r\n\
+\r\n\
+token=real_secret_123",
+    )
+    .await;
+
+    let response = sanitize_only_app()
+        .oneshot(upstream_request(port))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), 1024)
+        .await
+        .unwrap();
+    assert_eq!(&body[..], b"token=[REDACTED]");
+}
+
+#[tokio::test]
+async fn test_injection_disabled_route_forwards_body_unchanged() {
+    let app = create_app(ProxyConfig {
+        routes: vec![RouteConfig {
+            path: "/v1/health".to_string(),
+            inject: false,
+        }],
+        ..Default::default()
+    });
+
+    let (port, captured) = start_capturing_upstream().await;
+    let request = Request::builder()
+        .method("POST")
+        .uri("/v1/health")
+        .header("x-target-url", format!("http://0.0.0.0:{}", port))
+        .body(Body::from("token=DUMMY_TOKEN"))
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let (port, injected) = start_capturing_upstream().await;
+    let response = app
+        .oneshot(body_request(port, "POST", None, "token=DUMMY_TOKEN"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    assert!(captured.lock().unwrap()[0].ends_with("\r\n\r\ntoken=DUMMY_TOKEN"));
+    assert!(injected.lock().unwrap()[0].ends_with("\r\n\r\ntoken=real_secret_123"));
+}
+
+#[tokio::test]
+async fn test_configured_request_limit_rejects_larger_body() {
+    let (port, captured) = start_capturing_upstream().await;
+    let app = create_app(ProxyConfig {
+        max_request_size: 16,
+        ..Default::default()
+    });
+
+    let response = app
+        .oneshot(body_request(
+            port,
+            "POST",
+            None,
+            "a request body longer than sixteen bytes",
+        ))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    assert!(captured.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_configured_request_limit_allows_body_within_limit() {
+    let (port, _captured) = start_capturing_upstream().await;
+    let app = create_app(ProxyConfig {
+        max_request_size: 16,
+        ..Default::default()
+    });
+
+    let response = app
+        .oneshot(body_request(port, "POST", None, "short"))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_configured_response_limit_rejects_larger_body() {
+    let port = start_raw_upstream(
+        b"HTTP/1.1 200 OK\r\n\
+Content-Length: 32\r\n\
+\r\n\
+0123456789abcdef0123456789abcdef",
+    )
+    .await;
+    let app = create_app(ProxyConfig {
+        max_response_size: 16,
+        ..Default::default()
+    });
+
+    let response = app.oneshot(upstream_request(port)).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+}
+
+const ENTITY_ENCODED_SECRET_PAGE: &[u8] = b"HTTP/1.1 500 Internal Server Error\r\n\
+Content-Type: text/html; charset=utf-8\r\n\
+Content-Length: 50\r\n\
+\r\n\
+<p>Bad key: real&#95;secret&#x5f;123&amp;more</p>\n";
+
+#[tokio::test]
+async fn test_html_entity_encoded_secret_redacted_when_enabled() {
+    let port = start_raw_upstream(ENTITY_ENCODED_SECRET_PAGE).await;
+    let app = create_app(ProxyConfig {
+        decode_html_entities: true,
+        ..Default::default()
+    });
+
+    let response = app.oneshot(upstream_request(port)).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    let body = axum::body::to_bytes(response.into_body(), 1024)
+        .await
+        .unwrap();
+    assert_eq!(&body[..], b"<p>Bad key: [REDACTED]&amp;more</p>\n");
+}
+
+#[tokio::test]
+async fn test_html_entity_decoding_is_opt_in_and_size_bounded() {
+    for config in [
+        ProxyConfig::default(),
+        ProxyConfig {
+            decode_html_entities: true,
+            max_html_entity_decode_size: 16,
+            ..Default::default()
+        },
+    ] {
+        let port = start_raw_upstream(ENTITY_ENCODED_SECRET_PAGE).await;
+        let response = create_app(config)
+            .oneshot(upstream_request(port))
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), 1024)
+            .await
+            .unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("real&#95;secret"));
+    }
+}
+
+fn dlp_app() -> Router {
+    create_app(ProxyConfig {
+        dlp_rules: vec![
+            DlpRule::new("ssn", r"\b\d{3}-\d{2}-\d{4}\b").unwrap(),
+            DlpRule::new("card", r"\b4\d{15}\b")
+                .unwrap()
+                .with_action(DlpAction::Redact),
+        ],
+        ..Default::default()
+    })
+}
+
+#[tokio::test]
+async fn test_dlp_rule_blocks_matching_body() {
+    let (port, captured) = start_capturing_upstream().await;
+    let blocked = DLP_BLOCKED_TOTAL.with_label_values(&["ssn"]);
+    let before = blocked.get();
+
+    let response = dlp_app()
+        .oneshot(body_request(port, "POST", None, "my ssn is 123-45-6789"))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let body = axum::body::to_bytes(response.into_body(), 1024)
+        .await
+        .unwrap();
+    let body = String::from_utf8_lossy(&body);
+    assert!(body.contains("ssn"));
+    assert!(!body.contains("123-45-6789"));
+    assert!(captured.lock().unwrap().is_empty());
+    assert!(blocked.get() > before);
+}
+
+#[tokio::test]
+async fn test_dlp_clean_body_passes_and_redact_rule_forwards() {
+    let (port, captured) = start_capturing_upstream().await;
+    let response = dlp_app()
+        .oneshot(body_request(port, "POST", None, "token=DUMMY_TOKEN"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(captured.lock().unwrap()[0].ends_with("\r\n\r\ntoken=real_secret_123"));
+
+    let (port, captured) = start_capturing_upstream().await;
+    let response = dlp_app()
+        .oneshot(body_request(port, "POST", None, "card 4111111111111111 ok"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(captured.lock().unwrap()[0].ends_with("\r\n\r\ncard [DLP_REDACTED] ok"));
+}
+
+#[tokio::test]
+async fn test_injection_allowlist_blocks_host_outside_global_set() {
+    let (port, captured) = start_capturing_upstream().await;
+    let app = create_app(ProxyConfig {
+        injection_allowed_hosts: vec!["api.openai.com".to_string()],
+        ..Default::default()
+    });
+
+    let response = app
+        .clone()
+        .oneshot(body_request(port, "POST", None, "token=DUMMY_TOKEN"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert!(captured.lock().unwrap().is_empty());
+
+    // Nothing to inject, so the global set does not apply
+    let response = app
+        .oneshot(body_request(port, "POST", None, "hello"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_injection_allowlist_allows_listed_host() {
+    let (port, captured) = start_capturing_upstream().await;
+    let response = create_app(ProxyConfig {
+        injection_allowed_hosts: vec!["0.0.0.0".to_string()],
+        ..Default::default()
+    })
+    .oneshot(body_request(port, "POST", None, "token=DUMMY_TOKEN"))
+    .await
+    .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(captured.lock().unwrap()[0].ends_with("\r\n\r\ntoken=real_secret_123"));
+}
+
+/// App with an OpenAI key only allowed for api.openai.com and a local key allowed for 0.0.0.0
+fn create_two_strategy_app() -> Router {
+    use slapenir_proxy::strategy::{AuthStrategy, BearerStrategy};
+
+    std::env::set_var("SLAPENIR_TEST_HOSTS_OPENAI", "sk-openai-real");
+    std::env::set_var("SLAPENIR_TEST_HOSTS_LOCAL", "local-real-key");
+    let strategies: Vec<Box<dyn AuthStrategy>> = vec![
+        Box::new(
+            BearerStrategy::new(
+                "openai".to_string(),
+                "SLAPENIR_TEST_HOSTS_OPENAI".to_string(),
+                "DUMMY_HOSTS_OPENAI".to_string(),
+                vec!["api.openai.com".to_string()],
+            )
+            .unwrap(),
+        ),
+        Box::new(
+            BearerStrategy::new(
+                "local".to_string(),
+                "SLAPENIR_TEST_HOSTS_LOCAL".to_string(),
+                "DUMMY_HOSTS_LOCAL".to_string(),
+                vec!["0.0.0.0".to_string()],
+            )
+            .unwrap(),
+        ),
+    ];
+    let secret_map = SecretMap::from_strategies(&strategies).unwrap();
+    let state = AppState::with_config(
+        Arc::new(secret_map),
+        create_http_client(),
+        ProxyConfig::default(),
+    )
+    .with_strategies(strategies);
+
+    Router::new()
+        .route("/v1/{*path}", any(proxy_handler))
+        .with_state(state)
+}
+
+#[tokio::test]
+async fn test_strategy_dummy_refused_for_another_strategys_host() {
+    // No global injection allowlist, so each strategy's hosts decide alone
+    let (port, captured) = start_capturing_upstream().await;
+    let app = create_two_strategy_app();
+
+    let response = app
+        .clone()
+        .oneshot(body_request(port, "POST", None, "key=DUMMY_HOSTS_OPENAI"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert!(captured.lock().unwrap().is_empty());
+
+    let response = app
+        .oneshot(body_request(port, "POST", None, "key=DUMMY_HOSTS_LOCAL"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(captured.lock().unwrap()[0].ends_with("\r\n\r\nkey=local-real-key"));
+}
+
+#[tokio::test]
+async fn test_egress_allowlist_blocks_unlisted_host_before_injection() {
+    let (port, captured) = start_capturing_upstream().await;
+    let response = create_app(ProxyConfig {
+        egress_allowed_hosts: vec!["api.openai.com".to_string(), "*.anthropic.com".to_string()],
+        ..Default::default()
+    })
+    .oneshot(body_request(port, "POST", None, "token=DUMMY_TOKEN"))
+    .await
+    .unwrap();
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    let body = axum::body::to_bytes(response.into_body(), 1024)
+        .await
+        .unwrap();
+    // Refused by the egress check itself, not by the later injection checks
+    assert_eq!(&body[..], b"Host not in egress allowlist: 0.0.0.0");
+    assert!(captured.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_egress_allowlist_allows_listed_host() {
+    let (port, captured) = start_capturing_upstream().await;
+    let response = create_app(ProxyConfig {
+        egress_allowed_hosts: vec!["0.0.0.0".to_string()],
+        ..Default::default()
+    })
+    .oneshot(body_request(port, "POST", None, "token=DUMMY_TOKEN"))
+    .await
+    .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(captured.lock().unwrap()[0].ends_with("\r\n\r\ntoken=real_secret_123"));
+}
+
+const MIXED_CREDENTIAL_BODY: &str =
+    r#"{"api_key": "sk-proj-9fK2mQx7LpR4vT8wZ1nB", "auth": "DUMMY_TOKEN"}"#;
+
+async fn send_mixed_credentials(
+    policy: MixedCredentialPolicy,
+) -> (StatusCode, Arc<Mutex<Vec<String>>>) {
+    let (port, captured) = start_capturing_upstream().await;
+    let response = create_app(ProxyConfig {
+        mixed_credential_policy: policy,
+        ..Default::default()
+    })
+    .oneshot(body_request(port, "POST", None, MIXED_CREDENTIAL_BODY))
+    .await
+    .unwrap();
+    (response.status(), captured)
+}
+
+#[tokio::test]
+async fn test_mixed_credentials_warn_forwards_unchanged() {
+    let warned = MIXED_CREDENTIALS_TOTAL.with_label_values(&["warn"]);
+    let before = warned.get();
+
+    let (status, captured) = send_mixed_credentials(MixedCredentialPolicy::Warn).await;
+
+    assert_eq!(status, StatusCode::OK);
+    let forwarded = &captured.lock().unwrap()[0];
+    assert!(forwarded.contains("sk-proj-9fK2mQx7LpR4vT8wZ1nB"));
+    assert!(forwarded.contains("real_secret_123"));
+    assert!(warned.get() > before);
+}
+
+#[tokio::test]
+async fn test_mixed_credentials_strip_removes_unmanaged_credential() {
+    let (status, captured) = send_mixed_credentials(MixedCredentialPolicy::Strip).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert!(captured.lock().unwrap()[0].ends_with(r#"{"api_key": "", "auth": "real_secret_123"}"#));
+}
+
+#[tokio::test]
+async fn test_mixed_credentials_block_refuses_request() {
+    let (status, captured) = send_mixed_credentials(MixedCredentialPolicy::Block).await;
+
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert!(captured.lock().unwrap().is_empty());
+}
+
+const BINARY_BODY: &[u8] = b"\xff\xfetoken=DUMMY_TOKEN\x80";
+
+async fn send_binary_body(config: ProxyConfig) -> (StatusCode, Arc<Mutex<Vec<String>>>) {
+    let (port, captured) = start_capturing_upstream().await;
+    let request = Request::builder()
+        .method("POST")
+        .uri("/v1/chat")
+        .header("x-target-url", format!("http://0.0.0.0:{}", port))
+        .body(Body::from(BINARY_BODY))
+        .unwrap();
+    let response = create_app(config).oneshot(request).await.unwrap();
+    (response.status(), captured)
+}
+
+fn non_utf8_policy(policy: NonUtf8BodyPolicy) -> ProxyConfig {
+    ProxyConfig {
+        non_utf8_body_policy: policy,
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn test_non_utf8_body_reject_returns_400() {
+    let (status, captured) = send_binary_body(non_utf8_policy(NonUtf8BodyPolicy::Reject)).await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert!(captured.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_non_utf8_body_inject_bytes_replaces_dummy_only() {
+    let injected = NON_UTF8_REQUEST_BODIES_TOTAL.with_label_values(&["inject_bytes"]);
+    let before = injected.get();
+
+    let (status, captured) = send_binary_body(ProxyConfig::default()).await;
+
+    assert_eq!(status, StatusCode::OK);
+    // The capturing upstream decodes lossily, so each invalid byte reads as U+FFFD
+    assert!(captured.lock().unwrap()[0]
+        .ends_with("\r\n\r\n\u{FFFD}\u{FFFD}token=real_secret_123\u{FFFD}"));
+    assert!(injected.get() > before);
+}
+
+#[tokio::test]
+async fn test_non_utf8_body_passthrough_forwards_unmodified() {
+    let (status, captured) =
+        send_binary_body(non_utf8_policy(NonUtf8BodyPolicy::Passthrough)).await;
+
+    assert_eq!(status, StatusCode::OK);
+    assert!(
+        captured.lock().unwrap()[0].ends_with("\r\n\r\n\u{FFFD}\u{FFFD}token=DUMMY_TOKEN\u{FFFD}")
+    );
+}
+
+#[tokio::test]
+async fn test_non_utf8_body_matching_redact_rule_is_blocked() {
+    let (status, captured) = send_binary_body(ProxyConfig {
+        dlp_rules: vec![DlpRule::new("token", "token=")
+            .unwrap()
+            .with_action(DlpAction::Redact)],
+        ..Default::default()
+    })
+    .await;
+
+    assert_eq!(status, StatusCode::FORBIDDEN);
+    assert!(captured.lock().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn test_mixed_credentials_policy_ignores_body_without_dummy() {
+    let (port, captured) = start_capturing_upstream().await;
+    let response = create_app(ProxyConfig {
+        mixed_credential_policy: MixedCredentialPolicy::Block,
+        ..Default::default()
+    })
+    .oneshot(body_request(
+        port,
+        "POST",
+        None,
+        r#"{"api_key": "sk-proj-9fK2mQx7LpR4vT8wZ1nB"}"#,
+    ))
+    .await
+    .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(captured.lock().unwrap()[0].contains("sk-proj-9fK2mQx7LpR4vT8wZ1nB"));
+}
+
+#[tokio::test]
+async fn test_upstream_connection_close_signaled_to_client() {
+    let before = UPSTREAM_CONNECTION_CLOSE_TOTAL.get();
+    let port =
+        start_raw_upstream(b"HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: 2\r\n\r\nok")
+            .await;
+
+    let response = create_app(ProxyConfig::default())
+        .oneshot(upstream_request(port))
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["connection"], "close");
+    assert!(UPSTREAM_CONNECTION_CLOSE_TOTAL.get() > before);
+}
+
+#[tokio::test]
+async fn test_upstream_connection_close_not_propagated_when_disabled() {
+    let port =
+        start_raw_upstream(b"HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: 2\r\n\r\nok")
+            .await;
+
+    let response = create_app(ProxyConfig {
+        propagate_upstream_close: false,
+        ..Default::default()
+    })
+    .oneshot(upstream_request(port))
+    .await
+    .unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get("connection").is_none());
+}
+
+#[tokio::test]
+async fn test_upstream_connection_options_not_forwarded() {
+    let port = start_raw_upstream(
+        b"HTTP/1.1 200 OK\r\nConnection: keep-alive, X-Upstream-Hop\r\nKeep-Alive: timeout=5\r\nX-Upstream-Hop: 1\r\nX-Kept: 1\r\nContent-Length: 2\r\n\r\nok",
+    )
+    .await;
+
+    let response = create_app(ProxyConfig::default())
+        .oneshot(upstream_request(port))
+        .await
+        .unwrap();
+
+    let headers = response.headers();
+    assert!(headers.get("connection").is_none());
+    assert!(headers.get("keep-alive").is_none());
+    assert!(headers.get("x-upstream-hop").is_none());
+    assert_eq!(headers["x-kept"], "1");
+}
+
+/// Start a mock upstream that counts requests and answers each after a delay
+async fn start_slow_counting_upstream() -> (u16, Arc<std::sync::atomic::AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let counter = calls.clone();
+
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let counter = counter.clone();
+            tokio::spawn(async move {
+                let mut buf = vec![0u8; 8192];
+                let _ = stream.read(&mut buf).await;
+                counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                // Keep the first request in flight while the duplicate arrives
+                tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+                let _ = stream
+                    .write_all(
+                        b"HTTP/1.1 200 OK\r\nContent-Length: 22\r\n\r\nmodels real_secret_123",
+                    )
+                    .await;
+            });
+        }
+    });
+
+    (port, calls)
+}
+
+fn dedup_request(port: u16, method: &str) -> Request<Body> {
+    Request::builder()
+        .method(method)
+        .uri("/v1/models")
+        .header("x-target-url", format!("http://0.0.0.0:{}", port))
+        .body(Body::empty())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_identical_concurrent_gets_share_one_upstream_call() {
+    let (port, calls) = start_slow_counting_upstream().await;
+    let app = create_app(ProxyConfig {
+        deduplicate_requests: true,
+        ..Default::default()
+    });
+    let deduplicated = DEDUPLICATED_REQUESTS_TOTAL.get();
+
+    let (first, second) = tokio::join!(
+        app.clone().oneshot(dedup_request(port, "GET")),
+        app.clone().oneshot(dedup_request(port, "GET")),
+    );
+
+    assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+    assert_eq!(DEDUPLICATED_REQUESTS_TOTAL.get(), deduplicated + 1);
+    for response in [first.unwrap(), second.unwrap()] {
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), 1024)
+            .await
+            .unwrap();
+        assert_eq!(&body[..], b"models [REDACTED]");
+    }
+}
+
+#[tokio::test]
+async fn test_unkeyed_posts_and_disabled_dedup_are_not_shared() {
+    let (port, calls) = start_slow_counting_upstream().await;
+    let app = create_app(ProxyConfig {
+        deduplicate_requests: true,
+        ..Default::default()
+    });
+
+    let (first, second) = tokio::join!(
+        app.clone().oneshot(dedup_request(port, "POST")),
+        app.clone().oneshot(dedup_request(port, "POST")),
+    );
+    assert_eq!(first.unwrap().status(), StatusCode::OK);
+    assert_eq!(second.unwrap().status(), StatusCode::OK);
+    assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 2);
+
+    let app = create_app(ProxyConfig::default());
+    let (first, second) = tokio::join!(
+        app.clone().oneshot(dedup_request(port, "GET")),
+        app.clone().oneshot(dedup_request(port, "GET")),
+    );
+    assert_eq!(first.unwrap().status(), StatusCode::OK);
+    assert_eq!(second.unwrap().status(), StatusCode::OK);
+    assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 4);
+}
+
+const NUMERIC_SECRET_JSON_RESPONSE: &[u8] = b"HTTP/1.1 200 OK\r\n\
+Content-Type: application/json\r\n\
+Content-Length: 18\r\n\
+\r\n\
+{\"pin\": 987654321}";
+
+/// Fetch a JSON response whose secret is a bare number, which redaction breaks
+async fn fetch_numeric_secret_json(handling: InvalidSanitizedJson) -> axum::response::Response {
+    let port = start_raw_upstream(NUMERIC_SECRET_JSON_RESPONSE).await;
+    let secrets = HashMap::from([("DUMMY_PIN".to_string(), "987654321".to_string())]);
+    let state = AppState::with_config(
+        Arc::new(SecretMap::new(secrets).unwrap()),
+        create_http_client(),
+        ProxyConfig {
+            invalid_sanitized_json: handling,
+            ..Default::default()
+        },
+    );
+    Router::new()
+        .route("/v1/{*path}", any(proxy_handler))
+        .with_state(state)
+        .oneshot(upstream_request(port))
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_unparseable_sanitized_json_is_counted() {
+    let before = SANITIZED_JSON_INVALID_TOTAL.get();
+
+    let response = fetch_numeric_secret_json(InvalidSanitizedJson::Warn).await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "application/json");
+    let body = axum::body::to_bytes(response.into_body(), 1024)
+        .await
+        .unwrap();
+    assert_eq!(&body[..], b"{\"pin\": [REDACTED]}");
+    assert!(SANITIZED_JSON_INVALID_TOTAL.get() > before);
+}
+
+#[tokio::test]
+async fn test_unparseable_sanitized_json_relabelled_text_plain() {
+    let response = fetch_numeric_secret_json(InvalidSanitizedJson::TextPlain).await;
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], "text/plain");
+}
+
+#[tokio::test]
+async fn test_sanitized_json_that_still_parses_keeps_content_type() {
+    let port = start_raw_upstream(
+        b"HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 28\r\n\r\n{\"token\": \"real_secret_123\"}",
+    )
+    .await;
+    let response = create_app(ProxyConfig {
+        invalid_sanitized_json: InvalidSanitizedJson::TextPlain,
+        ..Default::default()
+    })
+    .oneshot(upstream_request(port))
+    .await
+    .unwrap();
+
+    assert_eq!(response.headers()["content-type"], "application/json");
+}
+
+/// Proxy a JSON body with value-aware JSON sanitization on
+async fn fetch_json_values(body: &str) -> Vec<u8> {
+    let raw = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+        body.len(),
+        body
+    );
+    let port = start_raw_upstream(Box::leak(raw.into_bytes().into_boxed_slice())).await;
+    let response = create_app(ProxyConfig {
+        sanitize_json_values: true,
+        ..Default::default()
+    })
+    .oneshot(upstream_request(port))
+    .await
+    .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    axum::body::to_bytes(response.into_body(), 1024)
+        .await
+        .unwrap()
+        .to_vec()
+}
+
+#[tokio::test]
+async fn test_json_values_sanitized_through_escapes() {
+    // `\u005f` is `_`, a spelling of the secret byte matching cannot see
+    let body = fetch_json_values(r#"{"key": "real\u005fsecret_123", "n": 1}"#).await;
+
+    assert_eq!(body, br#"{"key":"[REDACTED]","n":1}"#);
+}
+
+#[tokio::test]
+async fn test_json_secret_key_falls_back_to_byte_sanitization() {
+    // Value-aware redaction keeps keys, so a secret key is caught by the fallback
+    let body = fetch_json_values(r#"{"real_secret_123": "real_secret_123"}"#).await;
+
+    assert_eq!(body, br#"{"[REDACTED]": "[REDACTED]"}"#);
+}
+
+/// App whose `DUMMY_MAPS` key travels in the `key` query parameter
+fn create_query_param_app(allowed_host: &str) -> Router {
+    use slapenir_proxy::strategies::QueryParamStrategy;
+    use slapenir_proxy::strategy::AuthStrategy;
+
+    std::env::set_var("SLAPENIR_TEST_MAPS_KEY", "AIzaRealMapsKey");
+    let strategies: Vec<Box<dyn AuthStrategy>> = vec![Box::new(
+        QueryParamStrategy::new(
+            "maps".to_string(),
+            "SLAPENIR_TEST_MAPS_KEY".to_string(),
+            "key".to_string(),
+            "DUMMY_MAPS".to_string(),
+            vec![allowed_host.to_string()],
+        )
+        .unwrap(),
+    )];
+    let secret_map = SecretMap::from_strategies(&strategies).unwrap();
+    let state = AppState::with_config(
+        Arc::new(secret_map),
+        create_http_client(),
+        ProxyConfig::default(),
+    )
+    .with_strategies(strategies);
+
+    Router::new()
+        .route("/v1/{*path}", any(proxy_handler))
+        .with_state(state)
+}
+
+fn query_param_request(port: u16) -> Request<Body> {
+    Request::builder()
+        .method("GET")
+        .uri("/v1/geocode/json?address=1600+Amphitheatre&key=DUMMY_MAPS&language=en")
+        .header("x-target-url", format!("http://0.0.0.0:{}", port))
+        .body(Body::empty())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_query_param_key_injected_alongside_other_params() {
+    let (port, captured) = start_capturing_upstream().await;
+    let app = create_query_param_app("0.0.0.0");
+
+    let response = app.oneshot(query_param_request(port)).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let requests = captured.lock().unwrap();
+    assert_eq!(requests.len(), 1);
+    assert!(requests[0].starts_with(
+        "GET /v1/geocode/json?address=1600+Amphitheatre&key=AIzaRealMapsKey&language=en HTTP/1.1\r\n"
+    ));
+    assert!(!requests[0].contains("DUMMY_MAPS"));
+}
+
+#[tokio::test]
+async fn test_query_param_key_refused_for_unlisted_host() {
+    let (port, captured) = start_capturing_upstream().await;
+    let app = create_query_param_app("maps.googleapis.com");
+
+    let response = app.oneshot(query_param_request(port)).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+    assert!(captured.lock().unwrap().is_empty());
+}
+
+fn create_client_scoped_app() -> Router {
+    use slapenir_proxy::config::ClientScopeConfig;
+    use slapenir_proxy::strategy::{AuthStrategy, BearerStrategy};
+
+    std::env::set_var("SLAPENIR_TEST_SCOPE_OPENAI", "sk-scoped-openai");
+    std::env::set_var("SLAPENIR_TEST_SCOPE_GITHUB", "ghp_scoped_github");
+    let strategies: Vec<Box<dyn AuthStrategy>> = vec![
+        Box::new(
+            BearerStrategy::new(
+                "openai".to_string(),
+                "SLAPENIR_TEST_SCOPE_OPENAI".to_string(),
+                "DUMMY_SCOPE_OPENAI".to_string(),
+                vec![],
+            )
+            .unwrap(),
+        ),
+        Box::new(
+            BearerStrategy::new(
+                "github".to_string(),
+                "SLAPENIR_TEST_SCOPE_GITHUB".to_string(),
+                "DUMMY_SCOPE_GITHUB".to_string(),
+                vec![],
+            )
+            .unwrap(),
+        ),
+    ];
+    let secret_map = SecretMap::from_strategies(&strategies).unwrap();
+    let state = AppState::with_config(
+        Arc::new(secret_map),
+        create_http_client(),
+        ProxyConfig::default(),
+    )
+    .with_client_scopes(&[ClientScopeConfig {
+        common_name: Some("agent-openai".to_string()),
+        organization: None,
+        strategies: vec!["openai".to_string()],
+        runtime_secrets: false,
+    }])
+    .unwrap();
+
+    Router::new()
+        .route("/v1/{*path}", any(proxy_handler))
+        .with_state(state)
+}
+
+fn client_scoped_request(port: u16, common_name: Option<&str>) -> Request<Body> {
+    let mut request = Request::builder()
+        .method("POST")
+        .uri("/v1/chat")
+        .header("x-target-url", format!("http://0.0.0.0:{}", port))
+        .body(Body::from(
+            "openai=DUMMY_SCOPE_OPENAI github=DUMMY_SCOPE_GITHUB",
+        ))
+        .unwrap();
+    if let Some(common_name) = common_name {
+        request
+            .extensions_mut()
+            .insert(slapenir_proxy::mtls::ClientCertInfo {
+                common_name: common_name.to_string(),
+                organization: None,
+                serial: "01".to_string(),
+                valid: true,
+            });
+    }
+    request
+}
+
+#[tokio::test]
+async fn test_client_scoped_to_openai_cannot_inject_github_token() {
+    let (port, captured) = start_capturing_upstream().await;
+    let app = create_client_scoped_app();
+
+    let response = app
+        .oneshot(client_scoped_request(port, Some("agent-openai")))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let sent = captured.lock().unwrap().join("");
+    assert!(sent.contains("openai=sk-scoped-openai"), "{}", sent);
+    assert!(sent.contains("github=DUMMY_SCOPE_GITHUB"), "{}", sent);
+    assert!(!sent.contains("ghp_scoped_github"), "{}", sent);
+}
+
+#[tokio::test]
+async fn test_unscoped_client_injects_nothing() {
+    let (port, captured) = start_capturing_upstream().await;
+    let app = create_client_scoped_app();
+
+    for common_name in [Some("agent-unknown"), None] {
+        let response = app
+            .clone()
+            .oneshot(client_scoped_request(port, common_name))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    for sent in captured.lock().unwrap().iter() {
+        assert!(!sent.contains("sk-scoped-openai"), "{}", sent);
+        assert!(!sent.contains("ghp_scoped_github"), "{}", sent);
+    }
+}
+
+/// Start a mock upstream that writes `partial` and then never says more
+async fn start_stalled_upstream(partial: &'static [u8]) -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let mut buf = vec![0u8; 8192];
+                let _ = stream.read(&mut buf).await;
+                let _ = stream.write_all(partial).await;
+                tokio::time::sleep(std::time::Duration::from_secs(60)).await;
+            });
+        }
+    });
+
+    port
+}
+
+#[tokio::test]
+async fn test_unresponsive_upstream_times_out_with_504() {
+    let port = start_stalled_upstream(b"").await;
+    let app = create_app(ProxyConfig {
+        upstream_response_timeout: std::time::Duration::from_millis(200),
+        ..Default::default()
+    });
+    let timeouts = UPSTREAM_TIMEOUTS_TOTAL
+        .with_label_values(&["response"])
+        .get();
+
+    let started = std::time::Instant::now();
+    let response = app.oneshot(upstream_request(port)).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+    assert!(started.elapsed() < std::time::Duration::from_secs(2));
+    assert!(
+        UPSTREAM_TIMEOUTS_TOTAL
+            .with_label_values(&["response"])
+            .get()
+            > timeouts
+    );
+}
+
+#[tokio::test]
+async fn test_stalled_response_body_times_out_with_504() {
+    // Headers arrive, but the body stops short of its Content-Length
+    let port =
+        start_stalled_upstream(b"HTTP/1.1 200 OK\r\nContent-Length: 100\r\n\r\npartial").await;
+    let app = create_app(ProxyConfig {
+        upstream_response_timeout: std::time::Duration::from_millis(200),
+        ..Default::default()
+    });
+
+    let started = std::time::Instant::now();
+    let response = app.oneshot(upstream_request(port)).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::GATEWAY_TIMEOUT);
+    assert!(started.elapsed() < std::time::Duration::from_secs(2));
+}
+
+/// Start a mock upstream answering its nth connection with `responses[n]`
+/// (the last one repeats); an empty response closes the connection unanswered
+async fn start_flaky_upstream(
+    responses: &'static [&'static [u8]],
+) -> (u16, Arc<std::sync::atomic::AtomicUsize>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let attempts = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let counter = attempts.clone();
+
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let n = counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            let response = responses[n.min(responses.len() - 1)];
+            tokio::spawn(async move {
+                let mut buf = vec![0u8; 8192];
+                let _ = stream.read(&mut buf).await;
+                let _ = stream.write_all(response).await;
+            });
+        }
+    });
+
+    (port, attempts)
+}
+
+const UNAVAILABLE_RESPONSE: &[u8] =
+    b"HTTP/1.1 503 Service Unavailable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+const OK_RESPONSE: &[u8] = b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\nConnection: close\r\n\r\nok";
+
+fn retrying_config() -> ProxyConfig {
+    ProxyConfig {
+        upstream_retry: RetryPolicy {
+            max_attempts: 3,
+            base_delay: std::time::Duration::from_millis(10),
+            jitter: false,
+        },
+        ..Default::default()
+    }
+}
+
+fn get_request(port: u16) -> Request<Body> {
+    Request::builder()
+        .method("GET")
+        .uri("/v1/models")
+        .header("x-target-url", format!("http://0.0.0.0:{}", port))
+        .body(Body::empty())
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_get_retried_until_upstream_recovers() {
+    let (port, attempts) =
+        start_flaky_upstream(&[UNAVAILABLE_RESPONSE, UNAVAILABLE_RESPONSE, OK_RESPONSE]).await;
+    let app = create_app(retrying_config());
+    let retries = UPSTREAM_RETRIES_TOTAL.with_label_values(&["status"]).get();
+    let recovered = UPSTREAM_RETRY_OUTCOMES_TOTAL
+        .with_label_values(&["recovered"])
+        .get();
+
+    let response = app.oneshot(get_request(port)).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), 1024)
+        .await
+        .unwrap();
+    assert_eq!(&body[..], b"ok");
+    assert_eq!(attempts.load(std::sync::atomic::Ordering::SeqCst), 3);
+    assert!(UPSTREAM_RETRIES_TOTAL.with_label_values(&["status"]).get() >= retries + 2);
+    assert!(
+        UPSTREAM_RETRY_OUTCOMES_TOTAL
+            .with_label_values(&["recovered"])
+            .get()
+            > recovered
+    );
+}
+
+#[tokio::test]
+async fn test_get_retried_after_dropped_connections() {
+    let (port, attempts) = start_flaky_upstream(&[b"", b"", OK_RESPONSE]).await;
+    let app = create_app(retrying_config());
+
+    let response = app.oneshot(get_request(port)).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(attempts.load(std::sync::atomic::Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn test_retries_exhausted_returns_last_response() {
+    let (port, attempts) = start_flaky_upstream(&[UNAVAILABLE_RESPONSE]).await;
+    let app = create_app(retrying_config());
+
+    let response = app.oneshot(get_request(port)).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(attempts.load(std::sync::atomic::Ordering::SeqCst), 3);
+}
+
+#[tokio::test]
+async fn test_post_not_retried_once_sent() {
+    let (port, attempts) =
+        start_flaky_upstream(&[UNAVAILABLE_RESPONSE, UNAVAILABLE_RESPONSE, OK_RESPONSE]).await;
+    let app = create_app(retrying_config());
+
+    let response = app.oneshot(upstream_request(port)).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(attempts.load(std::sync::atomic::Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_retries_disabled_by_default() {
+    let (port, attempts) = start_flaky_upstream(&[UNAVAILABLE_RESPONSE, OK_RESPONSE]).await;
+    let app = create_app(ProxyConfig::default());
+
+    let response = app.oneshot(get_request(port)).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    assert_eq!(attempts.load(std::sync::atomic::Ordering::SeqCst), 1);
+}
@@ -0,0 +1,60 @@
+// Request Body Timeout Tests
+// Kept in their own binary so the active-connection gauge is not shared
+// with concurrently running proxy tests.
+
+use axum::{
+    body::{Body, Bytes},
+    http::{Request, StatusCode},
+    routing::any,
+    Router,
+};
+use futures::stream::{self, StreamExt};
+use slapenir_proxy::{
+    metrics::ACTIVE_CONNECTIONS,
+    middleware::AppState,
+    proxy::{create_http_client, proxy_handler, ProxyConfig},
+    sanitizer::SecretMap,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tower::util::ServiceExt;
+
+#[tokio::test]
+async fn test_stalled_request_body_times_out() {
+    let mut secrets = HashMap::new();
+    secrets.insert("DUMMY_TOKEN".to_string(), "real_secret_123".to_string());
+    let state = AppState::with_config(
+        Arc::new(SecretMap::new(secrets).unwrap()),
+        create_http_client(),
+        ProxyConfig {
+            request_body_timeout: Duration::from_millis(100),
+            ..Default::default()
+        },
+    );
+    let app = Router::new()
+        .route("/v1/{*path}", any(proxy_handler))
+        .with_state(state);
+
+    // Send the first byte, then stall forever
+    let stalled = stream::iter(vec![Ok::<_, std::io::Error>(Bytes::from_static(b"{"))])
+        .chain(stream::pending());
+    let request = Request::builder()
+        .method("POST")
+        .uri("/v1/chat")
+        .header("x-target-url", "http://0.0.0.0:9")
+        .body(Body::from_stream(stalled))
+        .unwrap();
+
+    let before = ACTIVE_CONNECTIONS.with_label_values(&["plain"]).get();
+    let response = tokio::time::timeout(Duration::from_secs(5), app.oneshot(request))
+        .await
+        .expect("handler should give up on the stalled body")
+        .unwrap();
+
+    assert_eq!(response.status(), StatusCode::REQUEST_TIMEOUT);
+    assert_eq!(
+        ACTIVE_CONNECTIONS.with_label_values(&["plain"]).get(),
+        before
+    );
+}
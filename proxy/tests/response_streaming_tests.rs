@@ -0,0 +1,473 @@
+// Response Streaming Tests
+// Covers the buffering-vs-streaming decision and the buffered-bytes gauge.
+// Kept in their own binary so the in-flight gauge is not shared with other
+// proxy tests running concurrently.
+
+use axum::{
+    body::Body,
+    http::{header, Request, StatusCode},
+    routing::any,
+    Router,
+};
+use slapenir_proxy::{
+    metrics::{
+        BUFFERED_RESPONSE_BYTES_IN_FLIGHT, STREAMED_RESPONSES_TOTAL, STREAM_LINE_TOO_LONG_TOTAL,
+    },
+    middleware::AppState,
+    proxy::{create_http_client, proxy_handler, ProxyConfig},
+    sanitizer::SecretMap,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tower::util::ServiceExt;
+
+/// Start a mock upstream that answers every request with `body`
+async fn start_upstream(body: &'static str) -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let mut buf = vec![0u8; 8192];
+                let _ = stream.read(&mut buf).await;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            });
+        }
+    });
+
+    port
+}
+
+/// Start a mock upstream sending `chunks` chunked, with no Content-Length
+async fn start_chunked_upstream(chunks: &'static [&'static str]) -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            tokio::spawn(async move {
+                let mut buf = vec![0u8; 8192];
+                let _ = stream.read(&mut buf).await;
+                let _ = stream
+                    .write_all(b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n")
+                    .await;
+                for chunk in chunks {
+                    let frame = format!("{:x}\r\n{}\r\n", chunk.len(), chunk);
+                    let _ = stream.write_all(frame.as_bytes()).await;
+                    // Separate reads on the proxy side
+                    tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+                }
+                let _ = stream.write_all(b"0\r\n\r\n").await;
+            });
+        }
+    });
+
+    port
+}
+
+fn create_app(config: ProxyConfig) -> Router {
+    let mut secrets = HashMap::new();
+    secrets.insert("DUMMY_TOKEN".to_string(), "real_secret_123".to_string());
+    let secret_map = SecretMap::new(secrets).unwrap();
+    let state = AppState::with_config(Arc::new(secret_map), create_http_client(), config);
+
+    Router::new()
+        .route("/v1/{*path}", any(proxy_handler))
+        .with_state(state)
+}
+
+fn upstream_request(port: u16) -> Request<Body> {
+    Request::builder()
+        .method("POST")
+        .uri("/v1/chat")
+        .header("x-target-url", format!("http://0.0.0.0:{}", port))
+        .body(Body::from("{}"))
+        .unwrap()
+}
+
+#[tokio::test]
+async fn test_response_above_threshold_is_streamed() {
+    let port = start_upstream("data: real_secret_123\n\ndata: more output follows\n\n").await;
+    let app = create_app(ProxyConfig {
+        stream_response_threshold: 16,
+        ..Default::default()
+    });
+    let streamed = STREAMED_RESPONSES_TOTAL.get();
+
+    let response = app.oneshot(upstream_request(port)).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get(header::CONTENT_LENGTH).is_none());
+    assert!(STREAMED_RESPONSES_TOTAL.get() > streamed);
+
+    let body = axum::body::to_bytes(response.into_body(), 1024)
+        .await
+        .unwrap();
+    assert_eq!(
+        &body[..],
+        b"data: [REDACTED]\n\ndata: more output follows\n\n"
+    );
+}
+
+#[tokio::test]
+async fn test_unsized_response_is_streamed_with_split_secret_redacted() {
+    let port = start_chunked_upstream(&["token=real_sec", "ret_123 done"]).await;
+    let app = create_app(ProxyConfig::default());
+    let streamed = STREAMED_RESPONSES_TOTAL.get();
+
+    let response = app.oneshot(upstream_request(port)).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(STREAMED_RESPONSES_TOTAL.get() > streamed);
+    let body = axum::body::to_bytes(response.into_body(), 1024)
+        .await
+        .unwrap();
+    assert_eq!(&body[..], b"token=[REDACTED] done");
+}
+
+#[tokio::test]
+async fn test_unsized_response_buffered_when_streaming_disabled() {
+    let port = start_chunked_upstream(&["token=real_sec", "ret_123 done"]).await;
+    let app = create_app(ProxyConfig {
+        stream_unsized_responses: false,
+        ..Default::default()
+    });
+
+    let response = app.oneshot(upstream_request(port)).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get(header::CONTENT_LENGTH).unwrap(),
+        "21"
+    );
+    let body = axum::body::to_bytes(response.into_body(), 1024)
+        .await
+        .unwrap();
+    assert_eq!(&body[..], b"token=[REDACTED] done");
+}
+
+#[tokio::test]
+async fn test_streamed_response_aborts_on_excessive_redactions() {
+    let port = start_upstream("real_secret_123 real_secret_123 real_secret_123").await;
+    let app = create_app(ProxyConfig {
+        stream_response_threshold: 16,
+        max_redactions_per_response: 2,
+        ..Default::default()
+    });
+
+    let response = app.oneshot(upstream_request(port)).await.unwrap();
+
+    let body = axum::body::to_bytes(response.into_body(), 1024).await;
+    assert!(body.is_err());
+}
+
+#[tokio::test]
+async fn test_buffered_response_tracked_in_flight() {
+    let port = start_upstream("buffered ok").await;
+    let app = create_app(ProxyConfig::default());
+    let before = BUFFERED_RESPONSE_BYTES_IN_FLIGHT.get();
+
+    let response = app.oneshot(upstream_request(port)).await.unwrap();
+
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers().get(header::CONTENT_LENGTH).unwrap(),
+        "11"
+    );
+    // Held until the response body has been handed off
+    assert_eq!(BUFFERED_RESPONSE_BYTES_IN_FLIGHT.get(), before + 11);
+
+    let body = axum::body::to_bytes(response.into_body(), 1024)
+        .await
+        .unwrap();
+    assert_eq!(&body[..], b"buffered ok");
+    assert_eq!(BUFFERED_RESPONSE_BYTES_IN_FLIGHT.get(), before);
+}
+
+#[tokio::test]
+async fn test_ndjson_response_is_redacted_line_by_line() {
+    use futures::StreamExt;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (release_tx, release_rx) = tokio::sync::oneshot::channel::<()>();
+
+    // Chunked NDJSON: the rest of the stream waits until the test has read
+    // the first line back, so the proxy cannot be buffering the whole body
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut buf = vec![0u8; 8192];
+        let _ = stream.read(&mut buf).await;
+        let first = "{\"n\":1,\"key\":\"real_secret_123\"}\n{\"n\":2,";
+        let head = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/x-ndjson\r\n\
+             Transfer-Encoding: chunked\r\n\r\n{:x}\r\n{}\r\n",
+            first.len(),
+            first
+        );
+        stream.write_all(head.as_bytes()).await.unwrap();
+        let _ = release_rx.await;
+        let rest = "\"ok\":true}\n";
+        let tail = format!("{:x}\r\n{}\r\n0\r\n\r\n", rest.len(), rest);
+        let _ = stream.write_all(tail.as_bytes()).await;
+    });
+
+    let app = create_app(ProxyConfig::default());
+    let streamed = STREAMED_RESPONSES_TOTAL.get();
+
+    let response = app.oneshot(upstream_request(port)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(STREAMED_RESPONSES_TOTAL.get() > streamed);
+
+    let mut body = response.into_body().into_data_stream();
+    let first = tokio::time::timeout(std::time::Duration::from_secs(5), body.next())
+        .await
+        .expect("first line should arrive before the stream ends")
+        .unwrap()
+        .unwrap();
+    // Only the complete line, redacted; the partial second line is held back
+    assert_eq!(&first[..], b"{\"n\":1,\"key\":\"[REDACTED]\"}\n");
+
+    release_tx.send(()).unwrap();
+    let mut rest = Vec::new();
+    while let Some(chunk) = body.next().await {
+        rest.extend_from_slice(&chunk.unwrap());
+    }
+    assert_eq!(rest, b"{\"n\":2,\"ok\":true}\n");
+}
+
+#[tokio::test]
+async fn test_sse_event_over_line_limit_aborts_stream() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    // One well-formed event, then a field that never terminates
+    let body = format!("data: first\n\ndata: {}", "x".repeat(256));
+
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut buf = vec![0u8; 8192];
+        let _ = stream.read(&mut buf).await;
+        let response = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        );
+        let _ = stream.write_all(response.as_bytes()).await;
+    });
+
+    let app = create_app(ProxyConfig {
+        stream_response_threshold: 16,
+        max_stream_line_length: 64,
+        ..Default::default()
+    });
+    let too_long = STREAM_LINE_TOO_LONG_TOTAL.get();
+
+    let response = app.oneshot(upstream_request(port)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let body = axum::body::to_bytes(response.into_body(), 4096).await;
+    assert!(body.is_err());
+    assert_eq!(STREAM_LINE_TOO_LONG_TOTAL.get(), too_long + 1);
+}
+
+#[tokio::test]
+async fn test_sse_events_delivered_incrementally_and_redacted() {
+    use futures::StreamExt;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (release_tx, release_rx) = tokio::sync::oneshot::channel::<()>();
+
+    // The final event waits until the test has read the first two back, so
+    // the proxy cannot be buffering the stream
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut buf = vec![0u8; 8192];
+        let _ = stream.read(&mut buf).await;
+        stream
+            .write_all(
+                b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream; charset=utf-8\r\n\
+                  Transfer-Encoding: chunked\r\n\r\n",
+            )
+            .await
+            .unwrap();
+        for event in [
+            "data: {\"delta\":\"Hello\"}\n\n",
+            "data: {\"delta\":\"key real_secret_123\"}\n\n",
+        ] {
+            let frame = format!("{:x}\r\n{}\r\n", event.len(), event);
+            stream.write_all(frame.as_bytes()).await.unwrap();
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        let _ = release_rx.await;
+        let last = "data: [DONE]\n\n";
+        let tail = format!("{:x}\r\n{}\r\n0\r\n\r\n", last.len(), last);
+        let _ = stream.write_all(tail.as_bytes()).await;
+    });
+
+    // Event streams are streamed even when unsized bodies would be buffered
+    let app = create_app(ProxyConfig {
+        stream_unsized_responses: false,
+        ..Default::default()
+    });
+    let streamed = STREAMED_RESPONSES_TOTAL.get();
+
+    let response = app.oneshot(upstream_request(port)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get(header::CONTENT_LENGTH).is_none());
+    assert!(STREAMED_RESPONSES_TOTAL.get() > streamed);
+
+    let mut body = response.into_body().into_data_stream();
+    let expected = b"data: {\"delta\":\"Hello\"}\n\ndata: {\"delta\":\"key [REDACTED]\"}\n\n";
+    let mut received = Vec::new();
+    while received.len() < expected.len() {
+        let chunk = tokio::time::timeout(std::time::Duration::from_secs(5), body.next())
+            .await
+            .expect("events should arrive before the stream ends")
+            .unwrap()
+            .unwrap();
+        received.extend_from_slice(&chunk);
+    }
+    assert_eq!(received, expected);
+
+    release_tx.send(()).unwrap();
+    let mut rest = Vec::new();
+    while let Some(chunk) = body.next().await {
+        rest.extend_from_slice(&chunk.unwrap());
+    }
+    assert_eq!(rest, b"data: [DONE]\n\n");
+}
+
+#[tokio::test]
+async fn test_stalled_stream_aborted_after_read_timeout() {
+    use futures::StreamExt;
+    use slapenir_proxy::metrics::UPSTREAM_TIMEOUTS_TOTAL;
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    // One event, then the upstream goes quiet without closing
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut buf = vec![0u8; 8192];
+        let _ = stream.read(&mut buf).await;
+        let event = "data: real_secret_123\n\n";
+        let head = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\n\
+             Transfer-Encoding: chunked\r\n\r\n{:x}\r\n{}\r\n",
+            event.len(),
+            event
+        );
+        stream.write_all(head.as_bytes()).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_secs(30)).await;
+    });
+
+    let app = create_app(ProxyConfig {
+        upstream_read_timeout: std::time::Duration::from_millis(200),
+        ..Default::default()
+    });
+    let timeouts = UPSTREAM_TIMEOUTS_TOTAL.with_label_values(&["read"]).get();
+
+    let response = app.oneshot(upstream_request(port)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    let mut body = response.into_body().into_data_stream();
+    let first = body.next().await.unwrap().unwrap();
+    assert_eq!(&first[..], b"data: [REDACTED]\n\n");
+
+    let started = std::time::Instant::now();
+    let stalled = tokio::time::timeout(std::time::Duration::from_secs(5), body.next())
+        .await
+        .expect("a stalled upstream should end the stream");
+    assert!(matches!(stalled, Some(Err(_))));
+    assert!(started.elapsed() < std::time::Duration::from_secs(2));
+    assert!(UPSTREAM_TIMEOUTS_TOTAL.with_label_values(&["read"]).get() > timeouts);
+}
+
+/// Gzip `body`, as an upstream honouring the agent's Accept-Encoding would
+fn gzip(body: &[u8]) -> Vec<u8> {
+    use std::io::Write;
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(body).unwrap();
+    encoder.finish().unwrap()
+}
+
+/// Start a mock upstream sending `encoded` chunked, as gzip-encoded `content_type`
+async fn start_gzip_stream_upstream(content_type: &'static str, encoded: Vec<u8>) -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut buf = vec![0u8; 8192];
+        let _ = stream.read(&mut buf).await;
+        let head = format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Encoding: gzip\r\n\
+             Transfer-Encoding: chunked\r\n\r\n",
+            content_type
+        );
+        stream.write_all(head.as_bytes()).await.unwrap();
+        for chunk in encoded.chunks(encoded.len() / 2 + 1) {
+            stream
+                .write_all(format!("{:x}\r\n", chunk.len()).as_bytes())
+                .await
+                .unwrap();
+            stream.write_all(chunk).await.unwrap();
+            stream.write_all(b"\r\n").await.unwrap();
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        let _ = stream.write_all(b"0\r\n\r\n").await;
+    });
+
+    port
+}
+
+#[tokio::test]
+async fn test_gzip_sse_is_decoded_and_redacted() {
+    let events = "data: {\"delta\":\"key real_secret_123\"}\n\ndata: [DONE]\n\n";
+    let port = start_gzip_stream_upstream("text/event-stream", gzip(events.as_bytes())).await;
+    let app = create_app(ProxyConfig::default());
+    let streamed = STREAMED_RESPONSES_TOTAL.get();
+
+    let response = app.oneshot(upstream_request(port)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(STREAMED_RESPONSES_TOTAL.get() > streamed);
+    assert!(response.headers().get(header::CONTENT_ENCODING).is_none());
+
+    let body = axum::body::to_bytes(response.into_body(), 4096)
+        .await
+        .unwrap();
+    assert_eq!(
+        &body[..],
+        b"data: {\"delta\":\"key [REDACTED]\"}\n\ndata: [DONE]\n\n"
+    );
+}
+
+#[tokio::test]
+async fn test_gzip_ndjson_is_decoded_and_redacted() {
+    let lines = "{\"n\":1,\"key\":\"real_secret_123\"}\n{\"n\":2,\"ok\":true}\n";
+    let port = start_gzip_stream_upstream("application/x-ndjson", gzip(lines.as_bytes())).await;
+    let app = create_app(ProxyConfig::default());
+    let streamed = STREAMED_RESPONSES_TOTAL.get();
+
+    let response = app.oneshot(upstream_request(port)).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(STREAMED_RESPONSES_TOTAL.get() > streamed);
+    assert!(response.headers().get(header::CONTENT_ENCODING).is_none());
+
+    let body = axum::body::to_bytes(response.into_body(), 4096)
+        .await
+        .unwrap();
+    assert_eq!(
+        &body[..],
+        b"{\"n\":1,\"key\":\"[REDACTED]\"}\n{\"n\":2,\"ok\":true}\n"
+    );
+}
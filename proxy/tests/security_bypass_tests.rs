@@ -284,6 +284,7 @@ mod memory_limits {
         let config = ProxyConfig {
             max_request_size: 1024,       // 1KB
             max_response_size: 10 * 1024, // 10KB
+            ..Default::default()
         };
 
         assert_eq!(config.max_request_size, 1024);
@@ -428,6 +429,44 @@ mod automaton_caching {
             per_call
         );
     }
+
+    #[test]
+    fn test_byte_automaton_caching_performance() {
+        let mut secrets = HashMap::new();
+        for i in 0..50 {
+            secrets.insert(
+                format!("DUMMY_{}", i),
+                format!("secret_key_number_{}_with_padding", i),
+            );
+        }
+        let map = SecretMap::new(secrets).unwrap();
+
+        let test_data: &[u8] =
+            b"\xff\xfe secret_key_number_25_with_padding and secret_key_number_10_with_padding";
+
+        // Warm up
+        for _ in 0..10 {
+            let _ = map.sanitize_bytes(test_data);
+        }
+
+        // Measure performance
+        let iterations = 1000;
+        let start = Instant::now();
+
+        for _ in 0..iterations {
+            let _ = map.sanitize_bytes(test_data);
+        }
+
+        let elapsed = start.elapsed();
+        let per_call = elapsed / iterations;
+
+        // Rebuilding the automaton per call would cost far more than this
+        assert!(
+            per_call.as_micros() < 200, // Allow some slack for CI
+            "Binary sanitization should be fast with cached automaton (was {:?} per call)",
+            per_call
+        );
+    }
 }
 
 // ============================================================================
@@ -1,7 +1,12 @@
 // Integration tests for Strategy Pattern in main application
 // Phase 9: Verify config.yaml loading and strategy building
 
-use slapenir_proxy::{build_strategies_from_config, config::Config, sanitizer::SecretMap};
+use slapenir_proxy::{
+    build_strategies_from_config,
+    config::Config,
+    sanitizer::{load_secrets_file, SecretMap},
+    AuthStrategy, BearerStrategy,
+};
 use std::env;
 
 #[test]
@@ -110,3 +115,41 @@ fn test_config_has_bearer_strategies() {
         "Should have at least 3 bearer strategies (OpenAI, Anthropic, GitHub)"
     );
 }
+
+#[cfg(unix)]
+#[test]
+fn test_secrets_file_merges_with_strategy_secrets() {
+    use std::io::Write;
+    use std::os::unix::fs::PermissionsExt;
+
+    env::set_var("TEST_SECRETS_FILE_TOKEN", "sk-strategy-real");
+    let strategies: Vec<Box<dyn AuthStrategy>> = vec![Box::new(
+        BearerStrategy::new(
+            "openai".to_string(),
+            "TEST_SECRETS_FILE_TOKEN".to_string(),
+            "DUMMY_STRATEGY".to_string(),
+            vec![],
+        )
+        .unwrap(),
+    )];
+
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(b"DUMMY_DB_PASSWORD: db-real-pass\n")
+        .unwrap();
+    std::fs::set_permissions(file.path(), std::fs::Permissions::from_mode(0o600)).unwrap();
+
+    let extra = load_secrets_file(file.path()).expect("Failed to load secrets file");
+    let map = SecretMap::from_strategies(&strategies)
+        .unwrap()
+        .with_additional_secrets(extra)
+        .unwrap();
+
+    assert_eq!(
+        map.inject("key=DUMMY_STRATEGY pw=DUMMY_DB_PASSWORD"),
+        "key=sk-strategy-real pw=db-real-pass"
+    );
+    assert_eq!(
+        map.sanitize("log: sk-strategy-real / db-real-pass"),
+        "log: [REDACTED] / [REDACTED]"
+    );
+}
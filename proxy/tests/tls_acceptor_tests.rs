@@ -240,3 +240,39 @@ fn test_mitm_acceptor_sync_send() {
     assert_send::<MitmAcceptor>();
     assert_sync::<MitmAcceptor>();
 }
+
+#[tokio::test]
+async fn test_mitm_handshake_records_metrics() {
+    use slapenir_proxy::metrics::{CERT_GENERATION_SECONDS, MITM_HANDSHAKES_TOTAL};
+
+    let ca = Arc::new(CertificateAuthority::generate().unwrap());
+    let acceptor = Arc::new(MitmAcceptor::new(ca));
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let handshakes = MITM_HANDSHAKES_TOTAL.with_label_values(&["client"]).get();
+    let generations = CERT_GENERATION_SECONDS.get_sample_count();
+
+    let acceptor_clone = acceptor.clone();
+    let server_handle = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut tls_stream = acceptor_clone.accept(stream, "metrics.test").await.unwrap();
+        let mut buf = vec![0u8; 16];
+        let n = tls_stream.read(&mut buf).await.unwrap();
+        tls_stream.write_all(&buf[..n]).await.unwrap();
+    });
+
+    let stream = TcpStream::connect(addr).await.unwrap();
+    let connector = create_test_connector();
+    let server_name = ServerName::try_from("metrics.test").unwrap();
+    let mut tls_stream = connector.connect(server_name, stream).await.unwrap();
+    tls_stream.write_all(b"ping").await.unwrap();
+    let mut buf = [0u8; 4];
+    tls_stream.read_exact(&mut buf).await.unwrap();
+
+    server_handle.await.unwrap();
+
+    assert!(MITM_HANDSHAKES_TOTAL.with_label_values(&["client"]).get() > handshakes);
+    assert!(CERT_GENERATION_SECONDS.get_sample_count() > generations);
+}
@@ -172,3 +172,96 @@ fn test_host_cert_has_serial() {
     // Serial should not be empty
     assert!(!cert.serial().is_empty());
 }
+
+/// Verify a leaf issued for `host` against `root_pem` as the only trust anchor
+fn verify_against_root(root_pem: &str, leaf_pem: &str, host: &str) -> Result<(), rustls::Error> {
+    use rustls::client::danger::ServerCertVerifier;
+    use rustls_pki_types::{CertificateDer, ServerName, UnixTime};
+
+    let parse = |pem: &str| -> CertificateDer<'static> {
+        rustls_pemfile::certs(&mut pem.as_bytes())
+            .next()
+            .unwrap()
+            .unwrap()
+    };
+    let mut roots = rustls::RootCertStore::empty();
+    roots.add(parse(root_pem)).unwrap();
+    let verifier = rustls::client::WebPkiServerVerifier::builder_with_provider(
+        std::sync::Arc::new(roots),
+        std::sync::Arc::new(rustls::crypto::ring::default_provider()),
+    )
+    .build()
+    .unwrap();
+
+    verifier
+        .verify_server_cert(
+            &parse(leaf_pem),
+            &[],
+            &ServerName::try_from(host.to_string()).unwrap(),
+            &[],
+            UnixTime::now(),
+        )
+        .map(|_| ())
+}
+
+#[test]
+fn test_replicas_sharing_ca_files_chain_to_one_root() {
+    let temp_dir = TempDir::new().unwrap();
+    let ca_path = temp_dir.path().join("ca.pem");
+    let key_path = temp_dir.path().join("ca.key");
+
+    // Exported once, then imported by every replica
+    let exported = CertificateAuthority::generate().unwrap();
+    exported.save(&ca_path, &key_path).unwrap();
+
+    let replica1 = CertificateAuthority::load(&ca_path, &key_path).unwrap();
+    let replica2 = CertificateAuthority::load(&ca_path, &key_path).unwrap();
+    let leaf1 = replica1.sign_for_host("api.github.com").unwrap();
+    let leaf2 = replica2.sign_for_host("api.github.com").unwrap();
+
+    let root = std::fs::read_to_string(&ca_path).unwrap();
+    verify_against_root(&root, leaf1.cert_pem(), "api.github.com").unwrap();
+    verify_against_root(&root, leaf2.cert_pem(), "api.github.com").unwrap();
+
+    // A CA generated independently is not trusted for the same leaves
+    let other = CertificateAuthority::generate().unwrap();
+    assert!(verify_against_root(other.cert_pem(), leaf1.cert_pem(), "api.github.com").is_err());
+}
+
+#[test]
+fn test_imported_external_ca_keeps_its_subject() {
+    use rcgen::{BasicConstraints, Certificate, CertificateParams, DnType, DnValue, IsCa};
+
+    // An operator's own root, with a subject unlike the generated one
+    let mut params = CertificateParams::default();
+    params.distinguished_name.push(
+        DnType::CountryName,
+        DnValue::PrintableString("GB".to_string()),
+    );
+    params
+        .distinguished_name
+        .push(DnType::OrganizationName, "Example Corp");
+    params
+        .distinguished_name
+        .push(DnType::CommonName, "Example Egress Root");
+    params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    let root = Certificate::from_params(params).unwrap();
+    let root_pem = root.serialize_pem().unwrap();
+
+    let ca =
+        CertificateAuthority::from_pem(root_pem.clone(), root.serialize_private_key_pem()).unwrap();
+    let leaf = ca.sign_for_host("api.openai.com").unwrap();
+
+    assert_eq!(ca.cert_pem(), root_pem);
+    verify_against_root(&root_pem, leaf.cert_pem(), "api.openai.com").unwrap();
+}
+
+#[test]
+fn test_import_rejects_mismatched_key() {
+    let ca1 = CertificateAuthority::generate().unwrap();
+    let ca2 = CertificateAuthority::generate().unwrap();
+
+    let result =
+        CertificateAuthority::from_pem(ca1.cert_pem().to_string(), ca2.key_pem().to_string());
+    assert!(result.is_err());
+}
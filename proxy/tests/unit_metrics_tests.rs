@@ -256,25 +256,25 @@ mod metrics_comprehensive_tests {
 
     #[test]
     fn test_connection_tracking_increment() {
-        inc_active_connections();
-        inc_active_connections();
-        inc_active_connections();
+        inc_active_connections(ConnectionKind::Plain);
+        inc_active_connections(ConnectionKind::Plain);
+        inc_active_connections(ConnectionKind::Plain);
     }
 
     #[test]
     fn test_connection_tracking_decrement() {
-        dec_active_connections();
-        dec_active_connections();
-        dec_active_connections();
+        dec_active_connections(ConnectionKind::Plain);
+        dec_active_connections(ConnectionKind::Plain);
+        dec_active_connections(ConnectionKind::Plain);
     }
 
     #[test]
     fn test_connection_tracking_increment_then_decrement() {
         for _ in 0..10 {
-            inc_active_connections();
+            inc_active_connections(ConnectionKind::Plain);
         }
         for _ in 0..10 {
-            dec_active_connections();
+            dec_active_connections(ConnectionKind::Plain);
         }
     }
 
@@ -282,8 +282,41 @@ mod metrics_comprehensive_tests {
     fn test_connection_tracking_balanced() {
         // Simulate balanced connections
         for _ in 0..100 {
-            inc_active_connections();
-            dec_active_connections();
+            inc_active_connections(ConnectionKind::Plain);
+            dec_active_connections(ConnectionKind::Plain);
+        }
+    }
+
+    #[test]
+    fn test_connection_kinds_tracked_separately() {
+        // Plain requests are exercised concurrently by other tests, so only
+        // the tunnel kinds are compared exactly
+        let tunnel_kinds = [
+            ConnectionKind::TunnelPassthrough,
+            ConnectionKind::TunnelMitm,
+            ConnectionKind::TunnelInspected,
+        ];
+        let gauge =
+            |kind: ConnectionKind| ACTIVE_CONNECTIONS.with_label_values(&[kind.as_str()]).get();
+
+        for kind in tunnel_kinds {
+            let before: Vec<i64> = tunnel_kinds.iter().map(|k| gauge(*k)).collect();
+
+            let guard = track_connection(kind);
+            for (other, was) in tunnel_kinds.iter().zip(&before) {
+                let expected = if *other == kind { was + 1 } else { *was };
+                assert_eq!(
+                    gauge(*other),
+                    expected,
+                    "{} while tracking {}",
+                    other.as_str(),
+                    kind.as_str()
+                );
+            }
+
+            drop(guard);
+            let after: Vec<i64> = tunnel_kinds.iter().map(|k| gauge(*k)).collect();
+            assert_eq!(after, before, "{} not balanced", kind.as_str());
         }
     }
 
@@ -296,7 +329,7 @@ mod metrics_comprehensive_tests {
 
         // Record some data to ensure metrics exist
         record_http_request("GET", 200, "/test", 0.1);
-        inc_active_connections();
+        inc_active_connections(ConnectionKind::Plain);
 
         let result = gather_metrics();
         assert!(result.is_ok());
@@ -407,7 +440,7 @@ mod metrics_comprehensive_tests {
     #[test]
     fn test_full_request_lifecycle_metrics() {
         // Simulate a complete request lifecycle
-        inc_active_connections();
+        inc_active_connections(ConnectionKind::Plain);
 
         HTTP_REQUEST_SIZE_BYTES.observe(1024.0);
         record_http_request("POST", 200, "/api/test", 0.123);
@@ -415,7 +448,7 @@ mod metrics_comprehensive_tests {
 
         record_secret_sanitized("api_key");
 
-        dec_active_connections();
+        dec_active_connections(ConnectionKind::Plain);
     }
 
     #[test]
@@ -423,12 +456,12 @@ mod metrics_comprehensive_tests {
         // Simulate mTLS connection lifecycle
         record_mtls_connection(0.05);
         update_cert_expiry("client_cert", 1735689600);
-        inc_active_connections();
+        inc_active_connections(ConnectionKind::Plain);
 
         // Request handling
         record_http_request("GET", 200, "/secure", 0.1);
 
-        dec_active_connections();
+        dec_active_connections(ConnectionKind::Plain);
     }
 
     #[test]
@@ -436,18 +469,18 @@ mod metrics_comprehensive_tests {
         // Simulate error scenarios
         record_mtls_error("handshake_failed");
         record_http_request("GET", 500, "/error", 0.5);
-        dec_active_connections(); // Connection closed due to error
+        dec_active_connections(ConnectionKind::Plain); // Connection closed due to error
     }
 
     #[test]
     fn test_high_load_metrics() {
         // Simulate high load
         for i in 0..100 {
-            inc_active_connections();
+            inc_active_connections(ConnectionKind::Plain);
             record_http_request("GET", 200, "/api", 0.01);
             record_secret_sanitized("api_key");
             if i % 2 == 0 {
-                dec_active_connections();
+                dec_active_connections(ConnectionKind::Plain);
             }
         }
     }
@@ -465,8 +498,8 @@ mod metrics_comprehensive_tests {
                 for _ in 0..10 {
                     record_http_request("GET", 200, "/concurrent", 0.01);
                     record_secret_sanitized(&format!("type_{}", i));
-                    inc_active_connections();
-                    dec_active_connections();
+                    inc_active_connections(ConnectionKind::Plain);
+                    dec_active_connections(ConnectionKind::Plain);
                 }
             });
             handles.push(handle);
@@ -486,9 +519,9 @@ mod metrics_comprehensive_tests {
         for _ in 0..5 {
             let handle = thread::spawn(|| {
                 for _ in 0..20 {
-                    inc_active_connections();
+                    inc_active_connections(ConnectionKind::Plain);
                     std::thread::sleep(std::time::Duration::from_millis(1));
-                    dec_active_connections();
+                    dec_active_connections(ConnectionKind::Plain);
                 }
             });
             handles.push(handle);
@@ -538,4 +571,39 @@ mod metrics_comprehensive_tests {
         assert!(!metrics1.is_empty());
         assert!(!metrics2.is_empty());
     }
+
+    // ===== Secret Material Tests =====
+
+    #[test]
+    fn test_secret_bytes_gauge_tracks_runtime_secrets() {
+        use slapenir_proxy::{
+            middleware::AppState,
+            proxy::{create_http_client, ProxyConfig},
+            sanitizer::SecretMap,
+        };
+        use std::collections::HashMap;
+        use std::sync::Arc;
+
+        let secrets = HashMap::from([("DUMMY_TOKEN".to_string(), "real_secret_123".to_string())]);
+        let state = AppState::with_config(
+            Arc::new(SecretMap::new(secrets).unwrap()),
+            create_http_client(),
+            ProxyConfig {
+                max_secret_bytes: Some(25),
+                ..Default::default()
+            },
+        );
+
+        let runtime = HashMap::from([("DUMMY_RT".to_string(), "rt_real".to_string())]);
+        state.register_secrets(runtime).unwrap();
+        assert_eq!(SECRET_BYTES_TOTAL.get(), 15 + 7);
+
+        // Rejected additions leave the total as it was
+        let runtime = HashMap::from([("DUMMY_BIG".to_string(), "rt_real_too_big".to_string())]);
+        assert!(state.register_secrets(runtime).is_err());
+        assert_eq!(SECRET_BYTES_TOTAL.get(), 15 + 7);
+
+        state.unregister_secrets(&["DUMMY_RT".to_string()]);
+        assert_eq!(SECRET_BYTES_TOTAL.get(), 15);
+    }
 }